@@ -0,0 +1,71 @@
+//! Polls the Metrics PDA every 15 seconds and prints the gauges in
+//! Prometheus text format.
+//!
+//! Usage: metrics_scraper <slots_state pubkey> [rpc url]
+
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use gorbagana_slots::prelude::*;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_program::pubkey::Pubkey;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+fn print_gauges(m: &client::MetricsSnapshot) {
+    let gauges: [(&str, u64); 13] = [
+        ("trashrush_updated_slot", m.updated_slot),
+        ("trashrush_total_pool_lamports", m.total_pool),
+        ("trashrush_spendable_lamports", m.spendable),
+        ("trashrush_pending_spins", m.pending_spins),
+        ("trashrush_total_spins", m.total_spins),
+        ("trashrush_jackpot_mini_lamports", m.jackpot_amounts[0]),
+        ("trashrush_jackpot_major_lamports", m.jackpot_amounts[1]),
+        ("trashrush_jackpot_grand_lamports", m.jackpot_amounts[2]),
+        ("trashrush_fee_balance_lamports", m.fee_balance),
+        ("trashrush_rtp_bps", m.current_rtp_bps as u64),
+        ("trashrush_paused", (m.pause_flags != 0) as u64),
+        ("trashrush_pause_flags", m.pause_flags as u64),
+        ("trashrush_bootstrap_active", m.bootstrap_active as u64),
+    ];
+    for (name, value) in gauges {
+        println!("{} {}", name, value);
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let slots_state = args
+        .next()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .expect("usage: metrics_scraper <slots_state pubkey> [rpc url]");
+    let rpc_url = args
+        .next()
+        .unwrap_or_else(|| "http://127.0.0.1:8899".to_string());
+
+    let (metrics_pda, _) =
+        Pubkey::find_program_address(&[METRICS_SEED, slots_state.as_ref()], &ID);
+    let rpc = RpcClient::new(rpc_url);
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig {
+            offset: 0,
+            length: metrics::METRICS_LEN,
+        }),
+        ..RpcAccountInfoConfig::default()
+    };
+
+    loop {
+        match rpc.get_account_with_config(&metrics_pda, config.clone()) {
+            Ok(resp) => match resp.value.and_then(|a| client::decode_metrics(&a.data)) {
+                Some(m) => print_gauges(&m),
+                None => eprintln!("metrics account {} missing or short", metrics_pda),
+            },
+            Err(e) => eprintln!("rpc error: {}", e),
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,84 @@
+//! Builds a settlement proof bundle for one spin and prints it as JSON:
+//! the commit and settle events, the config it was checked against, the
+//! outcome recomputed from the revealed VRF bytes, and a pass/fail
+//! verdict with reasons.
+//!
+//! Usage: settlement_proof <slots_state pubkey> <user pubkey> <spin nonce>
+//!            [--vrf <64 hex chars>] [--commit <sig> --settle <sig>] [--rpc <url>]
+//!
+//! Without --commit/--settle the transactions are found by scanning the
+//! player's PlayerState signatures. Without --vrf the bytes the settle
+//! logged in SpinProof are used. Exits non-zero if the verdict fails.
+
+mod proof;
+mod rpc;
+
+use std::str::FromStr;
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+
+const USAGE: &str = "usage: settlement_proof <slots_state> <user> <spin nonce> \
+                     [--vrf <hex>] [--commit <sig> --settle <sig>] [--rpc <url>]";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let slots_state = args
+        .next()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .expect(USAGE);
+    let user = args
+        .next()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .expect(USAGE);
+    let spin_nonce = args.next().and_then(|s| s.parse().ok()).expect(USAGE);
+
+    let (mut vrf, mut commit, mut settle) = (None, None, None);
+    let mut rpc_url = "http://127.0.0.1:8899".to_string();
+    while let Some(flag) = args.next() {
+        let value = args.next().expect(USAGE);
+        match flag.as_str() {
+            "--vrf" => vrf = Some(proof::parse_vrf_hex(&value).expect("--vrf: 64 hex chars")),
+            "--commit" => commit = Some(value),
+            "--settle" => settle = Some(value),
+            "--rpc" => rpc_url = value,
+            _ => panic!("{}", USAGE),
+        }
+    }
+
+    let rpc = RpcClient::new(rpc_url);
+    let (commit, settle) = match (commit, settle) {
+        (Some(commit), Some(settle)) => (
+            rpc::fetch_transaction(&rpc, &commit),
+            rpc::fetch_transaction(&rpc, &settle),
+        ),
+        (None, None) => match rpc::find_spin(&rpc, &user, spin_nonce) {
+            Ok((commit, settle)) => (Ok(commit), Ok(settle)),
+            Err(e) => (Err(e.clone()), Err(e)),
+        },
+        _ => panic!("--commit and --settle go together"),
+    };
+    let (commit, settle) = match (commit, settle) {
+        (Ok(commit), Ok(settle)) => (commit, settle),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("settlement_proof: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let config = rpc::fetch_config(&rpc, &slots_state)
+        .map_err(|e| eprintln!("settlement_proof: config: {}", e))
+        .ok();
+
+    let bundle = proof::assemble(&proof::ProofInput {
+        user,
+        spin_nonce,
+        commit,
+        settle,
+        config,
+        vrf_bytes: vrf,
+    });
+    println!("{}", serde_json::to_string_pretty(&bundle).expect("bundle serializes"));
+    if !bundle.verdict.pass {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,441 @@
+//! Decodes the spin events out of transaction logs and checks them
+//! against each other, the archived VRF bytes and the paytable.
+//!
+//! Nothing here talks to the network, so a bundle can be rebuilt from
+//! saved logs long after the RPC node has pruned the transactions.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::Engine;
+use gorbagana_slots::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// One transaction of the spin, as fetched or loaded from an archive.
+pub struct SpinTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub logs: Vec<String>,
+}
+
+/// Reels, paytable and caps read from SlotsState at `slot`.
+pub struct ConfigAt {
+    pub slot: u64,
+    pub reel_weights: ReelWeights,
+    /// NO_WILD when no symbol is wild.
+    pub wild_symbol: u8,
+    pub paytable: PayTable,
+    pub max_payout_per_spin: u64,
+}
+
+/// Everything the bundle is built from.
+pub struct ProofInput {
+    pub user: Pubkey,
+    pub spin_nonce: u64,
+    pub commit: SpinTransaction,
+    pub settle: SpinTransaction,
+    pub config: Option<ConfigAt>,
+    /// The 32 revealed bytes from the oracle archive. Without them, the
+    /// bytes the settle logged in SpinProof are used.
+    pub vrf_bytes: Option<[u8; 32]>,
+}
+
+/// Spin events this tool understands.
+pub enum SpinEvent {
+    Committed(SpinCommitted),
+    Settled(SpinSettled),
+    LinesSettled(SpinLinesSettled),
+    Proof(SpinProof),
+    Receipt(ReceiptAppended),
+    Voided(SpinVoided),
+}
+
+#[derive(Serialize)]
+pub struct ProofBundle {
+    pub user: String,
+    pub spin_nonce: u64,
+    pub commit_signature: String,
+    pub commit_slot: u64,
+    pub settle_signature: String,
+    pub settle_slot: u64,
+    pub events: Vec<Value>,
+    pub config: Option<Value>,
+    pub vrf_bytes: Option<String>,
+    pub recomputed: Option<Recomputed>,
+    pub verdict: Verdict,
+}
+
+#[derive(Serialize)]
+pub struct Recomputed {
+    pub vrf_hash: String,
+    pub symbols: [u8; 3],
+    pub grid: Option<[[u8; 3]; 3]>,
+    pub line_payouts: Option<Vec<u64>>,
+    /// Base win before the pool headroom and per-spin caps.
+    pub base_payout_full: Option<u64>,
+    pub receipt_leaf: Option<String>,
+}
+
+/// `pass` only if `reasons` is empty. Warnings flag checks that could
+/// not be made, not mismatches.
+#[derive(Serialize, Default)]
+pub struct Verdict {
+    pub pass: bool,
+    pub reasons: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn parse_vrf_hex(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+fn decode<T: AnchorDeserialize + Discriminator>(data: &[u8]) -> Option<T> {
+    let body = data.strip_prefix(&T::DISCRIMINATOR[..])?;
+    T::deserialize(&mut &body[..]).ok()
+}
+
+/// Spin events emitted in `logs`, in log order. Lines from other
+/// programs, and events from older layouts, are skipped.
+pub fn events_from_logs(logs: &[String]) -> Vec<SpinEvent> {
+    logs.iter()
+        .filter_map(|line| line.strip_prefix(PROGRAM_DATA))
+        .filter_map(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok())
+        .filter_map(|data| {
+            decode(&data)
+                .map(SpinEvent::Committed)
+                .or_else(|| decode(&data).map(SpinEvent::Settled))
+                .or_else(|| decode(&data).map(SpinEvent::LinesSettled))
+                .or_else(|| decode(&data).map(SpinEvent::Proof))
+                .or_else(|| decode(&data).map(SpinEvent::Receipt))
+                .or_else(|| decode(&data).map(SpinEvent::Voided))
+        })
+        .collect()
+}
+
+impl SpinEvent {
+    /// Whether the event belongs to `user`'s spin `spin_nonce`. Receipts
+    /// carry no nonce, so they match on the player alone.
+    pub fn is_for(&self, user: &Pubkey, spin_nonce: u64) -> bool {
+        match self {
+            SpinEvent::Committed(e) => e.user == *user && e.spin_nonce == spin_nonce,
+            SpinEvent::Settled(e) => e.user == *user && e.spin_nonce == spin_nonce,
+            SpinEvent::LinesSettled(e) => e.user == *user && e.spin_nonce == spin_nonce,
+            SpinEvent::Proof(e) => e.user == *user && e.spin_nonce == spin_nonce,
+            SpinEvent::Voided(e) => e.user == *user && e.spin_nonce == spin_nonce,
+            SpinEvent::Receipt(e) => e.player == *user,
+        }
+    }
+
+    fn to_json(&self, tx: &str) -> Value {
+        match self {
+            SpinEvent::Committed(e) => json!({
+                "event": "SpinCommitted", "tx": tx,
+                "bet_amount": e.bet_amount,
+                "randomness_account": e.randomness_account.to_string(),
+                "oracle_cost": e.oracle_cost,
+                "player_tier": e.player_tier,
+                "context_index": e.context_index,
+            }),
+            SpinEvent::Settled(e) => json!({
+                "event": "SpinSettled", "tx": tx,
+                "randomness_account": e.randomness_account.to_string(),
+                "symbols": e.symbols,
+                "base_payout": e.base_payout,
+                "jackpot_payout": e.jackpot_payout,
+                "total_payout": e.total_payout,
+                "fee_rebate": e.fee_rebate,
+                "fee_overflow": e.fee_overflow,
+                "vrf_hash": hex(&e.vrf_hash),
+                "vrf_prefix": hex(&e.vrf_prefix),
+                "version": e.version,
+                "context_index": e.context_index,
+                "free_spin": e.free_spin,
+            }),
+            SpinEvent::LinesSettled(e) => json!({
+                "event": "SpinLinesSettled", "tx": tx,
+                "lines": e.lines,
+                "line_bet": e.line_bet,
+                "grid": e.grid,
+                "line_payouts": e.line_payouts,
+            }),
+            SpinEvent::Proof(e) => json!({
+                "event": "SpinProof", "tx": tx,
+                "randomness_account": e.randomness_account.to_string(),
+                "seed_slot": e.seed_slot,
+                "random_bytes": hex(&e.random_bytes),
+                "seed": e.seed,
+            }),
+            SpinEvent::Receipt(e) => json!({
+                "event": "ReceiptAppended", "tx": tx,
+                "leaf_index": e.leaf_index,
+                "leaf": hex(&e.leaf),
+                "bet": e.bet,
+                "symbols": e.symbols,
+                "base_payout": e.base_payout,
+                "jackpot_payout": e.jackpot_payout,
+                "vrf_hash": hex(&e.vrf_hash),
+                "root": hex(&e.root),
+            }),
+            SpinEvent::Voided(e) => json!({
+                "event": "SpinVoided", "tx": tx,
+                "randomness_account": e.randomness_account.to_string(),
+                "refunded": e.refunded,
+                "reason_code": e.reason_code,
+            }),
+        }
+    }
+}
+
+/// Build the bundle and its verdict.
+pub fn assemble(input: &ProofInput) -> ProofBundle {
+    let (user, nonce) = (&input.user, input.spin_nonce);
+    let commit_events: Vec<_> = events_from_logs(&input.commit.logs)
+        .into_iter()
+        .filter(|e| e.is_for(user, nonce))
+        .collect();
+    let settle_events: Vec<_> = events_from_logs(&input.settle.logs)
+        .into_iter()
+        .filter(|e| e.is_for(user, nonce))
+        .collect();
+
+    let mut verdict = Verdict::default();
+    let committed = commit_events.iter().find_map(|e| match e {
+        SpinEvent::Committed(c) => Some(c),
+        _ => None,
+    });
+    let settled = settle_events.iter().find_map(|e| match e {
+        SpinEvent::Settled(s) => Some(s),
+        _ => None,
+    });
+    let lines = settle_events.iter().find_map(|e| match e {
+        SpinEvent::LinesSettled(l) => Some(l),
+        _ => None,
+    });
+    let logged_vrf = settle_events.iter().find_map(|e| match e {
+        SpinEvent::Proof(p) => Some(p.random_bytes),
+        _ => None,
+    });
+    let receipt = settle_events.iter().find_map(|e| match e {
+        SpinEvent::Receipt(r) if settled.is_some_and(|s| s.vrf_hash == r.vrf_hash) => Some(r),
+        _ => None,
+    });
+
+    if committed.is_none() {
+        verdict.reasons.push("no SpinCommitted for this spin in the commit tx".into());
+    }
+    if settled.is_none() {
+        let voided = settle_events.iter().any(|e| matches!(e, SpinEvent::Voided(_)));
+        verdict.reasons.push(if voided {
+            "spin was voided; there is no outcome to verify".into()
+        } else {
+            "no SpinSettled for this spin in the settle tx".into()
+        });
+    }
+    if input.commit.slot > input.settle.slot {
+        verdict.reasons.push("commit tx landed after the settle tx".into());
+    }
+
+    let mut recomputed = None;
+    if let (Some(c), Some(s)) = (committed, settled) {
+        if c.randomness_account != s.randomness_account {
+            verdict.reasons.push("commit and settle name different randomness accounts".into());
+        }
+        if s.base_payout.checked_add(s.jackpot_payout) != Some(s.total_payout) {
+            verdict.reasons.push("total_payout is not base_payout + jackpot_payout".into());
+        }
+        if let Some(r) = receipt {
+            let leaf = receipts::receipt_leaf(
+                user,
+                r.leaf_index,
+                r.bet,
+                r.symbols,
+                r.base_payout,
+                r.jackpot_payout,
+                &r.vrf_hash,
+            );
+            if leaf != r.leaf {
+                verdict.reasons.push("receipt leaf does not hash from its fields".into());
+            }
+            if r.bet != c.bet_amount
+                || r.symbols != s.symbols
+                || r.base_payout != s.base_payout
+                || r.jackpot_payout != s.jackpot_payout
+            {
+                verdict.reasons.push("receipt disagrees with the spin events".into());
+            }
+        } else {
+            verdict.warnings.push("no ReceiptAppended; receipt tree was not passed".into());
+        }
+        match input.vrf_bytes.or(logged_vrf) {
+            Some(vrf) => {
+                recomputed = Some(recompute(&vrf, c, s, lines, receipt, input, &mut verdict));
+            }
+            None => verdict.reasons.push("VRF bytes neither supplied nor logged".into()),
+        }
+    }
+
+    verdict.pass = verdict.reasons.is_empty();
+    let events = commit_events
+        .iter()
+        .map(|e| e.to_json(&input.commit.signature))
+        .chain(settle_events.iter().map(|e| e.to_json(&input.settle.signature)))
+        .collect();
+    ProofBundle {
+        user: user.to_string(),
+        spin_nonce: nonce,
+        commit_signature: input.commit.signature.clone(),
+        commit_slot: input.commit.slot,
+        settle_signature: input.settle.signature.clone(),
+        settle_slot: input.settle.slot,
+        events,
+        config: input.config.as_ref().map(|c| {
+            json!({
+                "read_at_slot": c.slot,
+                "reel_weights": c.reel_weights.weights,
+                "wild_symbol": c.wild_symbol,
+                "two_of_a_kind": c.paytable.two_of_a_kind,
+                "multipliers": c.paytable.multipliers,
+                "max_payout_per_spin": c.max_payout_per_spin,
+            })
+        }),
+        vrf_bytes: input.vrf_bytes.or(logged_vrf).map(|v| hex(&v)),
+        recomputed,
+        verdict,
+    }
+}
+
+fn recompute(
+    vrf: &[u8; 32],
+    committed: &SpinCommitted,
+    settled: &SpinSettled,
+    lines: Option<&SpinLinesSettled>,
+    receipt: Option<&ReceiptAppended>,
+    input: &ProofInput,
+    verdict: &mut Verdict,
+) -> Recomputed {
+    let vrf_hash = game_math::vrf_hash(vrf);
+    if vrf_hash != settled.vrf_hash {
+        verdict.reasons.push("VRF bytes do not hash to the logged vrf_hash".into());
+    }
+    if game_math::vrf_prefix(vrf) != settled.vrf_prefix {
+        verdict.reasons.push("VRF bytes do not start with the logged vrf_prefix".into());
+    }
+
+    let reels = match &input.config {
+        Some(config) => config.reel_weights,
+        None => {
+            verdict.warnings.push("no config snapshot; reels drawn on REEL_WEIGHTS".into());
+            fairness::REEL_WEIGHTS
+        }
+    };
+    let (symbols, grid) = match lines {
+        Some(_) => {
+            let mut seed = fairness::seed_from_vrf(vrf);
+            let grid = game_math::spin_grid(&mut seed, &reels);
+            (grid[0], Some(grid))
+        }
+        None => (fairness::symbols_for_reveal(vrf, &reels), None),
+    };
+    if symbols != settled.symbols {
+        verdict.reasons.push("VRF bytes do not reproduce the settled symbols".into());
+    }
+    if let (Some(grid), Some(l)) = (grid, lines) {
+        if grid != l.grid {
+            verdict.reasons.push("VRF bytes do not reproduce the settled grid".into());
+        }
+    }
+
+    let mut line_payouts = None;
+    let mut base_payout_full = None;
+    match &input.config {
+        Some(config) => {
+            if config.slot > input.settle.slot {
+                verdict.warnings.push(format!(
+                    "paytable read at slot {}, after the settle; a paytable change in \
+                     between shows up as a payout mismatch",
+                    config.slot
+                ));
+            }
+            let full = match (grid, lines) {
+                (Some(grid), Some(l)) => {
+                    let payouts: Vec<u64> = (0..l.lines as usize)
+                        .map(|line| {
+                            let symbols = game_math::line_symbols(&grid, line);
+                            let wild = config.wild_symbol;
+                            game_math::calculate_payout(symbols, l.line_bet, &config.paytable, wild)
+                                .0
+                        })
+                        .collect();
+                    if payouts[..] != l.line_payouts[..payouts.len()] {
+                        verdict.reasons.push("line payouts do not match the paytable".into());
+                    }
+                    let full = payouts.iter().fold(0u64, |acc, p| acc.saturating_add(*p));
+                    line_payouts = Some(payouts);
+                    full
+                }
+                _ => {
+                    let bet = committed.bet_amount;
+                    game_math::calculate_payout(symbols, bet, &config.paytable, config.wild_symbol)
+                        .0
+                }
+            };
+            if settled.base_payout > full {
+                verdict.reasons.push(format!(
+                    "base_payout {} exceeds the paytable win {}",
+                    settled.base_payout, full
+                ));
+            } else if settled.base_payout < full {
+                verdict.warnings.push(format!(
+                    "base win capped from {} to {} by pool headroom or max_payout_per_spin",
+                    full, settled.base_payout
+                ));
+            }
+            base_payout_full = Some(full);
+        }
+        None => verdict.warnings.push("no config snapshot; payouts not recomputed".into()),
+    }
+    if settled.jackpot_payout > 0 {
+        verdict.warnings.push(
+            "jackpot roll not re-derived; it needs the jackpot weights and odds at commit".into(),
+        );
+    }
+
+    let receipt_leaf = receipt.map(|r| {
+        hex(&receipts::receipt_leaf(
+            &input.user,
+            r.leaf_index,
+            committed.bet_amount,
+            symbols,
+            settled.base_payout,
+            settled.jackpot_payout,
+            &vrf_hash,
+        ))
+    });
+    if let (Some(leaf), Some(r)) = (&receipt_leaf, receipt) {
+        if *leaf != hex(&r.leaf) {
+            verdict.reasons.push("recomputed receipt leaf differs from the logged leaf".into());
+        }
+    }
+
+    Recomputed {
+        vrf_hash: hex(&vrf_hash),
+        symbols,
+        grid,
+        line_payouts,
+        base_payout_full,
+        receipt_leaf,
+    }
+}
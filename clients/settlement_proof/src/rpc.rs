@@ -0,0 +1,92 @@
+//! Fetches the inputs of a proof bundle over JSON-RPC.
+
+use std::str::FromStr;
+
+use anchor_lang::AccountDeserialize;
+use gorbagana_slots::prelude::*;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::proof::{events_from_logs, ConfigAt, SpinEvent, SpinTransaction};
+
+pub fn fetch_transaction(rpc: &RpcClient, signature: &str) -> Result<SpinTransaction, String> {
+    let sig = Signature::from_str(signature).map_err(|e| format!("{}: {}", signature, e))?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: None,
+        max_supported_transaction_version: Some(0),
+    };
+    let tx = rpc
+        .get_transaction_with_config(&sig, config)
+        .map_err(|e| format!("{}: {}", signature, e))?;
+    let logs = tx
+        .transaction
+        .meta
+        .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+        .ok_or_else(|| format!("{}: no logs (pruned by the node?)", signature))?;
+    Ok(SpinTransaction {
+        signature: signature.to_string(),
+        slot: tx.slot,
+        logs,
+    })
+}
+
+/// Commit and settle transactions of `user`'s spin `spin_nonce`, found
+/// by walking the signatures on the player's PlayerState. Only the
+/// newest 1000 are searched.
+pub fn find_spin(
+    rpc: &RpcClient,
+    user: &Pubkey,
+    spin_nonce: u64,
+) -> Result<(SpinTransaction, SpinTransaction), String> {
+    let player_state = client::player_state_address(user);
+    let signatures = rpc
+        .get_signatures_for_address(&player_state)
+        .map_err(|e| format!("signatures for {}: {}", player_state, e))?;
+
+    let (mut commit, mut settle) = (None, None);
+    for status in signatures.iter().filter(|s| s.err.is_none()) {
+        let tx = fetch_transaction(rpc, &status.signature)?;
+        for event in events_from_logs(&tx.logs) {
+            if !event.is_for(user, spin_nonce) {
+                continue;
+            }
+            match event {
+                SpinEvent::Committed(_) if commit.is_none() => commit = Some(tx.signature.clone()),
+                SpinEvent::Settled(_) | SpinEvent::Voided(_) if settle.is_none() => {
+                    settle = Some(tx.signature.clone())
+                }
+                _ => {}
+            }
+        }
+        if commit.is_some() && settle.is_some() {
+            break;
+        }
+    }
+    let commit = commit.ok_or_else(|| format!("spin {} has no commit on chain", spin_nonce))?;
+    let settle = settle.ok_or_else(|| format!("spin {} is not settled", spin_nonce))?;
+    Ok((fetch_transaction(rpc, &commit)?, fetch_transaction(rpc, &settle)?))
+}
+
+/// Current reels, paytable and caps. RPC has no historical account state, so
+/// this is the config as of the returned slot, not the settle slot.
+pub fn fetch_config(rpc: &RpcClient, slots_state: &Pubkey) -> Result<ConfigAt, String> {
+    let response = rpc
+        .get_account_with_commitment(slots_state, rpc.commitment())
+        .map_err(|e| format!("{}: {}", slots_state, e))?;
+    let account = response
+        .value
+        .ok_or_else(|| format!("{}: account not found", slots_state))?;
+    let state = SlotsState::try_deserialize(&mut &account.data[..])
+        .map_err(|e| format!("{}: {}", slots_state, e))?;
+    Ok(ConfigAt {
+        slot: response.context.slot,
+        reel_weights: state.reel_weights,
+        wild_symbol: state.wild_symbol,
+        paytable: state.paytable,
+        max_payout_per_spin: state.max_payout_per_spin,
+    })
+}
@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+
+declare_id!("5oZQfboUfDdhX4YGp2kYCk1txTTrYSsM8rtR2pS9tEw7"); // TODO: replace with your real program id
+
+/// Reference partner program for Gorbagana Slots settle hooks.
+///
+/// The slots program CPIs `on_spin_settled` after a settle completes,
+/// passing whatever accounts the caller appended after this program id in
+/// remaining_accounts. None of them arrive as signers. This example awards
+/// one point per lamport wagered into a per-player PDA the partner created
+/// beforehand.
+
+#[account]
+pub struct PartnerPoints {
+    pub player: Pubkey,
+    pub points: u64,
+    pub bump: u8,
+}
+
+#[event]
+pub struct PointsAwarded {
+    pub player: Pubkey,
+    pub points: u64,
+    pub total: u64,
+}
+
+#[program]
+pub mod example_settle_hook {
+    use super::*;
+
+    pub fn init_points(ctx: Context<InitPoints>) -> Result<()> {
+        let points = &mut ctx.accounts.points;
+        points.player = ctx.accounts.player.key();
+        points.points = 0;
+        points.bump = ctx.bumps.points;
+        Ok(())
+    }
+
+    /// Called by the slots program. Must match its OnSpinSettledArgs layout.
+    pub fn on_spin_settled(
+        ctx: Context<OnSpinSettled>,
+        user: Pubkey,
+        bet: u64,
+        _payout: u64,
+        _symbols: [u8; 3],
+    ) -> Result<()> {
+        let points = &mut ctx.accounts.points;
+        require_keys_eq!(points.player, user);
+
+        points.points = points.points.saturating_add(bet);
+
+        emit!(PointsAwarded {
+            player: user,
+            points: bet,
+            total: points.points,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitPoints<'info> {
+    #[account(
+        init,
+        payer = player,
+        seeds = [b"points", player.key().as_ref()],
+        bump,
+        space = 8 + 32 + 8 + 1,
+    )]
+    pub points: Account<'info, PartnerPoints>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OnSpinSettled<'info> {
+    #[account(
+        mut,
+        seeds = [b"points", points.player.as_ref()],
+        bump = points.bump,
+    )]
+    pub points: Account<'info, PartnerPoints>,
+}
@@ -0,0 +1,42 @@
+//! Bakes the immutable-program deployer key into the build.
+//!
+//! `GORBAGANA_IMMUTABLE_DEPLOYER` (base58) names who may initialize once
+//! the program has no upgrade authority. A program build (target_os
+//! "solana") does not compile without it. Host builds (tests, clients)
+//! leave it unset and get `None`, which lets nobody in.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const VAR: &str = "GORBAGANA_IMMUTABLE_DEPLOYER";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={VAR}");
+    let on_chain = env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("solana");
+
+    let value = match env::var(VAR) {
+        Ok(key) => {
+            let bytes = bs58::decode(key.trim())
+                .into_vec()
+                .unwrap_or_else(|e| panic!("{VAR} is not base58: {e}"));
+            let bytes: [u8; 32] = bytes
+                .try_into()
+                .unwrap_or_else(|b: Vec<u8>| panic!("{VAR} is {} bytes, not 32", b.len()));
+            format!("Some(Pubkey::new_from_array({bytes:?}))")
+        }
+        Err(_) if on_chain => format!(
+            "{{ compile_error!(\"set {VAR} to the deployer's base58 key to build the program\"); \
+             None }}"
+        ),
+        Err(_) => "None".to_string(),
+    };
+
+    let out = Path::new(&env::var("OUT_DIR").expect("OUT_DIR")).join("deployer.rs");
+    let source = format!(
+        "/// Who may initialize once the program is immutable (no upgrade\n\
+         /// authority), from {VAR} at build time; `None` lets nobody in.\n\
+         pub const IMMUTABLE_PROGRAM_DEPLOYER: Option<Pubkey> = {value};\n"
+    );
+    fs::write(out, source).expect("write deployer.rs");
+}
@@ -0,0 +1,205 @@
+//! Per-player registry of the program's accounts.
+//!
+//! The `AccountIndex` PDA (seeds `[ACCOUNT_INDEX_SEED, user]`) records which
+//! per-player accounts exist and their bumps, so a wallet can fetch one
+//! account and derive every address without knowing each seed scheme.
+//! Each init/close path for a per-player account takes the index PDA as a
+//! required, address-checked account and updates it whenever it exists;
+//! since the caller cannot leave it out, an existing index never lies.
+//! A player without an index can create one at any time and it starts
+//! from what is actually on-chain.
+
+use anchor_lang::prelude::*;
+
+use crate::{AccountIndex, ErrorCode};
+
+/// Bit positions in `AccountIndex.present`, also indexes into `bumps`.
+pub const INDEX_KIND_PLAYER_STATE: u8 = 0;
+
+/// Number of kinds the index has room for.
+pub const ACCOUNT_INDEX_SLOTS: usize = 32;
+
+impl AccountIndex {
+    pub const LEN: usize = 8 + 32 + 4 + ACCOUNT_INDEX_SLOTS + 1;
+
+    pub fn has(&self, kind: u8) -> bool {
+        (kind as usize) < ACCOUNT_INDEX_SLOTS && self.present & (1 << kind) != 0
+    }
+
+    pub fn bump_of(&self, kind: u8) -> Option<u8> {
+        self.has(kind).then(|| self.bumps[kind as usize])
+    }
+
+    pub fn mark_created(&mut self, kind: u8, bump: u8) -> Result<()> {
+        require!(
+            (kind as usize) < ACCOUNT_INDEX_SLOTS,
+            ErrorCode::InvalidAccountIndexKind
+        );
+        self.present |= 1 << kind;
+        self.bumps[kind as usize] = bump;
+        Ok(())
+    }
+
+    pub fn mark_closed(&mut self, kind: u8) -> Result<()> {
+        require!(
+            (kind as usize) < ACCOUNT_INDEX_SLOTS,
+            ErrorCode::InvalidAccountIndexKind
+        );
+        self.present &= !(1 << kind);
+        self.bumps[kind as usize] = 0;
+        Ok(())
+    }
+}
+
+/// Whether `info` is an initialized account of this program.
+pub fn exists(info: &AccountInfo) -> bool {
+    info.owner == &crate::ID && !info.data_is_empty()
+}
+
+/// Apply `update` to the index at `info` if it has been created; a
+/// player without an index is left alone. The index is read and written
+/// in place: `info` is borrowed from the instruction's accounts, not for
+/// the whole `'info`, so it cannot back an `Account<'info, _>`.
+pub fn update_if_exists<'info>(
+    info: &AccountInfo<'info>,
+    update: impl FnOnce(&mut AccountIndex) -> Result<()>,
+) -> Result<()> {
+    if !exists(info) {
+        return Ok(());
+    }
+    let mut index = AccountIndex::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    update(&mut index)?;
+    index.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::client::discover;
+    use crate::test_util::{pda, simulate, zeroed, Fixture, Game};
+    use crate::{instruction, PlayerState, ACCOUNT_INDEX_SEED, PLAYER_SEED};
+
+    fn system() -> Fixture {
+        Fixture::program(anchor_lang::system_program::ID)
+    }
+
+    /// init_account_index's accounts for `user` on the legacy machine.
+    fn indexing(user: Pubkey, player: Fixture) -> [Fixture; 4] {
+        [
+            Fixture::uninit(pda(&[ACCOUNT_INDEX_SEED, user.as_ref()]).0, AccountIndex::LEN),
+            player,
+            Fixture::wallet(user, 1_000_000_000).signer(),
+            system(),
+        ]
+    }
+
+    /// init_player's accounts for `user` on `game`'s machine.
+    fn registering(game: &Game, user: Pubkey, index: Fixture) -> [Fixture; 6] {
+        let [state, _, treasury] = game.fixtures();
+        let machine = crate::machine_seed(&game.state.machine_id);
+        let (player, _) = pda(&[PLAYER_SEED, machine, user.as_ref()]);
+        [
+            state,
+            Fixture::uninit(player, PlayerState::LEN),
+            index,
+            Fixture::wallet(user, 1_000_000_000).signer(),
+            treasury,
+            system(),
+        ]
+    }
+
+    #[test]
+    fn the_bitfield_follows_create_close_and_recreate() {
+        let mut index: AccountIndex = zeroed(AccountIndex::LEN);
+        assert_eq!(index.bump_of(INDEX_KIND_PLAYER_STATE), None);
+
+        index.mark_created(INDEX_KIND_PLAYER_STATE, 254).unwrap();
+        index.mark_created(31, 7).unwrap();
+        assert_eq!(index.bump_of(INDEX_KIND_PLAYER_STATE), Some(254));
+        assert_eq!(index.present, 1 | 1 << 31);
+
+        index.mark_closed(INDEX_KIND_PLAYER_STATE).unwrap();
+        assert!(!index.has(INDEX_KIND_PLAYER_STATE));
+        assert_eq!(index.bumps[0], 0);
+        assert_eq!(index.bump_of(31), Some(7));
+
+        index.mark_created(INDEX_KIND_PLAYER_STATE, 253).unwrap();
+        assert_eq!(index.bump_of(INDEX_KIND_PLAYER_STATE), Some(253));
+
+        let out_of_range = ACCOUNT_INDEX_SLOTS as u8;
+        assert!(!index.has(out_of_range));
+        assert_eq!(
+            index.mark_created(out_of_range, 1).unwrap_err(),
+            ErrorCode::InvalidAccountIndexKind.into()
+        );
+        assert_eq!(
+            index.mark_closed(out_of_range).unwrap_err(),
+            ErrorCode::InvalidAccountIndexKind.into()
+        );
+    }
+
+    #[test]
+    fn an_index_created_first_records_the_player_state() {
+        let game = Game::new();
+        let user = Pubkey::new_unique();
+        let (player, bump) = pda(&[PLAYER_SEED, user.as_ref()]);
+
+        let mut accounts = indexing(user, Fixture::wallet(player, 0));
+        simulate(&mut accounts, &instruction::InitAccountIndex {}.data()).unwrap();
+        let index = accounts[0].clone();
+        assert_eq!(discover(&user, &index.data), Some(vec![]));
+        assert_eq!(discover(&Pubkey::new_unique(), &index.data), None);
+
+        let mut accounts = registering(&game, user, index);
+        simulate(&mut accounts, &instruction::InitPlayer {}.data()).unwrap();
+        let index = accounts[2].read::<AccountIndex>();
+        assert_eq!(index.owner, user);
+        assert_eq!(index.bump_of(INDEX_KIND_PLAYER_STATE), Some(bump));
+        let found = discover(&user, &accounts[2].data).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, player);
+        assert_eq!(found[0].address, accounts[1].key);
+    }
+
+    #[test]
+    fn an_index_created_late_starts_from_the_chain() {
+        let game = Game::new();
+        let user = Pubkey::new_unique();
+        let index_key = pda(&[ACCOUNT_INDEX_SEED, user.as_ref()]).0;
+
+        // Without an index, registering leaves the address alone.
+        let mut accounts = registering(&game, user, Fixture::wallet(index_key, 0));
+        simulate(&mut accounts, &instruction::InitPlayer {}.data()).unwrap();
+        assert!(accounts[2].data.is_empty());
+
+        let mut indexed = indexing(user, accounts[1].clone());
+        simulate(&mut indexed, &instruction::InitAccountIndex {}.data()).unwrap();
+        let found = discover(&user, &indexed[0].data).unwrap();
+        assert_eq!(found.iter().map(|a| a.address).collect::<Vec<_>>(), [accounts[1].key]);
+
+        // Another player's index cannot stand in for the user's.
+        let other = Pubkey::new_unique();
+        let mut accounts = registering(&game, other, indexed[0].clone());
+        let seeds = anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintSeeds);
+        assert_eq!(
+            simulate(&mut accounts, &instruction::InitPlayer {}.data()),
+            Err(seeds.into())
+        );
+    }
+
+    #[test]
+    fn another_machine_leaves_the_legacy_index_alone() {
+        let mut game = Game::new();
+        game.state.machine_id = [7; 32];
+        let user = Pubkey::new_unique();
+        let (player, _) = pda(&[PLAYER_SEED, user.as_ref()]);
+
+        let mut accounts = indexing(user, Fixture::wallet(player, 0));
+        simulate(&mut accounts, &instruction::InitAccountIndex {}.data()).unwrap();
+        let mut accounts = registering(&game, user, accounts[0].clone());
+        simulate(&mut accounts, &instruction::InitPlayer {}.data()).unwrap();
+        assert_eq!(accounts[2].read::<AccountIndex>().present, 0);
+    }
+}
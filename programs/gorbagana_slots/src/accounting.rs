@@ -0,0 +1,312 @@
+//! Split of `total_pool` between the base game and the jackpots.
+//!
+//! Every bet joins `total_pool`, and its jackpot contributions are
+//! counted again in the tier `amount`s. The tiers are therefore a slice
+//! of the pool, not money next to it:
+//!
+//! `total_pool == base_pool + jackpot amounts`
+//!
+//! Only the base pool backs base wins, commits and withdrawals. Settles
+//! take their headroom from it (reservations::headroom_for), commits
+//! must be covered by it (pool_level::required_pool), and
+//! execute_withdrawal keeps the jackpots through required_floor. A jackpot hit pays out of
+//! its own tier. When a tier restarts from its seed after a hit, the seed
+//! is what moves from the base pool into the jackpots.
+//!
+//! [`ensure_pool_split`] runs after every commit and settle; the
+//! verify_accounting view reports the split with the treasury balance.
+//!
+//! `total_pool` is bookkeeping, and lamports sent straight to the
+//! treasury (airdrops, rent top-ups, stray transfers) make the two drift.
+//! sync_pool sets it back to what the treasury can spend, its balance
+//! above the rent-exempt minimum ([`spendable_lamports`]), less every
+//! other tracked bucket. A treasury short of the books still converges:
+//! the pool shrinks, and if it no longer covers the jackpots they are
+//! trimmed, grand first, so the split holds. Every lamport check against
+//! the treasury uses the spendable figure, so a payout can never take it
+//! below rent exemption.
+//!
+//! In a token deployment `total_pool` is in the token's base units and
+//! lives in the token treasury. The only other bucket paid in tokens is
+//! the claimables (wager race prizes and spins voided at a thaw); every
+//! other bucket stays in lamports in the SOL treasury, and the
+//! instructions that would fill one from a bet refuse token mode (see
+//! token_mode.rs). [`other_buckets`] is what sits next to the pool in the
+//! pool's currency, [`lamport_buckets`] what a token deployment's SOL
+//! treasury must still hold, and the checks run against each balance.
+
+use anchor_lang::prelude::*;
+
+use crate::{total_jackpot_amounts, ErrorCode, SlotsState};
+
+/// Return data of `verify_accounting`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct AccountingReport {
+    pub total_pool: u64,
+    pub base_pool: u64,
+    pub jackpot_total: u64,
+    /// total_pool plus every bucket held next to it in the treasury: fee
+    /// balance, player claimables, community share, faucet reserve,
+    /// booked operator fees, referral credit and VIP rebates.
+    pub tracked_total: u64,
+    pub treasury_lamports: u64,
+    /// Token treasury balance of a token deployment; 0 for SOL.
+    pub treasury_tokens: u64,
+}
+
+/// Part of `total_pool` outside the jackpot tiers.
+pub fn base_pool(state: &SlotsState) -> Result<u64> {
+    let jackpots = total_jackpot_amounts(&state.jackpots)?;
+    state
+        .total_pool
+        .checked_sub(jackpots)
+        .ok_or_else(|| error!(ErrorCode::AccountingInvariantViolated))
+}
+
+/// Fail if the jackpot tiers claim more than the pool holds.
+pub fn ensure_pool_split(state: &SlotsState) -> Result<()> {
+    base_pool(state).map(|_| ())
+}
+
+/// Treasury balance above its rent-exempt minimum.
+pub fn spendable_lamports(treasury: &AccountInfo) -> Result<u64> {
+    let rent_min = Rent::get()?.minimum_balance(treasury.data_len());
+    Ok(treasury.lamports().saturating_sub(rent_min))
+}
+
+/// Everything held next to `total_pool` in the pool's currency.
+pub fn other_buckets(state: &SlotsState) -> Result<u64> {
+    if state.exclusive_mint().is_some() {
+        return Ok(state.total_claimable);
+    }
+    [
+        state.fee_balance,
+        state.total_claimable,
+        state.community_balance,
+        state.faucet_reserve,
+        state.operator_fee_balance,
+        state.referral_balance,
+        state.vip_rebate_balance,
+    ]
+    .iter()
+    .try_fold(0u64, |acc, &b| acc.checked_add(b))
+    .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// Lamport buckets a token deployment's SOL treasury holds; 0 for a SOL
+/// deployment, whose buckets are all in [`other_buckets`].
+pub fn lamport_buckets(state: &SlotsState) -> Result<u64> {
+    if state.exclusive_mint().is_none() {
+        return Ok(0);
+    }
+    [
+        state.fee_balance,
+        state.community_balance,
+        state.faucet_reserve,
+        state.operator_fee_balance,
+        state.referral_balance,
+        state.vip_rebate_balance,
+    ]
+    .iter()
+    .try_fold(0u64, |acc, &b| acc.checked_add(b))
+    .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// Reset `total_pool` from what the pool's treasury can `spend` (lamports,
+/// or tokens in a token deployment); returns the new figure. Never fails
+/// on a short treasury, see the module docs.
+pub fn sync_pool(state: &mut SlotsState, spendable: u64) -> Result<u64> {
+    let pool = spendable.saturating_sub(other_buckets(state)?);
+    state.total_pool = pool;
+    let [mini, major, grand] = trim_jackpots(
+        [
+            state.jackpots.mini.amount,
+            state.jackpots.major.amount,
+            state.jackpots.grand.amount,
+        ],
+        pool,
+    );
+    state.jackpots.mini.amount = mini;
+    state.jackpots.major.amount = major;
+    state.jackpots.grand.amount = grand;
+    Ok(pool)
+}
+
+/// Tier amounts (mini, major, grand) cut down to fit `budget`, taking
+/// from the grand first.
+pub fn trim_jackpots(amounts: [u64; 3], budget: u64) -> [u64; 3] {
+    let mut left = budget;
+    let mut trimmed = [0; 3];
+    for tier in [0, 1, 2] {
+        trimmed[tier] = amounts[tier].min(left);
+        left -= trimmed[tier];
+    }
+    trimmed
+}
+
+/// Check the pool split and that the treasury holds every tracked bucket.
+/// A token deployment passes its token treasury's balance as
+/// `treasury_tokens`: the pool and claimables are checked against it, the
+/// lamport buckets against `treasury_lamports`.
+pub fn verify(
+    state: &SlotsState,
+    treasury_lamports: u64,
+    treasury_tokens: Option<u64>,
+) -> Result<AccountingReport> {
+    let base_pool = base_pool(state)?;
+    let tracked_total = other_buckets(state)?
+        .checked_add(state.total_pool)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let pool_balance = match (state.exclusive_mint(), treasury_tokens) {
+        (None, _) => treasury_lamports,
+        (Some(_), Some(tokens)) => tokens,
+        (Some(_), None) => return err!(ErrorCode::TokenTreasuryMissing),
+    };
+    require!(
+        pool_balance >= tracked_total && treasury_lamports >= lamport_buckets(state)?,
+        ErrorCode::AccountingInvariantViolated
+    );
+
+    Ok(AccountingReport {
+        total_pool: state.total_pool,
+        base_pool,
+        jackpot_total: state.total_pool - base_pool,
+        tracked_total,
+        treasury_lamports,
+        treasury_tokens: treasury_tokens.unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::instruction as ix;
+    use crate::test_util::{program_error, returned, simulate, Fixture, Game};
+    use crate::{pool_level, reservations, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    /// verify_accounting's accounts for a SOL deployment.
+    fn verifying(game: &Game) -> Vec<Fixture> {
+        let [state, _, treasury] = game.fixtures();
+        vec![state, treasury, Fixture::omitted()]
+    }
+
+    #[test]
+    fn jackpots_that_fit_are_kept() {
+        assert_eq!(trim_jackpots([10, 20, 30], 60), [10, 20, 30]);
+        assert_eq!(trim_jackpots([10, 20, 30], 1_000), [10, 20, 30]);
+    }
+
+    #[test]
+    fn short_pool_trims_the_grand_first() {
+        assert_eq!(trim_jackpots([10, 20, 30], 45), [10, 20, 15]);
+        assert_eq!(trim_jackpots([10, 20, 30], 25), [10, 15, 0]);
+        assert_eq!(trim_jackpots([10, 20, 30], 0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn a_big_base_win_cannot_spend_the_jackpot_reserves() {
+        let mut game = Game::new();
+        // No jackpot rolls, so the win is all base game.
+        game.state.jackpots.hit_weight_total = 0;
+        game.request(BET).unwrap();
+        // By the settle the jackpots hold all but one bet of the headroom.
+        let headroom = reservations::headroom_for(&game.state, &game.player).unwrap();
+        game.state.jackpots.grand.amount += headroom - BET;
+        let jackpots = total_jackpot_amounts(&game.state.jackpots).unwrap();
+        let win = game.reveal_where(|s| s.outcome.base_payout_full > 2 * BET);
+        game.reveal(win);
+
+        let settlement = game.settle().unwrap();
+        assert!(settlement.outcome.base_payout_full > BET);
+        assert_eq!(settlement.outcome.base_payout, BET);
+        assert_eq!(settlement.outcome.jackpot_payout, 0);
+        assert_eq!(total_jackpot_amounts(&game.state.jackpots).unwrap(), jackpots);
+        assert!(base_pool(&game.state).is_ok());
+    }
+
+    #[test]
+    fn a_commit_needs_base_pool_cover() {
+        let mut game = Game::new();
+        let required = pool_level::required_pool(&game.state, game.player.tier);
+        // One lamport more in the jackpots than the base pool can spare;
+        // total_pool alone would still cover the commit.
+        game.state.jackpots.grand.amount += game.state.total_pool - required + 1;
+        assert!(game.state.total_pool >= required);
+        assert_eq!(
+            game.request(BET).unwrap_err(),
+            ErrorCode::InsufficientPool.into()
+        );
+    }
+
+    #[test]
+    fn jackpots_over_the_pool_break_the_split() {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        game.reveal([3; 32]);
+        let base = base_pool(&game.state).unwrap();
+        game.state.jackpots.grand.amount += base + 1;
+
+        assert_eq!(
+            ensure_pool_split(&game.state).unwrap_err(),
+            ErrorCode::AccountingInvariantViolated.into()
+        );
+        assert_eq!(
+            game.settle().unwrap_err(),
+            ErrorCode::AccountingInvariantViolated.into()
+        );
+    }
+
+    #[test]
+    fn verify_accounting_reports_the_split() {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let data = ix::VerifyAccounting {}.data();
+
+        let mut accounts = verifying(&game);
+        let lamports = accounts[1].lamports;
+        simulate(&mut accounts, &data).unwrap();
+        let report = returned::<AccountingReport>();
+        let jackpots = total_jackpot_amounts(&game.state.jackpots).unwrap();
+        assert_eq!(report.total_pool, game.state.total_pool);
+        assert_eq!(report.jackpot_total, jackpots);
+        assert_eq!(report.base_pool + report.jackpot_total, report.total_pool);
+        assert_eq!(report.tracked_total, game.state.total_pool);
+        assert_eq!(report.treasury_lamports, lamports);
+
+        // A treasury short of the books.
+        let mut accounts = verifying(&game);
+        accounts[1].lamports = report.tracked_total - 1;
+        assert_eq!(
+            simulate(&mut accounts, &data),
+            Err(program_error(ErrorCode::AccountingInvariantViolated))
+        );
+
+        // Jackpots over the pool.
+        let mut overdrawn = game.clone();
+        overdrawn.state.jackpots.grand.amount += report.base_pool + 1;
+        let mut accounts = verifying(&overdrawn);
+        assert_eq!(
+            simulate(&mut accounts, &data),
+            Err(program_error(ErrorCode::AccountingInvariantViolated))
+        );
+
+        // Another wallet posing as the treasury.
+        let mut accounts = verifying(&game);
+        accounts[1] = Fixture::wallet(Pubkey::new_unique(), lamports);
+        let has_one = anchor_lang::error::Error::from(
+            anchor_lang::error::ErrorCode::ConstraintHasOne,
+        );
+        assert_eq!(simulate(&mut accounts, &data), Err(has_one.into()));
+
+        game.state.initialized = false;
+        let mut accounts = verifying(&game);
+        assert_eq!(
+            simulate(&mut accounts, &data),
+            Err(program_error(ErrorCode::Uninitialized))
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Golden check of the crate's public API.
+//!
+//! The public surface is read from the sources: the `pub mod`s of lib.rs,
+//! the `pub` items declared at the crate root or glob re-exported there
+//! (contexts, errors, events, state), and every path prelude.rs
+//! re-exports. The sorted list is compared with `api/public.txt`, so an
+//! item that becomes public, stops being public or leaves the prelude
+//! fails the test. Rerun with `UPDATE_API=1` to regenerate the file after
+//! a deliberate change.
+//!
+//! Every PDA seed constant must also be in the prelude; a new seed that
+//! is not re-exported fails even after a regeneration.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Set to rewrite the committed API list from the current sources.
+const UPDATE_VAR: &str = "UPDATE_API";
+const COMMITTED_API: &str = include_str!("../api/public.txt");
+
+const LIB: &str = include_str!("lib.rs");
+const PRELUDE: &str = include_str!("prelude.rs");
+/// Modules lib.rs glob re-exports at the crate root.
+const ROOT_GLOBS: [(&str, &str); 4] = [
+    ("contexts", include_str!("contexts.rs")),
+    ("errors", include_str!("errors.rs")),
+    ("events", include_str!("events.rs")),
+    ("state", include_str!("state.rs")),
+];
+
+/// `pub` items declared at column 0 of `source`, as `kind name`.
+fn pub_items(source: &str) -> Vec<String> {
+    const KINDS: [&str; 8] = [
+        "const", "enum", "fn", "mod", "static", "struct", "trait", "type",
+    ];
+    source
+        .lines()
+        .filter_map(|line| line.strip_prefix("pub "))
+        .filter_map(|rest| {
+            let mut words = rest.split_whitespace();
+            let kind = words.next()?;
+            let name: String = words
+                .next()?
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            KINDS.contains(&kind).then(|| format!("{kind} {name}"))
+        })
+        .collect()
+}
+
+/// The paths of every `pub use` in `source`, braces expanded.
+fn reexports(source: &str) -> Vec<String> {
+    let code: String = source
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .filter(|line| !line.trim_start().starts_with("#["))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut paths = Vec::new();
+    for statement in code.split(';') {
+        let Some(path) = statement.trim().strip_prefix("pub use ") else {
+            continue;
+        };
+        let path: String = path.split_whitespace().collect();
+        match path.split_once('{') {
+            Some((prefix, list)) => {
+                let prefix = prefix.trim_end_matches("::");
+                for item in list.trim_end_matches('}').split(',').filter(|i| !i.is_empty()) {
+                    paths.push(match item {
+                        "self" => prefix.to_string(),
+                        item => format!("{prefix}::{item}"),
+                    });
+                }
+            }
+            None => paths.push(path),
+        }
+    }
+    paths
+}
+
+fn public_api() -> String {
+    let mut lines = BTreeSet::new();
+    for item in pub_items(LIB) {
+        lines.insert(format!("root {item}"));
+    }
+    for (module, source) in ROOT_GLOBS {
+        for item in pub_items(source) {
+            lines.insert(format!("root {item} ({module})"));
+        }
+    }
+    for path in reexports(PRELUDE) {
+        lines.insert(format!("prelude {path}"));
+    }
+    let mut out = lines.into_iter().collect::<Vec<_>>().join("\n");
+    out.push('\n');
+    out
+}
+
+#[test]
+fn public_api_matches_the_committed_list() {
+    let current = public_api();
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("api/public.txt");
+        std::fs::write(path, &current).expect("write api/public.txt");
+        return;
+    }
+    let committed: BTreeSet<&str> = COMMITTED_API.lines().collect();
+    let current_set: BTreeSet<&str> = current.lines().collect();
+    let added: Vec<_> = current_set.difference(&committed).collect();
+    let removed: Vec<_> = committed.difference(&current_set).collect();
+    assert!(
+        added.is_empty() && removed.is_empty(),
+        "public API changed (rerun with {UPDATE_VAR}=1 if deliberate)\n\
+         added: {added:#?}\nremoved: {removed:#?}"
+    );
+}
+
+#[test]
+fn every_pda_seed_is_in_the_prelude() {
+    let exported = reexports(PRELUDE);
+    let missing: Vec<_> = pub_items(LIB)
+        .into_iter()
+        .filter_map(|item| item.strip_prefix("const ").map(str::to_string))
+        .filter(|name| name.ends_with("_SEED"))
+        .filter(|name| !exported.contains(&format!("crate::{name}")))
+        .collect();
+    assert!(missing.is_empty(), "seeds missing from the prelude: {missing:?}");
+}
+
+#[test]
+fn internal_modules_stay_private() {
+    // The prelude re-exports these whole; everything else is pub(crate).
+    let public: Vec<_> = pub_items(LIB)
+        .into_iter()
+        .filter_map(|item| item.strip_prefix("mod ").map(str::to_string))
+        .collect();
+    let exported = reexports(PRELUDE);
+    for module in public {
+        assert!(
+            module == "prelude" || exported.contains(&format!("crate::{module}")),
+            "module {module} is public but not re-exported by the prelude"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Settling many players' pending spins in one transaction.
+//!
+//! settle_spins_batch takes up to [`MAX_BATCH_SETTLE`] entries as
+//! remaining accounts, [`ACCOUNTS_PER_ENTRY`] per entry: the PlayerState
+//! (writable), its owner (writable, paid) and the randomness account the
+//! spin committed to. Each entry goes through settle_pending exactly as
+//! settle_spin does, with its position in the batch as `context_index`,
+//! and the cranker earns `crank_fee` per entry it does not own.
+//!
+//! An entry is skipped, and stays pending, when its spin is already
+//! settled, its randomness has not revealed yet, its player has a
+//! referrer (whose earnings PDA only settle_spin takes), or, for an entry
+//! the cranker does not own, settling it would drop an account a crank
+//! must pass (see needs_accounts). Any other
+//! failure fails the whole batch, so a batch either commits every
+//! non-skipped entry or none. BatchSettled reports how many settled.
+//! The RTP history, receipts, wager races and settle hooks are not fed by
+//! batches.
+//!
+//! Jackpot hits follow `jackpot_batch_mode`. In Sequential mode entries
+//! settle in order, and a later hit on the same tier sees the reseeded
+//! amount. In Split mode the winners of each tier are counted first,
+//! against the pre-batch state, and every one of them is paid an equal
+//! share of the pre-batch amount (game_math::split_jackpot); the
+//! remainder stays on the tier, on top of its reseed. No other entry can
+//! hit a tier with counted winners, and a counted winner whose headroom
+//! earlier entries used up misses like any unaffordable hit. Either way
+//! each hit emits JackpotBatchShare.
+
+use anchor_lang::prelude::*;
+
+use crate::game_math::{jackpot_pool_mut, split_jackpot, PayoutLimits};
+use crate::machines::machine_seed;
+use crate::randomness::RandomnessCheck;
+use crate::{
+    derive_pending_outcome, leaderboard, must_hit, reseed, reservations, tiers, ErrorCode, JackpotsConfig,
+    PlayerState, SlotsState, PLAYER_SEED,
+};
+
+/// Most entries one call settles, sized so a batch and its compute
+/// budget instructions fit one transaction.
+pub const MAX_BATCH_SETTLE: usize = 8;
+pub const ACCOUNTS_PER_ENTRY: usize = 3;
+
+/// Jackpot amounts one entry draws against in Split mode, per tier:
+/// its share for a tier it was counted as winning, 0 for any other split
+/// tier so it cannot hit it, None to draw against the pool as is.
+pub type SplitAmounts = [Option<u64>; 3];
+
+/// Where an entry's spin stands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Readiness {
+    /// No spin pending: settled by someone else already.
+    NotPending,
+    /// The committed generation has not revealed yet.
+    NotRevealed,
+    /// The player has a referrer; settle_spin takes the earnings PDA.
+    Referred,
+    /// A crank must pass accounts a batch does not take; settle_spin
+    /// takes them.
+    NeedsAccounts,
+    /// The account was re-seeded since the commit; settle_spin refuses
+    /// it too, and the spin waits for admin_void_spin or cancel_spin.
+    Rotated,
+    Revealed([u8; 32]),
+}
+
+impl Readiness {
+    pub fn settles(&self) -> bool {
+        matches!(self, Readiness::Revealed(_))
+    }
+}
+
+/// Split remaining accounts into entries. Each player may appear once.
+pub fn entries<'a, 'info>(accounts: &'a [AccountInfo<'info>]) -> Result<Vec<&'a [AccountInfo<'info>]>> {
+    require!(
+        !accounts.is_empty() && accounts.len().is_multiple_of(ACCOUNTS_PER_ENTRY),
+        ErrorCode::InvalidBatch
+    );
+    let entries: Vec<_> = accounts.chunks(ACCOUNTS_PER_ENTRY).collect();
+    require!(entries.len() <= MAX_BATCH_SETTLE, ErrorCode::InvalidBatch);
+    for (i, entry) in entries.iter().enumerate() {
+        require!(
+            entries[..i].iter().all(|other| other[0].key != entry[0].key),
+            ErrorCode::InvalidBatch
+        );
+    }
+    Ok(entries)
+}
+
+/// Load an entry's PlayerState, pinned to its user and `state`'s machine
+/// the way SettleSpin pins it. Both must be writable.
+pub fn load_player<'info>(
+    state: &SlotsState,
+    player_ai: &'info AccountInfo<'info>,
+    user_ai: &AccountInfo<'info>,
+) -> Result<Account<'info, PlayerState>> {
+    require!(
+        player_ai.is_writable && user_ai.is_writable,
+        ErrorCode::InvalidBatch
+    );
+    let player_state: Account<'info, PlayerState> = Account::try_from(player_ai)?;
+    let expected = Pubkey::create_program_address(
+        &[
+            PLAYER_SEED,
+            machine_seed(&state.machine_id),
+            user_ai.key.as_ref(),
+            &[player_state.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::Unauthorized)?;
+    require_keys_eq!(expected, player_ai.key(), ErrorCode::Unauthorized);
+    require_keys_eq!(player_state.owner, user_ai.key(), ErrorCode::Unauthorized);
+    Ok(player_state)
+}
+
+/// Check the randomness account against the pending spin and read it.
+pub fn readiness(player_state: &PlayerState, randomness_ai: &AccountInfo) -> Result<Readiness> {
+    if !player_state.has_pending_spin {
+        return Ok(Readiness::NotPending);
+    }
+    if player_state.referrer != Pubkey::default() {
+        return Ok(Readiness::Referred);
+    }
+    require_keys_eq!(
+        player_state.randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+    let check = RandomnessCheck::for_settle(player_state.owner, player_state, Clock::get()?.slot);
+    let data = check.load(randomness_ai)?;
+    if !check.same_generation(&data) {
+        return Ok(Readiness::Rotated);
+    }
+    if data.reveal_slot <= player_state.commit_slot {
+        return Ok(Readiness::NotRevealed);
+    }
+    Ok(Readiness::Revealed(data.value))
+}
+
+/// Whether a crank settling `player_state` must pass accounts a batch
+/// does not take: the VIP token account or current leaderboard its
+/// state records, or a registered settle hook (see optional.rs).
+pub fn needs_accounts(state: &SlotsState, player_state: &PlayerState, slot: u64) -> bool {
+    let vip = state.vip_config.enabled() && player_state.vip_token_account != Pubkey::default();
+    let board = player_state.leaderboard != Pubkey::default()
+        && player_state.leaderboard_epoch == leaderboard::epoch_at(state, slot);
+    let hook = state.settle_hooks.iter().any(|h| *h != Pubkey::default());
+    vip || board || hook
+}
+
+/// Jackpot tier a revealed spin hits against the current state, drawn as
+/// settle_pending draws it.
+pub fn peek_jackpot_tier(
+    state: &SlotsState,
+    player_state: &PlayerState,
+    random_bytes: &[u8; 32],
+) -> Result<Option<u8>> {
+    let headroom = reservations::headroom_for(state, player_state)?;
+    let (payout_cap, jackpots) = tiers::committed_terms(state, player_state);
+    let limits = PayoutLimits::for_settle(headroom, payout_cap);
+    let forced = must_hit::forced_tiers(state, player_state, None);
+    let (reels, table, wild) = (&state.reel_weights, &state.paytable, state.wild_symbol);
+    let (outcome, _) = derive_pending_outcome(
+        player_state,
+        random_bytes,
+        limits,
+        &jackpots,
+        forced,
+        reels,
+        table,
+        wild,
+    );
+    Ok(outcome.jackpot_tier)
+}
+
+/// Hits per tier [mini, major, grand].
+pub fn count_winners(hits: &[Option<u8>]) -> [u32; 3] {
+    let mut winners = [0u32; 3];
+    for tier in hits.iter().flatten() {
+        winners[*tier as usize] += 1;
+    }
+    winners
+}
+
+pub fn tier_amounts(jackpots: &JackpotsConfig) -> [u64; 3] {
+    [
+        jackpots.mini.amount,
+        jackpots.major.amount,
+        jackpots.grand.amount,
+    ]
+}
+
+/// Split-mode payout plan, fixed before the first entry settles.
+pub struct SplitPlan {
+    /// Peeked tier per entry, in batch order.
+    hits: Vec<Option<u8>>,
+    pub snapshot: [u64; 3],
+    pub winners: [u32; 3],
+    pub share: [u64; 3],
+    /// Split hits that actually paid; a counted winner can still miss if
+    /// earlier entries shrank its payout headroom.
+    paid: [u32; 3],
+}
+
+impl SplitPlan {
+    pub fn new(jackpots: &JackpotsConfig, hits: Vec<Option<u8>>) -> Self {
+        let snapshot = tier_amounts(jackpots);
+        let winners = count_winners(&hits);
+        let share = [0, 1, 2].map(|tier| split_jackpot(snapshot[tier], winners[tier]).0);
+        Self {
+            hits,
+            snapshot,
+            winners,
+            share,
+            paid: [0; 3],
+        }
+    }
+
+    /// Amounts entry `index` draws against.
+    pub fn amounts_for(&self, index: usize) -> SplitAmounts {
+        let mut amounts = [None; 3];
+        for (tier, amount) in amounts.iter_mut().enumerate() {
+            if self.winners[tier] > 0 {
+                let won = self.hits[index] == Some(tier as u8);
+                *amount = Some(if won { self.share[tier] } else { 0 });
+            }
+        }
+        amounts
+    }
+
+    pub fn record_hit(&mut self, tier: u8) {
+        self.paid[tier as usize] += 1;
+    }
+
+    /// Reseed every tier that paid split shares. Split hits only take
+    /// their share out of the tier, so what is left on it (the remainder
+    /// and the shares of winners that missed) stays on top of the seed.
+    pub fn finish(&self, state: &mut SlotsState) -> Result<()> {
+        for tier in 0..3u8 {
+            if self.paid[tier as usize] == 0 {
+                continue;
+            }
+            let policy = state.jackpot_reseed_policies[tier as usize];
+            let pool = jackpot_pool_mut(&mut state.jackpots, tier);
+            pool.amount = reseed::amount_after_hit(pool, policy)
+                .checked_add(pool.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+/// Override the tier amounts of a spin's committed jackpots.
+pub fn apply_split(jackpots: &mut JackpotsConfig, amounts: &SplitAmounts) {
+    for (tier, amount) in amounts.iter().enumerate() {
+        if let Some(amount) = amount {
+            jackpot_pool_mut(jackpots, tier as u8).amount = *amount;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use anchor_lang::solana_program::message::Message;
+    use anchor_lang::{InstructionData, ToAccountMetas};
+
+    /// Largest serialized transaction a validator accepts (1280-byte
+    /// IPv6 MTU less headers).
+    const PACKET_DATA_SIZE: usize = 1_232;
+
+    /// The compute budget program, whose instructions a cranker adds to
+    /// raise the limit and price of a batch.
+    const COMPUTE_BUDGET_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+    /// Serialized size of a legacy transaction calling settle_spins_batch
+    /// with `entries` distinct entries, behind SetComputeUnitLimit and
+    /// SetComputeUnitPrice, signed by the cranker alone.
+    fn batch_tx_size(entries: usize) -> usize {
+        let cranker = Pubkey::new_unique();
+        let mut accounts = crate::accounts::SettleSpinsBatch {
+            slots_state: Pubkey::new_unique(),
+            cranker,
+            treasury: Pubkey::new_unique(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None);
+        for _ in 0..entries {
+            accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+            accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+            accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+        }
+        let ix = Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: crate::instruction::SettleSpinsBatch {}.data(),
+        };
+        let mut unit_limit = vec![2];
+        unit_limit.extend_from_slice(&1_400_000u32.to_le_bytes());
+        let mut unit_price = vec![3];
+        unit_price.extend_from_slice(&1u64.to_le_bytes());
+        let budget = |data| Instruction::new_with_bytes(COMPUTE_BUDGET_ID, data, vec![]);
+        let message = Message::new(
+            &[budget(&unit_limit), budget(&unit_price), ix],
+            Some(&cranker),
+        );
+        let signatures = message.header.num_required_signatures as usize;
+        // compact-u16 signature count, the signatures, then the message.
+        1 + 64 * signatures + bincode::serialized_size(&message).unwrap() as usize
+    }
+
+    #[test]
+    fn winners_are_counted_per_tier() {
+        let hits = [Some(0), None, Some(2), Some(0), None];
+        assert_eq!(count_winners(&hits), [2, 0, 1]);
+        assert_eq!(count_winners(&[]), [0, 0, 0]);
+    }
+
+    #[test]
+    fn only_counted_winners_draw_a_share() {
+        let mut jackpots = JackpotsConfig::default();
+        jackpots.mini.amount = 1_001;
+        jackpots.grand.amount = 50_000;
+        let plan = SplitPlan::new(&jackpots, vec![Some(0), None, Some(0)]);
+        assert_eq!(plan.share[0], 500);
+        assert_eq!(plan.amounts_for(0), [Some(500), None, None]);
+        assert_eq!(plan.amounts_for(1), [Some(0), None, None]);
+        assert_eq!(plan.amounts_for(2), [Some(500), None, None]);
+    }
+
+    #[test]
+    fn a_full_batch_fits_one_transaction() {
+        let size = batch_tx_size(MAX_BATCH_SETTLE);
+        assert!(size <= PACKET_DATA_SIZE, "{size} bytes");
+    }
+
+    #[test]
+    fn a_crank_skips_players_whose_accounts_it_would_drop() {
+        let mut state = crate::test_util::zeroed_state();
+        state.leaderboard_epoch_slots = 100;
+        let mut player = crate::test_util::zeroed_player();
+        assert!(!needs_accounts(&state, &player, 250));
+
+        player.vip_token_account = Pubkey::new_unique();
+        assert!(!needs_accounts(&state, &player, 250), "VIP off");
+        state.vip_config.rebate_bps = 100;
+        assert!(needs_accounts(&state, &player, 250));
+        player.vip_token_account = Pubkey::default();
+
+        player.leaderboard = Pubkey::new_unique();
+        player.leaderboard_epoch = 2;
+        assert!(needs_accounts(&state, &player, 250));
+        assert!(!needs_accounts(&state, &player, 300), "board of a past epoch");
+        player.leaderboard = Pubkey::default();
+
+        state.settle_hooks[1] = Pubkey::new_unique();
+        assert!(needs_accounts(&state, &player, 250));
+    }
+}
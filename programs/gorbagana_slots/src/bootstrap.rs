@@ -0,0 +1,207 @@
+//! Cold-start limits for young pools.
+//!
+//! While `total_pool` is below the configured maturity threshold the game
+//! runs in bootstrap: the per-spin payout cap becomes a fraction of the
+//! pool instead of the configured `max_payout_per_spin`, and jackpot
+//! contributions are halved. Leaving bootstrap takes the full threshold;
+//! re-entering takes a drop of `hysteresis_bps` below it, so a pool
+//! hovering around the line does not flip every spin.
+
+use anchor_lang::prelude::*;
+
+/// Return data of `get_bootstrap_status`, so the UI can explain reduced
+/// limits.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct BootstrapStatus {
+    pub active: bool,
+    pub total_pool: u64,
+    pub maturity_threshold: u64,
+    /// Payout cap currently in effect.
+    pub effective_max_payout: u64,
+    pub configured_max_payout: u64,
+}
+
+/// Default bootstrap payout cap: 0.5% of the pool.
+pub const DEFAULT_BOOTSTRAP_PAYOUT_BPS: u16 = 50;
+/// Default re-entry band: 10% below the threshold.
+pub const DEFAULT_BOOTSTRAP_HYSTERESIS_BPS: u16 = 1_000;
+
+/// Bootstrap state after observing `total_pool`. A zero threshold disables
+/// bootstrap entirely.
+pub fn next_bootstrap_active(
+    active: bool,
+    total_pool: u64,
+    maturity_threshold: u64,
+    hysteresis_bps: u16,
+) -> bool {
+    if maturity_threshold == 0 {
+        return false;
+    }
+    if active {
+        return total_pool < maturity_threshold;
+    }
+    let reenter_below = ((maturity_threshold as u128)
+        * (10_000u128.saturating_sub(hysteresis_bps as u128))
+        / 10_000) as u64;
+    total_pool < reenter_below
+}
+
+/// Per-spin payout cap in effect: the configured cap, or while
+/// bootstrapping the smaller of it and `payout_bps` of the pool.
+pub fn effective_max_payout(
+    max_payout_per_spin: u64,
+    total_pool: u64,
+    payout_bps: u16,
+    active: bool,
+) -> u64 {
+    if !active {
+        return max_payout_per_spin;
+    }
+    let scaled = ((total_pool as u128) * (payout_bps as u128) / 10_000) as u64;
+    core::cmp::min(max_payout_per_spin, scaled)
+}
+
+/// Jackpot contribution in bps of bet, halved while bootstrapping.
+pub fn effective_contrib_bps(contrib_bps: u16, active: bool) -> u16 {
+    if active {
+        contrib_bps / 2
+    } else {
+        contrib_bps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, Fixture, Game, GAME_POOL};
+    use crate::{game_math, refresh_bootstrap, ErrorCode, SlotsState};
+
+    /// A machine whose configured cap its GAME_POOL cannot cover, with
+    /// bootstrap lasting until the pool reaches `maturity_threshold`.
+    fn young(maturity_threshold: u64) -> Game {
+        let mut game = Game::new();
+        game.state.max_payout_per_spin = 100 * LAMPORTS_PER_SOL;
+        game.state.max_bet = game.state.max_payout_per_spin / game_math::max_3oak_multiplier();
+        game.state.bootstrap_maturity_threshold = maturity_threshold;
+        refresh_bootstrap(&mut game.state);
+        game
+    }
+
+    #[test]
+    fn leaving_takes_the_threshold_and_reentering_the_band_below() {
+        let threshold = 1_000_000;
+        let step = |active, pool| next_bootstrap_active(active, pool, threshold, 1_000);
+
+        assert!(step(true, threshold - 1));
+        assert!(!step(true, threshold));
+        // Hovering just under the line after maturing stays mature...
+        assert!(!step(false, threshold - 1));
+        assert!(!step(false, 900_000));
+        // ...until the pool falls 10% below it.
+        assert!(step(false, 899_999));
+        assert!(!next_bootstrap_active(true, 0, 0, 1_000));
+    }
+
+    #[test]
+    fn the_cap_scales_with_the_pool_only_while_bootstrapping() {
+        assert_eq!(effective_max_payout(1_000, 100_000, 50, true), 500);
+        assert_eq!(effective_max_payout(300, 100_000, 50, true), 300);
+        assert_eq!(effective_max_payout(1_000, 100_000, 50, false), 1_000);
+        assert_eq!(effective_max_payout(u64::MAX, u64::MAX, 10_000, true), u64::MAX);
+        assert_eq!(effective_contrib_bps(101, true), 50);
+        assert_eq!(effective_contrib_bps(101, false), 101);
+    }
+
+    #[test]
+    fn a_young_pool_spins_under_the_reduced_cap() {
+        let bet = LAMPORTS_PER_SOL / 20;
+        let mut mature = young(0);
+        assert_eq!(
+            mature.request(bet).unwrap_err(),
+            ErrorCode::InsufficientPool.into()
+        );
+
+        let mut game = young(100 * LAMPORTS_PER_SOL);
+        assert!(game.state.bootstrap_active);
+        let cap = GAME_POOL * DEFAULT_BOOTSTRAP_PAYOUT_BPS as u64 / 10_000;
+        assert_eq!(crate::effective_max_payout(&game.state), cap);
+
+        let grand = game.state.jackpots.grand.amount;
+        let contrib_bps = game.state.jackpots.grand.contrib_bps as u64;
+        game.request(bet).unwrap();
+        assert_eq!(
+            game.state.jackpots.grand.amount - grand,
+            bet * contrib_bps / 2 / 10_000
+        );
+
+        let payouts: Vec<u64> = (0..=u8::MAX)
+            .map(|byte| {
+                let mut probe = game.clone();
+                probe.reveal([byte; 32]);
+                probe.settle().unwrap().total_payout
+            })
+            .collect();
+        assert_eq!(payouts.iter().max(), Some(&cap));
+    }
+
+    #[test]
+    fn deposits_cross_the_threshold_with_hysteresis() {
+        let threshold = 2 * GAME_POOL;
+        let mut game = young(threshold);
+        assert!(game.state.bootstrap_active);
+
+        game.fund(GAME_POOL - 1);
+        assert!(game.state.bootstrap_active);
+        game.fund(1);
+        assert!(!game.state.bootstrap_active);
+        assert_eq!(crate::effective_max_payout(&game.state), 100 * LAMPORTS_PER_SOL);
+
+        // Paying out a little does not bring bootstrap back...
+        game.state.total_pool -= 2 * LAMPORTS_PER_SOL;
+        refresh_bootstrap(&mut game.state);
+        assert!(!game.state.bootstrap_active);
+        // ...a drop through the 10% band does.
+        game.state.total_pool = threshold / 10 * 9 - 1;
+        refresh_bootstrap(&mut game.state);
+        assert!(game.state.bootstrap_active);
+    }
+
+    #[test]
+    fn only_the_authority_configures_bootstrap() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut authority = game.authority();
+        let mut no_metrics = Fixture::omitted();
+        let set = |payout_bps, hysteresis_bps| {
+            crate::instruction::SetBootstrapConfig {
+                maturity_threshold: 2 * GAME_POOL,
+                payout_bps,
+                hysteresis_bps,
+            }
+            .data()
+        };
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(50, 0)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        for (payout_bps, hysteresis_bps) in [(0, 0), (10_001, 0), (50, 10_000)] {
+            assert_eq!(
+                simulate(
+                    [&mut state, &mut authority, &mut no_metrics],
+                    &set(payout_bps, hysteresis_bps)
+                ),
+                Err(program_error(ErrorCode::InvalidBootstrapConfig))
+            );
+        }
+
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(10_000, 0)).unwrap();
+        let state = state.read::<SlotsState>();
+        assert_eq!(state.bootstrap_maturity_threshold, 2 * GAME_POOL);
+        assert!(state.bootstrap_active);
+    }
+}
@@ -0,0 +1,431 @@
+//! Off-chain helpers for reading program accounts from raw RPC data.
+//! Decoders work on the full account data, discriminator included.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+
+use crate::account_index::{ACCOUNT_INDEX_SLOTS, INDEX_KIND_PLAYER_STATE};
+use crate::epoch_reports::{EpochReport, EpochTotals, EPOCH_REPORTS_PER_PAGE};
+use crate::feature_stats::{self, FeaturePnl};
+use crate::machines::{machine_seed, LEGACY_MACHINE_ID};
+use crate::metrics::*;
+use crate::{
+    SlotsState, ACCOUNT_INDEX_SEED, EPOCH_REPORT_SEED, LEADERBOARD_SEED, PLAYER_SEED,
+    RTP_HISTORY_HOURS, SLOTS_SEED, TREASURY_SEED, WAGER_RACE_SEED,
+};
+
+const DISCRIMINATOR_LEN: usize = 8;
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Option<i64> {
+    data.get(offset..offset + 8)
+        .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// Decode an RtpHistory account into (hour_start_unix, rtp_bps), oldest
+/// first, one entry per hour of the window. Hours with no wagers report
+/// an RTP of 0.
+pub fn decode_rtp_history(data: &[u8]) -> Option<Vec<(i64, u32)>> {
+    // slots_state (32) | head_hour (8) | bump (1) | padding (7) | entries
+    let head_hour = read_i64(data, DISCRIMINATOR_LEN + 32)?;
+    let entries_offset = DISCRIMINATOR_LEN + 32 + 8 + 8;
+    const ENTRY_LEN: usize = 24;
+
+    let mut out = Vec::with_capacity(RTP_HISTORY_HOURS);
+    for age in (0..RTP_HISTORY_HOURS as i64).rev() {
+        let hour = head_hour - age;
+        if hour < 0 {
+            continue;
+        }
+        let at = entries_offset + (hour as usize % RTP_HISTORY_HOURS) * ENTRY_LEN;
+        let wagered = read_u64(data, at)?;
+        let paid = read_u64(data, at + 8)?;
+        let rtp_bps = if wagered == 0 {
+            0
+        } else {
+            ((paid as u128) * 10_000 / wagered as u128) as u32
+        };
+        out.push((hour * 3_600, rtp_bps));
+    }
+    Some(out)
+}
+
+/// Gauges read from a Metrics account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub updated_slot: u64,
+    pub total_pool: u64,
+    pub spendable: u64,
+    pub pending_spins: u64,
+    pub total_spins: u64,
+    pub jackpot_amounts: [u64; 3],
+    pub fee_balance: u64,
+    pub current_rtp_bps: u32,
+    pub pause_flags: u8,
+    pub bootstrap_active: bool,
+    pub beta_mode: bool,
+}
+
+/// Decode a Metrics account (or a data slice of at least METRICS_LEN
+/// bytes from offset 0).
+pub fn decode_metrics(data: &[u8]) -> Option<MetricsSnapshot> {
+    if data.len() < METRICS_LEN {
+        return None;
+    }
+    Some(MetricsSnapshot {
+        updated_slot: read_u64(data, OFFSET_UPDATED_SLOT)?,
+        total_pool: read_u64(data, OFFSET_TOTAL_POOL)?,
+        spendable: read_u64(data, OFFSET_SPENDABLE)?,
+        pending_spins: read_u64(data, OFFSET_PENDING_SPINS)?,
+        total_spins: read_u64(data, OFFSET_TOTAL_SPINS)?,
+        jackpot_amounts: [
+            read_u64(data, OFFSET_JACKPOT_AMOUNTS)?,
+            read_u64(data, OFFSET_JACKPOT_AMOUNTS + 8)?,
+            read_u64(data, OFFSET_JACKPOT_AMOUNTS + 16)?,
+        ],
+        fee_balance: read_u64(data, OFFSET_FEE_BALANCE)?,
+        current_rtp_bps: read_u32(data, OFFSET_CURRENT_RTP_BPS)?,
+        pause_flags: data[OFFSET_PAUSE_FLAGS],
+        bootstrap_active: data[OFFSET_BOOTSTRAP_ACTIVE] != 0,
+        beta_mode: data[OFFSET_BETA_MODE] != 0,
+    })
+}
+
+/// Per-feature house P&L from a SlotsState account, as get_feature_pnl
+/// would return it.
+pub fn decode_feature_pnl(slots_state_data: &[u8]) -> Option<FeaturePnl> {
+    let state = SlotsState::try_deserialize(&mut &slots_state_data[..]).ok()?;
+    Some(feature_stats::pnl(&state))
+}
+
+/// Address of a player's PlayerState on the legacy machine.
+pub fn player_state_address(user: &Pubkey) -> Pubkey {
+    machine_player_state_address(&LEGACY_MACHINE_ID, user)
+}
+
+/// Address of a player's PlayerState on machine `machine_id`.
+pub fn machine_player_state_address(machine_id: &[u8; 32], user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PLAYER_SEED, machine_seed(machine_id), user.as_ref()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Address of machine `machine_id`'s SlotsState; the legacy machine's is
+/// the `[SLOTS_SEED]` PDA initialize created.
+pub fn machine_address(machine_id: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[SLOTS_SEED, machine_seed(machine_id)], &crate::ID).0
+}
+
+/// Address of machine `machine_id`'s treasury.
+pub fn treasury_address(machine_id: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[TREASURY_SEED, machine_seed(machine_id)], &crate::ID).0
+}
+
+/// Instruction to commit a wallet's next spin with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpinEntry {
+    /// No PlayerState yet: onboard_and_spin creates it in the same
+    /// instruction. This is the default first-spin call.
+    OnboardAndSpin,
+    RequestSpin,
+}
+
+/// Pick the spin instruction from the PlayerState fetch at
+/// [`player_state_address`] (`None` when the account does not exist).
+pub fn spin_entry(player_state_data: Option<&[u8]>) -> SpinEntry {
+    match player_state_data {
+        Some(data) if data.len() > DISCRIMINATOR_LEN => SpinEntry::RequestSpin,
+        _ => SpinEntry::OnboardAndSpin,
+    }
+}
+
+/// Address of a player's AccountIndex.
+pub fn account_index_address(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ACCOUNT_INDEX_SEED, user.as_ref()], &crate::ID).0
+}
+
+/// A per-player account recorded in an AccountIndex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscoveredAccount {
+    pub kind: u8,
+    pub address: Pubkey,
+}
+
+/// Every per-player account of `user`, from the data of their AccountIndex
+/// (fetch [`account_index_address`]). Addresses are derived from the stored
+/// bumps, so no further RPC calls are needed. The PlayerState found is the
+/// legacy machine's.
+pub fn discover(user: &Pubkey, index_data: &[u8]) -> Option<Vec<DiscoveredAccount>> {
+    // owner (32) | present (4) | bumps (ACCOUNT_INDEX_SLOTS) | bump (1)
+    let owner = index_data.get(DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + 32)?;
+    if owner != user.as_ref() {
+        return None;
+    }
+    let present = read_u32(index_data, DISCRIMINATOR_LEN + 32)?;
+    let bumps_at = DISCRIMINATOR_LEN + 32 + 4;
+    let bumps = index_data.get(bumps_at..bumps_at + ACCOUNT_INDEX_SLOTS)?;
+
+    let mut out = Vec::new();
+    for kind in 0..ACCOUNT_INDEX_SLOTS as u8 {
+        if present & (1 << kind) == 0 {
+            continue;
+        }
+        let bump = [bumps[kind as usize]];
+        let seed: &[u8] = match kind {
+            INDEX_KIND_PLAYER_STATE => PLAYER_SEED,
+            _ => continue,
+        };
+        let address =
+            Pubkey::create_program_address(&[seed, user.as_ref(), &bump], &crate::ID).ok()?;
+        out.push(DiscoveredAccount { kind, address });
+    }
+    Some(out)
+}
+
+/// Address of epoch report page `page` of `slots_state`.
+pub fn epoch_report_page_address(slots_state: &Pubkey, page: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[EPOCH_REPORT_SEED, slots_state.as_ref(), &page.to_le_bytes()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Address of wager race `race_id` of `slots_state`.
+pub fn wager_race_address(slots_state: &Pubkey, race_id: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[WAGER_RACE_SEED, slots_state.as_ref(), &race_id.to_le_bytes()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Address of the leaderboard of `epoch` of `slots_state`.
+pub fn leaderboard_address(slots_state: &Pubkey, epoch: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[LEADERBOARD_SEED, slots_state.as_ref(), &epoch.to_le_bytes()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Closed epoch reports of an EpochReportPage, oldest first. Concatenate
+/// pages 0, 1, ... for the full history.
+pub fn decode_epoch_reports(data: &[u8]) -> Option<Vec<EpochReport>> {
+    // slots_state (32) | page (4) | epoch_length_secs (4) | open_epoch (8)
+    // | open_base (EpochTotals) | count (1) | reports | bump (1)
+    let count_at = DISCRIMINATOR_LEN + 32 + 4 + 4 + 8 + EpochTotals::LEN;
+    let count = (*data.get(count_at)? as usize).min(EPOCH_REPORTS_PER_PAGE);
+    const REPORT_LEN: usize = 8 + EpochTotals::LEN;
+
+    let mut out = Vec::with_capacity(count);
+    for i in 0..count {
+        let at = count_at + 1 + i * REPORT_LEN;
+        let field = |n: usize| read_u64(data, at + 8 + n * 8);
+        out.push(EpochReport {
+            epoch: read_i64(data, at)?,
+            totals: EpochTotals {
+                spins: field(0)?,
+                wagered: field(1)?,
+                paid: field(2)?,
+                fee_rebates: field(3)?,
+                oracle_charges: field(4)?,
+                jackpot_hits: field(5)?,
+                jackpot_paid: field(6)?,
+                deposits: field(7)?,
+                withdrawals: field(8)?,
+            },
+        });
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anchor_lang::InstructionData;
+    use bytemuck::Zeroable;
+
+    use super::*;
+    use crate::test_util::{pda, program_error, simulate, Fixture, Game};
+    use crate::{
+        ErrorCode, Metrics, PlayerState, RtpHistory, DEFAULT_MIN_BET, METRICS_SEED,
+        PAUSE_ALL, RTP_HISTORY_SEED,
+    };
+
+    const HOUR: i64 = 3_600;
+
+    #[test]
+    fn a_week_with_gaps_decodes_hour_by_hour() {
+        let mut history = RtpHistory::zeroed();
+        let start = 1_000 * HOUR;
+        history.head_hour = start / HOUR;
+        let mut expected: HashMap<i64, (u64, u64)> = HashMap::new();
+
+        // Ten days, to wrap the ring: busy hours, a 30-hour outage, a
+        // silent day and a half, and a timestamp from an hour already
+        // closed, which lands in the newest bucket.
+        let mut now = start;
+        let mut head = start / HOUR;
+        for step in 0..240i64 {
+            now += HOUR;
+            if (40..70).contains(&step) || (150..186).contains(&step) {
+                continue;
+            }
+            for spin in 0..=(step % 3) {
+                let (wagered, paid) = (1_000 + spin as u64, (step % 5) as u64 * 400);
+                let at = if step == 100 && spin == 1 { now - 2 * HOUR } else { now + spin * 60 };
+                history.record(at, wagered, paid);
+                head = head.max(at / HOUR);
+                let entry = expected.entry(head).or_default();
+                entry.0 += wagered;
+                entry.1 += paid;
+            }
+        }
+
+        let data = Fixture::zero_copy(Pubkey::new_unique(), &history).data;
+        let decoded = decode_rtp_history(&data).unwrap();
+        assert_eq!(decoded.len(), RTP_HISTORY_HOURS);
+        assert_eq!(decoded.last().unwrap().0, head * HOUR);
+        for (i, (hour_start, rtp_bps)) in decoded.iter().enumerate() {
+            assert_eq!(*hour_start, (head - (RTP_HISTORY_HOURS - 1 - i) as i64) * HOUR);
+            let (wagered, paid) = expected.get(&(hour_start / HOUR)).copied().unwrap_or_default();
+            let rtp = (paid * 10_000).checked_div(wagered).unwrap_or(0);
+            assert_eq!(*rtp_bps as u64, rtp, "hour {}", hour_start / HOUR);
+        }
+        // The outage decodes as silent hours.
+        assert!(decoded.iter().filter(|(_, rtp)| *rtp == 0).count() >= 36);
+
+        // A truncated account does not decode.
+        assert_eq!(decode_rtp_history(&data[..data.len() / 2]), None);
+    }
+
+    #[test]
+    fn settle_writes_the_created_history() {
+        let mut game = Game::new();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        let win = game.reveal_where(|settlement| settlement.total_payout > 0);
+        game.reveal(win);
+        let mut accounts = game.settle_spin_fixtures();
+
+        let (key, _) = pda(&[RTP_HISTORY_SEED, accounts[0].key.as_ref()]);
+        let mut history = Fixture::uninit(key, RtpHistory::LEN);
+        let mut payer = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let init = crate::instruction::InitRtpHistory {}.data();
+        simulate([&mut accounts[0], &mut history, &mut payer, &mut system], &init).unwrap();
+        let empty = decode_rtp_history(&history.data).unwrap();
+        assert!(empty.iter().all(|(_, rtp)| *rtp == 0));
+
+        accounts[6] = history;
+        simulate(&mut accounts, &crate::instruction::SettleSpin {}.data()).unwrap();
+        let player = accounts[1].read::<PlayerState>();
+        assert!(!player.has_pending_spin);
+        let decoded = decode_rtp_history(&accounts[6].data).unwrap();
+        let (hour_start, rtp_bps) = *decoded.last().unwrap();
+        assert_eq!(hour_start, game.slot as i64 / HOUR * HOUR);
+        assert!(player.total_won > 0);
+        assert_eq!(rtp_bps as u64, player.total_won * 10_000 / DEFAULT_MIN_BET);
+    }
+
+    #[test]
+    fn metrics_decode_at_the_documented_offsets() {
+        let metrics = Metrics {
+            slots_state: Pubkey::new_unique(),
+            updated_slot: 0x0101,
+            total_pool: 0x0202,
+            spendable: 0x0303,
+            pending_spins: 0x0404,
+            total_spins: 0x0505,
+            jackpot_amounts: [0x0606, 0x0707, 0x0808],
+            fee_balance: 0x0909,
+            current_rtp_bps: 0x0a0a,
+            pause_flags: 0x0b,
+            bootstrap_active: 1,
+            beta_mode: 1,
+            bump: 0x0e,
+        };
+        let data = Fixture::zero_copy(Pubkey::new_unique(), &metrics).data;
+        assert_eq!(data.len(), METRICS_LEN);
+        assert_eq!(&data[OFFSET_SLOTS_STATE..][..32], metrics.slots_state.as_ref());
+        assert_eq!(data[OFFSET_BUMP], 0x0e);
+        assert_eq!(
+            decode_metrics(&data),
+            Some(MetricsSnapshot {
+                updated_slot: 0x0101,
+                total_pool: 0x0202,
+                spendable: 0x0303,
+                pending_spins: 0x0404,
+                total_spins: 0x0505,
+                jackpot_amounts: [0x0606, 0x0707, 0x0808],
+                fee_balance: 0x0909,
+                current_rtp_bps: 0x0a0a,
+                pause_flags: 0x0b,
+                bootstrap_active: true,
+                beta_mode: true,
+            })
+        );
+        // A scraper's data slice must reach the last field.
+        assert_eq!(decode_metrics(&data[..METRICS_LEN - 1]), None);
+    }
+
+    #[test]
+    fn metrics_follow_settle_and_pause() {
+        let mut game = Game::new();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        let reveal = game.reveal_where(|_| true);
+        game.reveal(reveal);
+        let mut accounts = game.settle_spin_fixtures();
+
+        let (key, _) = pda(&[METRICS_SEED, accounts[0].key.as_ref()]);
+        let mut metrics = Fixture::uninit(key, Metrics::LEN);
+        let mut payer = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let init = crate::instruction::InitMetrics {}.data();
+        simulate([&mut accounts[0], &mut metrics, &mut payer, &mut system], &init).unwrap();
+        let created = decode_metrics(&metrics.data).unwrap();
+        assert_eq!(created.updated_slot, game.slot);
+        assert_eq!(created.total_pool, game.state.total_pool);
+        assert_eq!(created.pending_spins, 1);
+
+        // Metrics of another machine are refused.
+        let mut other = Game::new().fixtures()[0].clone();
+        let refresh = crate::instruction::RefreshMetrics {}.data();
+        let seeds = anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintSeeds);
+        assert_eq!(simulate([&mut other, &mut metrics], &refresh), Err(seeds.into()));
+
+        accounts[7] = metrics;
+        simulate(&mut accounts, &crate::instruction::SettleSpin {}.data()).unwrap();
+        let state = accounts[0].read::<crate::SlotsState>();
+        let settled = decode_metrics(&accounts[7].data).unwrap();
+        assert_eq!(settled.pending_spins, 0);
+        assert_eq!(settled.total_spins, state.total_spins);
+        assert_eq!(settled.total_pool, state.total_pool);
+        assert_eq!(settled.fee_balance, state.fee_balance);
+
+        // Only the authority's pause reaches the gauges.
+        let pause = crate::instruction::EmergencyPause {}.data();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let [state, .., metrics] = &mut accounts[..8] else { unreachable!() };
+        assert_eq!(
+            simulate([&mut *state, &mut stranger, &mut *metrics], &pause),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(decode_metrics(&metrics.data).unwrap().pause_flags, 0);
+        let mut authority = game.authority();
+        simulate([&mut *state, &mut authority, &mut *metrics], &pause).unwrap();
+        assert_eq!(decode_metrics(&metrics.data).unwrap().pause_flags, PAUSE_ALL);
+    }
+}
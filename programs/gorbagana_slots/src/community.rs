@@ -0,0 +1,94 @@
+//! Community revenue share.
+//!
+//! `community_bps` of each spin's house take (bet minus payout, when
+//! positive) leaves `total_pool` for `community_balance` at settle, and
+//! losing deferred spins are routed the same way when flush_stats folds
+//! them in. A spin the house loses accrues nothing; its would-be share of
+//! the loss goes to `community_deficit` and is netted against later
+//! accruals, so the community shares losses as well as wins.
+//!
+//! What accrues leaves the pool for good, so it is charged to the
+//! extraction budget (extraction.rs) like any other outflow. A share the
+//! budget cannot cover is cut down to what it can, and the rest stays in
+//! the pool: a settle never fails on the budget.
+//!
+//! `community_balance` sits in the treasury until the permissionless
+//! `sweep_community` moves it to the [`CommunityVault`] PDA. Only the
+//! community authority set at initialize can withdraw from the vault, and
+//! only to a destination it has listed in the vault. The sweep stays
+//! inside the program, so it is not booked as a treasury outflow.
+
+use anchor_lang::prelude::*;
+
+use crate::{extraction, CommunityVault, ErrorCode, SlotsState};
+
+/// Destination slots in the vault's withdrawal allowlist.
+pub const MAX_COMMUNITY_DESTINATIONS: usize = 4;
+
+impl CommunityVault {
+    pub const LEN: usize = 8 + 32 + 32 * MAX_COMMUNITY_DESTINATIONS + 8 + 1;
+
+    pub fn allows(&self, destination: &Pubkey) -> bool {
+        *destination != Pubkey::default() && self.destinations.contains(destination)
+    }
+}
+
+/// Community share of one settled amount and the deficit afterwards.
+/// `bet` and `payout` may cover several spins at once.
+pub fn route(bps: u16, deficit: u64, bet: u64, payout: u64) -> (u64, u64) {
+    let share_of = |amount: u64| ((amount as u128) * (bps as u128) / 10_000) as u64;
+    if payout > bet {
+        return (0, deficit.saturating_add(share_of(payout - bet)));
+    }
+    let gross = share_of(bet - payout);
+    let netted = gross.min(deficit);
+    (gross - netted, deficit - netted)
+}
+
+/// Move the community share of a settle out of the pool, within the
+/// extraction budget. Returns the amount accrued.
+pub fn accrue(state: &mut SlotsState, bet: u64, payout: u64, now_slot: u64) -> Result<u64> {
+    if state.community_bps == 0 {
+        return Ok(0);
+    }
+    let (share, deficit) = route(state.community_bps, state.community_deficit, bet, payout);
+    state.community_deficit = deficit;
+    if share == 0 {
+        return Ok(0);
+    }
+    let share = extraction::charge_within_budget(state, share, now_slot);
+    state.total_pool = state
+        .total_pool
+        .checked_sub(share)
+        .ok_or(ErrorCode::MathOverflow)?;
+    state.community_balance = state
+        .community_balance
+        .checked_add(share)
+        .ok_or(ErrorCode::MathOverflow)?;
+    state.community_accrued = state
+        .community_accrued
+        .checked_add(share)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_house_loss_is_netted_against_later_shares() {
+        // 20% of a 1_000 take.
+        assert_eq!(route(2_000, 0, 1_000, 0), (200, 0));
+        // A 500 loss: no accrual, 100 owed back.
+        assert_eq!(route(2_000, 0, 1_000, 1_500), (0, 100));
+        assert_eq!(route(2_000, 100, 1_000, 750), (0, 50));
+        assert_eq!(route(2_000, 50, 1_000, 0), (150, 0));
+        // Breaking even changes nothing.
+        assert_eq!(route(2_000, 50, 1_000, 1_000), (0, 50));
+        assert_eq!(route(0, 50, 1_000, 0), (0, 50));
+        // Neither side overflows.
+        assert_eq!(route(10_000, u64::MAX, 0, u64::MAX), (0, u64::MAX));
+        assert_eq!(route(10_000, 0, u64::MAX, 0), (u64::MAX, 0));
+    }
+}
@@ -0,0 +1,253 @@
+//! Authority-updatable game configuration.
+//!
+//! update_config takes an [`UpdateConfigArgs`] and applies only the fields
+//! that are `Some`, then validates the result as a whole:
+//!
+//! - jackpot `contrib_bps` must sum to less than 10_000;
+//! - while any jackpot has a seed, the tier hit weights must not all be
+//!   zero;
+//! - hit weights pass the jackpot_bounds ceilings, valued at the new
+//!   seeds. Tables outside them still go through propose_jackpot_weights;
+//! - a must-hit-by ceiling may not be below its tier's seed;
+//! - max_payout_per_spin must still cover the top paytable win on max_bet;
+//! - min_pool_threshold may only be lowered to a value the pool already
+//!   covers;
+//! - the theoretical RTP, jackpot contributions included, must stay
+//!   within `10_000 - house_edge_bps` (see paytable.rs).
+//!
+//! The ConfigUpdated event carries a [`ConfigSnapshot`] from before and
+//! after the change.
+
+use anchor_lang::prelude::*;
+
+use crate::jackpot_bounds::{self, JackpotWeights};
+use crate::{must_hit, paytable, tiers, ErrorCode, SlotsState};
+
+/// Tunables for update_config; `None` leaves a value unchanged. Per-tier
+/// arrays are ordered mini, major, grand.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateConfigArgs {
+    pub max_payout_per_spin: Option<u64>,
+    pub min_pool_threshold: Option<u64>,
+    pub house_edge_bps: Option<u16>,
+    pub contrib_bps: Option<[u16; 3]>,
+    pub hit_weights: Option<JackpotWeights>,
+    pub seeds: Option<[u64; 3]>,
+    /// Must-hit-by ceilings (0 = none); see must_hit.rs.
+    pub must_hit_by: Option<[u64; 3]>,
+}
+
+/// The values update_config can change, as reported in ConfigUpdated.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConfigSnapshot {
+    pub max_payout_per_spin: u64,
+    pub min_pool_threshold: u64,
+    pub house_edge_bps: u16,
+    pub contrib_bps: [u16; 3],
+    pub hit_weights: [u32; 3],
+    pub hit_weight_total: u32,
+    pub seeds: [u64; 3],
+    pub must_hit_by: [u64; 3],
+}
+
+pub fn snapshot(state: &SlotsState) -> ConfigSnapshot {
+    let j = &state.jackpots;
+    ConfigSnapshot {
+        max_payout_per_spin: state.max_payout_per_spin,
+        min_pool_threshold: state.min_pool_threshold,
+        house_edge_bps: state.house_edge_bps,
+        contrib_bps: [j.mini.contrib_bps, j.major.contrib_bps, j.grand.contrib_bps],
+        hit_weights: [j.mini.hit_weight, j.major.hit_weight, j.grand.hit_weight],
+        hit_weight_total: j.hit_weight_total,
+        seeds: [j.mini.seed, j.major.seed, j.grand.seed],
+        must_hit_by: state.jackpot_must_hit_by,
+    }
+}
+
+/// Apply the provided fields to `state` and validate the result. The
+/// caller's transaction rolls back on error.
+pub fn apply(state: &mut SlotsState, args: &UpdateConfigArgs) -> Result<()> {
+    if let Some(max_payout) = args.max_payout_per_spin {
+        require!(max_payout > 0, ErrorCode::InvalidConfig);
+        state.max_payout_per_spin = max_payout;
+        require!(
+            tiers::max_bet_covered(state, state.max_bet),
+            ErrorCode::BetLimitsExceedPayoutCap
+        );
+    }
+    if let Some(threshold) = args.min_pool_threshold {
+        require!(
+            threshold >= state.min_pool_threshold || state.total_pool >= threshold,
+            ErrorCode::InsufficientPool
+        );
+        state.min_pool_threshold = threshold;
+    }
+    if let Some(edge) = args.house_edge_bps {
+        require!(edge <= 10_000, ErrorCode::InvalidConfig);
+        state.house_edge_bps = edge;
+    }
+    if let Some(contrib) = args.contrib_bps {
+        let total: u32 = contrib.iter().map(|&b| b as u32).sum();
+        require!(total < 10_000, ErrorCode::InvalidConfig);
+        state.jackpots.mini.contrib_bps = contrib[0];
+        state.jackpots.major.contrib_bps = contrib[1];
+        state.jackpots.grand.contrib_bps = contrib[2];
+    }
+    if let Some(seeds) = args.seeds {
+        state.jackpots.mini.seed = seeds[0];
+        state.jackpots.major.seed = seeds[1];
+        state.jackpots.grand.seed = seeds[2];
+    }
+    if let Some(ceilings) = args.must_hit_by {
+        state.jackpot_must_hit_by = ceilings;
+    }
+    if let Some(weights) = args.hit_weights {
+        jackpot_bounds::check(state, &weights)?;
+        jackpot_bounds::apply(&mut state.jackpots, &weights)?;
+    }
+
+    let j = &state.jackpots;
+    let seeded = j.mini.seed > 0 || j.major.seed > 0 || j.grand.seed > 0;
+    let weighted = j.mini.hit_weight > 0 || j.major.hit_weight > 0 || j.grand.hit_weight > 0;
+    require!(!seeded || weighted, ErrorCode::InvalidJackpotWeights);
+    must_hit::validate(state)?;
+    must_hit::refresh(state);
+    paytable::refresh_theoretical_rtp(state)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{emitted, program_error, simulate, Fixture, Game};
+    use crate::{instruction, ConfigUpdated};
+
+    /// Rare tier hits well inside the jackpot_bounds ceilings.
+    const WEIGHTS: JackpotWeights = JackpotWeights {
+        mini: 100,
+        major: 10,
+        grand: 1,
+        no_hit: 1_000_000 - 111,
+    };
+
+    #[test]
+    fn only_the_provided_fields_change() {
+        let mut state = Game::new().state;
+        let before = snapshot(&state);
+
+        apply(&mut state, &UpdateConfigArgs::default()).unwrap();
+        assert_eq!(snapshot(&state), before);
+
+        let args = UpdateConfigArgs {
+            max_payout_per_spin: Some(2 * before.max_payout_per_spin),
+            hit_weights: Some(WEIGHTS),
+            ..UpdateConfigArgs::default()
+        };
+        apply(&mut state, &args).unwrap();
+        let after = snapshot(&state);
+        assert_eq!(
+            after,
+            ConfigSnapshot {
+                max_payout_per_spin: 2 * before.max_payout_per_spin,
+                hit_weights: WEIGHTS.tiers(),
+                hit_weight_total: 1_000_000,
+                ..before
+            }
+        );
+    }
+
+    #[test]
+    fn each_tunable_is_validated() {
+        let state = Game::new().state;
+        let refused = |args: UpdateConfigArgs| apply(&mut state.clone(), &args).unwrap_err();
+        let args = UpdateConfigArgs::default;
+
+        assert_eq!(
+            refused(UpdateConfigArgs { max_payout_per_spin: Some(0), ..args() }),
+            ErrorCode::InvalidConfig.into()
+        );
+        let uncovered = state.max_bet * state.paytable.max_multiplier() - 1;
+        assert_eq!(
+            refused(UpdateConfigArgs { max_payout_per_spin: Some(uncovered), ..args() }),
+            ErrorCode::BetLimitsExceedPayoutCap.into()
+        );
+        assert_eq!(
+            refused(UpdateConfigArgs { house_edge_bps: Some(10_001), ..args() }),
+            ErrorCode::InvalidConfig.into()
+        );
+        assert_eq!(
+            refused(UpdateConfigArgs { contrib_bps: Some([5_000, 4_000, 1_000]), ..args() }),
+            ErrorCode::InvalidConfig.into()
+        );
+        // Contributions under 100% can still push the RTP over its target.
+        assert_eq!(
+            refused(UpdateConfigArgs { contrib_bps: Some([3_000, 3_000, 3_000]), ..args() }),
+            ErrorCode::RtpExceedsTarget.into()
+        );
+        let no_tier = JackpotWeights {
+            no_hit: 1,
+            ..JackpotWeights::default()
+        };
+        assert_eq!(
+            refused(UpdateConfigArgs { hit_weights: Some(no_tier), ..args() }),
+            ErrorCode::InvalidJackpotWeights.into()
+        );
+        let hot = JackpotWeights { grand: 20_000, ..WEIGHTS };
+        assert_eq!(
+            refused(UpdateConfigArgs { hit_weights: Some(hot), ..args() }),
+            ErrorCode::JackpotHitProbabilityTooHigh.into()
+        );
+        let below_seed = [0, 0, state.jackpots.grand.seed - 1];
+        assert_eq!(
+            refused(UpdateConfigArgs { must_hit_by: Some(below_seed), ..args() }),
+            ErrorCode::MustHitBelowSeed.into()
+        );
+    }
+
+    #[test]
+    fn the_pool_threshold_drops_only_to_what_the_pool_covers() {
+        let mut state = Game::new().state;
+        let pool = state.total_pool;
+        state.min_pool_threshold = pool + 2_000;
+        let threshold = |value| UpdateConfigArgs {
+            min_pool_threshold: Some(value),
+            ..UpdateConfigArgs::default()
+        };
+
+        assert_eq!(
+            apply(&mut state, &threshold(pool + 1_000)).unwrap_err(),
+            ErrorCode::InsufficientPool.into()
+        );
+        apply(&mut state, &threshold(pool + 3_000)).unwrap();
+        apply(&mut state, &threshold(pool)).unwrap();
+        assert_eq!(state.min_pool_threshold, pool);
+    }
+
+    #[test]
+    fn only_the_authority_updates_and_the_event_carries_both_sides() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let args = UpdateConfigArgs {
+            house_edge_bps: Some(game.state.house_edge_bps + 1),
+            ..UpdateConfigArgs::default()
+        };
+        let update = instruction::UpdateConfig { args }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &update),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        emitted::<ConfigUpdated>();
+        simulate([&mut state, &mut authority, &mut no_metrics], &update).unwrap();
+        let event = emitted::<ConfigUpdated>().pop().unwrap();
+        assert_eq!(event.old, snapshot(&game.state));
+        assert_eq!(event.new, snapshot(&state.read::<SlotsState>()));
+        assert_eq!(event.new.house_edge_bps, game.state.house_edge_bps + 1);
+        assert_eq!(event.authority, authority.key);
+    }
+}
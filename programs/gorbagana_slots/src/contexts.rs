@@ -0,0 +1,1765 @@
+//! Account contexts for the instructions in the program module, in the
+//! same order.
+
+use anchor_lang::solana_program::sysvar::instructions as ix_sysvar;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::machines::machine_seed;
+use crate::program::GorbaganaSlotsVrf;
+use crate::signed_bet::SignedBet;
+use crate::*;
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    /// The legacy machine's state, at `[SLOTS_SEED]`: `init` makes this
+    /// instruction succeed once, so no second state can share the legacy
+    /// treasury, fee vault and PlayerStates.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [SLOTS_SEED],
+        bump,
+        space = SlotsState::LEN,
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    /// PDA treasury vault for SOL (program-signable via seeds). System-owned
+    /// with no data: initialize funds its rent rather than `init`ing it.
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// PDA the operator fee is paid out of; see operator_fee.rs.
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED],
+        bump,
+    )]
+    pub fee_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// This program, to locate its ProgramData account.
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, GorbaganaSlotsVrf>,
+    /// Upgradeable-loader ProgramData holding the upgrade authority.
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(machine_id: [u8; 32])]
+pub struct InitializeMachine<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [SLOTS_SEED, machine_id.as_ref()],
+        bump,
+        space = SlotsState::LEN,
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_id.as_ref()],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED, machine_id.as_ref()],
+        bump,
+    )]
+    pub fee_vault: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, GorbaganaSlotsVrf>,
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPlayer<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump,
+        space = PlayerState::LEN,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// CHECK: the player's AccountIndex PDA; updated if it exists
+    #[account(
+        mut,
+        seeds = [ACCOUNT_INDEX_SEED, user.key().as_ref()],
+        bump,
+    )]
+    pub account_index: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Treasury PDA vault – must be same as in slots_state.
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPlayerSponsored<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = sponsor,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump,
+        space = PlayerState::LEN,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// CHECK: the player's AccountIndex PDA; updated if it exists
+    #[account(
+        mut,
+        seeds = [ACCOUNT_INDEX_SEED, user.key().as_ref()],
+        bump,
+    )]
+    pub account_index: UncheckedAccount<'info>,
+
+    /// Must co-sign so every sponsored init costs a real key.
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = sponsor.key() == slots_state.onboarding_sponsor @ ErrorCode::Unauthorized
+    )]
+    pub sponsor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OnboardAndSpin<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump,
+        space = PlayerState::LEN,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// CHECK: the player's AccountIndex PDA; updated if it exists
+    #[account(
+        mut,
+        seeds = [ACCOUNT_INDEX_SEED, user.key().as_ref()],
+        bump,
+    )]
+    pub account_index: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: Switchboard randomness account
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Allowlist page holding the player; only needed in beta mode.
+    #[account(has_one = slots_state)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// CHECK: queue of the randomness account; only needed while the
+    /// oracle queue whitelist is set. Checked in oracle_queue.rs.
+    pub oracle_queue: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestSpin<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: Switchboard randomness account
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Allowlist page holding the player; only needed in beta mode.
+    #[account(has_one = slots_state)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// CHECK: queue of the randomness account; only needed while the
+    /// oracle queue whitelist is set. Checked in oracle_queue.rs.
+    pub oracle_queue: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bet: SignedBet)]
+pub struct RequestSpinWithSignature<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), bet.player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// Submits and pays for the transaction; receives the relayer fee.
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: Switchboard randomness account, checked against the signed bet
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Allowlist page holding the player; only needed in beta mode.
+    #[account(has_one = slots_state)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// CHECK: queue of the randomness account; only needed while the
+    /// oracle queue whitelist is set. Checked in oracle_queue.rs.
+    pub oracle_queue: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: instructions sysvar, address-checked
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSession<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleSpin<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// CHECK: the spin's player; need not sign. Pinned by the player_state
+    /// seeds and owner check, so the payout cannot be redirected.
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    /// Submits the settle: the player, or a crank paid `crank_fee`.
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: same randomness account used in request_spin
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Optional hourly RTP history; settle skips it when absent.
+    #[account(
+        mut,
+        seeds = [RTP_HISTORY_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub rtp_history: Option<AccountLoader<'info, RtpHistory>>,
+
+    /// Optional scraper metrics; refreshed when present.
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: Option<AccountLoader<'info, Metrics>>,
+
+    /// Optional compressed receipt tree; settle appends when present.
+    #[account(
+        mut,
+        seeds = [RECEIPT_TREE_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// Optional current epoch report page; a settle in a new epoch
+    /// closes the previous one into it.
+    #[account(
+        mut,
+        seeds = [
+            EPOCH_REPORT_SEED,
+            slots_state.key().as_ref(),
+            &slots_state.epoch_report_page.to_le_bytes()
+        ],
+        bump = epoch_report_page.bump,
+    )]
+    pub epoch_report_page: Option<Account<'info, EpochReportPage>>,
+
+    /// Optional wager race the settled bet counts towards.
+    #[account(mut, has_one = slots_state)]
+    pub wager_race: Option<Account<'info, WagerRace>>,
+
+    /// Earnings PDA of the player's referrer; required when they have one.
+    #[account(
+        mut,
+        seeds = [REFERRAL_SEED, slots_state.key().as_ref(), player_state.referrer.as_ref()],
+        bump = referral_earnings.bump,
+    )]
+    pub referral_earnings: Option<Account<'info, ReferralEarnings>>,
+
+    /// Optional leaderboard of the current epoch; skipped when absent,
+    /// except that a crank must pass the one player_state records.
+    #[account(mut, has_one = slots_state)]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Optional VIP token account of the player; a crank must pass the
+    /// one player_state records. See vip.rs.
+    #[account(
+        constraint = vip_token_account.mint == slots_state.vip_config.mint
+            @ ErrorCode::VipTokenAccountMismatch,
+        constraint = vip_token_account.owner == user.key() @ ErrorCode::VipTokenAccountMismatch,
+    )]
+    pub vip_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Entries come as remaining-account triples; see batch_settle.rs.
+#[derive(Accounts)]
+pub struct SettleSpinsBatch<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    /// Submits the batch; paid `crank_fee` per entry it does not own.
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAndRespin<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: same randomness account used in request_spin
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// CHECK: Switchboard randomness account for the re-bet, checked like
+    /// in request_spin; must not be randomness_account_data
+    #[account(mut)]
+    pub new_randomness_account_data: AccountInfo<'info>,
+
+    /// Allowlist page holding the player; only needed in beta mode.
+    #[account(has_one = slots_state)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// CHECK: queue of the randomness account; only needed while the
+    /// oracle queue whitelist is set. Checked in oracle_queue.rs.
+    pub oracle_queue: Option<UncheckedAccount<'info>>,
+
+    /// Optional hourly RTP history; settle skips it when absent.
+    #[account(
+        mut,
+        seeds = [RTP_HISTORY_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub rtp_history: Option<AccountLoader<'info, RtpHistory>>,
+
+    /// Optional scraper metrics; refreshed when present.
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: Option<AccountLoader<'info, Metrics>>,
+
+    /// Optional compressed receipt tree; settle appends when present.
+    #[account(
+        mut,
+        seeds = [RECEIPT_TREE_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// Optional current epoch report page; a settle in a new epoch
+    /// closes the previous one into it.
+    #[account(
+        mut,
+        seeds = [
+            EPOCH_REPORT_SEED,
+            slots_state.key().as_ref(),
+            &slots_state.epoch_report_page.to_le_bytes()
+        ],
+        bump = epoch_report_page.bump,
+    )]
+    pub epoch_report_page: Option<Account<'info, EpochReportPage>>,
+
+    /// Optional wager race the settled bet counts towards.
+    #[account(mut, has_one = slots_state)]
+    pub wager_race: Option<Account<'info, WagerRace>>,
+
+    /// Earnings PDA of the player's referrer; required when they have one.
+    #[account(
+        mut,
+        seeds = [REFERRAL_SEED, slots_state.key().as_ref(), player_state.referrer.as_ref()],
+        bump = referral_earnings.bump,
+    )]
+    pub referral_earnings: Option<Account<'info, ReferralEarnings>>,
+
+    /// Optional leaderboard of the current epoch; skipped when absent.
+    #[account(mut, has_one = slots_state)]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+
+    /// Optional VIP token account of the player; see vip.rs.
+    #[account(
+        constraint = vip_token_account.mint == slots_state.vip_config.mint
+            @ ErrorCode::VipTokenAccountMismatch,
+        constraint = vip_token_account.owner == user.key() @ ErrorCode::VipTokenAccountMismatch,
+    )]
+    pub vip_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddToPool<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToPool<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [POOL_POSITION_SEED, slots_state.key().as_ref(), user.key().as_ref()],
+        bump,
+        space = PoolPosition::LEN,
+    )]
+    pub pool_position: Account<'info, PoolPosition>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromPool<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_POSITION_SEED, slots_state.key().as_ref(), user.key().as_ref()],
+        bump = pool_position.bump,
+    )]
+    pub pool_position: Account<'info, PoolPosition>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitTokenTreasury<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = treasury,
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestSpinToken<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// SOL treasury; receives the oracle cost.
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(address = slots_state.exclusive_mint @ ErrorCode::WrongCurrencyMode)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+
+    /// CHECK: Switchboard randomness account
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Allowlist page holding the player; only needed in beta mode.
+    #[account(has_one = slots_state)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    /// CHECK: queue of the randomness account; only needed while the
+    /// oracle queue whitelist is set. Checked in oracle_queue.rs.
+    pub oracle_queue: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleSpinToken<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Authority of the token treasury.
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(address = slots_state.exclusive_mint @ ErrorCode::WrongCurrencyMode)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+
+    /// CHECK: same randomness account used in request_spin_token
+    #[account(mut)]
+    pub randomness_account_data: AccountInfo<'info>,
+
+    /// Optional hourly RTP history; settle skips it when absent.
+    #[account(
+        mut,
+        seeds = [RTP_HISTORY_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub rtp_history: Option<AccountLoader<'info, RtpHistory>>,
+
+    /// Optional scraper metrics; refreshed when present.
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: Option<AccountLoader<'info, Metrics>>,
+
+    /// Optional compressed receipt tree; settle appends when present.
+    #[account(
+        mut,
+        seeds = [RECEIPT_TREE_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddToPoolToken<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub user: Signer<'info>,
+
+    #[account(address = slots_state.exclusive_mint @ ErrorCode::WrongCurrencyMode)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawalToken<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub authority: Signer<'info>,
+
+    /// Authority of the token treasury.
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(address = slots_state.exclusive_mint @ ErrorCode::WrongCurrencyMode)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = authority,
+    )]
+    pub authority_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawClaimableToken<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    pub user: Signer<'info>,
+
+    /// Authority of the token treasury.
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(address = slots_state.exclusive_mint @ ErrorCode::WrongCurrencyMode)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// Optional scraper metrics; refreshed when present.
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: Option<AccountLoader<'info, Metrics>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(page: u8)]
+pub struct InitAllowlistPage<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [ALLOWLIST_SEED, slots_state.key().as_ref(), &[page]],
+        bump,
+        space = Allowlist::LEN,
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAllowlist<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        has_one = slots_state,
+        seeds = [ALLOWLIST_SEED, slots_state.key().as_ref(), &[allowlist.page]],
+        bump = allowlist.bump,
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitRtpHistory<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [RTP_HISTORY_SEED, slots_state.key().as_ref()],
+        bump,
+        space = RtpHistory::LEN,
+    )]
+    pub rtp_history: AccountLoader<'info, RtpHistory>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitAccountIndex<'info> {
+    #[account(
+        init,
+        payer = user,
+        seeds = [ACCOUNT_INDEX_SEED, user.key().as_ref()],
+        bump,
+        space = AccountIndex::LEN,
+    )]
+    pub account_index: Account<'info, AccountIndex>,
+
+    /// CHECK: the player's PlayerState PDA on the legacy machine (see
+    /// machines.rs); only probed for existence
+    #[account(
+        seeds = [PLAYER_SEED, user.key().as_ref()],
+        bump,
+    )]
+    pub player_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitMetrics<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+        space = Metrics::LEN,
+    )]
+    pub metrics: AccountLoader<'info, Metrics>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitReceiptTree<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [RECEIPT_TREE_SEED, slots_state.key().as_ref()],
+        bump,
+        space = ReceiptTree::LEN,
+    )]
+    pub receipt_tree: AccountLoader<'info, ReceiptTree>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ViewReceiptTree<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        seeds = [RECEIPT_TREE_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub receipt_tree: AccountLoader<'info, ReceiptTree>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshMetrics<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: AccountLoader<'info, Metrics>,
+}
+
+#[derive(Accounts)]
+pub struct ViewState<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAccounting<'info> {
+    #[account(has_one = treasury)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// The token treasury; required in a token deployment, whose pool
+    /// it holds.
+    #[account(address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing)]
+    pub treasury_token: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct SyncPool<'info> {
+    #[account(mut, has_one = treasury)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// The token treasury; required in a token deployment, whose pool
+    /// it holds.
+    #[account(address = slots_state.token_treasury @ ErrorCode::TokenTreasuryMissing)]
+    pub treasury_token: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: Pubkey)]
+pub struct AdminVoidSpin<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    /// CHECK: randomness account the pending spin committed to
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: Pubkey)]
+pub struct ForceOutcome<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+}
+
+#[derive(Accounts)]
+#[instruction(player: Pubkey)]
+pub struct SetPlayerTier<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), player.as_ref()],
+        bump = player_state.bump,
+    )]
+    pub player_state: Account<'info, PlayerState>,
+}
+
+#[derive(Accounts)]
+pub struct SettleSpinDeferred<'info> {
+    /// Read-only on purpose: deferred settles must not serialize on it.
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    pub user: Signer<'info>,
+
+    /// CHECK: same randomness account used in request_spin
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FlushStats<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSpin<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: randomness account the pending spin committed to
+    pub randomness_account_data: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawClaimable<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRebate<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemFreeSpin<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    pub user: Signer<'info>,
+}
+
+/// The player's own self-exclusion and loss limit.
+#[derive(Accounts)]
+pub struct SetPlayerLimits<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct SetReferrer<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump = player_state.bump,
+        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [REFERRAL_SEED, slots_state.key().as_ref(), referrer.as_ref()],
+        bump,
+        space = ReferralEarnings::LEN,
+    )]
+    pub referral_earnings: Account<'info, ReferralEarnings>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralEarnings<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [REFERRAL_SEED, slots_state.key().as_ref(), referrer.key().as_ref()],
+        bump = referral_earnings.bump,
+    )]
+    pub referral_earnings: Account<'info, ReferralEarnings>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(machine_id: [u8; 32])]
+pub struct MigrateState<'info> {
+    /// CHECK: may be shorter than the current layout, so it is read as
+    /// SlotsState only once grown. Its discriminator is checked before the
+    /// realloc, and it must be `machine_id`'s address: the
+    /// `[SLOTS_SEED, machine_id]` PDA (`[SLOTS_SEED]` for the legacy
+    /// machine).
+    #[account(
+        mut,
+        owner = crate::ID,
+        constraint = migration::is_state_address(slots_state.key, &machine_id)
+            @ ErrorCode::InvalidMachineId,
+    )]
+    pub slots_state: UncheckedAccount<'info>,
+
+    /// Pays the rent for the extra bytes; must be the authority.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePlayer<'info> {
+    /// Machine the PlayerState belongs to; seeds its address.
+    pub slots_state: Account<'info, SlotsState>,
+
+    /// CHECK: may be shorter than the current layout, so it is read as
+    /// PlayerState only once grown; its discriminator is checked before
+    /// the realloc.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [PLAYER_SEED, machine_seed(&slots_state.machine_id), user.key().as_ref()],
+        bump,
+    )]
+    pub player_state: UncheckedAccount<'info>,
+
+    /// The player; pays the rent for the extra bytes.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePoolPosition<'info> {
+    /// Machine the PoolPosition belongs to; seeds its address.
+    pub slots_state: Account<'info, SlotsState>,
+
+    /// CHECK: may be shorter than the current layout, so it is read as
+    /// PoolPosition only once grown; its discriminator is checked before
+    /// the realloc.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [POOL_POSITION_SEED, slots_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub pool_position: UncheckedAccount<'info>,
+
+    /// The depositor; pays the rent for the extra bytes.
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyPause<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+    pub authority: Signer<'info>,
+
+    /// Optional scraper metrics; refreshed by emergency_pause when present.
+    #[account(
+        mut,
+        seeds = [METRICS_SEED, slots_state.key().as_ref()],
+        bump,
+    )]
+    pub metrics: Option<AccountLoader<'info, Metrics>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRecovery<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+    pub recovery_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+    pub pending_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitEpochReports<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [EPOCH_REPORT_SEED, slots_state.key().as_ref(), &0u32.to_le_bytes()],
+        bump,
+        space = EpochReportPage::LEN,
+    )]
+    pub epoch_report_page: Account<'info, EpochReportPage>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenEpochReportPage<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        seeds = [
+            EPOCH_REPORT_SEED,
+            slots_state.key().as_ref(),
+            &slots_state.epoch_report_page.to_le_bytes()
+        ],
+        bump = previous_page.bump,
+    )]
+    pub previous_page: Account<'info, EpochReportPage>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            EPOCH_REPORT_SEED,
+            slots_state.key().as_ref(),
+            &(slots_state.epoch_report_page + 1).to_le_bytes()
+        ],
+        bump,
+        space = EpochReportPage::LEN,
+    )]
+    pub new_page: Account<'info, EpochReportPage>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEpoch<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [
+            EPOCH_REPORT_SEED,
+            slots_state.key().as_ref(),
+            &slots_state.epoch_report_page.to_le_bytes()
+        ],
+        bump = epoch_report_page.bump,
+    )]
+    pub epoch_report_page: Account<'info, EpochReportPage>,
+}
+
+#[derive(Accounts)]
+#[instruction(race_id: u32)]
+pub struct CreateWagerRace<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [WAGER_RACE_SEED, slots_state.key().as_ref(), &race_id.to_le_bytes()],
+        bump,
+        space = WagerRace::LEN,
+    )]
+    pub wager_race: Account<'info, WagerRace>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeWagerRace<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut, has_one = slots_state)]
+    pub wager_race: Account<'info, WagerRace>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWagerRace<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut, has_one = slots_state)]
+    pub wager_race: Account<'info, WagerRace>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct CreateLeaderboard<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [LEADERBOARD_SEED, slots_state.key().as_ref(), &epoch.to_le_bytes()],
+        bump,
+        space = Leaderboard::LEN,
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitSymbolMeta<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [SYMBOL_META_SEED, slots_state.key().as_ref()],
+        bump,
+        space = SymbolMeta::LEN,
+    )]
+    pub symbol_meta: Account<'info, SymbolMeta>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSymbolMeta<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [SYMBOL_META_SEED, slots_state.key().as_ref()],
+        bump = symbol_meta.bump,
+    )]
+    pub symbol_meta: Account<'info, SymbolMeta>,
+}
+
+#[derive(Accounts)]
+pub struct ViewGameStatus<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        seeds = [SYMBOL_META_SEED, slots_state.key().as_ref()],
+        bump = symbol_meta.bump,
+    )]
+    pub symbol_meta: Option<Account<'info, SymbolMeta>>,
+}
+
+#[derive(Accounts)]
+pub struct InitCommunityVault<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [COMMUNITY_VAULT_SEED, slots_state.key().as_ref()],
+        bump,
+        space = CommunityVault::LEN,
+    )]
+    pub community_vault: Account<'info, CommunityVault>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepCommunity<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [COMMUNITY_VAULT_SEED, slots_state.key().as_ref()],
+        bump = community_vault.bump,
+    )]
+    pub community_vault: Account<'info, CommunityVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommunityAdmin<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        constraint = community_authority.key() == slots_state.community_authority
+            @ ErrorCode::Unauthorized
+    )]
+    pub community_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [COMMUNITY_VAULT_SEED, slots_state.key().as_ref()],
+        bump = community_vault.bump,
+    )]
+    pub community_vault: Account<'info, CommunityVault>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCommunity<'info> {
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        constraint = community_authority.key() == slots_state.community_authority
+            @ ErrorCode::Unauthorized
+    )]
+    pub community_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [COMMUNITY_VAULT_SEED, slots_state.key().as_ref()],
+        bump = community_vault.bump,
+    )]
+    pub community_vault: Account<'info, CommunityVault>,
+
+    /// CHECK: must be on the vault's destination allowlist
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCommunityAuthority<'info> {
+    #[account(mut)]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(
+        constraint = community_authority.key() == slots_state.community_authority
+            @ ErrorCode::Unauthorized
+    )]
+    pub community_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        has_one = treasury
+    )]
+    pub slots_state: Account<'info, SlotsState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// System-owned and never `init`ed; withdraw_fees funds its rent when
+    /// it has none yet.
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED, machine_seed(&slots_state.machine_id)],
+        bump,
+    )]
+    pub fee_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,289 @@
+//! Per-epoch accounting close-outs for finance reporting.
+//!
+//! Nothing is reset at an epoch boundary. Each report is the difference
+//! between the lifetime counters ([`lifetime_totals`]) at the start and
+//! end of the epoch, so the reports of a deployment sum to its lifetime
+//! counters since init_epoch_reports.
+//!
+//! Reports are appended to `EpochReportPage` PDAs (seeds
+//! `[EPOCH_REPORT_SEED, slots_state, page as u32 LE]`), EPOCH_REPORTS_PER_PAGE
+//! each. The page in use (`SlotsState.epoch_report_page`) also carries
+//! the open epoch and the lifetime totals it opened at. The close-out is
+//! lazy: the first settle of a new epoch that passes the current page
+//! closes the previous one, and the permissionless close_epoch does the
+//! same without a spin. Activity between the boundary and that moment
+//! still counts towards the closing epoch. Epochs with no close in
+//! between are merged into one report, labelled with the first of them.
+//!
+//! A full page stops closing epochs until open_epoch_report_page has
+//! created the next one, which inherits the open epoch and its totals.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, EpochReportPage, SlotsState};
+
+pub const EPOCH_REPORTS_PER_PAGE: usize = 8;
+
+/// Lifetime counters an epoch report is taken over.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochTotals {
+    pub spins: u64,
+    pub wagered: u64,
+    pub paid: u64,
+    /// Winner fee rebates paid from the fee balance.
+    pub fee_rebates: u64,
+    /// Oracle costs charged to players.
+    pub oracle_charges: u64,
+    pub jackpot_hits: u64,
+    pub jackpot_paid: u64,
+    /// add_to_pool deposits.
+    pub deposits: u64,
+    /// Treasury outflows, all categories.
+    pub withdrawals: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochReport {
+    /// Epoch index: unix_timestamp / epoch_length_secs.
+    pub epoch: i64,
+    pub totals: EpochTotals,
+}
+
+impl EpochTotals {
+    pub const LEN: usize = 9 * 8;
+
+    /// Field-wise `self - earlier`. Lifetime counters only grow, so
+    /// saturation only guards against a corrupt base.
+    pub fn since(&self, earlier: &EpochTotals) -> EpochTotals {
+        EpochTotals {
+            spins: self.spins.saturating_sub(earlier.spins),
+            wagered: self.wagered.saturating_sub(earlier.wagered),
+            paid: self.paid.saturating_sub(earlier.paid),
+            fee_rebates: self.fee_rebates.saturating_sub(earlier.fee_rebates),
+            oracle_charges: self.oracle_charges.saturating_sub(earlier.oracle_charges),
+            jackpot_hits: self.jackpot_hits.saturating_sub(earlier.jackpot_hits),
+            jackpot_paid: self.jackpot_paid.saturating_sub(earlier.jackpot_paid),
+            deposits: self.deposits.saturating_sub(earlier.deposits),
+            withdrawals: self.withdrawals.saturating_sub(earlier.withdrawals),
+        }
+    }
+}
+
+impl EpochReportPage {
+    pub const LEN: usize = 8
+        + 32
+        + 4
+        + 4
+        + 8
+        + EpochTotals::LEN
+        + 1
+        + EPOCH_REPORTS_PER_PAGE * (8 + EpochTotals::LEN)
+        + 1;
+
+    pub fn is_full(&self) -> bool {
+        self.count as usize >= EPOCH_REPORTS_PER_PAGE
+    }
+}
+
+pub fn lifetime_totals(state: &SlotsState) -> EpochTotals {
+    EpochTotals {
+        spins: state.total_spins,
+        wagered: state.total_wagered,
+        paid: state.total_payout,
+        fee_rebates: state.total_fee_rebates,
+        oracle_charges: state.oracle_costs_charged,
+        jackpot_hits: state.jackpot_hits,
+        jackpot_paid: state.jackpot_paid,
+        deposits: state.total_deposits,
+        withdrawals: state.outflow_totals.iter().fold(0u64, |a, t| a.saturating_add(*t)),
+    }
+}
+
+pub fn epoch_of(length_secs: u32, now_ts: i64) -> i64 {
+    time::bucket_for_timestamp(now_ts, length_secs as i64)
+}
+
+/// Close the open epoch if `now_ts` is past it and the page has room.
+/// Returns the report appended, if any.
+pub fn close_if_due(
+    page: &mut EpochReportPage,
+    state: &SlotsState,
+    now_ts: i64,
+) -> Option<EpochReport> {
+    let epoch = epoch_of(page.epoch_length_secs, now_ts);
+    if epoch <= page.open_epoch || page.is_full() {
+        return None;
+    }
+    let totals = lifetime_totals(state);
+    let report = EpochReport {
+        epoch: page.open_epoch,
+        totals: totals.since(&page.open_base),
+    };
+    page.reports[page.count as usize] = report;
+    page.count += 1;
+    page.open_epoch = epoch;
+    page.open_base = totals;
+    Some(report)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::entrypoint::ProgramResult;
+    use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::client::decode_epoch_reports;
+    use crate::test_util::{pda, program_error, simulate, Fixture, Game};
+    use crate::{instruction, ErrorCode, DEFAULT_MIN_BET, EPOCH_REPORT_SEED};
+
+    const LENGTH: u32 = 100;
+
+    fn system() -> Fixture {
+        Fixture::program(anchor_lang::system_program::ID)
+    }
+
+    fn page_key(state: &Pubkey, page: u32) -> Pubkey {
+        pda(&[EPOCH_REPORT_SEED, state.as_ref(), &page.to_le_bytes()]).0
+    }
+
+    /// `game`'s state, with epoch reports started, and page 0.
+    fn reporting(game: &mut Game) -> (Fixture, Fixture) {
+        let [mut state, ..] = game.fixtures();
+        let mut page = Fixture::uninit(page_key(&state.key, 0), EpochReportPage::LEN);
+        let init = instruction::InitEpochReports { epoch_length_secs: LENGTH }.data();
+        simulate([&mut state, &mut page, &mut game.authority(), &mut system()], &init).unwrap();
+        game.state = state.read::<SlotsState>();
+        (state, page)
+    }
+
+    /// Commit, reveal and settle one spin with `page` passed, and carry
+    /// the result back into `game`.
+    fn spin(game: &mut Game, state: &mut Fixture, page: &mut Fixture) {
+        game.request(DEFAULT_MIN_BET).unwrap();
+        game.reveal([game.slot as u8; 32]);
+        let mut accounts = game.settle_spin_fixtures();
+        accounts[0].key = state.key;
+        accounts[9] = page.clone();
+        simulate(&mut accounts, &instruction::SettleSpin {}.data()).unwrap();
+        let rent = Rent::default().minimum_balance(0);
+        game.state = accounts[0].read::<SlotsState>();
+        game.player = accounts[1].read();
+        game.wallet = accounts[2].lamports;
+        game.treasury = accounts[4].lamports - rent;
+        *state = accounts[0].clone();
+        *page = accounts[9].clone();
+    }
+
+    fn plus(a: EpochTotals, b: &EpochTotals) -> EpochTotals {
+        EpochTotals {
+            spins: a.spins + b.spins,
+            wagered: a.wagered + b.wagered,
+            paid: a.paid + b.paid,
+            fee_rebates: a.fee_rebates + b.fee_rebates,
+            oracle_charges: a.oracle_charges + b.oracle_charges,
+            jackpot_hits: a.jackpot_hits + b.jackpot_hits,
+            jackpot_paid: a.jackpot_paid + b.jackpot_paid,
+            deposits: a.deposits + b.deposits,
+            withdrawals: a.withdrawals + b.withdrawals,
+        }
+    }
+
+    fn close(state: &mut Fixture, page: &mut Fixture) -> ProgramResult {
+        simulate([state, page], &instruction::CloseEpoch {}.data())
+    }
+
+    #[test]
+    fn only_the_authority_starts_reports() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), LAMPORTS_PER_SOL).signer();
+        let init = |epoch_length_secs| {
+            instruction::InitEpochReports { epoch_length_secs }.data()
+        };
+
+        let mut page = Fixture::uninit(page_key(&state.key, 0), EpochReportPage::LEN);
+        assert_eq!(
+            simulate([&mut state, &mut page, &mut stranger, &mut system()], &init(LENGTH)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        let mut page = Fixture::uninit(page_key(&state.key, 0), EpochReportPage::LEN);
+        assert_eq!(
+            simulate([&mut state, &mut page, &mut game.authority(), &mut system()], &init(0)),
+            Err(program_error(ErrorCode::InvalidEpochConfig))
+        );
+    }
+
+    #[test]
+    fn reports_across_three_boundaries_sum_to_the_lifetime_counters() {
+        let mut game = Game::new();
+        let (mut state, mut page) = reporting(&mut game);
+        let base = lifetime_totals(&game.state);
+        let first = epoch_of(LENGTH, game.slot as i64);
+        assert_eq!(page.read::<EpochReportPage>().open_base, base);
+
+        for _ in 0..4 {
+            game.fund(LAMPORTS_PER_SOL);
+            spin(&mut game, &mut state, &mut page);
+            game.advance(LENGTH as u64);
+        }
+        // The settles of epochs 2 to 4 closed the three before them.
+        assert_eq!(page.read::<EpochReportPage>().count, 3);
+
+        // close_epoch closes the last one without a spin, once.
+        close(&mut state, &mut page).unwrap();
+        close(&mut state, &mut page).unwrap();
+        let reports = decode_epoch_reports(&page.data).unwrap();
+        let epochs: Vec<i64> = reports.iter().map(|r| r.epoch).collect();
+        assert_eq!(epochs, [first, first + 1, first + 2, first + 3]);
+        assert_eq!(&reports[..], &page.read::<EpochReportPage>().reports[..4]);
+
+        let sum = reports.iter().fold(EpochTotals::default(), |sum, r| plus(sum, &r.totals));
+        assert_eq!(sum, lifetime_totals(&game.state).since(&base));
+        assert_eq!(sum.spins, 4);
+        assert_eq!(sum.wagered, 4 * DEFAULT_MIN_BET);
+        assert_eq!(sum.deposits, 4 * LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn a_full_page_waits_for_the_next_one() {
+        let mut game = Game::new();
+        let (mut state, page) = reporting(&mut game);
+        let mut full = page.read::<EpochReportPage>();
+        full.count = EPOCH_REPORTS_PER_PAGE as u8;
+        let mut full = Fixture::account(page.key, &full);
+        let mut payer = Fixture::wallet(Pubkey::new_unique(), LAMPORTS_PER_SOL).signer();
+        let open = instruction::OpenEpochReportPage {}.data();
+
+        // A page with room does not roll over.
+        let mut next = Fixture::uninit(page_key(&state.key, 1), EpochReportPage::LEN);
+        let mut open_page = page.clone();
+        assert_eq!(
+            simulate([&mut state, &mut open_page, &mut next, &mut payer, &mut system()], &open),
+            Err(program_error(ErrorCode::EpochReportPageNotFull))
+        );
+
+        // A full page closes nothing, even past its epoch.
+        game.advance(LENGTH as u64);
+        close(&mut state, &mut full).unwrap();
+        let stuck = full.read::<EpochReportPage>();
+        assert_eq!(stuck.count as usize, EPOCH_REPORTS_PER_PAGE);
+        assert_eq!(stuck.open_epoch, epoch_of(LENGTH, game.slot as i64) - 1);
+
+        let mut next = Fixture::uninit(page_key(&state.key, 1), EpochReportPage::LEN);
+        simulate([&mut state, &mut full, &mut next, &mut payer, &mut system()], &open).unwrap();
+        let opened = next.read::<EpochReportPage>();
+        assert_eq!(opened.page, 1);
+        assert_eq!(opened.count, 0);
+        assert_eq!(opened.open_epoch, stuck.open_epoch);
+        assert_eq!(opened.open_base, stuck.open_base);
+        assert_eq!(state.read::<SlotsState>().epoch_report_page, 1);
+
+        // The new page takes the overdue close; the old one is refused.
+        close(&mut state, &mut next).unwrap();
+        assert_eq!(decode_epoch_reports(&next.data).unwrap()[0].epoch, stuck.open_epoch);
+        let seeds = anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintSeeds);
+        assert_eq!(close(&mut state, &mut full), Err(seeds.into()));
+    }
+}
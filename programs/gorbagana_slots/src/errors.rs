@@ -0,0 +1,306 @@
+//! Program error codes. New codes go at the end: clients match on the
+//! numeric value.
+
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Invalid bet amount")]
+    InvalidBetAmount,
+    #[msg("Bet amount too high")]
+    BetTooHigh,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Insufficient pool funds")]
+    InsufficientPool,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Game is paused")]
+    GamePaused,
+    #[msg("Slots state not initialized")]
+    Uninitialized,
+    #[msg("Math overflow")]
+    MathOverflow,
+
+    #[msg("Player state not initialized")]
+    PlayerStateMissing,
+    #[msg("Player already has a pending spin")]
+    PendingSpinExists,
+    #[msg("No pending spin for this player")]
+    NoPendingSpin,
+    #[msg("Randomness account does not match player state")]
+    RandomnessAccountMismatch,
+    #[msg("Randomness account parse failed")]
+    RandomnessParseFailed,
+    #[msg("Randomness not yet revealed or invalid seed slot")]
+    RandomnessNotResolved,
+
+    #[msg("Player is not on the beta allowlist")]
+    NotAllowlisted,
+    #[msg("Allowlist page is full")]
+    AllowlistPageFull,
+    #[msg("Player already on this allowlist page")]
+    AlreadyAllowlisted,
+    #[msg("Allowlist pages must be created in order")]
+    InvalidAllowlistPage,
+    #[msg("Invalid rebate configuration")]
+    InvalidRebateConfig,
+    #[msg("Only the program upgrade authority may initialize")]
+    NotUpgradeAuthority,
+    #[msg("Settle hook program is not registered")]
+    UnknownSettleHook,
+    #[msg("Invalid settle hook slot or program")]
+    InvalidSettleHook,
+    #[msg("Settle hook timelock has not elapsed")]
+    SettleHookTimelocked,
+    #[msg("Invalid bootstrap configuration")]
+    InvalidBootstrapConfig,
+    #[msg("A spin was committed too recently to withdraw")]
+    WithdrawalCooldown,
+    #[msg("Invalid jackpot tier")]
+    InvalidJackpotTier,
+    #[msg("Jackpot tier is disabled")]
+    JackpotTierDisabled,
+    #[msg("Randomness is already revealed")]
+    RandomnessAlreadyRevealed,
+    #[msg("Nothing to withdraw")]
+    NothingClaimable,
+    #[msg("Earmark release exceeds the amount held")]
+    EarmarkUnderflow,
+    #[msg("Earmark ledger does not match the subsystem's outstanding amount")]
+    EarmarkMismatch,
+    #[msg("Earmarked obligations exceed the pool")]
+    ObligationsExceedPool,
+    #[msg("Game is not paused")]
+    NotPaused,
+    #[msg("Resume slot must be in the future")]
+    InvalidResumeSlot,
+    #[msg("No resume is scheduled")]
+    NoScheduledResume,
+    #[msg("This settle changes shared state; use settle_spin")]
+    SettleNeedsWriteLock,
+    #[msg("Receipt tree is full")]
+    ReceiptTreeFull,
+    #[msg("Receipt proof does not match a known root")]
+    InvalidReceiptProof,
+    #[msg("Instruction is not available in this deployment's currency mode")]
+    WrongCurrencyMode,
+    #[msg("Signed bet is not signed by the player or does not match")]
+    InvalidBetSignature,
+    #[msg("Signed bet has expired")]
+    SignedBetExpired,
+    #[msg("Signed bet nonce does not match the player's next nonce")]
+    SignedBetNonceMismatch,
+    #[msg("Invalid jackpot reseed configuration")]
+    InvalidReseedConfig,
+    #[msg("Unknown account index kind")]
+    InvalidAccountIndexKind,
+    #[msg("Outflow category is unknown or not allowed here")]
+    InvalidOutflowCategory,
+    #[msg("Outflow memo must be zero-padded UTF-8")]
+    InvalidOutflowMemo,
+    #[msg("Game is frozen for an upgrade")]
+    UpgradeFrozen,
+    #[msg("Game is not frozen for an upgrade")]
+    NotFrozen,
+    #[msg("Spin was committed under an incompatible program version")]
+    SpinVersionIncompatible,
+    #[msg("No recovery authority is configured")]
+    RecoveryNotConfigured,
+    #[msg("Authority has not been inactive for the recovery window")]
+    RecoveryWindowNotElapsed,
+    #[msg("Invalid recovery configuration")]
+    InvalidRecoveryConfig,
+    #[msg("Invalid tier limits")]
+    InvalidTierLimits,
+    #[msg("Invalid player tier")]
+    InvalidPlayerTier,
+    #[msg("Oracle is not revealing; new spins are paused")]
+    OracleUnhealthy,
+    #[msg("Invalid oracle health configuration")]
+    InvalidOracleHealthConfig,
+    #[msg("No community authority is configured")]
+    CommunityNotConfigured,
+    #[msg("Destination is not on the community vault allowlist")]
+    CommunityDestinationNotAllowed,
+    #[msg("Invalid community share configuration")]
+    InvalidCommunityConfig,
+    #[msg("Every symbol needs a name and a content hash")]
+    InvalidSymbolMeta,
+    #[msg("Symbol meta timelock has not elapsed")]
+    SymbolMetaTimelocked,
+    #[msg("Sponsored onboarding budget for today is used up")]
+    SponsoredBudgetExhausted,
+    #[msg("Invalid epoch report configuration")]
+    InvalidEpochConfig,
+    #[msg("Current epoch report page is not full yet")]
+    EpochReportPageNotFull,
+    #[msg("Test-only instruction refused on this network")]
+    TestOnlyInstruction,
+    #[msg("Invalid forced outcome")]
+    InvalidForcedOutcome,
+    #[msg("Invalid jackpot weights")]
+    InvalidJackpotWeights,
+    #[msg("Invalid jackpot bounds")]
+    InvalidJackpotBounds,
+    #[msg("A jackpot tier would hit more often than allowed")]
+    JackpotHitProbabilityTooHigh,
+    #[msg("Expected jackpot cost per spin exceeds the configured ceiling")]
+    JackpotCostTooHigh,
+    #[msg("Jackpot weights are still timelocked")]
+    JackpotWeightsTimelocked,
+    #[msg("Invalid wager race")]
+    InvalidWagerRace,
+    #[msg("Wager race is already finalized")]
+    WagerRaceClosed,
+    #[msg("Wager race has not ended yet")]
+    WagerRaceNotEnded,
+    #[msg("Remaining accounts do not match the wager race board")]
+    WagerRaceWinnerMismatch,
+    #[msg("Randomness account was re-seeded after the commit")]
+    RandomnessSeedChanged,
+    #[msg("Pending spin has not expired yet")]
+    SpinNotExpired,
+    #[msg("Bet amount too low")]
+    BetTooLow,
+    #[msg("Invalid bet limits")]
+    InvalidBetLimits,
+    #[msg("Top paytable win on max_bet exceeds max_payout_per_spin")]
+    BetLimitsExceedPayoutCap,
+    #[msg("Invalid game configuration")]
+    InvalidConfig,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    #[msg("Oracle queue account missing or does not match the randomness account")]
+    OracleQueueMismatch,
+    #[msg("Oracle queue authority is not whitelisted")]
+    OracleQueueNotWhitelisted,
+    #[msg("Oracle queue reveal latency exceeds the spin expiry margin")]
+    OracleQueueTooSlow,
+    #[msg("Token treasury has not been initialized")]
+    TokenTreasuryMissing,
+    #[msg("Commit would derive more outcomes per reveal than allowed")]
+    TooManyOutcomes,
+    #[msg("Settle would derive more outcomes than the spin committed")]
+    OutcomeBudgetExceeded,
+    #[msg("SlotsState was written by a newer program version")]
+    UnsupportedStateVersion,
+    #[msg("Withdrawal exceeds the authority extraction budget")]
+    ExtractionBudgetExceeded,
+    #[msg("Lines must be between 1 and 5")]
+    InvalidLines,
+    #[msg("Paytable's implied RTP exceeds max_rtp_bps")]
+    PaytableRtpTooHigh,
+    #[msg("Operator keys may not play")]
+    OperatorCannotPlay,
+    #[msg("Respin must commit to a different randomness account than the settled spin")]
+    RespinReusesRandomness,
+    #[msg("Respin commit attempted before the settled spin was cleared")]
+    RespinBeforeSettleCleared,
+    #[msg("Devnet faucet is disabled")]
+    FaucetDisabled,
+    #[msg("Devnet faucet already used in this window")]
+    FaucetCooldown,
+    #[msg("Player reached the devnet faucet lifetime cap")]
+    FaucetLimitReached,
+    #[msg("Devnet faucet reserve is too low")]
+    FaucetReserveEmpty,
+    #[msg("An optional account this configuration requires was not passed")]
+    OptionalAccountMissing,
+    #[msg("Pool accounting invariant violated")]
+    AccountingInvariantViolated,
+    #[msg("Randomness generation was already used by a recent spin")]
+    RandomnessGenerationReused,
+    #[msg("Operator fee exceeds MAX_OPERATOR_FEE_BPS")]
+    InvalidOperatorFee,
+    #[msg("Theoretical RTP exceeds 10_000 - house_edge_bps")]
+    RtpExceedsTarget,
+    #[msg("Deposit is too small to mint a pool share")]
+    DepositTooSmallForShares,
+    #[msg("Not enough pool shares")]
+    InsufficientShares,
+    #[msg("Withdrawal would take the pool below its reserve")]
+    PoolReserveBreached,
+    #[msg("A withdrawal is already pending")]
+    WithdrawalAlreadyPending,
+    #[msg("No withdrawal is pending")]
+    NoPendingWithdrawal,
+    #[msg("Withdrawal is still timelocked")]
+    WithdrawalLocked,
+    #[msg("Withdrawal delay is below MIN_WITHDRAWAL_DELAY_SLOTS")]
+    WithdrawalDelayTooShort,
+    #[msg("Unknown pause flag")]
+    InvalidPauseFlags,
+    #[msg("Free spins are not enabled")]
+    FreeSpinsDisabled,
+    #[msg("A free spin is already redeemed and not yet played")]
+    FreeSpinAlreadyRedeemed,
+    #[msg("Not enough loyalty points")]
+    InsufficientLoyaltyPoints,
+    #[msg("Bet exceeds the free-spin credit")]
+    FreeSpinBetTooLarge,
+    #[msg("Invalid loyalty configuration")]
+    InvalidLoyaltyConfig,
+    #[msg("A player cannot refer themselves")]
+    SelfReferral,
+    #[msg("Referrer is already set")]
+    ReferrerAlreadySet,
+    #[msg("Referrer can only be set before the first spin")]
+    ReferrerAfterFirstSpin,
+    #[msg("Referral share exceeds MAX_REFERRAL_BPS")]
+    InvalidReferralBps,
+    #[msg("Batch entries must be distinct (player_state, user, randomness) triples, at most MAX_BATCH_SETTLE")]
+    InvalidBatch,
+    #[msg("Spin committed sooner than min_slots_between_spins after the last one")]
+    SpinTooSoon,
+    #[msg("Player is self-excluded")]
+    SelfExcluded,
+    #[msg("Self-exclusion can only be extended")]
+    SelfExclusionShortened,
+    #[msg("Session loss limit reached")]
+    LossLimitReached,
+    #[msg("Loss limit can only be raised or removed in a window without losses")]
+    LossLimitLocked,
+    #[msg("Reel weights must be non-zero and sum below u64::MAX")]
+    InvalidReelWeights,
+    #[msg("The all-zero machine id is reserved for the legacy machine")]
+    InvalidMachineId,
+    #[msg("Scatter symbol out of range or max_free_spins below free_spins")]
+    InvalidScatterConfig,
+    #[msg("The scatter symbol must not pay a multiplier")]
+    ScatterSymbolPays,
+    #[msg("A held free spin plays one line at free_spin_bet")]
+    FreeSpinTermsMismatch,
+    #[msg("Wild symbol out of range or already the scatter")]
+    InvalidWildSymbol,
+    #[msg("Leaderboards can only be created for the current or a later epoch")]
+    LeaderboardEpochEnded,
+    #[msg("A must-hit-by ceiling cannot be below its tier's seed")]
+    MustHitBelowSeed,
+    #[msg("VIP config needs a mint and a rebate within the house edge")]
+    InvalidVipConfig,
+    #[msg("VIP token account is not the player's account for the VIP mint")]
+    VipTokenAccountMismatch,
+    #[msg("Spin count must be between 1 and 10")]
+    InvalidSpinCount,
+    #[msg("Randomness account is not owned by the Switchboard on-demand program")]
+    RandomnessAccountWrongOwner,
+    #[msg("Randomness account data is too small to be a randomness account")]
+    RandomnessAccountTooSmall,
+    #[msg("Randomness seed slot is too old to commit to")]
+    RandomnessSeedSlotTooOld,
+    #[msg("Too many accounts passed to the settle hook")]
+    TooManySettleHookAccounts,
+    #[msg("Only the deployer may declare a test network guard")]
+    TestNetworkGuardNotAllowed,
+    #[msg("Account is not the one the player state records")]
+    RecordedAccountMismatch,
+    #[msg("A jackpot holds less than the voided spin contributed to it")]
+    JackpotUnwindShortfall,
+    #[msg("A jackpot donation cap cannot be below the tier's current amount")]
+    JackpotCapBelowAmount,
+}
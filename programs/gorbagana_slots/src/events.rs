@@ -0,0 +1,1018 @@
+//! Program events.
+
+use anchor_lang::prelude::*;
+
+use crate::community::MAX_COMMUNITY_DESTINATIONS;
+use crate::config::ConfigSnapshot;
+use crate::epoch_reports::EpochTotals;
+use crate::jackpot_bounds::JackpotWeights;
+use crate::operators::MAX_STAFF_WALLETS;
+use crate::oracle_cost::OracleCostMode;
+use crate::oracle_queue::{OracleQueueEntry, MAX_ORACLE_QUEUES};
+use crate::outflows::OutflowCategory;
+use crate::reseed::ReseedPolicy;
+use crate::tiers::TierLimits;
+use crate::{JackpotBatchMode, SYMBOL_COUNT};
+
+#[event]
+pub struct SpinCommitted {
+    pub user: Pubkey,
+    pub bet_amount: u64,
+    pub randomness_account: Pubkey,
+    /// Oracle cost booked for this spin; charged to the player on top of
+    /// the bet under PlayerPays, absorbed by the house otherwise.
+    pub oracle_cost: u64,
+    pub oracle_cost_mode: OracleCostMode,
+    pub player_tier: u8,
+    /// Spin this event belongs to; see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+    pub context_index: u8,
+    /// Part of bet_amount booked as operator fee instead of joining the
+    /// pool.
+    pub operator_fee: u64,
+}
+
+#[event]
+pub struct PoolBelowRequired {
+    pub total_pool: u64,
+    pub required_pool: u64,
+    /// Deposit that would reopen the table.
+    pub shortfall: u64,
+}
+
+/// sync_pool reset total_pool from the treasury; `delta` is new - old.
+#[event]
+pub struct PoolSynced {
+    pub old: u64,
+    pub new: u64,
+    pub delta: i64,
+}
+
+#[event]
+pub struct PoolRestored {
+    pub total_pool: u64,
+    pub required_pool: u64,
+}
+
+#[event]
+pub struct OracleGateChanged {
+    /// False when commits are now refused.
+    pub open: bool,
+    pub slot: u64,
+    pub last_successful_reveal_slot: u64,
+    pub oldest_unrevealed_commit_slot: u64,
+}
+
+#[event]
+pub struct OracleHealthConfigChanged {
+    pub max_reveal_gap_slots: u64,
+    pub expected_reveal_slots: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct OracleQueuesChanged {
+    pub queues: [OracleQueueEntry; MAX_ORACLE_QUEUES],
+    pub margin_slots: u32,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct OracleCostChanged {
+    pub cost_lamports: u64,
+    pub mode: OracleCostMode,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct SpinSettled {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    pub symbols: [u8; 3],
+    pub base_payout: u64,
+    pub jackpot_payout: u64,
+    pub total_payout: u64,
+    /// Winner fee rebate paid on top, from the fee balance (not in RTP).
+    pub fee_rebate: u64,
+    /// sha256 of the 32 revealed VRF bytes, for archival verification.
+    pub vrf_hash: [u8; 32],
+    /// First 8 revealed bytes (the PRNG seed) in clear.
+    pub vrf_prefix: [u8; 8],
+    /// Layout version of this event; see SPIN_SETTLED_VERSION.
+    pub version: u8,
+    pub player_tier: u8,
+    /// Part of the base win cut by pool headroom and paid from the fee
+    /// balance instead (not in total_payout or RTP).
+    pub fee_overflow: u64,
+    /// Spin this event belongs to; see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+    pub context_index: u8,
+    /// Matching reels behind the base win: 3, 2, or 0 for no win.
+    pub match_count: u8,
+    /// Program-wide ordinal of this settle, to order settles across
+    /// players. 0 for a deferred settle, which cannot write SlotsState:
+    /// the StatsFlushed that folds it assigns its ordinal.
+    pub global_spin_counter: u64,
+    /// A scatter free spin: no stake behind it, none in total_wagered.
+    pub free_spin: bool,
+}
+
+/// Emitted next to SpinSettled for a multi-line spin.
+#[event]
+pub struct SpinLinesSettled {
+    pub user: Pubkey,
+    pub lines: u8,
+    pub line_bet: u64,
+    /// Rows top to bottom; SpinSettled.symbols is row 0.
+    pub grid: [[u8; 3]; 3],
+    /// Paytable win per payline before caps (rows, then the diagonals).
+    pub line_payouts: [u64; 5],
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// Emitted next to every SpinSettled: the reveal the reels were drawn
+/// from, to replay with the fairness module.
+#[event]
+pub struct SpinProof {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    /// Switchboard generation the spin was committed to.
+    pub seed_slot: u64,
+    /// The 32 bytes read from the randomness account.
+    pub random_bytes: [u8; 32],
+    /// PRNG seed derived from them (fairness::seed_from_vrf).
+    pub seed: u64,
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// A pending spin whose randomness account was re-seeded since the commit,
+/// voided by admin_void_spin next to its SpinVoided. Settles refuse such a
+/// spin (RandomnessSeedChanged): the account's authority could re-seed it
+/// after seeing a losing reveal.
+#[event]
+pub struct RandomnessRotated {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    pub committed_seed_slot: u64,
+    pub current_seed_slot: u64,
+    pub refunded: u64,
+    /// Spin this event belongs to; see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// A pending spin voided by the authority; the bet went to the player's
+/// claimable balance.
+#[event]
+pub struct SpinVoided {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    pub refunded: u64,
+    pub reason_code: u8,
+    pub authority: Pubkey,
+    /// Spin this event belongs to; see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// A pending spin cancelled by its player after spin_expiry_slots without
+/// a reveal. The bet went back to the player if the committed generation
+/// never revealed, and stayed in the pool if the account was re-seeded.
+#[event]
+pub struct SpinCancelled {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    pub refunded: u64,
+    pub forfeited: u64,
+    pub commit_slot: u64,
+    /// Spin this event belongs to; see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// A categorized treasury outflow, for the finance ledger.
+#[event]
+pub struct TreasuryOutflow {
+    pub category: OutflowCategory,
+    pub amount: u64,
+    pub to: Pubkey,
+    /// Zero-padded UTF-8 reference; all zeros = none.
+    pub memo: [u8; 32],
+    /// Lifetime total of this category after this outflow.
+    pub category_total: u64,
+}
+
+/// settle_and_respin put part of a win straight into the next spin.
+#[event]
+pub struct WinningsRebet {
+    pub user: Pubkey,
+    /// Payout plus fee rebate of the settled spin.
+    pub winnings: u64,
+    pub rebet: u64,
+    /// Remainder credited to the claimable balance.
+    pub to_claimable: u64,
+}
+
+#[event]
+pub struct ClaimableWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+/// A spin receipt appended to the ReceiptTree. Carries the full leaf
+/// preimage so indexers can rebuild the tree and serve proofs.
+#[event]
+pub struct ReceiptAppended {
+    pub leaf_index: u64,
+    pub leaf: [u8; 32],
+    pub player: Pubkey,
+    pub bet: u64,
+    pub symbols: [u8; 3],
+    pub base_payout: u64,
+    pub jackpot_payout: u64,
+    /// sha256 of the 32 revealed VRF bytes.
+    pub vrf_hash: [u8; 32],
+    pub root: [u8; 32],
+}
+
+/// Deferred player stats folded into SlotsState by flush_stats.
+#[event]
+pub struct StatsFlushed {
+    pub players: u32,
+    pub spins: u64,
+    pub wagered: u64,
+    /// global_spin_counter of the first folded settle; the rest follow in
+    /// remaining-account order, each player's by spin_nonce.
+    pub first_global_spin_counter: u64,
+}
+
+#[event]
+pub struct RTPUpdate {
+    pub total_spins: u64,
+    pub total_wagered: u64,
+    pub total_payout: u64,
+    pub house_profit: u64,
+    pub current_rtp_bps: u32,
+}
+
+#[event]
+pub struct TokenTreasuryInitialized {
+    pub mint: Pubkey,
+    pub token_treasury: Pubkey,
+    pub decimals: u8,
+}
+
+#[event]
+pub struct PoolDeposit {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub new_pool: u64,
+    /// LP shares minted for the deposit.
+    pub shares: u128,
+}
+
+#[event]
+pub struct PoolWithdrawal {
+    pub user: Pubkey,
+    pub shares: u128,
+    pub amount: u64,
+    pub new_pool: u64,
+}
+
+/// Donation to a jackpot tier. Donors get no claim on the funds.
+#[event]
+pub struct JackpotBoosted {
+    pub donor: Pubkey,
+    pub tier: u8,
+    pub amount: u64,
+    /// Part of amount credited to the tier; the rest went to the pool.
+    pub credited_to_tier: u64,
+    pub new_tier_amount: u64,
+    pub tier_boosted_total: u64,
+}
+
+#[event]
+pub struct FeeBalanceFunded {
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub new_fee_balance: u64,
+}
+
+#[event]
+pub struct WinnerRebateStatus {
+    pub suspended: bool,
+    pub fee_balance: u64,
+}
+
+#[event]
+pub struct SettleHookProposed {
+    pub index: u8,
+    pub hook_program: Pubkey,
+    pub activate_slot: u64,
+}
+
+#[event]
+pub struct SettleHookChanged {
+    pub index: u8,
+    /// Default key when the slot was cleared.
+    pub hook_program: Pubkey,
+}
+
+#[event]
+pub struct SettleHookInvoked {
+    pub hook_program: Pubkey,
+    pub user: Pubkey,
+    pub payout: u64,
+}
+
+#[event]
+pub struct AllowlistUpdated {
+    pub player: Pubkey,
+    pub page: u8,
+    pub added: bool,
+    pub count: u16,
+}
+
+#[event]
+pub struct BetaModeChanged {
+    pub beta_mode: bool,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct BootstrapModeChanged {
+    pub active: bool,
+    pub total_pool: u64,
+    pub maturity_threshold: u64,
+}
+
+#[event]
+pub struct JackpotBatchModeChanged {
+    pub mode: JackpotBatchMode,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct JackpotReseedPolicyChanged {
+    pub tier: u8,
+    pub policy: ReseedPolicy,
+    pub rebuild_bps: u16,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub old: ConfigSnapshot,
+    pub new: ConfigSnapshot,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct JackpotBoundsChanged {
+    pub max_hit_ppm: u32,
+    pub max_cost_bps: u16,
+    pub reference_bet: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct JackpotWeightsProposed {
+    pub weights: JackpotWeights,
+    pub activate_slot: u64,
+}
+
+#[event]
+pub struct JackpotWeightsChanged {
+    pub weights: JackpotWeights,
+    /// True when installed through the timelocked override path.
+    pub overridden: bool,
+    pub authority: Pubkey,
+}
+
+/// A tier's donation ceiling changed; 0 is uncapped.
+#[event]
+pub struct JackpotMaxCapChanged {
+    pub tier: u8,
+    pub old_cap: u64,
+    pub new_cap: u64,
+    pub authority: Pubkey,
+}
+
+/// One winner's jackpot share in a batch settle.
+#[event]
+pub struct JackpotBatchShare {
+    pub user: Pubkey,
+    pub tier: u8,
+    pub mode: JackpotBatchMode,
+    /// Tier amount the share was computed from (pre-batch in Split mode).
+    pub snapshot_amount: u64,
+    /// Hits on this tier in the batch (1 in Sequential mode).
+    pub winners: u32,
+    pub share: u64,
+}
+
+/// End of a settle_spins_batch call. Entries not settled stay pending.
+#[event]
+pub struct BatchSettled {
+    pub attempted: u8,
+    pub settled: u8,
+}
+
+#[event]
+pub struct EmergencyAction {
+    /// Pause flags now in effect (0 = resumed); see pause.rs.
+    pub pause_flags: u8,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityHeartbeat {
+    pub authority: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AuthorityTransferProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferCancelled {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct RecoveryConfigChanged {
+    pub recovery_authority: Pubkey,
+    pub inactivity_slots: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct RecoveryClaimed {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    /// Slots since the previous authority's last action.
+    pub inactive_slots: u64,
+}
+
+#[event]
+pub struct CommunityShareChanged {
+    pub community_bps: u16,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct CommunitySwept {
+    pub amount: u64,
+    /// Lifetime community accruals at the time of the sweep.
+    pub community_accrued: u64,
+}
+
+#[event]
+pub struct CommunityWithdrawn {
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub total_withdrawn: u64,
+}
+
+#[event]
+pub struct CommunityDestinationsChanged {
+    pub destinations: [Pubkey; MAX_COMMUNITY_DESTINATIONS],
+}
+
+#[event]
+pub struct CommunityAuthorityChanged {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct OperatorFeeChanged {
+    pub operator_fee_bps: u16,
+    pub authority: Pubkey,
+}
+
+/// `swept` moved from the treasury into the fee vault, then `amount` was
+/// paid out of it.
+#[event]
+pub struct FeesWithdrawn {
+    pub swept: u64,
+    pub amount: u64,
+    /// Withdrawable balance left in the vault.
+    pub vault_balance: u64,
+    pub operator_fees_accrued: u64,
+}
+
+/// A jackpot tier paid out. `tier` is game_math::JACKPOT_TIER_* (0 = mini,
+/// 1 = major, 2 = grand); `reset_to_seed` is what the tier restarts at,
+/// or for a Split-mode batch hit what it holds until the batch reseeds it.
+#[event]
+pub struct JackpotHit {
+    pub user: Pubkey,
+    pub tier: u8,
+    pub amount: u64,
+    pub reset_to_seed: u64,
+    pub randomness_account: Pubkey,
+    pub spin_nonce: u64,
+    /// Awarded by the tier's must-hit-by ceiling rather than the roll.
+    pub forced: bool,
+}
+
+/// A commit's jackpot contributions and the tier amounts after them, both
+/// indexed by tier (mini, major, grand). Skipped when nothing was added.
+#[event]
+pub struct JackpotContribution {
+    pub contributions: [u64; 3],
+    pub new_amounts: [u64; 3],
+}
+
+#[event]
+pub struct JackpotQualifyingBetChanged {
+    pub qualifying_bet: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct FaucetFunded {
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub new_reserve: u64,
+}
+
+#[event]
+pub struct FaucetConfigChanged {
+    pub amount: u64,
+    pub interval_slots: u64,
+    pub lifetime_cap: u64,
+}
+
+/// A devnet faucet drip into a session balance; see faucet.rs.
+#[event]
+pub struct FaucetDrip {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub received_total: u64,
+    pub next_drip_slot: u64,
+    pub reserve_left: u64,
+}
+
+#[event]
+pub struct OutcomeForced {
+    pub player: Pubkey,
+    pub symbols: [u8; 3],
+    pub jackpot_tier: u8,
+}
+
+#[event]
+pub struct WagerRaceCreated {
+    pub race_id: u32,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub prize_budget: u64,
+}
+
+#[event]
+pub struct WagerRacePrize {
+    pub race_id: u32,
+    /// Board place, 0 = first.
+    pub rank: u8,
+    pub player: Pubkey,
+    pub wagered: u64,
+    pub prize: u64,
+}
+
+#[event]
+pub struct WagerRaceFinalized {
+    pub race_id: u32,
+    /// Credited to claimable balances.
+    pub paid: u64,
+    /// Budget returned to the pool.
+    pub returned: u64,
+    pub cancelled: bool,
+}
+
+#[event]
+pub struct EpochClosed {
+    pub page: u32,
+    pub epoch: i64,
+    pub totals: EpochTotals,
+}
+
+#[event]
+pub struct SymbolMetaProposed {
+    pub activate_slot: u64,
+    /// Config hash the table will produce once active.
+    pub config_hash: [u8; 32],
+}
+
+#[event]
+pub struct SymbolMetaChanged {
+    pub version: u32,
+    pub config_hash: [u8; 32],
+}
+
+#[event]
+pub struct PlayerOnboarded {
+    pub user: Pubkey,
+    pub sponsor: Pubkey,
+    /// Sponsored inits today, including this one.
+    pub sponsored_today: u32,
+    pub daily_budget: u32,
+}
+
+#[event]
+pub struct OnboardingSponsorChanged {
+    pub sponsor: Pubkey,
+    pub daily_budget: u32,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct PlayerTierChanged {
+    pub player: Pubkey,
+    pub old_tier: u8,
+    pub new_tier: u8,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct BetLimitsChanged {
+    pub min_bet: u64,
+    pub max_bet: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct TierLimitsChanged {
+    pub tier: u8,
+    pub limits: TierLimits,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct UpgradeFrozen {
+    pub frozen_version: u16,
+    pub pending_spins: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ExtractionCharged {
+    pub amount: u64,
+    /// Part of `amount` drawn from the override.
+    pub from_override: u64,
+    /// Budget left in the period after this charge.
+    pub remaining: u64,
+    pub override_remaining: u64,
+    pub period_end: u64,
+}
+
+#[event]
+pub struct ExtractionBudgetChanged {
+    pub budget_bps: u16,
+    /// The new fraction applies to periods opening after this slot.
+    pub effective_after_slot: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ExtractionOverrideProposed {
+    pub amount: u64,
+    pub activate_slot: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct PaytableChanged {
+    pub two_of_a_kind: [u64; SYMBOL_COUNT],
+    pub three_of_a_kind: [u64; SYMBOL_COUNT],
+    pub implied_rtp_bps: u64,
+    pub authority: Pubkey,
+}
+
+/// initialize_machine created a machine; see machines.rs.
+#[event]
+pub struct MachineInitialized {
+    pub machine_id: [u8; 32],
+    pub slots_state: Pubkey,
+    pub treasury: Pubkey,
+    pub authority: Pubkey,
+}
+
+/// set_reel_config replaced the reel weights and 3-of-a-kind column.
+#[event]
+pub struct ReelConfigChanged {
+    pub weights: [u64; SYMBOL_COUNT],
+    pub total_weight: u64,
+    pub three_of_a_kind: [u64; SYMBOL_COUNT],
+    pub implied_rtp_bps: u64,
+    pub authority: Pubkey,
+}
+
+/// A commit from an operator key was refused; see operators.rs.
+#[event]
+pub struct OperatorPlayBlocked {
+    pub player: Pubkey,
+    pub role: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct StaffWalletsChanged {
+    pub wallets: [Pubkey; MAX_STAFF_WALLETS],
+    pub authority: Pubkey,
+}
+
+/// Lifetime totals after a settle; see player_stats.rs.
+#[event]
+pub struct PlayerStatsUpdated {
+    pub user: Pubkey,
+    pub total_spins: u64,
+    pub total_wagered: u64,
+    pub total_won: u64,
+    pub biggest_win: u64,
+    pub last_spin_slot: u64,
+    pub spin_nonce: u64,
+}
+
+#[event]
+pub struct PlayerMigrated {
+    pub user: Pubkey,
+    pub old_len: u32,
+    pub new_len: u32,
+}
+
+#[event]
+pub struct StateMigrated {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub old_len: u32,
+    pub new_len: u32,
+}
+
+#[event]
+pub struct UpgradeThawed {
+    pub from_version: u16,
+    pub to_version: u16,
+    pub compatible: bool,
+    /// Incompatible pending spins refunded by the thaw itself.
+    pub voided: u32,
+    pub authority: Pubkey,
+}
+
+/// A resume slot was scheduled (at_slot > 0) or canceled (at_slot = 0).
+#[event]
+pub struct ResumeScheduled {
+    pub at_slot: u64,
+    pub authority: Pubkey,
+}
+
+/// request_withdrawal: `amount` is held against the pool and can be
+/// executed from `unlock_slot`. `category` is an OutflowCategory.
+#[event]
+pub struct WithdrawalRequested {
+    pub amount: u64,
+    pub unlock_slot: u64,
+    pub category: u8,
+    pub memo: [u8; 32],
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub amount: u64,
+    pub category: u8,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub amount: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalDelayChanged {
+    pub delay_slots: u64,
+    pub authority: Pubkey,
+}
+
+/// Points credited for a settled spin.
+#[event]
+pub struct LoyaltyPointsAccrued {
+    pub user: Pubkey,
+    pub wager: u64,
+    pub points: u64,
+    pub balance: u64,
+}
+
+/// Points burned for a house-funded spin of up to `free_spin_bet`.
+#[event]
+pub struct FreeSpinRedeemed {
+    pub user: Pubkey,
+    pub points_burned: u64,
+    pub points_left: u64,
+    pub free_spin_bet: u64,
+}
+
+#[event]
+pub struct LoyaltyConfigChanged {
+    pub points_per_lamport_bps: u16,
+    pub free_spin_point_cost: u64,
+    pub free_spin_bet: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ReferrerSet {
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+}
+
+/// Referral credit of one settled spin; `accrued` is the referrer's
+/// unclaimed total after it.
+#[event]
+pub struct ReferralAccrued {
+    pub referrer: Pubkey,
+    pub user: Pubkey,
+    pub bet_amount: u64,
+    pub amount: u64,
+    pub accrued: u64,
+}
+
+#[event]
+pub struct ReferralEarningsClaimed {
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralBpsChanged {
+    pub referral_bps: u16,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct SelfExclusionSet {
+    pub user: Pubkey,
+    pub until_slot: u64,
+}
+
+#[event]
+pub struct LossLimitSet {
+    pub user: Pubkey,
+    /// 0 = no limit.
+    pub loss_limit_lamports: u64,
+}
+
+#[event]
+pub struct LossWindowChanged {
+    pub loss_window_slots: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct SpinGapChanged {
+    pub min_slots_between_spins: u16,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ScatterConfigChanged {
+    pub symbol: u8,
+    pub free_spins: u16,
+    pub max_free_spins: u16,
+    /// Base game plus free spins, as paytable::validate checks it.
+    pub implied_rtp_bps: u64,
+    pub authority: Pubkey,
+}
+
+/// Three scatters on the payline; see scatter.rs.
+#[event]
+pub struct FreeSpinsAwarded {
+    pub user: Pubkey,
+    pub awarded: u16,
+    pub free_spins_remaining: u16,
+    pub free_spin_bet: u64,
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+#[event]
+pub struct WildSymbolChanged {
+    /// NO_WILD when the feature is off.
+    pub wild_symbol: u8,
+    pub implied_rtp_bps: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct LeaderboardCreated {
+    pub epoch: u64,
+    pub epoch_slots: u64,
+    pub payer: Pubkey,
+}
+
+/// A settle placed the player on their epoch's leaderboard; see
+/// leaderboard.rs.
+#[event]
+pub struct LeaderboardUpdated {
+    pub epoch: u64,
+    pub player: Pubkey,
+    /// The player's payouts this epoch.
+    pub total_won: u64,
+    /// Board place, 0 = first.
+    pub rank: u8,
+}
+
+#[event]
+pub struct LeaderboardEpochChanged {
+    pub leaderboard_epoch_slots: u64,
+    pub authority: Pubkey,
+}
+
+/// A tier reached its must-hit-by ceiling; the next eligible settle
+/// awards it. See must_hit.rs.
+#[event]
+pub struct JackpotMustHitArmed {
+    pub tier: u8,
+    pub amount: u64,
+    pub must_hit_by: u64,
+}
+
+#[event]
+pub struct VipConfigChanged {
+    pub mint: Pubkey,
+    pub min_balance: u64,
+    pub rebate_bps: u16,
+    pub authority: Pubkey,
+}
+
+/// Rebate of one lost VIP spin; `accrued` is the player's unclaimed
+/// total after it. See vip.rs.
+#[event]
+pub struct VipRebateAccrued {
+    pub user: Pubkey,
+    pub stake: u64,
+    pub amount: u64,
+    pub accrued: u64,
+    pub spin_nonce: u64,
+}
+
+#[event]
+pub struct VipRebateClaimed {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+/// Emitted next to SpinSettled for a request_spins commit, which carries
+/// the totals. See multi_spin.rs.
+#[event]
+pub struct SpinsSettled {
+    pub user: Pubkey,
+    pub spins: u8,
+    pub spin_bet: u64,
+    /// Reels of each spin in draw order; zero past `spins`.
+    pub symbols: [[u8; 3]; 10],
+    /// What each spin paid after its caps, jackpot included.
+    pub payouts: [u64; 10],
+    pub spin_nonce: u64,
+    pub context_index: u8,
+}
+
+/// Hook accounts a settle was given but did not use; the settle went
+/// through without the hook. See settle_hooks.rs.
+#[event]
+pub struct SettleHookSkipped {
+    pub hook_program: Pubkey,
+    pub user: Pubkey,
+    /// Anchor error code of the reason.
+    pub error_code: u32,
+}
+
+/// The pool emptied with LP shares outstanding; they are worth nothing
+/// and were written off so deposits can mint again. See pool_shares.rs.
+#[event]
+pub struct PoolSharesWrittenOff {
+    pub shares: u128,
+    /// The epoch that starts now.
+    pub share_epoch: u32,
+}
+
+#[event]
+pub struct PoolPositionMigrated {
+    pub user: Pubkey,
+    pub old_len: u32,
+    pub new_len: u32,
+}
@@ -0,0 +1,211 @@
+//! Per-period cap on what the authority can take out of the pool.
+//!
+//! Every outflow to an authority-controlled destination (execute_withdrawal,
+//! execute_withdrawal_token and withdraw_fees' sweep into the fee vault)
+//! is charged against one shared budget, and so is the community share
+//! that leaves the pool at settle (see [`charge_within_budget`]). A
+//! period lasts [`EXTRACTION_PERIOD_SLOTS`] and opens with the first
+//! charge after the previous one ended. Its budget is fixed then, at
+//! `extraction_budget_bps` of total_pool at that moment. A new fraction
+//! applies from the next period, so raising it cannot empty the current
+//! one.
+//!
+//! Beyond the budget, the authority proposes an override amount. It is
+//! usable [`EXTRACTION_PERIOD_SLOTS`] after the proposal and is drawn down
+//! by whatever the budget does not cover, so LPs and players see any
+//! large withdrawal a full period ahead.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, ExtractionCharged, SlotsState};
+
+/// Length of a budget period, and the override timelock (~1 day).
+pub const EXTRACTION_PERIOD_SLOTS: u64 = 216_000;
+/// Default budget: 20% of the pool per period.
+pub const DEFAULT_EXTRACTION_BUDGET_BPS: u16 = 2_000;
+/// Largest budget the authority may set: half the pool per period. A
+/// whole-pool budget would be no limit at all.
+pub const MAX_EXTRACTION_BUDGET_BPS: u16 = 5_000;
+
+/// Open a new period if the current one has ended.
+fn roll(state: &mut SlotsState, now_slot: u64) {
+    if !time::has_reached(state.extraction_period_end, now_slot) {
+        return;
+    }
+    state.extraction_period_end = now_slot.saturating_add(EXTRACTION_PERIOD_SLOTS);
+    state.extraction_period_budget =
+        (state.total_pool as u128 * state.extraction_budget_bps as u128 / 10_000) as u64;
+    state.extraction_period_used = 0;
+}
+
+/// Budget left in the current period.
+pub fn remaining(state: &SlotsState) -> u64 {
+    state
+        .extraction_period_budget
+        .saturating_sub(state.extraction_period_used)
+}
+
+fn override_active(state: &SlotsState, now_slot: u64) -> bool {
+    state.extraction_override_activate_slot != 0
+        && time::has_reached(state.extraction_override_activate_slot, now_slot)
+}
+
+/// Charge `amount` leaving to the authority. Call before total_pool is
+/// reduced, so a period opened here snapshots the pool as it was.
+pub fn charge(state: &mut SlotsState, amount: u64, now_slot: u64) -> Result<()> {
+    roll(state, now_slot);
+
+    let from_budget = amount.min(remaining(state));
+    let from_override = amount - from_budget;
+    if from_override > 0 {
+        require!(
+            override_active(state, now_slot) && from_override <= state.extraction_override,
+            ErrorCode::ExtractionBudgetExceeded
+        );
+        state.extraction_override -= from_override;
+        if state.extraction_override == 0 {
+            state.extraction_override_activate_slot = 0;
+        }
+    }
+    state.extraction_period_used += from_budget;
+
+    emit!(ExtractionCharged {
+        amount,
+        from_override,
+        remaining: remaining(state),
+        override_remaining: state.extraction_override,
+        period_end: state.extraction_period_end,
+    });
+    Ok(())
+}
+
+/// Charge what is left of the budget, up to `amount`, and never the
+/// override. For outflows a player's settle triggers, which must not fail
+/// on the budget: they shrink to fit it instead. Returns the amount
+/// charged.
+pub fn charge_within_budget(state: &mut SlotsState, amount: u64, now_slot: u64) -> u64 {
+    roll(state, now_slot);
+
+    let charged = amount.min(remaining(state));
+    if charged == 0 {
+        return 0;
+    }
+    state.extraction_period_used += charged;
+
+    emit!(ExtractionCharged {
+        amount: charged,
+        from_override: 0,
+        remaining: remaining(state),
+        override_remaining: state.extraction_override,
+        period_end: state.extraction_period_end,
+    });
+    charged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outflows::OutflowCategory;
+    use crate::test_util::zeroed_state;
+    use crate::{community, operator_fee, withdrawal_timelock};
+
+    fn state() -> SlotsState {
+        let mut state = zeroed_state();
+        state.total_pool = 100_000;
+        state.extraction_budget_bps = DEFAULT_EXTRACTION_BUDGET_BPS;
+        state
+    }
+
+    #[test]
+    fn a_period_budget_is_fixed_when_it_opens() {
+        let mut s = state();
+        charge(&mut s, 5_000, 10).unwrap();
+        assert_eq!(s.extraction_period_budget, 20_000);
+        assert_eq!(s.extraction_period_end, 10 + EXTRACTION_PERIOD_SLOTS);
+        assert_eq!(remaining(&s), 15_000);
+
+        // Neither a bigger pool nor a bigger fraction moves this period.
+        s.total_pool = 1_000_000;
+        s.extraction_budget_bps = 10_000;
+        charge(&mut s, 15_000, 20).unwrap();
+        assert_eq!(remaining(&s), 0);
+        assert!(charge(&mut s, 1, 30).is_err());
+        assert_eq!(s.extraction_period_used, 20_000);
+    }
+
+    #[test]
+    fn the_next_period_reopens_the_budget() {
+        let mut s = state();
+        charge(&mut s, 20_000, 0).unwrap();
+        assert!(charge(&mut s, 1, EXTRACTION_PERIOD_SLOTS - 1).is_err());
+
+        s.total_pool = 50_000;
+        charge(&mut s, 1, EXTRACTION_PERIOD_SLOTS).unwrap();
+        assert_eq!(s.extraction_period_budget, 10_000);
+        assert_eq!(remaining(&s), 9_999);
+    }
+
+    #[test]
+    fn an_override_covers_the_excess_only_once_active() {
+        let mut s = state();
+        s.extraction_override = 30_000;
+        s.extraction_override_activate_slot = 1_000;
+
+        assert!(charge(&mut s, 25_000, 999).is_err());
+        assert_eq!(s.extraction_override, 30_000);
+
+        // The budget is used first; the override takes the rest.
+        charge(&mut s, 25_000, 1_000).unwrap();
+        assert_eq!(remaining(&s), 0);
+        assert_eq!(s.extraction_override, 25_000);
+        assert!(charge(&mut s, 25_001, 1_001).is_err());
+
+        // Drawn down to zero, it closes.
+        charge(&mut s, 25_000, 1_002).unwrap();
+        assert_eq!(s.extraction_override, 0);
+        assert_eq!(s.extraction_override_activate_slot, 0);
+        assert!(charge(&mut s, 1, 1_003).is_err());
+    }
+
+    #[test]
+    fn withdrawals_fee_sweeps_and_community_accrual_share_one_budget() {
+        let mut s = state();
+        s.withdrawal_delay_slots = 0;
+        s.community_bps = 10_000;
+        s.operator_fee_balance = 5_000;
+
+        // An operator withdrawal opens the period: 20_000 of budget.
+        withdrawal_timelock::request(&mut s, 12_000, OutflowCategory::Fee, [0; 32], 0).unwrap();
+        withdrawal_timelock::execute(&mut s, 10, u64::MAX).unwrap();
+        assert_eq!(remaining(&s), 8_000);
+
+        // The fee sweep comes out of the same budget.
+        let sweep = operator_fee::withdraw(&mut s, 0, 0, 20).unwrap();
+        assert_eq!(sweep.swept, 5_000);
+        assert_eq!(remaining(&s), 3_000);
+
+        // A settle's community share is cut down to what is left.
+        let pool = s.total_pool;
+        assert_eq!(community::accrue(&mut s, 10_000, 0, 30).unwrap(), 3_000);
+        assert_eq!(s.total_pool, pool - 3_000);
+        assert_eq!(s.community_balance, 3_000);
+        assert_eq!(remaining(&s), 0);
+
+        // Nothing more leaves this period, by any route.
+        assert_eq!(community::accrue(&mut s, 10_000, 0, 40).unwrap(), 0);
+        assert_eq!(s.total_pool, pool - 3_000);
+        s.operator_fee_balance = 1;
+        assert_eq!(
+            operator_fee::withdraw(&mut s, 0, 0, 50).err(),
+            Some(ErrorCode::ExtractionBudgetExceeded.into())
+        );
+        withdrawal_timelock::request(&mut s, 1, OutflowCategory::Fee, [0; 32], 60).unwrap();
+        assert_eq!(
+            withdrawal_timelock::execute(&mut s, 60, u64::MAX).err(),
+            Some(ErrorCode::ExtractionBudgetExceeded.into())
+        );
+
+        // The next period accrues again.
+        assert!(community::accrue(&mut s, 10_000, 0, 10 + EXTRACTION_PERIOD_SLOTS).unwrap() > 0);
+    }
+}
@@ -0,0 +1,46 @@
+//! What an off-chain verifier needs to replay a spin's reels.
+//!
+//! Every settle emits a SpinProof with the 32 bytes it read from the
+//! Switchboard account, the generation's seed_slot and the PRNG seed
+//! derived from them. [`symbols_for_reveal`] turns those bytes into the
+//! reels SpinSettled reports, with the same code the program runs: the
+//! functions below are re-exports, not copies. A multi-line spin keeps
+//! drawing rows 1 and 2 from the same seed (game_math::spin_grid).
+//!
+//! The reels are drawn on the machine's `reel_weights` and priced by
+//! calculate_payout with its `paytable` and `wild_symbol`; all three only
+//! change while no spin is pending, so the live SlotsState is the one the
+//! spin settled under.
+
+pub use crate::game_math::{
+    calculate_payout, calculate_payout_3oak, generate_weighted_symbol, next_random_u64,
+    seed_from_vrf, spin_symbols,
+};
+pub use crate::{PayTable, ReelWeights, NO_WILD, PAYTABLE, REEL_WEIGHTS};
+
+/// The three reels a settle of `random_bytes` lands on `reels`.
+pub fn symbols_for_reveal(random_bytes: &[u8; 32], reels: &ReelWeights) -> [u8; 3] {
+    let mut seed = seed_from_vrf(random_bytes);
+    spin_symbols(&mut seed, reels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_reveal_lands_known_symbols() {
+        // Only the first 8 bytes seed the PRNG, little-endian.
+        let mut random_bytes = [0xffu8; 32];
+        random_bytes[..8].copy_from_slice(&0x0123_4567_89ab_cdefu64.to_le_bytes());
+        assert_eq!(seed_from_vrf(&random_bytes), 0x0123_4567_89ab_cdef);
+        assert_eq!(symbols_for_reveal(&random_bytes, &REEL_WEIGHTS), [5, 2, 3]);
+
+        let mut seed = 42;
+        let mut draw = || generate_weighted_symbol(next_random_u64(&mut seed), &REEL_WEIGHTS);
+        let symbols = [draw(), draw(), draw()];
+        assert_eq!(symbols, [10, 10, 11]);
+        assert_eq!(calculate_payout_3oak(symbols, 1_000, &PAYTABLE), 0);
+        assert_eq!(calculate_payout_3oak([11, 11, 11], 1_000, &PAYTABLE), 14_000);
+    }
+}
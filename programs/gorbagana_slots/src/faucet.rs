@@ -0,0 +1,75 @@
+//! Devnet faucet for demo credits.
+//!
+//! On a test network, new wallets can get a funded session without
+//! hunting for devnet SOL. The authority funds a faucet reserve with
+//! fund_faucet and sets the drip with set_faucet_config. devnet_faucet
+//! then moves one drip from the reserve into the caller's session
+//! (claimable) balance. Each player may drip once per
+//! `faucet_interval_slots` and receive at most `faucet_lifetime_cap`
+//! overall; the last drip is cut to fit the cap.
+//!
+//! The reserve lives in the treasury like every other bucket but is
+//! tracked only in `faucet_reserve`, never in total_pool, fee_balance or
+//! any promo budget. A drip moves lamports from it to total_claimable.
+//!
+//! Every faucet instruction checks qa::require_test_network first. The
+//! faucet cannot touch outcomes, so unlike force_outcome it is not behind
+//! the `qa` feature. A zero `faucet_amount` (the default) disables it.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, PlayerState, SlotsState};
+
+/// Lamports one devnet_faucet call would credit `player_state` at slot
+/// `now`; errors if none.
+pub fn next_drip(state: &SlotsState, player_state: &PlayerState, now: u64) -> Result<u64> {
+    require!(state.faucet_amount > 0, ErrorCode::FaucetDisabled);
+    // The first drip is never rate limited.
+    require!(
+        player_state.faucet_last_slot == 0
+            || time::is_expired(
+                player_state.faucet_last_slot,
+                state.faucet_interval_slots,
+                now
+            ),
+        ErrorCode::FaucetCooldown
+    );
+    let remaining = state
+        .faucet_lifetime_cap
+        .saturating_sub(player_state.faucet_received);
+    let amount = state.faucet_amount.min(remaining);
+    require!(amount > 0, ErrorCode::FaucetLimitReached);
+    require!(state.faucet_reserve >= amount, ErrorCode::FaucetReserveEmpty);
+    Ok(amount)
+}
+
+/// Book a drip of `amount` (from next_drip) at slot `now`.
+pub fn drip(
+    state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    amount: u64,
+    now: u64,
+) -> Result<()> {
+    state.faucet_reserve = state
+        .faucet_reserve
+        .checked_sub(amount)
+        .ok_or(ErrorCode::FaucetReserveEmpty)?;
+    state.faucet_dripped = state
+        .faucet_dripped
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    state.total_claimable = state
+        .total_claimable
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.claimable_balance = player_state
+        .claimable_balance
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.faucet_received = player_state
+        .faucet_received
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.faucet_last_slot = now;
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! House P&L attributed per feature.
+//!
+//! `SlotsState.feature_stats` keeps lifetime wagered and paid lamports for
+//! each [`Feature`]:
+//!
+//! - BaseGame: the bet minus its jackpot contributions, and paytable wins;
+//! - Jackpots: the jackpot contributions, and jackpot wins;
+//! - Promos: nothing wagered; winner fee rebates, fee-funded overflow and
+//!   wager race prizes.
+//!
+//! Wagers are attributed at commit and taken back when a spin is voided,
+//! so BaseGame plus Jackpots wagered always equals `total_wagered`, and
+//! their paid sum equals `total_payout`. Promo payouts sit outside
+//! total_payout, so the feature nets add up to the house net after
+//! promotions.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, SlotsState};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    BaseGame,
+    Jackpots,
+    Promos,
+}
+
+pub const FEATURE_KINDS: usize = 3;
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct FeatureTotals {
+    pub wagered: u64,
+    pub paid: u64,
+}
+
+impl FeatureTotals {
+    /// House net: wagered minus paid.
+    pub fn net(&self) -> i128 {
+        self.wagered as i128 - self.paid as i128
+    }
+}
+
+/// Per-feature totals and their sum, as returned by get_feature_pnl.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeaturePnl {
+    /// Indexed by Feature.
+    pub features: [FeatureTotals; FEATURE_KINDS],
+    pub net: i128,
+}
+
+pub fn pnl(state: &SlotsState) -> FeaturePnl {
+    FeaturePnl {
+        features: state.feature_stats,
+        net: state.feature_stats.iter().map(FeatureTotals::net).sum(),
+    }
+}
+
+/// Split a committed bet between the base game and the jackpots.
+pub fn record_wager(state: &mut SlotsState, bet_amount: u64, jackpot_contribs: u64) -> Result<()> {
+    let base = bet_amount.saturating_sub(jackpot_contribs);
+    add(&mut state.feature_stats[Feature::BaseGame as usize].wagered, base)?;
+    add(&mut state.feature_stats[Feature::Jackpots as usize].wagered, jackpot_contribs)
+}
+
+/// Undo record_wager for a voided spin.
+pub fn unrecord_wager(state: &mut SlotsState, bet_amount: u64, jackpot_contribs: u64) {
+    let base = bet_amount.saturating_sub(jackpot_contribs);
+    let stats = &mut state.feature_stats;
+    let base_wagered = &mut stats[Feature::BaseGame as usize].wagered;
+    *base_wagered = base_wagered.saturating_sub(base);
+    let jackpot_wagered = &mut stats[Feature::Jackpots as usize].wagered;
+    *jackpot_wagered = jackpot_wagered.saturating_sub(jackpot_contribs);
+}
+
+pub fn record_paid(state: &mut SlotsState, feature: Feature, amount: u64) -> Result<()> {
+    add(&mut state.feature_stats[feature as usize].paid, amount)
+}
+
+fn add(counter: &mut u64, amount: u64) -> Result<()> {
+    *counter = counter.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::client::decode_feature_pnl;
+    use crate::test_util::{returned, simulate, zeroed_state, Game};
+    use crate::{instruction, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    fn totals(state: &SlotsState, feature: Feature) -> FeatureTotals {
+        state.feature_stats[feature as usize]
+    }
+
+    #[test]
+    fn a_bet_splits_between_the_base_game_and_the_jackpots_until_voided() {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let contribs: u64 = game.player.pending_jackpot_contribs.iter().sum();
+        assert!(contribs > 0);
+        assert_eq!(totals(&game.state, Feature::BaseGame).wagered, BET - contribs);
+        assert_eq!(totals(&game.state, Feature::Jackpots).wagered, contribs);
+
+        game.admin_void().unwrap();
+        assert_eq!(pnl(&game.state), FeaturePnl::default());
+    }
+
+    #[test]
+    fn feature_nets_sum_to_the_house_net() {
+        let mut game = Game::new();
+        game.play_where(BET, |s| s.total_payout > 2 * BET).unwrap();
+        for value in 0..12u8 {
+            game.request(BET).unwrap();
+            game.reveal([value.wrapping_mul(37); 32]);
+            game.settle().unwrap();
+        }
+        let state = &game.state;
+        let base = totals(state, Feature::BaseGame);
+        let jackpots = totals(state, Feature::Jackpots);
+        let promos = totals(state, Feature::Promos);
+
+        assert!(state.total_payout > 0);
+        assert_eq!(base.wagered + jackpots.wagered, state.total_wagered);
+        assert_eq!(base.paid + jackpots.paid, state.total_payout);
+        assert_eq!(promos.wagered, 0);
+        let house = state.total_wagered as i128 - state.total_payout as i128;
+        assert_eq!(pnl(state).net, house - promos.paid as i128);
+        assert_eq!(pnl(state).net, base.net() + jackpots.net() + promos.net());
+        game.verify().unwrap();
+    }
+
+    #[test]
+    fn the_view_and_the_client_decoder_agree() {
+        let mut game = Game::new();
+        game.play_where(BET, |s| s.total_payout > 0).unwrap();
+        let [mut state, ..] = game.fixtures();
+
+        simulate([&mut state], &instruction::GetFeaturePnl {}.data()).unwrap();
+        let viewed = returned::<FeaturePnl>();
+        assert_eq!(viewed, pnl(&game.state));
+        assert_eq!(decode_feature_pnl(&state.data), Some(viewed));
+        assert_eq!(decode_feature_pnl(&state.data[..8]), None);
+    }
+
+    #[test]
+    fn counters_refuse_to_wrap() {
+        let mut state = zeroed_state();
+        state.feature_stats[Feature::Promos as usize].paid = u64::MAX;
+        assert_eq!(
+            record_paid(&mut state, Feature::Promos, 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            record_wager(&mut state, u64::MAX, 0).and_then(|_| record_wager(&mut state, 1, 0)),
+            Err(ErrorCode::MathOverflow.into())
+        );
+
+        // An unwind never takes a counter below zero.
+        let mut state = zeroed_state();
+        unrecord_wager(&mut state, BET, BET / 10);
+        assert_eq!(pnl(&state), FeaturePnl::default());
+    }
+}
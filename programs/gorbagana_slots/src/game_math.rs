@@ -0,0 +1,871 @@
+//! Pure outcome derivation shared by the on-chain settle path and
+//! off-chain verifiers. Nothing in here touches accounts or the clock.
+
+use crate::{JackpotPool, JackpotsConfig, PayTable, ReelWeights, SYMBOL_COUNT, SYMBOL_PAYOUTS_3OAK};
+
+pub const JACKPOT_TIER_MINI: u8 = 0;
+pub const JACKPOT_TIER_MAJOR: u8 = 1;
+pub const JACKPOT_TIER_GRAND: u8 = 2;
+
+/// Highest 3-of-a-kind multiplier in the paytable.
+pub const fn max_3oak_multiplier() -> u64 {
+    let mut max = 0;
+    let mut i = 0;
+    while i < SYMBOL_COUNT {
+        if SYMBOL_PAYOUTS_3OAK[i] > max {
+            max = SYMBOL_PAYOUTS_3OAK[i];
+        }
+        i += 1;
+    }
+    max
+}
+
+/// Paylines of the 3x3 grid as (row, reel): the three rows, then the two
+/// diagonals. Row 0 is the single-line game's reels.
+pub const MAX_PAYLINES: u8 = 5;
+pub const PAYLINES: [[(usize, usize); 3]; MAX_PAYLINES as usize] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(2, 0), (1, 1), (0, 2)],
+];
+
+/// Most spins one request_spins commit may play off its reveal.
+pub const MAX_SPINS_PER_COMMIT: u8 = 10;
+
+/// Everything a settlement decides from one VRF reveal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpinOutcome {
+    pub symbols: [u8; 3],
+    /// Paytable win before any cap is applied.
+    pub base_payout_full: u64,
+    /// Matching reels behind the base win: 3, 2, or 0 for no win. Best
+    /// line of a multi-line spin.
+    pub match_count: u8,
+    /// Paytable win after the affordability cap.
+    pub base_payout: u64,
+    /// Raw jackpot roll in [0, hit_weight_total), if a roll happened.
+    pub jackpot_roll: Option<u32>,
+    /// Tier awarded (0=mini, 1=major, 2=grand), only when jackpot_payout > 0.
+    pub jackpot_tier: Option<u8>,
+    /// The tier was awarded by its must-hit-by ceiling, not the roll.
+    pub jackpot_forced: bool,
+    pub jackpot_payout: u64,
+    pub total_payout: u64,
+}
+
+/// What a settle may pay. `base` caps the base win: pool headroom outside
+/// the jackpot reserves, within the payout cap. `total` caps base plus
+/// jackpot: the payout cap. A jackpot pays out of its own reserve, so it
+/// only needs what `total` leaves after the base win.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutLimits {
+    pub total: u64,
+    pub base: u64,
+}
+
+impl PayoutLimits {
+    /// `max` for both: one budget shared by base win and jackpot.
+    pub const fn uniform(max: u64) -> Self {
+        PayoutLimits {
+            total: max,
+            base: max,
+        }
+    }
+
+    /// Limits for a settle with `headroom` of base pool and `payout_cap`.
+    pub fn for_settle(headroom: u64, payout_cap: u64) -> Self {
+        PayoutLimits {
+            total: payout_cap,
+            base: headroom.min(payout_cap),
+        }
+    }
+}
+
+/// A base win after the caps, and what the payout cap leaves for a
+/// jackpot on the same spin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CappedPayout {
+    pub base_payout: u64,
+    pub remaining_for_jackpot: u64,
+}
+
+/// The cap logic every settle path and simulate_payout share: the base
+/// win is cut to `limits.base`, and a jackpot may only use what
+/// `limits.total` leaves after it.
+pub fn compute_capped_payout(base_payout_full: u64, limits: PayoutLimits) -> CappedPayout {
+    let base_payout = core::cmp::min(base_payout_full, limits.base);
+    CappedPayout {
+        base_payout,
+        remaining_for_jackpot: limits.total.saturating_sub(base_payout),
+    }
+}
+
+/// Simple LCG-based PRNG to expand a single VRF seed
+/// into multiple 64-bit random values.
+pub fn next_random_u64(seed: &mut u64) -> u64 {
+    const A: u64 = 6364136223846793005;
+    const C: u64 = 1;
+    *seed = seed.wrapping_mul(A).wrapping_add(C);
+    *seed
+}
+
+/// sha256 of the full 32 revealed bytes, published in SpinSettled and the
+/// receipt so an archived reveal can be checked against the settle.
+pub fn vrf_hash(random_bytes: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hash(random_bytes).to_bytes()
+}
+
+/// First 8 revealed bytes, published in clear alongside the hash.
+pub fn vrf_prefix(random_bytes: &[u8; 32]) -> [u8; 8] {
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&random_bytes[0..8]);
+    prefix
+}
+
+/// Whether `random_bytes` is the reveal a settle published `hash` for.
+pub fn reveal_matches(hash: &[u8; 32], random_bytes: &[u8; 32]) -> bool {
+    vrf_hash(random_bytes) == *hash
+}
+
+/// Turn the first 8 revealed bytes into the u64 PRNG seed.
+pub fn seed_from_vrf(random_bytes: &[u8; 32]) -> u64 {
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&random_bytes[0..8]);
+    u64::from_le_bytes(head)
+}
+
+/// Sample a reel symbol index [0, SYMBOL_COUNT) from `reels`. A strip
+/// with no weight always lands symbol 0; set_reel_config refuses one.
+pub fn generate_weighted_symbol(random_u64: u64, reels: &ReelWeights) -> u8 {
+    if reels.total_weight == 0 {
+        return 0;
+    }
+    let mut r = random_u64 % reels.total_weight;
+    for (idx, &w) in reels.weights.iter().enumerate() {
+        if r < w {
+            return idx as u8;
+        }
+        r -= w;
+    }
+    (SYMBOL_COUNT - 1) as u8
+}
+
+/// Draw the three reels, in order, from the PRNG.
+pub fn spin_symbols(seed: &mut u64, reels: &ReelWeights) -> [u8; 3] {
+    let s1 = generate_weighted_symbol(next_random_u64(seed), reels);
+    let s2 = generate_weighted_symbol(next_random_u64(seed), reels);
+    let s3 = generate_weighted_symbol(next_random_u64(seed), reels);
+    [s1, s2, s3]
+}
+
+/// Draw the 3x3 grid row by row; row 0 is what spin_symbols draws.
+pub fn spin_grid(seed: &mut u64, reels: &ReelWeights) -> [[u8; 3]; 3] {
+    let row0 = spin_symbols(seed, reels);
+    let row1 = spin_symbols(seed, reels);
+    let row2 = spin_symbols(seed, reels);
+    [row0, row1, row2]
+}
+
+/// Symbols under payline `line`.
+pub fn line_symbols(grid: &[[u8; 3]; 3], line: usize) -> [u8; 3] {
+    PAYLINES[line].map(|(row, reel)| grid[row][reel])
+}
+
+/// Win for `count` matching `symbol`s on a `bet_amount` bet.
+pub fn evaluate_match(symbol: u8, count: u8, bet_amount: u64, paytable: &PayTable) -> u64 {
+    bet_amount.saturating_mul(paytable.multiplier(symbol, count))
+}
+
+/// Win on one line and the match that paid it: 3 of a kind, else exactly
+/// 2 of a kind (the odd reel out may be anywhere). The `wild` symbol
+/// (NO_WILD for none) matches any other, and the line pays the best match
+/// it completes: [wild, 5, 5] and [wild, wild, 5] pay symbol 5's 3 of a
+/// kind, [wild, 5, 6] the better of 5's and 6's 2 of a kind. Three wilds
+/// pay the wild's own 3 of a kind. Returns (0, 0) for no win, including a
+/// 2-of-a-kind the table does not pay.
+pub fn calculate_payout(
+    symbols: [u8; 3],
+    bet_amount: u64,
+    paytable: &PayTable,
+    wild: u8,
+) -> (u64, u8) {
+    if symbols.iter().any(|&s| s as usize >= SYMBOL_COUNT) {
+        return (0, 0);
+    }
+
+    let all_wild = symbols.iter().all(|&s| s == wild);
+    let mut best = (0, 0);
+    for &symbol in &symbols {
+        if symbol == wild && !all_wild {
+            continue;
+        }
+        let count = symbols.iter().filter(|&&s| s == symbol || s == wild).count() as u8;
+        if count < 2 {
+            continue;
+        }
+        let payout = evaluate_match(symbol, count, bet_amount, paytable);
+        if payout > best.0 {
+            best = (payout, count);
+        }
+    }
+    best
+}
+
+/// 3-of-a-kind win under `paytable`, ignoring its other columns and any
+/// wild. Settlement goes through calculate_payout.
+pub fn calculate_payout_3oak(symbols: [u8; 3], bet_amount: u64, paytable: &PayTable) -> u64 {
+    let [s1, s2, s3] = symbols;
+
+    if s1 as usize >= SYMBOL_COUNT
+        || s2 as usize >= SYMBOL_COUNT
+        || s3 as usize >= SYMBOL_COUNT
+    {
+        return 0;
+    }
+
+    if s1 == s2 && s2 == s3 {
+        return evaluate_match(s1, 3, bet_amount, paytable);
+    }
+
+    0
+}
+
+pub fn jackpot_pool(jackpots: &JackpotsConfig, tier: u8) -> &JackpotPool {
+    match tier {
+        JACKPOT_TIER_MINI => &jackpots.mini,
+        JACKPOT_TIER_MAJOR => &jackpots.major,
+        _ => &jackpots.grand,
+    }
+}
+
+pub fn jackpot_pool_mut(jackpots: &mut JackpotsConfig, tier: u8) -> &mut JackpotPool {
+    match tier {
+        JACKPOT_TIER_MINI => &mut jackpots.mini,
+        JACKPOT_TIER_MAJOR => &mut jackpots.major,
+        _ => &mut jackpots.grand,
+    }
+}
+
+/// Full jackpot odds, as a fraction of JACKPOT_ODDS_ONE.
+pub const JACKPOT_ODDS_ONE: u64 = 1 << 32;
+
+/// Share of the full jackpot odds a stake of `bet_amount` gets:
+/// `min(bet, qualifying_bet) / qualifying_bet`, scaled to
+/// JACKPOT_ODDS_ONE and rounded down (error below 2^-32). A zero
+/// `qualifying_bet` gives every bet full odds.
+pub fn jackpot_odds(bet_amount: u64, qualifying_bet: u64) -> u64 {
+    if qualifying_bet == 0 || bet_amount >= qualifying_bet {
+        return JACKPOT_ODDS_ONE;
+    }
+    (((bet_amount as u128) << 32) / qualifying_bet as u128) as u64
+}
+
+/// Roll which jackpot tier (if any) the weighted draw selects, with every
+/// tier's chance scaled by `odds` (see jackpot_odds). Returns (roll,
+/// tier). Consumes one PRNG value when hit weights are set.
+///
+/// At full odds a tier hits when `r = x % hit_total` falls in its weight
+/// band. Below it the same PRNG value `x` is read as the fraction
+/// `x / 2^64` of the weight range: tier bands shrink to `odds / 2^32` of
+/// their width, all in integers.
+pub fn roll_jackpot_tier(
+    seed: &mut u64,
+    jackpots: &JackpotsConfig,
+    odds: u64,
+) -> Option<(u32, Option<u8>)> {
+    let hit_total = jackpots.hit_weight_total;
+    if hit_total == 0 {
+        return None;
+    }
+
+    let x = next_random_u64(seed);
+    let r = (x as u32) % hit_total;
+    let in_band = |cumulative: u32| {
+        if odds >= JACKPOT_ODDS_ONE {
+            return r < cumulative;
+        }
+        // x / 2^64 * hit_total < cumulative * odds / 2^32
+        (x as u128) * (hit_total as u128) < ((cumulative as u128) * (odds as u128)) << 32
+    };
+
+    let mut acc = jackpots.mini.hit_weight;
+    if in_band(acc) {
+        return Some((r, Some(JACKPOT_TIER_MINI)));
+    }
+    acc += jackpots.major.hit_weight;
+    if in_band(acc) {
+        return Some((r, Some(JACKPOT_TIER_MAJOR)));
+    }
+    acc += jackpots.grand.hit_weight;
+    if in_band(acc) {
+        return Some((r, Some(JACKPOT_TIER_GRAND)));
+    }
+    Some((r, None))
+}
+
+/// Amount a rolled pool pays: the FULL amount if affordable, else nothing
+/// (no partial jackpots and no silent "burning" of amounts).
+pub fn jackpot_award(pool: &JackpotPool, max_jackpot_payout: u64) -> u64 {
+    if pool.amount == 0 || pool.amount > max_jackpot_payout {
+        return 0;
+    }
+    pool.amount
+}
+
+/// Equal split of a tier's snapshot amount between `winners` hits in one
+/// batch. Returns (share per winner, remainder left in the pool), so
+/// `share * winners + remainder == snapshot` always holds.
+pub fn split_jackpot(snapshot: u64, winners: u32) -> (u64, u64) {
+    if winners == 0 {
+        return (0, snapshot);
+    }
+    let share = snapshot / winners as u64;
+    (share, snapshot - share * winners as u64)
+}
+
+/// Outcome with chosen symbols and jackpot tier, capped exactly like
+/// derive_outcome. Only reachable through the QA force_outcome path.
+pub fn forced_outcome(
+    symbols: [u8; 3],
+    jackpot_tier: Option<u8>,
+    bet_amount: u64,
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    paytable: &PayTable,
+    wild: u8,
+) -> SpinOutcome {
+    let (base_payout_full, match_count) = calculate_payout(symbols, bet_amount, paytable, wild);
+    let CappedPayout {
+        base_payout,
+        remaining_for_jackpot,
+    } = compute_capped_payout(base_payout_full, limits);
+    let mut outcome = SpinOutcome {
+        symbols,
+        base_payout_full,
+        match_count,
+        base_payout,
+        total_payout: base_payout,
+        ..SpinOutcome::default()
+    };
+
+    if let Some(tier) = jackpot_tier {
+        let paid = jackpot_award(jackpot_pool(jackpots, tier), remaining_for_jackpot);
+        if paid > 0 {
+            outcome.jackpot_tier = Some(tier);
+            outcome.jackpot_payout = paid;
+            outcome.total_payout = base_payout.saturating_add(paid);
+        }
+    }
+    outcome
+}
+
+/// Full outcome for one reveal, given what the house can afford this
+/// spin (see PayoutLimits), the stake's jackpot_odds and the tiers its
+/// must-hit-by ceilings force (a bit per tier, see must_hit.rs).
+#[allow(clippy::too_many_arguments)]
+pub fn derive_outcome(
+    random_bytes: &[u8; 32],
+    bet_amount: u64,
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    jackpot_odds: u64,
+    forced_jackpots: u8,
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    wild: u8,
+) -> SpinOutcome {
+    let mut seed = seed_from_vrf(random_bytes);
+    let symbols = spin_symbols(&mut seed, reels);
+    let win = calculate_payout(symbols, bet_amount, paytable, wild);
+    capped_outcome(&mut seed, symbols, win, limits, jackpots, jackpot_odds, forced_jackpots)
+}
+
+/// A multi-line spin's grid and per-line wins, with its capped outcome.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LinesOutcome {
+    pub grid: [[u8; 3]; 3],
+    /// Paytable win of each payline before caps; 0 past the lines played.
+    pub line_payouts: [u64; MAX_PAYLINES as usize],
+    /// `symbols` is row 0; the base win is the sum of the line wins.
+    pub outcome: SpinOutcome,
+}
+
+/// Outcome of a spin on `lines` paylines (2..=MAX_PAYLINES) at
+/// `line_bet` each. The caps apply to the summed win, exactly as to a
+/// single line's. A single line is derive_outcome, which draws only row 0.
+#[allow(clippy::too_many_arguments)]
+pub fn derive_outcome_lines(
+    random_bytes: &[u8; 32],
+    line_bet: u64,
+    lines: u8,
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    jackpot_odds: u64,
+    forced_jackpots: u8,
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    wild: u8,
+) -> LinesOutcome {
+    let mut seed = seed_from_vrf(random_bytes);
+    let grid = spin_grid(&mut seed, reels);
+
+    let mut line_payouts = [0u64; MAX_PAYLINES as usize];
+    let mut base_payout_full = 0u64;
+    let mut match_count = 0u8;
+    for (line, payout) in line_payouts.iter_mut().enumerate().take(lines as usize) {
+        let (win, count) = calculate_payout(line_symbols(&grid, line), line_bet, paytable, wild);
+        *payout = win;
+        base_payout_full = base_payout_full.saturating_add(win);
+        match_count = match_count.max(count);
+    }
+
+    LinesOutcome {
+        grid,
+        line_payouts,
+        outcome: capped_outcome(
+            &mut seed,
+            grid[0],
+            (base_payout_full, match_count),
+            limits,
+            jackpots,
+            jackpot_odds,
+            forced_jackpots,
+        ),
+    }
+}
+
+/// A multi-spin commit's reels and per-spin payouts, with the spins
+/// summed into one outcome.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpinsOutcome {
+    /// Reels of each spin in draw order; zero past the spins played.
+    pub symbols: [[u8; 3]; MAX_SPINS_PER_COMMIT as usize],
+    /// What each spin paid after its caps, jackpot included.
+    pub payouts: [u64; MAX_SPINS_PER_COMMIT as usize],
+    /// `symbols` is the first spin's, `match_count` the best. The base
+    /// wins are summed, `base_payout_full` each within the spin's payout
+    /// cap, so only the pool limit shows as a shortfall.
+    pub outcome: SpinOutcome,
+}
+
+/// Outcome of `spins` (2..=MAX_SPINS_PER_COMMIT) spins at `spin_bet`
+/// each, drawn one after the other from the one seed. `limits.total` is
+/// the whole commit's cap: each spin may pay its share of it, and the
+/// base wins together stay within `limits.base`. Only one jackpot is
+/// awarded per commit; the spins after a hit draw against empty tiers.
+#[allow(clippy::too_many_arguments)]
+pub fn derive_outcome_spins(
+    random_bytes: &[u8; 32],
+    spin_bet: u64,
+    spins: u8,
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    jackpot_odds: u64,
+    forced_jackpots: u8,
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    wild: u8,
+) -> SpinsOutcome {
+    let mut seed = seed_from_vrf(random_bytes);
+    let spin_cap = limits.total / spins.max(1) as u64;
+    let mut jackpots = jackpots.clone();
+    let mut forced_jackpots = forced_jackpots;
+    let mut base_left = limits.base;
+
+    let mut drawn = SpinsOutcome::default();
+    for spin in 0..(spins as usize).min(MAX_SPINS_PER_COMMIT as usize) {
+        let symbols = spin_symbols(&mut seed, reels);
+        let win = calculate_payout(symbols, spin_bet, paytable, wild);
+        let spin_limits = PayoutLimits::for_settle(base_left, spin_cap);
+        let outcome = capped_outcome(
+            &mut seed,
+            symbols,
+            win,
+            spin_limits,
+            &jackpots,
+            jackpot_odds,
+            forced_jackpots,
+        );
+        base_left -= outcome.base_payout;
+        drawn.symbols[spin] = symbols;
+        drawn.payouts[spin] = outcome.total_payout;
+
+        let total = &mut drawn.outcome;
+        total.base_payout_full = total
+            .base_payout_full
+            .saturating_add(outcome.base_payout_full.min(spin_cap));
+        total.match_count = total.match_count.max(outcome.match_count);
+        total.base_payout = total.base_payout.saturating_add(outcome.base_payout);
+        total.total_payout = total.total_payout.saturating_add(outcome.total_payout);
+        total.jackpot_roll = total.jackpot_roll.or(outcome.jackpot_roll);
+        if let Some(tier) = outcome.jackpot_tier {
+            total.jackpot_roll = outcome.jackpot_roll;
+            total.jackpot_tier = Some(tier);
+            total.jackpot_forced = outcome.jackpot_forced;
+            total.jackpot_payout = outcome.jackpot_payout;
+            for tier in [JACKPOT_TIER_MINI, JACKPOT_TIER_MAJOR, JACKPOT_TIER_GRAND] {
+                jackpot_pool_mut(&mut jackpots, tier).amount = 0;
+            }
+            forced_jackpots = 0;
+        }
+    }
+    drawn.outcome.symbols = drawn.symbols[0];
+    drawn
+}
+
+/// Cap the drawn base win, then award the jackpot from what is left: a
+/// forced tier if one is affordable, else the roll. `win` is the uncapped
+/// base win and its match count.
+fn capped_outcome(
+    seed: &mut u64,
+    symbols: [u8; 3],
+    win: (u64, u8),
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    jackpot_odds: u64,
+    forced_jackpots: u8,
+) -> SpinOutcome {
+    let (base_payout_full, match_count) = win;
+    // First cap the base payout, then whatever remains may fund a jackpot.
+    let CappedPayout {
+        base_payout,
+        remaining_for_jackpot,
+    } = compute_capped_payout(base_payout_full, limits);
+
+    let mut outcome = SpinOutcome {
+        symbols,
+        base_payout_full,
+        match_count,
+        base_payout,
+        total_payout: base_payout,
+        ..SpinOutcome::default()
+    };
+
+    if remaining_for_jackpot == 0 {
+        return outcome;
+    }
+
+    // A forced tier pays without a roll, biggest first. One the spin
+    // cannot afford stays forced for a later spin and the roll goes on.
+    let forced = [JACKPOT_TIER_GRAND, JACKPOT_TIER_MAJOR, JACKPOT_TIER_MINI]
+        .into_iter()
+        .filter(|tier| forced_jackpots & (1 << tier) != 0)
+        .map(|tier| (tier, jackpot_award(jackpot_pool(jackpots, tier), remaining_for_jackpot)))
+        .find(|&(_, paid)| paid > 0);
+    if let Some((tier, paid)) = forced {
+        outcome.jackpot_tier = Some(tier);
+        outcome.jackpot_forced = true;
+        outcome.jackpot_payout = paid;
+        outcome.total_payout = base_payout.saturating_add(paid);
+        return outcome;
+    }
+
+    if let Some((roll, tier)) = roll_jackpot_tier(seed, jackpots, jackpot_odds) {
+        outcome.jackpot_roll = Some(roll);
+        if let Some(tier) = tier {
+            let paid = jackpot_award(jackpot_pool(jackpots, tier), remaining_for_jackpot);
+            if paid > 0 {
+                outcome.jackpot_tier = Some(tier);
+                outcome.jackpot_payout = paid;
+                outcome.total_payout = base_payout.saturating_add(paid);
+            }
+        }
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NO_WILD, PAYTABLE, REEL_WEIGHTS, SYMBOL_WEIGHTS, TOTAL_WEIGHT};
+
+    #[test]
+    fn weighted_symbol_follows_weights() {
+        let mut counts = [0u64; SYMBOL_COUNT];
+        for r in 0..TOTAL_WEIGHT {
+            counts[generate_weighted_symbol(r, &REEL_WEIGHTS) as usize] += 1;
+        }
+        assert_eq!(counts, SYMBOL_WEIGHTS);
+        assert_eq!(SYMBOL_WEIGHTS.iter().sum::<u64>(), TOTAL_WEIGHT);
+    }
+
+    #[test]
+    fn weighted_symbol_bucket_edges() {
+        let reels = &REEL_WEIGHTS;
+        assert_eq!(generate_weighted_symbol(0, reels), 0);
+        assert_eq!(generate_weighted_symbol(1, reels), 1);
+        assert_eq!(generate_weighted_symbol(2, reels), 1);
+        assert_eq!(generate_weighted_symbol(3, reels), 2);
+        assert_eq!(generate_weighted_symbol(TOTAL_WEIGHT - 1, reels), (SYMBOL_COUNT - 1) as u8);
+        assert_eq!(generate_weighted_symbol(TOTAL_WEIGHT, reels), 0);
+        assert!((generate_weighted_symbol(u64::MAX, reels) as usize) < SYMBOL_COUNT);
+    }
+
+    #[test]
+    fn payout_3oak_pays_only_three_of_a_kind() {
+        for s in 0..SYMBOL_COUNT as u8 {
+            let want = SYMBOL_PAYOUTS_3OAK[s as usize] * 1_000;
+            assert_eq!(calculate_payout_3oak([s, s, s], 1_000, &PAYTABLE), want);
+            assert_eq!(calculate_payout([s, s, s], 1_000, &PAYTABLE, NO_WILD), (want, 3));
+        }
+        assert_eq!(calculate_payout_3oak([0, 0, 1], 1_000, &PAYTABLE), 0);
+        assert_eq!(calculate_payout_3oak([0, 1, 2], 1_000, &PAYTABLE), 0);
+        assert_eq!(calculate_payout_3oak([0, 0, 0], 0, &PAYTABLE), 0);
+    }
+
+    #[test]
+    fn wild_completes_every_placement() {
+        const W: u8 = 0;
+        let mut table = PAYTABLE;
+        table.two_of_a_kind = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let three = |s: u8| (SYMBOL_PAYOUTS_3OAK[s as usize] * 10, 3);
+        let two = |s: u8| (table.two_of_a_kind[s as usize] * 10, 2);
+        let pay = |symbols| calculate_payout(symbols, 10, &table, W);
+
+        assert_eq!(pay([W, W, W]), three(W));
+        for s in 1..SYMBOL_COUNT as u8 {
+            // One wild with a pair, and two wilds, anywhere on the line.
+            for line in [[W, s, s], [s, W, s], [s, s, W], [W, W, s], [W, s, W], [s, W, W]] {
+                assert_eq!(pay(line), three(s), "{line:?}");
+            }
+            assert_eq!(pay([s, s, s]), three(s));
+            for t in 1..SYMBOL_COUNT as u8 {
+                if t == s {
+                    continue;
+                }
+                // One wild between two strangers pairs with the better.
+                let want = two(s).max(two(t));
+                for line in [[W, s, t], [s, W, t], [s, t, W]] {
+                    assert_eq!(pay(line), want, "{line:?}");
+                }
+                // No wild: plain pairs, as without the feature.
+                assert_eq!(pay([s, s, t]), two(s));
+                assert_eq!(pay([t, s, s]), two(s));
+            }
+        }
+        // The same lines without a wild only pay literal matches.
+        assert_eq!(calculate_payout([W, 5, 5], 10, &PAYTABLE, NO_WILD), (0, 0));
+        assert_eq!(calculate_payout([W, W, 5], 10, &PAYTABLE, NO_WILD), (0, 0));
+        assert_eq!(calculate_payout([W, W, W], 10, &PAYTABLE, NO_WILD), three(W));
+    }
+
+    #[test]
+    fn reel_config_tables_are_parameters() {
+        // A two-symbol strip: symbol 11 on 3 of 4 stops, symbol 5 on 1.
+        let mut weights = [0u64; SYMBOL_COUNT];
+        weights[5] = 1;
+        weights[11] = 3;
+        let reels = ReelWeights::new(weights);
+        assert_eq!(reels.total_weight, 4);
+        assert_eq!(generate_weighted_symbol(0, &reels), 5);
+        assert_eq!(generate_weighted_symbol(1, &reels), 11);
+        assert_eq!(generate_weighted_symbol(3, &reels), 11);
+        assert_eq!(generate_weighted_symbol(4, &reels), 5);
+        // No weight at all never divides by zero.
+        let empty = ReelWeights::new([0; SYMBOL_COUNT]);
+        assert_eq!(generate_weighted_symbol(7, &empty), 0);
+
+        let mut three = [0u64; SYMBOL_COUNT];
+        three[11] = 40;
+        let table = PayTable::from_3oak(three);
+        assert_eq!(calculate_payout_3oak([11, 11, 11], 1_000, &table), 40_000);
+        assert_eq!(calculate_payout_3oak([0, 0, 0], 1_000, &table), 0);
+    }
+
+    #[test]
+    fn payout_3oak_rejects_bad_symbols_and_saturates() {
+        let bad = SYMBOL_COUNT as u8;
+        assert_eq!(calculate_payout_3oak([bad, bad, bad], 1_000, &PAYTABLE), 0);
+        assert_eq!(calculate_payout_3oak([0, 0, bad], 1_000, &PAYTABLE), 0);
+        assert_eq!(calculate_payout_3oak([0, 0, 0], u64::MAX, &PAYTABLE), u64::MAX);
+    }
+
+    #[test]
+    fn capped_payout_with_pool_below_threshold() {
+        // No base-pool headroom: the base win is cut to nothing and the
+        // whole payout cap is left for a jackpot paid from its own tier.
+        let limits = PayoutLimits::for_settle(0, 50_000);
+        assert_eq!(
+            compute_capped_payout(20_000, limits),
+            CappedPayout {
+                base_payout: 0,
+                remaining_for_jackpot: 50_000,
+            }
+        );
+    }
+
+    #[test]
+    fn capped_payout_exactly_at_cap() {
+        let limits = PayoutLimits::for_settle(1_000_000, 50_000);
+        assert_eq!(
+            compute_capped_payout(50_000, limits),
+            CappedPayout {
+                base_payout: 50_000,
+                remaining_for_jackpot: 0,
+            }
+        );
+        assert_eq!(compute_capped_payout(50_001, limits).base_payout, 50_000);
+        assert_eq!(
+            compute_capped_payout(49_999, limits),
+            CappedPayout {
+                base_payout: 49_999,
+                remaining_for_jackpot: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn capped_payout_matches_derived_outcome() {
+        let limits = PayoutLimits::for_settle(30_000, 50_000);
+        let jackpots = JackpotsConfig::default();
+        let outcome =
+            forced_outcome([0, 0, 0], None, 1_000_000, limits, &jackpots, &PAYTABLE, NO_WILD);
+        let capped = compute_capped_payout(outcome.base_payout_full, limits);
+        assert_eq!(outcome.base_payout, capped.base_payout);
+        assert_eq!(capped.remaining_for_jackpot, 20_000);
+    }
+
+    /// splitmix64 of `i`: a seed per simulated settle, as distinct
+    /// reveals would give.
+    fn spread(i: u64) -> u64 {
+        let mut z = i.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    #[test]
+    fn jackpot_hit_rate_scales_with_the_bet() {
+        const ROLLS: u64 = 200_000;
+        const QUALIFYING_BET: u64 = 1_000_000;
+        let weights = [3_000u32, 1_500, 500];
+        let mut jackpots = JackpotsConfig::default();
+        jackpots.mini.hit_weight = weights[0];
+        jackpots.major.hit_weight = weights[1];
+        jackpots.grand.hit_weight = weights[2];
+        jackpots.hit_weight_total = 100_000;
+
+        let bets = [
+            (QUALIFYING_BET, 1.0),
+            (QUALIFYING_BET / 2, 0.5),
+            (QUALIFYING_BET / 4, 0.25),
+        ];
+        for (bet, share) in bets {
+            let odds = jackpot_odds(bet, QUALIFYING_BET);
+            let mut hits = [0u64; 3];
+            for i in 0..ROLLS {
+                let mut seed = spread(i);
+                if let Some((_, Some(tier))) = roll_jackpot_tier(&mut seed, &jackpots, odds) {
+                    hits[tier as usize] += 1;
+                }
+            }
+            // Each tier within four standard deviations of its rate.
+            for (tier, weight) in weights.into_iter().enumerate() {
+                let p = share * weight as f64 / jackpots.hit_weight_total as f64;
+                let expected = p * ROLLS as f64;
+                let sd = (expected * (1.0 - p)).sqrt();
+                let off = (hits[tier] as f64 - expected).abs();
+                assert!(
+                    off < 4.0 * sd,
+                    "bet {bet} tier {tier}: {} hits, {expected} expected",
+                    hits[tier]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn forced_jackpot_pays_the_biggest_affordable_tier() {
+        let mut jackpots = JackpotsConfig::default();
+        jackpots.major.amount = 500;
+        jackpots.grand.amount = 10_000;
+        // No hit weights: only a forced tier can pay.
+        let draw = |cap: u64, forced: u8| {
+            let limits = PayoutLimits::for_settle(0, cap);
+            let odds = JACKPOT_ODDS_ONE;
+            let reels = &REEL_WEIGHTS;
+            derive_outcome(&[7; 32], 0, limits, &jackpots, odds, forced, reels, &PAYTABLE, NO_WILD)
+        };
+        let both = (1 << JACKPOT_TIER_MAJOR) | (1 << JACKPOT_TIER_GRAND);
+
+        let grand = draw(20_000, both);
+        assert_eq!(grand.jackpot_tier, Some(JACKPOT_TIER_GRAND));
+        assert!(grand.jackpot_forced);
+        assert_eq!((grand.jackpot_payout, grand.total_payout), (10_000, 10_000));
+        assert_eq!(grand.jackpot_roll, None);
+
+        // The grand is out of reach: the major goes instead.
+        let major = draw(5_000, both);
+        assert_eq!(major.jackpot_tier, Some(JACKPOT_TIER_MAJOR));
+        assert_eq!(major.jackpot_payout, 500);
+
+        let missed = draw(5_000, 1 << JACKPOT_TIER_GRAND);
+        assert_eq!((missed.jackpot_tier, missed.jackpot_forced), (None, false));
+        assert_eq!(draw(20_000, 0).jackpot_tier, None);
+    }
+
+    #[test]
+    fn multi_spin_caps_each_spin_and_pays_one_jackpot() {
+        let mut jackpots = JackpotsConfig::default();
+        jackpots.mini.amount = 400;
+        let spins = MAX_SPINS_PER_COMMIT;
+        let reels = &REEL_WEIGHTS;
+        let draw = |limits: PayoutLimits, forced: u8| {
+            derive_outcome_spins(
+                &[3; 32],
+                1_000,
+                spins,
+                limits,
+                &jackpots,
+                JACKPOT_ODDS_ONE,
+                forced,
+                reels,
+                &PAYTABLE,
+                NO_WILD,
+            )
+        };
+
+        // The spins replay the seed's draws in order.
+        let drawn = draw(PayoutLimits::uniform(u64::MAX), 0);
+        let mut seed = seed_from_vrf(&[3; 32]);
+        assert_eq!(drawn.symbols[0], spin_symbols(&mut seed, reels));
+        assert_eq!(drawn.outcome.symbols, drawn.symbols[0]);
+        let paid: u64 = drawn.payouts.iter().sum();
+        assert_eq!(paid, drawn.outcome.total_payout);
+
+        // A spin never pays more than its share of the commit's cap.
+        let capped = draw(PayoutLimits::for_settle(u64::MAX, 10 * 500), 0);
+        assert!(capped.payouts.iter().all(|&p| p <= 500));
+
+        // A forced mini pays once, on the first spin; the rest find it empty.
+        let forced = draw(PayoutLimits::for_settle(0, 10 * 1_000), 1 << JACKPOT_TIER_MINI);
+        assert_eq!(forced.payouts[0], 400);
+        assert_eq!(forced.outcome.jackpot_tier, Some(JACKPOT_TIER_MINI));
+        assert_eq!(forced.outcome.total_payout, 400);
+        assert_eq!(forced.outcome.base_payout, 0);
+    }
+
+    #[test]
+    fn next_random_u64_steps_the_lcg() {
+        let mut seed = 0u64;
+        assert_eq!(next_random_u64(&mut seed), 1);
+        assert_eq!(seed, 1);
+        assert_eq!(next_random_u64(&mut seed), 6_364_136_223_846_793_006);
+
+        let (mut a, mut b) = (42u64, 42u64);
+        let first: Vec<u64> = (0..8).map(|_| next_random_u64(&mut a)).collect();
+        let again: Vec<u64> = (0..8).map(|_| next_random_u64(&mut b)).collect();
+        assert_eq!(first, again);
+        assert_eq!(a, first[7]);
+    }
+
+    #[test]
+    fn next_random_u64_wraps() {
+        let mut seed = u64::MAX;
+        let want = u64::MAX.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        assert_eq!(next_random_u64(&mut seed), want);
+    }
+}
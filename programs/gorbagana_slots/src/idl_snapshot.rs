@@ -0,0 +1,233 @@
+//! Golden check of the program's IDL.
+//!
+//! The test builds the IDL the way `anchor idl build` does: it runs the
+//! `__anchor_private_print_idl*` tests of an `idl-build` build (in its own
+//! target directory, so the outer cargo's lock is not in the way) and
+//! assembles the printed sections into one document. Type paths are cut
+//! to their last segment where that is unambiguous, as Anchor does, so
+//! moving a type between modules leaves the IDL unchanged. The result is
+//! compared with `idl/gorbagana_slots.json`; any renamed instruction,
+//! account, argument, event, error or type fails it. Rerun with
+//! `UPDATE_IDL=1` to regenerate the file after a deliberate change.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde_json::{Map, Value};
+
+/// Set to rewrite the committed IDL from the current program.
+const UPDATE_VAR: &str = "UPDATE_IDL";
+const COMMITTED_IDL: &str = include_str!("../idl/gorbagana_slots.json");
+
+/// stdout of the IDL print tests.
+fn print_idl_sections() -> String {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("../../target"))
+        .join("idl-build");
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args([
+            "test",
+            "__anchor_private_print_idl",
+            "--features",
+            "idl-build",
+        ])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .args(["--", "--show-output", "--quiet"])
+        .env("ANCHOR_IDL_BUILD_PROGRAM_PATH", &manifest_dir)
+        .env("ANCHOR_IDL_BUILD_NO_DOCS", "FALSE")
+        .env("ANCHOR_IDL_BUILD_RESOLUTION", "TRUE")
+        .env("ANCHOR_IDL_BUILD_SKIP_LINT", "FALSE")
+        .current_dir(&manifest_dir)
+        .output()
+        .expect("run the idl-build tests");
+    assert!(
+        output.status.success(),
+        "idl-build failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("utf-8 output")
+}
+
+/// The printed sections as (kind, body) pairs.
+fn sections(stdout: &str) -> Vec<(&str, String)> {
+    let mut out = Vec::new();
+    let mut open: Option<(&str, Vec<&str>)> = None;
+    for line in stdout.lines() {
+        if let Some(kind) = line
+            .strip_prefix("--- IDL begin ")
+            .and_then(|rest| rest.strip_suffix(" ---"))
+        {
+            open = Some((kind, Vec::new()));
+        } else if line.starts_with("--- IDL end ") {
+            let (kind, lines) = open.take().expect("section was opened");
+            out.push((kind, lines.join("\n")));
+        } else if let Some((_, lines)) = open.as_mut() {
+            lines.push(line);
+        }
+    }
+    out
+}
+
+fn parse(body: &str) -> Value {
+    serde_json::from_str(body).expect("section is JSON")
+}
+
+/// Merge the sections into one IDL, as the Anchor CLI does.
+fn assemble(stdout: &str) -> Value {
+    let mut program = None;
+    let mut address = String::new();
+    let (mut constants, mut events, mut errors) = (Vec::new(), Vec::new(), Value::Null);
+    let mut types = BTreeMap::new();
+    for (kind, body) in sections(stdout) {
+        match kind {
+            "address" => address = body.chars().filter(|c| c.is_alphanumeric()).collect(),
+            "const" => constants.push(parse(&body)),
+            "event" => {
+                let mut print = parse(&body);
+                events.push(print["event"].take());
+                for ty in print["types"].as_array_mut().into_iter().flatten() {
+                    types.insert(ty["name"].to_string(), ty.take());
+                }
+            }
+            "errors" => errors = parse(&body),
+            "program" => program = Some(parse(&body)),
+            other => panic!("unknown IDL section {other}"),
+        }
+    }
+
+    let mut idl = program.expect("program section printed");
+    for ty in idl["types"].as_array_mut().into_iter().flatten() {
+        types.insert(ty["name"].to_string(), ty.take());
+    }
+    idl["address"] = Value::String(address);
+    idl["constants"] = Value::Array(constants);
+    idl["events"] = Value::Array(events);
+    idl["errors"] = errors;
+    idl["types"] = Value::Array(types.into_values().collect());
+    for list in ["accounts", "constants", "events", "instructions", "types"] {
+        if let Some(items) = idl[list].as_array_mut() {
+            items.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        }
+    }
+    shorten_paths(idl)
+}
+
+fn is_path(s: &str) -> bool {
+    s.contains("::")
+        && s.split("::")
+            .all(|seg| !seg.is_empty() && seg.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+fn collect_paths<'a>(value: &'a Value, paths: &mut BTreeMap<&'a str, Vec<&'a str>>) {
+    match value {
+        Value::String(s) if is_path(s) => {
+            let name = s.rsplit("::").next().expect("path has a last segment");
+            let seen = paths.entry(name).or_default();
+            if !seen.contains(&s.as_str()) {
+                seen.push(s);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_paths(v, paths)),
+        Value::Object(map) => map.values().for_each(|v| collect_paths(v, paths)),
+        _ => {}
+    }
+}
+
+fn replace_paths(value: &mut Value, unique: &BTreeMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(name) = unique.get(s.as_str()) {
+                *s = name.clone();
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| replace_paths(v, unique)),
+        Value::Object(map) => map.values_mut().for_each(|v| replace_paths(v, unique)),
+        _ => {}
+    }
+}
+
+/// `crate::module::Type` becomes `Type` unless two paths share the name.
+fn shorten_paths(mut idl: Value) -> Value {
+    let mut paths = BTreeMap::new();
+    collect_paths(&idl, &mut paths);
+    let unique: BTreeMap<String, String> = paths
+        .into_iter()
+        .filter(|(_, full)| full.len() == 1)
+        .map(|(name, full)| (full[0].to_string(), name.to_string()))
+        .collect();
+    replace_paths(&mut idl, &unique);
+    idl
+}
+
+fn render(idl: &Value) -> String {
+    let mut out = serde_json::to_string_pretty(idl).expect("IDL serializes");
+    out.push('\n');
+    out
+}
+
+/// Top-level names that differ between two IDLs, per list.
+fn changed_names(old: &Value, new: &Value) -> Vec<String> {
+    let names = |idl: &Value, list: &str| -> Vec<(String, String)> {
+        idl[list]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|item| (item["name"].to_string(), item.to_string()))
+            .collect()
+    };
+    let mut out = Vec::new();
+    for list in [
+        "instructions",
+        "accounts",
+        "events",
+        "errors",
+        "types",
+        "constants",
+    ] {
+        let (old, new): (Map<_, _>, Map<_, _>) = (
+            names(old, list)
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+            names(new, list)
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+        );
+        for (name, item) in &new {
+            match old.get(name) {
+                None => out.push(format!("{list}: added {name}")),
+                Some(was) if was != item => out.push(format!("{list}: changed {name}")),
+                Some(_) => {}
+            }
+        }
+        for name in old.keys().filter(|name| !new.contains_key(*name)) {
+            out.push(format!("{list}: removed {name}"));
+        }
+    }
+    out
+}
+
+#[test]
+fn idl_matches_the_committed_snapshot() {
+    let idl = assemble(&print_idl_sections());
+    let fresh = render(&idl);
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/idl/gorbagana_slots.json");
+        std::fs::write(path, &fresh).expect("write IDL snapshot");
+        return;
+    }
+    if fresh != COMMITTED_IDL {
+        let committed: Value = serde_json::from_str(COMMITTED_IDL).unwrap_or(Value::Null);
+        panic!(
+            "IDL changed; if deliberate, rerun with {}=1 and commit idl/gorbagana_slots.json:\n{}",
+            UPDATE_VAR,
+            changed_names(&committed, &idl).join("\n")
+        );
+    }
+}
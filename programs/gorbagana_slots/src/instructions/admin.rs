@@ -0,0 +1,2217 @@
+//! Handlers of the admin instructions: initialisation, configuration,
+//! pausing, operators, machines and migrations.
+
+use crate::*;
+
+pub fn initialize(
+    ctx: Context<Initialize>,
+    authority: Pubkey,
+    exclusive_mint: Option<Pubkey>,
+    recovery_authority: Option<Pubkey>,
+    community_authority: Option<Pubkey>,
+    network_guard: Pubkey,
+) -> Result<()> {
+    require_deployer(&ctx.accounts.payer, &ctx.accounts.program_data)?;
+    qa::check_network_guard(
+        &network_guard,
+        ctx.accounts.payer.key,
+        IMMUTABLE_PROGRAM_DEPLOYER,
+    )?;
+
+    let slots_state = &mut ctx.accounts.slots_state;
+    slots_state.treasury = ctx.accounts.treasury.key();
+    slots_state.fee_vault = ctx.accounts.fee_vault.key();
+    slots_state.machine_id = machines::LEGACY_MACHINE_ID;
+    fund_vault_rent(
+        &ctx.accounts.system_program,
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.treasury.to_account_info(),
+    )?;
+    fund_vault_rent(
+        &ctx.accounts.system_program,
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.fee_vault.to_account_info(),
+    )?;
+    init_slots_state(
+        slots_state,
+        authority,
+        exclusive_mint,
+        recovery_authority,
+        community_authority,
+        network_guard,
+    )
+}
+
+pub fn initialize_machine(
+    ctx: Context<InitializeMachine>,
+    machine_id: [u8; 32],
+    authority: Pubkey,
+    exclusive_mint: Option<Pubkey>,
+    recovery_authority: Option<Pubkey>,
+    community_authority: Option<Pubkey>,
+    network_guard: Pubkey,
+) -> Result<()> {
+    require_deployer(&ctx.accounts.payer, &ctx.accounts.program_data)?;
+    qa::check_network_guard(
+        &network_guard,
+        ctx.accounts.payer.key,
+        IMMUTABLE_PROGRAM_DEPLOYER,
+    )?;
+    require!(
+        !machines::is_legacy(&machine_id),
+        ErrorCode::InvalidMachineId
+    );
+
+    let slots_state = &mut ctx.accounts.slots_state;
+    slots_state.treasury = ctx.accounts.treasury.key();
+    slots_state.fee_vault = ctx.accounts.fee_vault.key();
+    slots_state.machine_id = machine_id;
+    fund_vault_rent(
+        &ctx.accounts.system_program,
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.treasury.to_account_info(),
+    )?;
+    fund_vault_rent(
+        &ctx.accounts.system_program,
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.fee_vault.to_account_info(),
+    )?;
+    init_slots_state(
+        slots_state,
+        authority,
+        exclusive_mint,
+        recovery_authority,
+        community_authority,
+        network_guard,
+    )?;
+
+    emit!(MachineInitialized {
+        machine_id,
+        slots_state: slots_state.key(),
+        treasury: slots_state.treasury,
+        authority,
+    });
+    Ok(())
+}
+
+pub fn set_onboarding_sponsor(
+    ctx: Context<EmergencyPause>,
+    sponsor: Pubkey,
+    daily_budget: u32,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.onboarding_sponsor = sponsor;
+    slots_state.sponsored_daily_budget = daily_budget;
+
+    emit!(OnboardingSponsorChanged {
+        sponsor,
+        daily_budget,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_bet_limits(ctx: Context<EmergencyPause>, min_bet: u64, max_bet: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        max_bet > 0 && min_bet <= max_bet,
+        ErrorCode::InvalidBetLimits
+    );
+    require!(
+        tiers::max_bet_covered(slots_state, max_bet),
+        ErrorCode::BetLimitsExceedPayoutCap
+    );
+
+    slots_state.min_bet = min_bet;
+    slots_state.max_bet = max_bet;
+
+    emit!(BetLimitsChanged {
+        min_bet,
+        max_bet,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_spin_expiry(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(slots > 0, ErrorCode::InvalidAmount);
+
+    slots_state.spin_expiry_slots = slots;
+    Ok(())
+}
+
+pub fn set_min_slots_between_spins(ctx: Context<EmergencyPause>, slots: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.min_slots_between_spins = slots;
+
+    emit!(SpinGapChanged {
+        min_slots_between_spins: slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_crank_fee(ctx: Context<EmergencyPause>, crank_fee: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(crank_fee <= MAX_CRANK_FEE, ErrorCode::InvalidAmount);
+
+    slots_state.crank_fee = crank_fee;
+    Ok(())
+}
+
+pub fn migrate_state(ctx: Context<MigrateState>, machine_id: [u8; 32]) -> Result<()> {
+    let state_ai = &ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    migration::ensure_discriminator::<SlotsState>(&state_ai.try_borrow_data()?)?;
+    let old_len = state_ai.data_len();
+    if old_len < SlotsState::LEN {
+        let rent_due = Rent::get()?
+            .minimum_balance(SlotsState::LEN)
+            .saturating_sub(state_ai.lamports());
+        if rent_due > 0 {
+            let transfer_accounts = system_program::Transfer {
+                from: authority.to_account_info(),
+                to: state_ai.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            );
+            system_program::transfer(transfer_ctx, rent_due)?;
+        }
+        state_ai.realloc(SlotsState::LEN, true)?;
+    }
+
+    let mut slots_state = SlotsState::try_deserialize(&mut &state_ai.try_borrow_data()?[..])?;
+    require!(
+        slots_state.machine_id == machine_id,
+        ErrorCode::InvalidMachineId
+    );
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(&mut slots_state, authority.key())?;
+
+    let from_version = migration::upgrade(&mut slots_state, Clock::get()?.slot)?;
+    slots_state.try_serialize(&mut &mut state_ai.try_borrow_mut_data()?[..])?;
+
+    emit!(StateMigrated {
+        from_version,
+        to_version: SLOTS_STATE_VERSION,
+        old_len: old_len as u32,
+        new_len: state_ai.data_len() as u32,
+    });
+
+    Ok(())
+}
+
+pub fn migrate_player(ctx: Context<MigratePlayer>) -> Result<()> {
+    let player_ai = &ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    migration::ensure_discriminator::<PlayerState>(&player_ai.try_borrow_data()?)?;
+    let old_len = player_ai.data_len();
+    if old_len < PlayerState::LEN {
+        let rent_due = Rent::get()?
+            .minimum_balance(PlayerState::LEN)
+            .saturating_sub(player_ai.lamports());
+        if rent_due > 0 {
+            let transfer_accounts = system_program::Transfer {
+                from: user.to_account_info(),
+                to: player_ai.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            );
+            system_program::transfer(transfer_ctx, rent_due)?;
+        }
+        player_ai.realloc(PlayerState::LEN, true)?;
+    }
+
+    let player_state = PlayerState::try_deserialize(&mut &player_ai.try_borrow_data()?[..])?;
+    require_keys_eq!(player_state.owner, user.key(), ErrorCode::Unauthorized);
+
+    emit!(PlayerMigrated {
+        user: user.key(),
+        old_len: old_len as u32,
+        new_len: player_ai.data_len() as u32,
+    });
+    Ok(())
+}
+
+pub fn migrate_pool_position(ctx: Context<MigratePoolPosition>) -> Result<()> {
+    let position_ai = &ctx.accounts.pool_position;
+    let user = &ctx.accounts.user;
+
+    migration::ensure_discriminator::<PoolPosition>(&position_ai.try_borrow_data()?)?;
+    let old_len = position_ai.data_len();
+    if old_len < PoolPosition::LEN {
+        let rent_due = Rent::get()?
+            .minimum_balance(PoolPosition::LEN)
+            .saturating_sub(position_ai.lamports());
+        if rent_due > 0 {
+            let transfer_accounts = system_program::Transfer {
+                from: user.to_account_info(),
+                to: position_ai.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                transfer_accounts,
+            );
+            system_program::transfer(transfer_ctx, rent_due)?;
+        }
+        position_ai.realloc(PoolPosition::LEN, true)?;
+    }
+
+    let position = PoolPosition::try_deserialize(&mut &position_ai.try_borrow_data()?[..])?;
+    require_keys_eq!(position.owner, user.key(), ErrorCode::Unauthorized);
+
+    emit!(PoolPositionMigrated {
+        user: user.key(),
+        old_len: old_len as u32,
+        new_len: position_ai.data_len() as u32,
+    });
+    Ok(())
+}
+
+pub fn set_extraction_budget(ctx: Context<EmergencyPause>, budget_bps: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        budget_bps <= extraction::MAX_EXTRACTION_BUDGET_BPS,
+        ErrorCode::InvalidConfig
+    );
+
+    slots_state.extraction_budget_bps = budget_bps;
+
+    emit!(ExtractionBudgetChanged {
+        budget_bps,
+        effective_after_slot: slots_state.extraction_period_end,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn propose_extraction_override(ctx: Context<EmergencyPause>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    let activate_slot = if amount == 0 {
+        0
+    } else {
+        Clock::get()?
+            .slot
+            .checked_add(EXTRACTION_PERIOD_SLOTS)
+            .ok_or(ErrorCode::MathOverflow)?
+    };
+    slots_state.extraction_override = amount;
+    slots_state.extraction_override_activate_slot = activate_slot;
+
+    emit!(ExtractionOverrideProposed {
+        amount,
+        activate_slot,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_paytable(
+    ctx: Context<EmergencyPause>,
+    two_of_a_kind: [u64; SYMBOL_COUNT],
+    three_of_a_kind: [u64; SYMBOL_COUNT],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        pause::is_paused(slots_state.pause_flags, PAUSE_NEW_SPINS),
+        ErrorCode::NotPaused
+    );
+    require!(slots_state.pending_spins == 0, ErrorCode::PendingSpinExists);
+
+    let mut table = slots_state.paytable;
+    table.two_of_a_kind = two_of_a_kind;
+    for (row, &three) in table.multipliers.iter_mut().zip(three_of_a_kind.iter()) {
+        row[0] = three;
+    }
+    let implied_rtp_bps = paytable::validate(slots_state, &slots_state.reel_weights, &table)?;
+    slots_state.paytable = table;
+    paytable::refresh_theoretical_rtp(slots_state)?;
+
+    emit!(PaytableChanged {
+        two_of_a_kind,
+        three_of_a_kind,
+        implied_rtp_bps,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_reel_config(
+    ctx: Context<EmergencyPause>,
+    weights: [u64; SYMBOL_COUNT],
+    three_of_a_kind: [u64; SYMBOL_COUNT],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        pause::is_paused(slots_state.pause_flags, PAUSE_NEW_SPINS),
+        ErrorCode::NotPaused
+    );
+    require!(slots_state.pending_spins == 0, ErrorCode::PendingSpinExists);
+
+    let reels = ReelWeights::new(weights);
+    require!(
+        reels.total_weight > 0 && reels.total_weight < u64::MAX,
+        ErrorCode::InvalidReelWeights
+    );
+    let mut table = slots_state.paytable;
+    for (row, &three) in table.multipliers.iter_mut().zip(three_of_a_kind.iter()) {
+        row[0] = three;
+    }
+    let implied_rtp_bps = paytable::validate(slots_state, &reels, &table)?;
+    slots_state.reel_weights = reels;
+    slots_state.paytable = table;
+    paytable::refresh_theoretical_rtp(slots_state)?;
+
+    emit!(ReelConfigChanged {
+        weights,
+        total_weight: reels.total_weight,
+        three_of_a_kind,
+        implied_rtp_bps,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_scatter_config(
+    ctx: Context<EmergencyPause>,
+    symbol: u8,
+    free_spins: u16,
+    max_free_spins: u16,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        pause::is_paused(slots_state.pause_flags, PAUSE_NEW_SPINS),
+        ErrorCode::NotPaused
+    );
+    require!(slots_state.pending_spins == 0, ErrorCode::PendingSpinExists);
+
+    let config = ScatterConfig {
+        symbol,
+        free_spins,
+        max_free_spins,
+    };
+    require!(
+        !config.enabled() || config.symbol != slots_state.wild_symbol,
+        ErrorCode::InvalidScatterConfig
+    );
+    let implied_rtp_bps = paytable::validate_with(
+        slots_state,
+        &slots_state.reel_weights,
+        &slots_state.paytable,
+        slots_state.wild_symbol,
+        &config,
+    )?;
+    slots_state.scatter = config;
+    paytable::refresh_theoretical_rtp(slots_state)?;
+
+    emit!(ScatterConfigChanged {
+        symbol,
+        free_spins,
+        max_free_spins,
+        implied_rtp_bps,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_wild_symbol(ctx: Context<EmergencyPause>, wild_symbol: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        pause::is_paused(slots_state.pause_flags, PAUSE_NEW_SPINS),
+        ErrorCode::NotPaused
+    );
+    require!(slots_state.pending_spins == 0, ErrorCode::PendingSpinExists);
+    let scatter = slots_state.scatter;
+    require!(
+        wild_symbol == NO_WILD
+            || ((wild_symbol as usize) < SYMBOL_COUNT
+                && !(scatter.enabled() && scatter.symbol == wild_symbol)),
+        ErrorCode::InvalidWildSymbol
+    );
+
+    let implied_rtp_bps = paytable::validate_with(
+        slots_state,
+        &slots_state.reel_weights,
+        &slots_state.paytable,
+        wild_symbol,
+        &scatter,
+    )?;
+    slots_state.wild_symbol = wild_symbol;
+    paytable::refresh_theoretical_rtp(slots_state)?;
+
+    emit!(WildSymbolChanged {
+        wild_symbol,
+        implied_rtp_bps,
+        authority: authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_max_rtp(ctx: Context<EmergencyPause>, max_rtp_bps: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(max_rtp_bps <= 10_000, ErrorCode::InvalidConfig);
+    require!(
+        paytable::implied_rtp_with_scatter_bps(
+            &slots_state.reel_weights,
+            &slots_state.paytable,
+            slots_state.wild_symbol,
+            &slots_state.scatter,
+        ) <= max_rtp_bps as u64,
+        ErrorCode::PaytableRtpTooHigh
+    );
+
+    slots_state.max_rtp_bps = max_rtp_bps;
+    Ok(())
+}
+
+pub fn set_staff_wallets(
+    ctx: Context<EmergencyPause>,
+    wallets: [Pubkey; MAX_STAFF_WALLETS],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+
+    slots_state.staff_wallets = wallets;
+    emit!(StaffWalletsChanged {
+        wallets,
+        authority: ctx.accounts.authority.key(),
+    });
+    Ok(())
+}
+
+pub fn set_max_outcomes_per_reveal(ctx: Context<EmergencyPause>, max: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(max > 0, ErrorCode::InvalidConfig);
+
+    slots_state.max_outcomes_per_reveal = max;
+    Ok(())
+}
+
+pub fn init_rtp_history(ctx: Context<InitRtpHistory>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let mut history = ctx.accounts.rtp_history.load_init()?;
+    history.slots_state = slots_state.key();
+    history.bump = ctx.bumps.rtp_history;
+    history.head_hour =
+        time::bucket_for_timestamp(Clock::get()?.unix_timestamp, time::SECONDS_PER_HOUR);
+    Ok(())
+}
+
+pub fn init_metrics(ctx: Context<InitMetrics>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let mut m = ctx.accounts.metrics.load_init()?;
+    m.slots_state = slots_state.key();
+    m.bump = ctx.bumps.metrics;
+    metrics::snapshot(&mut m, slots_state, Clock::get()?.slot);
+    Ok(())
+}
+
+pub fn init_receipt_tree(ctx: Context<InitReceiptTree>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let mut tree = ctx.accounts.receipt_tree.load_init()?;
+    tree.slots_state = slots_state.key();
+    tree.bump = ctx.bumps.receipt_tree;
+    tree.init_empty();
+    Ok(())
+}
+
+pub fn verify_receipt(
+    ctx: Context<ViewReceiptTree>,
+    leaf: [u8; 32],
+    leaf_index: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let tree = ctx.accounts.receipt_tree.load()?;
+    require!(
+        tree.verify(&leaf, leaf_index, &proof),
+        ErrorCode::InvalidReceiptProof
+    );
+    Ok(())
+}
+
+pub fn refresh_metrics(ctx: Context<RefreshMetrics>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let mut m = ctx.accounts.metrics.load_mut()?;
+    metrics::snapshot(&mut m, slots_state, Clock::get()?.slot);
+    Ok(())
+}
+
+pub fn get_game_status(ctx: Context<ViewGameStatus>) -> Result<GameStatus> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let meta = optional::present(ctx.accounts.symbol_meta.as_ref(), "symbol_meta");
+    Ok(GameStatus {
+        pause_flags: slots_state.pause_flags,
+        frozen_version: slots_state.frozen_version,
+        program_version: PROGRAM_VERSION,
+        config_hash: symbol_meta::config_hash(
+            &slots_state.reel_weights,
+            &slots_state.paytable,
+            meta.map(|m| &m.entries),
+        ),
+        symbol_meta_version: meta.map_or(0, |m| m.version),
+        pool_shortfall: pool_level::shortfall(slots_state),
+    })
+}
+
+pub fn get_feature_pnl(ctx: Context<ViewState>) -> Result<FeaturePnl> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    Ok(feature_stats::pnl(slots_state))
+}
+
+pub fn get_bootstrap_status(ctx: Context<ViewState>) -> Result<BootstrapStatus> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let active = bootstrap::next_bootstrap_active(
+        slots_state.bootstrap_active,
+        slots_state.total_pool,
+        slots_state.bootstrap_maturity_threshold,
+        slots_state.bootstrap_hysteresis_bps,
+    );
+
+    Ok(BootstrapStatus {
+        active,
+        total_pool: slots_state.total_pool,
+        maturity_threshold: slots_state.bootstrap_maturity_threshold,
+        effective_max_payout: bootstrap::effective_max_payout(
+            slots_state.max_payout_per_spin,
+            slots_state.total_pool,
+            slots_state.bootstrap_payout_bps,
+            active,
+        ),
+        configured_max_payout: slots_state.max_payout_per_spin,
+    })
+}
+
+pub fn update_config(ctx: Context<EmergencyPause>, args: UpdateConfigArgs) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    let old = config::snapshot(slots_state);
+    config::apply(slots_state, &args)?;
+    pool_level::refresh(slots_state);
+
+    emit!(ConfigUpdated {
+        old,
+        new: config::snapshot(slots_state),
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_bootstrap_config(
+    ctx: Context<EmergencyPause>,
+    maturity_threshold: u64,
+    payout_bps: u16,
+    hysteresis_bps: u16,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        payout_bps > 0 && payout_bps <= 10_000,
+        ErrorCode::InvalidBootstrapConfig
+    );
+    require!(hysteresis_bps < 10_000, ErrorCode::InvalidBootstrapConfig);
+
+    slots_state.bootstrap_maturity_threshold = maturity_threshold;
+    slots_state.bootstrap_payout_bps = payout_bps;
+    slots_state.bootstrap_hysteresis_bps = hysteresis_bps;
+    refresh_bootstrap(slots_state);
+
+    Ok(())
+}
+
+pub fn set_traffic_half_life(ctx: Context<EmergencyPause>, half_life_secs: u32) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(half_life_secs > 0, ErrorCode::InvalidAmount);
+
+    slots_state.traffic_half_life_secs = half_life_secs;
+    Ok(())
+}
+
+pub fn propose_settle_hook(
+    ctx: Context<EmergencyPause>,
+    index: u8,
+    hook_program: Pubkey,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        (index as usize) < MAX_SETTLE_HOOKS
+            && hook_program != Pubkey::default()
+            && hook_program != crate::ID,
+        ErrorCode::InvalidSettleHook
+    );
+
+    let activate_slot = Clock::get()?
+        .slot
+        .checked_add(SETTLE_HOOK_TIMELOCK_SLOTS)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.pending_settle_hook = hook_program;
+    slots_state.pending_settle_hook_index = index;
+    slots_state.pending_settle_hook_activate_slot = activate_slot;
+
+    emit!(SettleHookProposed {
+        index,
+        hook_program,
+        activate_slot,
+    });
+
+    Ok(())
+}
+
+pub fn activate_settle_hook(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        slots_state.pending_settle_hook != Pubkey::default(),
+        ErrorCode::InvalidSettleHook
+    );
+    require!(
+        time::has_reached(
+            slots_state.pending_settle_hook_activate_slot,
+            Clock::get()?.slot
+        ),
+        ErrorCode::SettleHookTimelocked
+    );
+
+    let index = slots_state.pending_settle_hook_index;
+    let hook_program = slots_state.pending_settle_hook;
+    slots_state.settle_hooks[index as usize] = hook_program;
+    slots_state.pending_settle_hook = Pubkey::default();
+    slots_state.pending_settle_hook_activate_slot = 0;
+
+    emit!(SettleHookChanged {
+        index,
+        hook_program,
+    });
+
+    Ok(())
+}
+
+pub fn init_epoch_reports(ctx: Context<InitEpochReports>, epoch_length_secs: u32) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(epoch_length_secs > 0, ErrorCode::InvalidEpochConfig);
+
+    let page = &mut ctx.accounts.epoch_report_page;
+    page.slots_state = slots_state.key();
+    page.page = 0;
+    page.epoch_length_secs = epoch_length_secs;
+    page.open_epoch = epoch_reports::epoch_of(epoch_length_secs, Clock::get()?.unix_timestamp);
+    page.open_base = epoch_reports::lifetime_totals(slots_state);
+    page.count = 0;
+    page.reports = [EpochReport::default(); EPOCH_REPORTS_PER_PAGE];
+    page.bump = ctx.bumps.epoch_report_page;
+    slots_state.epoch_report_page = 0;
+    Ok(())
+}
+
+pub fn open_epoch_report_page(ctx: Context<OpenEpochReportPage>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let previous = &ctx.accounts.previous_page;
+    require!(previous.is_full(), ErrorCode::EpochReportPageNotFull);
+
+    let page = &mut ctx.accounts.new_page;
+    page.slots_state = slots_state.key();
+    page.page = previous.page + 1;
+    page.epoch_length_secs = previous.epoch_length_secs;
+    page.open_epoch = previous.open_epoch;
+    page.open_base = previous.open_base;
+    page.count = 0;
+    page.reports = [EpochReport::default(); EPOCH_REPORTS_PER_PAGE];
+    page.bump = ctx.bumps.new_page;
+    slots_state.epoch_report_page = page.page;
+    Ok(())
+}
+
+pub fn close_epoch(ctx: Context<CloseEpoch>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let page: &mut EpochReportPage = &mut ctx.accounts.epoch_report_page;
+    close_epoch_if_present(Some(page), slots_state)
+}
+
+pub fn init_symbol_meta(
+    ctx: Context<InitSymbolMeta>,
+    entries: [SymbolEntry; SYMBOL_COUNT],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        symbol_meta::entries_valid(&entries),
+        ErrorCode::InvalidSymbolMeta
+    );
+
+    let meta = &mut ctx.accounts.symbol_meta;
+    meta.slots_state = slots_state.key();
+    meta.entries = entries;
+    meta.pending_entries = [SymbolEntry::default(); SYMBOL_COUNT];
+    meta.pending_activate_slot = 0;
+    meta.version = 1;
+    meta.bump = ctx.bumps.symbol_meta;
+
+    emit!(SymbolMetaChanged {
+        version: meta.version,
+        config_hash: symbol_meta::config_hash(
+            &slots_state.reel_weights,
+            &slots_state.paytable,
+            Some(&meta.entries),
+        ),
+    });
+
+    Ok(())
+}
+
+pub fn propose_symbol_meta(
+    ctx: Context<UpdateSymbolMeta>,
+    entries: [SymbolEntry; SYMBOL_COUNT],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        symbol_meta::entries_valid(&entries),
+        ErrorCode::InvalidSymbolMeta
+    );
+
+    let activate_slot = Clock::get()?
+        .slot
+        .checked_add(SYMBOL_META_TIMELOCK_SLOTS)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let meta = &mut ctx.accounts.symbol_meta;
+    meta.pending_entries = entries;
+    meta.pending_activate_slot = activate_slot;
+
+    emit!(SymbolMetaProposed {
+        activate_slot,
+        config_hash: symbol_meta::config_hash(
+            &slots_state.reel_weights,
+            &slots_state.paytable,
+            Some(&entries),
+        ),
+    });
+
+    Ok(())
+}
+
+pub fn activate_symbol_meta(ctx: Context<UpdateSymbolMeta>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+
+    let meta = &mut ctx.accounts.symbol_meta;
+    require!(
+        meta.pending_activate_slot != 0,
+        ErrorCode::InvalidSymbolMeta
+    );
+    require!(
+        time::has_reached(meta.pending_activate_slot, Clock::get()?.slot),
+        ErrorCode::SymbolMetaTimelocked
+    );
+
+    meta.entries = meta.pending_entries;
+    meta.pending_entries = [SymbolEntry::default(); SYMBOL_COUNT];
+    meta.pending_activate_slot = 0;
+    meta.version = meta.version.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(SymbolMetaChanged {
+        version: meta.version,
+        config_hash: symbol_meta::config_hash(
+            &slots_state.reel_weights,
+            &slots_state.paytable,
+            Some(&meta.entries),
+        ),
+    });
+
+    Ok(())
+}
+
+pub fn remove_settle_hook(ctx: Context<EmergencyPause>, index: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        (index as usize) < MAX_SETTLE_HOOKS,
+        ErrorCode::InvalidSettleHook
+    );
+
+    slots_state.settle_hooks[index as usize] = Pubkey::default();
+
+    emit!(SettleHookChanged {
+        index,
+        hook_program: Pubkey::default(),
+    });
+
+    Ok(())
+}
+
+pub fn set_beta_mode(ctx: Context<EmergencyPause>, enabled: bool) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.beta_mode = enabled;
+
+    emit!(BetaModeChanged {
+        beta_mode: enabled,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_oracle_cost(
+    ctx: Context<EmergencyPause>,
+    cost_lamports: u64,
+    mode: OracleCostMode,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.oracle_cost_lamports = cost_lamports;
+    slots_state.oracle_cost_mode = mode;
+
+    emit!(OracleCostChanged {
+        cost_lamports,
+        mode,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_oracle_queues(
+    ctx: Context<EmergencyPause>,
+    queues: [OracleQueueEntry; MAX_ORACLE_QUEUES],
+    margin_slots: u32,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.oracle_queues = queues;
+    slots_state.oracle_queue_margin_slots = margin_slots;
+
+    emit!(OracleQueuesChanged {
+        queues,
+        margin_slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_oracle_health_gate(
+    ctx: Context<EmergencyPause>,
+    max_reveal_gap_slots: u64,
+    expected_reveal_slots: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        max_reveal_gap_slots == 0 || expected_reveal_slots > 0,
+        ErrorCode::InvalidOracleHealthConfig
+    );
+
+    slots_state.oracle_max_reveal_gap_slots = max_reveal_gap_slots;
+    slots_state.oracle_expected_reveal_slots = expected_reveal_slots;
+    oracle_health::refresh_gate(slots_state, Clock::get()?.slot);
+
+    emit!(OracleHealthConfigChanged {
+        max_reveal_gap_slots,
+        expected_reveal_slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn check_oracle_health(ctx: Context<FlushStats>) -> Result<OracleHealth> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    Ok(oracle_health::refresh_gate(slots_state, Clock::get()?.slot))
+}
+
+pub fn get_oracle_health(ctx: Context<ViewState>) -> Result<OracleHealthStatus> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    Ok(oracle_health::status(slots_state, Clock::get()?.slot))
+}
+
+pub fn init_allowlist_page(ctx: Context<InitAllowlistPage>, page: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        page == slots_state.allowlist_pages,
+        ErrorCode::InvalidAllowlistPage
+    );
+
+    let allowlist = &mut ctx.accounts.allowlist;
+    allowlist.slots_state = slots_state.key();
+    allowlist.page = page;
+    allowlist.bump = ctx.bumps.allowlist;
+    allowlist.entries = Vec::new();
+
+    slots_state.allowlist_pages = slots_state
+        .allowlist_pages
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
+}
+
+pub fn add_to_allowlist(ctx: Context<UpdateAllowlist>, player: Pubkey) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let allowlist = &mut ctx.accounts.allowlist;
+
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        allowlist.entries.len() < ALLOWLIST_PAGE_CAPACITY,
+        ErrorCode::AllowlistPageFull
+    );
+
+    match allowlist.entries.binary_search(&player) {
+        Ok(_) => return err!(ErrorCode::AlreadyAllowlisted),
+        Err(pos) => allowlist.entries.insert(pos, player),
+    }
+
+    emit!(AllowlistUpdated {
+        player,
+        page: allowlist.page,
+        added: true,
+        count: allowlist.entries.len() as u16,
+    });
+
+    Ok(())
+}
+
+pub fn remove_from_allowlist(ctx: Context<UpdateAllowlist>, player: Pubkey) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let allowlist = &mut ctx.accounts.allowlist;
+
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+
+    let pos = allowlist
+        .entries
+        .binary_search(&player)
+        .map_err(|_| ErrorCode::NotAllowlisted)?;
+    allowlist.entries.remove(pos);
+
+    emit!(AllowlistUpdated {
+        player,
+        page: allowlist.page,
+        added: false,
+        count: allowlist.entries.len() as u16,
+    });
+
+    Ok(())
+}
+
+pub fn emergency_pause(ctx: Context<EmergencyPause>) -> Result<()> {
+    let flags = pause::toggled(ctx.accounts.slots_state.pause_flags);
+    write_pause_flags(ctx, flags)
+}
+
+pub fn set_pause_flags(ctx: Context<EmergencyPause>, flags: u8) -> Result<()> {
+    pause::validate(flags)?;
+    write_pause_flags(ctx, flags)
+}
+
+pub fn heartbeat(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+
+    emit!(AuthorityHeartbeat {
+        authority: slots_state.authority,
+        slot: slots_state.last_authority_action_slot,
+    });
+
+    Ok(())
+}
+
+pub fn propose_authority(ctx: Context<EmergencyPause>, new_authority: Pubkey) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        new_authority != Pubkey::default() && new_authority != slots_state.authority,
+        ErrorCode::InvalidAuthority
+    );
+
+    slots_state.pending_authority = new_authority;
+
+    emit!(AuthorityTransferProposed {
+        authority: authority.key(),
+        pending_authority: new_authority,
+    });
+
+    Ok(())
+}
+
+pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let new_authority = ctx.accounts.pending_authority.key();
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require!(
+        slots_state.pending_authority != Pubkey::default(),
+        ErrorCode::NoPendingAuthority
+    );
+    require_keys_eq!(
+        new_authority,
+        slots_state.pending_authority,
+        ErrorCode::Unauthorized
+    );
+
+    let previous_authority = slots_state.authority;
+    slots_state.authority = new_authority;
+    slots_state.pending_authority = Pubkey::default();
+    // The recovery authority must differ from the authority.
+    if slots_state.recovery_authority == new_authority {
+        slots_state.recovery_authority = Pubkey::default();
+    }
+    slots_state.last_authority_action_slot = Clock::get()?.slot;
+
+    emit!(AuthorityTransferred {
+        previous_authority,
+        new_authority,
+    });
+
+    Ok(())
+}
+
+pub fn cancel_authority_transfer(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        slots_state.pending_authority != Pubkey::default(),
+        ErrorCode::NoPendingAuthority
+    );
+
+    let pending_authority = slots_state.pending_authority;
+    slots_state.pending_authority = Pubkey::default();
+
+    emit!(AuthorityTransferCancelled {
+        authority: authority.key(),
+        pending_authority,
+    });
+
+    Ok(())
+}
+
+pub fn set_recovery_config(
+    ctx: Context<EmergencyPause>,
+    recovery_authority: Pubkey,
+    inactivity_slots: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(inactivity_slots > 0, ErrorCode::InvalidRecoveryConfig);
+    require!(
+        recovery_authority != slots_state.authority,
+        ErrorCode::InvalidRecoveryConfig
+    );
+
+    slots_state.recovery_authority = recovery_authority;
+    slots_state.recovery_inactivity_slots = inactivity_slots;
+
+    emit!(RecoveryConfigChanged {
+        recovery_authority,
+        inactivity_slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn claim_recovery(ctx: Context<ClaimRecovery>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let claimant = ctx.accounts.recovery_authority.key();
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require!(
+        slots_state.recovery_authority != Pubkey::default(),
+        ErrorCode::RecoveryNotConfigured
+    );
+    require_keys_eq!(
+        claimant,
+        slots_state.recovery_authority,
+        ErrorCode::Unauthorized
+    );
+    let now = Clock::get()?.slot;
+    require!(
+        time::is_expired(
+            slots_state.last_authority_action_slot,
+            slots_state.recovery_inactivity_slots,
+            now
+        ),
+        ErrorCode::RecoveryWindowNotElapsed
+    );
+
+    let previous_authority = slots_state.authority;
+    let inactive_slots =
+        time::slots_elapsed_saturating(slots_state.last_authority_action_slot, now);
+    slots_state.authority = claimant;
+    slots_state.recovery_authority = Pubkey::default();
+    slots_state.pending_authority = Pubkey::default();
+    slots_state.last_authority_action_slot = now;
+
+    emit!(RecoveryClaimed {
+        previous_authority,
+        new_authority: claimant,
+        inactive_slots,
+    });
+
+    Ok(())
+}
+
+pub fn set_referral_bps(ctx: Context<EmergencyPause>, referral_bps: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    require!(
+        referral_bps <= referrals::MAX_REFERRAL_BPS,
+        ErrorCode::InvalidReferralBps
+    );
+
+    slots_state.referral_bps = referral_bps;
+
+    emit!(ReferralBpsChanged {
+        referral_bps,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_loss_window(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots > 0, ErrorCode::InvalidAmount);
+
+    slots_state.loss_window_slots = slots;
+
+    emit!(LossWindowChanged {
+        loss_window_slots: slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_vip_config(ctx: Context<EmergencyPause>, config: VipConfig) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    vip::validate(&config, slots_state.theoretical_rtp_bps)?;
+
+    slots_state.vip_config = config;
+
+    emit!(VipConfigChanged {
+        mint: config.mint,
+        min_balance: config.min_balance,
+        rebate_bps: config.rebate_bps,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_loyalty_config(
+    ctx: Context<EmergencyPause>,
+    points_per_lamport_bps: u16,
+    free_spin_point_cost: u64,
+    free_spin_bet: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        free_spin_bet == 0 || free_spin_point_cost > 0,
+        ErrorCode::InvalidLoyaltyConfig
+    );
+    require!(
+        free_spin_bet <= slots_state.max_bet,
+        ErrorCode::InvalidLoyaltyConfig
+    );
+
+    slots_state.points_per_lamport_bps = points_per_lamport_bps;
+    slots_state.free_spin_point_cost = free_spin_point_cost;
+    slots_state.free_spin_bet = free_spin_bet;
+
+    emit!(LoyaltyConfigChanged {
+        points_per_lamport_bps,
+        free_spin_point_cost,
+        free_spin_bet,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_tier_limits(ctx: Context<EmergencyPause>, tier: u8, limits: TierLimits) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        (1..=MAX_PLAYER_TIERS).contains(&(tier as usize)),
+        ErrorCode::InvalidPlayerTier
+    );
+    tiers::validate_limits(&limits)?;
+
+    slots_state.tier_limits[tier as usize - 1] = limits;
+
+    emit!(TierLimitsChanged {
+        tier,
+        limits,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn pre_upgrade_freeze(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots_state.frozen_version == 0, ErrorCode::UpgradeFrozen);
+
+    slots_state.pause_flags = PAUSE_ALL;
+    slots_state.scheduled_resume_slot = 0;
+    slots_state.frozen_version = PROGRAM_VERSION;
+
+    emit!(UpgradeFrozen {
+        frozen_version: PROGRAM_VERSION,
+        pending_spins: slots_state.pending_spins,
+        authority: authority.key(),
+    });
+
+    refresh_metrics_if_present(&ctx.accounts.metrics, slots_state, Clock::get()?.slot)?;
+
+    Ok(())
+}
+
+pub fn post_upgrade_thaw<'info>(
+    ctx: Context<'_, '_, 'info, 'info, EmergencyPause<'info>>,
+    compatible: bool,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots_state.frozen_version != 0, ErrorCode::NotFrozen);
+
+    let mut voided = 0u32;
+    if !compatible {
+        slots_state.min_settle_version = PROGRAM_VERSION;
+        for ai in ctx.remaining_accounts.iter() {
+            let mut player_state: Account<'info, PlayerState> = Account::try_from(ai)?;
+            if !player_state.has_pending_spin || player_state.commit_version >= PROGRAM_VERSION {
+                continue;
+            }
+            let randomness_account = player_state.randomness_account;
+            let refund = void_pending_spin(slots_state, &mut player_state)?;
+            player_state.claimable_balance = player_state
+                .claimable_balance
+                .checked_add(refund)
+                .ok_or(ErrorCode::MathOverflow)?;
+            slots_state.total_claimable = slots_state
+                .total_claimable
+                .checked_add(refund)
+                .ok_or(ErrorCode::MathOverflow)?;
+            player_state.exit(&crate::ID)?;
+            voided += 1;
+
+            emit!(SpinVoided {
+                user: player_state.owner,
+                randomness_account,
+                refunded: refund,
+                reason_code: VOID_REASON_UPGRADE,
+                authority: authority.key(),
+                spin_nonce: player_state.spin_nonce,
+                context_index: 0,
+            });
+        }
+    }
+
+    let from_version = slots_state.frozen_version;
+    slots_state.frozen_version = 0;
+    slots_state.pause_flags = 0;
+
+    emit!(UpgradeThawed {
+        from_version,
+        to_version: PROGRAM_VERSION,
+        compatible,
+        voided,
+        authority: authority.key(),
+    });
+
+    refresh_metrics_if_present(&ctx.accounts.metrics, slots_state, Clock::get()?.slot)?;
+
+    Ok(())
+}
+
+pub fn schedule_resume(ctx: Context<EmergencyPause>, at_slot: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots_state.pause_flags != 0, ErrorCode::NotPaused);
+    require!(slots_state.frozen_version == 0, ErrorCode::UpgradeFrozen);
+    require!(
+        !time::has_reached(at_slot, Clock::get()?.slot),
+        ErrorCode::InvalidResumeSlot
+    );
+
+    slots_state.scheduled_resume_slot = at_slot;
+
+    emit!(ResumeScheduled {
+        at_slot,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn cancel_scheduled_resume(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    let at_slot = slots_state.scheduled_resume_slot;
+    require!(
+        at_slot != 0 && !time::has_reached(at_slot, Clock::get()?.slot),
+        ErrorCode::NoScheduledResume
+    );
+
+    slots_state.scheduled_resume_slot = 0;
+
+    emit!(ResumeScheduled {
+        at_slot: 0,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::entrypoint::ProgramResult;
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::instruction as ix;
+    use crate::test_util::{emitted, pda, program_error, set_clock_slot, simulate, Fixture, Game};
+
+    fn system() -> Fixture {
+        Fixture::program(anchor_lang::system_program::ID)
+    }
+
+    /// This program as deployed by the upgradeable loader, with its
+    /// program data account naming `upgrade_authority`.
+    fn deployed(upgrade_authority: Option<Pubkey>) -> [Fixture; 2] {
+        use anchor_lang::solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+
+        let programdata_address = pda(&[crate::ID.as_ref()]).0;
+        let program = UpgradeableLoaderState::Program {
+            programdata_address,
+        };
+        let program_data = UpgradeableLoaderState::ProgramData {
+            slot: 1,
+            upgrade_authority_address: upgrade_authority,
+        };
+        let loaded = |key, state, executable| Fixture {
+            owner: bpf_loader_upgradeable::ID,
+            data: bincode::serialize(&state).unwrap(),
+            writable: false,
+            executable,
+            ..Fixture::wallet(key, 1)
+        };
+        [
+            loaded(crate::ID, program, true),
+            loaded(programdata_address, program_data, false),
+        ]
+    }
+
+    /// The accounts of `initialize` or `initialize_machine` for `machine`.
+    fn initializing(machine: &[u8], payer: Pubkey, deployed: [Fixture; 2]) -> Vec<Fixture> {
+        let [program, program_data] = deployed;
+        vec![
+            Fixture::uninit(pda(&[SLOTS_SEED, machine]).0, SlotsState::LEN),
+            Fixture::wallet(pda(&[TREASURY_SEED, machine]).0, 0),
+            Fixture::wallet(pda(&[FEE_VAULT_SEED, machine]).0, 0),
+            Fixture::wallet(payer, 1_000_000_000).signer(),
+            program,
+            program_data,
+            system(),
+        ]
+    }
+
+    fn initialize_data(authority: Pubkey) -> Vec<u8> {
+        ix::Initialize {
+            authority,
+            exclusive_mint: None,
+            recovery_authority: None,
+            community_authority: None,
+            network_guard: Pubkey::default(),
+        }
+        .data()
+    }
+
+    fn initialize_machine_data(machine_id: [u8; 32], authority: Pubkey) -> Vec<u8> {
+        ix::InitializeMachine {
+            machine_id,
+            authority,
+            exclusive_mint: None,
+            recovery_authority: None,
+            community_authority: None,
+            network_guard: Pubkey::default(),
+        }
+        .data()
+    }
+
+    #[test]
+    fn only_the_upgrade_authority_initializes() {
+        set_clock_slot(1);
+        let deployer = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let data = initialize_data(deployer);
+        let not_deployer = Err(program_error(ErrorCode::NotUpgradeAuthority));
+
+        let mut accounts = initializing(&[], stranger, deployed(Some(deployer)));
+        assert_eq!(simulate(&mut accounts, &data), not_deployer);
+
+        let mut accounts = initializing(&[], deployer, deployed(Some(deployer)));
+        simulate(&mut accounts, &data).unwrap();
+        let state = accounts[0].read::<SlotsState>();
+        assert!(state.initialized);
+        assert_eq!(state.authority, deployer);
+        assert_eq!(state.treasury, accounts[1].key);
+        assert_eq!(state.fee_vault, accounts[2].key);
+        let rent = Rent::default().minimum_balance(0);
+        assert_eq!(accounts[1].lamports, rent);
+        assert_eq!(accounts[2].lamports, rent);
+
+        // Without a deployer key built in, an immutable program has no
+        // one to initialize it.
+        assert_eq!(IMMUTABLE_PROGRAM_DEPLOYER, None);
+        let mut accounts = initializing(&[], deployer, deployed(None));
+        assert_eq!(simulate(&mut accounts, &data), not_deployer);
+    }
+
+    #[test]
+    fn a_stranger_cannot_initialize_another_machine() {
+        set_clock_slot(1);
+        let deployer = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let machine_id = [7; 32];
+        let data = initialize_machine_data(machine_id, stranger);
+
+        let mut accounts = initializing(&machine_id, stranger, deployed(Some(deployer)));
+        assert_eq!(
+            simulate(&mut accounts, &data),
+            Err(program_error(ErrorCode::NotUpgradeAuthority))
+        );
+
+        let mut accounts = initializing(&machine_id, deployer, deployed(Some(deployer)));
+        simulate(&mut accounts, &data).unwrap();
+        assert_eq!(accounts[0].read::<SlotsState>().machine_id, machine_id);
+    }
+
+    #[test]
+    fn initialize_reads_the_program_data_of_this_program() {
+        let deployer = Pubkey::new_unique();
+        let [program, _] = deployed(Some(deployer));
+        let [_, mut forged] = deployed(Some(deployer));
+        forged.key = Pubkey::new_unique();
+        let mut accounts = initializing(&[], deployer, [program, forged]);
+        assert_eq!(
+            simulate(&mut accounts, &initialize_data(deployer)),
+            Err(Error::from(anchor_lang::error::ErrorCode::ConstraintRaw).into())
+        );
+    }
+
+    fn allowlist_page(state: &Fixture, page: u8) -> Fixture {
+        let (key, _) = pda(&[ALLOWLIST_SEED, state.key.as_ref(), &[page]]);
+        Fixture::uninit(key, Allowlist::LEN)
+    }
+
+    /// Allowlist `player` on `page` as `authority`.
+    fn allowlist(
+        state: &mut Fixture,
+        page: &mut Fixture,
+        authority: &mut Fixture,
+        player: Pubkey,
+    ) -> ProgramResult {
+        let data = ix::AddToAllowlist { player }.data();
+        simulate([state, page, authority], &data)
+    }
+
+    #[test]
+    fn a_full_allowlist_page_overflows_to_the_next() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut first = allowlist_page(&state, 0);
+        let mut second = allowlist_page(&state, 1);
+        let init = |page| ix::InitAllowlistPage { page }.data();
+
+        // Pages open in order, by the authority only.
+        let mut skipped = allowlist_page(&state, 1);
+        assert_eq!(
+            simulate([&mut state, &mut skipped, &mut authority, &mut system()], &init(1)),
+            Err(program_error(ErrorCode::InvalidAllowlistPage))
+        );
+        let mut refused = allowlist_page(&state, 0);
+        assert_eq!(
+            simulate([&mut state, &mut refused, &mut stranger, &mut system()], &init(0)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut first, &mut authority, &mut system()], &init(0)).unwrap();
+
+        let players: Vec<Pubkey> =
+            (0..=ALLOWLIST_PAGE_CAPACITY).map(|_| Pubkey::new_unique()).collect();
+        for player in &players[..ALLOWLIST_PAGE_CAPACITY] {
+            allowlist(&mut state, &mut first, &mut authority, *player).unwrap();
+        }
+        let overflow = players[ALLOWLIST_PAGE_CAPACITY];
+        assert_eq!(
+            allowlist(&mut state, &mut first, &mut authority, overflow),
+            Err(program_error(ErrorCode::AllowlistPageFull))
+        );
+        let page = first.read::<Allowlist>();
+        assert_eq!(page.entries.len(), ALLOWLIST_PAGE_CAPACITY);
+        assert!(page.entries.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(players[..ALLOWLIST_PAGE_CAPACITY].iter().all(|p| page.contains(p)));
+
+        simulate([&mut state, &mut second, &mut authority, &mut system()], &init(1)).unwrap();
+        assert_eq!(
+            allowlist(&mut state, &mut second, &mut stranger, overflow),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        allowlist(&mut state, &mut second, &mut authority, overflow).unwrap();
+        assert_eq!(
+            allowlist(&mut state, &mut second, &mut authority, overflow),
+            Err(program_error(ErrorCode::AlreadyAllowlisted))
+        );
+        assert_eq!(state.read::<SlotsState>().allowlist_pages, 2);
+
+        let remove = |player| ix::RemoveFromAllowlist { player }.data();
+        simulate([&mut state, &mut second, &mut authority], &remove(overflow)).unwrap();
+        assert_eq!(
+            simulate([&mut state, &mut second, &mut authority], &remove(overflow)),
+            Err(program_error(ErrorCode::NotAllowlisted))
+        );
+        assert!(second.read::<Allowlist>().entries.is_empty());
+    }
+
+    #[test]
+    fn beta_mode_lets_only_allowlisted_players_spin() {
+        let mut game = Game::new();
+        game.randomness.seed(game.slot, 0);
+        let data = game.request_spin_data(DEFAULT_MIN_BET);
+        let beta = |enabled| ix::SetBetaMode { enabled }.data();
+        let mut accounts = game.request_spin_fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+
+        assert_eq!(
+            simulate([&mut accounts[0], &mut stranger, &mut no_metrics], &beta(true)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut accounts[0], &mut authority, &mut no_metrics], &beta(true)).unwrap();
+        assert!(accounts[0].read::<SlotsState>().beta_mode);
+
+        // Players may still register.
+        let user = Pubkey::new_unique();
+        let machine = machine_seed(&game.state.machine_id);
+        let mut registering = [
+            accounts[0].clone(),
+            Fixture::uninit(pda(&[PLAYER_SEED, machine, user.as_ref()]).0, PlayerState::LEN),
+            Fixture::wallet(pda(&[ACCOUNT_INDEX_SEED, user.as_ref()]).0, 0),
+            Fixture::wallet(user, 1_000_000_000).signer(),
+            accounts[3].clone(),
+            system(),
+        ];
+        simulate(&mut registering, &ix::InitPlayer {}.data()).unwrap();
+        assert_eq!(registering[1].read::<PlayerState>().owner, user);
+
+        // Spinning needs a page holding the player.
+        let mut spin = accounts.clone();
+        assert_eq!(
+            simulate(&mut spin, &data),
+            Err(program_error(ErrorCode::OptionalAccountMissing))
+        );
+        let mut page = Allowlist {
+            slots_state: accounts[0].key,
+            page: 0,
+            bump: 0,
+            entries: vec![user],
+        };
+        let mut spin = accounts.clone();
+        spin[5] = Fixture::account(Pubkey::new_unique(), &page);
+        assert_eq!(
+            simulate(&mut spin, &data),
+            Err(program_error(ErrorCode::NotAllowlisted))
+        );
+        page.entries.push(game.user);
+        page.entries.sort();
+        spin[5] = Fixture::account(Pubkey::new_unique(), &page);
+        simulate(&mut spin, &data).unwrap();
+        assert!(spin[1].read::<PlayerState>().has_pending_spin);
+
+        // A page of another machine does not count.
+        let mut spin = accounts.clone();
+        page.slots_state = Pubkey::new_unique();
+        spin[5] = Fixture::account(Pubkey::new_unique(), &page);
+        assert!(simulate(&mut spin, &data).is_err());
+
+        // Out of beta anyone spins.
+        simulate([&mut accounts[0], &mut authority, &mut no_metrics], &beta(false)).unwrap();
+        simulate(&mut accounts, &data).unwrap();
+        assert!(accounts[1].read::<PlayerState>().has_pending_spin);
+    }
+
+    #[test]
+    fn initialize_leaves_the_max_bet_covered() {
+        let state = Game::new().state;
+        assert!(state.min_bet > 0 && state.min_bet <= state.max_bet);
+        assert!(state.max_bet * game_math::max_3oak_multiplier() <= state.max_payout_per_spin);
+        assert_eq!(
+            game_math::max_3oak_multiplier(),
+            *SYMBOL_PAYOUTS_3OAK.iter().max().unwrap()
+        );
+    }
+
+    #[test]
+    fn request_spin_holds_bets_to_the_limits() {
+        let mut game = Game::new();
+        game.state.min_bet = game.state.max_bet / 2;
+
+        assert_eq!(
+            game.request(game.state.min_bet - 1).unwrap_err(),
+            ErrorCode::BetTooLow.into()
+        );
+        assert_eq!(
+            game.request(game.state.max_bet + 1).unwrap_err(),
+            ErrorCode::BetTooHigh.into()
+        );
+        game.clone().request(game.state.min_bet).unwrap();
+        game.clone().request(game.state.max_bet).unwrap();
+
+        // A max bet the payout cap no longer covers stops every spin.
+        game.state.max_payout_per_spin = game.state.max_bet * game_math::max_3oak_multiplier() - 1;
+        assert_eq!(
+            game.request(game.state.min_bet).unwrap_err(),
+            ErrorCode::BetLimitsExceedPayoutCap.into()
+        );
+    }
+
+    #[test]
+    fn only_the_authority_sets_covered_bet_limits() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let limits = |min_bet, max_bet| ix::SetBetLimits { min_bet, max_bet }.data();
+        let covered = game.state.max_payout_per_spin / game_math::max_3oak_multiplier();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &limits(1, covered)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        for (min_bet, max_bet, error) in [
+            (2, 1, ErrorCode::InvalidBetLimits),
+            (0, 0, ErrorCode::InvalidBetLimits),
+            (1, covered + 1, ErrorCode::BetLimitsExceedPayoutCap),
+        ] {
+            assert_eq!(
+                simulate([&mut state, &mut authority, &mut no_metrics], &limits(min_bet, max_bet)),
+                Err(program_error(error))
+            );
+        }
+        simulate([&mut state, &mut authority, &mut no_metrics], &limits(covered, covered))
+            .unwrap();
+        let set = state.read::<SlotsState>();
+        assert_eq!((set.min_bet, set.max_bet), (covered, covered));
+    }
+
+    #[test]
+    fn authority_moves_only_when_the_proposed_key_accepts() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut successor = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let propose = |new_authority| ix::ProposeAuthority { new_authority }.data();
+        let accept = ix::AcceptAuthority {}.data();
+        let cancel = ix::CancelAuthorityTransfer {}.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut successor], &accept),
+            Err(program_error(ErrorCode::NoPendingAuthority))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &propose(successor.key)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        for invalid in [Pubkey::default(), authority.key] {
+            assert_eq!(
+                simulate([&mut state, &mut authority, &mut no_metrics], &propose(invalid)),
+                Err(program_error(ErrorCode::InvalidAuthority))
+            );
+        }
+
+        // A fat-fingered key can be withdrawn before anyone accepts it.
+        let typo = Pubkey::new_unique();
+        simulate([&mut state, &mut authority, &mut no_metrics], &propose(typo)).unwrap();
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &cancel),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &cancel).unwrap();
+        assert_eq!(state.read::<SlotsState>().pending_authority, Pubkey::default());
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &cancel),
+            Err(program_error(ErrorCode::NoPendingAuthority))
+        );
+
+        simulate([&mut state, &mut authority, &mut no_metrics], &propose(successor.key)).unwrap();
+        assert_eq!(
+            simulate([&mut state, &mut stranger], &accept),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        let mut unsigned = Fixture {
+            signer: false,
+            ..successor.clone()
+        };
+        let not_signed =
+            anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::AccountNotSigner);
+        assert_eq!(simulate([&mut state, &mut unsigned], &accept), Err(not_signed.into()));
+        assert_eq!(state.read::<SlotsState>().authority, authority.key);
+
+        emitted::<AuthorityTransferred>();
+        simulate([&mut state, &mut successor], &accept).unwrap();
+        let moved = state.read::<SlotsState>();
+        assert_eq!(moved.authority, successor.key);
+        assert_eq!(moved.pending_authority, Pubkey::default());
+        assert_eq!(moved.last_authority_action_slot, game.slot);
+        let event = emitted::<AuthorityTransferred>().pop().unwrap();
+        assert_eq!((event.previous_authority, event.new_authority), (authority.key, successor.key));
+
+        // The old key has lost the admin instructions; the new one has them.
+        let pause = ix::EmergencyPause {}.data();
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &pause),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut successor, &mut no_metrics], &pause).unwrap();
+    }
+
+    #[test]
+    fn an_accepted_authority_cannot_also_be_the_recovery_key() {
+        let mut game = Game::new();
+        let successor = Pubkey::new_unique();
+        game.state.recovery_authority = successor;
+        game.state.pending_authority = successor;
+        let [mut state, ..] = game.fixtures();
+        let mut signer = Fixture::wallet(successor, 1_000_000_000).signer();
+
+        simulate([&mut state, &mut signer], &ix::AcceptAuthority {}.data()).unwrap();
+        let moved = state.read::<SlotsState>();
+        assert_eq!(moved.authority, successor);
+        assert_eq!(moved.recovery_authority, Pubkey::default());
+    }
+
+    /// A machine whose recovery key may take over after 1_000 slots
+    /// without an authority action, the last one now.
+    fn recoverable() -> (Game, Fixture) {
+        let mut game = Game::new();
+        let recovery = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        game.state.recovery_authority = recovery.key;
+        game.state.recovery_inactivity_slots = 1_000;
+        game.state.last_authority_action_slot = game.slot;
+        (game, recovery)
+    }
+
+    #[test]
+    fn recovery_waits_for_the_whole_inactivity_window() {
+        let (mut game, mut recovery) = recoverable();
+        let [mut state, ..] = game.fixtures();
+        let claim = ix::ClaimRecovery {}.data();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+
+        game.advance(999);
+        assert_eq!(
+            simulate([&mut state, &mut recovery], &claim),
+            Err(program_error(ErrorCode::RecoveryWindowNotElapsed))
+        );
+        game.advance(1);
+        assert_eq!(
+            simulate([&mut state, &mut stranger], &claim),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        emitted::<RecoveryClaimed>();
+        simulate([&mut state, &mut recovery], &claim).unwrap();
+
+        let recovered = state.read::<SlotsState>();
+        assert_eq!(recovered.authority, recovery.key);
+        assert_eq!(recovered.recovery_authority, Pubkey::default());
+        assert_eq!(recovered.last_authority_action_slot, game.slot);
+        let mut claims = emitted::<RecoveryClaimed>();
+        assert_eq!(claims.len(), 1);
+        let event = claims.pop().unwrap();
+        assert_eq!(event.previous_authority, game.state.authority);
+        assert_eq!(event.inactive_slots, 1_000);
+        assert_eq!(
+            simulate(
+                [&mut state, &mut game.authority(), &mut Fixture::omitted()],
+                &ix::Heartbeat {}.data()
+            ),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+    }
+
+    #[test]
+    fn a_heartbeat_restarts_the_inactivity_window() {
+        let (mut game, mut recovery) = recoverable();
+        let [mut state, ..] = game.fixtures();
+        let claim = ix::ClaimRecovery {}.data();
+
+        game.advance(900);
+        emitted::<AuthorityHeartbeat>();
+        simulate(
+            [&mut state, &mut game.authority(), &mut Fixture::omitted()],
+            &ix::Heartbeat {}.data(),
+        )
+        .unwrap();
+        let mut beats = emitted::<AuthorityHeartbeat>();
+        assert_eq!(beats.len(), 1);
+        assert_eq!(beats.pop().unwrap().slot, game.slot);
+        assert_eq!(state.read::<SlotsState>().last_authority_action_slot, game.slot);
+
+        // Past the window from the old action, not from the heartbeat.
+        game.advance(999);
+        assert_eq!(
+            simulate([&mut state, &mut recovery], &claim),
+            Err(program_error(ErrorCode::RecoveryWindowNotElapsed))
+        );
+        game.advance(1);
+        simulate([&mut state, &mut recovery], &claim).unwrap();
+        assert_eq!(state.read::<SlotsState>().authority, recovery.key);
+    }
+
+    #[test]
+    fn a_paytable_swaps_only_when_paused_idle_and_within_the_rtp_cap() {
+        let mut game = Game::new();
+        let mut busy = game.clone();
+        busy.request(DEFAULT_MIN_BET).unwrap();
+        busy.state.pause_flags = PAUSE_NEW_SPINS;
+        let running = game.clone();
+        game.state.pause_flags = PAUSE_NEW_SPINS;
+        let two_of_a_kind = game.state.paytable.two_of_a_kind;
+        let current = game.state.paytable.multipliers.map(|row| row[0]);
+        let set = |three_of_a_kind| ix::SetPaytable { two_of_a_kind, three_of_a_kind }.data();
+        let leaner = current.map(|m| m - m / 10);
+        let richer = current.map(|m| m * 10);
+
+        for (machine, three_of_a_kind, error) in [
+            (&busy, leaner, ErrorCode::PendingSpinExists),
+            (&running, leaner, ErrorCode::NotPaused),
+            (&game, richer, ErrorCode::PaytableRtpTooHigh),
+        ] {
+            let [mut state, ..] = machine.fixtures();
+            assert_eq!(
+                simulate(
+                    [&mut state, &mut machine.authority(), &mut Fixture::omitted()],
+                    &set(three_of_a_kind)
+                ),
+                Err(program_error(error))
+            );
+        }
+
+        let [mut state, ..] = game.fixtures();
+        emitted::<PaytableChanged>();
+        simulate([&mut state, &mut game.authority(), &mut Fixture::omitted()], &set(leaner))
+            .unwrap();
+        let mut changes = emitted::<PaytableChanged>();
+        assert_eq!(changes.len(), 1);
+        let change = changes.pop().unwrap();
+        assert_eq!(change.three_of_a_kind, leaner);
+        assert_eq!(change.two_of_a_kind, two_of_a_kind);
+        assert_eq!(change.authority, game.state.authority);
+        let swapped = state.read::<SlotsState>();
+        assert_eq!(swapped.paytable.multipliers.map(|row| row[0]), leaner);
+        assert!(change.implied_rtp_bps < game.state.theoretical_rtp_bps as u64);
+        assert!(swapped.theoretical_rtp_bps < game.state.theoretical_rtp_bps);
+    }
+
+    /// A machine frozen for a deploy while a spin committed by the old
+    /// code is revealed and waiting, and the player who owns it.
+    fn frozen_for_upgrade() -> (Game, Fixture) {
+        let mut game = Game::new();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        game.reveal([7; 32]);
+        game.player.commit_version = PROGRAM_VERSION - 1;
+
+        let [mut state, player, _] = game.fixtures();
+        emitted::<UpgradeFrozen>();
+        simulate(
+            [&mut state, &mut game.authority(), &mut Fixture::omitted()],
+            &ix::PreUpgradeFreeze {}.data(),
+        )
+        .unwrap();
+        let mut frozen = emitted::<UpgradeFrozen>();
+        assert_eq!(frozen.len(), 1);
+        let event = frozen.pop().unwrap();
+        assert_eq!((event.frozen_version, event.pending_spins), (PROGRAM_VERSION, 1));
+        game.state = state.read();
+        (game, player)
+    }
+
+    fn thaw(game: &Game, player: Fixture, compatible: bool) -> (SlotsState, PlayerState) {
+        let [mut state, ..] = game.fixtures();
+        let mut player = Fixture {
+            writable: true,
+            ..player
+        };
+        simulate(
+            [&mut state, &mut game.authority(), &mut Fixture::omitted(), &mut player],
+            &ix::PostUpgradeThaw { compatible }.data(),
+        )
+        .unwrap();
+        (state.read(), player.read())
+    }
+
+    #[test]
+    fn a_frozen_machine_refuses_spins_settles_and_withdrawals() {
+        let (game, _) = frozen_for_upgrade();
+        assert_eq!(game.state.pause_flags, PAUSE_ALL);
+        assert_eq!(game.state.frozen_version, PROGRAM_VERSION);
+        let paused: Error = ErrorCode::GamePaused.into();
+
+        assert_eq!(game.clone().settle().unwrap_err(), paused);
+        let mut next = game.next_player();
+        assert_eq!(next.request(DEFAULT_MIN_BET).unwrap_err(), paused);
+
+        let [state, _, treasury] = game.fixtures();
+        let lp = Pubkey::new_unique();
+        let (position, bump) = pda(&[POOL_POSITION_SEED, state.key.as_ref(), lp.as_ref()]);
+        let shares = PoolPosition {
+            owner: lp,
+            slots_state: state.key,
+            shares: 1_000,
+            deposited: 1_000,
+            withdrawn: 0,
+            bump,
+            share_epoch: game.state.share_epoch,
+        };
+        let mut withdrawing = vec![
+            state,
+            Fixture::wallet(lp, 1_000_000_000).signer(),
+            treasury,
+            Fixture::account(position, &shares),
+            system(),
+        ];
+        assert_eq!(
+            simulate(&mut withdrawing, &ix::WithdrawFromPool { shares: 1_000 }.data()),
+            Err(program_error(ErrorCode::GamePaused))
+        );
+
+        // Neither a manual unpause nor a second freeze gets around it.
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        for data in [
+            ix::SetPauseFlags { flags: 0 }.data(),
+            ix::PreUpgradeFreeze {}.data(),
+        ] {
+            assert_eq!(
+                simulate([&mut state, &mut authority, &mut Fixture::omitted()], &data),
+                Err(program_error(ErrorCode::UpgradeFrozen))
+            );
+        }
+    }
+
+    #[test]
+    fn a_compatible_thaw_lets_the_old_spin_settle() {
+        let (mut game, player) = frozen_for_upgrade();
+        emitted::<UpgradeThawed>();
+        let (state, player) = thaw(&game, player, true);
+
+        let mut thawed = emitted::<UpgradeThawed>();
+        assert_eq!(thawed.len(), 1);
+        let event = thawed.pop().unwrap();
+        assert!(event.compatible);
+        assert_eq!(event.voided, 0);
+        assert_eq!((state.pause_flags, state.frozen_version), (0, 0));
+        assert!(player.has_pending_spin);
+        game.state = state;
+        game.settle().unwrap();
+        assert_eq!(game.state.pending_spins, 0);
+    }
+
+    #[test]
+    fn an_incompatible_thaw_refunds_the_old_spin() {
+        let (game, player) = frozen_for_upgrade();
+        emitted::<UpgradeThawed>();
+        let (state, player) = thaw(&game, player, false);
+
+        let mut thawed = emitted::<UpgradeThawed>();
+        assert_eq!(thawed.len(), 1);
+        let event = thawed.pop().unwrap();
+        assert!(!event.compatible);
+        assert_eq!(event.voided, 1);
+        assert_eq!((state.pause_flags, state.frozen_version), (0, 0));
+        assert_eq!(state.min_settle_version, PROGRAM_VERSION);
+        assert!(!player.has_pending_spin);
+        assert_eq!(player.claimable_balance, DEFAULT_MIN_BET);
+        assert_eq!(state.total_claimable, DEFAULT_MIN_BET);
+        assert_eq!(state.pending_spins, 0);
+        assert_eq!(state.total_wagered, 0);
+        accounting::verify(&state, game.treasury, None).expect("books hold");
+    }
+}
@@ -0,0 +1,615 @@
+//! Handlers of the jackpot instructions: bounds, caps, weights, reseeds
+//! and the wager races and leaderboards that share its funding.
+
+use crate::*;
+
+pub fn boost_jackpot(ctx: Context<AddToPool>, tier: u8, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    pause::ensure_not_paused(slots_state, PAUSE_DEPOSITS)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(tier <= JACKPOT_TIER_GRAND, ErrorCode::InvalidJackpotTier);
+    require!(
+        jackpot_pool_mut(&mut slots_state.jackpots, tier).hit_weight > 0,
+        ErrorCode::JackpotTierDisabled
+    );
+    require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, amount)?;
+
+    // Jackpot amounts are part of total_pool, so the whole donation
+    // joins the pool whether or not the tier can take it.
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let cap = slots_state.jackpot_max_caps[tier as usize];
+    let pool = jackpot_pool_mut(&mut slots_state.jackpots, tier);
+    let credited = if cap == 0 {
+        amount
+    } else {
+        core::cmp::min(amount, cap.saturating_sub(pool.amount))
+    };
+    pool.amount = pool
+        .amount
+        .checked_add(credited)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let new_tier_amount = pool.amount;
+
+    let boosted = &mut slots_state.jackpot_boosted_totals[tier as usize];
+    *boosted = boosted.saturating_add(credited);
+    let tier_boosted_total = *boosted;
+    must_hit::refresh(slots_state);
+
+    emit!(JackpotBoosted {
+        donor: user.key(),
+        tier,
+        amount,
+        credited_to_tier: credited,
+        new_tier_amount,
+        tier_boosted_total,
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_max_cap(ctx: Context<EmergencyPause>, tier: u8, cap: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(tier <= JACKPOT_TIER_GRAND, ErrorCode::InvalidJackpotTier);
+    let amount = jackpot_pool_mut(&mut slots_state.jackpots, tier).amount;
+    require!(cap == 0 || cap >= amount, ErrorCode::JackpotCapBelowAmount);
+
+    let old_cap = std::mem::replace(&mut slots_state.jackpot_max_caps[tier as usize], cap);
+
+    emit!(JackpotMaxCapChanged {
+        tier,
+        old_cap,
+        new_cap: cap,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn estimate_jackpot_eta(ctx: Context<ViewState>) -> Result<JackpotEtaReport> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let clock = Clock::get()?;
+    let (spin_weight, wager_weight) = risk::decayed_traffic(
+        slots_state.traffic_spin_weight,
+        slots_state.traffic_wager_weight,
+        slots_state.traffic_last_update_ts,
+        clock.unix_timestamp,
+        slots_state.traffic_half_life_secs,
+    );
+
+    Ok(risk::estimate_eta(
+        &slots_state.jackpots,
+        spin_weight,
+        wager_weight,
+        slots_state.traffic_half_life_secs,
+    ))
+}
+
+pub fn get_jackpot_rebuild_status(ctx: Context<ViewState>) -> Result<JackpotRebuildReport> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    Ok(reseed::rebuild_report(
+        &slots_state.jackpots,
+        slots_state.jackpot_reseed_policies,
+    ))
+}
+
+pub fn create_wager_race(
+    ctx: Context<CreateWagerRace>,
+    race_id: u32,
+    start_slot: u64,
+    end_slot: u64,
+    prize_budget: u64,
+    prize_split_bps: [u16; WAGER_RACE_BOARD_SIZE],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        race_id == slots_state.wager_race_count + 1
+            && end_slot > start_slot
+            && !time::has_reached(end_slot, Clock::get()?.slot)
+            && prize_budget > 0
+            && wager_race::split_valid(&prize_split_bps),
+        ErrorCode::InvalidWagerRace
+    );
+    let spendable = slots_state
+        .total_pool
+        .saturating_sub(required_floor(slots_state)?);
+    require!(prize_budget <= spendable, ErrorCode::InsufficientPool);
+
+    slots_state
+        .earmarks
+        .earmark(EarmarkKind::PendingAward, prize_budget)?;
+    slots_state.wager_race_count = race_id;
+
+    let race = &mut ctx.accounts.wager_race;
+    race.slots_state = slots_state.key();
+    race.race_id = race_id;
+    race.start_slot = start_slot;
+    race.end_slot = end_slot;
+    race.prize_budget = prize_budget;
+    race.prize_split_bps = prize_split_bps;
+    race.board = [WagerRaceEntry::default(); WAGER_RACE_BOARD_SIZE];
+    race.finalized = false;
+    race.bump = ctx.bumps.wager_race;
+
+    emit!(WagerRaceCreated {
+        race_id,
+        start_slot,
+        end_slot,
+        prize_budget,
+    });
+
+    Ok(())
+}
+
+pub fn finalize_wager_race<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FinalizeWagerRace<'info>>,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let race = &mut ctx.accounts.wager_race;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require!(!race.finalized, ErrorCode::WagerRaceClosed);
+    require!(
+        time::has_reached(race.end_slot, Clock::get()?.slot),
+        ErrorCode::WagerRaceNotEnded
+    );
+
+    let ranked = race.board.iter().take_while(|e| !e.is_empty()).count();
+    require!(
+        ctx.remaining_accounts.len() >= ranked,
+        ErrorCode::WagerRaceWinnerMismatch
+    );
+
+    let mut paid = 0u64;
+    for (rank, (entry, ai)) in race
+        .board
+        .iter()
+        .zip(ctx.remaining_accounts.iter())
+        .take(ranked)
+        .enumerate()
+    {
+        let mut player_state: Account<'info, PlayerState> = Account::try_from(ai)?;
+        require_keys_eq!(
+            player_state.owner,
+            entry.player,
+            ErrorCode::WagerRaceWinnerMismatch
+        );
+        let prize = race.prize_for(rank);
+        player_state.claimable_balance = player_state
+            .claimable_balance
+            .checked_add(prize)
+            .ok_or(ErrorCode::MathOverflow)?;
+        player_state.exit(&crate::ID)?;
+        paid = paid.checked_add(prize).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(WagerRacePrize {
+            race_id: race.race_id,
+            rank: rank as u8,
+            player: entry.player,
+            wagered: entry.wagered,
+            prize,
+        });
+    }
+
+    // Prizes leave the pool for the claimable balances; the rest of
+    // the budget simply stops being earmarked.
+    slots_state
+        .earmarks
+        .release(EarmarkKind::PendingAward, race.prize_budget)?;
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_sub(paid)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_claimable = slots_state
+        .total_claimable
+        .checked_add(paid)
+        .ok_or(ErrorCode::MathOverflow)?;
+    feature_stats::record_paid(slots_state, Feature::Promos, paid)?;
+    race.finalized = true;
+    pool_level::refresh(slots_state);
+
+    emit!(WagerRaceFinalized {
+        race_id: race.race_id,
+        paid,
+        returned: race.prize_budget - paid,
+        cancelled: false,
+    });
+
+    Ok(())
+}
+
+pub fn cancel_wager_race(ctx: Context<CancelWagerRace>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let race = &mut ctx.accounts.wager_race;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(!race.finalized, ErrorCode::WagerRaceClosed);
+
+    slots_state
+        .earmarks
+        .release(EarmarkKind::PendingAward, race.prize_budget)?;
+    race.finalized = true;
+
+    emit!(WagerRaceFinalized {
+        race_id: race.race_id,
+        paid: 0,
+        returned: race.prize_budget,
+        cancelled: true,
+    });
+
+    Ok(())
+}
+
+pub fn create_leaderboard(ctx: Context<CreateLeaderboard>, epoch: u64) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require!(
+        epoch >= leaderboard::epoch_at(slots_state, Clock::get()?.slot),
+        ErrorCode::LeaderboardEpochEnded
+    );
+
+    let board = &mut ctx.accounts.leaderboard;
+    board.slots_state = slots_state.key();
+    board.epoch = epoch;
+    board.epoch_slots = slots_state.leaderboard_epoch_slots;
+    board.entries = [LeaderboardEntry::default(); LEADERBOARD_SIZE];
+    board.bump = ctx.bumps.leaderboard;
+
+    emit!(LeaderboardCreated {
+        epoch,
+        epoch_slots: board.epoch_slots,
+        payer: ctx.accounts.payer.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_leaderboard_epoch(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots > 0, ErrorCode::InvalidAmount);
+
+    slots_state.leaderboard_epoch_slots = slots;
+
+    emit!(LeaderboardEpochChanged {
+        leaderboard_epoch_slots: slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_batch_mode(ctx: Context<EmergencyPause>, mode: JackpotBatchMode) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.jackpot_batch_mode = mode;
+
+    emit!(JackpotBatchModeChanged {
+        mode,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_qualifying_bet(ctx: Context<EmergencyPause>, qualifying_bet: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    slots_state.jackpot_qualifying_bet = qualifying_bet;
+
+    emit!(JackpotQualifyingBetChanged {
+        qualifying_bet,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_reseed_policy(
+    ctx: Context<EmergencyPause>,
+    tier: u8,
+    policy: ReseedPolicy,
+    rebuild_bps: u16,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(tier <= JACKPOT_TIER_GRAND, ErrorCode::InvalidJackpotTier);
+    require!(rebuild_bps <= 10_000, ErrorCode::InvalidReseedConfig);
+    require!(
+        policy == ReseedPolicy::ReserveFunded || rebuild_bps > 0,
+        ErrorCode::InvalidReseedConfig
+    );
+
+    slots_state.jackpot_reseed_policies[tier as usize] = policy;
+    slots_state.jackpot_rebuild_bps[tier as usize] = rebuild_bps;
+
+    emit!(JackpotReseedPolicyChanged {
+        tier,
+        policy,
+        rebuild_bps,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_bounds(
+    ctx: Context<EmergencyPause>,
+    max_hit_ppm: u32,
+    max_cost_bps: u16,
+    reference_bet: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        max_hit_ppm <= jackpot_bounds::ABSOLUTE_MAX_TIER_HIT_PPM
+            && max_cost_bps <= 10_000
+            && reference_bet > 0,
+        ErrorCode::InvalidJackpotBounds
+    );
+
+    slots_state.jackpot_max_hit_ppm = max_hit_ppm;
+    slots_state.jackpot_max_cost_bps = max_cost_bps;
+    slots_state.jackpot_cost_reference_bet = reference_bet;
+
+    emit!(JackpotBoundsChanged {
+        max_hit_ppm,
+        max_cost_bps,
+        reference_bet,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_jackpot_weights(ctx: Context<EmergencyPause>, weights: JackpotWeights) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    jackpot_bounds::check(slots_state, &weights)?;
+
+    jackpot_bounds::apply(&mut slots_state.jackpots, &weights)?;
+
+    emit!(JackpotWeightsChanged {
+        weights,
+        overridden: false,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn propose_jackpot_weights(
+    ctx: Context<EmergencyPause>,
+    weights: JackpotWeights,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    jackpot_bounds::check_absolute(&weights)?;
+
+    let activate_slot = Clock::get()?
+        .slot
+        .checked_add(JACKPOT_WEIGHTS_TIMELOCK_SLOTS)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.pending_jackpot_weights = weights;
+    slots_state.pending_jackpot_weights_activate_slot = activate_slot;
+
+    emit!(JackpotWeightsProposed {
+        weights,
+        activate_slot,
+    });
+
+    Ok(())
+}
+
+pub fn activate_jackpot_weights(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    let activate_slot = slots_state.pending_jackpot_weights_activate_slot;
+    require!(activate_slot != 0, ErrorCode::InvalidJackpotWeights);
+    require!(
+        time::has_reached(activate_slot, Clock::get()?.slot),
+        ErrorCode::JackpotWeightsTimelocked
+    );
+
+    let weights = slots_state.pending_jackpot_weights;
+    jackpot_bounds::apply(&mut slots_state.jackpots, &weights)?;
+    slots_state.pending_jackpot_weights = JackpotWeights::default();
+    slots_state.pending_jackpot_weights_activate_slot = 0;
+
+    emit!(JackpotWeightsChanged {
+        weights,
+        overridden: true,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::instruction as ix;
+    use crate::test_util::{emitted, program_error, simulate, Fixture, Game, GAME_WALLET};
+
+    const DONATION: u64 = 1_000_000_000;
+    const GRAND: usize = JACKPOT_TIER_GRAND as usize;
+
+    /// boost_jackpot's accounts, with a fresh donor wallet.
+    fn boosting(game: &Game) -> Vec<Fixture> {
+        let [state, _, treasury] = game.fixtures();
+        vec![
+            state,
+            Fixture::wallet(Pubkey::new_unique(), GAME_WALLET).signer(),
+            treasury,
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    fn boost(tier: u8, amount: u64) -> Vec<u8> {
+        ix::BoostJackpot { tier, amount }.data()
+    }
+
+    #[test]
+    fn a_boost_past_the_cap_leaves_the_rest_in_the_pool() {
+        let mut game = Game::new();
+        let grand = game.state.jackpots.grand.amount;
+        game.state.jackpot_max_caps[GRAND] = grand + DONATION / 4;
+        let mut accounts = boosting(&game);
+        emitted::<JackpotBoosted>();
+        simulate(&mut accounts, &boost(JACKPOT_TIER_GRAND, DONATION)).unwrap();
+
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!(state.jackpots.grand.amount, grand + DONATION / 4);
+        assert_eq!(state.total_pool, game.state.total_pool + DONATION);
+        assert_eq!(state.jackpot_boosted_totals[GRAND], DONATION / 4);
+        let mut events = emitted::<JackpotBoosted>();
+        assert_eq!(events.len(), 1);
+        let event = events.pop().unwrap();
+        assert_eq!(event.donor, accounts[1].key);
+        assert_eq!(event.amount, DONATION);
+        assert_eq!(event.credited_to_tier, DONATION / 4);
+        assert_eq!(event.new_tier_amount, state.jackpots.grand.amount);
+
+        // A tier at its cap takes nothing more; the pool still does.
+        simulate(&mut accounts, &boost(JACKPOT_TIER_GRAND, DONATION)).unwrap();
+        let full = accounts[0].read::<SlotsState>();
+        assert_eq!(full.jackpots.grand.amount, state.jackpots.grand.amount);
+        assert_eq!(full.total_pool, state.total_pool + DONATION);
+        assert_eq!(emitted::<JackpotBoosted>().pop().unwrap().credited_to_tier, 0);
+    }
+
+    #[test]
+    fn a_disabled_tier_or_paused_deposits_refuse_a_boost() {
+        let mut game = Game::new();
+        game.state.jackpots.mini.hit_weight = 0;
+        let mut accounts = boosting(&game);
+        assert_eq!(
+            simulate(&mut accounts, &boost(game_math::JACKPOT_TIER_MINI, DONATION)),
+            Err(program_error(ErrorCode::JackpotTierDisabled))
+        );
+        assert_eq!(
+            simulate(&mut accounts, &boost(JACKPOT_TIER_GRAND + 1, DONATION)),
+            Err(program_error(ErrorCode::InvalidJackpotTier))
+        );
+
+        game.state.pause_flags = PAUSE_DEPOSITS;
+        let mut accounts = boosting(&game);
+        assert_eq!(
+            simulate(&mut accounts, &boost(JACKPOT_TIER_GRAND, DONATION)),
+            Err(program_error(ErrorCode::GamePaused))
+        );
+        assert_eq!(accounts[1].lamports, GAME_WALLET);
+    }
+
+    #[test]
+    fn the_donor_gets_no_claim_on_a_boost() {
+        let game = Game::new();
+        let mut accounts = boosting(&game);
+        let rent = accounts[2].lamports - game.treasury;
+        simulate(&mut accounts, &boost(JACKPOT_TIER_GRAND, DONATION)).unwrap();
+
+        // The lamports left the donor for the pool: no LP shares, no
+        // claimable balance and no deposit booked to anyone.
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!(accounts[1].lamports, GAME_WALLET - DONATION);
+        assert_eq!(state.total_shares, game.state.total_shares);
+        assert_eq!(state.total_claimable, game.state.total_claimable);
+        assert_eq!(state.total_deposits, game.state.total_deposits);
+        accounting::verify(&state, accounts[2].lamports - rent, None).expect("books hold");
+    }
+
+    #[test]
+    fn a_cap_below_the_tier_is_refused_and_a_change_reported() {
+        let game = Game::new();
+        let grand = game.state.jackpots.grand.amount;
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), GAME_WALLET).signer();
+        let mut no_metrics = Fixture::omitted();
+        let cap = |cap| {
+            ix::SetJackpotMaxCap {
+                tier: JACKPOT_TIER_GRAND,
+                cap,
+            }
+            .data()
+        };
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &cap(grand)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &cap(grand - 1)),
+            Err(program_error(ErrorCode::JackpotCapBelowAmount))
+        );
+
+        emitted::<JackpotMaxCapChanged>();
+        simulate([&mut state, &mut authority, &mut no_metrics], &cap(grand)).unwrap();
+        simulate([&mut state, &mut authority, &mut no_metrics], &cap(0)).unwrap();
+        assert_eq!(state.read::<SlotsState>().jackpot_max_caps[GRAND], 0);
+        let events = emitted::<JackpotMaxCapChanged>();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].old_cap, events[0].new_cap), (0, grand));
+        assert_eq!((events[1].old_cap, events[1].new_cap), (grand, 0));
+        assert_eq!(events[1].tier, JACKPOT_TIER_GRAND);
+        assert_eq!(events[1].authority, authority.key);
+    }
+}
@@ -0,0 +1,9 @@
+//! Instruction handlers, one module per area. The `#[program]` module in
+//! lib.rs keeps each instruction's name, docs and arguments, which the IDL
+//! is built from, and delegates its body here.
+
+pub mod admin;
+pub mod jackpot;
+pub mod player;
+pub mod pool;
+pub mod spin;
@@ -0,0 +1,321 @@
+//! Handlers of the player instructions: account setup, claimables,
+//! responsible-gaming limits, referrals, rebates and tiers.
+
+use crate::*;
+
+pub fn init_player(ctx: Context<InitPlayer>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    init_player_state(
+        &mut ctx.accounts.player_state,
+        ctx.accounts.user.key(),
+        ctx.bumps.player_state,
+        machines::indexes_players(slots_state).then_some(&*ctx.accounts.account_index),
+    )
+}
+
+pub fn init_player_sponsored(ctx: Context<InitPlayerSponsored>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let now_ts = Clock::get()?.unix_timestamp;
+    let sponsored_today = onboarding::consume_budget(slots_state, now_ts)?;
+
+    let user = ctx.accounts.user.key();
+    init_player_state(
+        &mut ctx.accounts.player_state,
+        user,
+        ctx.bumps.player_state,
+        machines::indexes_players(slots_state).then_some(&*ctx.accounts.account_index),
+    )?;
+
+    emit!(PlayerOnboarded {
+        user,
+        sponsor: ctx.accounts.sponsor.key(),
+        sponsored_today,
+        daily_budget: slots_state.sponsored_daily_budget,
+    });
+
+    Ok(())
+}
+
+pub fn init_account_index(ctx: Context<InitAccountIndex>) -> Result<()> {
+    let index = &mut ctx.accounts.account_index;
+    index.owner = ctx.accounts.user.key();
+    index.bump = ctx.bumps.account_index;
+
+    if account_index::exists(&ctx.accounts.player_state) {
+        let bump = ctx.bumps.player_state;
+        index.mark_created(INDEX_KIND_PLAYER_STATE, bump)?;
+    }
+    Ok(())
+}
+
+pub fn withdraw_claimable(ctx: Context<WithdrawClaimable>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require_sol_mode(slots_state)?;
+
+    let amount = player_state.claimable_balance;
+    require!(amount > 0, ErrorCode::NothingClaimable);
+
+    player_state.claimable_balance = 0;
+    slots_state.total_claimable = slots_state.total_claimable.saturating_sub(amount);
+
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        treasury,
+        user.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(
+        slots_state,
+        OutflowCategory::Refund,
+        amount,
+        user.key(),
+        [0; 32],
+    )?;
+
+    emit!(ClaimableWithdrawn {
+        user: user.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn withdraw_claimable_token(ctx: Context<WithdrawClaimableToken>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    token_mode::require_token_mode(slots_state, &ctx.accounts.mint.key())?;
+
+    let amount = player_state.claimable_balance;
+    require!(amount > 0, ErrorCode::NothingClaimable);
+
+    player_state.claimable_balance = 0;
+    slots_state.total_claimable = slots_state.total_claimable.saturating_sub(amount);
+
+    token_mode::transfer_from_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.treasury_token,
+        &ctx.accounts.user_token,
+        &ctx.accounts.treasury,
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(
+        slots_state,
+        OutflowCategory::Refund,
+        amount,
+        user.key(),
+        [0; 32],
+    )?;
+
+    emit!(ClaimableWithdrawn {
+        user: user.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn set_self_exclusion(ctx: Context<SetPlayerLimits>, until_slot: u64) -> Result<()> {
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    require!(
+        ctx.accounts.slots_state.initialized,
+        ErrorCode::Uninitialized
+    );
+    require!(
+        !time::has_reached(until_slot, Clock::get()?.slot),
+        ErrorCode::InvalidAmount
+    );
+    require!(
+        until_slot >= player_state.self_excluded_until,
+        ErrorCode::SelfExclusionShortened
+    );
+
+    player_state.self_excluded_until = until_slot;
+
+    emit!(SelfExclusionSet {
+        user: user.key(),
+        until_slot,
+    });
+
+    Ok(())
+}
+
+pub fn set_loss_limit(ctx: Context<SetPlayerLimits>, loss_limit_lamports: u64) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    let now = Clock::get()?.slot;
+    let losses = responsible_gaming::window_losses(slots_state, player_state, now);
+    require!(
+        responsible_gaming::limit_change_allowed(
+            player_state.loss_limit_lamports,
+            loss_limit_lamports,
+            losses
+        ),
+        ErrorCode::LossLimitLocked
+    );
+
+    player_state.loss_limit_lamports = loss_limit_lamports;
+
+    emit!(LossLimitSet {
+        user: user.key(),
+        loss_limit_lamports,
+    });
+
+    Ok(())
+}
+
+pub fn set_referrer(ctx: Context<SetReferrer>, referrer: Pubkey) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let earnings = &mut ctx.accounts.referral_earnings;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!(
+        referrer != user.key() && referrer != Pubkey::default(),
+        ErrorCode::SelfReferral
+    );
+    require!(
+        player_state.referrer == Pubkey::default(),
+        ErrorCode::ReferrerAlreadySet
+    );
+    require!(
+        player_state.spin_nonce == 0 && !player_state.has_pending_spin,
+        ErrorCode::ReferrerAfterFirstSpin
+    );
+
+    // init_if_needed hands over a zeroed account when it just made it.
+    if earnings.referrer == Pubkey::default() {
+        earnings.referrer = referrer;
+        earnings.slots_state = slots_state.key();
+        earnings.bump = ctx.bumps.referral_earnings;
+    }
+    player_state.referrer = referrer;
+
+    emit!(ReferrerSet {
+        user: user.key(),
+        referrer,
+    });
+
+    Ok(())
+}
+
+pub fn claim_referral_earnings(ctx: Context<ClaimReferralEarnings>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let referrer = &ctx.accounts.referrer;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+
+    let amount = referrals::take(slots_state, &mut ctx.accounts.referral_earnings)?;
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        &ctx.accounts.treasury,
+        referrer.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(
+        slots_state,
+        OutflowCategory::Fee,
+        amount,
+        referrer.key(),
+        [0; 32],
+    )?;
+
+    emit!(ReferralEarningsClaimed {
+        referrer: referrer.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn claim_rebate(ctx: Context<ClaimRebate>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+
+    let amount = vip::take(slots_state, &mut ctx.accounts.player_state)?;
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        &ctx.accounts.treasury,
+        user.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(
+        slots_state,
+        OutflowCategory::Refund,
+        amount,
+        user.key(),
+        [0; 32],
+    )?;
+
+    emit!(VipRebateClaimed {
+        user: user.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn redeem_free_spin(ctx: Context<RedeemFreeSpin>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    loyalty::redeem(slots_state, player_state, &ctx.accounts.user.key())?;
+    pool_level::refresh(slots_state);
+
+    Ok(())
+}
+
+pub fn set_player_tier(ctx: Context<SetPlayerTier>, player: Pubkey, tier: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        tier as usize <= MAX_PLAYER_TIERS,
+        ErrorCode::InvalidPlayerTier
+    );
+
+    let player_state = &mut ctx.accounts.player_state;
+    let old_tier = player_state.tier;
+    player_state.tier = tier;
+
+    emit!(PlayerTierChanged {
+        player,
+        old_tier,
+        new_tier: tier,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
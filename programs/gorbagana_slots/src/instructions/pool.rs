@@ -0,0 +1,1183 @@
+//! Handlers of the pool instructions: deposits and withdrawals, the
+//! withdrawal timelock, the token treasury, accounting checks, the
+//! community vault and the operator fee.
+
+use crate::*;
+
+pub fn add_to_pool(ctx: Context<DepositToPool>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+    let position = &mut ctx.accounts.pool_position;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    pause::ensure_not_paused(slots_state, PAUSE_DEPOSITS)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, amount)?;
+
+    // init_if_needed hands over a zeroed account when it just made it.
+    if position.owner == Pubkey::default() {
+        position.owner = user.key();
+        position.slots_state = slots_state.key();
+        position.bump = ctx.bumps.pool_position;
+    }
+    let shares = pool_shares::mint(slots_state, position, amount)?;
+
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_deposits = slots_state
+        .total_deposits
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(PoolDeposit {
+        user: user.key(),
+        amount,
+        new_pool: slots_state.total_pool,
+        shares,
+    });
+
+    refresh_bootstrap(slots_state);
+    pool_level::refresh(slots_state);
+
+    Ok(())
+}
+
+pub fn withdraw_from_pool(ctx: Context<WithdrawFromPool>, shares: u128) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+    let position = &mut ctx.accounts.pool_position;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    pause::ensure_not_paused(slots_state, PAUSE_WITHDRAWALS)?;
+
+    // Priced and floored net of the pending spins' reservations, so an
+    // LP cannot leave ahead of a win they see coming.
+    let amount = pool_shares::burn(slots_state, position, shares)?;
+    let must_keep = required_floor(slots_state)?;
+    require!(
+        slots_state.total_pool.saturating_sub(must_keep) >= amount,
+        ErrorCode::PoolReserveBreached
+    );
+    slots_state.total_pool -= amount;
+
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        &ctx.accounts.treasury,
+        user.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(
+        slots_state,
+        OutflowCategory::LpWithdrawal,
+        amount,
+        user.key(),
+        [0; 32],
+    )?;
+    pool_level::refresh(slots_state);
+
+    emit!(PoolWithdrawal {
+        user: user.key(),
+        shares,
+        amount,
+        new_pool: slots_state.total_pool,
+    });
+
+    Ok(())
+}
+
+pub fn fund_fee_balance(ctx: Context<AddToPool>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, amount)?;
+
+    slots_state.fee_balance = slots_state
+        .fee_balance
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(FeeBalanceFunded {
+        funder: user.key(),
+        amount,
+        new_fee_balance: slots_state.fee_balance,
+    });
+
+    Ok(())
+}
+
+pub fn fund_faucet(ctx: Context<AddToPool>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    qa::require_test_network(slots_state)?;
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, user.key())?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, amount)?;
+
+    slots_state.faucet_reserve = slots_state
+        .faucet_reserve
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(FaucetFunded {
+        funder: user.key(),
+        amount,
+        new_reserve: slots_state.faucet_reserve,
+    });
+
+    Ok(())
+}
+
+pub fn set_faucet_config(
+    ctx: Context<EmergencyPause>,
+    amount: u64,
+    interval_slots: u64,
+    lifetime_cap: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    qa::require_test_network(slots_state)?;
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        amount == 0 || (interval_slots > 0 && lifetime_cap >= amount),
+        ErrorCode::InvalidConfig
+    );
+
+    slots_state.faucet_amount = amount;
+    slots_state.faucet_interval_slots = interval_slots;
+    slots_state.faucet_lifetime_cap = lifetime_cap;
+
+    emit!(FaucetConfigChanged {
+        amount,
+        interval_slots,
+        lifetime_cap,
+    });
+    Ok(())
+}
+
+pub fn devnet_faucet(ctx: Context<DepositSession>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    qa::require_test_network(slots_state)?;
+    require_sol_mode(slots_state)?;
+
+    let now = Clock::get()?.slot;
+    let amount = faucet::next_drip(slots_state, player_state, now)?;
+    faucet::drip(slots_state, player_state, amount, now)?;
+
+    emit!(FaucetDrip {
+        user: ctx.accounts.user.key(),
+        amount,
+        received_total: player_state.faucet_received,
+        next_drip_slot: now.saturating_add(slots_state.faucet_interval_slots),
+        reserve_left: slots_state.faucet_reserve,
+    });
+
+    Ok(())
+}
+
+pub fn set_winner_rebate(
+    ctx: Context<EmergencyPause>,
+    rebate_lamports: u64,
+    daily_cap_per_player: u64,
+    suspend_below: u64,
+    resume_at: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(resume_at >= suspend_below, ErrorCode::InvalidRebateConfig);
+    require!(
+        rebate_lamports == 0 || daily_cap_per_player >= rebate_lamports,
+        ErrorCode::InvalidRebateConfig
+    );
+
+    slots_state.winner_fee_rebate_lamports = rebate_lamports;
+    slots_state.rebate_daily_cap_per_player = daily_cap_per_player;
+    slots_state.rebate_suspend_below = suspend_below;
+    slots_state.rebate_resume_at = resume_at;
+
+    Ok(())
+}
+
+pub fn set_fee_overflow_policy(
+    ctx: Context<EmergencyPause>,
+    enabled: bool,
+    max_per_spin: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(!enabled || max_per_spin > 0, ErrorCode::InvalidAmount);
+
+    slots_state.use_fees_for_overflow = enabled;
+    slots_state.fee_overflow_max_per_spin = max_per_spin;
+
+    Ok(())
+}
+
+pub fn request_withdrawal(
+    ctx: Context<EmergencyPause>,
+    amount: u64,
+    category: u8,
+    memo: [u8; 32],
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    let category = outflows::validate(OutflowSource::OperatorWithdrawal, category, &memo)?;
+
+    let available = slots_state
+        .total_pool
+        .saturating_sub(required_floor(slots_state)?);
+    require!(amount <= available, ErrorCode::InsufficientPool);
+
+    let clock = Clock::get()?;
+    let unlock_slot =
+        withdrawal_timelock::request(slots_state, amount, category, memo, clock.slot)?;
+    pool_level::refresh(slots_state);
+
+    emit!(WithdrawalRequested {
+        amount,
+        unlock_slot,
+        category: category as u8,
+        memo,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn cancel_withdrawal(ctx: Context<EmergencyPause>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+
+    let amount = withdrawal_timelock::cancel(slots_state)?;
+    pool_level::refresh(slots_state);
+
+    emit!(WithdrawalCancelled {
+        amount,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn set_withdrawal_delay(ctx: Context<EmergencyPause>, delay_slots: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        delay_slots >= MIN_WITHDRAWAL_DELAY_SLOTS,
+        ErrorCode::WithdrawalDelayTooShort
+    );
+
+    slots_state.withdrawal_delay_slots = delay_slots;
+
+    emit!(WithdrawalDelayChanged {
+        delay_slots,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    pause::ensure_not_paused(slots_state, PAUSE_WITHDRAWALS)?;
+
+    let clock = Clock::get()?;
+    let withdrawal_timelock::MaturedWithdrawal {
+        amount,
+        category,
+        memo,
+    } = withdrawal_timelock::execute(
+        slots_state,
+        clock.slot,
+        accounting::spendable_lamports(treasury)?,
+    )?;
+
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        treasury,
+        authority.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(slots_state, category, amount, authority.key(), memo)?;
+    pool_level::refresh(slots_state);
+
+    refresh_metrics_if_present(&ctx.accounts.metrics, slots_state, clock.slot)?;
+
+    emit!(WithdrawalExecuted {
+        amount,
+        category: category as u8,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn init_token_treasury(ctx: Context<InitTokenTreasury>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+    let mint = &ctx.accounts.mint;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(
+        slots_state.exclusive_mint() == Some(mint.key()),
+        ErrorCode::WrongCurrencyMode
+    );
+
+    slots_state.token_treasury = ctx.accounts.treasury_token.key();
+    slots_state.token_decimals = mint.decimals;
+
+    emit!(TokenTreasuryInitialized {
+        mint: mint.key(),
+        token_treasury: slots_state.token_treasury,
+        decimals: mint.decimals,
+    });
+
+    Ok(())
+}
+
+pub fn add_to_pool_token(ctx: Context<AddToPoolToken>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let user = &ctx.accounts.user;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    token_mode::require_token_mode(slots_state, &ctx.accounts.mint.key())?;
+    require_authority(slots_state, user.key())?;
+    pause::ensure_not_paused(slots_state, PAUSE_DEPOSITS)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(
+        ctx.accounts.user_token.amount >= amount,
+        ErrorCode::InsufficientFunds
+    );
+
+    token_mode::transfer_to_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.user_token,
+        &ctx.accounts.treasury_token,
+        user,
+        amount,
+    )?;
+
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_deposits = slots_state
+        .total_deposits
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(PoolDeposit {
+        user: user.key(),
+        amount,
+        new_pool: slots_state.total_pool,
+        shares: 0,
+    });
+
+    refresh_bootstrap(slots_state);
+    pool_level::refresh(slots_state);
+
+    Ok(())
+}
+
+pub fn execute_withdrawal_token(ctx: Context<ExecuteWithdrawalToken>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    token_mode::require_token_mode(slots_state, &ctx.accounts.mint.key())?;
+    require_authority(slots_state, authority.key())?;
+    pause::ensure_not_paused(slots_state, PAUSE_WITHDRAWALS)?;
+
+    let clock = Clock::get()?;
+    // The claimables are paid from the same token account.
+    let treasury_spendable = ctx
+        .accounts
+        .treasury_token
+        .amount
+        .saturating_sub(accounting::other_buckets(slots_state)?);
+    let withdrawal_timelock::MaturedWithdrawal {
+        amount,
+        category,
+        memo,
+    } = withdrawal_timelock::execute(slots_state, clock.slot, treasury_spendable)?;
+
+    token_mode::transfer_from_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.treasury_token,
+        &ctx.accounts.authority_token,
+        &ctx.accounts.treasury,
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+    outflows::book(slots_state, category, amount, authority.key(), memo)?;
+    pool_level::refresh(slots_state);
+
+    emit!(WithdrawalExecuted {
+        amount,
+        category: category as u8,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn verify_accounting(ctx: Context<VerifyAccounting>) -> Result<AccountingReport> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let treasury_tokens = token_treasury_balance(slots_state, &ctx.accounts.treasury_token)?;
+    accounting::verify(slots_state, ctx.accounts.treasury.lamports(), treasury_tokens)
+}
+
+pub fn sync_pool(ctx: Context<SyncPool>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let spendable = match token_treasury_balance(slots_state, &ctx.accounts.treasury_token)? {
+        Some(tokens) => tokens,
+        None => accounting::spendable_lamports(&ctx.accounts.treasury)?,
+    };
+    let old = slots_state.total_pool;
+    let new = accounting::sync_pool(slots_state, spendable)?;
+    accounting::ensure_pool_split(slots_state)?;
+    pool_level::refresh(slots_state);
+
+    emit!(PoolSynced {
+        old,
+        new,
+        delta: (new as i128 - old as i128) as i64,
+    });
+
+    Ok(())
+}
+
+/// Token treasury balance of a token deployment, which must pass the
+/// account; None for a SOL deployment.
+fn token_treasury_balance(
+    slots_state: &SlotsState,
+    treasury_token: &Option<Account<TokenAccount>>,
+) -> Result<Option<u64>> {
+    if slots_state.exclusive_mint().is_none() {
+        return Ok(None);
+    }
+    let treasury_token = optional::required(treasury_token.as_ref(), "treasury_token")?;
+    Ok(Some(treasury_token.amount))
+}
+
+pub fn check_pool_level(ctx: Context<FlushStats>) -> Result<u64> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    Ok(pool_level::refresh(slots_state))
+}
+
+pub fn set_community_bps(ctx: Context<EmergencyPause>, community_bps: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    require!(community_bps <= 10_000, ErrorCode::InvalidCommunityConfig);
+    require!(
+        community_bps == 0 || slots_state.community_authority != Pubkey::default(),
+        ErrorCode::CommunityNotConfigured
+    );
+
+    slots_state.community_bps = community_bps;
+
+    emit!(CommunityShareChanged {
+        community_bps,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn init_community_vault(ctx: Context<InitCommunityVault>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let vault = &mut ctx.accounts.community_vault;
+    vault.slots_state = slots_state.key();
+    vault.destinations = [Pubkey::default(); MAX_COMMUNITY_DESTINATIONS];
+    vault.total_withdrawn = 0;
+    vault.bump = ctx.bumps.community_vault;
+    Ok(())
+}
+
+pub fn sweep_community(ctx: Context<SweepCommunity>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+
+    let amount = slots_state.community_balance;
+    slots_state.community_balance = 0;
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        &ctx.accounts.treasury,
+        ctx.accounts.community_vault.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        amount,
+    )?;
+
+    emit!(CommunitySwept {
+        amount,
+        community_accrued: slots_state.community_accrued,
+    });
+
+    Ok(())
+}
+
+pub fn set_community_destinations(
+    ctx: Context<CommunityAdmin>,
+    destinations: [Pubkey; MAX_COMMUNITY_DESTINATIONS],
+) -> Result<()> {
+    let vault = &mut ctx.accounts.community_vault;
+    vault.destinations = destinations;
+
+    emit!(CommunityDestinationsChanged { destinations });
+
+    Ok(())
+}
+
+pub fn withdraw_community(ctx: Context<WithdrawCommunity>, amount: u64) -> Result<()> {
+    let vault = &mut ctx.accounts.community_vault;
+    let destination = &ctx.accounts.destination;
+    require!(
+        vault.allows(destination.key),
+        ErrorCode::CommunityDestinationNotAllowed
+    );
+
+    let vault_info = vault.to_account_info();
+    let reserve = Rent::get()?.minimum_balance(vault_info.data_len());
+    let available = vault_info.lamports().saturating_sub(reserve);
+    require!(amount <= available, ErrorCode::InsufficientFunds);
+
+    **vault_info.try_borrow_mut_lamports()? -= amount;
+    **destination.try_borrow_mut_lamports()? += amount;
+    vault.total_withdrawn = vault
+        .total_withdrawn
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(CommunityWithdrawn {
+        amount,
+        destination: destination.key(),
+        total_withdrawn: vault.total_withdrawn,
+    });
+
+    Ok(())
+}
+
+pub fn set_community_authority(
+    ctx: Context<SetCommunityAuthority>,
+    new_authority: Pubkey,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    require!(
+        new_authority != Pubkey::default(),
+        ErrorCode::InvalidCommunityConfig
+    );
+
+    let previous_authority = slots_state.community_authority;
+    slots_state.community_authority = new_authority;
+
+    emit!(CommunityAuthorityChanged {
+        previous_authority,
+        new_authority,
+    });
+
+    Ok(())
+}
+
+pub fn set_operator_fee(ctx: Context<EmergencyPause>, operator_fee_bps: u16) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    require!(
+        operator_fee_bps <= operator_fee::MAX_OPERATOR_FEE_BPS,
+        ErrorCode::InvalidOperatorFee
+    );
+
+    slots_state.operator_fee_bps = operator_fee_bps;
+
+    emit!(OperatorFeeChanged {
+        operator_fee_bps,
+        authority: authority.key(),
+    });
+
+    Ok(())
+}
+
+pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+    let fee_vault = &ctx.accounts.fee_vault;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    pause::ensure_not_paused(slots_state, PAUSE_WITHDRAWALS)?;
+    if slots_state.fee_vault == Pubkey::default() {
+        slots_state.fee_vault = fee_vault.key();
+    }
+    fund_vault_rent(
+        &ctx.accounts.system_program,
+        authority.to_account_info(),
+        fee_vault.to_account_info(),
+    )?;
+
+    let vault_available = fee_vault
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(fee_vault.data_len()));
+    let clock = Clock::get()?;
+    let operator_fee::FeeWithdrawal { swept, amount } =
+        operator_fee::withdraw(slots_state, vault_available, amount, clock.slot)?;
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        &ctx.accounts.treasury,
+        fee_vault.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        swept,
+    )?;
+    operator_fee::pay_out(
+        &ctx.accounts.system_program,
+        fee_vault,
+        authority.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.fee_vault,
+        amount,
+    )?;
+
+    emit!(FeesWithdrawn {
+        swept,
+        amount,
+        vault_balance: vault_available + swept - amount,
+        operator_fees_accrued: slots_state.operator_fees_accrued,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{emitted, pda, program_error, simulate, Fixture, Game};
+
+    const DAY: u64 = time::SECONDS_PER_DAY as u64;
+
+    /// A machine rebating 10_000 lamports a win, 25_000 a player a day,
+    /// suspended below 50_000 of fee balance until it is back at 80_000.
+    fn rebating() -> Game {
+        let mut game = Game::new();
+        game.state.winner_fee_rebate_lamports = 10_000;
+        game.state.rebate_daily_cap_per_player = 25_000;
+        game.state.rebate_suspend_below = 50_000;
+        game.state.rebate_resume_at = 80_000;
+        game.state.fee_balance = 1_000_000;
+        game
+    }
+
+    fn win(game: &mut Game) -> Settlement {
+        game.play_where(DEFAULT_MIN_BET, |s| s.total_payout > 0).unwrap()
+    }
+
+    #[test]
+    fn winners_get_the_rebate_from_the_fee_balance_up_to_a_daily_cap() {
+        let mut game = rebating();
+        let loss = game.play_where(DEFAULT_MIN_BET, |s| s.total_payout == 0).unwrap();
+        assert_eq!(loss.fee_rebate, 0);
+
+        let mut unrebated = game.clone();
+        unrebated.state.winner_fee_rebate_lamports = 0;
+        let first = win(&mut game);
+        let plain = win(&mut unrebated);
+        assert_eq!(first.fee_rebate, 10_000);
+        // Paid on top of the win, not counted in it, and not by the pool.
+        assert_eq!(first.total_payout, plain.total_payout);
+        assert_eq!(first.owed(), plain.owed() + 10_000);
+        assert_eq!(game.state.total_pool, unrebated.state.total_pool);
+        assert_eq!(game.state.fee_balance, unrebated.state.fee_balance - 10_000);
+        assert_eq!(game.state.total_fee_rebates, 10_000);
+
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        // The cap leaves 5_000 for today, then nothing.
+        assert_eq!(win(&mut game).fee_rebate, 5_000);
+        assert_eq!(win(&mut game).fee_rebate, 0);
+        assert_eq!(game.player.rebate_paid_today, 25_000);
+
+        // Tomorrow the allowance is back.
+        let today = game.player.rebate_day;
+        game.advance(DAY);
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert_eq!(game.player.rebate_day, today + 1);
+        assert_eq!(game.player.rebate_paid_today, 10_000);
+        assert_eq!(game.state.total_fee_rebates, 35_000);
+    }
+
+    #[test]
+    fn a_clock_stepping_back_does_not_reopen_yesterday() {
+        let mut game = rebating();
+        game.advance(DAY - game.slot % DAY - 10);
+        for _ in 0..3 {
+            win(&mut game);
+        }
+        assert_eq!(game.player.rebate_paid_today, 25_000);
+        let day = game.player.rebate_day;
+
+        // Past midnight, then a timestamp from before it.
+        game.advance(20);
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert_eq!(game.player.rebate_day, day + 1);
+        game.slot -= 40;
+        crate::test_util::set_clock_slot(game.slot);
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert_eq!(game.player.rebate_day, day + 1);
+        assert_eq!(game.player.rebate_paid_today, 20_000);
+    }
+
+    #[test]
+    fn rebates_suspend_on_a_low_fee_balance_until_it_recovers() {
+        let mut game = rebating();
+        game.state.rebate_daily_cap_per_player = u64::MAX;
+        game.state.fee_balance = 60_000;
+
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert!(!game.state.rebate_suspended);
+        // 50_000 is not below the threshold; 40_000 is.
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert_eq!(win(&mut game).fee_rebate, 0);
+        assert!(game.state.rebate_suspended);
+        assert_eq!(game.state.fee_balance, 40_000);
+
+        // Back above the suspend line is not enough to resume.
+        game.state.fee_balance = 79_999;
+        assert_eq!(win(&mut game).fee_rebate, 0);
+        assert!(game.state.rebate_suspended);
+        game.state.fee_balance = 80_000;
+        assert_eq!(win(&mut game).fee_rebate, 10_000);
+        assert!(!game.state.rebate_suspended);
+
+        // Never more than the fee balance holds.
+        game.state.rebate_suspend_below = 0;
+        game.state.rebate_resume_at = 0;
+        game.state.fee_balance = 3_000;
+        assert_eq!(win(&mut game).fee_rebate, 3_000);
+        assert_eq!(game.state.fee_balance, 0);
+    }
+
+    /// Pool headroom left above the threshold by `overflowing`.
+    const HEADROOM: u64 = DEFAULT_MIN_BET / 4;
+
+    /// A committed spin whose reveal wins more than twice the HEADROOM
+    /// the pool has left, with the jackpots off, `fees` of fee balance in
+    /// the treasury and the overflow policy on.
+    fn overflowing(fees: u64) -> Game {
+        let mut game = Game::new();
+        let jackpots = &mut game.state.jackpots;
+        for tier in [&mut jackpots.mini, &mut jackpots.major, &mut jackpots.grand] {
+            tier.hit_weight = 0;
+        }
+        game.state.use_fees_for_overflow = true;
+        game.state.fee_overflow_max_per_spin = u64::MAX;
+        game.state.fee_balance = fees;
+        game.treasury += fees;
+        game.request(DEFAULT_MIN_BET).unwrap();
+        let headroom = reservations::headroom_for(&game.state, &game.player).unwrap();
+        game.state.min_pool_threshold += headroom - HEADROOM;
+        let value = game.reveal_where(|s| s.outcome.base_payout_full > 2 * HEADROOM);
+        game.reveal(value);
+        game
+    }
+
+    #[test]
+    fn the_fee_balance_covers_a_win_the_pool_cannot() {
+        let mut game = overflowing(100 * DEFAULT_MIN_BET);
+        let before = game.clone();
+        let settlement = game.settle().unwrap();
+
+        let full = settlement.outcome.base_payout_full;
+        assert_eq!(settlement.total_payout, HEADROOM);
+        assert_eq!(settlement.fee_overflow, full - HEADROOM);
+        assert_eq!(game.wallet - before.wallet, full);
+        // The pool pays what its headroom allows, the fees the rest.
+        assert_eq!(before.state.total_pool - game.state.total_pool, HEADROOM);
+        assert_eq!(
+            before.state.fee_balance - game.state.fee_balance,
+            settlement.fee_overflow
+        );
+        assert_eq!(game.state.total_fee_overflow, settlement.fee_overflow);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_short_fee_balance_tops_up_what_it_holds() {
+        let fees = HEADROOM / 2;
+        let mut game = overflowing(fees);
+        let before = game.clone();
+        let settlement = game.settle().unwrap();
+
+        assert_eq!(settlement.total_payout, HEADROOM);
+        assert_eq!(settlement.fee_overflow, fees);
+        assert!(settlement.outcome.base_payout_full > HEADROOM + fees);
+        assert_eq!(game.wallet - before.wallet, HEADROOM + fees);
+        assert_eq!(before.state.total_pool - game.state.total_pool, HEADROOM);
+        assert_eq!(game.state.fee_balance, 0);
+        assert_eq!(game.state.total_fee_overflow, fees);
+        game.verify().expect("books hold");
+    }
+
+    /// fund_faucet's accounts: the authority paying into the treasury.
+    fn funding_faucet(game: &Game) -> Vec<Fixture> {
+        let [state, _, treasury] = game.fixtures();
+        vec![
+            state,
+            game.authority(),
+            treasury,
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    /// devnet_faucet's accounts for the game's player.
+    fn dripping(game: &Game) -> Vec<Fixture> {
+        let [state, player, treasury] = game.fixtures();
+        vec![
+            state,
+            player,
+            Fixture::wallet(game.user, game.wallet).signer(),
+            treasury,
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    #[test]
+    fn the_faucet_drips_once_per_window_while_its_reserve_lasts() {
+        let mut game = Game::new();
+        game.state.network_guard = qa::LOCALNET_MARKER;
+        game.state.faucet_amount = 1_000;
+        game.state.faucet_interval_slots = 100;
+        game.state.faucet_lifetime_cap = 10_000;
+        let drip = crate::instruction::DevnetFaucet {}.data();
+
+        let mut accounts = dripping(&game);
+        assert_eq!(
+            simulate(&mut accounts, &drip),
+            Err(program_error(ErrorCode::FaucetReserveEmpty))
+        );
+
+        let mut funding = funding_faucet(&game);
+        let fund = crate::instruction::FundFaucet { amount: 2_500 }.data();
+        simulate(&mut funding, &fund).unwrap();
+        assert_eq!(funding[2].lamports, accounts[3].lamports + 2_500);
+        let funded = funding[0].read::<SlotsState>();
+        assert_eq!(funded.faucet_reserve, 2_500);
+        assert_eq!(funded.total_pool, game.state.total_pool);
+        accounts[0] = funding[0].clone();
+
+        emitted::<FaucetDrip>();
+        simulate(&mut accounts, &drip).unwrap();
+        let mut drips = emitted::<FaucetDrip>();
+        assert_eq!(drips.len(), 1);
+        let first = drips.pop().unwrap();
+        assert_eq!((first.amount, first.reserve_left), (1_000, 1_500));
+        assert_eq!(first.next_drip_slot, game.slot + 100);
+
+        // Once per window: the last slot of it is still too early.
+        game.advance(99);
+        assert_eq!(
+            simulate(&mut accounts, &drip),
+            Err(program_error(ErrorCode::FaucetCooldown))
+        );
+        game.advance(1);
+        simulate(&mut accounts, &drip).unwrap();
+        let player = accounts[1].read::<PlayerState>();
+        assert_eq!(player.claimable_balance, 2_000);
+        assert_eq!(player.faucet_received, 2_000);
+        assert_eq!(player.faucet_last_slot, game.slot);
+
+        // The next window opens on a reserve too small for a drip.
+        game.advance(100);
+        assert_eq!(
+            simulate(&mut accounts, &drip),
+            Err(program_error(ErrorCode::FaucetReserveEmpty))
+        );
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!((state.faucet_reserve, state.total_claimable), (500, 2_000));
+    }
+
+    #[test]
+    fn a_mainnet_machine_refuses_every_faucet_instruction() {
+        let mut game = Game::new();
+        assert!(!qa::is_test_network(&game.state.network_guard));
+        game.state.faucet_amount = 1_000;
+        game.state.faucet_interval_slots = 100;
+        game.state.faucet_lifetime_cap = 10_000;
+        game.state.faucet_reserve = 5_000;
+        game.treasury += 5_000;
+
+        let mut funding = funding_faucet(&game);
+        let fund = crate::instruction::FundFaucet { amount: 1_000 }.data();
+        let mut accounts = dripping(&game);
+        let drip = crate::instruction::DevnetFaucet {}.data();
+        let [mut state, ..] = game.fixtures();
+        let config = crate::instruction::SetFaucetConfig {
+            amount: 1_000,
+            interval_slots: 100,
+            lifetime_cap: 10_000,
+        }
+        .data();
+        let refused = Err(program_error(ErrorCode::TestOnlyInstruction));
+        assert_eq!(simulate(&mut funding, &fund), refused);
+        assert_eq!(simulate(&mut accounts, &drip), refused);
+        assert_eq!(
+            simulate(
+                [&mut state, &mut game.authority(), &mut Fixture::omitted()],
+                &config
+            ),
+            refused
+        );
+        assert_eq!(accounts[1].read::<PlayerState>().claimable_balance, 0);
+    }
+
+    #[test]
+    fn only_the_authority_sets_a_consistent_rebate() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut no_metrics = Fixture::omitted();
+        let set = |rebate_lamports, daily_cap_per_player, suspend_below, resume_at| {
+            crate::instruction::SetWinnerRebate {
+                rebate_lamports,
+                daily_cap_per_player,
+                suspend_below,
+                resume_at,
+            }
+            .data()
+        };
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(10, 20, 5, 8)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        // Resuming below the suspend line would flap.
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(10, 20, 8, 5)),
+            Err(program_error(ErrorCode::InvalidRebateConfig))
+        );
+        // A cap below one rebate could never pay it.
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(10, 9, 5, 8)),
+            Err(program_error(ErrorCode::InvalidRebateConfig))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(10, 20, 5, 8)).unwrap();
+        let state = state.read::<SlotsState>();
+        assert_eq!(
+            (
+                state.winner_fee_rebate_lamports,
+                state.rebate_daily_cap_per_player,
+                state.rebate_suspend_below,
+                state.rebate_resume_at
+            ),
+            (10, 20, 5, 8)
+        );
+    }
+
+    /// A machine sharing 20% of its take with a community authority.
+    fn sharing() -> (Game, Pubkey) {
+        let mut game = Game::new();
+        let community = Pubkey::new_unique();
+        game.state.community_authority = community;
+        game.state.community_bps = 2_000;
+        (game, community)
+    }
+
+    #[test]
+    fn the_community_shares_the_take_and_the_losses() {
+        let (mut game, _) = sharing();
+        let pool = game.state.total_pool;
+        let loss = game.play_where(DEFAULT_MIN_BET, |s| s.total_payout == 0).unwrap();
+        let share = loss.bet_amount / 5;
+        assert_eq!(game.state.community_balance, share);
+        assert_eq!(game.state.community_accrued, share);
+        assert_eq!(game.state.total_pool, pool + loss.bet_amount - share);
+        game.verify().unwrap();
+
+        let big = |s: &Settlement| s.total_payout > 3 * s.bet_amount;
+        let win = game.play_where(DEFAULT_MIN_BET, big).unwrap();
+        let deficit = (win.total_payout - win.bet_amount) / 5;
+        assert_eq!(game.state.community_deficit, deficit);
+        assert_eq!(game.state.community_accrued, share);
+
+        // The next takes repay the deficit before anything accrues.
+        game.play_where(DEFAULT_MIN_BET, |s| s.total_payout == 0).unwrap();
+        assert_eq!(game.state.community_deficit, deficit - share);
+        assert_eq!(game.state.community_accrued, share);
+        game.verify().unwrap();
+    }
+
+    #[test]
+    fn only_the_authority_sets_a_configured_share() {
+        let (game, _) = sharing();
+        let [mut state, ..] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut authority = game.authority();
+        let mut no_metrics = Fixture::omitted();
+        let set = |community_bps| crate::instruction::SetCommunityBps { community_bps }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(100)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(10_001)),
+            Err(program_error(ErrorCode::InvalidCommunityConfig))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(100)).unwrap();
+        assert_eq!(state.read::<SlotsState>().community_bps, 100);
+
+        let unconfigured = Game::new();
+        let mut authority = unconfigured.authority();
+        let [mut unconfigured, ..] = unconfigured.fixtures();
+        assert_eq!(
+            simulate([&mut unconfigured, &mut authority, &mut no_metrics], &set(100)),
+            Err(program_error(ErrorCode::CommunityNotConfigured))
+        );
+    }
+
+    #[test]
+    fn the_vault_pays_only_listed_destinations() {
+        let (mut game, community) = sharing();
+        game.play_where(DEFAULT_MIN_BET, |s| s.total_payout == 0).unwrap();
+        let share = game.state.community_balance;
+        let [mut state, _, mut treasury] = game.fixtures();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+
+        let (key, _) = pda(&[COMMUNITY_VAULT_SEED, state.key.as_ref()]);
+        let mut vault = Fixture::uninit(key, CommunityVault::LEN);
+        let mut payer = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let init = crate::instruction::InitCommunityVault {}.data();
+        simulate([&mut state, &mut vault, &mut payer, &mut system], &init).unwrap();
+        let reserve = vault.lamports;
+
+        let sweep = crate::instruction::SweepCommunity {}.data();
+        let treasury_before = treasury.lamports;
+        simulate([&mut state, &mut treasury, &mut vault, &mut system], &sweep).unwrap();
+        assert_eq!(vault.lamports, reserve + share);
+        assert_eq!(treasury.lamports, treasury_before - share);
+        assert_eq!(state.read::<SlotsState>().community_balance, 0);
+
+        let listed = Pubkey::new_unique();
+        let mut destinations = [Pubkey::default(); MAX_COMMUNITY_DESTINATIONS];
+        destinations[0] = listed;
+        let list = crate::instruction::SetCommunityDestinations { destinations }.data();
+        let mut operator = game.authority();
+        assert_eq!(
+            simulate([&mut state, &mut operator, &mut vault], &list),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        let mut community = Fixture::wallet(community, 0).signer();
+        simulate([&mut state, &mut community, &mut vault], &list).unwrap();
+
+        let withdraw = |amount| crate::instruction::WithdrawCommunity { amount }.data();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 0);
+        assert_eq!(
+            simulate([&mut state, &mut community, &mut vault, &mut stranger], &withdraw(1)),
+            Err(program_error(ErrorCode::CommunityDestinationNotAllowed))
+        );
+        let mut destination = Fixture::wallet(listed, 0);
+        assert_eq!(
+            simulate(
+                [&mut state, &mut community, &mut vault, &mut destination],
+                &withdraw(share + 1)
+            ),
+            Err(program_error(ErrorCode::InsufficientFunds))
+        );
+        let accounts = [&mut state, &mut community, &mut vault, &mut destination];
+        simulate(accounts, &withdraw(share)).unwrap();
+        assert_eq!(destination.lamports, share);
+        assert_eq!(vault.lamports, reserve);
+        assert_eq!(vault.read::<CommunityVault>().total_withdrawn, share);
+    }
+}
@@ -0,0 +1,1997 @@
+//! Handlers of the spin instructions: commits, settles, voids and
+//! cancels, and the token-mode and test-network spin paths.
+
+use crate::*;
+
+pub fn request_spin(
+    ctx: Context<RequestSpin>,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+    lines: u8,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!((1..=MAX_PAYLINES).contains(&lines), ErrorCode::InvalidLines);
+
+    // Bet limits apply per line.
+    let mut commit = validate_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        accounting::spendable_lamports(treasury)?,
+        &ctx.accounts.randomness_account_data,
+        randomness_account,
+        bet_amount,
+        ctx.accounts.allowlist.as_deref(),
+        ctx.accounts.oracle_queue.as_deref(),
+    )?;
+    outcome_budget::ensure_commit(slots_state, lines)?;
+    commit.lines = lines;
+    commit.outcomes = lines;
+    let stake = bet_amount
+        .checked_mul(lines as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // A held scatter free spin plays first, then a redeemed loyalty
+    // spin; either costs the player nothing.
+    let user_stake = if scatter::fund(player_state, bet_amount, lines)?
+        || loyalty::fund(slots_state, player_state, stake)?
+    {
+        0
+    } else {
+        stake
+    };
+
+    // Ensure user has enough funds for the stake plus any oracle cost.
+    let charge = user_stake
+        .checked_add(oracle_cost::player_charge(slots_state))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(user.lamports() >= charge, ErrorCode::InsufficientFunds);
+
+    // Transfer bet user -> treasury (user signs, no PDA needed).
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, charge)?;
+
+    record_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        randomness_account,
+        stake,
+        commit,
+        0,
+    )
+}
+
+pub fn request_spins(
+    ctx: Context<RequestSpin>,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+    count: u8,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!(
+        (1..=MAX_SPINS_PER_COMMIT).contains(&count),
+        ErrorCode::InvalidSpinCount
+    );
+    // Held free spins play one at a time through request_spin.
+    require!(
+        player_state.free_spins_remaining == 0,
+        ErrorCode::FreeSpinTermsMismatch
+    );
+
+    // Bet limits apply per spin; the pool must cover every spin's cap.
+    let treasury_spendable = accounting::spendable_lamports(treasury)?;
+    let mut commit = validate_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        treasury_spendable,
+        &ctx.accounts.randomness_account_data,
+        randomness_account,
+        bet_amount,
+        ctx.accounts.allowlist.as_deref(),
+        ctx.accounts.oracle_queue.as_deref(),
+    )?;
+    outcome_budget::ensure_commit(slots_state, count)?;
+    multi_spin::ensure_covered(slots_state, player_state.tier, count, treasury_spendable)?;
+    commit.payout_cap = multi_spin::commit_cap(commit.payout_cap, count)?;
+    commit.spins = count;
+    commit.outcomes = count;
+    let stake = bet_amount
+        .checked_mul(count as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let charge = stake
+        .checked_add(oracle_cost::player_charge(slots_state))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(user.lamports() >= charge, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, charge)?;
+
+    record_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        randomness_account,
+        stake,
+        commit,
+        0,
+    )
+}
+
+pub fn onboard_and_spin(
+    ctx: Context<OnboardAndSpin>,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+    session_deposit: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+
+    // init_if_needed hands over a zeroed account when it just created
+    // it. Only that case is initialized; an existing PlayerState is
+    // never reset, and the seeds tie it to the signer.
+    if player_state.owner == Pubkey::default() {
+        init_player_state(
+            player_state,
+            user.key(),
+            ctx.bumps.player_state,
+            machines::indexes_players(slots_state).then_some(&*ctx.accounts.account_index),
+        )?;
+    }
+    require_keys_eq!(player_state.owner, user.key(), ErrorCode::Unauthorized);
+
+    // All checks before any lamports move, as in request_spin.
+    let commit = validate_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        accounting::spendable_lamports(treasury)?,
+        &ctx.accounts.randomness_account_data,
+        randomness_account,
+        bet_amount,
+        ctx.accounts.allowlist.as_deref(),
+        ctx.accounts.oracle_queue.as_deref(),
+    )?;
+
+    // Rent was taken by init; the rest comes over in one transfer.
+    let charge = bet_amount
+        .checked_add(oracle_cost::player_charge(slots_state))
+        .and_then(|v| v.checked_add(session_deposit))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(user.lamports() >= charge, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, charge)?;
+
+    if session_deposit > 0 {
+        player_state.claimable_balance = player_state
+            .claimable_balance
+            .checked_add(session_deposit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        slots_state.total_claimable = slots_state
+            .total_claimable
+            .checked_add(session_deposit)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    record_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        randomness_account,
+        bet_amount,
+        commit,
+        0,
+    )
+}
+
+pub fn request_spin_with_signature(
+    ctx: Context<RequestSpinWithSignature>,
+    bet: SignedBet,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let relayer = &ctx.accounts.relayer;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+
+    require_keys_eq!(bet.program_id, crate::ID, ErrorCode::InvalidBetSignature);
+    require_keys_eq!(
+        bet.game_id,
+        slots_state.key(),
+        ErrorCode::InvalidBetSignature
+    );
+    require_keys_eq!(
+        bet.player,
+        player_state.owner,
+        ErrorCode::InvalidBetSignature
+    );
+    require!(
+        !time::has_reached(bet.max_slot.saturating_add(1), Clock::get()?.slot),
+        ErrorCode::SignedBetExpired
+    );
+    require!(
+        bet.nonce == player_state.next_bet_nonce,
+        ErrorCode::SignedBetNonceMismatch
+    );
+
+    let ix_sysvar = &ctx.accounts.instructions;
+    let current = ix_sysvar::load_current_index_checked(ix_sysvar)?;
+    require!(current > 0, ErrorCode::InvalidBetSignature);
+    let ed25519_ix = ix_sysvar::load_instruction_at_checked(current as usize - 1, ix_sysvar)?;
+    signed_bet::verify_ed25519_ix(&ed25519_ix, &bet.player, &bet.message())?;
+
+    let commit = validate_commit(
+        slots_state,
+        player_state,
+        &bet.player,
+        accounting::spendable_lamports(treasury)?,
+        &ctx.accounts.randomness_account_data,
+        bet.randomness_account,
+        bet.bet_amount,
+        ctx.accounts.allowlist.as_deref(),
+        ctx.accounts.oracle_queue.as_deref(),
+    )?;
+
+    // Fund from the session balance: the lamports are already in the
+    // treasury, they just stop being owed to the player.
+    let debit = bet
+        .bet_amount
+        .checked_add(bet.relayer_fee)
+        .and_then(|v| v.checked_add(oracle_cost::player_charge(slots_state)))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        player_state.claimable_balance >= debit,
+        ErrorCode::InsufficientFunds
+    );
+    player_state.claimable_balance -= debit;
+    slots_state.total_claimable = slots_state.total_claimable.saturating_sub(debit);
+    player_state.next_bet_nonce = player_state
+        .next_bet_nonce
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        treasury,
+        relayer.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        bet.relayer_fee,
+    )?;
+    if bet.relayer_fee > 0 {
+        outflows::book(
+            slots_state,
+            OutflowCategory::Fee,
+            bet.relayer_fee,
+            relayer.key(),
+            [0; 32],
+        )?;
+    }
+
+    record_commit(
+        slots_state,
+        player_state,
+        &bet.player,
+        bet.randomness_account,
+        bet.bet_amount,
+        commit,
+        0,
+    )
+}
+
+pub fn deposit_session(ctx: Context<DepositSession>, amount: u64) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+
+    let transfer_accounts = system_program::Transfer {
+        from: user.to_account_info(),
+        to: treasury.to_account_info(),
+    };
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        transfer_accounts,
+    );
+    system_program::transfer(transfer_ctx, amount)?;
+
+    player_state.claimable_balance = player_state
+        .claimable_balance
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_claimable = slots_state
+        .total_claimable
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
+}
+
+pub fn settle_spin(ctx: Context<SettleSpin>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+    let cranked = ctx.accounts.cranker.key() != user.key();
+
+    require_sol_mode(slots_state)?;
+    if cranked {
+        settle_hooks::require_for_crank(slots_state, ctx.remaining_accounts)?;
+    }
+    close_epoch_if_present(ctx.accounts.epoch_report_page.as_deref_mut(), slots_state)?;
+    let settlement = settle_pending(
+        slots_state,
+        player_state,
+        &user.key(),
+        &ctx.accounts.randomness_account_data,
+        &ctx.accounts.rtp_history,
+        0,
+        None,
+    )?;
+    record_wager_race_if_present(
+        ctx.accounts.wager_race.as_deref_mut(),
+        player_state,
+        &settlement,
+    )?;
+    record_leaderboard(
+        slots_state,
+        ctx.accounts.leaderboard.as_mut(),
+        player_state,
+        &settlement,
+        cranked,
+    )?;
+    accrue_vip_rebate_if_present(
+        slots_state,
+        player_state,
+        ctx.accounts.vip_token_account.as_ref(),
+        &user.key(),
+        &settlement,
+        cranked,
+    )?;
+    accrue_referral_if_present(
+        slots_state,
+        player_state,
+        ctx.accounts.referral_earnings.as_deref_mut(),
+        &user.key(),
+        &settlement,
+    )?;
+
+    // Transfer payout from treasury PDA -> user.
+    transfer_from_treasury(
+        &ctx.accounts.system_program,
+        treasury,
+        user.to_account_info(),
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        settlement.owed(),
+    )?;
+
+    finish_settle(
+        slots_state,
+        &settlement,
+        &user.key(),
+        &ctx.accounts.receipt_tree,
+        &ctx.accounts.metrics,
+        ctx.remaining_accounts,
+    )?;
+
+    pay_crank_fee(
+        slots_state,
+        &ctx.accounts.system_program,
+        treasury,
+        &ctx.accounts.cranker,
+        &user.key(),
+        ctx.bumps.treasury,
+    )
+}
+
+pub fn settle_spins_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SettleSpinsBatch<'info>>,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let treasury = &ctx.accounts.treasury;
+    let system_program = &ctx.accounts.system_program;
+    let treasury_bump = ctx.bumps.treasury;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    let entries = batch_settle::entries(ctx.remaining_accounts)?;
+
+    // Every entry is loaded and classified before the first settles,
+    // so Split mode counts winners against the pre-batch state.
+    let slot = Clock::get()?.slot;
+    let mut loaded = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let player_state = batch_settle::load_player(slots_state, &entry[0], &entry[1])?;
+        let mut readiness = batch_settle::readiness(&player_state, &entry[2])?;
+        if readiness.settles()
+            && entry[1].key() != ctx.accounts.cranker.key()
+            && batch_settle::needs_accounts(slots_state, &player_state, slot)
+        {
+            readiness = Readiness::NeedsAccounts;
+        }
+        loaded.push((player_state, readiness));
+    }
+    let mut split = match slots_state.jackpot_batch_mode {
+        JackpotBatchMode::Sequential => None,
+        JackpotBatchMode::Split => {
+            let hits = loaded
+                .iter()
+                .map(|(player_state, readiness)| match readiness {
+                    Readiness::Revealed(bytes) => {
+                        batch_settle::peek_jackpot_tier(slots_state, player_state, bytes)
+                    }
+                    _ => Ok(None),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Some(SplitPlan::new(&slots_state.jackpots, hits))
+        }
+    };
+
+    let mut settled = 0u8;
+    for (index, (entry, (mut player_state, readiness))) in entries.iter().zip(loaded).enumerate() {
+        if !readiness.settles() {
+            continue;
+        }
+        let user = entry[1].key();
+        let amounts = split.as_ref().map(|plan| plan.amounts_for(index));
+        let before = batch_settle::tier_amounts(&slots_state.jackpots);
+        let settlement = settle_pending(
+            slots_state,
+            &mut player_state,
+            &user,
+            &entry[2],
+            &None,
+            index as u8,
+            amounts.as_ref(),
+        )?;
+        if let Some(tier) = settlement.outcome.jackpot_tier {
+            let counted = amounts.is_some_and(|a| a[tier as usize].is_some());
+            let (snapshot_amount, winners) = match split.as_mut() {
+                Some(plan) if counted => {
+                    plan.record_hit(tier);
+                    (plan.snapshot[tier as usize], plan.winners[tier as usize])
+                }
+                _ => (before[tier as usize], 1),
+            };
+            emit!(JackpotBatchShare {
+                user,
+                tier,
+                mode: slots_state.jackpot_batch_mode,
+                snapshot_amount,
+                winners,
+                share: settlement.outcome.jackpot_payout,
+            });
+        }
+
+        transfer_from_treasury(
+            system_program,
+            treasury,
+            entry[1].clone(),
+            &slots_state.machine_id,
+            treasury_bump,
+            settlement.owed(),
+        )?;
+        player_state.exit(&crate::ID)?;
+        pay_crank_fee(
+            slots_state,
+            system_program,
+            treasury,
+            &ctx.accounts.cranker,
+            &user,
+            treasury_bump,
+        )?;
+        settled += 1;
+    }
+
+    if let Some(plan) = &split {
+        plan.finish(slots_state)?;
+        accounting::ensure_pool_split(slots_state)?;
+        pool_level::refresh(slots_state);
+    }
+
+    emit!(BatchSettled {
+        attempted: entries.len() as u8,
+        settled,
+    });
+
+    Ok(())
+}
+
+pub fn settle_and_respin(
+    ctx: Context<SettleAndRespin>,
+    new_randomness_account: Pubkey,
+    next_bet_amount: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let treasury = &ctx.accounts.treasury;
+
+    require_sol_mode(slots_state)?;
+    let settled_randomness = ctx.accounts.randomness_account_data.key();
+    require!(
+        new_randomness_account != settled_randomness
+            && ctx.accounts.new_randomness_account_data.key() != settled_randomness,
+        ErrorCode::RespinReusesRandomness
+    );
+    close_epoch_if_present(ctx.accounts.epoch_report_page.as_deref_mut(), slots_state)?;
+    let settlement = settle_pending(
+        slots_state,
+        player_state,
+        &user.key(),
+        &ctx.accounts.randomness_account_data,
+        &ctx.accounts.rtp_history,
+        0,
+        None,
+    )?;
+    record_wager_race_if_present(
+        ctx.accounts.wager_race.as_deref_mut(),
+        player_state,
+        &settlement,
+    )?;
+    record_leaderboard(
+        slots_state,
+        ctx.accounts.leaderboard.as_mut(),
+        player_state,
+        &settlement,
+        false,
+    )?;
+    accrue_vip_rebate_if_present(
+        slots_state,
+        player_state,
+        ctx.accounts.vip_token_account.as_ref(),
+        &user.key(),
+        &settlement,
+        false,
+    )?;
+    accrue_referral_if_present(
+        slots_state,
+        player_state,
+        ctx.accounts.referral_earnings.as_deref_mut(),
+        &user.key(),
+        &settlement,
+    )?;
+
+    let winnings = settlement.owed();
+    let oracle_charge = oracle_cost::player_charge(slots_state);
+    let rebet = next_bet_amount.min(winnings.saturating_sub(oracle_charge));
+
+    // Nothing is moved until the commit checks pass, so a failed
+    // respin falls through to the normal payout below. Those checks
+    // assume the settled spin is gone; if it is not, fail outright
+    // instead of letting PendingSpinExists pass as a skipped respin.
+    let commit = if rebet > 0 {
+        require!(
+            !player_state.has_pending_spin && player_state.randomness_account == Pubkey::default(),
+            ErrorCode::RespinBeforeSettleCleared
+        );
+        validate_commit(
+            slots_state,
+            player_state,
+            &user.key(),
+            accounting::spendable_lamports(treasury)?,
+            &ctx.accounts.new_randomness_account_data,
+            new_randomness_account,
+            rebet,
+            ctx.accounts.allowlist.as_deref(),
+            ctx.accounts.oracle_queue.as_deref(),
+        )
+        .map_err(|err| msg!("respin skipped: {}", err))
+        .ok()
+    } else {
+        None
+    };
+
+    match commit {
+        Some(commit) => {
+            // The winnings never leave the treasury: the re-bet and the
+            // oracle cost are booked by the commit, the rest is owed.
+            let to_claimable = winnings - rebet - oracle_charge;
+            player_state.claimable_balance = player_state
+                .claimable_balance
+                .checked_add(to_claimable)
+                .ok_or(ErrorCode::MathOverflow)?;
+            slots_state.total_claimable = slots_state
+                .total_claimable
+                .checked_add(to_claimable)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            record_commit(
+                slots_state,
+                player_state,
+                &user.key(),
+                new_randomness_account,
+                rebet,
+                commit,
+                RESPIN_CONTEXT_INDEX,
+            )?;
+
+            emit!(WinningsRebet {
+                user: user.key(),
+                winnings,
+                rebet,
+                to_claimable,
+            });
+        }
+        None => transfer_from_treasury(
+            &ctx.accounts.system_program,
+            treasury,
+            user.to_account_info(),
+            &slots_state.machine_id,
+            ctx.bumps.treasury,
+            settlement.owed(),
+        )?,
+    }
+
+    finish_settle(
+        slots_state,
+        &settlement,
+        &user.key(),
+        &ctx.accounts.receipt_tree,
+        &ctx.accounts.metrics,
+        ctx.remaining_accounts,
+    )
+}
+
+pub fn settle_spin_deferred(ctx: Context<SettleSpinDeferred>) -> Result<()> {
+    let slots_state = &ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let randomness_ai = &ctx.accounts.randomness_account_data;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    pause::ensure_open(slots_state, PAUSE_SETTLEMENT)?;
+    require!(player_state.has_pending_spin, ErrorCode::NoPendingSpin);
+    ensure_settle_version(slots_state, player_state)?;
+    // Referral credit and VIP rebates move pool funds; a free spin's
+    // stats differ.
+    require!(
+        player_state.referrer == Pubkey::default() && !is_free_spin(player_state),
+        ErrorCode::SettleNeedsWriteLock
+    );
+    require!(
+        !slots_state.vip_config.enabled() || player_state.vip_token_account == Pubkey::default(),
+        ErrorCode::SettleNeedsWriteLock
+    );
+    require!(
+        multi_spin::count(player_state) == 1,
+        ErrorCode::SettleNeedsWriteLock
+    );
+    require_keys_eq!(
+        player_state.randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+
+    let bet_amount = player_state.pending_bet_amount;
+    require!(bet_amount > 0, ErrorCode::InvalidBetAmount);
+
+    let clock = Clock::get()?;
+    let check = RandomnessCheck::for_settle(user.key(), player_state, clock.slot);
+    let randomness_data = check.load(randomness_ai)?;
+    let random_bytes = check.reveal(randomness_ai, &randomness_data)?;
+
+    // Same affordability as settle_spin, which refreshes bootstrap
+    // first; only proceed if that refresh would be a no-op.
+    require!(
+        bootstrap::next_bootstrap_active(
+            slots_state.bootstrap_active,
+            slots_state.total_pool,
+            slots_state.bootstrap_maturity_threshold,
+            slots_state.bootstrap_hysteresis_bps,
+        ) == slots_state.bootstrap_active,
+        ErrorCode::SettleNeedsWriteLock
+    );
+    let available_pool = reservations::headroom_for(slots_state, player_state)?;
+    let (payout_cap, jackpots) = tiers::committed_terms(slots_state, player_state);
+    let limits = PayoutLimits::for_settle(available_pool, payout_cap);
+    let forced_jackpots = must_hit::forced_tiers(slots_state, player_state, None);
+
+    let (outcome, detail) = draw_pending_outcome(
+        player_state,
+        &random_bytes,
+        limits,
+        &jackpots,
+        forced_jackpots,
+        &slots_state.reel_weights,
+        &slots_state.paytable,
+        slots_state.wild_symbol,
+    )?;
+    require!(outcome.total_payout == 0, ErrorCode::SettleNeedsWriteLock);
+    // A forced outcome is only applied by the full settle.
+    #[cfg(feature = "qa")]
+    require!(
+        !player_state.forced_outcome.active,
+        ErrorCode::SettleNeedsWriteLock
+    );
+
+    player_state.deferred_spins = player_state
+        .deferred_spins
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.deferred_wagered = player_state
+        .deferred_wagered
+        .checked_add(booked_wager(player_state))
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.deferred_last_ts = player_state.deferred_last_ts.max(clock.unix_timestamp);
+    // SlotsState is read-only here; flush_stats releases the reservation.
+    player_state.deferred_reserved = player_state
+        .deferred_reserved
+        .checked_add(player_state.commit_payout_cap)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.commit_payout_cap = 0;
+
+    emit!(SpinSettled {
+        user: user.key(),
+        randomness_account: randomness_ai.key(),
+        symbols: outcome.symbols,
+        base_payout: 0,
+        jackpot_payout: 0,
+        total_payout: 0,
+        fee_rebate: 0,
+        vrf_hash: game_math::vrf_hash(&random_bytes),
+        vrf_prefix: game_math::vrf_prefix(&random_bytes),
+        version: SPIN_SETTLED_VERSION,
+        player_tier: player_state.tier,
+        fee_overflow: 0,
+        spin_nonce: player_state.spin_nonce,
+        context_index: 0,
+        match_count: outcome.match_count,
+        global_spin_counter: 0,
+        free_spin: false,
+    });
+    record_spin_proof(
+        user.key(),
+        player_state,
+        randomness_ai.key(),
+        &random_bytes,
+        0,
+    );
+    if let Some(detail) = &detail {
+        emit_drawn_detail(user.key(), player_state, detail, 0);
+    }
+    scatter::award(
+        &slots_state.scatter,
+        player_state,
+        &user.key(),
+        outcome.symbols,
+        0,
+    );
+    player_stats::record_settle(player_state, user.key(), 1, 0, clock.slot)?;
+    responsible_gaming::record_settle(slots_state, player_state, bet_amount, 0, clock.slot);
+    loyalty::accrue(slots_state, player_state, &user.key(), bet_amount);
+
+    player_state.has_pending_spin = false;
+    player_state.pending_bet_amount = 0;
+    player_state.pending_lines = 0;
+    player_state.spins_remaining = 0;
+    player_state.randomness_account = Pubkey::default();
+
+    invoke_settle_hook(
+        slots_state,
+        ctx.remaining_accounts,
+        OnSpinSettledArgs {
+            user: user.key(),
+            bet: bet_amount,
+            payout: 0,
+            symbols: outcome.symbols,
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn flush_stats<'info>(ctx: Context<'_, '_, 'info, 'info, FlushStats<'info>>) -> Result<()> {
+    // A plain &mut SlotsState, so the traffic fields borrow separately.
+    let slots_state: &mut SlotsState = &mut ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    let mut players = 0u32;
+    let mut spins = 0u64;
+    let mut wagered = 0u64;
+    let mut reserved = 0u64;
+    let mut last_ts = slots_state.traffic_last_update_ts;
+
+    for ai in ctx.remaining_accounts.iter() {
+        let mut player_state: Account<'info, PlayerState> = Account::try_from(ai)?;
+        if player_state.deferred_spins == 0 {
+            continue;
+        }
+        players += 1;
+        spins = spins
+            .checked_add(player_state.deferred_spins)
+            .ok_or(ErrorCode::MathOverflow)?;
+        wagered = wagered
+            .checked_add(player_state.deferred_wagered)
+            .ok_or(ErrorCode::MathOverflow)?;
+        reserved = reserved.saturating_add(player_state.deferred_reserved);
+        last_ts = last_ts.max(player_state.deferred_last_ts);
+
+        player_state.deferred_spins = 0;
+        player_state.deferred_wagered = 0;
+        player_state.deferred_reserved = 0;
+        player_state.exit(&crate::ID)?;
+    }
+
+    if spins == 0 {
+        return Ok(());
+    }
+
+    // Wagers were counted at commit; only the settle-side stats move.
+    let first_global_spin_counter = slots_state
+        .global_spin_counter
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_spins = slots_state
+        .total_spins
+        .checked_add(spins)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.global_spin_counter = slots_state
+        .global_spin_counter
+        .checked_add(spins)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.pending_spins = slots_state.pending_spins.saturating_sub(spins);
+    slots_state.reserved_liability = slots_state.reserved_liability.saturating_sub(reserved);
+    risk::record_spins(
+        &mut slots_state.traffic_spin_weight,
+        &mut slots_state.traffic_wager_weight,
+        &mut slots_state.traffic_last_update_ts,
+        last_ts,
+        slots_state.traffic_half_life_secs,
+        spins,
+        wagered,
+    );
+    // Deferred settles are all losing spins: the whole wager is take.
+    community::accrue(slots_state, wagered, 0, Clock::get()?.slot)?;
+    pool_level::refresh(slots_state);
+
+    emit!(StatsFlushed {
+        players,
+        spins,
+        wagered,
+        first_global_spin_counter,
+    });
+
+    Ok(())
+}
+
+pub fn admin_void_spin(ctx: Context<AdminVoidSpin>, player: Pubkey, reason_code: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let authority = &ctx.accounts.authority;
+    let randomness_ai = &ctx.accounts.randomness_account_data;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require_authority(slots_state, authority.key())?;
+    require!(player_state.has_pending_spin, ErrorCode::NoPendingSpin);
+    require_keys_eq!(
+        player_state.randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+
+    ensure_voidable(slots_state, player_state, randomness_ai)?;
+
+    let randomness_account = player_state.randomness_account;
+    let committed_seed_slot = player_state.commit_slot;
+    let rotated = rotated_seed_slot(player_state, randomness_ai);
+    let refund = void_pending_spin(slots_state, player_state)?;
+    player_state.claimable_balance = player_state
+        .claimable_balance
+        .checked_add(refund)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_claimable = slots_state
+        .total_claimable
+        .checked_add(refund)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(SpinVoided {
+        user: player,
+        randomness_account,
+        refunded: refund,
+        reason_code,
+        authority: authority.key(),
+        spin_nonce: player_state.spin_nonce,
+        context_index: 0,
+    });
+    if let Some(current_seed_slot) = rotated {
+        emit!(RandomnessRotated {
+            user: player,
+            randomness_account,
+            committed_seed_slot,
+            current_seed_slot,
+            refunded: refund,
+            spin_nonce: player_state.spin_nonce,
+            context_index: 0,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn cancel_spin(ctx: Context<CancelSpin>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+    let randomness_ai = &ctx.accounts.randomness_account_data;
+    let clock = Clock::get()?;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_sol_mode(slots_state)?;
+    require!(player_state.has_pending_spin, ErrorCode::NoPendingSpin);
+    require_keys_eq!(
+        player_state.randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+    require!(
+        time::is_expired(
+            player_state.commit_slot,
+            slots_state.spin_expiry_slots,
+            clock.slot
+        ),
+        ErrorCode::SpinNotExpired
+    );
+
+    // Same rule as admin_void_spin: once the committed generation has
+    // revealed, the spin must settle, or a losing player could cancel.
+    ensure_voidable(slots_state, player_state, randomness_ai)?;
+
+    // Until expiry anyone can fetch the committed generation's reveal from
+    // the oracle and settle it, so one still unrevealed now is an oracle
+    // failure and the bet goes back. A re-seeded (or unreadable) account
+    // is forfeit: the player replaced the generation and may have seen it.
+    let randomness_account = player_state.randomness_account;
+    let commit_slot = player_state.commit_slot;
+    let (refunded, forfeited) = if holds_committed_generation(player_state, randomness_ai) {
+        let refund = void_pending_spin(slots_state, player_state)?;
+        if refund > 0 {
+            transfer_from_treasury(
+                &ctx.accounts.system_program,
+                &ctx.accounts.treasury,
+                user.to_account_info(),
+                &slots_state.machine_id,
+                ctx.bumps.treasury,
+                refund,
+            )?;
+            outflows::book(
+                slots_state,
+                OutflowCategory::Refund,
+                refund,
+                user.key(),
+                [0; 32],
+            )?;
+        }
+        pool_level::refresh(slots_state);
+        (refund, 0)
+    } else {
+        (0, forfeit_pending_spin(slots_state, player_state)?)
+    };
+
+    emit!(SpinCancelled {
+        user: user.key(),
+        randomness_account,
+        refunded,
+        forfeited,
+        commit_slot,
+        spin_nonce: player_state.spin_nonce,
+        context_index: 0,
+    });
+
+    Ok(())
+}
+
+pub fn request_spin_token(
+    ctx: Context<RequestSpinToken>,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    token_mode::require_token_mode(slots_state, &ctx.accounts.mint.key())?;
+
+    let commit = validate_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        ctx.accounts.treasury_token.amount,
+        &ctx.accounts.randomness_account_data,
+        randomness_account,
+        bet_amount,
+        ctx.accounts.allowlist.as_deref(),
+        ctx.accounts.oracle_queue.as_deref(),
+    )?;
+
+    require!(
+        ctx.accounts.user_token.amount >= bet_amount,
+        ErrorCode::InsufficientFunds
+    );
+    let oracle_charge = oracle_cost::player_charge(slots_state);
+    require!(
+        user.lamports() >= oracle_charge,
+        ErrorCode::InsufficientFunds
+    );
+
+    if oracle_charge > 0 {
+        let transfer_accounts = system_program::Transfer {
+            from: user.to_account_info(),
+            to: ctx.accounts.treasury.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            transfer_accounts,
+        );
+        system_program::transfer(transfer_ctx, oracle_charge)?;
+    }
+    token_mode::transfer_to_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.user_token,
+        &ctx.accounts.treasury_token,
+        user,
+        bet_amount,
+    )?;
+
+    record_commit(
+        slots_state,
+        player_state,
+        &user.key(),
+        randomness_account,
+        bet_amount,
+        commit,
+        0,
+    )
+}
+
+pub fn settle_spin_token(ctx: Context<SettleSpinToken>) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let player_state = &mut ctx.accounts.player_state;
+    let user = &ctx.accounts.user;
+
+    token_mode::require_token_mode(slots_state, &ctx.accounts.mint.key())?;
+    let settlement = settle_pending(
+        slots_state,
+        player_state,
+        &user.key(),
+        &ctx.accounts.randomness_account_data,
+        &ctx.accounts.rtp_history,
+        0,
+        None,
+    )?;
+
+    token_mode::transfer_from_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.treasury_token,
+        &ctx.accounts.user_token,
+        &ctx.accounts.treasury,
+        &slots_state.machine_id,
+        ctx.bumps.treasury,
+        settlement.owed(),
+    )?;
+
+    finish_settle(
+        slots_state,
+        &settlement,
+        &user.key(),
+        &ctx.accounts.receipt_tree,
+        &ctx.accounts.metrics,
+        ctx.remaining_accounts,
+    )
+}
+
+pub fn simulate_payout(
+    ctx: Context<ViewState>,
+    symbols: [u8; 3],
+    bet_amount: u64,
+) -> Result<PayoutSimulation> {
+    let slots_state = &ctx.accounts.slots_state;
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+
+    simulate::simulate(slots_state, symbols, bet_amount)
+}
+
+pub fn force_outcome(
+    ctx: Context<ForceOutcome>,
+    player: Pubkey,
+    symbols: [u8; 3],
+    jackpot_tier: u8,
+) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+
+    require!(cfg!(feature = "qa"), ErrorCode::TestOnlyInstruction);
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    qa::require_test_network(slots_state)?;
+    require_authority(slots_state, ctx.accounts.authority.key())?;
+    require!(
+        symbols.iter().all(|&s| (s as usize) < SYMBOL_COUNT)
+            && (jackpot_tier <= JACKPOT_TIER_GRAND || jackpot_tier == qa::NO_FORCED_JACKPOT),
+        ErrorCode::InvalidForcedOutcome
+    );
+
+    ctx.accounts.player_state.forced_outcome = ForcedOutcome {
+        active: true,
+        symbols,
+        jackpot_tier,
+    };
+
+    emit!(OutcomeForced {
+        player,
+        symbols,
+        jackpot_tier,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::instruction as ix;
+    use crate::test_util::{
+        emitted, pda, program_error, simulate, Fixture, Game, Randomness, GAME_WALLET,
+    };
+
+    const BET: u64 = DEFAULT_MIN_BET;
+    const DEPOSIT: u64 = 5 * DEFAULT_MIN_BET;
+
+    /// settle_and_respin's accounts, every optional one left out.
+    fn respinning(game: &Game, next: &Randomness) -> Vec<Fixture> {
+        let [state, player, treasury] = game.fixtures();
+        let randomness = |r: &Randomness| Fixture {
+            writable: true,
+            ..r.fixture()
+        };
+        let mut accounts = vec![
+            state,
+            player,
+            Fixture::wallet(game.user, game.wallet).signer(),
+            treasury,
+            randomness(&game.randomness),
+            randomness(next),
+        ];
+        accounts.extend(std::iter::repeat_with(Fixture::omitted).take(10));
+        accounts.push(Fixture::program(anchor_lang::system_program::ID));
+        accounts
+    }
+
+    fn respin_data(next: &Randomness, next_bet_amount: u64) -> Vec<u8> {
+        ix::SettleAndRespin {
+            new_randomness_account: next.key,
+            next_bet_amount,
+        }
+        .data()
+    }
+
+    /// A game whose committed spin has revealed a win worth more than
+    /// two bets, and what settling it pays.
+    fn won() -> (Game, u64) {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let win = game.reveal_where(|settlement| settlement.owed() > 2 * BET);
+        game.reveal(win);
+        let owed = game.clone().settle().unwrap().owed();
+        (game, owed)
+    }
+
+    /// A randomness account seeded this slot.
+    fn fresh(game: &Game) -> Randomness {
+        let mut next = Randomness::default();
+        next.seed(game.slot, 9);
+        next
+    }
+
+    #[test]
+    fn a_win_is_rebet_on_the_new_randomness() {
+        let (game, owed) = won();
+        let next = fresh(&game);
+        let mut accounts = respinning(&game, &next);
+        let (wallet, treasury) = (accounts[2].lamports, accounts[3].lamports);
+
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+        let player = accounts[1].read::<PlayerState>();
+        assert!(player.has_pending_spin);
+        assert_eq!(player.randomness_account, next.key);
+        assert_eq!(player.pending_bet_amount, BET);
+        assert_eq!(player.commit_slot, game.slot);
+        assert_eq!(player.claimable_balance, owed - BET);
+        assert_eq!(accounts[0].read::<SlotsState>().total_claimable, owed - BET);
+        // Nothing left the treasury.
+        assert_eq!(accounts[2].lamports, wallet);
+        assert_eq!(accounts[3].lamports, treasury);
+    }
+
+    #[test]
+    fn a_failed_respin_falls_back_to_paying_the_win() {
+        let (game, owed) = won();
+        let paid = |accounts: &[Fixture], wallet| {
+            let player = accounts[1].read::<PlayerState>();
+            assert!(!player.has_pending_spin);
+            assert_eq!(player.claimable_balance, 0);
+            assert_eq!(accounts[2].lamports, wallet + owed);
+        };
+
+        // A generation seeded two slots back is too old to commit to.
+        let mut stale = Randomness::default();
+        stale.seed(game.slot - 2, 9);
+        let mut accounts = respinning(&game, &stale);
+        let wallet = accounts[2].lamports;
+        simulate(&mut accounts, &respin_data(&stale, BET)).unwrap();
+        paid(&accounts, wallet);
+
+        // New spins paused: the settle still goes through.
+        let mut paused = game.clone();
+        paused.state.pause_flags = PAUSE_NEW_SPINS;
+        let next = fresh(&paused);
+        let mut accounts = respinning(&paused, &next);
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+        paid(&accounts, wallet);
+
+        // A re-bet over the bet limit.
+        let mut accounts = respinning(&game, &next);
+        simulate(&mut accounts, &respin_data(&next, game.state.max_bet + 1)).unwrap();
+        paid(&accounts, wallet);
+    }
+
+    #[test]
+    fn a_lost_spin_settles_without_a_respin() {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let loss = game.reveal_where(|settlement| settlement.owed() == 0);
+        game.reveal(loss);
+        let next = fresh(&game);
+        let mut accounts = respinning(&game, &next);
+        let wallet = accounts[2].lamports;
+
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+        assert!(!accounts[1].read::<PlayerState>().has_pending_spin);
+        assert_eq!(accounts[2].lamports, wallet);
+    }
+
+    #[test]
+    fn the_settled_randomness_cannot_be_rebet() {
+        let (game, _) = won();
+        let mut accounts = respinning(&game, &game.randomness);
+        assert_eq!(
+            simulate(&mut accounts, &respin_data(&game.randomness, BET)),
+            Err(program_error(ErrorCode::RespinReusesRandomness))
+        );
+
+        // Nor named for the new commit while another account is passed.
+        let next = fresh(&game);
+        let mut accounts = respinning(&game, &next);
+        assert_eq!(
+            simulate(&mut accounts, &respin_data(&game.randomness, BET)),
+            Err(program_error(ErrorCode::RespinReusesRandomness))
+        );
+
+        // Nor passed as the new account under a fresh name, and a refused
+        // respin has settled nothing.
+        let mut accounts = respinning(&game, &game.randomness);
+        let wallet = accounts[2].lamports;
+        assert_eq!(
+            simulate(&mut accounts, &respin_data(&next, BET)),
+            Err(program_error(ErrorCode::RespinReusesRandomness))
+        );
+        let player = accounts[1].read::<PlayerState>();
+        assert!(player.has_pending_spin);
+        assert_eq!(player.randomness_account, game.randomness.key);
+        assert_eq!(accounts[2].lamports, wallet);
+    }
+
+    #[test]
+    fn a_new_account_failing_request_spin_checks_falls_back() {
+        let (game, owed) = won();
+        let next = fresh(&game);
+
+        // Not owned by Switchboard.
+        let mut accounts = respinning(&game, &next);
+        accounts[5].owner = Pubkey::new_unique();
+        let wallet = accounts[2].lamports;
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+        assert!(!accounts[1].read::<PlayerState>().has_pending_spin);
+        assert_eq!(accounts[2].lamports, wallet + owed);
+
+        // The queue whitelist is on and no queue account is passed.
+        let mut gated = game.clone();
+        gated.state.oracle_queues[0] = OracleQueueEntry {
+            authority: Pubkey::new_unique(),
+            expected_reveal_slots: 1,
+        };
+        let mut accounts = respinning(&gated, &next);
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+        let player = accounts[1].read::<PlayerState>();
+        assert!(!player.has_pending_spin);
+        assert_eq!(player.randomness_account, Pubkey::default());
+        assert_eq!(accounts[2].lamports, wallet + owed);
+    }
+
+    #[test]
+    fn the_respin_commits_only_after_the_settle_cleared() {
+        let (game, owed) = won();
+        let next = fresh(&game);
+        let mut accounts = respinning(&game, &next);
+        simulate(&mut accounts, &respin_data(&next, BET)).unwrap();
+
+        // The settled spin is gone from the player's seed and pending
+        // bookkeeping; only the new commit remains.
+        let player = accounts[1].read::<PlayerState>();
+        assert_eq!(player.randomness_account, next.key);
+        assert_eq!(player.pending_bet_amount, BET);
+        assert_eq!(player.claimable_balance, owed - BET);
+    }
+
+    #[test]
+    fn a_settle_pause_or_rotated_randomness_fails_the_whole_respin() {
+        let (mut game, _) = won();
+        let next = fresh(&game);
+
+        let mut paused = game.clone();
+        paused.state.pause_flags = PAUSE_SETTLEMENT;
+        let mut accounts = respinning(&paused, &next);
+        assert_eq!(
+            simulate(&mut accounts, &respin_data(&next, BET)),
+            Err(program_error(ErrorCode::GamePaused))
+        );
+
+        game.randomness.seed(game.slot, 42);
+        let mut accounts = respinning(&game, &next);
+        assert_eq!(
+            simulate(&mut accounts, &respin_data(&next, BET)),
+            Err(program_error(ErrorCode::RandomnessSeedChanged))
+        );
+    }
+
+    /// admin_void_spin's accounts for the game's pending spin.
+    fn voiding(game: &Game) -> Vec<Fixture> {
+        let [state, player, _] = game.fixtures();
+        vec![state, game.authority(), player, game.randomness.fixture()]
+    }
+
+    #[test]
+    fn a_void_of_rotated_randomness_refunds_and_reports_it() {
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let committed_seed_slot = game.player.commit_slot;
+        let nonce = game.player.spin_nonce;
+        // Re-seeded and revealed for another round: the settle refuses it.
+        game.advance(2);
+        game.randomness.seed(game.slot, 42);
+        game.reveal([7; 32]);
+        assert_eq!(
+            game.clone().settle().unwrap_err(),
+            ErrorCode::RandomnessSeedChanged.into()
+        );
+
+        emitted::<SpinVoided>();
+        emitted::<RandomnessRotated>();
+        let mut accounts = voiding(&game);
+        let data = ix::AdminVoidSpin {
+            player: game.user,
+            reason_code: 3,
+        }
+        .data();
+        simulate(&mut accounts, &data).unwrap();
+
+        let player = accounts[2].read::<PlayerState>();
+        assert!(!player.has_pending_spin);
+        let mut voided = emitted::<SpinVoided>();
+        assert_eq!(voided.len(), 1);
+        let voided = voided.pop().unwrap();
+        assert!(voided.refunded > 0);
+        assert_eq!(player.claimable_balance, voided.refunded);
+        let mut events = emitted::<RandomnessRotated>();
+        assert_eq!(events.len(), 1);
+        let event = events.pop().unwrap();
+        assert_eq!(event.user, game.user);
+        assert_eq!(event.randomness_account, game.randomness.key);
+        assert_eq!(event.committed_seed_slot, committed_seed_slot);
+        assert_eq!(event.current_seed_slot, game.randomness.data.seed_slot);
+        assert_eq!(event.refunded, voided.refunded);
+        assert_eq!(event.spin_nonce, nonce);
+
+        // A void of the committed, unrevealed generation is no rotation.
+        let mut game = Game::new();
+        game.request(BET).unwrap();
+        let data = ix::AdminVoidSpin {
+            player: game.user,
+            reason_code: 3,
+        }
+        .data();
+        let mut accounts = voiding(&game);
+        simulate(&mut accounts, &data).unwrap();
+        assert_eq!(emitted::<SpinVoided>().len(), 1);
+        assert!(emitted::<RandomnessRotated>().is_empty());
+    }
+
+    /// settle_spins_batch's accounts for `seats`' pending spins on the
+    /// machine as `table` left it, cranked by a third party.
+    fn batching(table: &Game, seats: &[Game]) -> Vec<Fixture> {
+        let [state, _, treasury] = table.fixtures();
+        let mut accounts = vec![
+            state,
+            Fixture::wallet(Pubkey::new_unique(), GAME_WALLET).signer(),
+            treasury,
+            Fixture::program(anchor_lang::system_program::ID),
+        ];
+        for seat in seats {
+            let [_, player, _] = seat.fixtures();
+            accounts.push(player);
+            accounts.push(Fixture::wallet(seat.user, seat.wallet));
+            accounts.push(seat.randomness.fixture());
+        }
+        accounts
+    }
+
+    /// Two players whose revealed spins both hit the major, and the
+    /// machine they left, in `mode`.
+    fn two_major_hits(mode: JackpotBatchMode) -> (Game, Vec<Game>) {
+        let mut game = Game::new();
+        game.state.jackpot_batch_mode = mode;
+        let jackpots = &mut game.state.jackpots;
+        jackpots.mini.hit_weight = 0;
+        jackpots.grand.hit_weight = 0;
+        jackpots.major.hit_weight = jackpots.hit_weight_total;
+        jackpots.major.amount = 3 * jackpots.major.seed;
+        let mut seats = Vec::new();
+        for _ in 0..2 {
+            game = game.next_player();
+            game.request(game.state.max_bet).unwrap();
+            let hit = game.reveal_where(|settlement| {
+                settlement.outcome.jackpot_tier == Some(game_math::JACKPOT_TIER_MAJOR)
+            });
+            game.reveal(hit);
+            seats.push(game.clone());
+        }
+        (game, seats)
+    }
+
+    /// Run the batch; returns the machine after it, its spendable
+    /// treasury and what each seat's wallet received.
+    fn settle_batch(table: &Game, seats: &[Game]) -> (SlotsState, u64, Vec<u64>) {
+        let mut accounts = batching(table, seats);
+        let rent = accounts[2].lamports - table.treasury;
+        simulate(&mut accounts, &ix::SettleSpinsBatch {}.data()).unwrap();
+        let received = seats
+            .iter()
+            .enumerate()
+            .map(|(i, seat)| accounts[5 + 3 * i].lamports - seat.wallet)
+            .collect();
+        (accounts[0].read(), accounts[2].lamports - rent, received)
+    }
+
+    /// The batch took exactly what it paid out of the pool (the jackpot
+    /// tiers are part of it), released every reservation and left the
+    /// books whole.
+    fn assert_conserved(table: &Game, state: &SlotsState, treasury: u64, paid: u64) {
+        assert_eq!(table.treasury - treasury, paid);
+        assert_eq!(table.state.total_pool - state.total_pool, paid);
+        assert_eq!(state.pending_spins, 0);
+        assert_eq!(state.reserved_liability, 0);
+        accounting::verify(state, treasury, None).expect("books hold");
+    }
+
+    #[test]
+    fn a_split_batch_shares_the_pre_batch_jackpot() {
+        let (table, seats) = two_major_hits(JackpotBatchMode::Split);
+        let major = table.state.jackpots.major.amount;
+        emitted::<JackpotBatchShare>();
+        let (state, treasury, received) = settle_batch(&table, &seats);
+
+        let shares = emitted::<JackpotBatchShare>();
+        assert_eq!(shares.len(), 2);
+        for (share, seat) in shares.iter().zip(&seats) {
+            assert_eq!(share.user, seat.user);
+            assert_eq!(share.snapshot_amount, major);
+            assert_eq!(share.winners, 2);
+            assert_eq!(share.share, major / 2);
+        }
+        assert_eq!(shares.iter().map(|s| s.share).sum::<u64>(), major);
+        // The tier is reseeded once, out of the base pool.
+        assert_eq!(state.jackpots.major.amount, table.state.jackpots.major.seed);
+        assert_conserved(&table, &state, treasury, received.iter().sum());
+    }
+
+    #[test]
+    fn a_sequential_batch_pays_the_second_hit_the_reseed() {
+        let (table, seats) = two_major_hits(JackpotBatchMode::Sequential);
+        let major = table.state.jackpots.major.amount;
+        emitted::<JackpotBatchShare>();
+        let (state, treasury, received) = settle_batch(&table, &seats);
+
+        let shares = emitted::<JackpotBatchShare>();
+        assert_eq!(shares.len(), 2);
+        assert_eq!(shares[0].snapshot_amount, major);
+        assert_eq!(shares[0].share, major);
+        // The second hit sees the tier reseeded by the first.
+        let seed = table.state.jackpots.major.seed;
+        assert_eq!(shares[1].snapshot_amount, seed);
+        assert_eq!(shares[1].share, seed);
+        assert_eq!(state.jackpots.major.amount, seed);
+        for share in &shares {
+            assert_eq!(share.winners, 1);
+        }
+        assert_conserved(&table, &state, treasury, received.iter().sum());
+    }
+
+    /// A new player at `game`'s machine whose committed spin revealed an
+    /// outcome satisfying `wanted`.
+    fn seat_where(game: &Game, wanted: impl Fn(&Settlement) -> bool) -> Game {
+        let mut seat = game.next_player();
+        seat.request(BET).unwrap();
+        let value = seat.reveal_where(wanted);
+        seat.reveal(value);
+        seat
+    }
+
+    #[test]
+    fn a_batch_settles_each_ready_entry_and_skips_the_rest() {
+        let game = Game::new();
+        let winner = seat_where(&game, |settlement| settlement.owed() > 0);
+        let loser = seat_where(&winner, |settlement| settlement.owed() == 0);
+        let mut settled = seat_where(&loser, |_| true);
+        settled.settle().unwrap();
+        let mut unrevealed = settled.next_player();
+        unrevealed.request(BET).unwrap();
+        let seats = [winner, loser, settled, unrevealed];
+        let table = &seats[3];
+
+        emitted::<SpinSettled>();
+        emitted::<BatchSettled>();
+        let mut accounts = batching(table, &seats);
+        let rent = accounts[2].lamports - table.treasury;
+        simulate(&mut accounts, &ix::SettleSpinsBatch {}.data()).unwrap();
+
+        // One result per settled entry, tagged with its place in the batch.
+        let results = emitted::<SpinSettled>();
+        assert_eq!(results.len(), 2);
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.user, seats[index].user);
+            assert_eq!(result.context_index, index as u8);
+            assert_eq!(result.spin_nonce, seats[index].player.spin_nonce);
+        }
+        assert!(results[0].total_payout > 0);
+        assert_eq!(results[1].total_payout, 0);
+        let mut batches = emitted::<BatchSettled>();
+        assert_eq!(batches.len(), 1);
+        let batch = batches.pop().unwrap();
+        assert_eq!((batch.attempted, batch.settled), (4, 2));
+
+        let player = |i: usize| accounts[4 + 3 * i].read::<PlayerState>();
+        let received = |i: usize| accounts[5 + 3 * i].lamports - seats[i].wallet;
+        assert!(!player(0).has_pending_spin && !player(1).has_pending_spin);
+        assert!(received(0) >= results[0].total_payout);
+        assert_eq!(received(1), 0);
+        // The settled spin is left alone, the unrevealed one stays pending.
+        assert_eq!(player(2).spin_nonce, seats[2].player.spin_nonce);
+        assert_eq!(received(2), 0);
+        assert!(player(3).has_pending_spin);
+        assert_eq!(received(3), 0);
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!(state.pending_spins, 1);
+        assert_eq!(table.treasury - (accounts[2].lamports - rent), received(0));
+    }
+
+    #[test]
+    fn a_bad_entry_fails_the_whole_batch() {
+        let game = Game::new();
+        let winner = seat_where(&game, |settlement| settlement.owed() > 0);
+        let mut stray = seat_where(&winner, |_| true);
+        // The entry names a randomness account its spin did not commit to.
+        stray.randomness = Randomness::default();
+        let seats = [winner, stray];
+
+        let mut accounts = batching(&seats[1], &seats);
+        assert_eq!(
+            simulate(&mut accounts, &ix::SettleSpinsBatch {}.data()),
+            Err(program_error(ErrorCode::RandomnessAccountMismatch))
+        );
+        // Entries are checked before any settles: the winner is untouched.
+        assert!(accounts[4].read::<PlayerState>().has_pending_spin);
+        assert_eq!(accounts[5].lamports, seats[0].wallet);
+    }
+
+    /// settle_spin_deferred's accounts for the game's pending spin.
+    fn deferring(game: &Game) -> Vec<Fixture> {
+        let [state, player, _] = game.fixtures();
+        vec![
+            state,
+            player,
+            Fixture::wallet(game.user, game.wallet).signer(),
+            game.randomness.fixture(),
+        ]
+    }
+
+    /// A game whose pending spin has revealed a loss.
+    fn lost(mut game: Game) -> Game {
+        game.request(BET).unwrap();
+        let loss = game.reveal_where(|settlement| settlement.outcome.total_payout == 0);
+        game.reveal(loss);
+        game
+    }
+
+    #[test]
+    fn a_deferred_settle_books_what_the_full_settle_does() {
+        let mut game = Game::new();
+        game.state.community_bps = 1_000;
+        let game = lost(game);
+        let mut full = game.clone();
+        full.settle().unwrap();
+
+        let mut accounts = deferring(&game);
+        simulate(&mut accounts, &ix::SettleSpinDeferred {}.data()).unwrap();
+        // Nothing moved yet: the settle left SlotsState alone.
+        assert_eq!(accounts[0].read::<SlotsState>().total_pool, game.state.total_pool);
+        let mut flushing = vec![accounts[0].clone(), accounts[1].clone()];
+        simulate(&mut flushing, &ix::FlushStats {}.data()).unwrap();
+
+        let state = flushing[0].read::<SlotsState>();
+        let expected = &full.state;
+        assert!(expected.community_balance > 0);
+        assert_eq!(state.total_pool, expected.total_pool);
+        assert_eq!(state.community_balance, expected.community_balance);
+        assert_eq!(state.community_accrued, expected.community_accrued);
+        assert_eq!(state.community_deficit, expected.community_deficit);
+        assert_eq!(state.total_spins, expected.total_spins);
+        assert_eq!(state.total_wagered, expected.total_wagered);
+        assert_eq!(state.total_payout, expected.total_payout);
+        assert_eq!(state.global_spin_counter, expected.global_spin_counter);
+        assert_eq!(state.pending_spins, expected.pending_spins);
+        assert_eq!(state.reserved_liability, expected.reserved_liability);
+        let player = flushing[1].read::<PlayerState>();
+        assert!(!player.has_pending_spin);
+        assert_eq!(player.deferred_spins, 0);
+        assert_eq!(player.deferred_wagered, 0);
+        assert_eq!(player.deferred_reserved, 0);
+    }
+
+    #[test]
+    fn a_free_spin_cannot_settle_deferred() {
+        let mut game = Game::new();
+        game.state.free_spin_bet = BET;
+        game.player.free_spin_credit = BET;
+        game.state.earmarks.earmark(EarmarkKind::Voucher, BET).unwrap();
+        game.request(BET).unwrap();
+        game.reveal([7; 32]);
+        assert!(game.player.pending_free_spin);
+
+        let mut accounts = deferring(&game);
+        assert_eq!(
+            simulate(&mut accounts, &ix::SettleSpinDeferred {}.data()),
+            Err(program_error(ErrorCode::SettleNeedsWriteLock))
+        );
+        let player = accounts[1].read::<PlayerState>();
+        assert!(player.has_pending_spin);
+        assert_eq!(player.deferred_wagered, 0);
+    }
+
+    #[test]
+    fn the_global_spin_counter_rises_across_every_settle_path() {
+        // Probing for a reveal settles copies too: drain before each step.
+        let mut counters = Vec::new();
+        let mut single = lost(Game::new());
+        emitted::<SpinSettled>();
+        single.settle().unwrap();
+        counters.extend(emitted::<SpinSettled>().iter().map(|s| s.global_spin_counter));
+
+        let first = seat_where(&single, |_| true);
+        let seats = [first.clone(), seat_where(&first, |_| true)];
+        let mut table = seats[1].clone();
+        let mut accounts = batching(&table, &seats);
+        let rent = accounts[2].lamports - table.treasury;
+        emitted::<SpinSettled>();
+        simulate(&mut accounts, &ix::SettleSpinsBatch {}.data()).unwrap();
+        counters.extend(emitted::<SpinSettled>().iter().map(|s| s.global_spin_counter));
+        table.state = accounts[0].read();
+        table.treasury = accounts[2].lamports - rent;
+
+        // A deferred settle is numbered when its stats are flushed.
+        let deferred = lost(table.next_player());
+        let mut accounts = deferring(&deferred);
+        simulate(&mut accounts, &ix::SettleSpinDeferred {}.data()).unwrap();
+        let mut flushing = vec![accounts[0].clone(), accounts[1].clone()];
+        emitted::<StatsFlushed>();
+        simulate(&mut flushing, &ix::FlushStats {}.data()).unwrap();
+        let mut flushes = emitted::<StatsFlushed>();
+        assert_eq!(flushes.len(), 1);
+        let flush = flushes.pop().unwrap();
+        assert_eq!(flush.spins, 1);
+        counters.push(flush.first_global_spin_counter);
+
+        let mut last = lost(Game {
+            state: flushing[0].read(),
+            ..deferred.next_player()
+        });
+        emitted::<SpinSettled>();
+        last.settle().unwrap();
+        counters.extend(emitted::<SpinSettled>().iter().map(|s| s.global_spin_counter));
+
+        assert_eq!(counters.len(), 5);
+        assert!(counters.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert_eq!(last.state.global_spin_counter, counters[4]);
+    }
+
+    #[test]
+    fn an_expired_cancel_pays_the_bet_back_from_the_treasury() {
+        let mut game = Game::new();
+        let before = game.clone();
+        game.request(BET).unwrap();
+        game.advance(game.state.spin_expiry_slots);
+        let [state, player, treasury] = game.fixtures();
+        let mut accounts = vec![
+            state,
+            player,
+            Fixture::wallet(game.user, game.wallet).signer(),
+            game.randomness.fixture(),
+            treasury,
+            Fixture::program(anchor_lang::system_program::ID),
+        ];
+        let rent = accounts[4].lamports - game.treasury;
+
+        emitted::<SpinCancelled>();
+        simulate(&mut accounts, &ix::CancelSpin {}.data()).unwrap();
+        let mut cancels = emitted::<SpinCancelled>();
+        assert_eq!(cancels.len(), 1);
+        let cancel = cancels.pop().unwrap();
+        assert_eq!((cancel.refunded, cancel.forfeited), (BET, 0));
+        assert_eq!(accounts[2].lamports, game.wallet + BET);
+        assert_eq!(accounts[4].lamports - rent, game.treasury - BET);
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!(state.total_wagered, before.state.total_wagered);
+        assert_eq!(state.total_pool, before.state.total_pool);
+        assert_eq!(state.jackpots.major.amount, before.state.jackpots.major.amount);
+        assert_eq!(state.pending_spins, 0);
+        accounting::verify(&state, game.treasury - BET, None).expect("books hold");
+    }
+
+    #[test]
+    fn a_commit_reports_its_jackpot_contributions() {
+        let mut game = Game::new();
+        emitted::<JackpotContribution>();
+        let before = game.state.jackpots.clone();
+        game.request(BET).unwrap();
+
+        let mut events = emitted::<JackpotContribution>();
+        assert_eq!(events.len(), 1);
+        let event = events.pop().unwrap();
+        let jackpots = &game.state.jackpots;
+        let tiers = [
+            (&before.mini, &jackpots.mini),
+            (&before.major, &jackpots.major),
+            (&before.grand, &jackpots.grand),
+        ];
+        for (tier, (was, now)) in tiers.into_iter().enumerate() {
+            assert!(event.contributions[tier] > 0);
+            assert_eq!(now.amount, was.amount + event.contributions[tier]);
+            assert_eq!(event.new_amounts[tier], now.amount);
+        }
+
+        // A commit that adds nothing reports nothing.
+        game.reveal([0; 32]);
+        game.settle().unwrap();
+        game.state.jackpots.mini.contrib_bps = 0;
+        game.state.jackpots.major.contrib_bps = 0;
+        game.state.jackpots.grand.contrib_bps = 0;
+        game.request(BET).unwrap();
+        assert!(emitted::<JackpotContribution>().is_empty());
+    }
+
+    #[test]
+    fn a_jackpot_hit_reports_its_tier_and_reset() {
+        let mut game = Game::new();
+        // Every roll lands on the major.
+        let jackpots = &mut game.state.jackpots;
+        jackpots.mini.hit_weight = 0;
+        jackpots.grand.hit_weight = 0;
+        jackpots.major.hit_weight = jackpots.hit_weight_total;
+        jackpots.major.amount = BET;
+        game.request(game.state.max_bet).unwrap();
+        let hit = game.reveal_where(|settlement| settlement.outcome.jackpot_tier.is_some());
+        game.reveal(hit);
+        emitted::<JackpotHit>();
+        let nonce = game.player.spin_nonce;
+        let settlement = game.settle().unwrap();
+
+        let mut events = emitted::<JackpotHit>();
+        assert_eq!(events.len(), 1);
+        let event = events.pop().unwrap();
+        assert_eq!(event.user, game.user);
+        assert_eq!(event.tier, game_math::JACKPOT_TIER_MAJOR);
+        assert_eq!(event.amount, settlement.outcome.jackpot_payout);
+        assert_eq!(event.reset_to_seed, game.state.jackpots.major.amount);
+        assert_eq!(event.randomness_account, game.randomness.key);
+        assert_eq!(event.spin_nonce, nonce);
+
+        // A spin without a hit reports none.
+        game.state.jackpots.major.hit_weight = 0;
+        game.play_where(BET, |_| true).unwrap();
+        assert!(emitted::<JackpotHit>().is_empty());
+    }
+
+    /// onboard_and_spin's accounts for `user` committing on `next`, with
+    /// `player` at the user's PlayerState address.
+    fn onboarding(game: &Game, user: &Fixture, player: Fixture, next: &Randomness) -> Vec<Fixture> {
+        let [state, _, treasury] = game.fixtures();
+        vec![
+            state,
+            player,
+            Fixture::wallet(pda(&[ACCOUNT_INDEX_SEED, user.key.as_ref()]).0, 0),
+            user.clone(),
+            treasury,
+            Fixture {
+                writable: true,
+                ..next.fixture()
+            },
+            Fixture::omitted(),
+            Fixture::omitted(),
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    fn onboard_data(next: &Randomness, bet_amount: u64, session_deposit: u64) -> Vec<u8> {
+        ix::OnboardAndSpin {
+            randomness_account: next.key,
+            bet_amount,
+            session_deposit,
+        }
+        .data()
+    }
+
+    /// A new wallet and the uninitialized PlayerState address it spins from.
+    fn newcomer(game: &Game, lamports: u64) -> (Fixture, Fixture) {
+        let user = Fixture::wallet(Pubkey::new_unique(), lamports).signer();
+        let machine = machine_seed(&game.state.machine_id);
+        let (player, _) = pda(&[PLAYER_SEED, machine, user.key.as_ref()]);
+        (user, Fixture::uninit(player, PlayerState::LEN))
+    }
+
+    #[test]
+    fn a_fresh_player_is_created_funded_and_committed_at_once() {
+        let game = Game::new();
+        let next = fresh(&game);
+        let (user, player) = newcomer(&game, GAME_WALLET);
+        let mut accounts = onboarding(&game, &user, player, &next);
+        let treasury = accounts[4].lamports;
+
+        assert_eq!(client::spin_entry(None), client::SpinEntry::OnboardAndSpin);
+        simulate(&mut accounts, &onboard_data(&next, BET, DEPOSIT)).unwrap();
+        assert_eq!(client::spin_entry(Some(&accounts[1].data)), client::SpinEntry::RequestSpin);
+        let player = accounts[1].read::<PlayerState>();
+        assert_eq!(player.owner, user.key);
+        assert!(player.has_pending_spin);
+        assert_eq!(player.randomness_account, next.key);
+        assert_eq!(player.pending_bet_amount, BET);
+        assert_eq!(player.claimable_balance, DEPOSIT);
+        assert_eq!(accounts[0].read::<SlotsState>().total_claimable, DEPOSIT);
+
+        let charge = BET + oracle_cost::player_charge(&game.state) + DEPOSIT;
+        let rent = Rent::default().minimum_balance(PlayerState::LEN);
+        assert_eq!(accounts[1].lamports, rent);
+        assert_eq!(accounts[3].lamports, GAME_WALLET - rent - charge);
+        assert_eq!(accounts[4].lamports, treasury + charge);
+    }
+
+    #[test]
+    fn an_existing_player_is_not_reset() {
+        let mut game = Game::new();
+        game.player.claimable_balance = DEPOSIT;
+        game.state.total_claimable = DEPOSIT;
+        game.player.spin_nonce = 7;
+        let next = fresh(&game);
+        let [_, mut player, _] = game.fixtures();
+        player.lamports = Rent::default().minimum_balance(PlayerState::LEN);
+        let user = Fixture::wallet(game.user, game.wallet).signer();
+        let mut accounts = onboarding(&game, &user, player, &next);
+
+        simulate(&mut accounts, &onboard_data(&next, BET, 0)).unwrap();
+        let player = accounts[1].read::<PlayerState>();
+        assert_eq!(player.owner, game.user);
+        assert_eq!(player.claimable_balance, DEPOSIT);
+        // The commit counts on from the old nonce.
+        assert_eq!(player.spin_nonce, 8);
+        assert!(player.has_pending_spin);
+
+        // The pending spin now blocks a second onboard_and_spin.
+        let again = fresh(&game);
+        accounts[5] = Fixture {
+            writable: true,
+            ..again.fixture()
+        };
+        assert_eq!(
+            simulate(&mut accounts, &onboard_data(&again, BET, 0)),
+            Err(program_error(ErrorCode::PendingSpinExists))
+        );
+    }
+
+    #[test]
+    fn onboarding_keeps_the_spin_checks() {
+        let mut game = Game::new();
+        let next = fresh(&game);
+
+        // Another wallet's PlayerState cannot be used.
+        let (user, _) = newcomer(&game, GAME_WALLET);
+        let [_, theirs, _] = game.fixtures();
+        let mut accounts = onboarding(&game, &user, theirs, &next);
+        let seeds = anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintSeeds);
+        assert_eq!(simulate(&mut accounts, &onboard_data(&next, BET, 0)), Err(seeds.into()));
+
+        // The charge must be covered on top of the rent.
+        let rent = Rent::default().minimum_balance(PlayerState::LEN);
+        let (user, player) = newcomer(&game, rent + BET);
+        let mut accounts = onboarding(&game, &user, player, &next);
+        assert_eq!(
+            simulate(&mut accounts, &onboard_data(&next, BET, DEPOSIT)),
+            Err(program_error(ErrorCode::InsufficientFunds))
+        );
+
+        let (user, player) = newcomer(&game, GAME_WALLET);
+        let mut accounts = onboarding(&game, &user, player, &next);
+        let max_bet = game.state.max_bet;
+        assert_eq!(
+            simulate(&mut accounts, &onboard_data(&next, max_bet + 1, 0)),
+            Err(program_error(ErrorCode::BetTooHigh))
+        );
+
+        game.state.pause_flags = PAUSE_NEW_SPINS;
+        let (user, player) = newcomer(&game, GAME_WALLET);
+        let mut accounts = onboarding(&game, &user, player, &next);
+        assert_eq!(
+            simulate(&mut accounts, &onboard_data(&next, BET, 0)),
+            Err(program_error(ErrorCode::GamePaused))
+        );
+    }
+}
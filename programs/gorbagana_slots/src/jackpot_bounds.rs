@@ -0,0 +1,335 @@
+//! Tail-risk bounds on the jackpot hit weights.
+//!
+//! One jackpot roll happens per spin: tier `i` hits with probability
+//! `hit_weight_i / hit_weight_total`, and whatever the tier weights leave
+//! of the total is the no-hit weight. set_jackpot_weights refuses a table
+//! where
+//!
+//! - any tier hits more often than `jackpot_max_hit_ppm` (parts per
+//!   million), itself capped at [`ABSOLUTE_MAX_TIER_HIT_PPM`], or
+//! - the expected jackpot cost of a spin, `sum(p_i * max(amount_i,
+//!   seed_i))`, exceeds `jackpot_max_cost_bps` of
+//!   `jackpot_cost_reference_bet`. The cost does not scale with the bet,
+//!   so the reference should be the smallest bet the operator expects.
+//!
+//! A table outside the configured ceilings can still go live through
+//! propose_jackpot_weights / activate_jackpot_weights, which wait
+//! JACKPOT_WEIGHTS_TIMELOCK_SLOTS. The absolute hit ceiling holds on both
+//! paths.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, JackpotsConfig, SlotsState};
+
+/// No tier may hit more than 1% of spins, whatever the configuration.
+pub const ABSOLUTE_MAX_TIER_HIT_PPM: u32 = 10_000;
+/// Default expected jackpot cost ceiling: 5% of the reference bet.
+pub const DEFAULT_MAX_JACKPOT_COST_BPS: u16 = 500;
+/// Default reference bet for the cost ceiling (0.01 SOL).
+pub const DEFAULT_JACKPOT_COST_REFERENCE_BET: u64 = 10_000_000;
+
+/// Hit weights for the three tiers plus the no-hit weight.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct JackpotWeights {
+    pub mini: u32,
+    pub major: u32,
+    pub grand: u32,
+    pub no_hit: u32,
+}
+
+impl JackpotWeights {
+    pub fn tiers(&self) -> [u32; 3] {
+        [self.mini, self.major, self.grand]
+    }
+
+    /// Roll range (`hit_weight_total`), or None on overflow.
+    pub fn total(&self) -> Option<u32> {
+        self.tiers()
+            .iter()
+            .try_fold(self.no_hit, |acc, &w| acc.checked_add(w))
+    }
+}
+
+/// Hit probability of a tier weight, in parts per million.
+pub fn hit_ppm(weight: u32, total: u32) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    (weight as u64) * 1_000_000 / (total as u64)
+}
+
+/// Expected jackpot cost of one spin as bps of `reference_bet`, valuing
+/// each tier at its current amount or its seed, whichever is larger.
+pub fn expected_cost_bps(
+    jackpots: &JackpotsConfig,
+    weights: &JackpotWeights,
+    reference_bet: u64,
+) -> u128 {
+    let total = weights.total().unwrap_or(0) as u128;
+    if total == 0 || reference_bet == 0 {
+        return 0;
+    }
+    let pools = [&jackpots.mini, &jackpots.major, &jackpots.grand];
+    let weighted: u128 = pools
+        .iter()
+        .zip(weights.tiers())
+        .map(|(pool, w)| (w as u128) * (pool.amount.max(pool.seed) as u128))
+        .sum();
+    weighted * 10_000 / (total * reference_bet as u128)
+}
+
+/// Checks every weight table must pass: a representable total and no
+/// tier above ABSOLUTE_MAX_TIER_HIT_PPM.
+pub fn check_absolute(weights: &JackpotWeights) -> Result<()> {
+    let total = weights.total().ok_or(ErrorCode::InvalidJackpotWeights)?;
+    require!(
+        weights
+            .tiers()
+            .iter()
+            .all(|&w| hit_ppm(w, total) <= ABSOLUTE_MAX_TIER_HIT_PPM as u64),
+        ErrorCode::JackpotHitProbabilityTooHigh
+    );
+    Ok(())
+}
+
+/// Full check against the configured ceilings.
+pub fn check(state: &SlotsState, weights: &JackpotWeights) -> Result<()> {
+    check_absolute(weights)?;
+    let total = weights.total().ok_or(ErrorCode::InvalidJackpotWeights)?;
+    require!(
+        weights
+            .tiers()
+            .iter()
+            .all(|&w| hit_ppm(w, total) <= state.jackpot_max_hit_ppm as u64),
+        ErrorCode::JackpotHitProbabilityTooHigh
+    );
+    require!(
+        expected_cost_bps(&state.jackpots, weights, state.jackpot_cost_reference_bet)
+            <= state.jackpot_max_cost_bps as u128,
+        ErrorCode::JackpotCostTooHigh
+    );
+    Ok(())
+}
+
+/// Install `weights` into the live jackpot config.
+pub fn apply(jackpots: &mut JackpotsConfig, weights: &JackpotWeights) -> Result<()> {
+    jackpots.hit_weight_total = weights.total().ok_or(ErrorCode::InvalidJackpotWeights)?;
+    jackpots.mini.hit_weight = weights.mini;
+    jackpots.major.hit_weight = weights.major;
+    jackpots.grand.hit_weight = weights.grand;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, zeroed_state, Fixture, Game};
+    use crate::{instruction, JACKPOT_WEIGHTS_TIMELOCK_SLOTS};
+
+    const MILLION: u32 = 1_000_000;
+
+    /// `grand` out of a million, the rest no-hit.
+    fn grand(weight: u32) -> JackpotWeights {
+        JackpotWeights {
+            grand: weight,
+            no_hit: MILLION - weight,
+            ..JackpotWeights::default()
+        }
+    }
+
+    /// A state whose only jackpot value is a 0.1 SOL mini seed, so a mini
+    /// weight of 5000 in a million costs exactly 500 bps of a 0.01 SOL bet.
+    fn priced() -> SlotsState {
+        let mut state = zeroed_state();
+        state.jackpot_max_hit_ppm = ABSOLUTE_MAX_TIER_HIT_PPM;
+        state.jackpot_max_cost_bps = DEFAULT_MAX_JACKPOT_COST_BPS;
+        state.jackpot_cost_reference_bet = DEFAULT_JACKPOT_COST_REFERENCE_BET;
+        state.jackpots.mini.seed = 100_000_000;
+        state
+    }
+
+    fn mini(weight: u32) -> JackpotWeights {
+        JackpotWeights {
+            mini: weight,
+            no_hit: MILLION - weight,
+            ..JackpotWeights::default()
+        }
+    }
+
+    #[test]
+    fn the_absolute_hit_ceiling_holds_at_one_percent() {
+        let max = ABSOLUTE_MAX_TIER_HIT_PPM;
+        assert_eq!(hit_ppm(max, MILLION), 10_000);
+        assert_eq!(hit_ppm(1, 0), 0);
+        check_absolute(&grand(max)).unwrap();
+        assert_eq!(
+            check_absolute(&grand(max + 1)).unwrap_err(),
+            ErrorCode::JackpotHitProbabilityTooHigh.into()
+        );
+
+        let overflowing = JackpotWeights {
+            grand: 1,
+            no_hit: u32::MAX,
+            ..JackpotWeights::default()
+        };
+        assert_eq!(overflowing.total(), None);
+        assert_eq!(
+            check_absolute(&overflowing).unwrap_err(),
+            ErrorCode::InvalidJackpotWeights.into()
+        );
+    }
+
+    #[test]
+    fn the_configured_hit_ceiling_is_tighter() {
+        let mut state = priced();
+        state.jackpot_max_hit_ppm = 100;
+
+        check(&state, &grand(100)).unwrap();
+        assert_eq!(
+            check(&state, &grand(101)).unwrap_err(),
+            ErrorCode::JackpotHitProbabilityTooHigh.into()
+        );
+    }
+
+    #[test]
+    fn the_expected_cost_counts_the_larger_of_amount_and_seed() {
+        let mut state = priced();
+        assert_eq!(
+            expected_cost_bps(&state.jackpots, &mini(5_000), DEFAULT_JACKPOT_COST_REFERENCE_BET),
+            500
+        );
+        check(&state, &mini(5_000)).unwrap();
+
+        state.jackpot_max_cost_bps = 499;
+        assert_eq!(
+            check(&state, &mini(5_000)).unwrap_err(),
+            ErrorCode::JackpotCostTooHigh.into()
+        );
+
+        // A grown pool costs more than its seed.
+        state.jackpot_max_cost_bps = DEFAULT_MAX_JACKPOT_COST_BPS;
+        state.jackpots.mini.amount = 2 * state.jackpots.mini.seed;
+        assert_eq!(
+            check(&state, &mini(5_000)).unwrap_err(),
+            ErrorCode::JackpotCostTooHigh.into()
+        );
+        check(&state, &mini(2_500)).unwrap();
+
+        // The installed table rolls over the whole total.
+        apply(&mut state.jackpots, &mini(2_500)).unwrap();
+        assert_eq!(state.jackpots.hit_weight_total, MILLION);
+        assert_eq!(state.jackpots.mini.hit_weight, 2_500);
+    }
+
+    #[test]
+    fn bounds_and_weights_are_set_by_the_authority_within_limits() {
+        let mut game = Game::new();
+        game.state.jackpots = priced().jackpots;
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let bounds = |max_hit_ppm, max_cost_bps, reference_bet| {
+            instruction::SetJackpotBounds {
+                max_hit_ppm,
+                max_cost_bps,
+                reference_bet,
+            }
+            .data()
+        };
+        let weights = |weights| instruction::SetJackpotWeights { weights }.data();
+        let reference = DEFAULT_JACKPOT_COST_REFERENCE_BET;
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &bounds(100, 500, reference)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        for invalid in [
+            bounds(ABSOLUTE_MAX_TIER_HIT_PPM + 1, 500, reference),
+            bounds(100, 10_001, reference),
+            bounds(100, 500, 0),
+        ] {
+            assert_eq!(
+                simulate([&mut state, &mut authority, &mut no_metrics], &invalid),
+                Err(program_error(ErrorCode::InvalidJackpotBounds))
+            );
+        }
+        simulate([&mut state, &mut authority, &mut no_metrics], &bounds(100, 500, reference))
+            .unwrap();
+        assert_eq!(state.read::<SlotsState>().jackpot_max_hit_ppm, 100);
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &weights(mini(100))),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &weights(mini(101))),
+            Err(program_error(ErrorCode::JackpotHitProbabilityTooHigh))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &weights(mini(100))).unwrap();
+        let jackpots = state.read::<SlotsState>().jackpots;
+        assert_eq!((jackpots.mini.hit_weight, jackpots.hit_weight_total), (100, MILLION));
+    }
+
+    #[test]
+    fn an_exotic_table_goes_live_only_through_the_timelock() {
+        let mut game = Game::new();
+        game.state.jackpots = priced().jackpots;
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let propose = |weights| instruction::ProposeJackpotWeights { weights }.data();
+        let activate = instruction::ActivateJackpotWeights {}.data();
+        // Within the absolute ceiling, but at twice the cost ceiling.
+        let exotic = mini(10_000);
+
+        assert_eq!(
+            simulate(
+                [&mut state, &mut authority, &mut no_metrics],
+                &instruction::SetJackpotWeights { weights: exotic }.data()
+            ),
+            Err(program_error(ErrorCode::JackpotCostTooHigh))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &activate),
+            Err(program_error(ErrorCode::InvalidJackpotWeights))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &propose(exotic)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &propose(grand(10_001))),
+            Err(program_error(ErrorCode::JackpotHitProbabilityTooHigh))
+        );
+
+        simulate([&mut state, &mut authority, &mut no_metrics], &propose(exotic)).unwrap();
+        let proposed = state.read::<SlotsState>();
+        assert_eq!(proposed.pending_jackpot_weights, exotic);
+        assert_eq!(
+            proposed.pending_jackpot_weights_activate_slot,
+            game.slot + JACKPOT_WEIGHTS_TIMELOCK_SLOTS
+        );
+
+        game.advance(JACKPOT_WEIGHTS_TIMELOCK_SLOTS - 1);
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &activate),
+            Err(program_error(ErrorCode::JackpotWeightsTimelocked))
+        );
+        game.advance(1);
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &activate),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &activate).unwrap();
+        let live = state.read::<SlotsState>();
+        assert_eq!(live.jackpots.mini.hit_weight, exotic.mini);
+        assert_eq!(live.pending_jackpot_weights, JackpotWeights::default());
+        assert_eq!(live.pending_jackpot_weights_activate_slot, 0);
+    }
+}
@@ -0,0 +1,994 @@
+//! Byte layouts of program accounts, for indexers and layout guards.
+//!
+//! Offsets are derived by serializing each field of a populated account in
+//! declaration order and summing the Borsh lengths, so they always reflect
+//! what is really written on-chain. Every account starts with its 8-byte
+//! Anchor discriminator.
+//!
+//! Field lists here must follow the struct declarations. Appending a field
+//! adds an entry; reordering or resizing one moves offsets, which [`diff`]
+//! reports as a list of human-readable changes.
+//!
+//! The tests pin every account to a golden fixture under `layouts/`: every
+//! field set to a value derived from its name, serialized, and recorded
+//! as `name offset size value`. A reordered, resized or retyped field
+//! fails the test with the offsets that moved. `layouts/events.txt` pins
+//! the discriminator of every event and `layouts/event_payloads.txt` a
+//! populated payload of each. Accounts other than SlotsState and
+//! PlayerState, and the events, are populated from their IDL types. Rerun
+//! with `UPDATE_LAYOUTS=1` to regenerate them after a deliberate change.
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+
+use crate::{PlayerState, SlotsState};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldOffset {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountLayout {
+    pub account: &'static str,
+    pub discriminator: [u8; 8],
+    pub fields: Vec<FieldOffset>,
+}
+
+impl AccountLayout {
+    /// Serialized length including the discriminator.
+    pub fn len(&self) -> usize {
+        self.fields.last().map(|f| f.offset + f.size).unwrap_or(8)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldOffset> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+struct Walker {
+    offset: usize,
+    fields: Vec<FieldOffset>,
+}
+
+impl Walker {
+    fn new() -> Self {
+        Walker {
+            offset: 8,
+            fields: Vec::new(),
+        }
+    }
+
+    fn field<T: AnchorSerialize>(&mut self, name: &'static str, value: &T) {
+        let size = value.try_to_vec().expect("field serializes").len();
+        self.fields.push(FieldOffset {
+            name,
+            offset: self.offset,
+            size,
+        });
+        self.offset += size;
+    }
+}
+
+macro_rules! walk {
+    ($value:expr; $($field:ident),* $(,)?) => {{
+        let mut w = Walker::new();
+        $( w.field(stringify!($field), &$value.$field); )*
+        w.fields
+    }};
+}
+
+/// SlotsState fields in declaration order, handed to `$m`.
+macro_rules! slots_state_fields {
+    ($m:ident!($value:expr)) => {
+        $m!($value;
+            authority,
+            treasury,
+            initialized,
+            pause_flags,
+            total_spins,
+            total_wagered,
+            total_payout,
+            house_profit,
+            current_rtp_bps,
+            house_edge_bps,
+            total_pool,
+            max_payout_per_spin,
+            min_pool_threshold,
+            jackpots,
+            beta_mode,
+            allowlist_pages,
+            traffic_half_life_secs,
+            traffic_spin_weight,
+            traffic_wager_weight,
+            traffic_last_update_ts,
+            fee_balance,
+            winner_fee_rebate_lamports,
+            rebate_daily_cap_per_player,
+            rebate_suspend_below,
+            rebate_resume_at,
+            rebate_suspended,
+            settle_hooks,
+            pending_settle_hook,
+            pending_settle_hook_index,
+            pending_settle_hook_activate_slot,
+            jackpot_batch_mode,
+            bootstrap_maturity_threshold,
+            bootstrap_payout_bps,
+            bootstrap_hysteresis_bps,
+            bootstrap_active,
+            last_commit_slot,
+            withdrawal_cooldown_slots,
+            jackpot_max_caps,
+            jackpot_boosted_totals,
+            total_claimable,
+            earmarks,
+            scheduled_resume_slot,
+            pending_spins,
+            exclusive_mint,
+            jackpot_reseed_policies,
+            jackpot_rebuild_bps,
+            outflow_totals,
+            oracle_cost_lamports,
+            oracle_cost_mode,
+            oracle_costs_paid,
+            oracle_costs_charged,
+            frozen_version,
+            min_settle_version,
+            recovery_authority,
+            recovery_inactivity_slots,
+            last_authority_action_slot,
+            tier_limits,
+            last_successful_reveal_slot,
+            oldest_unrevealed_commit_slot,
+            oracle_max_reveal_gap_slots,
+            oracle_expected_reveal_slots,
+            oracle_gate_closed,
+            community_authority,
+            community_bps,
+            community_balance,
+            community_accrued,
+            community_deficit,
+            onboarding_sponsor,
+            sponsored_daily_budget,
+            sponsored_day,
+            sponsored_today,
+            sponsored_total,
+            total_deposits,
+            total_fee_rebates,
+            jackpot_hits,
+            jackpot_paid,
+            epoch_report_page,
+            network_guard,
+            jackpot_max_hit_ppm,
+            jackpot_max_cost_bps,
+            jackpot_cost_reference_bet,
+            pending_jackpot_weights,
+            pending_jackpot_weights_activate_slot,
+            pool_below_required,
+            wager_race_count,
+            reserved_liability,
+            spin_expiry_slots,
+            min_bet,
+            max_bet,
+            use_fees_for_overflow,
+            fee_overflow_max_per_spin,
+            total_fee_overflow,
+            pending_authority,
+            oracle_queues,
+            oracle_queue_margin_slots,
+            feature_stats,
+            token_treasury,
+            token_decimals,
+            crank_fee,
+            max_outcomes_per_reveal,
+            version,
+            extraction_budget_bps,
+            extraction_period_end,
+            extraction_period_budget,
+            extraction_period_used,
+            extraction_override,
+            extraction_override_activate_slot,
+            paytable,
+            max_rtp_bps,
+            staff_wallets,
+            faucet_reserve,
+            faucet_amount,
+            faucet_interval_slots,
+            faucet_lifetime_cap,
+            faucet_dripped,
+            jackpot_qualifying_bet,
+            operator_fee_bps,
+            fee_vault,
+            operator_fee_balance,
+            operator_fees_accrued,
+            theoretical_rtp_bps,
+            total_shares,
+            house_shares,
+            withdrawal_delay_slots,
+            pending_withdrawal_amount,
+            pending_withdrawal_unlock_slot,
+            pending_withdrawal_category,
+            pending_withdrawal_memo,
+            points_per_lamport_bps,
+            free_spin_point_cost,
+            free_spin_bet,
+            referral_bps,
+            referral_balance,
+            min_slots_between_spins,
+            loss_window_slots,
+            global_spin_counter,
+            reel_weights,
+            machine_id,
+            scatter,
+            wild_symbol,
+            leaderboard_epoch_slots,
+            jackpot_must_hit_by,
+            jackpot_forced_hits,
+            vip_config,
+            vip_rebate_balance,
+            share_epoch,
+        )
+    };
+}
+
+/// PlayerState fields in declaration order, handed to `$m`.
+macro_rules! player_state_fields {
+    ($m:ident!($value:expr)) => {
+        $m!($value;
+            owner,
+            bump,
+            randomness_account,
+            pending_bet_amount,
+            has_pending_spin,
+            rebate_day,
+            rebate_paid_today,
+            commit_slot,
+            commit_seed_slothash,
+            pending_jackpot_contribs,
+            claimable_balance,
+            deferred_spins,
+            deferred_wagered,
+            deferred_last_ts,
+            next_bet_nonce,
+            commit_version,
+            tier,
+            forced_outcome,
+            commit_payout_cap,
+            commit_jackpot_mask,
+            race_id,
+            race_wagered,
+            deferred_reserved,
+            spin_nonce,
+            pending_outcomes,
+            pending_lines,
+            total_spins,
+            total_wagered,
+            total_won,
+            biggest_win,
+            last_spin_slot,
+            faucet_last_slot,
+            faucet_received,
+            commit_jackpot_odds,
+            recent_seeds,
+            recent_seed_head,
+            pending_operator_fee,
+            loyalty_points,
+            free_spin_credit,
+            pending_free_spin,
+            referrer,
+            last_request_slot,
+            self_excluded_until,
+            loss_limit_lamports,
+            session_losses,
+            session_start_slot,
+            last_reveal,
+            last_reveal_seed_slot,
+            free_spins_remaining,
+            free_spin_bet,
+            pending_bonus_spin,
+            leaderboard_epoch,
+            leaderboard_won,
+            rebate_accrued,
+            spins_remaining,
+            vip_token_account,
+            leaderboard,
+        )
+    };
+}
+
+pub fn slots_state(state: &SlotsState) -> AccountLayout {
+    AccountLayout {
+        account: "SlotsState",
+        discriminator: SlotsState::DISCRIMINATOR,
+        fields: slots_state_fields!(walk!(state)),
+    }
+}
+
+pub fn player_state(state: &PlayerState) -> AccountLayout {
+    AccountLayout {
+        account: "PlayerState",
+        discriminator: PlayerState::DISCRIMINATOR,
+        fields: player_state_fields!(walk!(state)),
+    }
+}
+
+/// Human-readable differences between a committed layout and the current
+/// one: moved, resized, added and removed fields. Empty when identical.
+pub fn diff(expected: &AccountLayout, actual: &AccountLayout) -> Vec<String> {
+    let mut out = Vec::new();
+    if expected.discriminator != actual.discriminator {
+        out.push(format!(
+            "{}: discriminator {:?} -> {:?}",
+            actual.account, expected.discriminator, actual.discriminator
+        ));
+    }
+    for e in &expected.fields {
+        match actual.field(e.name) {
+            None => out.push(format!("{}.{}: removed (was at {})", actual.account, e.name, e.offset)),
+            Some(a) if a.offset != e.offset || a.size != e.size => out.push(format!(
+                "{}.{}: offset {} size {} -> offset {} size {}",
+                actual.account, e.name, e.offset, e.size, a.offset, a.size
+            )),
+            Some(_) => {}
+        }
+    }
+    for a in &actual.fields {
+        if expected.field(a.name).is_none() {
+            out.push(format!("{}.{}: added at {}", actual.account, a.name, a.offset));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorDeserialize;
+
+    use bytemuck::Pod;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::events::*;
+    use crate::test_util::{zeroed_player, zeroed_state};
+    use crate::{
+        AccountIndex, Allowlist, CommunityVault, EpochReportPage, Leaderboard, Metrics,
+        PoolPosition, ReceiptTree, ReferralEarnings, RtpHistory, SymbolMeta, WagerRace,
+    };
+
+    /// Set to rewrite the fixtures under `layouts/` from the current structs.
+    const UPDATE_VAR: &str = "UPDATE_LAYOUTS";
+
+    const SLOTS_STATE_FIXTURE: &str = include_str!("../layouts/slots_state.txt");
+    const PLAYER_STATE_FIXTURE: &str = include_str!("../layouts/player_state.txt");
+    const EVENTS_FIXTURE: &str = include_str!("../layouts/events.txt");
+    const EVENT_PAYLOADS_FIXTURE: &str = include_str!("../layouts/event_payloads.txt");
+    const IDL: &str = include_str!("../idl/gorbagana_slots.json");
+
+    /// Eight bytes derived from `name` alone.
+    fn name_seed(name: &str) -> [u8; 8] {
+        name.bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+                (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+            .to_le_bytes()
+    }
+
+    /// Set `field` to bytes derived from `name` alone, so the value does
+    /// not depend on where the field sits. Types those bytes do not
+    /// decode as (bools, enums) get all ones instead.
+    fn fill<T: AnchorSerialize + AnchorDeserialize>(field: &mut T, name: &str) {
+        let len = field.try_to_vec().expect("field serializes").len();
+        let seed = name_seed(name);
+        let named: Vec<u8> = (0..len).map(|i| seed[i % 8]).collect();
+        for bytes in [named, vec![1; len]] {
+            if let Ok(value) = T::try_from_slice(&bytes) {
+                *field = value;
+                return;
+            }
+        }
+        panic!("cannot populate field {name}");
+    }
+
+    macro_rules! populate {
+        ($value:expr; $($field:ident),* $(,)?) => {{
+            $( fill(&mut $value.$field, stringify!($field)); )*
+        }};
+    }
+
+    /// Each field serialized on its own, in list order.
+    macro_rules! values {
+        ($value:expr; $($field:ident),* $(,)?) => {
+            vec![$( $value.$field.try_to_vec().expect("field serializes") ),*]
+        };
+    }
+
+    fn populated_state() -> SlotsState {
+        let mut state = zeroed_state();
+        slots_state_fields!(populate!(state));
+        state
+    }
+
+    fn populated_player() -> PlayerState {
+        let mut player = zeroed_player();
+        player_state_fields!(populate!(player));
+        player
+    }
+
+    fn account_bytes<T: AnchorSerialize + Discriminator>(account: &T) -> Vec<u8> {
+        let mut bytes = T::DISCRIMINATOR.to_vec();
+        bytes.extend(account.try_to_vec().expect("account serializes"));
+        bytes
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Fixture text: the discriminator, then one `name offset size value`
+    /// line per field.
+    fn render(layout: &AccountLayout, bytes: &[u8]) -> String {
+        let mut out = format!(
+            "# {} golden layout; regenerate with {}=1.\ndiscriminator {}\n",
+            layout.account,
+            UPDATE_VAR,
+            to_hex(&layout.discriminator)
+        );
+        for f in &layout.fields {
+            out += &format!(
+                "{} {} {} {}\n",
+                f.name,
+                f.offset,
+                f.size,
+                to_hex(&bytes[f.offset..f.offset + f.size])
+            );
+        }
+        out
+    }
+
+    struct Fixture {
+        layout: AccountLayout,
+        values: Vec<String>,
+    }
+
+    fn parse(account: &'static str, text: &'static str) -> Fixture {
+        let mut layout = AccountLayout {
+            account,
+            discriminator: [0; 8],
+            fields: Vec::new(),
+        };
+        let mut values = Vec::new();
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let parts: Vec<&'static str> = line.split(' ').collect();
+            if let ["discriminator", hex] = parts[..] {
+                let bytes = from_hex(hex);
+                layout.discriminator.copy_from_slice(&bytes);
+                continue;
+            }
+            let [name, offset, size, value] = parts[..] else {
+                panic!("bad fixture line: {line}");
+            };
+            layout.fields.push(FieldOffset {
+                name,
+                offset: offset.parse().expect("offset"),
+                size: size.parse().expect("size"),
+            });
+            values.push(value.to_string());
+        }
+        Fixture { layout, values }
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex"))
+            .collect()
+    }
+
+    /// What moved between the fixture and `bytes`: the field list's own
+    /// changes, then each field whose value is no longer at its offset,
+    /// with where that value is now if it can be found.
+    fn explain(fixture: &Fixture, layout: &AccountLayout, bytes: &[u8]) -> Vec<String> {
+        let mut out = diff(&fixture.layout, layout);
+        for (f, value) in fixture.layout.fields.iter().zip(&fixture.values) {
+            let want = from_hex(value);
+            if bytes.get(f.offset..f.offset + f.size) == Some(&want[..]) {
+                continue;
+            }
+            let found = (want.len() > 1)
+                .then(|| bytes.windows(want.len()).position(|w| w == &want[..]))
+                .flatten();
+            let (account, name, offset) = (layout.account, f.name, f.offset);
+            out.push(match found {
+                Some(at) => format!("{account}.{name}: value moved {offset} -> {at}"),
+                None => format!("{account}.{name}: value at {offset} changed"),
+            });
+        }
+        out
+    }
+
+    fn check_golden(file: &str, fixture_text: &'static str, layout: AccountLayout, bytes: Vec<u8>) {
+        let fresh = render(&layout, &bytes);
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            let path = format!("{}/layouts/{file}", env!("CARGO_MANIFEST_DIR"));
+            std::fs::write(path, &fresh).expect("write layout fixture");
+            return;
+        }
+        if fresh != fixture_text {
+            let fixture = parse(layout.account, fixture_text);
+            panic!(
+                "{} layout changed; if deliberate, rerun with {}=1 and commit layouts/{}:\n{}",
+                layout.account,
+                UPDATE_VAR,
+                file,
+                explain(&fixture, &layout, &bytes).join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn slots_state_layout_covers_the_account() {
+        let layout = slots_state(&zeroed_state());
+        assert_eq!(layout.len(), SlotsState::LEN);
+        assert_eq!(layout.field("authority").map(|f| f.offset), Some(8));
+        assert_eq!(layout.field("treasury").map(|f| f.offset), Some(40));
+        assert_eq!(layout.field("initialized").map(|f| (f.offset, f.size)), Some((72, 1)));
+        assert_eq!(layout.field("pause_flags").map(|f| (f.offset, f.size)), Some((73, 1)));
+    }
+
+    #[test]
+    fn player_state_layout_covers_the_account() {
+        let layout = player_state(&zeroed_player());
+        assert_eq!(layout.len(), PlayerState::LEN);
+        assert_eq!(layout.field("owner").map(|f| f.offset), Some(8));
+    }
+
+    #[test]
+    fn diff_of_identical_layouts_is_empty() {
+        let layout = slots_state(&zeroed_state());
+        assert!(diff(&layout, &layout.clone()).is_empty());
+    }
+
+    #[test]
+    fn offsets_match_the_serialized_account() {
+        let state = populated_state();
+        let bytes = account_bytes(&state);
+        let layout = slots_state(&state);
+        assert_eq!(bytes.len(), layout.len());
+        for (f, value) in layout.fields.iter().zip(slots_state_fields!(values!(state))) {
+            assert_eq!(&bytes[f.offset..f.offset + f.size], &value[..], "SlotsState.{}", f.name);
+        }
+
+        let player = populated_player();
+        let bytes = account_bytes(&player);
+        let layout = player_state(&player);
+        assert_eq!(bytes.len(), layout.len());
+        for (f, value) in layout.fields.iter().zip(player_state_fields!(values!(player))) {
+            assert_eq!(&bytes[f.offset..f.offset + f.size], &value[..], "PlayerState.{}", f.name);
+        }
+    }
+
+    #[test]
+    fn slots_state_matches_its_golden_fixture() {
+        let state = populated_state();
+        check_golden(
+            "slots_state.txt",
+            SLOTS_STATE_FIXTURE,
+            slots_state(&state),
+            account_bytes(&state),
+        );
+    }
+
+    #[test]
+    fn player_state_matches_its_golden_fixture() {
+        let player = populated_player();
+        check_golden(
+            "player_state.txt",
+            PLAYER_STATE_FIXTURE,
+            player_state(&player),
+            account_bytes(&player),
+        );
+    }
+
+    /// Every event in events.rs order, handed to `$m`.
+    macro_rules! all_events {
+        ($m:ident) => {
+            $m![
+                SpinCommitted,
+                PoolBelowRequired,
+                PoolSynced,
+                PoolRestored,
+                OracleGateChanged,
+                OracleHealthConfigChanged,
+                OracleQueuesChanged,
+                OracleCostChanged,
+                SpinSettled,
+                SpinLinesSettled,
+                SpinProof,
+                RandomnessRotated,
+                SpinVoided,
+                SpinCancelled,
+                TreasuryOutflow,
+                WinningsRebet,
+                ClaimableWithdrawn,
+                ReceiptAppended,
+                StatsFlushed,
+                RTPUpdate,
+                TokenTreasuryInitialized,
+                PoolDeposit,
+                PoolWithdrawal,
+                JackpotBoosted,
+                FeeBalanceFunded,
+                WinnerRebateStatus,
+                SettleHookProposed,
+                SettleHookChanged,
+                SettleHookInvoked,
+                AllowlistUpdated,
+                BetaModeChanged,
+                BootstrapModeChanged,
+                JackpotBatchModeChanged,
+                JackpotReseedPolicyChanged,
+                ConfigUpdated,
+                JackpotBoundsChanged,
+                JackpotWeightsProposed,
+                JackpotWeightsChanged,
+                JackpotMaxCapChanged,
+                JackpotBatchShare,
+                BatchSettled,
+                EmergencyAction,
+                AuthorityHeartbeat,
+                AuthorityTransferProposed,
+                AuthorityTransferCancelled,
+                AuthorityTransferred,
+                RecoveryConfigChanged,
+                RecoveryClaimed,
+                CommunityShareChanged,
+                CommunitySwept,
+                CommunityWithdrawn,
+                CommunityDestinationsChanged,
+                CommunityAuthorityChanged,
+                OperatorFeeChanged,
+                FeesWithdrawn,
+                JackpotHit,
+                JackpotContribution,
+                JackpotQualifyingBetChanged,
+                FaucetFunded,
+                FaucetConfigChanged,
+                FaucetDrip,
+                OutcomeForced,
+                WagerRaceCreated,
+                WagerRacePrize,
+                WagerRaceFinalized,
+                EpochClosed,
+                SymbolMetaProposed,
+                SymbolMetaChanged,
+                PlayerOnboarded,
+                OnboardingSponsorChanged,
+                PlayerTierChanged,
+                BetLimitsChanged,
+                TierLimitsChanged,
+                UpgradeFrozen,
+                ExtractionCharged,
+                ExtractionBudgetChanged,
+                ExtractionOverrideProposed,
+                PaytableChanged,
+                MachineInitialized,
+                ReelConfigChanged,
+                OperatorPlayBlocked,
+                StaffWalletsChanged,
+                PlayerStatsUpdated,
+                PlayerMigrated,
+                StateMigrated,
+                UpgradeThawed,
+                ResumeScheduled,
+                WithdrawalRequested,
+                WithdrawalExecuted,
+                WithdrawalCancelled,
+                WithdrawalDelayChanged,
+                LoyaltyPointsAccrued,
+                FreeSpinRedeemed,
+                LoyaltyConfigChanged,
+                ReferrerSet,
+                ReferralAccrued,
+                ReferralEarningsClaimed,
+                ReferralBpsChanged,
+                SelfExclusionSet,
+                LossLimitSet,
+                LossWindowChanged,
+                SpinGapChanged,
+                ScatterConfigChanged,
+                FreeSpinsAwarded,
+                WildSymbolChanged,
+                LeaderboardCreated,
+                LeaderboardUpdated,
+                LeaderboardEpochChanged,
+                JackpotMustHitArmed,
+                VipConfigChanged,
+                VipRebateAccrued,
+                VipRebateClaimed,
+                SpinsSettled,
+                SettleHookSkipped,
+                PoolSharesWrittenOff,
+                PoolPositionMigrated,
+            ]
+        };
+    }
+
+    macro_rules! discriminators {
+        ($($event:ident),* $(,)?) => {
+            vec![$( (stringify!($event), <$event as Discriminator>::DISCRIMINATOR) ),*]
+        };
+    }
+
+    fn event_discriminators() -> Vec<(&'static str, [u8; 8])> {
+        all_events!(discriminators)
+    }
+
+    #[test]
+    fn every_event_is_listed() {
+        let source = include_str!("events.rs");
+        let declared: Vec<&str> = source
+            .split("#[event]")
+            .skip(1)
+            .filter_map(|rest| rest.split("pub struct ").nth(1))
+            .filter_map(|rest| rest.split([' ', '{']).next())
+            .collect();
+        let listed: Vec<&str> = event_discriminators().iter().map(|(name, _)| *name).collect();
+        assert_eq!(listed, declared);
+    }
+
+    #[test]
+    fn event_discriminators_match_their_fixture() {
+        let fresh: String = event_discriminators()
+            .iter()
+            .map(|(name, disc)| format!("{name} {}\n", to_hex(disc)))
+            .collect();
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/layouts/events.txt");
+            std::fs::write(path, &fresh).expect("write event fixture");
+            return;
+        }
+        let changed: Vec<&str> = fresh
+            .lines()
+            .filter(|line| !EVENTS_FIXTURE.lines().any(|l| l == *line))
+            .collect();
+        assert!(
+            fresh == EVENTS_FIXTURE,
+            "event discriminators changed; if deliberate, rerun with {}=1 and commit \
+             layouts/events.txt: {changed:?}",
+            UPDATE_VAR
+        );
+    }
+
+    /// Builds populated values of the other accounts and the events from
+    /// their IDL type definitions: every leaf gets bytes derived from its
+    /// field path, a bool is true, an Option is Some, a Vec holds two
+    /// elements and an enum takes a variant picked by name. The Rust type
+    /// must decode the bytes and write them back unchanged, so the IDL and
+    /// the struct agree before the bytes are compared with a fixture.
+    struct IdlEncoder {
+        types: Vec<Value>,
+    }
+
+    impl IdlEncoder {
+        fn new() -> Self {
+            let idl: Value = serde_json::from_str(IDL).expect("IDL is JSON");
+            let types = idl["types"].as_array().expect("IDL types").clone();
+            IdlEncoder { types }
+        }
+
+        fn definition(&self, name: &str) -> &Value {
+            self.types
+                .iter()
+                .find(|t| t["name"] == name)
+                .unwrap_or_else(|| panic!("type {name} missing from the IDL"))
+        }
+
+        /// Top-level fields of `name` as (field, encoded bytes).
+        fn fields(&self, name: &str) -> Vec<(String, Vec<u8>)> {
+            let fields = self.definition(name)["type"]["fields"]
+                .as_array()
+                .unwrap_or_else(|| panic!("{name} is not a struct with named fields"));
+            fields
+                .iter()
+                .map(|f| {
+                    let field = f["name"].as_str().expect("field name").to_string();
+                    let mut bytes = Vec::new();
+                    self.encode(&f["type"], &field, &mut bytes);
+                    (field, bytes)
+                })
+                .collect()
+        }
+
+        fn encode(&self, ty: &Value, path: &str, out: &mut Vec<u8>) {
+            let seed = name_seed(path);
+            let named = |len: usize| (0..len).map(|i| seed[i % 8]).collect::<Vec<u8>>();
+            if let Some(primitive) = ty.as_str() {
+                match primitive {
+                    "bool" => out.push(1),
+                    "u8" | "i8" => out.extend(named(1)),
+                    "u16" | "i16" => out.extend(named(2)),
+                    "u32" | "i32" => out.extend(named(4)),
+                    "u64" | "i64" => out.extend(named(8)),
+                    "u128" | "i128" => out.extend(named(16)),
+                    "pubkey" => out.extend(named(32)),
+                    other => panic!("{path}: unhandled IDL type {other}"),
+                }
+                return;
+            }
+            if let Some([inner, len]) = ty["array"].as_array().map(Vec::as_slice) {
+                for _ in 0..len.as_u64().expect("array length") {
+                    self.encode(inner, path, out);
+                }
+            } else if let Some(inner) = ty.get("option") {
+                out.push(1);
+                self.encode(inner, path, out);
+            } else if let Some(inner) = ty.get("vec") {
+                out.extend(2u32.to_le_bytes());
+                self.encode(inner, path, out);
+                self.encode(inner, path, out);
+            } else if let Some(name) = ty["defined"]["name"].as_str() {
+                let def = &self.definition(name)["type"];
+                match def["kind"].as_str() {
+                    Some("struct") => {
+                        for f in def["fields"].as_array().expect("named fields") {
+                            let field = f["name"].as_str().expect("field name");
+                            self.encode(&f["type"], &format!("{path}.{field}"), out);
+                        }
+                    }
+                    Some("enum") => {
+                        let variants = def["variants"].as_array().expect("variants");
+                        let pick = seed[0] as usize % variants.len();
+                        assert!(variants[pick].get("fields").is_none(), "{path}: data enum");
+                        out.push(pick as u8);
+                    }
+                    other => panic!("{path}: unhandled IDL kind {other:?}"),
+                }
+            } else {
+                panic!("{path}: unhandled IDL type {ty}");
+            }
+        }
+    }
+
+    fn borsh_round_trip<T: AnchorSerialize + AnchorDeserialize>(bytes: &[u8]) -> Vec<u8> {
+        T::try_from_slice(bytes).expect("decodes").try_to_vec().expect("serializes")
+    }
+
+    fn pod_round_trip<T: Pod>(bytes: &[u8]) -> Vec<u8> {
+        assert_eq!(bytes.len(), core::mem::size_of::<T>(), "zero-copy size");
+        bytemuck::bytes_of(&bytemuck::pod_read_unaligned::<T>(bytes)).to_vec()
+    }
+
+    type RoundTrip = fn(&[u8]) -> Vec<u8>;
+    /// Name, fixture file, fixture text, discriminator and round trip.
+    type OtherAccount = (&'static str, &'static str, &'static str, [u8; 8], RoundTrip);
+
+    macro_rules! borsh_accounts {
+        ($($account:ident => $file:literal),* $(,)?) => {
+            vec![$(
+                (
+                    stringify!($account),
+                    $file,
+                    include_str!(concat!("../layouts/", $file)),
+                    <$account as Discriminator>::DISCRIMINATOR,
+                    borsh_round_trip::<$account> as RoundTrip,
+                )
+            ),*]
+        };
+    }
+
+    macro_rules! pod_accounts {
+        ($($account:ident => $file:literal),* $(,)?) => {
+            vec![$(
+                (
+                    stringify!($account),
+                    $file,
+                    include_str!(concat!("../layouts/", $file)),
+                    <$account as Discriminator>::DISCRIMINATOR,
+                    pod_round_trip::<$account> as RoundTrip,
+                )
+            ),*]
+        };
+    }
+
+    /// Accounts besides SlotsState and PlayerState, with their fixtures.
+    fn other_accounts() -> Vec<OtherAccount> {
+        let mut accounts = borsh_accounts![
+            AccountIndex => "account_index.txt",
+            Allowlist => "allowlist.txt",
+            CommunityVault => "community_vault.txt",
+            EpochReportPage => "epoch_report_page.txt",
+            Leaderboard => "leaderboard.txt",
+            PoolPosition => "pool_position.txt",
+            ReferralEarnings => "referral_earnings.txt",
+            SymbolMeta => "symbol_meta.txt",
+            WagerRace => "wager_race.txt",
+        ];
+        accounts.extend(pod_accounts![
+            Metrics => "metrics.txt",
+            ReceiptTree => "receipt_tree.txt",
+            RtpHistory => "rtp_history.txt",
+        ]);
+        accounts
+    }
+
+    #[test]
+    fn every_account_has_a_fixture() {
+        let idl: Value = serde_json::from_str(IDL).expect("IDL is JSON");
+        let mut declared: Vec<&str> = idl["accounts"]
+            .as_array()
+            .expect("IDL accounts")
+            .iter()
+            .filter_map(|a| a["name"].as_str())
+            .collect();
+        declared.sort_unstable();
+        let mut listed: Vec<&str> = other_accounts().iter().map(|(name, ..)| *name).collect();
+        listed.extend(["PlayerState", "SlotsState"]);
+        listed.sort_unstable();
+        assert_eq!(listed, declared);
+    }
+
+    #[test]
+    fn other_accounts_match_their_golden_fixtures() {
+        let encoder = IdlEncoder::new();
+        for (account, file, text, discriminator, round_trip) in other_accounts() {
+            let mut layout = AccountLayout {
+                account,
+                discriminator,
+                fields: Vec::new(),
+            };
+            let mut bytes = discriminator.to_vec();
+            for (name, value) in encoder.fields(account) {
+                // FieldOffset names are 'static; the test leaks a few.
+                let name: &'static str = Box::leak(name.into_boxed_str());
+                layout.fields.push(FieldOffset {
+                    name,
+                    offset: bytes.len(),
+                    size: value.len(),
+                });
+                bytes.extend(value);
+            }
+            assert_eq!(round_trip(&bytes[8..]), bytes[8..], "{account} round trip");
+            check_golden(file, text, layout, bytes);
+        }
+    }
+
+    macro_rules! round_trips {
+        ($($event:ident),* $(,)?) => {
+            vec![$( (stringify!($event), borsh_round_trip::<$event> as RoundTrip) ),*]
+        };
+    }
+
+    #[test]
+    fn event_payloads_match_their_fixture() {
+        let encoder = IdlEncoder::new();
+        let mut fresh = format!(
+            "# Populated event payloads, after the discriminator; regenerate with {UPDATE_VAR}=1.\n"
+        );
+        for (event, round_trip) in all_events!(round_trips) {
+            let payload: Vec<u8> = encoder.fields(event).into_iter().flat_map(|(_, b)| b).collect();
+            assert_eq!(round_trip(&payload), payload, "{event} round trip");
+            fresh += &format!("{event} {}\n", to_hex(&payload));
+        }
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/layouts/event_payloads.txt");
+            std::fs::write(path, &fresh).expect("write event payload fixture");
+            return;
+        }
+        let changed: Vec<&str> = fresh
+            .lines()
+            .filter(|line| !EVENT_PAYLOADS_FIXTURE.lines().any(|l| l == *line))
+            .filter_map(|line| line.split(' ').next())
+            .collect();
+        assert!(
+            fresh == EVENT_PAYLOADS_FIXTURE,
+            "event payloads changed; if deliberate, rerun with {UPDATE_VAR}=1 and commit \
+             layouts/event_payloads.txt: {changed:?}"
+        );
+    }
+}
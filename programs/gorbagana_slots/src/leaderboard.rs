@@ -0,0 +1,195 @@
+//! Per-epoch leaderboards of the biggest winners.
+//!
+//! Slots are cut into epochs of `SlotsState.leaderboard_epoch_slots`
+//! (about a week by default); epoch `n` covers slots `[n * len, (n + 1) *
+//! len)`. A `Leaderboard` PDA (seeds `[LEADERBOARD_SEED, slots_state,
+//! epoch as u64 LE]`) ranks the top players of one epoch by the payouts
+//! of the spins they settled in it.
+//!
+//! Anyone may create the board of the current or a later epoch with
+//! create_leaderboard and pay its rent, so settles never do. A player's
+//! epoch total lives in their PlayerState (`leaderboard_epoch`,
+//! `leaderboard_won`) and grows on every settle_spin and settle_and_respin,
+//! board or not; one given the current board also places the total on it.
+//! The deferred and token settles do not count. The board a player's
+//! own settle was given is recorded in `PlayerState.leaderboard` until
+//! the epoch ends, and a crank settling a win for them must pass it. A missing
+//! board, or one of another epoch, is skipped rather than failing the
+//! spin, so play carries on across an epoch boundary until someone
+//! creates the next board.
+//!
+//! The board records the epoch length it was created under. Changing
+//! the length renumbers epochs, so boards of the old length stop
+//! receiving updates.
+//!
+//! Ties rank the player who reached the total first higher, as on wager
+//! race boards.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, Leaderboard, PlayerState, SlotsState};
+
+/// Ranked places on a leaderboard.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Default epoch length (~1 week of 400ms slots).
+pub const DEFAULT_LEADERBOARD_EPOCH_SLOTS: u64 = 1_512_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    /// Default key = empty place.
+    pub player: Pubkey,
+    pub total_won: u64,
+    /// Slot `total_won` was reached; breaks ties, earlier first.
+    pub reached_slot: u64,
+}
+
+impl LeaderboardEntry {
+    pub fn is_empty(&self) -> bool {
+        self.player == Pubkey::default()
+    }
+
+    /// Whether `self` ranks above `other`.
+    fn outranks(&self, other: &LeaderboardEntry) -> bool {
+        if other.is_empty() {
+            return !self.is_empty();
+        }
+        self.total_won > other.total_won
+            || (self.total_won == other.total_won && self.reached_slot < other.reached_slot)
+    }
+}
+
+impl Leaderboard {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + LEADERBOARD_SIZE * (32 + 8 + 8) + 1;
+}
+
+/// Epoch `slot` falls in under the machine's current epoch length.
+pub fn epoch_at(state: &SlotsState, slot: u64) -> u64 {
+    slot / state.leaderboard_epoch_slots.max(1)
+}
+
+/// Whether `board` ranks the epoch `slot` falls in, under the current
+/// epoch length.
+pub fn is_current(state: &SlotsState, board: &Leaderboard, slot: u64) -> bool {
+    board.epoch == epoch_at(state, slot) && board.epoch_slots == state.leaderboard_epoch_slots
+}
+
+/// Add a settled payout to the player's epoch total and, when `board` is
+/// the current epoch's, place the total on it. Returns the player's place
+/// (0 = first) when the board was updated and they are on it.
+pub fn record(
+    state: &SlotsState,
+    board: Option<&mut Leaderboard>,
+    player_state: &mut PlayerState,
+    payout: u64,
+    slot: u64,
+) -> Result<Option<usize>> {
+    let epoch = epoch_at(state, slot);
+    if player_state.leaderboard_epoch != epoch {
+        player_state.leaderboard_epoch = epoch;
+        player_state.leaderboard_won = 0;
+        player_state.leaderboard = Pubkey::default();
+    }
+    if payout == 0 {
+        return Ok(None);
+    }
+    player_state.leaderboard_won = player_state
+        .leaderboard_won
+        .checked_add(payout)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let Some(board) = board else {
+        return Ok(None);
+    };
+    if !is_current(state, board, slot) {
+        return Ok(None);
+    }
+    let entry = LeaderboardEntry {
+        player: player_state.owner,
+        total_won: player_state.leaderboard_won,
+        reached_slot: slot,
+    };
+    Ok(place(&mut board.entries, entry))
+}
+
+/// Put `entry` on the board in rank order, replacing the player's old
+/// entry or, if the player is new and outranks it, the last one. Returns
+/// the entry's place, or None when it does not qualify.
+pub fn place(
+    board: &mut [LeaderboardEntry; LEADERBOARD_SIZE],
+    entry: LeaderboardEntry,
+) -> Option<usize> {
+    let last = LEADERBOARD_SIZE - 1;
+    let mut i = match board.iter().position(|e| e.player == entry.player) {
+        Some(i) => i,
+        None if entry.outranks(&board[last]) => last,
+        None => return None,
+    };
+    board[i] = entry;
+    while i > 0 && board[i].outranks(&board[i - 1]) {
+        board.swap(i, i - 1);
+        i -= 1;
+    }
+    Some(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(total_won: u64, reached_slot: u64) -> LeaderboardEntry {
+        LeaderboardEntry {
+            player: Pubkey::new_unique(),
+            total_won,
+            reached_slot,
+        }
+    }
+
+    #[test]
+    fn board_keeps_the_top_winners_in_order() {
+        let mut board = [LeaderboardEntry::default(); LEADERBOARD_SIZE];
+        for won in 1..=LEADERBOARD_SIZE as u64 {
+            place(&mut board, entry(won * 100, won));
+        }
+        assert_eq!(board[0].total_won, 1_000);
+        assert_eq!(board[LEADERBOARD_SIZE - 1].total_won, 100);
+
+        // Full board: a tie with the last place reached later stays off.
+        assert_eq!(place(&mut board, entry(100, 99)), None);
+        let late = entry(550, 20);
+        assert_eq!(place(&mut board, late), Some(5));
+        assert_eq!(board[LEADERBOARD_SIZE - 1].total_won, 200);
+
+        // A player already on the board moves rather than duplicating.
+        let climbed = LeaderboardEntry {
+            total_won: 2_000,
+            reached_slot: 30,
+            ..late
+        };
+        assert_eq!(place(&mut board, climbed), Some(0));
+        assert_eq!(board.iter().filter(|e| e.player == late.player).count(), 1);
+        assert!(board.windows(2).all(|w| w[0].total_won >= w[1].total_won));
+    }
+
+    #[test]
+    fn a_crank_settling_a_win_must_pass_the_recorded_board() {
+        use crate::test_util::Game;
+        use crate::DEFAULT_MIN_BET;
+
+        let mut game = Game::new();
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        game.player.leaderboard_epoch = epoch_at(&game.state, game.slot);
+        game.player.leaderboard = Pubkey::new_unique();
+        let win = game.reveal_where(|s| s.total_payout > 0);
+        let loss = game.reveal_where(|s| s.total_payout == 0);
+
+        let mut lost = game.clone();
+        lost.reveal(loss);
+        lost.crank().expect("a loss places nothing");
+
+        game.reveal(win);
+        assert_eq!(game.crank().unwrap_err(), ErrorCode::OptionalAccountMissing.into());
+        game.settle().expect("the player settles");
+        assert_eq!(game.player.leaderboard, Pubkey::default());
+    }
+}
@@ -1,7 +1,132 @@
+use anchor_lang::solana_program::{instruction::AccountMeta, instruction::Instruction, program::invoke};
+use anchor_lang::solana_program::sysvar::instructions as ix_sysvar;
 use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token::TokenAccount;
 use switchboard_on_demand::on_demand::accounts::RandomnessAccountData;
-use core::convert::TryInto;
-use std::str::FromStr;
+
+pub mod account_index;
+pub(crate) mod accounting;
+#[cfg(test)]
+mod api_snapshot;
+pub(crate) mod batch_settle;
+pub(crate) mod bootstrap;
+#[cfg(not(target_os = "solana"))]
+pub mod client;
+pub mod community;
+pub(crate) mod config;
+pub(crate) mod contexts;
+pub(crate) mod epoch_reports;
+pub(crate) mod errors;
+pub(crate) mod events;
+pub(crate) mod extraction;
+pub mod fairness;
+pub(crate) mod faucet;
+pub(crate) mod feature_stats;
+pub mod game_math;
+pub(crate) mod instructions;
+pub mod jackpot_bounds;
+#[cfg(not(target_os = "solana"))]
+pub mod layout;
+pub mod leaderboard;
+pub(crate) mod loyalty;
+pub(crate) mod machines;
+pub mod metrics;
+pub(crate) mod migration;
+pub mod multi_spin;
+pub mod must_hit;
+pub(crate) mod obligations;
+pub(crate) mod onboarding;
+pub mod oracle_cost;
+pub(crate) mod oracle_health;
+pub(crate) mod oracle_queue;
+pub(crate) mod outcome_budget;
+pub(crate) mod operator_fee;
+pub(crate) mod operators;
+pub(crate) mod optional;
+pub(crate) mod outflows;
+pub(crate) mod pause;
+pub(crate) mod paytable;
+pub(crate) mod player_stats;
+pub mod pool_level;
+pub mod pool_shares;
+pub mod prelude;
+pub mod qa;
+pub mod randomness;
+pub(crate) mod rate_limit;
+pub mod receipts;
+pub(crate) mod referrals;
+pub mod reservations;
+pub(crate) mod responsible_gaming;
+pub(crate) mod reseed;
+pub(crate) mod risk;
+pub mod scatter;
+pub(crate) mod seed_replay;
+#[cfg(all(test, not(feature = "idl-build")))]
+mod idl_snapshot;
+#[cfg(test)]
+mod sequence_fuzz;
+pub(crate) mod settle_hooks;
+pub(crate) mod signed_bet;
+pub(crate) mod simulate;
+pub(crate) mod state;
+pub mod symbol_meta;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub(crate) mod tiers;
+pub(crate) mod time;
+pub(crate) mod token_mode;
+#[cfg(all(feature = "vectors", not(target_os = "solana")))]
+pub mod vectors;
+pub mod vip;
+pub(crate) mod wager_race;
+pub(crate) mod withdrawal_timelock;
+
+use game_math::{
+    derive_outcome, jackpot_pool_mut, LinesOutcome, PayoutLimits, SpinOutcome, SpinsOutcome,
+    JACKPOT_TIER_GRAND, MAX_PAYLINES, MAX_SPINS_PER_COMMIT,
+};
+use account_index::INDEX_KIND_PLAYER_STATE;
+use accounting::AccountingReport;
+use batch_settle::{Readiness, SplitAmounts, SplitPlan};
+use bootstrap::BootstrapStatus;
+use community::MAX_COMMUNITY_DESTINATIONS;
+use config::UpdateConfigArgs;
+use epoch_reports::{EpochReport, EPOCH_REPORTS_PER_PAGE};
+use extraction::{DEFAULT_EXTRACTION_BUDGET_BPS, EXTRACTION_PERIOD_SLOTS};
+use feature_stats::{Feature, FeaturePnl, FeatureTotals, FEATURE_KINDS};
+use jackpot_bounds::JackpotWeights;
+use leaderboard::{LeaderboardEntry, DEFAULT_LEADERBOARD_EPOCH_SLOTS, LEADERBOARD_SIZE};
+use machines::machine_seed;
+use migration::SLOTS_STATE_VERSION;
+use obligations::{EarmarkKind, EarmarkedObligations};
+use operators::MAX_STAFF_WALLETS;
+use oracle_cost::OracleCostMode;
+use oracle_health::{OracleHealth, OracleHealthStatus};
+use oracle_queue::{OracleQueueEntry, MAX_ORACLE_QUEUES};
+use outcome_budget::{DEFAULT_MAX_OUTCOMES_PER_REVEAL, SPIN_OUTCOMES};
+use outflows::{OutflowCategory, OutflowSource, OUTFLOW_CATEGORIES};
+use pause::{PAUSE_ALL, PAUSE_DEPOSITS, PAUSE_NEW_SPINS, PAUSE_SETTLEMENT, PAUSE_WITHDRAWALS};
+use paytable::DEFAULT_MAX_RTP_BPS;
+use qa::ForcedOutcome;
+use randomness::RandomnessCheck;
+use reseed::{JackpotRebuildReport, ReseedPolicy};
+use responsible_gaming::DEFAULT_LOSS_WINDOW_SLOTS;
+use risk::JackpotEtaReport;
+use scatter::ScatterConfig;
+use seed_replay::{UsedSeed, RECENT_SEEDS};
+use settle_hooks::HookCall;
+use signed_bet::SignedBet;
+use simulate::PayoutSimulation;
+use symbol_meta::SymbolEntry;
+use tiers::{TierLimits, MAX_PLAYER_TIERS};
+use vip::VipConfig;
+use wager_race::{WagerRaceEntry, WAGER_RACE_BOARD_SIZE};
+use withdrawal_timelock::{DEFAULT_WITHDRAWAL_DELAY_SLOTS, MIN_WITHDRAWAL_DELAY_SLOTS};
+
+pub use contexts::*;
+pub use errors::ErrorCode;
+pub use events::*;
+pub use state::*;
 
 declare_id!("Fg6PaFpoGXkYsidMpWxTWqkq5tqMZf7Q6Y3hftsz5A5c"); // TODO: replace with your real program id
 
@@ -10,18 +135,108 @@ declare_id!("Fg6PaFpoGXkYsidMpWxTWqkq5tqMZf7Q6Y3hftsz5A5c"); // TODO: replace wi
 pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID_STR: &str =
     "RANDMo5gFnqnXJW5Z52KNmd24sAo95KAd5VbiCtq5Rh";
 
+/// SWITCHBOARD_ON_DEMAND_PROGRAM_ID_STR, parsed at compile time.
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("RANDMo5gFnqnXJW5Z52KNmd24sAo95KAd5VbiCtq5Rh");
+
+// IMMUTABLE_PROGRAM_DEPLOYER, generated by build.rs from
+// GORBAGANA_IMMUTABLE_DEPLOYER.
+include!(concat!(env!("OUT_DIR"), "/deployer.rs"));
+
 // =========================
 // CONFIG / CONSTANTS
 // =========================
 
+// PDA seeds
+pub const SLOTS_SEED: &[u8] = b"slots";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const PLAYER_SEED: &[u8] = b"player";
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+pub const RTP_HISTORY_SEED: &[u8] = b"rtp_history";
+pub const METRICS_SEED: &[u8] = b"metrics";
+pub const RECEIPT_TREE_SEED: &[u8] = b"receipt_tree";
+pub const ACCOUNT_INDEX_SEED: &[u8] = b"index";
+pub const COMMUNITY_VAULT_SEED: &[u8] = b"community_vault";
+pub const SYMBOL_META_SEED: &[u8] = b"symbol_meta";
+pub const EPOCH_REPORT_SEED: &[u8] = b"epoch_report";
+pub const WAGER_RACE_SEED: &[u8] = b"wager_race";
+pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
+pub const POOL_POSITION_SEED: &[u8] = b"pool_position";
+pub const REFERRAL_SEED: &[u8] = b"referral";
+pub const LEADERBOARD_SEED: &[u8] = b"leaderboard";
+
+/// Max partner settle hooks registered at once.
+pub const MAX_SETTLE_HOOKS: usize = 2;
+/// Delay between proposing and activating a settle hook (~1 day).
+pub const SETTLE_HOOK_TIMELOCK_SLOTS: u64 = 216_000;
+/// Delay before a proposed symbol meta table replaces the active one.
+pub const SYMBOL_META_TIMELOCK_SLOTS: u64 = 216_000;
+/// Delay before jackpot weights outside the configured bounds go live.
+pub const JACKPOT_WEIGHTS_TIMELOCK_SLOTS: u64 = 216_000;
+/// Anchor discriminator of the hook's `on_spin_settled` instruction,
+/// sha256("global:on_spin_settled")[..8].
+pub const ON_SPIN_SETTLED_DISCRIMINATOR: [u8; 8] = [194, 195, 93, 57, 66, 250, 230, 182];
+
+/// Logic version of this build. Bump whenever a change could settle an
+/// already-committed spin differently; see pre_upgrade_freeze.
+pub const PROGRAM_VERSION: u16 = 1;
+/// SpinVoided reason_code used when post_upgrade_thaw refunds a spin
+/// committed under an incompatible version.
+pub const VOID_REASON_UPGRADE: u8 = 250;
+
+/// Default authority inactivity after which the recovery authority may
+/// take over (~30 days).
+pub const DEFAULT_RECOVERY_INACTIVITY_SLOTS: u64 = 6_480_000;
+
+/// SpinSettled layout version. 2 appended vrf_hash, vrf_prefix and version
+/// to the v1 fields, 3 appended player_tier, 4 appended fee_overflow, 5
+/// appended spin_nonce and context_index, 6 appended match_count, 7
+/// appended global_spin_counter, 8 appended free_spin; older decoders
+/// still read the prefix.
+pub const SPIN_SETTLED_VERSION: u8 = 8;
+
+/// Spin events are addressed by (signature, position in the log) and,
+/// semantically, by (player, spin_nonce, context_index). `spin_nonce` is
+/// the player's PlayerState.spin_nonce at the spin's commit, so commit,
+/// settle, void and cancel events of one spin share it.
+/// `context_index` is the event's ordinal within a composite player
+/// action: settle_and_respin settles at 0 and commits the respin at this
+/// index. Every other instruction emits 0.
+pub const RESPIN_CONTEXT_INDEX: u8 = 1;
+
+/// Default time a commit may wait for its reveal before the oracle-health
+/// gate treats it as overdue (~1 minute).
+pub const DEFAULT_ORACLE_EXPECTED_REVEAL_SLOTS: u64 = 150;
+
+/// Default smallest bet (0.001 SOL). The default max_bet is derived from
+/// max_payout_per_spin in initialize.
+pub const DEFAULT_MIN_BET: u64 = 1_000_000;
+
+/// Default wait after a commit before the player may cancel a spin whose
+/// randomness never revealed (~1 hour).
+pub const DEFAULT_SPIN_EXPIRY_SLOTS: u64 = 9_000;
+
+/// Ceiling for the crank fee paid to a third party that settles someone
+/// else's spin (0.005 SOL).
+pub const MAX_CRANK_FEE: u64 = 5_000_000;
+
+/// Hourly buckets kept in RtpHistory (7 days).
+pub const RTP_HISTORY_HOURS: usize = 168;
+
 pub const SYMBOL_COUNT: usize = 12;
 pub const TOTAL_WEIGHT: u64 = 78;
 
-// Weights: higher = more common symbol.
+/// `SlotsState.wild_symbol` when no symbol is wild.
+pub const NO_WILD: u8 = u8::MAX;
+
+// Default reel weights (see REEL_WEIGHTS): higher = more common symbol.
 pub const SYMBOL_WEIGHTS: [u64; SYMBOL_COUNT] = [
     1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
 ];
 
+/// Max players per allowlist page (kept sorted for binary search).
+pub const ALLOWLIST_PAGE_CAPACITY: usize = 128;
+
 // 3-of-a-kind multipliers (× bet) for symbols 0..11.
 pub const SYMBOL_PAYOUTS_3OAK: [u64; SYMBOL_COUNT] = [
     553, // symbol 0 (rarest)
@@ -38,160 +253,12 @@ pub const SYMBOL_PAYOUTS_3OAK: [u64; SYMBOL_COUNT] = [
     14,  // 11 (most common)
 ];
 
-/// A single progressive jackpot pool (e.g. Mini / Major / Grand)
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct JackpotPool {
-    /// Current jackpot amount (lamports) available to be won.
-    pub amount: u64,
-    /// Seed amount (lamports) after reset when hit.
-    pub seed: u64,
-    /// Contribution per paid bet (basis points of bet).
-    /// e.g. 100 = 1% of bet.
-    pub contrib_bps: u16,
-    /// Selection weight when a jackpot hit occurs.
-    pub hit_weight: u32,
-}
-
-/// All jackpots combined
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct JackpotsConfig {
-    pub mini: JackpotPool,
-    pub major: JackpotPool,
-    pub grand: JackpotPool,
-    /// Sum of mini + major + grand hit weights.
-    pub hit_weight_total: u32,
-}
-
-// =========================
-// ACCOUNT STATE
-// =========================
-
-#[account]
-pub struct SlotsState {
-    /// Admin authority.
-    pub authority: Pubkey,
-    /// Treasury PDA (holds game funds + pool).
-    pub treasury: Pubkey,
-
-    pub initialized: bool;
-    pub paused: bool;
-
-    // RTP stats
-    pub total_spins: u64,
-    pub total_wagered: u64,
-    pub total_payout: u64,
-    pub house_profit: u64,
-    /// Current RTP, basis points (e.g. 9550 = 95.50%).
-    pub current_rtp_bps: u32,
-
-    /// Optional target house edge, basis points.
-    pub house_edge_bps: u16,
-
-    // Pool accounting
-    pub total_pool: u64,
-    pub max_payout_per_spin: u64,
-    pub min_pool_threshold: u64,
-
-    // Progressive jackpots
-    pub jackpots: JackpotsConfig,
-}
-
-/// Per-player state. A player can have **one pending spin**
-/// tied to a Switchboard randomness account.
-#[account]
-pub struct PlayerState {
-    pub owner: Pubkey,
-    pub bump: u8,
-
-    /// Randomness account currently committed for a spin (if any).
-    pub randomness_account: Pubkey,
-    /// Bet amount for pending spin.
-    pub pending_bet_amount: u64,
-    /// True if there is a pending spin waiting for VRF reveal.
-    pub has_pending_spin: bool,
-}
-
-// =========================
-// EVENTS
-// =========================
-
-#[event]
-pub struct SpinCommitted {
-    pub user: Pubkey,
-    pub bet_amount: u64,
-    pub randomness_account: Pubkey,
-}
-
-#[event]
-pub struct SpinSettled {
-    pub user: Pubkey,
-    pub randomness_account: Pubkey,
-    pub symbols: [u8; 3],
-    pub base_payout: u64,
-    pub jackpot_payout: u64,
-    pub total_payout: u64,
-}
-
-#[event]
-pub struct RTPUpdate {
-    pub total_spins: u64,
-    pub total_wagered: u64,
-    pub total_payout: u64,
-    pub house_profit: u64,
-    pub current_rtp_bps: u32,
-}
-
-#[event]
-pub struct PoolDeposit {
-    pub user: Pubkey,
-    pub amount: u64,
-    pub new_pool: u64,
-}
-
-#[event]
-pub struct EmergencyAction {
-    pub action: String,
-    pub authority: Pubkey,
-}
-
-// =========================
-// ERRORS
-// =========================
+/// Paytable of the current 3-reel game: the 3-of-a-kind column is
+/// SYMBOL_PAYOUTS_3OAK and there are no 4- or 5-of-a-kind wins yet.
+pub const PAYTABLE: PayTable = PayTable::from_3oak(SYMBOL_PAYOUTS_3OAK);
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid bet amount")]
-    InvalidBetAmount,
-    #[msg("Bet amount too high")]
-    BetTooHigh,
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-    #[msg("Insufficient pool funds")]
-    InsufficientPool,
-    #[msg("Unauthorized")]
-    Unauthorized,
-    #[msg("Game is paused")]
-    GamePaused,
-    #[msg("Slots state not initialized")]
-    Uninitialized,
-    #[msg("Math overflow")]
-    MathOverflow,
-
-    #[msg("Player state not initialized")]
-    PlayerStateMissing,
-    #[msg("Player already has a pending spin")]
-    PendingSpinExists,
-    #[msg("No pending spin for this player")]
-    NoPendingSpin,
-    #[msg("Randomness account does not match player state")]
-    RandomnessAccountMismatch,
-    #[msg("Randomness account parse failed")]
-    RandomnessParseFailed,
-    #[msg("Randomness not yet revealed or invalid seed slot")]
-    RandomnessNotResolved,
-}
+/// Reel weights initialize installs: SYMBOL_WEIGHTS and TOTAL_WEIGHT.
+pub const REEL_WEIGHTS: ReelWeights = ReelWeights::new(SYMBOL_WEIGHTS);
 
 // =========================
 // PROGRAM
@@ -201,804 +268,2837 @@ pub enum ErrorCode {
 pub mod gorbagana_slots_vrf {
     use super::*;
 
-    /// Initialize global game state + jackpots + treasury PDA.
-    pub fn initialize(ctx: Context<Initialize>, authority: Pubkey) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-
-        slots_state.authority = authority;
-        slots_state.treasury = ctx.accounts.treasury.key();
-        slots_state.initialized = true;
-        slots_state.paused = false;
-
-        slots_state.total_spins = 0;
-        slots_state.total_wagered = 0;
-        slots_state.total_payout = 0;
-        slots_state.house_profit = 0;
-        slots_state.current_rtp_bps = 0;
-        slots_state.house_edge_bps = 500; // 5% target (informational)
-
-        slots_state.total_pool = 0;
-        slots_state.max_payout_per_spin = 1_000_000_000; // 1 SOL (example)
-        slots_state.min_pool_threshold = 100_000_000;    // 0.1 SOL
-
-        // Example jackpots config – tune for your RTP.
-        let mini_seed = 10_000_000;      // 0.01 SOL
-        let major_seed = 100_000_000;    // 0.1 SOL
-        let grand_seed = 1_000_000_000;  // 1 SOL
-
-        slots_state.jackpots = JackpotsConfig {
-            mini: JackpotPool {
-                amount: mini_seed,
-                seed: mini_seed,
-                contrib_bps: 50, // 0.5% of bet
-                hit_weight: 500,
-            },
-            major: JackpotPool {
-                amount: major_seed,
-                seed: major_seed,
-                contrib_bps: 100, // 1.0% of bet
-                hit_weight: 300,
-            },
-            grand: JackpotPool {
-                amount: grand_seed,
-                seed: grand_seed,
-                contrib_bps: 50, // 0.5% of bet
-                hit_weight: 200,
-            },
-            hit_weight_total: 500 + 300 + 200,
-        };
+    /// Initialize the legacy machine: game state + jackpots + treasury PDA.
+    /// Only the program's upgrade authority (or, for an immutable program,
+    /// IMMUTABLE_PROGRAM_DEPLOYER) may pay for and create the game.
+    /// `exclusive_mint` makes this a token-only deployment; it can only be
+    /// chosen here, never switched later. A test `network_guard` is only
+    /// accepted from IMMUTABLE_PROGRAM_DEPLOYER (see qa.rs).
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        authority: Pubkey,
+        exclusive_mint: Option<Pubkey>,
+        recovery_authority: Option<Pubkey>,
+        community_authority: Option<Pubkey>,
+        network_guard: Pubkey,
+    ) -> Result<()> {
+        instructions::admin::initialize(
+            ctx,
+            authority,
+            exclusive_mint,
+            recovery_authority,
+            community_authority,
+            network_guard,
+        )
+    }
 
-        Ok(())
+    /// Create another machine of this deployment: its own SlotsState,
+    /// treasury and fee vault, seeded with `machine_id`, configured as
+    /// initialize configures the first. Same deployer check. The all-zero
+    /// id is the legacy machine's. See machines.rs.
+    pub fn initialize_machine(
+        ctx: Context<InitializeMachine>,
+        machine_id: [u8; 32],
+        authority: Pubkey,
+        exclusive_mint: Option<Pubkey>,
+        recovery_authority: Option<Pubkey>,
+        community_authority: Option<Pubkey>,
+        network_guard: Pubkey,
+    ) -> Result<()> {
+        instructions::admin::initialize_machine(
+            ctx,
+            machine_id,
+            authority,
+            exclusive_mint,
+            recovery_authority,
+            community_authority,
+            network_guard,
+        )
     }
 
     /// Initialize per-player state (PDA).
     pub fn init_player(ctx: Context<InitPlayer>) -> Result<()> {
-        let slots_state = &ctx.accounts.slots_state;
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
+        instructions::player::init_player(ctx)
+    }
+
+    /// Create a player whose rent is paid by the operator's onboarding
+    /// sponsor. The user co-signs, each user can only be sponsored once
+    /// and the number of sponsored inits per day is capped.
+    pub fn init_player_sponsored(ctx: Context<InitPlayerSponsored>) -> Result<()> {
+        instructions::player::init_player_sponsored(ctx)
+    }
+
+    /// Set the onboarding sponsor key and its daily budget (admin only).
+    /// `Pubkey::default()` disables sponsored onboarding.
+    pub fn set_onboarding_sponsor(
+        ctx: Context<EmergencyPause>,
+        sponsor: Pubkey,
+        daily_budget: u32,
+    ) -> Result<()> {
+        instructions::admin::set_onboarding_sponsor(ctx, sponsor, daily_budget)
+    }
 
-        let player_state = &mut ctx.accounts.player_state;
-        player_state.owner = ctx.accounts.user.key();
-        player_state.bump = *ctx.bumps.get("player_state").unwrap();
-        player_state.randomness_account = Pubkey::default();
-        player_state.pending_bet_amount = 0;
-        player_state.has_pending_spin = false;
-        Ok(())
+    /// Create the player's account index, seeded from the accounts that
+    /// already exist. Optional; wallets can discover everything from it.
+    pub fn init_account_index(ctx: Context<InitAccountIndex>) -> Result<()> {
+        instructions::player::init_account_index(ctx)
     }
 
-    /// STEP 1: Commit to a spin.
+    /// STEP 1: Commit to a spin on `lines` paylines (1..=5) of the 3x3
+    /// grid, `bet_amount` per line. One line plays row 0 only.
     pub fn request_spin(
         ctx: Context<RequestSpin>,
         randomness_account: Pubkey,
         bet_amount: u64,
+        lines: u8,
     ) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-        let player_state = &mut ctx.accounts.player_state;
-        let user = &ctx.accounts.user;
-        let treasury = &ctx.accounts.treasury;
-        let randomness_ai = &ctx.accounts.randomness_account_data;
+        instructions::spin::request_spin(ctx, randomness_account, bet_amount, lines)
+    }
 
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
-        require!(!slots_state.paused, ErrorCode::GamePaused);
-        require!(bet_amount > 0, ErrorCode::InvalidBetAmount);
-        require!(
-            bet_amount <= slots_state.max_payout_per_spin,
-            ErrorCode::BetTooHigh
-        );
-        require!(!player_state.has_pending_spin, ErrorCode::PendingSpinExists);
+    /// Commit to `count` single-line spins (1..=10) of `bet_amount` each
+    /// against one reveal, escrowing the whole stake. See multi_spin.rs.
+    pub fn request_spins(
+        ctx: Context<RequestSpin>,
+        randomness_account: Pubkey,
+        bet_amount: u64,
+        count: u8,
+    ) -> Result<()> {
+        instructions::spin::request_spins(ctx, randomness_account, bet_amount, count)
+    }
 
-        // Ensure randomness account matches parameter and is owned by Switchboard.
-        require_keys_eq!(
-            randomness_account,
-            randomness_ai.key(),
-            ErrorCode::RandomnessAccountMismatch
-        );
-        let expected_sb_pid = Pubkey::from_str(SWITCHBOARD_ON_DEMAND_PROGRAM_ID_STR)
-            .map_err(|_| ErrorCode::RandomnessParseFailed)?;
-        require_keys_eq!(
-            *randomness_ai.owner,
-            expected_sb_pid,
-            ErrorCode::RandomnessAccountMismatch
-        );
+    /// First spin in one instruction: create the PlayerState if it does
+    /// not exist yet, optionally fund the session balance with
+    /// `session_deposit`, then commit `bet_amount` exactly like
+    /// request_spin. Works for existing players too.
+    pub fn onboard_and_spin(
+        ctx: Context<OnboardAndSpin>,
+        randomness_account: Pubkey,
+        bet_amount: u64,
+        session_deposit: u64,
+    ) -> Result<()> {
+        instructions::spin::onboard_and_spin(ctx, randomness_account, bet_amount, session_deposit)
+    }
 
-        // Ensure user has enough funds for bet.
-        require!(user.lamports() >= bet_amount, ErrorCode::InsufficientFunds);
+    /// STEP 1, relayed: commit a bet the player signed off-chain. The
+    /// preceding instruction must be the Ed25519 verification of
+    /// `bet.message()`; the bet and relayer fee come out of the player's
+    /// session (claimable) balance, so the player signs nothing on-chain.
+    pub fn request_spin_with_signature(
+        ctx: Context<RequestSpinWithSignature>,
+        bet: SignedBet,
+    ) -> Result<()> {
+        instructions::spin::request_spin_with_signature(ctx, bet)
+    }
 
-        // Ensure pool is sufficiently funded to cover min threshold + max payout
-        // BEFORE accepting a new bet (fairness best-practice).
-        let required_pool = slots_state
-            .min_pool_threshold
-            .saturating_add(slots_state.max_payout_per_spin);
-        require!(
-            slots_state.total_pool >= required_pool,
-            ErrorCode::InsufficientPool
-        );
-        require!(
-            treasury.lamports() >= required_pool,
-            ErrorCode::InsufficientPool
-        );
+    /// Top up the session balance that relayed bets draw from. Withdrawn
+    /// with withdraw_claimable.
+    pub fn deposit_session(ctx: Context<DepositSession>, amount: u64) -> Result<()> {
+        instructions::spin::deposit_session(ctx, amount)
+    }
 
-        // Basic freshness check: allow same-slot or previous-slot commit.
-        let clock = Clock::get()?;
-        let randomness_data = RandomnessAccountData::parse(randomness_ai.data.borrow())
-            .map_err(|_| ErrorCode::RandomnessParseFailed)?;
+    /// STEP 2: Settle a previously committed spin using Switchboard VRF.
+    /// Anyone may submit it once the randomness is revealed; the payout
+    /// always goes to the player_state owner, and a cranker other than the
+    /// player earns `crank_fee`. Such a crank must pass the VIP token
+    /// account and leaderboard player_state records and call a registered
+    /// settle hook (see optional.rs). A randomness account re-seeded since
+    /// the commit fails with RandomnessSeedChanged; admin_void_spin refunds
+    /// it.
+    pub fn settle_spin(ctx: Context<SettleSpin>) -> Result<()> {
+        instructions::spin::settle_spin(ctx)
+    }
 
-        let seed_slot = randomness_data.seed_slot;
-        let current_slot = clock.slot;
-        require!(
-            seed_slot == current_slot || seed_slot == current_slot.saturating_sub(1),
-            ErrorCode::RandomnessNotResolved
-        );
+    /// Settle up to batch_settle::MAX_BATCH_SETTLE players' pending spins,
+    /// passed as (player_state, user, randomness_account) remaining-account
+    /// triples. Entries whose randomness has not revealed, or was re-seeded
+    /// since the commit, are skipped and stay pending; every other entry
+    /// commits or the batch fails.
+    pub fn settle_spins_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleSpinsBatch<'info>>,
+    ) -> Result<()> {
+        instructions::spin::settle_spins_batch(ctx)
+    }
 
-        // Transfer bet user -> treasury (user signs, no PDA needed).
-        let transfer_accounts = system_program::Transfer {
-            from: user.to_account_info(),
-            to: treasury.to_account_info(),
-        };
-        let transfer_ctx =
-            CpiContext::new(ctx.accounts.system_program.to_account_info(), transfer_accounts);
-        system_program::transfer(transfer_ctx, bet_amount)?;
-
-        // Update accounting
-        slots_state.total_wagered = slots_state
-            .total_wagered
-            .checked_add(bet_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-        slots_state.total_pool = slots_state
-            .total_pool
-            .checked_add(bet_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Settle, and re-bet up to `next_bet_amount` of the winnings on
+    /// `new_randomness_account` in the same instruction. Winnings beyond
+    /// the re-bet (and any oracle cost) go to the claimable balance. If the
+    /// spin lost, or the new commit fails any of its usual checks, this is
+    /// a plain settle and the winnings are paid out.
+    ///
+    /// The two randomness accounts are never interchangeable. The new one
+    /// must differ from the one being settled, which fails the whole
+    /// instruction with RespinReusesRandomness. The new commit is only
+    /// validated once the settlement has cleared the pending spin, and a
+    /// pending spin still set at that point fails with
+    /// RespinBeforeSettleCleared rather than falling back.
+    pub fn settle_and_respin(
+        ctx: Context<SettleAndRespin>,
+        new_randomness_account: Pubkey,
+        next_bet_amount: u64,
+    ) -> Result<()> {
+        instructions::spin::settle_and_respin(ctx, new_randomness_account, next_bet_amount)
+    }
 
-        // Jackpot contributions (accounting only, still in treasury)
-        apply_jackpot_contributions(slots_state, bet_amount)?;
+    /// Settle a losing spin without write-locking slots_state, so such
+    /// settles run in parallel. The spin's stats are parked in PlayerState
+    /// until flush_stats. Anything that must touch shared state (a payout,
+    /// a bootstrap transition) fails with SettleNeedsWriteLock and goes
+    /// through settle_spin instead, as do a multi-spin commit and a free
+    /// spin.
+    pub fn settle_spin_deferred(ctx: Context<SettleSpinDeferred>) -> Result<()> {
+        instructions::spin::settle_spin_deferred(ctx)
+    }
 
-        // Store pending spin in player_state
-        player_state.randomness_account = randomness_account;
-        player_state.pending_bet_amount = bet_amount;
-        player_state.has_pending_spin = true;
+    /// Permissionless: fold deferred stats of the PlayerState accounts
+    /// passed as writable remaining accounts into SlotsState.
+    pub fn flush_stats<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FlushStats<'info>>,
+    ) -> Result<()> {
+        instructions::spin::flush_stats(ctx)
+    }
 
-        emit!(SpinCommitted {
-            user: user.key(),
-            bet_amount,
-            randomness_account,
-        });
+    /// Incident tool: void a player's pending spin (admin only). The bet is
+    /// credited to the player's claimable balance rather than sent to the
+    /// wallet. Refused once the committed randomness has revealed, so it
+    /// cannot be used against a winning reveal. A spin whose randomness
+    /// was re-seeded since the commit also reports RandomnessRotated.
+    pub fn admin_void_spin(
+        ctx: Context<AdminVoidSpin>,
+        player: Pubkey,
+        reason_code: u8,
+    ) -> Result<()> {
+        instructions::spin::admin_void_spin(ctx, player, reason_code)
+    }
 
-        Ok(())
+    /// Player cancels a pending spin whose randomness has not revealed
+    /// within spin_expiry_slots of the commit. The bet is refunded if the
+    /// committed generation never revealed, and forfeit if the player
+    /// re-seeded the account.
+    pub fn cancel_spin(ctx: Context<CancelSpin>) -> Result<()> {
+        instructions::spin::cancel_spin(ctx)
     }
 
-    /// STEP 2: Settle a previously committed spin using Switchboard VRF.
-    pub fn settle_spin(ctx: Context<SettleSpin>) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-        let player_state = &mut ctx.accounts.player_state;
-        let user = &ctx.accounts.user;
-        let treasury = &ctx.accounts.treasury;
-        let randomness_ai = &ctx.accounts.randomness_account_data;
-
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
-        require!(!slots_state.paused, ErrorCode::GamePaused);
-        require!(player_state.has_pending_spin, ErrorCode::NoPendingSpin);
-
-        // Ensure we are settling the correct randomness account and that it is Switchboard-owned.
-        require_keys_eq!(
-            player_state.randomness_account,
-            randomness_ai.key(),
-            ErrorCode::RandomnessAccountMismatch
-        );
-        let expected_sb_pid = Pubkey::from_str(SWITCHBOARD_ON_DEMAND_PROGRAM_ID_STR)
-            .map_err(|_| ErrorCode::RandomnessParseFailed)?;
-        require_keys_eq!(
-            *randomness_ai.owner,
-            expected_sb_pid,
-            ErrorCode::RandomnessAccountMismatch
-        );
+    /// Set the public-tier bet range (admin only). The top paytable win on
+    /// `max_bet` must fit within max_payout_per_spin.
+    pub fn set_bet_limits(ctx: Context<EmergencyPause>, min_bet: u64, max_bet: u64) -> Result<()> {
+        instructions::admin::set_bet_limits(ctx, min_bet, max_bet)
+    }
 
-        let bet_amount = player_state.pending_bet_amount;
-        require!(bet_amount > 0, ErrorCode::InvalidBetAmount);
-
-        // =========================
-        // READ VRF RANDOMNESS
-        // =========================
-        let clock = Clock::get()?;
-        let randomness_data = RandomnessAccountData::parse(randomness_ai.data.borrow())
-            .map_err(|_| ErrorCode::RandomnessParseFailed)?;
-
-        // Get the 32 bytes of random data for this slot
-        let random_bytes = randomness_data
-            .get_value(clock.slot)
-            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
-
-        // Turn first 8 bytes into a u64 seed.
-        let mut seed: u64 = u64::from_le_bytes(
-            random_bytes[0..8]
-                .try_into()
-                .map_err(|_| ErrorCode::RandomnessParseFailed)?,
-        );
+    /// Set how long a spin waits for its reveal before the player may
+    /// cancel it (admin only).
+    pub fn set_spin_expiry(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+        instructions::admin::set_spin_expiry(ctx, slots)
+    }
 
-        // =========================
-        // SYMBOLS + BASE PAYOUT (3-OAK ONLY)
-        // =========================
-        let s1 = generate_weighted_symbol(next_random_u64(&mut seed));
-        let s2 = generate_weighted_symbol(next_random_u64(&mut seed));
-        let s3 = generate_weighted_symbol(next_random_u64(&mut seed));
-
-        let base_payout_full = calculate_payout_3oak([s1, s2, s3], bet_amount);
-
-        // =========================
-        // GLOBAL CAPS & POOL LIMITS
-        // =========================
-        // Compute how much we can afford to pay in TOTAL this spin,
-        // before considering jackpots, to avoid burning jackpot amounts.
-        let available_pool = slots_state
-            .total_pool
-            .saturating_sub(slots_state.min_pool_threshold);
-        let cap_by_house = slots_state.max_payout_per_spin;
-        let max_total_affordable = core::cmp::min(available_pool, cap_by_house);
-
-        // If we can't pay anything, no payout (including jackpots).
-        if max_total_affordable == 0 {
-            // Update stats & clear pending spin, then exit cleanly.
-            slots_state.total_spins = slots_state
-                .total_spins
-                .checked_add(1)
-                .ok_or(ErrorCode::MathOverflow)?;
-
-            // house_profit = total_wagered - total_payout, saturating.
-            slots_state.house_profit =
-                slots_state.total_wagered.saturating_sub(slots_state.total_payout);
-
-            if slots_state.total_wagered > 0 {
-                slots_state.current_rtp_bps = ((slots_state.total_payout as u128)
-                    .saturating_mul(10_000)
-                    / (slots_state.total_wagered as u128)) as u32;
-            }
+    /// Set the fewest slots between one player's commits (admin only);
+    /// 0 turns the limit off. See rate_limit.rs.
+    pub fn set_min_slots_between_spins(ctx: Context<EmergencyPause>, slots: u16) -> Result<()> {
+        instructions::admin::set_min_slots_between_spins(ctx, slots)
+    }
 
-            emit!(SpinSettled {
-                user: user.key(),
-                randomness_account: randomness_ai.key(),
-                symbols: [s1, s2, s3],
-                base_payout: 0,
-                jackpot_payout: 0,
-                total_payout: 0,
-            });
+    /// Set the fee paid to third-party settle cranks (admin only).
+    pub fn set_crank_fee(ctx: Context<EmergencyPause>, crank_fee: u64) -> Result<()> {
+        instructions::admin::set_crank_fee(ctx, crank_fee)
+    }
 
-            emit!(RTPUpdate {
-                total_spins: slots_state.total_spins,
-                total_wagered: slots_state.total_wagered,
-                total_payout: slots_state.total_payout,
-                house_profit: slots_state.house_profit,
-                current_rtp_bps: slots_state.current_rtp_bps,
-            });
+    /// Grow SlotsState to the current layout and fill in the fields added
+    /// since its version (admin only). `machine_id` names the machine the
+    /// account must be. See migration.rs.
+    pub fn migrate_state(ctx: Context<MigrateState>, machine_id: [u8; 32]) -> Result<()> {
+        instructions::admin::migrate_state(ctx, machine_id)
+    }
 
-            player_state.has_pending_spin = false;
-            player_state.pending_bet_amount = 0;
-            player_state.randomness_account = Pubkey::default();
+    /// Grow the caller's PlayerState to the current layout (anyone may
+    /// migrate their own; the caller pays the extra rent). New fields
+    /// start at zero. A no-op on an account that is already current.
+    pub fn migrate_player(ctx: Context<MigratePlayer>) -> Result<()> {
+        instructions::admin::migrate_player(ctx)
+    }
 
-            return Ok(());
-        }
+    /// Grow the caller's PoolPosition to the current layout, as
+    /// migrate_player does for PlayerState.
+    pub fn migrate_pool_position(ctx: Context<MigratePoolPosition>) -> Result<()> {
+        instructions::admin::migrate_pool_position(ctx)
+    }
 
-        // First cap the base payout by what we can afford.
-        let base_payout = core::cmp::min(base_payout_full, max_total_affordable);
+    /// Set the fraction of the pool the authority may withdraw per
+    /// extraction period (admin only), at most
+    /// extraction::MAX_EXTRACTION_BUDGET_BPS. Applies from the next period.
+    pub fn set_extraction_budget(ctx: Context<EmergencyPause>, budget_bps: u16) -> Result<()> {
+        instructions::admin::set_extraction_budget(ctx, budget_bps)
+    }
 
-        // Remaining capacity for jackpots this spin.
-        let remaining_for_jackpot = max_total_affordable.saturating_sub(base_payout);
+    /// Allow `amount` beyond the extraction budget, usable one period
+    /// from now (admin only). Replaces any earlier override; 0 cancels.
+    pub fn propose_extraction_override(
+        ctx: Context<EmergencyPause>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::admin::propose_extraction_override(ctx, amount)
+    }
 
-        // =========================
-        // JACKPOT (if affordable)
-        // =========================
-        let jackpot_payout = if remaining_for_jackpot > 0 {
-            maybe_hit_jackpot(slots_state, &mut seed, remaining_for_jackpot)?
-        } else {
-            0
-        };
+    /// Replace the 2- and 3-of-a-kind paytable columns (admin only). New
+    /// spins must be paused with none pending; see paytable.rs.
+    pub fn set_paytable(
+        ctx: Context<EmergencyPause>,
+        two_of_a_kind: [u64; SYMBOL_COUNT],
+        three_of_a_kind: [u64; SYMBOL_COUNT],
+    ) -> Result<()> {
+        instructions::admin::set_paytable(ctx, two_of_a_kind, three_of_a_kind)
+    }
 
-        let total_payout = base_payout
-            .checked_add(jackpot_payout)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Replace the reel weights and the 3-of-a-kind paytable column
+    /// together (admin only). Same preconditions and checks as
+    /// set_paytable; the weights must be non-zero and their sum fit in a
+    /// u64. See paytable.rs.
+    pub fn set_reel_config(
+        ctx: Context<EmergencyPause>,
+        weights: [u64; SYMBOL_COUNT],
+        three_of_a_kind: [u64; SYMBOL_COUNT],
+    ) -> Result<()> {
+        instructions::admin::set_reel_config(ctx, weights, three_of_a_kind)
+    }
 
-        // Transfer payout from treasury PDA -> user, signing as PDA.
-        if total_payout > 0 {
-            require!(
-                treasury.lamports() >= total_payout,
-                ErrorCode::InsufficientPool
-            );
-            require!(
-                slots_state.total_pool >= total_payout,
-                ErrorCode::InsufficientPool
-            );
-
-            let payout_accounts = system_program::Transfer {
-                from: treasury.to_account_info(),
-                to: user.to_account_info(),
-            };
-            let treasury_bump = *ctx.bumps.get("treasury").unwrap();
-            let signer_seeds: &[&[u8]] = &[b"treasury", &[treasury_bump]];
-
-            let payout_ctx = CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                payout_accounts,
-            )
-            .with_signer(&[signer_seeds]);
-            system_program::transfer(payout_ctx, total_payout)?;
-
-            slots_state.total_payout = slots_state
-                .total_payout
-                .checked_add(total_payout)
-                .ok_or(ErrorCode::MathOverflow)?;
-            slots_state.total_pool = slots_state
-                .total_pool
-                .checked_sub(total_payout)
-                .ok_or(ErrorCode::MathOverflow)?;
-        }
+    /// Set the scatter symbol and the free spins three of them award
+    /// (admin only); `free_spins == 0` turns the feature off. Same
+    /// preconditions as set_paytable, and the scatter must pay nothing
+    /// and not be the wild. Held free spins keep their bet. See
+    /// scatter.rs.
+    pub fn set_scatter_config(
+        ctx: Context<EmergencyPause>,
+        symbol: u8,
+        free_spins: u16,
+        max_free_spins: u16,
+    ) -> Result<()> {
+        instructions::admin::set_scatter_config(ctx, symbol, free_spins, max_free_spins)
+    }
 
-        // =========================
-        // RTP STATS
-        // =========================
-        slots_state.total_spins = slots_state
-            .total_spins
-            .checked_add(1)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Make `wild_symbol` complete any match, or NO_WILD for none (admin
+    /// only). Same preconditions and RTP check as set_paytable, with the
+    /// wild priced in; the scatter cannot be wild.
+    pub fn set_wild_symbol(ctx: Context<EmergencyPause>, wild_symbol: u8) -> Result<()> {
+        instructions::admin::set_wild_symbol(ctx, wild_symbol)
+    }
 
-        // Use saturating_sub so we never underflow if payouts exceed wagers.
-        slots_state.house_profit = slots_state
-            .total_wagered
-            .saturating_sub(slots_state.total_payout);
+    /// Set the ceiling on the paytable's implied RTP (admin only). The
+    /// live table must already fit under it.
+    pub fn set_max_rtp(ctx: Context<EmergencyPause>, max_rtp_bps: u16) -> Result<()> {
+        instructions::admin::set_max_rtp(ctx, max_rtp_bps)
+    }
 
-        if slots_state.total_wagered > 0 {
-            slots_state.current_rtp_bps = ((slots_state.total_payout as u128)
-                .saturating_mul(10_000)
-                / (slots_state.total_wagered as u128)) as u32;
-        }
+    /// Replace the staff wallets barred from playing (admin only); default
+    /// keys leave a slot empty. See operators.rs.
+    pub fn set_staff_wallets(
+        ctx: Context<EmergencyPause>,
+        wallets: [Pubkey; MAX_STAFF_WALLETS],
+    ) -> Result<()> {
+        instructions::admin::set_staff_wallets(ctx, wallets)
+    }
 
-        emit!(SpinSettled {
-            user: user.key(),
-            randomness_account: randomness_ai.key(),
-            symbols: [s1, s2, s3],
-            base_payout,
-            jackpot_payout,
-            total_payout,
-        });
+    /// Set how many outcomes a single reveal may ever yield (admin only).
+    /// Spins already committed keep the budget they committed.
+    pub fn set_max_outcomes_per_reveal(ctx: Context<EmergencyPause>, max: u8) -> Result<()> {
+        instructions::admin::set_max_outcomes_per_reveal(ctx, max)
+    }
 
-        emit!(RTPUpdate {
-            total_spins: slots_state.total_spins,
-            total_wagered: slots_state.total_wagered,
-            total_payout: slots_state.total_payout,
-            house_profit: slots_state.house_profit,
-            current_rtp_bps: slots_state.current_rtp_bps,
-        });
+    /// Player withdraws their claimable balance from the treasury.
+    pub fn withdraw_claimable(ctx: Context<WithdrawClaimable>) -> Result<()> {
+        instructions::player::withdraw_claimable(ctx)
+    }
 
-        // Clear pending spin
-        player_state.has_pending_spin = false;
-        player_state.pending_bet_amount = 0;
-        player_state.randomness_account = Pubkey::default();
+    /// Anyone can top up the pool (deposits go via treasury). The deposit
+    /// mints LP shares into the depositor's PoolPosition; see
+    /// pool_shares.rs.
+    pub fn add_to_pool(ctx: Context<DepositToPool>, amount: u64) -> Result<()> {
+        instructions::pool::add_to_pool(ctx, amount)
+    }
 
-        Ok(())
+    /// Burn `shares` of the caller's PoolPosition for their slice of the
+    /// pool as it stands now, net of pending spins' reservations.
+    /// Rejected, not partially filled, if the payout would take the pool
+    /// below required_floor.
+    pub fn withdraw_from_pool(ctx: Context<WithdrawFromPool>, shares: u128) -> Result<()> {
+        instructions::pool::withdraw_from_pool(ctx, shares)
     }
 
-    /// Anyone can top up the pool (deposits go via treasury).
-    pub fn add_to_pool(ctx: Context<AddToPool>, amount: u64) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-        let user = &ctx.accounts.user;
-        let treasury = &ctx.accounts.treasury;
+    /// Anyone can fund the fee balance (used for winner fee rebates).
+    /// Lamports go to the treasury but are tracked outside total_pool.
+    pub fn fund_fee_balance(ctx: Context<AddToPool>, amount: u64) -> Result<()> {
+        instructions::pool::fund_fee_balance(ctx, amount)
+    }
 
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(user.lamports() >= amount, ErrorCode::InsufficientFunds);
+    /// Fund the devnet faucet reserve (admin only, test networks only).
+    /// Lamports go to the treasury, tracked only in faucet_reserve.
+    pub fn fund_faucet(ctx: Context<AddToPool>, amount: u64) -> Result<()> {
+        instructions::pool::fund_faucet(ctx, amount)
+    }
 
-        let transfer_accounts = system_program::Transfer {
-            from: user.to_account_info(),
-            to: treasury.to_account_info(),
-        };
-        let transfer_ctx =
-            CpiContext::new(ctx.accounts.system_program.to_account_info(), transfer_accounts);
-        system_program::transfer(transfer_ctx, amount)?;
+    /// Set the devnet faucet drip (admin only, test networks only):
+    /// `amount` per call (0 disables), at most once per `interval_slots`
+    /// per player, `lifetime_cap` per player overall.
+    pub fn set_faucet_config(
+        ctx: Context<EmergencyPause>,
+        amount: u64,
+        interval_slots: u64,
+        lifetime_cap: u64,
+    ) -> Result<()> {
+        instructions::pool::set_faucet_config(ctx, amount, interval_slots, lifetime_cap)
+    }
 
-        slots_state.total_pool = slots_state
-            .total_pool
-            .checked_add(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Credit one faucet drip to the caller's session balance (test
+    /// networks only). See faucet.rs for the limits.
+    pub fn devnet_faucet(ctx: Context<DepositSession>) -> Result<()> {
+        instructions::pool::devnet_faucet(ctx)
+    }
 
-        emit!(PoolDeposit {
-            user: user.key(),
-            amount,
-            new_pool: slots_state.total_pool,
-        });
+    /// Anyone can donate to a specific jackpot tier. Lamports join the
+    /// pool; the tier is credited up to its cap and the rest stays general
+    /// pool headroom. The donor gets no claim on them. Refused while
+    /// deposits are paused.
+    pub fn boost_jackpot(ctx: Context<AddToPool>, tier: u8, amount: u64) -> Result<()> {
+        instructions::jackpot::boost_jackpot(ctx, tier, amount)
+    }
 
-        Ok(())
+    /// Set a tier's donation ceiling (admin only). 0 = uncapped; a cap
+    /// below the tier's current amount is refused. Contributions can still
+    /// carry a tier past its cap, after which donations credit it nothing.
+    pub fn set_jackpot_max_cap(ctx: Context<EmergencyPause>, tier: u8, cap: u64) -> Result<()> {
+        instructions::jackpot::set_jackpot_max_cap(ctx, tier, cap)
     }
 
-    /// Authority-only withdrawal from the pool.
-    /// Respects min_pool_threshold **and** jackpot balances so operator
-    /// cannot drain reserves required to pay jackpots.
-    pub fn claim_payout(ctx: Context<ClaimPayout>, amount: u64) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-        let authority = &ctx.accounts.authority;
-        let treasury = &ctx.accounts.treasury;
+    /// Configure the winner fee rebate (admin only). `rebate_lamports = 0`
+    /// disables it. Rebates suspend below `suspend_below` and resume at
+    /// `resume_at`, which must be at least `suspend_below`.
+    pub fn set_winner_rebate(
+        ctx: Context<EmergencyPause>,
+        rebate_lamports: u64,
+        daily_cap_per_player: u64,
+        suspend_below: u64,
+        resume_at: u64,
+    ) -> Result<()> {
+        instructions::pool::set_winner_rebate(
+            ctx,
+            rebate_lamports,
+            daily_cap_per_player,
+            suspend_below,
+            resume_at,
+        )
+    }
 
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(
-            authority.key() == slots_state.authority,
-            ErrorCode::Unauthorized
-        );
+    /// Configure paying headroom-capped wins from the fee balance (admin
+    /// only). `max_per_spin` bounds the fee draw of a single spin.
+    pub fn set_fee_overflow_policy(
+        ctx: Context<EmergencyPause>,
+        enabled: bool,
+        max_per_spin: u64,
+    ) -> Result<()> {
+        instructions::pool::set_fee_overflow_policy(ctx, enabled, max_per_spin)
+    }
 
-        // Compute total jackpot balances.
-        let jackpot_total = total_jackpot_amounts(&slots_state.jackpots)?;
+    /// Start an authority withdrawal from the pool (admin only). The
+    /// amount is held against the pool until execute_withdrawal pays it
+    /// after `withdrawal_delay_slots`, or cancel_withdrawal drops it. See
+    /// withdrawal_timelock.rs.
+    pub fn request_withdrawal(
+        ctx: Context<EmergencyPause>,
+        amount: u64,
+        category: u8,
+        memo: [u8; 32],
+    ) -> Result<()> {
+        instructions::pool::request_withdrawal(ctx, amount, category, memo)
+    }
 
-        // Funds that must remain in the pool:
-        // min_pool_threshold + full jackpot balances.
-        let must_keep = slots_state
-            .min_pool_threshold
-            .checked_add(jackpot_total)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Drop the pending withdrawal (admin only).
+    pub fn cancel_withdrawal(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::pool::cancel_withdrawal(ctx)
+    }
 
-        // Amount actually available for withdrawal.
-        let available_for_claim = slots_state.total_pool.saturating_sub(must_keep);
-        require!(amount <= available_for_claim, ErrorCode::InsufficientPool);
-        require!(
-            treasury.lamports() >= amount,
-            ErrorCode::InsufficientPool
-        );
+    /// Set the delay between request_withdrawal and execute_withdrawal
+    /// (admin only). A pending request keeps its unlock slot.
+    pub fn set_withdrawal_delay(ctx: Context<EmergencyPause>, delay_slots: u64) -> Result<()> {
+        instructions::pool::set_withdrawal_delay(ctx, delay_slots)
+    }
 
-        let new_pool = slots_state
-            .total_pool
-            .checked_sub(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-        slots_state.total_pool = new_pool;
+    /// Pay the matured withdrawal to the authority. Respects
+    /// min_pool_threshold **and** jackpot balances as they stand now, so
+    /// the operator cannot drain reserves required to pay jackpots.
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        instructions::pool::execute_withdrawal(ctx)
+    }
 
-        let payout_accounts = system_program::Transfer {
-            from: treasury.to_account_info(),
-            to: authority.to_account_info(),
-        };
-        let treasury_bump = *ctx.bumps.get("treasury").unwrap();
-        let signer_seeds: &[&[u8]] = &[b"treasury", &[treasury_bump]];
+    /// Create the token treasury of a token deployment (admin only): the
+    /// treasury PDA's associated token account for `exclusive_mint`.
+    pub fn init_token_treasury(ctx: Context<InitTokenTreasury>) -> Result<()> {
+        instructions::pool::init_token_treasury(ctx)
+    }
 
-        let payout_ctx = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            payout_accounts,
-        )
-        .with_signer(&[signer_seeds]);
-        system_program::transfer(payout_ctx, amount)?;
+    /// request_spin for a token deployment: the bet moves from the
+    /// player's token account to the token treasury. The oracle cost is
+    /// still charged in lamports.
+    pub fn request_spin_token(
+        ctx: Context<RequestSpinToken>,
+        randomness_account: Pubkey,
+        bet_amount: u64,
+    ) -> Result<()> {
+        instructions::spin::request_spin_token(ctx, randomness_account, bet_amount)
+    }
 
-        Ok(())
+    /// settle_spin for a token deployment: the payout moves from the token
+    /// treasury to the player.
+    pub fn settle_spin_token(ctx: Context<SettleSpinToken>) -> Result<()> {
+        instructions::spin::settle_spin_token(ctx)
     }
 
-    /// Pause/unpause game (admin only).
-    pub fn emergency_pause(ctx: Context<EmergencyPause>) -> Result<()> {
-        let slots_state = &mut ctx.accounts.slots_state;
-        let authority = &ctx.accounts.authority;
+    /// add_to_pool for a token deployment. LP shares are SOL only, so
+    /// only the authority funds a token pool and no shares are minted.
+    pub fn add_to_pool_token(ctx: Context<AddToPoolToken>, amount: u64) -> Result<()> {
+        instructions::pool::add_to_pool_token(ctx, amount)
+    }
 
-        require!(slots_state.initialized, ErrorCode::Uninitialized);
-        require!(
-            authority.key() == slots_state.authority,
-            ErrorCode::Unauthorized
-        );
+    /// execute_withdrawal for a token deployment: same timelock, floor and
+    /// cooldown, paid to the authority's token account.
+    pub fn execute_withdrawal_token(ctx: Context<ExecuteWithdrawalToken>) -> Result<()> {
+        instructions::pool::execute_withdrawal_token(ctx)
+    }
 
-        slots_state.paused = !slots_state.paused;
+    /// withdraw_claimable for a token deployment, whose claimables are in
+    /// tokens: the balance moves from the token treasury to the player.
+    pub fn withdraw_claimable_token(ctx: Context<WithdrawClaimableToken>) -> Result<()> {
+        instructions::player::withdraw_claimable_token(ctx)
+    }
 
-        emit!(EmergencyAction {
-            action: if slots_state.paused {
-                "paused".to_string()
-            } else {
-                "resumed".to_string()
-            },
-            authority: authority.key(),
-        });
+    /// Create the hourly RTP history PDA (anyone may pay for it).
+    pub fn init_rtp_history(ctx: Context<InitRtpHistory>) -> Result<()> {
+        instructions::admin::init_rtp_history(ctx)
+    }
 
-        Ok(())
+    /// Create the scraper metrics account. Anyone may pay for it.
+    pub fn init_metrics(ctx: Context<InitMetrics>) -> Result<()> {
+        instructions::admin::init_metrics(ctx)
     }
-}
 
-// =========================
-// ACCOUNT CONTEXTS
-// =========================
+    /// Create the compressed receipt tree. Anyone may pay for it.
+    pub fn init_receipt_tree(ctx: Context<InitReceiptTree>) -> Result<()> {
+        instructions::admin::init_receipt_tree(ctx)
+    }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 1024, // plenty; tighten for production
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    /// PDA treasury vault for SOL (program-signable via seeds).
-    #[account(
-        init,
-        payer = payer,
-        seeds = [b"treasury"],
-        bump,
-        space = 8, // no data needed, just rent-exempt holder
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct InitPlayer<'info> {
-    #[account(
-        mut,
-        has_one = treasury
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    #[account(
-        init,
-        payer = user,
-        seeds = [b"player", user.key().as_ref()],
-        bump,
-        space = 8 + 32 + 1 + 32 + 8 + 1, // PlayerState size
-    )]
-    pub player_state: Account<'info, PlayerState>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    /// Treasury PDA vault – must be same as in slots_state.
-    #[account(
-        mut,
-        seeds = [b"treasury"],
-        bump,
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct RequestSpin<'info> {
-    #[account(
-        mut,
-        has_one = treasury
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    #[account(
-        mut,
-        seeds = [b"player", user.key().as_ref()],
-        bump = player_state.bump,
-        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
-    )]
-    pub player_state: Account<'info, PlayerState>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    #[account(
-        mut,
-        seeds = [b"treasury"],
-        bump,
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    /// CHECK: Switchboard randomness account
-    #[account(mut)]
-    pub randomness_account_data: AccountInfo<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct SettleSpin<'info> {
-    #[account(
-        mut,
-        has_one = treasury
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    #[account(
-        mut,
-        seeds = [b"player", user.key().as_ref()],
-        bump = player_state.bump,
-        constraint = player_state.owner == user.key() @ ErrorCode::Unauthorized
-    )]
-    pub player_state: Account<'info, PlayerState>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    #[account(
-        mut,
-        seeds = [b"treasury"],
-        bump,
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    /// CHECK: same randomness account used in request_spin
-    #[account(mut)]
-    pub randomness_account_data: AccountInfo<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct AddToPool<'info> {
-    #[account(
-        mut,
-        has_one = treasury
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    #[account(
-        mut,
-        seeds = [b"treasury"],
-        bump,
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct ClaimPayout<'info> {
-    #[account(
-        mut,
-        has_one = treasury
-    )]
-    pub slots_state: Account<'info, SlotsState>,
-
-    #[account(mut)]
-    pub authority: Signer<'info>,
-
-    #[account(
-        mut,
-        seeds = [b"treasury"],
-        bump,
-    )]
-    pub treasury: SystemAccount<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct EmergencyPause<'info> {
-    #[account(mut)]
-    pub slots_state: Account<'info, SlotsState>,
-    pub authority: Signer<'info>,
-}
+    /// Read-only: check a receipt against the current or a recent root,
+    /// for dispute resolution. `proof` lists siblings from the leaf level up.
+    pub fn verify_receipt(
+        ctx: Context<ViewReceiptTree>,
+        leaf: [u8; 32],
+        leaf_index: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        instructions::admin::verify_receipt(ctx, leaf, leaf_index, proof)
+    }
 
-// =========================
-// INTERNAL HELPERS
-// =========================
+    /// Permissionless refresh of the metrics account.
+    pub fn refresh_metrics(ctx: Context<RefreshMetrics>) -> Result<()> {
+        instructions::admin::refresh_metrics(ctx)
+    }
 
-/// Simple LCG-based PRNG to expand a single VRF seed
-/// into multiple 64-bit random values.
-fn next_random_u64(seed: &mut u64) -> u64 {
-    const A: u64 = 6364136223846793005;
-    const C: u64 = 1;
-    *seed = seed.wrapping_mul(A).wrapping_add(C);
-    *seed
-}
-
-/// Sample a reel symbol index [0, SYMBOL_COUNT) using SYMBOL_WEIGHTS.
-fn generate_weighted_symbol(random_u64: u64) -> u8 {
-    let mut r = random_u64 % TOTAL_WEIGHT;
-    for (idx, &w) in SYMBOL_WEIGHTS.iter().enumerate() {
-        if r < w {
-            return idx as u8;
-        }
-        r -= w;
+    /// Read-only: per-tier jackpot hit probability and expected time to hit
+    /// at the current traffic, returned via return data.
+    pub fn estimate_jackpot_eta(ctx: Context<ViewState>) -> Result<JackpotEtaReport> {
+        instructions::jackpot::estimate_jackpot_eta(ctx)
     }
-    (SYMBOL_COUNT - 1) as u8
-}
 
-/// Only pay 3-of-a-kind wins. No 2-of-a-kind payouts.
-fn calculate_payout_3oak(symbols: [u8; 3], bet_amount: u64) -> u64 {
-    let [s1, s2, s3] = symbols;
+    /// Read-only: reseed policy of each jackpot tier and whether it is
+    /// still rebuilding to its seed. A rebuilding tier is worth less than
+    /// its seed, so RTP is temporarily below the configured tables.
+    pub fn get_jackpot_rebuild_status(ctx: Context<ViewState>) -> Result<JackpotRebuildReport> {
+        instructions::jackpot::get_jackpot_rebuild_status(ctx)
+    }
 
-    if s1 as usize >= SYMBOL_COUNT
-        || s2 as usize >= SYMBOL_COUNT
-        || s3 as usize >= SYMBOL_COUNT
-    {
-        return 0;
+    /// Read-only: the base/jackpot split of the pool and the treasury
+    /// balance behind it. Fails with AccountingInvariantViolated if the
+    /// jackpots exceed the pool or the treasury is short of the tracked
+    /// buckets; see accounting.rs.
+    pub fn verify_accounting(ctx: Context<VerifyAccounting>) -> Result<AccountingReport> {
+        instructions::pool::verify_accounting(ctx)
     }
 
-    if s1 == s2 && s2 == s3 {
-        let idx = s1 as usize;
-        let mult = SYMBOL_PAYOUTS_3OAK[idx];
-        return bet_amount.saturating_mul(mult);
+    /// Permissionless: reset total_pool from the treasury's spendable
+    /// balance, so lamports sent straight to it join the pool and a short
+    /// treasury stops being booked as funds. See accounting.rs.
+    pub fn sync_pool(ctx: Context<SyncPool>) -> Result<()> {
+        instructions::pool::sync_pool(ctx)
     }
 
-    0
-}
+    /// Read-only: pause state, versions and the config hash. Pass the
+    /// symbol meta account to bind it into the hash.
+    pub fn get_game_status(ctx: Context<ViewGameStatus>) -> Result<GameStatus> {
+        instructions::admin::get_game_status(ctx)
+    }
 
-/// Update jackpot pool accounting (contribution from bet).
-fn apply_jackpot_contributions(slots_state: &mut SlotsState, bet_amount: u64) -> Result<()> {
-    let mini_contrib = (bet_amount as u128)
-        .saturating_mul(slots_state.jackpots.mini.contrib_bps as u128)
-        / 10_000;
-    let major_contrib = (bet_amount as u128)
-        .saturating_mul(slots_state.jackpots.major.contrib_bps as u128)
-        / 10_000;
-    let grand_contrib = (bet_amount as u128)
-        .saturating_mul(slots_state.jackpots.grand.contrib_bps as u128)
-        / 10_000;
+    /// Read-only: house P&L per feature; see feature_stats.rs.
+    pub fn get_feature_pnl(ctx: Context<ViewState>) -> Result<FeaturePnl> {
+        instructions::admin::get_feature_pnl(ctx)
+    }
 
-    slots_state.jackpots.mini.amount = slots_state
-        .jackpots
-        .mini
-        .amount
-        .checked_add(mini_contrib as u64)
-        .ok_or(ErrorCode::MathOverflow)?;
-    slots_state.jackpots.major.amount = slots_state
-        .jackpots
-        .major
-        .amount
-        .checked_add(major_contrib as u64)
-        .ok_or(ErrorCode::MathOverflow)?;
-    slots_state.jackpots.grand.amount = slots_state
-        .jackpots
-        .grand
-        .amount
-        .checked_add(grand_contrib as u64)
-        .ok_or(ErrorCode::MathOverflow)?;
-    Ok(())
-}
+    /// Read-only: whether bootstrap limits apply and the cap in effect.
+    pub fn get_bootstrap_status(ctx: Context<ViewState>) -> Result<BootstrapStatus> {
+        instructions::admin::get_bootstrap_status(ctx)
+    }
 
-/// Sum all jackpot amounts safely.
-fn total_jackpot_amounts(jackpots: &JackpotsConfig) -> Result<u64> {
-    let sum1 = jackpots
-        .mini
-        .amount
-        .checked_add(jackpots.major.amount)
-        .ok_or(ErrorCode::MathOverflow)?;
-    let sum2 = sum1
-        .checked_add(jackpots.grand.amount)
-        .ok_or(ErrorCode::MathOverflow)?;
-    Ok(sum2)
-}
+    /// Read-only: what `symbols` would pay on `bet_amount` under the live
+    /// paytable, pool headroom, payout cap and jackpots. See simulate.rs.
+    pub fn simulate_payout(
+        ctx: Context<ViewState>,
+        symbols: [u8; 3],
+        bet_amount: u64,
+    ) -> Result<PayoutSimulation> {
+        instructions::spin::simulate_payout(ctx, symbols, bet_amount)
+    }
 
-/// Randomly choose whether a jackpot hits, and which one,
-/// using the VRF-derived seed.
-/// Only award a jackpot if the FULL jackpot amount is affordable
-/// (no partial jackpots and no silent "burning" of amounts).
-fn maybe_hit_jackpot(
-    slots_state: &mut SlotsState,
-    seed: &mut u64,
-    max_jackpot_payout: u64,
-) -> Result<u64> {
-    let hit_total = slots_state.jackpots.hit_weight_total;
-    if hit_total == 0 || max_jackpot_payout == 0 {
-        return Ok(0);
+    /// Update game tunables (admin only). Only the fields set in `args`
+    /// change; see config.rs for the validation rules.
+    pub fn update_config(ctx: Context<EmergencyPause>, args: UpdateConfigArgs) -> Result<()> {
+        instructions::admin::update_config(ctx, args)
+    }
+
+    /// Configure cold-start limits (admin only). A zero threshold disables
+    /// bootstrap.
+    pub fn set_bootstrap_config(
+        ctx: Context<EmergencyPause>,
+        maturity_threshold: u64,
+        payout_bps: u16,
+        hysteresis_bps: u16,
+    ) -> Result<()> {
+        instructions::admin::set_bootstrap_config(
+            ctx,
+            maturity_threshold,
+            payout_bps,
+            hysteresis_bps,
+        )
     }
 
-    let r = (next_random_u64(seed) as u32) % hit_total;
+    /// Set the half-life of the traffic averages (admin only).
+    pub fn set_traffic_half_life(ctx: Context<EmergencyPause>, half_life_secs: u32) -> Result<()> {
+        instructions::admin::set_traffic_half_life(ctx, half_life_secs)
+    }
 
-    let mut acc = slots_state.jackpots.mini.hit_weight;
-    if r < acc {
-        return Ok(award_jackpot(&mut slots_state.jackpots.mini, max_jackpot_payout));
+    /// Propose a partner settle hook for slot `index` (admin only).
+    /// It becomes active after SETTLE_HOOK_TIMELOCK_SLOTS via
+    /// activate_settle_hook. A new proposal replaces the pending one.
+    pub fn propose_settle_hook(
+        ctx: Context<EmergencyPause>,
+        index: u8,
+        hook_program: Pubkey,
+    ) -> Result<()> {
+        instructions::admin::propose_settle_hook(ctx, index, hook_program)
     }
 
-    acc += slots_state.jackpots.major.hit_weight;
-    if r < acc {
-        return Ok(award_jackpot(&mut slots_state.jackpots.major, max_jackpot_payout));
+    /// Install the pending settle hook once its timelock has passed (admin only).
+    pub fn activate_settle_hook(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::activate_settle_hook(ctx)
     }
 
-    acc += slots_state.jackpots.grand.hit_weight;
-    if r < acc {
-        return Ok(award_jackpot(&mut slots_state.jackpots.grand, max_jackpot_payout));
+    /// Start per-epoch accounting reports with epochs of
+    /// `epoch_length_secs` (admin only). The first epoch opens now.
+    pub fn init_epoch_reports(
+        ctx: Context<InitEpochReports>,
+        epoch_length_secs: u32,
+    ) -> Result<()> {
+        instructions::admin::init_epoch_reports(ctx, epoch_length_secs)
     }
 
-    Ok(0)
-}
+    /// Permissionless: once the current epoch report page is full, create
+    /// the next one. It inherits the open epoch and its opening totals.
+    pub fn open_epoch_report_page(ctx: Context<OpenEpochReportPage>) -> Result<()> {
+        instructions::admin::open_epoch_report_page(ctx)
+    }
+
+    /// Permissionless: close the previous epoch if it has ended, without
+    /// waiting for a settle.
+    pub fn close_epoch(ctx: Context<CloseEpoch>) -> Result<()> {
+        instructions::admin::close_epoch(ctx)
+    }
+
+    /// Create wager race `race_id` (admin only; ids are sequential from
+    /// 1). `prize_budget` is earmarked from the pool until the race is
+    /// finalized or cancelled.
+    pub fn create_wager_race(
+        ctx: Context<CreateWagerRace>,
+        race_id: u32,
+        start_slot: u64,
+        end_slot: u64,
+        prize_budget: u64,
+        prize_split_bps: [u16; WAGER_RACE_BOARD_SIZE],
+    ) -> Result<()> {
+        instructions::jackpot::create_wager_race(
+            ctx,
+            race_id,
+            start_slot,
+            end_slot,
+            prize_budget,
+            prize_split_bps,
+        )
+    }
+
+    /// Permissionless, once the race has ended: credit each board place's
+    /// prize to the player's claimable balance. Pass the PlayerState of
+    /// every ranked entry, in board order, as writable remaining accounts.
+    /// Shares of empty places return to the pool.
+    pub fn finalize_wager_race<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeWagerRace<'info>>,
+    ) -> Result<()> {
+        instructions::jackpot::finalize_wager_race(ctx)
+    }
+
+    /// Cancel an unfinalized wager race (admin only). No prizes are paid
+    /// and the whole budget returns to the pool.
+    pub fn cancel_wager_race(ctx: Context<CancelWagerRace>) -> Result<()> {
+        instructions::jackpot::cancel_wager_race(ctx)
+    }
 
-/// Award a jackpot if and only if the pool's `amount`
-/// is <= `max_jackpot_payout`. Otherwise, do not award.
-fn award_jackpot(pool: &mut JackpotPool, max_jackpot_payout: u64) -> u64 {
-    if pool.amount == 0 {
-        return 0;
+    /// Create the leaderboard of `epoch` (permissionless; the payer funds
+    /// the rent). Only the current epoch or a later one.
+    pub fn create_leaderboard(ctx: Context<CreateLeaderboard>, epoch: u64) -> Result<()> {
+        instructions::jackpot::create_leaderboard(ctx, epoch)
     }
 
-    // If we can't afford to pay the full jackpot, treat as no hit.
-    if pool.amount > max_jackpot_payout {
-        return 0;
+    /// Set the leaderboard epoch length (admin only). Renumbers epochs:
+    /// boards created under the old length stop receiving updates.
+    pub fn set_leaderboard_epoch(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+        instructions::jackpot::set_leaderboard_epoch(ctx, slots)
     }
 
-    let amount = pool.amount;
-    pool.amount = pool.seed;
-    amount
+    /// Create the symbol meta table with its first entries (admin only).
+    /// Nothing reads the table yet, so this one skips the timelock.
+    pub fn init_symbol_meta(
+        ctx: Context<InitSymbolMeta>,
+        entries: [SymbolEntry; SYMBOL_COUNT],
+    ) -> Result<()> {
+        instructions::admin::init_symbol_meta(ctx, entries)
+    }
+
+    /// Propose a new symbol meta table (admin only). It becomes active
+    /// after SYMBOL_META_TIMELOCK_SLOTS via activate_symbol_meta. A new
+    /// proposal replaces the pending one.
+    pub fn propose_symbol_meta(
+        ctx: Context<UpdateSymbolMeta>,
+        entries: [SymbolEntry; SYMBOL_COUNT],
+    ) -> Result<()> {
+        instructions::admin::propose_symbol_meta(ctx, entries)
+    }
+
+    /// Install the pending symbol meta once its timelock has passed
+    /// (admin only).
+    pub fn activate_symbol_meta(ctx: Context<UpdateSymbolMeta>) -> Result<()> {
+        instructions::admin::activate_symbol_meta(ctx)
+    }
+
+    /// Clear a settle hook slot immediately (admin only). Removing a hook
+    /// can only reduce what settle does, so it skips the timelock.
+    pub fn remove_settle_hook(ctx: Context<EmergencyPause>, index: u8) -> Result<()> {
+        instructions::admin::remove_settle_hook(ctx, index)
+    }
+
+    /// Turn beta (allowlist-only) mode on or off (admin only).
+    pub fn set_beta_mode(ctx: Context<EmergencyPause>, enabled: bool) -> Result<()> {
+        instructions::admin::set_beta_mode(ctx, enabled)
+    }
+
+    /// Choose how same-batch jackpot hits are paid (admin only).
+    pub fn set_jackpot_batch_mode(
+        ctx: Context<EmergencyPause>,
+        mode: JackpotBatchMode,
+    ) -> Result<()> {
+        instructions::jackpot::set_jackpot_batch_mode(ctx, mode)
+    }
+
+    /// Set the stake that gets full jackpot odds (admin only); smaller
+    /// stakes get a proportional share. 0 gives every stake full odds.
+    /// Pending spins keep the odds they committed with.
+    pub fn set_jackpot_qualifying_bet(
+        ctx: Context<EmergencyPause>,
+        qualifying_bet: u64,
+    ) -> Result<()> {
+        instructions::jackpot::set_jackpot_qualifying_bet(ctx, qualifying_bet)
+    }
+
+    /// Set the per-spin oracle cost and who bears it (admin only).
+    /// `cost_lamports = 0` stops tracking.
+    pub fn set_oracle_cost(
+        ctx: Context<EmergencyPause>,
+        cost_lamports: u64,
+        mode: OracleCostMode,
+    ) -> Result<()> {
+        instructions::admin::set_oracle_cost(ctx, cost_lamports, mode)
+    }
+
+    /// Set the oracle queue whitelist and latency margin (admin only). An
+    /// all-empty list turns the queue check off; see oracle_queue.rs.
+    pub fn set_oracle_queues(
+        ctx: Context<EmergencyPause>,
+        queues: [OracleQueueEntry; MAX_ORACLE_QUEUES],
+        margin_slots: u32,
+    ) -> Result<()> {
+        instructions::admin::set_oracle_queues(ctx, queues, margin_slots)
+    }
+
+    /// Configure the oracle-health gate (admin only). Commits are refused
+    /// while no reveal has landed for `max_reveal_gap_slots` and a commit
+    /// has waited more than `expected_reveal_slots`. 0 disables the gate.
+    pub fn set_oracle_health_gate(
+        ctx: Context<EmergencyPause>,
+        max_reveal_gap_slots: u64,
+        expected_reveal_slots: u64,
+    ) -> Result<()> {
+        instructions::admin::set_oracle_health_gate(
+            ctx,
+            max_reveal_gap_slots,
+            expected_reveal_slots,
+        )
+    }
+
+    /// Permissionless: record whether the oracle-health gate is open, so
+    /// an outage shows up as an OracleGateChanged event. Commits check the
+    /// gate themselves; this only makes the state visible.
+    pub fn check_oracle_health(ctx: Context<FlushStats>) -> Result<OracleHealth> {
+        instructions::admin::check_oracle_health(ctx)
+    }
+
+    /// Read-only: oracle-health assessment at the current slot.
+    pub fn get_oracle_health(ctx: Context<ViewState>) -> Result<OracleHealthStatus> {
+        instructions::admin::get_oracle_health(ctx)
+    }
+
+    /// Permissionless: record whether the pool can take a public-tier
+    /// spin, emitting PoolBelowRequired / PoolRestored on a change.
+    /// Returns the deposit that would reopen the table (0 if open).
+    pub fn check_pool_level(ctx: Context<FlushStats>) -> Result<u64> {
+        instructions::pool::check_pool_level(ctx)
+    }
+
+    /// Choose how a jackpot tier returns to its seed after a hit (admin
+    /// only). `rebuild_bps` is the slice of each bet routed to the tier
+    /// while it rebuilds under FeeRebuilt.
+    pub fn set_jackpot_reseed_policy(
+        ctx: Context<EmergencyPause>,
+        tier: u8,
+        policy: ReseedPolicy,
+        rebuild_bps: u16,
+    ) -> Result<()> {
+        instructions::jackpot::set_jackpot_reseed_policy(ctx, tier, policy, rebuild_bps)
+    }
+
+    /// Set the jackpot tail-risk ceilings (admin only). `max_hit_ppm` may
+    /// not exceed jackpot_bounds::ABSOLUTE_MAX_TIER_HIT_PPM.
+    pub fn set_jackpot_bounds(
+        ctx: Context<EmergencyPause>,
+        max_hit_ppm: u32,
+        max_cost_bps: u16,
+        reference_bet: u64,
+    ) -> Result<()> {
+        instructions::jackpot::set_jackpot_bounds(ctx, max_hit_ppm, max_cost_bps, reference_bet)
+    }
+
+    /// Replace the jackpot hit weights (admin only). The table must pass
+    /// the configured tail-risk bounds; see jackpot_bounds.rs.
+    pub fn set_jackpot_weights(
+        ctx: Context<EmergencyPause>,
+        weights: JackpotWeights,
+    ) -> Result<()> {
+        instructions::jackpot::set_jackpot_weights(ctx, weights)
+    }
+
+    /// Propose jackpot weights outside the configured bounds (admin
+    /// only). They go live after JACKPOT_WEIGHTS_TIMELOCK_SLOTS via
+    /// activate_jackpot_weights. The absolute hit ceiling still applies.
+    pub fn propose_jackpot_weights(
+        ctx: Context<EmergencyPause>,
+        weights: JackpotWeights,
+    ) -> Result<()> {
+        instructions::jackpot::propose_jackpot_weights(ctx, weights)
+    }
+
+    /// Install the pending jackpot weights once their timelock has passed
+    /// (admin only).
+    pub fn activate_jackpot_weights(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::jackpot::activate_jackpot_weights(ctx)
+    }
+
+    /// Create the next allowlist page (admin only). Pages are sequential.
+    pub fn init_allowlist_page(ctx: Context<InitAllowlistPage>, page: u8) -> Result<()> {
+        instructions::admin::init_allowlist_page(ctx, page)
+    }
+
+    /// Add a player to an allowlist page (admin only).
+    /// A full page rejects; the caller moves on to the next page.
+    pub fn add_to_allowlist(ctx: Context<UpdateAllowlist>, player: Pubkey) -> Result<()> {
+        instructions::admin::add_to_allowlist(ctx, player)
+    }
+
+    /// Remove a player from an allowlist page (admin only).
+    pub fn remove_from_allowlist(ctx: Context<UpdateAllowlist>, player: Pubkey) -> Result<()> {
+        instructions::admin::remove_from_allowlist(ctx, player)
+    }
+
+    /// Pause everything, or resume everything if anything is paused
+    /// (admin only). See pause.rs.
+    pub fn emergency_pause(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::emergency_pause(ctx)
+    }
+
+    /// Set the pause flags to `flags`, a mask of pause::PAUSE_* (admin
+    /// only).
+    pub fn set_pause_flags(ctx: Context<EmergencyPause>, flags: u8) -> Result<()> {
+        instructions::admin::set_pause_flags(ctx, flags)
+    }
+
+    /// Prove the authority key is still alive (admin only). Any admin
+    /// instruction does the same; this one does nothing else.
+    pub fn heartbeat(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::heartbeat(ctx)
+    }
+
+    /// Propose `new_authority` as the next authority (admin only). Nothing
+    /// changes until it signs accept_authority, so a mistyped key cannot
+    /// lock the program out. A new proposal replaces the pending one.
+    pub fn propose_authority(ctx: Context<EmergencyPause>, new_authority: Pubkey) -> Result<()> {
+        instructions::admin::propose_authority(ctx, new_authority)
+    }
+
+    /// Become the authority. Signed by the pending authority.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        instructions::admin::accept_authority(ctx)
+    }
+
+    /// Withdraw the pending authority proposal (admin only).
+    pub fn cancel_authority_transfer(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::cancel_authority_transfer(ctx)
+    }
+
+    /// Set the recovery authority and the inactivity window after which
+    /// it may claim the authority role (admin only). `Pubkey::default()`
+    /// disables recovery.
+    pub fn set_recovery_config(
+        ctx: Context<EmergencyPause>,
+        recovery_authority: Pubkey,
+        inactivity_slots: u64,
+    ) -> Result<()> {
+        instructions::admin::set_recovery_config(ctx, recovery_authority, inactivity_slots)
+    }
+
+    /// Take over as authority after the authority has been inactive for
+    /// the whole recovery window. Signed by the recovery authority, which
+    /// is cleared and has to be set again by the new authority.
+    pub fn claim_recovery(ctx: Context<ClaimRecovery>) -> Result<()> {
+        instructions::admin::claim_recovery(ctx)
+    }
+
+    /// Set the community share of each spin's house take (admin only).
+    pub fn set_community_bps(ctx: Context<EmergencyPause>, community_bps: u16) -> Result<()> {
+        instructions::pool::set_community_bps(ctx, community_bps)
+    }
+
+    /// Create the community vault. Anyone may pay for it.
+    pub fn init_community_vault(ctx: Context<InitCommunityVault>) -> Result<()> {
+        instructions::pool::init_community_vault(ctx)
+    }
+
+    /// Permissionless: move the accrued community balance from the
+    /// treasury into the community vault.
+    pub fn sweep_community(ctx: Context<SweepCommunity>) -> Result<()> {
+        instructions::pool::sweep_community(ctx)
+    }
+
+    /// Replace the vault's withdrawal allowlist (community authority only).
+    pub fn set_community_destinations(
+        ctx: Context<CommunityAdmin>,
+        destinations: [Pubkey; MAX_COMMUNITY_DESTINATIONS],
+    ) -> Result<()> {
+        instructions::pool::set_community_destinations(ctx, destinations)
+    }
+
+    /// Withdraw from the community vault to an allowlisted destination
+    /// (community authority only). The vault keeps its rent reserve.
+    pub fn withdraw_community(ctx: Context<WithdrawCommunity>, amount: u64) -> Result<()> {
+        instructions::pool::withdraw_community(ctx, amount)
+    }
+
+    /// Hand the community role to a new key, e.g. a governance PDA
+    /// (community authority only).
+    pub fn set_community_authority(
+        ctx: Context<SetCommunityAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        instructions::pool::set_community_authority(ctx, new_authority)
+    }
+
+    /// Set the referrers' share of the expected house edge (admin only).
+    /// Applies to settles from now on.
+    pub fn set_referral_bps(ctx: Context<EmergencyPause>, referral_bps: u16) -> Result<()> {
+        instructions::admin::set_referral_bps(ctx, referral_bps)
+    }
+
+    /// Lock the player out of new spins until `until_slot`. Only ever
+    /// extends; see responsible_gaming.rs.
+    pub fn set_self_exclusion(ctx: Context<SetPlayerLimits>, until_slot: u64) -> Result<()> {
+        instructions::player::set_self_exclusion(ctx, until_slot)
+    }
+
+    /// Set the player's session loss limit (0 = none). Raising or
+    /// removing it waits for a window without losses.
+    pub fn set_loss_limit(ctx: Context<SetPlayerLimits>, loss_limit_lamports: u64) -> Result<()> {
+        instructions::player::set_loss_limit(ctx, loss_limit_lamports)
+    }
+
+    /// Set the window player loss limits count over (admin only).
+    pub fn set_loss_window(ctx: Context<EmergencyPause>, slots: u64) -> Result<()> {
+        instructions::admin::set_loss_window(ctx, slots)
+    }
+
+    /// Name the player's referrer, once and before their first spin.
+    /// Creates the referrer's earnings PDA if needed. See referrals.rs.
+    pub fn set_referrer(ctx: Context<SetReferrer>, referrer: Pubkey) -> Result<()> {
+        instructions::player::set_referrer(ctx, referrer)
+    }
+
+    /// Pay the referrer everything their referrals have accrued.
+    pub fn claim_referral_earnings(ctx: Context<ClaimReferralEarnings>) -> Result<()> {
+        instructions::player::claim_referral_earnings(ctx)
+    }
+
+    /// Configure the VIP tier (admin only). `rebate_bps = 0` turns it
+    /// off; otherwise the rebate must stay within the house edge. See
+    /// vip.rs.
+    pub fn set_vip_config(ctx: Context<EmergencyPause>, config: VipConfig) -> Result<()> {
+        instructions::admin::set_vip_config(ctx, config)
+    }
+
+    /// Player withdraws the VIP rebates they have accrued.
+    pub fn claim_rebate(ctx: Context<ClaimRebate>) -> Result<()> {
+        instructions::player::claim_rebate(ctx)
+    }
+
+    /// Configure loyalty points and free spins (admin only). Points accrue
+    /// at `points_per_lamport_bps` of each settled wager; `free_spin_bet`
+    /// 0 turns redemption off. Credits already redeemed keep their size.
+    pub fn set_loyalty_config(
+        ctx: Context<EmergencyPause>,
+        points_per_lamport_bps: u16,
+        free_spin_point_cost: u64,
+        free_spin_bet: u64,
+    ) -> Result<()> {
+        instructions::admin::set_loyalty_config(
+            ctx,
+            points_per_lamport_bps,
+            free_spin_point_cost,
+            free_spin_bet,
+        )
+    }
+
+    /// Burn `free_spin_point_cost` loyalty points for a house-funded
+    /// stake of up to `free_spin_bet` on the next request_spin. See
+    /// loyalty.rs.
+    pub fn redeem_free_spin(ctx: Context<RedeemFreeSpin>) -> Result<()> {
+        instructions::player::redeem_free_spin(ctx)
+    }
+
+    /// Set the operator fee taken from every bet (admin only). Applies
+    /// to commits from now on.
+    pub fn set_operator_fee(ctx: Context<EmergencyPause>, operator_fee_bps: u16) -> Result<()> {
+        instructions::pool::set_operator_fee(ctx, operator_fee_bps)
+    }
+
+    /// Move the booked operator fees into the fee vault, then pay
+    /// `amount` of the vault to the authority; 0 pays all of it (admin
+    /// only). The pool, min_pool_threshold and the jackpots are not
+    /// touched. The authority pays the vault's rent if it has none yet, as
+    /// for states initialized before the fee vault existed.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        instructions::pool::withdraw_fees(ctx, amount)
+    }
+
+    /// QA only: make the next settle of `player` land on `symbols` and,
+    /// unless `jackpot_tier` is qa::NO_FORCED_JACKPOT, award that jackpot
+    /// (admin only). Needs the `qa` feature and a test network guard.
+    /// Anchor's dispatcher ignores cfg on a handler, so the handler exists
+    /// in every build and refuses to run without the feature.
+    pub fn force_outcome(
+        ctx: Context<ForceOutcome>,
+        player: Pubkey,
+        symbols: [u8; 3],
+        jackpot_tier: u8,
+    ) -> Result<()> {
+        instructions::spin::force_outcome(ctx, player, symbols, jackpot_tier)
+    }
+
+    /// Assign a player's limit tier (admin only; 0 = public). Applies to
+    /// the next commit; a pending spin keeps settling under the new tier's
+    /// payout cap, which the pool floor check already covers.
+    pub fn set_player_tier(ctx: Context<SetPlayerTier>, player: Pubkey, tier: u8) -> Result<()> {
+        instructions::player::set_player_tier(ctx, player, tier)
+    }
+
+    /// Set the limits of tier 1..=MAX_PLAYER_TIERS (admin only).
+    pub fn set_tier_limits(
+        ctx: Context<EmergencyPause>,
+        tier: u8,
+        limits: TierLimits,
+    ) -> Result<()> {
+        instructions::admin::set_tier_limits(ctx, tier, limits)
+    }
+
+    /// Start a deploy (admin only): pause commits and settles and record
+    /// the running PROGRAM_VERSION. Stays frozen until post_upgrade_thaw.
+    pub fn pre_upgrade_freeze(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::pre_upgrade_freeze(ctx)
+    }
+
+    /// Finish a deploy (admin only) and resume. With `compatible`, spins
+    /// committed before the upgrade settle normally under the new code.
+    /// Otherwise they can no longer settle: those passed as remaining
+    /// accounts (PlayerState, writable) are refunded to the players'
+    /// claimable balance here, any others via admin_void_spin.
+    pub fn post_upgrade_thaw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyPause<'info>>,
+        compatible: bool,
+    ) -> Result<()> {
+        instructions::admin::post_upgrade_thaw(ctx, compatible)
+    }
+
+    /// Pre-announce when a paused game resumes (admin only). The first
+    /// instruction at or after `at_slot` clears the pause.
+    pub fn schedule_resume(ctx: Context<EmergencyPause>, at_slot: u64) -> Result<()> {
+        instructions::admin::schedule_resume(ctx, at_slot)
+    }
+
+    /// Cancel a scheduled resume before it triggers (admin only).
+    pub fn cancel_scheduled_resume(ctx: Context<EmergencyPause>) -> Result<()> {
+        instructions::admin::cancel_scheduled_resume(ctx)
+    }
+}
+
+// =========================
+// INTERNAL HELPERS
+// =========================
+
+/// Admin gate: `signer` must be the authority. Also records the action
+/// for the dead-man's switch, so every admin instruction is a heartbeat.
+pub(crate) fn require_authority(slots_state: &mut SlotsState, signer: Pubkey) -> Result<()> {
+    require!(signer == slots_state.authority, ErrorCode::Unauthorized);
+    slots_state.last_authority_action_slot = Clock::get()?.slot;
+    Ok(())
+}
+
+/// Update jackpot pool accounting (contribution from bet).
+/// Returns the [mini, major, grand] amounts added.
+pub(crate) fn apply_jackpot_contributions(
+    slots_state: &mut SlotsState,
+    bet_amount: u64,
+) -> Result<[u64; 3]> {
+    let active = slots_state.bootstrap_active;
+    let policies = slots_state.jackpot_reseed_policies;
+    let rebuild_bps = slots_state.jackpot_rebuild_bps;
+    // A rebuilding tier takes its rebuild slice instead of the normal
+    // contribution until it is back at its seed.
+    let contrib = |pool: &JackpotPool, tier: usize| {
+        if reseed::is_rebuilding(pool, policies[tier]) {
+            return reseed::rebuild_contribution(pool, bet_amount, rebuild_bps[tier]);
+        }
+        let bps = bootstrap::effective_contrib_bps(pool.contrib_bps, active);
+        ((bet_amount as u128).saturating_mul(bps as u128) / 10_000) as u64
+    };
+    let mini_contrib = contrib(&slots_state.jackpots.mini, 0);
+    let major_contrib = contrib(&slots_state.jackpots.major, 1);
+    let grand_contrib = contrib(&slots_state.jackpots.grand, 2);
+
+    slots_state.jackpots.mini.amount = slots_state
+        .jackpots
+        .mini
+        .amount
+        .checked_add(mini_contrib)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.jackpots.major.amount = slots_state
+        .jackpots
+        .major
+        .amount
+        .checked_add(major_contrib)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.jackpots.grand.amount = slots_state
+        .jackpots
+        .grand
+        .amount
+        .checked_add(grand_contrib)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let contributions = [mini_contrib, major_contrib, grand_contrib];
+    must_hit::refresh(slots_state);
+    if contributions.iter().any(|&c| c > 0) {
+        let jackpots = &slots_state.jackpots;
+        emit!(JackpotContribution {
+            contributions,
+            new_amounts: [
+                jackpots.mini.amount,
+                jackpots.major.amount,
+                jackpots.grand.amount,
+            ],
+        });
+    }
+    Ok(contributions)
+}
+
+/// Append the settle's receipt if the caller passed the tree.
+pub(crate) fn append_receipt_if_present(
+    receipt_tree: &Option<AccountLoader<ReceiptTree>>,
+    player: &Pubkey,
+    bet: u64,
+    outcome: &game_math::SpinOutcome,
+    vrf_hash: &[u8; 32],
+) -> Result<()> {
+    let Some(loader) = optional::present(receipt_tree.as_ref(), "receipt_tree") else {
+        return Ok(());
+    };
+    let mut tree = loader.load_mut()?;
+    let leaf_index = tree.next_index;
+    let leaf = receipts::receipt_leaf(
+        player,
+        leaf_index,
+        bet,
+        outcome.symbols,
+        outcome.base_payout,
+        outcome.jackpot_payout,
+        vrf_hash,
+    );
+    tree.append(leaf)?;
+
+    emit!(ReceiptAppended {
+        leaf_index,
+        leaf,
+        player: *player,
+        bet,
+        symbols: outcome.symbols,
+        base_payout: outcome.base_payout,
+        jackpot_payout: outcome.jackpot_payout,
+        vrf_hash: *vrf_hash,
+        root: tree.root,
+    });
+    Ok(())
+}
+
+/// Shared body of emergency_pause and set_pause_flags.
+fn write_pause_flags(ctx: Context<EmergencyPause>, flags: u8) -> Result<()> {
+    let slots_state = &mut ctx.accounts.slots_state;
+    let authority = &ctx.accounts.authority;
+
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    require_authority(slots_state, authority.key())?;
+    require!(slots_state.frozen_version == 0, ErrorCode::UpgradeFrozen);
+
+    slots_state.pause_flags = flags;
+    // A manual change supersedes any scheduled resume.
+    slots_state.scheduled_resume_slot = 0;
+
+    emit!(EmergencyAction {
+        pause_flags: flags,
+        authority: authority.key(),
+    });
+
+    refresh_metrics_if_present(&ctx.accounts.metrics, slots_state, Clock::get()?.slot)?;
+
+    Ok(())
+}
+
+/// Refresh the metrics account if the caller passed it.
+pub(crate) fn refresh_metrics_if_present(
+    metrics_account: &Option<AccountLoader<Metrics>>,
+    slots_state: &SlotsState,
+    slot: u64,
+) -> Result<()> {
+    if let Some(loader) = optional::present(metrics_account.as_ref(), "metrics") {
+        metrics::snapshot(&mut *loader.load_mut()?, slots_state, slot);
+    }
+    Ok(())
+}
+
+/// Result of resolving a pending spin, before any lamports move.
+#[derive(Debug)]
+pub(crate) struct Settlement {
+    pub(crate) bet_amount: u64,
+    /// The player's own money in the bet: 0 on a free spin.
+    pub(crate) staked: u64,
+    pub(crate) outcome: game_math::SpinOutcome,
+    pub(crate) total_payout: u64,
+    pub(crate) fee_rebate: u64,
+    pub(crate) fee_overflow: u64,
+    /// sha256 of the revealed bytes, as in SpinSettled.
+    pub(crate) vrf_hash: [u8; 32],
+}
+
+impl Settlement {
+    /// Lamports owed to the player out of the treasury.
+    pub(crate) fn owed(&self) -> u64 {
+        self.total_payout
+            .saturating_add(self.fee_rebate)
+            .saturating_add(self.fee_overflow)
+    }
+}
+
+/// Resolve the player's pending spin against its randomness: checks,
+/// outcome, jackpot reset, rebate, pool accounting, stats and settle
+/// events. A randomness account re-seeded since the commit is refused
+/// (RandomnessSeedChanged, see randomness.rs): the stake stays escrowed
+/// until admin_void_spin refunds it or cancel_spin forfeits it. The
+/// pending spin is cleared; the caller moves `owed()` lamports and then
+/// calls finish_settle. `split` is a Split-mode batch entry's
+/// jackpot terms (see batch_settle.rs); a split hit only takes its share
+/// out of the tier, and the batch reseeds it afterwards.
+pub(crate) fn settle_pending(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    user: &Pubkey,
+    randomness_ai: &AccountInfo,
+    rtp_history: &Option<AccountLoader<RtpHistory>>,
+    context_index: u8,
+    split: Option<&SplitAmounts>,
+) -> Result<Settlement> {
+    require!(slots_state.initialized, ErrorCode::Uninitialized);
+    pause::ensure_not_paused(slots_state, PAUSE_SETTLEMENT)?;
+    require!(player_state.has_pending_spin, ErrorCode::NoPendingSpin);
+    ensure_settle_version(slots_state, player_state)?;
+
+    // Ensure we are settling the correct randomness account; it is read
+    // below.
+    require_keys_eq!(
+        player_state.randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+
+    let bet_amount = player_state.pending_bet_amount;
+    require!(bet_amount > 0, ErrorCode::InvalidBetAmount);
+    // What the player really staked: nothing on a free spin.
+    let wager = booked_wager(player_state);
+    let free_spin = player_state.pending_bonus_spin;
+    let spins = multi_spin::count(player_state);
+
+    // =========================
+    // READ VRF RANDOMNESS
+    // =========================
+    let clock = Clock::get()?;
+    let check = RandomnessCheck::for_settle(*user, player_state, clock.slot);
+    let randomness_data = check.load(randomness_ai)?;
+
+    // The 32 revealed bytes of the committed generation, however many
+    // slots after the reveal this settle lands. Same pubkey is not
+    // enough: a re-seeded account is refused.
+    let random_bytes = check.reveal(randomness_ai, &randomness_data)?;
+    oracle_health::note_reveal(slots_state, clock.slot);
+    // Archived with the outcome so a reveal produced later can be checked.
+    let vrf_hash = game_math::vrf_hash(&random_bytes);
+
+    // =========================
+    // GLOBAL CAPS & POOL LIMITS
+    // =========================
+    // The base win is paid from base-pool headroom, never from the
+    // jackpot reserves (see accounting.rs); a jackpot pays from its own
+    // tier, within what the payout cap leaves after the base win.
+    // Only this spin's reservation and unreserved headroom: spins that
+    // committed earlier keep theirs whatever the settle order.
+    let available_pool = reservations::headroom_for(slots_state, player_state)?;
+    refresh_bootstrap(slots_state);
+    // Cap and jackpots as of commit, so mid-flight limit changes only
+    // apply to later spins.
+    let (cap_by_house, mut jackpots) = tiers::committed_terms(slots_state, player_state);
+    if let Some(split) = split {
+        batch_settle::apply_split(&mut jackpots, split);
+    }
+    let limits = PayoutLimits::for_settle(available_pool, cap_by_house);
+    let forced_jackpots = must_hit::forced_tiers(slots_state, player_state, split);
+
+    // =========================
+    // SYMBOLS + PAYOUTS (see game_math::derive_outcome)
+    // =========================
+    let (outcome, detail) = draw_pending_outcome(
+        player_state,
+        &random_bytes,
+        limits,
+        &jackpots,
+        forced_jackpots,
+        &slots_state.reel_weights,
+        &slots_state.paytable,
+        slots_state.wild_symbol,
+    )?;
+
+    // QA builds: a forced outcome replaces the drawn one, same caps. It
+    // pays a single line of a single spin.
+    #[cfg(feature = "qa")]
+    let (outcome, detail) = if player_state.forced_outcome.active {
+        let forced = core::mem::take(&mut player_state.forced_outcome);
+        let forced_outcome = game_math::forced_outcome(
+            forced.symbols,
+            (forced.jackpot_tier != qa::NO_FORCED_JACKPOT).then_some(forced.jackpot_tier),
+            multi_spin::unit_bet(player_state),
+            limits,
+            &jackpots,
+            &slots_state.paytable,
+            slots_state.wild_symbol,
+        );
+        (forced_outcome, None)
+    } else {
+        (outcome, detail)
+    };
+
+    // Traffic EWMA for jackpot ETA estimates.
+    risk::record_spin(
+        &mut slots_state.traffic_spin_weight,
+        &mut slots_state.traffic_wager_weight,
+        &mut slots_state.traffic_last_update_ts,
+        clock.unix_timestamp,
+        slots_state.traffic_half_life_secs,
+        bet_amount,
+    );
+
+    let base_payout = outcome.base_payout;
+    let jackpot_payout = outcome.jackpot_payout;
+    let fee_overflow = fee_overflow(slots_state, &outcome, cap_by_house)?;
+
+    // A jackpot that paid out restarts from its seed, or from zero if
+    // the tier rebuilds from bets.
+    if let Some(tier) = outcome.jackpot_tier {
+        let policy = slots_state.jackpot_reseed_policies[tier as usize];
+        let pool = jackpot_pool_mut(&mut slots_state.jackpots, tier);
+        pool.amount = if split.is_some_and(|split| split[tier as usize].is_some()) {
+            pool.amount
+                .checked_sub(jackpot_payout)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            reseed::amount_after_hit(pool, policy)
+        };
+        let reset_to_seed = pool.amount;
+        must_hit::clear(slots_state, tier);
+        slots_state.jackpot_hits = slots_state.jackpot_hits.saturating_add(1);
+        slots_state.jackpot_paid = slots_state
+            .jackpot_paid
+            .checked_add(jackpot_payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(JackpotHit {
+            user: *user,
+            tier,
+            amount: jackpot_payout,
+            reset_to_seed,
+            randomness_account: randomness_ai.key(),
+            spin_nonce: player_state.spin_nonce,
+            forced: outcome.jackpot_forced,
+        });
+    }
+
+    let total_payout = base_payout
+        .checked_add(jackpot_payout)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Winners get the fee rebate on top, funded from fee_balance only.
+    let fee_rebate = if total_payout > 0 {
+        winner_fee_rebate(slots_state, player_state, clock.unix_timestamp)?
+    } else {
+        0
+    };
+
+    if total_payout > 0 {
+        require!(
+            slots_state.total_pool >= total_payout,
+            ErrorCode::InsufficientPool
+        );
+        slots_state.total_payout = slots_state
+            .total_payout
+            .checked_add(total_payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+        slots_state.total_pool = slots_state
+            .total_pool
+            .checked_sub(total_payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    community::accrue(slots_state, wager, total_payout, clock.slot)?;
+    pool_level::refresh(slots_state);
+    feature_stats::record_paid(slots_state, Feature::BaseGame, base_payout)?;
+    feature_stats::record_paid(slots_state, Feature::Jackpots, jackpot_payout)?;
+    feature_stats::record_paid(
+        slots_state,
+        Feature::Promos,
+        fee_rebate.saturating_add(fee_overflow),
+    )?;
+
+    // =========================
+    // RTP STATS
+    // =========================
+    slots_state.total_spins = slots_state
+        .total_spins
+        .checked_add(spins as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.global_spin_counter = slots_state
+        .global_spin_counter
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Use saturating_sub so we never underflow if payouts exceed wagers.
+    slots_state.house_profit = slots_state
+        .total_wagered
+        .saturating_sub(slots_state.total_payout);
+
+    if slots_state.total_wagered > 0 {
+        slots_state.current_rtp_bps = ((slots_state.total_payout as u128)
+            .saturating_mul(10_000)
+            / (slots_state.total_wagered as u128)) as u32;
+    }
+
+    emit!(SpinSettled {
+        user: *user,
+        randomness_account: randomness_ai.key(),
+        symbols: outcome.symbols,
+        base_payout,
+        jackpot_payout,
+        total_payout,
+        fee_rebate,
+        vrf_hash,
+        vrf_prefix: game_math::vrf_prefix(&random_bytes),
+        version: SPIN_SETTLED_VERSION,
+        player_tier: player_state.tier,
+        fee_overflow,
+        spin_nonce: player_state.spin_nonce,
+        context_index,
+        match_count: outcome.match_count,
+        global_spin_counter: slots_state.global_spin_counter,
+        free_spin,
+    });
+    record_spin_proof(
+        *user,
+        player_state,
+        randomness_ai.key(),
+        &random_bytes,
+        context_index,
+    );
+    if let Some(detail) = &detail {
+        emit_drawn_detail(*user, player_state, detail, context_index);
+    }
+    // Every spin of a multi-spin commit may land the scatter.
+    let landed = match &detail {
+        Some(DrawnDetail::Spins(drawn)) => &drawn.symbols[..spins as usize],
+        _ => core::slice::from_ref(&outcome.symbols),
+    };
+    for symbols in landed {
+        scatter::award(
+            &slots_state.scatter,
+            player_state,
+            user,
+            *symbols,
+            context_index,
+        );
+    }
+    player_stats::record_settle(player_state, *user, spins, total_payout, clock.slot)?;
+    responsible_gaming::record_settle(
+        slots_state,
+        player_state,
+        wager,
+        total_payout,
+        clock.slot,
+    );
+    loyalty::accrue(slots_state, player_state, user, wager);
+
+    if let Some(history) = optional::present(rtp_history.as_ref(), "rtp_history") {
+        history
+            .load_mut()?
+            .record(clock.unix_timestamp, wager, total_payout);
+    }
+
+    emit!(RTPUpdate {
+        total_spins: slots_state.total_spins,
+        total_wagered: slots_state.total_wagered,
+        total_payout: slots_state.total_payout,
+        house_profit: slots_state.house_profit,
+        current_rtp_bps: slots_state.current_rtp_bps,
+    });
+
+    accounting::ensure_pool_split(slots_state)?;
+
+    // Clear pending spin
+    reservations::release(slots_state, player_state);
+    player_state.has_pending_spin = false;
+    player_state.pending_bet_amount = 0;
+    player_state.pending_lines = 0;
+    player_state.spins_remaining = 0;
+    player_state.pending_bonus_spin = false;
+    player_state.randomness_account = Pubkey::default();
+    slots_state.pending_spins = slots_state.pending_spins.saturating_sub(1);
+
+    Ok(Settlement {
+        bet_amount,
+        staked: wager,
+        outcome,
+        total_payout,
+        fee_rebate,
+        fee_overflow,
+        vrf_hash,
+    })
+}
+
+/// Post-transfer tail of a settle: receipt, metrics and, last of all,
+/// the partner hook.
+pub(crate) fn finish_settle<'info>(
+    slots_state: &SlotsState,
+    settlement: &Settlement,
+    user: &Pubkey,
+    receipt_tree: &Option<AccountLoader<ReceiptTree>>,
+    metrics_account: &Option<AccountLoader<Metrics>>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let Settlement {
+        bet_amount,
+        outcome,
+        total_payout,
+        vrf_hash,
+        ..
+    } = settlement;
+
+    append_receipt_if_present(receipt_tree, user, *bet_amount, outcome, vrf_hash)?;
+    refresh_metrics_if_present(metrics_account, slots_state, Clock::get()?.slot)?;
+
+    // Partner hook goes last, after every transfer and state change.
+    invoke_settle_hook(
+        slots_state,
+        remaining_accounts,
+        OnSpinSettledArgs {
+            user: *user,
+            bet: *bet_amount,
+            payout: *total_payout,
+            symbols: outcome.symbols,
+        },
+    )
+}
+
+/// Randomness generation a validated commit binds to.
+pub(crate) struct CommitTarget {
+    seed_slot: u64,
+    seed_slothash: [u8; 32],
+    current_slot: u64,
+    /// Terms the spin settles under.
+    payout_cap: u64,
+    jackpot_mask: u8,
+    /// Paylines played; the committed stake covers all of them.
+    lines: u8,
+    /// Spins played off the reveal, likewise.
+    spins: u8,
+    /// Outcomes the spin may derive from its reveal.
+    outcomes: u8,
+}
+
+/// Every check a spin commit needs before funds move: pause, bet limits,
+/// allowlist, randomness ownership and freshness, pool coverage.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn validate_commit(
+    slots_state: &mut SlotsState,
+    player_state: &PlayerState,
+    player: &Pubkey,
+    treasury_spendable: u64,
+    randomness_ai: &AccountInfo,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+    allowlist: Option<&Allowlist>,
+    oracle_queue: Option<&AccountInfo>,
+) -> Result<CommitTarget> {
+    pause::ensure_not_paused(slots_state, PAUSE_NEW_SPINS)?;
+    operators::ensure_not_operator(slots_state, player)?;
+    require!(bet_amount > 0, ErrorCode::InvalidBetAmount);
+    require!(
+        tiers::max_bet_covered(slots_state, slots_state.max_bet),
+        ErrorCode::BetLimitsExceedPayoutCap
+    );
+    require!(bet_amount >= slots_state.min_bet, ErrorCode::BetTooLow);
+    require!(
+        bet_amount <= tiers::bet_cap(slots_state, player_state.tier),
+        ErrorCode::BetTooHigh
+    );
+    outcome_budget::ensure_commit(slots_state, SPIN_OUTCOMES)?;
+    require!(!player_state.has_pending_spin, ErrorCode::PendingSpinExists);
+    let clock = Clock::get()?;
+    rate_limit::ensure_spin_gap(slots_state, player_state, clock.slot)?;
+    responsible_gaming::ensure_may_commit(slots_state, player_state, clock.slot)?;
+
+    // Beta launch: only allowlisted players may spin. The client passes
+    // the page holding the player; lookup is a binary search.
+    if slots_state.beta_mode {
+        let page = optional::required(allowlist, "allowlist")?;
+        require!(page.contains(player), ErrorCode::NotAllowlisted);
+    }
+
+    // Ensure randomness account matches parameter and is a Switchboard
+    // randomness account (see randomness.rs).
+    require_keys_eq!(
+        randomness_account,
+        randomness_ai.key(),
+        ErrorCode::RandomnessAccountMismatch
+    );
+    let check = RandomnessCheck::for_commit(*player, clock.slot);
+    let randomness_data = check.load(randomness_ai)?;
+
+    // Ensure pool is sufficiently funded to cover min threshold + max payout
+    // BEFORE accepting a new bet (fairness best-practice). A young pool
+    // only has to cover the reduced bootstrap cap.
+    refresh_bootstrap(slots_state);
+    let required_pool = pool_level::required_pool(slots_state, player_state.tier);
+    require!(
+        slots_state.total_pool >= required_pool,
+        ErrorCode::InsufficientPool
+    );
+    require!(
+        treasury_spendable >= required_pool,
+        ErrorCode::InsufficientPool
+    );
+
+    oracle_queue::ensure_allowed(slots_state, &randomness_data.queue, oracle_queue)?;
+
+    // Basic freshness check: allow same-slot or previous-slot commit.
+    check.ensure_fresh(randomness_ai, &randomness_data)?;
+    let seed_slot = randomness_data.seed_slot;
+    let current_slot = clock.slot;
+    seed_replay::ensure_unused(
+        player_state,
+        &UsedSeed {
+            randomness_account,
+            seed_slot,
+        },
+        randomness_data.reveal_slot,
+    )?;
+    oracle_health::ensure_healthy(slots_state, current_slot)?;
+
+    Ok(CommitTarget {
+        seed_slot,
+        seed_slothash: randomness_data.seed_slothash,
+        current_slot,
+        payout_cap: tiers::payout_cap(slots_state, player_state.tier),
+        jackpot_mask: tiers::jackpot_mask(slots_state, player_state.tier),
+        lines: 1,
+        spins: 1,
+        outcomes: SPIN_OUTCOMES,
+    })
+}
+
+/// Book a funded bet: accounting, jackpot contributions and the pending
+/// spin bound to its randomness generation.
+pub(crate) fn record_commit(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    player: &Pubkey,
+    randomness_account: Pubkey,
+    bet_amount: u64,
+    commit: CommitTarget,
+    context_index: u8,
+) -> Result<()> {
+    // Update accounting. The operator fee is booked next to the pool. A
+    // free spin's stake is notional and books nothing: no wager, fee or
+    // jackpot contribution.
+    let wager = if is_free_spin(player_state) {
+        0
+    } else {
+        bet_amount
+    };
+    slots_state.total_wagered = slots_state
+        .total_wagered
+        .checked_add(wager)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let operator_fee = operator_fee::take(slots_state, player_state, wager)?;
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_add(wager - operator_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Jackpot contributions (accounting only, still in treasury)
+    let jackpot_contribs = apply_jackpot_contributions(slots_state, wager)?;
+    feature_stats::record_wager(slots_state, wager, jackpot_contribs.iter().sum())?;
+
+    // Oracle cost: already collected with the bet if the player pays it.
+    let oracle_cost = oracle_cost::book_spin(slots_state)?;
+
+    // Store pending spin in player_state, bound to this randomness generation.
+    player_state.randomness_account = randomness_account;
+    player_state.pending_bet_amount = bet_amount;
+    player_state.has_pending_spin = true;
+    slots_state.pending_spins = slots_state.pending_spins.saturating_add(1);
+    player_state.commit_slot = commit.seed_slot;
+    player_state.commit_seed_slothash = commit.seed_slothash;
+    seed_replay::record(
+        player_state,
+        UsedSeed {
+            randomness_account,
+            seed_slot: commit.seed_slot,
+        },
+    );
+    player_state.pending_jackpot_contribs = jackpot_contribs;
+    player_state.commit_version = PROGRAM_VERSION;
+    player_state.commit_payout_cap = commit.payout_cap;
+    reservations::reserve(slots_state, commit.payout_cap)?;
+    player_state.commit_jackpot_mask = commit.jackpot_mask;
+    // A multi-spin commit rolls for the jackpot once per spin, so each
+    // roll gets the odds of one spin's stake. A multi-line spin rolls
+    // once, on the whole stake.
+    let spin_stake = bet_amount / commit.spins.max(1) as u64;
+    player_state.commit_jackpot_odds =
+        game_math::jackpot_odds(spin_stake, slots_state.jackpot_qualifying_bet);
+    player_state.pending_outcomes = commit.outcomes;
+    player_state.pending_lines = commit.lines;
+    player_state.spins_remaining = commit.spins;
+    player_stats::record_wager(player_state, wager)?;
+    player_state.spin_nonce = player_state
+        .spin_nonce
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.last_commit_slot = commit.current_slot;
+    player_state.last_request_slot = commit.current_slot;
+    oracle_health::note_commit(slots_state, commit.current_slot);
+    accounting::ensure_pool_split(slots_state)?;
+
+    emit!(SpinCommitted {
+        user: *player,
+        bet_amount,
+        randomness_account,
+        oracle_cost,
+        oracle_cost_mode: slots_state.oracle_cost_mode,
+        player_tier: player_state.tier,
+        spin_nonce: player_state.spin_nonce,
+        context_index,
+        operator_fee,
+    });
+
+    Ok(())
+}
+
+/// Close the open epoch into the report page if the caller passed it and
+/// the epoch has ended.
+pub(crate) fn close_epoch_if_present(
+    page: Option<&mut EpochReportPage>,
+    slots_state: &SlotsState,
+) -> Result<()> {
+    let Some(page) = optional::present(page, "epoch_report_page") else {
+        return Ok(());
+    };
+    let now_ts = Clock::get()?.unix_timestamp;
+    if let Some(report) = epoch_reports::close_if_due(page, slots_state, now_ts) {
+        emit!(EpochClosed {
+            page: page.page,
+            epoch: report.epoch,
+            totals: report.totals,
+        });
+    }
+    Ok(())
+}
+
+/// Credit the referrer of a resolved settle. Their earnings PDA is
+/// required when the player has one.
+pub(crate) fn accrue_referral_if_present(
+    slots_state: &mut SlotsState,
+    player_state: &PlayerState,
+    earnings: Option<&mut ReferralEarnings>,
+    user: &Pubkey,
+    settlement: &Settlement,
+) -> Result<()> {
+    if player_state.referrer == Pubkey::default() {
+        return Ok(());
+    }
+    let earnings = optional::required(earnings, "referral_earnings")?;
+    referrals::accrue(slots_state, earnings, user, settlement.bet_amount)?;
+    pool_level::refresh(slots_state);
+    Ok(())
+}
+
+/// Count a resolved settle towards the wager race if the caller passed it.
+pub(crate) fn record_wager_race_if_present(
+    race: Option<&mut WagerRace>,
+    player_state: &mut PlayerState,
+    settlement: &Settlement,
+) -> Result<()> {
+    let Some(race) = optional::present(race, "wager_race") else {
+        return Ok(());
+    };
+    wager_race::record(race, player_state, settlement.bet_amount, Clock::get()?.slot)?;
+    Ok(())
+}
+
+/// Rebate a lost stake to a VIP player if the caller passed their token
+/// account. The context has already pinned its mint and owner. The
+/// player's own settle records the account it passed, or none; a
+/// `cranked` one must pass the recorded account.
+pub(crate) fn accrue_vip_rebate_if_present(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    token_account: Option<&Account<TokenAccount>>,
+    user: &Pubkey,
+    settlement: &Settlement,
+    cranked: bool,
+) -> Result<()> {
+    let Settlement {
+        staked,
+        total_payout,
+        ..
+    } = settlement;
+    let config = slots_state.vip_config;
+    let token_account = if cranked && config.enabled() {
+        optional::recorded(token_account, &player_state.vip_token_account, "vip_token_account")?
+    } else {
+        optional::present(token_account, "vip_token_account")
+    };
+    if token_account.is_some() || !cranked {
+        player_state.vip_token_account = token_account.map(|a| a.key()).unwrap_or_default();
+    }
+    let Some(token_account) = token_account else {
+        return Ok(());
+    };
+    if !config.enabled() || token_account.amount < config.min_balance || *total_payout > 0 {
+        return Ok(());
+    }
+    vip::accrue(slots_state, player_state, user, *staked)?;
+    pool_level::refresh(slots_state);
+    Ok(())
+}
+
+/// Count a resolved settle's payout towards the player's leaderboard
+/// epoch, and onto the board if the caller passed the current one. The
+/// player's own settle records the current board it passed, or none; a
+/// `cranked` win in the same epoch must pass the recorded board.
+pub(crate) fn record_leaderboard(
+    slots_state: &SlotsState,
+    board: Option<&mut Account<Leaderboard>>,
+    player_state: &mut PlayerState,
+    settlement: &Settlement,
+    cranked: bool,
+) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let same_epoch = player_state.leaderboard_epoch == leaderboard::epoch_at(slots_state, slot);
+    let board = if cranked && same_epoch && settlement.total_payout > 0 {
+        optional::recorded(board, &player_state.leaderboard, "leaderboard")?
+    } else {
+        optional::present(board, "leaderboard")
+    };
+    let current = board
+        .as_ref()
+        .filter(|b| leaderboard::is_current(slots_state, b, slot))
+        .map(|b| b.key());
+    let placed = leaderboard::record(
+        slots_state,
+        board.map(|b| &mut **b),
+        player_state,
+        settlement.total_payout,
+        slot,
+    )?;
+    if current.is_some() || !cranked {
+        player_state.leaderboard = current.unwrap_or_default();
+    }
+    let Some(rank) = placed else {
+        return Ok(());
+    };
+    emit!(LeaderboardUpdated {
+        epoch: player_state.leaderboard_epoch,
+        player: player_state.owner,
+        total_won: player_state.leaderboard_won,
+        rank: rank as u8,
+    });
+    Ok(())
+}
+
+/// Only the program's upgrade authority (or, for an immutable program,
+/// IMMUTABLE_PROGRAM_DEPLOYER) may create a machine.
+fn require_deployer(payer: &Signer, program_data: &ProgramData) -> Result<()> {
+    let allowed = match program_data.upgrade_authority_address {
+        Some(upgrade_authority) => payer.key() == upgrade_authority,
+        None => IMMUTABLE_PROGRAM_DEPLOYER == Some(payer.key()),
+    };
+    require!(allowed, ErrorCode::NotUpgradeAuthority);
+    Ok(())
+}
+
+/// Default configuration of a new machine; its treasury, fee vault and
+/// machine id are set by the caller.
+fn init_slots_state(
+    slots_state: &mut SlotsState,
+    authority: Pubkey,
+    exclusive_mint: Option<Pubkey>,
+    recovery_authority: Option<Pubkey>,
+    community_authority: Option<Pubkey>,
+    network_guard: Pubkey,
+) -> Result<()> {
+    slots_state.authority = authority;
+    slots_state.initialized = true;
+    slots_state.pause_flags = 0;
+
+    slots_state.total_spins = 0;
+    slots_state.total_wagered = 0;
+    slots_state.total_payout = 0;
+    slots_state.house_profit = 0;
+    slots_state.current_rtp_bps = 0;
+    slots_state.house_edge_bps = 500; // 5% edge: theoretical RTP may not exceed 95%
+
+    slots_state.total_pool = 0;
+    slots_state.max_payout_per_spin = 1_000_000_000; // 1 SOL (example)
+    slots_state.min_pool_threshold = 100_000_000;    // 0.1 SOL
+
+    // Example jackpots config – tune for your RTP.
+    let mini_seed = 10_000_000;      // 0.01 SOL
+    let major_seed = 100_000_000;    // 0.1 SOL
+    let grand_seed = 1_000_000_000;  // 1 SOL
+
+    slots_state.jackpots = JackpotsConfig {
+        mini: JackpotPool {
+            amount: mini_seed,
+            seed: mini_seed,
+            contrib_bps: 50, // 0.5% of bet
+            hit_weight: 500,
+        },
+        major: JackpotPool {
+            amount: major_seed,
+            seed: major_seed,
+            contrib_bps: 100, // 1.0% of bet
+            hit_weight: 300,
+        },
+        grand: JackpotPool {
+            amount: grand_seed,
+            seed: grand_seed,
+            contrib_bps: 50, // 0.5% of bet
+            hit_weight: 200,
+        },
+        hit_weight_total: 500 + 300 + 200,
+    };
+
+    slots_state.beta_mode = false;
+    slots_state.allowlist_pages = 0;
+
+    slots_state.traffic_half_life_secs = risk::DEFAULT_TRAFFIC_HALF_LIFE_SECS;
+    slots_state.traffic_spin_weight = 0;
+    slots_state.traffic_wager_weight = 0;
+    slots_state.traffic_last_update_ts = 0;
+
+    slots_state.fee_balance = 0;
+    slots_state.winner_fee_rebate_lamports = 0; // disabled until configured
+    slots_state.rebate_daily_cap_per_player = 0;
+    slots_state.rebate_suspend_below = 0;
+    slots_state.rebate_resume_at = 0;
+    slots_state.rebate_suspended = false;
+
+    slots_state.settle_hooks = [Pubkey::default(); MAX_SETTLE_HOOKS];
+    slots_state.pending_settle_hook = Pubkey::default();
+    slots_state.pending_settle_hook_index = 0;
+    slots_state.pending_settle_hook_activate_slot = 0;
+
+    slots_state.jackpot_batch_mode = JackpotBatchMode::Sequential;
+
+    slots_state.bootstrap_maturity_threshold = 0; // disabled until configured
+    slots_state.bootstrap_payout_bps = bootstrap::DEFAULT_BOOTSTRAP_PAYOUT_BPS;
+    slots_state.bootstrap_hysteresis_bps = bootstrap::DEFAULT_BOOTSTRAP_HYSTERESIS_BPS;
+    slots_state.bootstrap_active = false;
+
+    slots_state.last_commit_slot = 0;
+    slots_state.withdrawal_cooldown_slots = 0;
+
+    slots_state.jackpot_max_caps = [0; 3];
+    slots_state.jackpot_must_hit_by = [0; 3];
+    slots_state.jackpot_forced_hits = 0;
+    slots_state.vip_config = VipConfig::default();
+    slots_state.vip_rebate_balance = 0;
+    slots_state.share_epoch = 0;
+    slots_state.jackpot_boosted_totals = [0; 3];
+
+    slots_state.total_claimable = 0;
+    slots_state.earmarks = EarmarkedObligations::default();
+
+    slots_state.scheduled_resume_slot = 0;
+
+    slots_state.pending_spins = 0;
+
+    slots_state.exclusive_mint = exclusive_mint.unwrap_or_default();
+
+    slots_state.jackpot_reseed_policies = [ReseedPolicy::ReserveFunded; 3];
+    slots_state.jackpot_rebuild_bps = [0; 3];
+
+    slots_state.outflow_totals = [0; OUTFLOW_CATEGORIES];
+
+    slots_state.oracle_cost_lamports = 0;
+    slots_state.oracle_cost_mode = OracleCostMode::HouseAbsorbs;
+    slots_state.oracle_costs_paid = 0;
+    slots_state.oracle_costs_charged = 0;
+
+    slots_state.frozen_version = 0;
+    slots_state.min_settle_version = 0;
+
+    slots_state.recovery_authority = recovery_authority.unwrap_or_default();
+    slots_state.recovery_inactivity_slots = DEFAULT_RECOVERY_INACTIVITY_SLOTS;
+    slots_state.last_authority_action_slot = Clock::get()?.slot;
+
+    slots_state.tier_limits = [TierLimits::default(); MAX_PLAYER_TIERS];
+
+    slots_state.last_successful_reveal_slot = slots_state.last_authority_action_slot;
+    slots_state.oldest_unrevealed_commit_slot = 0;
+    slots_state.oracle_max_reveal_gap_slots = 0;
+    slots_state.oracle_expected_reveal_slots = DEFAULT_ORACLE_EXPECTED_REVEAL_SLOTS;
+    slots_state.oracle_gate_closed = false;
+
+    slots_state.community_authority = community_authority.unwrap_or_default();
+    slots_state.community_bps = 0;
+    slots_state.community_balance = 0;
+    slots_state.community_accrued = 0;
+    slots_state.community_deficit = 0;
+
+    slots_state.onboarding_sponsor = Pubkey::default();
+    slots_state.sponsored_daily_budget = 0;
+    slots_state.sponsored_day = 0;
+    slots_state.sponsored_today = 0;
+    slots_state.sponsored_total = 0;
+
+    slots_state.total_deposits = 0;
+    slots_state.total_fee_rebates = 0;
+    slots_state.jackpot_hits = 0;
+    slots_state.jackpot_paid = 0;
+    slots_state.epoch_report_page = 0;
+
+    slots_state.network_guard = network_guard;
+
+    slots_state.jackpot_max_hit_ppm = jackpot_bounds::ABSOLUTE_MAX_TIER_HIT_PPM;
+    slots_state.jackpot_max_cost_bps = jackpot_bounds::DEFAULT_MAX_JACKPOT_COST_BPS;
+    slots_state.jackpot_cost_reference_bet =
+        jackpot_bounds::DEFAULT_JACKPOT_COST_REFERENCE_BET;
+    slots_state.pending_jackpot_weights = JackpotWeights::default();
+    slots_state.pending_jackpot_weights_activate_slot = 0;
+
+    slots_state.pool_below_required = false;
+    slots_state.wager_race_count = 0;
+    slots_state.reserved_liability = 0;
+    slots_state.spin_expiry_slots = DEFAULT_SPIN_EXPIRY_SLOTS;
+    slots_state.min_bet = DEFAULT_MIN_BET;
+    slots_state.max_bet = slots_state.max_payout_per_spin / game_math::max_3oak_multiplier();
+    slots_state.use_fees_for_overflow = false;
+    slots_state.fee_overflow_max_per_spin = 0;
+    slots_state.total_fee_overflow = 0;
+    slots_state.pending_authority = Pubkey::default();
+    slots_state.oracle_queues = [OracleQueueEntry::default(); MAX_ORACLE_QUEUES];
+    slots_state.oracle_queue_margin_slots = 0;
+    slots_state.feature_stats = [FeatureTotals::default(); FEATURE_KINDS];
+    slots_state.token_treasury = Pubkey::default();
+    slots_state.token_decimals = 0;
+    slots_state.crank_fee = 0;
+    slots_state.max_outcomes_per_reveal = DEFAULT_MAX_OUTCOMES_PER_REVEAL;
+    slots_state.extraction_budget_bps = DEFAULT_EXTRACTION_BUDGET_BPS;
+    slots_state.extraction_period_end = 0;
+    slots_state.extraction_period_budget = 0;
+    slots_state.extraction_period_used = 0;
+    slots_state.extraction_override = 0;
+    slots_state.extraction_override_activate_slot = 0;
+    slots_state.paytable = PAYTABLE;
+    slots_state.reel_weights = REEL_WEIGHTS;
+    slots_state.wild_symbol = NO_WILD;
+    slots_state.leaderboard_epoch_slots = DEFAULT_LEADERBOARD_EPOCH_SLOTS;
+    slots_state.max_rtp_bps = DEFAULT_MAX_RTP_BPS;
+    paytable::refresh_theoretical_rtp(slots_state)?;
+    slots_state.withdrawal_delay_slots = DEFAULT_WITHDRAWAL_DELAY_SLOTS;
+    slots_state.loss_window_slots = DEFAULT_LOSS_WINDOW_SLOTS;
+    slots_state.version = SLOTS_STATE_VERSION;
+
+    Ok(())
+}
+
+/// Fresh PlayerState for `owner`, recorded in its account index when
+/// given one (the legacy machine's; see machines.rs).
+fn init_player_state<'info>(
+    player_state: &mut PlayerState,
+    owner: Pubkey,
+    bump: u8,
+    account_index: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    player_state.owner = owner;
+    player_state.bump = bump;
+    player_state.randomness_account = Pubkey::default();
+    player_state.pending_bet_amount = 0;
+    player_state.has_pending_spin = false;
+    player_state.rebate_day = 0;
+    player_state.rebate_paid_today = 0;
+    player_state.commit_slot = 0;
+    player_state.commit_seed_slothash = [0u8; 32];
+    player_state.pending_jackpot_contribs = [0; 3];
+    player_state.claimable_balance = 0;
+    player_state.deferred_spins = 0;
+    player_state.deferred_wagered = 0;
+    player_state.deferred_last_ts = 0;
+    player_state.next_bet_nonce = 0;
+    player_state.commit_version = 0;
+    player_state.tier = 0;
+    player_state.forced_outcome = ForcedOutcome::default();
+    player_state.commit_payout_cap = 0;
+    player_state.commit_jackpot_mask = 0;
+    player_state.race_id = 0;
+    player_state.race_wagered = 0;
+    player_state.deferred_reserved = 0;
+    player_state.spin_nonce = 0;
+    player_state.pending_outcomes = 0;
+    player_state.pending_lines = 0;
+    player_state.total_spins = 0;
+    player_state.total_wagered = 0;
+    player_state.total_won = 0;
+    player_state.biggest_win = 0;
+    player_state.last_spin_slot = 0;
+    player_state.faucet_last_slot = 0;
+    player_state.faucet_received = 0;
+    player_state.commit_jackpot_odds = 0;
+    player_state.recent_seeds = [UsedSeed::default(); RECENT_SEEDS];
+    player_state.recent_seed_head = 0;
+    player_state.pending_operator_fee = 0;
+    player_state.loyalty_points = 0;
+    player_state.free_spin_credit = 0;
+    player_state.pending_free_spin = false;
+    player_state.referrer = Pubkey::default();
+    player_state.free_spins_remaining = 0;
+    player_state.free_spin_bet = 0;
+    player_state.pending_bonus_spin = false;
+    player_state.leaderboard_epoch = 0;
+    player_state.leaderboard_won = 0;
+    player_state.rebate_accrued = 0;
+    player_state.spins_remaining = 0;
+    player_state.vip_token_account = Pubkey::default();
+    player_state.leaderboard = Pubkey::default();
+
+    match account_index {
+        Some(info) => account_index::update_if_exists(info, |index| {
+            index.mark_created(INDEX_KIND_PLAYER_STATE, bump)
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Lamport-moving instructions, and the settings that would carve a
+/// bet's share into a lamport bucket, are dead in a token-only deployment.
+pub(crate) fn require_sol_mode(slots_state: &SlotsState) -> Result<()> {
+    require!(
+        slots_state.exclusive_mint().is_none(),
+        ErrorCode::WrongCurrencyMode
+    );
+    Ok(())
+}
+
+/// A pending spin may only settle if its commit version is still
+/// supported after the last upgrade.
+pub(crate) fn ensure_settle_version(
+    slots_state: &SlotsState,
+    player_state: &PlayerState,
+) -> Result<()> {
+    require!(
+        player_state.commit_version >= slots_state.min_settle_version,
+        ErrorCode::SpinVersionIncompatible
+    );
+    Ok(())
+}
+
+/// Funds that must stay in the pool when withdrawing: min_pool_threshold,
+/// full jackpot balances, every earmarked obligation and the payout caps
+/// reserved by pending spins.
+pub(crate) fn required_floor(slots_state: &SlotsState) -> Result<u64> {
+    let jackpot_total = total_jackpot_amounts(&slots_state.jackpots)?;
+    let earmarked = slots_state.earmarks.total()?;
+    let floor = slots_state
+        .min_pool_threshold
+        .checked_add(jackpot_total)
+        .and_then(|v| v.checked_add(earmarked))
+        .and_then(|v| v.checked_add(slots_state.reserved_liability))
+        .and_then(|v| v.checked_add(slots_state.pending_withdrawal_amount))
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(floor)
+}
+
+/// Re-evaluate bootstrap against the current pool, emitting on a change.
+pub(crate) fn refresh_bootstrap(slots_state: &mut SlotsState) {
+    let active = bootstrap::next_bootstrap_active(
+        slots_state.bootstrap_active,
+        slots_state.total_pool,
+        slots_state.bootstrap_maturity_threshold,
+        slots_state.bootstrap_hysteresis_bps,
+    );
+    if active != slots_state.bootstrap_active {
+        slots_state.bootstrap_active = active;
+        emit!(BootstrapModeChanged {
+            active,
+            total_pool: slots_state.total_pool,
+            maturity_threshold: slots_state.bootstrap_maturity_threshold,
+        });
+    }
+}
+
+/// Per-spin payout cap in effect, given the stored bootstrap state.
+pub(crate) fn effective_max_payout(slots_state: &SlotsState) -> u64 {
+    bootstrap::effective_max_payout(
+        slots_state.max_payout_per_spin,
+        slots_state.total_pool,
+        slots_state.bootstrap_payout_bps,
+        slots_state.bootstrap_active,
+    )
+}
+
+/// Unwind a pending spin's commit-time accounting (wagered, pool, operator
+/// fee, jackpot contributions) and clear it. Returns the bet to refund (0
+/// for a free spin, whose credit is restored); the caller moves the
+/// lamports. A tier that paid out and reseeded in the meantime must
+/// still hold the contribution, or the void fails with
+/// JackpotUnwindShortfall instead of leaving the jackpots short.
+pub(crate) fn void_pending_spin(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+) -> Result<u64> {
+    let bet_amount = player_state.pending_bet_amount;
+    // Only what the commit booked: nothing for a free spin.
+    let wager = booked_wager(player_state);
+
+    slots_state.total_wagered = slots_state
+        .total_wagered
+        .checked_sub(wager)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let fee_back = operator_fee::unwind(slots_state, player_state);
+    slots_state.total_pool = slots_state
+        .total_pool
+        .checked_add(fee_back)
+        .and_then(|pool| pool.checked_sub(wager))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let [mini, major, grand] = player_state.pending_jackpot_contribs;
+    feature_stats::unrecord_wager(
+        slots_state,
+        wager,
+        mini.saturating_add(major).saturating_add(grand),
+    );
+    // A tier hit since the commit was reseeded; if it no longer holds the
+    // contribution, fail rather than leave the jackpots short.
+    let jackpots = &mut slots_state.jackpots;
+    for (pool, contrib) in [
+        (&mut jackpots.mini, mini),
+        (&mut jackpots.major, major),
+        (&mut jackpots.grand, grand),
+    ] {
+        pool.amount = pool
+            .amount
+            .checked_sub(contrib)
+            .ok_or(ErrorCode::JackpotUnwindShortfall)?;
+    }
+
+    player_state.has_pending_spin = false;
+    player_state.pending_bet_amount = 0;
+    player_state.randomness_account = Pubkey::default();
+    player_state.pending_jackpot_contribs = [0; 3];
+    player_state.pending_outcomes = 0;
+    player_state.pending_lines = 0;
+    player_state.spins_remaining = 0;
+    player_stats::unrecord_wager(player_state, wager);
+    reservations::release(slots_state, player_state);
+    slots_state.pending_spins = slots_state.pending_spins.saturating_sub(1);
+
+    if scatter::unwind(player_state) {
+        return Ok(0);
+    }
+    loyalty::unwind(slots_state, player_state, bet_amount)
+}
+
+/// Whether the pending spin is free, scatter-won or loyalty-redeemed: its
+/// stake is one the player never paid.
+pub(crate) fn is_free_spin(player_state: &PlayerState) -> bool {
+    player_state.pending_bonus_spin || player_state.pending_free_spin
+}
+
+/// Wager the pending spin's commit booked, and its settle or void
+/// unwinds: nothing for a free spin.
+pub(crate) fn booked_wager(player_state: &PlayerState) -> u64 {
+    if is_free_spin(player_state) {
+        0
+    } else {
+        player_state.pending_bet_amount
+    }
+}
+
+/// Close a pending spin as a loss, without drawing it: the stake stays in
+/// the pool and the jackpots keep their contributions. For a spin whose
+/// randomness the player re-seeded; refunding it instead would let a
+/// player who saw a losing reveal replace it and take the bet back. A
+/// free spin is spent. Returns the stake kept.
+pub(crate) fn forfeit_pending_spin(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+) -> Result<u64> {
+    let wager = booked_wager(player_state);
+    let clock = Clock::get()?;
+
+    community::accrue(slots_state, wager, 0, clock.slot)?;
+    slots_state.house_profit = slots_state
+        .total_wagered
+        .saturating_sub(slots_state.total_payout);
+    responsible_gaming::record_settle(slots_state, player_state, wager, 0, clock.slot);
+
+    reservations::release(slots_state, player_state);
+    player_state.has_pending_spin = false;
+    player_state.pending_bet_amount = 0;
+    player_state.pending_lines = 0;
+    player_state.spins_remaining = 0;
+    player_state.pending_bonus_spin = false;
+    player_state.pending_free_spin = false;
+    player_state.randomness_account = Pubkey::default();
+    slots_state.pending_spins = slots_state.pending_spins.saturating_sub(1);
+    pool_level::refresh(slots_state);
+    Ok(wager)
+}
+
+/// A reveal for the committed generation decides the spin: only one whose
+/// randomness is unrevealed or was re-seeded may be voided. A spin that
+/// can no longer settle under this version is voidable regardless.
+pub(crate) fn ensure_voidable(
+    slots_state: &SlotsState,
+    player_state: &PlayerState,
+    randomness_ai: &AccountInfo,
+) -> Result<()> {
+    let settleable = player_state.commit_version >= slots_state.min_settle_version;
+    let parsed = RandomnessAccountData::parse(randomness_ai.data.borrow());
+    if let (true, Ok(randomness_data)) = (settleable, parsed) {
+        let same_generation = randomness_data.seed_slot == player_state.commit_slot
+            && randomness_data.seed_slothash == player_state.commit_seed_slothash;
+        require!(
+            !(same_generation && randomness_data.reveal_slot != 0),
+            ErrorCode::RandomnessAlreadyRevealed
+        );
+    }
+    Ok(())
+}
+
+/// Whether the randomness account still holds the pending spin's committed
+/// generation; false for one re-seeded since or that does not parse.
+pub(crate) fn holds_committed_generation(
+    player_state: &PlayerState,
+    randomness_ai: &AccountInfo,
+) -> bool {
+    RandomnessAccountData::parse(randomness_ai.data.borrow()).is_ok_and(|randomness_data| {
+        randomness_data.seed_slot == player_state.commit_slot
+            && randomness_data.seed_slothash == player_state.commit_seed_slothash
+    })
+}
+
+/// The randomness account's current seed slot if it was re-seeded since
+/// the pending spin's commit; None for the committed generation or an
+/// account that does not parse.
+pub(crate) fn rotated_seed_slot(
+    player_state: &PlayerState,
+    randomness_ai: &AccountInfo,
+) -> Option<u64> {
+    let randomness_data = RandomnessAccountData::parse(randomness_ai.data.borrow()).ok()?;
+    let same_generation = randomness_data.seed_slot == player_state.commit_slot
+        && randomness_data.seed_slothash == player_state.commit_seed_slothash;
+    (!same_generation).then_some(randomness_data.seed_slot)
+}
+
+/// Detail a settle reports next to SpinSettled when the spin drew more
+/// than one payline or spin.
+pub(crate) enum DrawnDetail {
+    Lines(LinesOutcome),
+    Spins(SpinsOutcome),
+}
+
+/// Draw the pending spin's outcome from its reveal (see
+/// derive_pending_outcome). Takes the outcomes it derives from the spin's
+/// committed budget.
+#[allow(clippy::too_many_arguments)]
+fn draw_pending_outcome(
+    player_state: &mut PlayerState,
+    random_bytes: &[u8; 32],
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    forced_jackpots: u8,
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    wild: u8,
+) -> Result<(SpinOutcome, Option<DrawnDetail>)> {
+    let outcomes = player_state.pending_lines.max(1) * multi_spin::count(player_state);
+    outcome_budget::consume(player_state, outcomes)?;
+    Ok(derive_pending_outcome(
+        player_state,
+        random_bytes,
+        limits,
+        jackpots,
+        forced_jackpots,
+        reels,
+        paytable,
+        wild,
+    ))
+}
+
+/// The pending spin's outcome for a reveal: one line, the 3x3 grid when
+/// it plays several paylines, or one line per spin of a multi-spin commit.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn derive_pending_outcome(
+    player_state: &PlayerState,
+    random_bytes: &[u8; 32],
+    limits: PayoutLimits,
+    jackpots: &JackpotsConfig,
+    forced_jackpots: u8,
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    wild: u8,
+) -> (SpinOutcome, Option<DrawnDetail>) {
+    let lines = player_state.pending_lines.max(1);
+    let spins = multi_spin::count(player_state);
+    let bet_amount = player_state.pending_bet_amount;
+    let jackpot_odds = tiers::committed_jackpot_odds(player_state);
+    if spins > 1 {
+        let drawn = game_math::derive_outcome_spins(
+            random_bytes,
+            multi_spin::unit_bet(player_state),
+            spins,
+            limits,
+            jackpots,
+            jackpot_odds,
+            forced_jackpots,
+            reels,
+            paytable,
+            wild,
+        );
+        return (drawn.outcome, Some(DrawnDetail::Spins(drawn)));
+    }
+    if lines == 1 {
+        let outcome = derive_outcome(
+            random_bytes,
+            bet_amount,
+            limits,
+            jackpots,
+            jackpot_odds,
+            forced_jackpots,
+            reels,
+            paytable,
+            wild,
+        );
+        return (outcome, None);
+    }
+    let drawn = game_math::derive_outcome_lines(
+        random_bytes,
+        bet_amount / lines as u64,
+        lines,
+        limits,
+        jackpots,
+        jackpot_odds,
+        forced_jackpots,
+        reels,
+        paytable,
+        wild,
+    );
+    (drawn.outcome, Some(DrawnDetail::Lines(drawn)))
+}
+
+/// Keep the settled reveal on the PlayerState and publish it as SpinProof,
+/// next to SpinSettled.
+fn record_spin_proof(
+    user: Pubkey,
+    player_state: &mut PlayerState,
+    randomness_account: Pubkey,
+    random_bytes: &[u8; 32],
+    context_index: u8,
+) {
+    player_state.last_reveal = *random_bytes;
+    player_state.last_reveal_seed_slot = player_state.commit_slot;
+    emit!(SpinProof {
+        user,
+        randomness_account,
+        seed_slot: player_state.commit_slot,
+        random_bytes: *random_bytes,
+        seed: game_math::seed_from_vrf(random_bytes),
+        spin_nonce: player_state.spin_nonce,
+        context_index,
+    });
+}
+
+/// Publish the per-line or per-spin detail of a settle.
+fn emit_drawn_detail(
+    user: Pubkey,
+    player_state: &PlayerState,
+    detail: &DrawnDetail,
+    context_index: u8,
+) {
+    match detail {
+        DrawnDetail::Lines(drawn) => emit_lines_settled(user, player_state, drawn, context_index),
+        DrawnDetail::Spins(drawn) => emit!(SpinsSettled {
+            user,
+            spins: multi_spin::count(player_state),
+            spin_bet: multi_spin::unit_bet(player_state),
+            symbols: drawn.symbols,
+            payouts: drawn.payouts,
+            spin_nonce: player_state.spin_nonce,
+            context_index,
+        }),
+    }
+}
+
+/// Grid and per-line wins of a multi-line spin, next to its SpinSettled.
+fn emit_lines_settled(
+    user: Pubkey,
+    player_state: &PlayerState,
+    drawn: &LinesOutcome,
+    context_index: u8,
+) {
+    let lines = player_state.pending_lines;
+    emit!(SpinLinesSettled {
+        user,
+        lines,
+        line_bet: player_state.pending_bet_amount / lines.max(1) as u64,
+        grid: drawn.grid,
+        line_payouts: drawn.line_payouts,
+        spin_nonce: player_state.spin_nonce,
+        context_index,
+    });
+}
+
+/// Pay `crank_fee` from the house pool to a cranker that settled someone
+/// else's spin. Skipped, not failed, when the pool is down to its floor,
+/// so the settle itself always goes through.
+fn pay_crank_fee<'info>(
+    slots_state: &mut SlotsState,
+    system_program: &Program<'info, System>,
+    treasury: &SystemAccount<'info>,
+    cranker: &Signer<'info>,
+    player: &Pubkey,
+    treasury_bump: u8,
+) -> Result<()> {
+    let fee = slots_state.crank_fee;
+    if fee == 0 || cranker.key() == *player {
+        return Ok(());
+    }
+    let headroom = slots_state
+        .total_pool
+        .saturating_sub(required_floor(slots_state)?);
+    if fee > headroom {
+        return Ok(());
+    }
+
+    slots_state.total_pool -= fee;
+    transfer_from_treasury(
+        system_program,
+        treasury,
+        cranker.to_account_info(),
+        &slots_state.machine_id,
+        treasury_bump,
+        fee,
+    )?;
+    outflows::book(slots_state, OutflowCategory::Fee, fee, cranker.key(), [0; 32])?;
+    pool_level::refresh(slots_state);
+    Ok(())
+}
+
+/// Move lamports out of `machine_id`'s treasury PDA, signing with its
+/// seeds.
+pub(crate) fn transfer_from_treasury<'info>(
+    system_program: &Program<'info, System>,
+    treasury: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    machine_id: &[u8; 32],
+    treasury_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    require!(
+        accounting::spendable_lamports(treasury)? >= amount,
+        ErrorCode::InsufficientPool
+    );
+
+    let bump = [treasury_bump];
+    let signer_seeds: &[&[u8]] = &[TREASURY_SEED, machine_seed(machine_id), &bump];
+    let signer = [signer_seeds];
+    let transfer_ctx = CpiContext::new(
+        system_program.to_account_info(),
+        system_program::Transfer {
+            from: treasury.to_account_info(),
+            to,
+        },
+    )
+    .with_signer(&signer);
+    system_program::transfer(transfer_ctx, amount)
+}
+
+/// Top `vault`, a system-owned PDA of this program, up to the rent-exempt
+/// minimum of an empty account out of `payer`. The treasury and the fee
+/// vault hold no data and are never `init`ed; this transfer creates them.
+pub(crate) fn fund_vault_rent<'info>(
+    system_program: &Program<'info, System>,
+    payer: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+) -> Result<()> {
+    let missing = Rent::get()?
+        .minimum_balance(vault.data_len())
+        .saturating_sub(vault.lamports());
+    if missing == 0 {
+        return Ok(());
+    }
+    let transfer_ctx = CpiContext::new(
+        system_program.to_account_info(),
+        system_program::Transfer {
+            from: payer,
+            to: vault,
+        },
+    );
+    system_program::transfer(transfer_ctx, missing)
+}
+
+/// Part of the base win that pool headroom cut, paid from fee_balance
+/// when use_fees_for_overflow is set. Only headroom cuts qualify: the win
+/// is never lifted above `cap_by_house`, the per-spin cap the spin was
+/// committed under, and jackpots are not topped up.
+fn fee_overflow(
+    slots_state: &mut SlotsState,
+    outcome: &game_math::SpinOutcome,
+    cap_by_house: u64,
+) -> Result<u64> {
+    if !slots_state.use_fees_for_overflow {
+        return Ok(0);
+    }
+
+    let overflow = outcome
+        .base_payout_full
+        .min(cap_by_house)
+        .saturating_sub(outcome.base_payout)
+        .min(slots_state.fee_overflow_max_per_spin)
+        .min(slots_state.fee_balance);
+
+    slots_state.fee_balance = slots_state
+        .fee_balance
+        .checked_sub(overflow)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_fee_overflow = slots_state
+        .total_fee_overflow
+        .checked_add(overflow)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(overflow)
+}
+
+/// Winner fee rebate for this settle, debited from fee_balance and
+/// recorded against the player's daily cap. Rebates suspend when the fee
+/// balance falls below `rebate_suspend_below` and only resume at
+/// `rebate_resume_at`, so they don't flap around a single threshold.
+fn winner_fee_rebate(
+    slots_state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    now_ts: i64,
+) -> Result<u64> {
+    if slots_state.winner_fee_rebate_lamports == 0 {
+        return Ok(0);
+    }
+
+    let was_suspended = slots_state.rebate_suspended;
+    if was_suspended {
+        slots_state.rebate_suspended = slots_state.fee_balance < slots_state.rebate_resume_at;
+    } else {
+        slots_state.rebate_suspended = slots_state.fee_balance < slots_state.rebate_suspend_below;
+    }
+    if slots_state.rebate_suspended != was_suspended {
+        emit!(WinnerRebateStatus {
+            suspended: slots_state.rebate_suspended,
+            fee_balance: slots_state.fee_balance,
+        });
+    }
+    if slots_state.rebate_suspended {
+        return Ok(0);
+    }
+
+    // Forward-only: a timestamp stepping back over midnight must not
+    // reopen yesterday's allowance.
+    let (today, new_day) =
+        time::advance_bucket(player_state.rebate_day, now_ts, time::SECONDS_PER_DAY);
+    if new_day {
+        player_state.rebate_day = today;
+        player_state.rebate_paid_today = 0;
+    }
+
+    let allowance = slots_state
+        .rebate_daily_cap_per_player
+        .saturating_sub(player_state.rebate_paid_today);
+    let rebate = slots_state
+        .winner_fee_rebate_lamports
+        .min(allowance)
+        .min(slots_state.fee_balance);
+
+    slots_state.fee_balance = slots_state
+        .fee_balance
+        .checked_sub(rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+    slots_state.total_fee_rebates = slots_state
+        .total_fee_rebates
+        .checked_add(rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.rebate_paid_today = player_state
+        .rebate_paid_today
+        .checked_add(rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(rebate)
+}
+
+/// CPI the partner hook named by `remaining_accounts[0]`, passing the rest
+/// of `remaining_accounts` as its accounts. No remaining accounts = no hook;
+/// unusable ones are skipped (see settle_hooks.rs).
+///
+/// Guarantees: only registered hooks are called; every account is passed
+/// without signer privileges, so a hook can't spend the player's or the
+/// treasury's lamports; it runs after all state changes and transfers; and
+/// the runtime rejects A -> B -> A reentrancy into this program.
+fn invoke_settle_hook<'info>(
+    slots_state: &SlotsState,
+    remaining_accounts: &[AccountInfo<'info>],
+    args: OnSpinSettledArgs,
+) -> Result<()> {
+    let (hook_program, hook_accounts) = match settle_hooks::plan(slots_state, remaining_accounts) {
+        HookCall::Absent => {
+            if slots_state.settle_hooks.iter().any(|h| *h != Pubkey::default()) {
+                optional::note_absent("settle hook");
+            }
+            return Ok(());
+        }
+        HookCall::Skip { hook_program, code } => {
+            emit!(SettleHookSkipped {
+                hook_program,
+                user: args.user,
+                error_code: code.into(),
+            });
+            return Ok(());
+        }
+        HookCall::Invoke {
+            hook_program,
+            hook_accounts,
+        } => (hook_program, hook_accounts),
+    };
+
+    let mut data = ON_SPIN_SETTLED_DISCRIMINATOR.to_vec();
+    args.serialize(&mut data)?;
+
+    let ix = Instruction {
+        program_id: hook_program.key(),
+        accounts: hook_accounts
+            .iter()
+            .map(|a| AccountMeta {
+                pubkey: a.key(),
+                is_signer: false,
+                is_writable: a.is_writable,
+            })
+            .collect(),
+        data,
+    };
+    invoke(&ix, remaining_accounts)?;
+
+    emit!(SettleHookInvoked {
+        hook_program: hook_program.key(),
+        user: args.user,
+        payout: args.payout,
+    });
+
+    Ok(())
+}
+
+/// Sum all jackpot amounts safely.
+pub(crate) fn total_jackpot_amounts(jackpots: &JackpotsConfig) -> Result<u64> {
+    let sum1 = jackpots
+        .mini
+        .amount
+        .checked_add(jackpots.major.amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let sum2 = sum1
+        .checked_add(jackpots.grand.amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(sum2)
 }
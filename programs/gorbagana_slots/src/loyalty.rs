@@ -0,0 +1,192 @@
+//! Loyalty points and house-funded free spins.
+//!
+//! Every settled spin earns `wager * points_per_lamport_bps / 10_000`
+//! points into `PlayerState.loyalty_points`. redeem_free_spin burns
+//! `free_spin_point_cost` of them for a credit of `free_spin_bet`, which
+//! is earmarked as a voucher (see obligations.rs) so the pool keeps it
+//! until it is played.
+//!
+//! The next request_spin spends the credit: the voucher is released and
+//! the spin's stake, which may not exceed the credit, is notional, as on
+//! a scatter free spin. The player transfers nothing and the commit books
+//! no wager, operator fee or jackpot contribution, so `total_wagered` and
+//! the jackpots only ever grow from real stakes. The spin reserves its
+//! payout cap and its winnings pay out of the pool normally. It earns no
+//! points.
+//!
+//! `PlayerState.pending_free_spin` marks the pending spin as house funded.
+//! A void re-issues the credit instead of refunding lamports the player
+//! never paid.
+
+use anchor_lang::prelude::*;
+
+use crate::obligations::EarmarkKind;
+use crate::{
+    required_floor, ErrorCode, FreeSpinRedeemed, LoyaltyPointsAccrued, PlayerState, SlotsState,
+};
+
+pub fn points_for(points_per_lamport_bps: u16, wager: u64) -> u64 {
+    let points = (wager as u128) * (points_per_lamport_bps as u128) / 10_000;
+    points.min(u64::MAX as u128) as u64
+}
+
+/// Credit the points of a settled spin and clear its free-spin mark.
+/// Returns the points earned; a free spin earns none.
+pub fn accrue(
+    state: &SlotsState,
+    player_state: &mut PlayerState,
+    user: &Pubkey,
+    wager: u64,
+) -> u64 {
+    let free_spin = std::mem::take(&mut player_state.pending_free_spin);
+    let points = if free_spin {
+        0
+    } else {
+        points_for(state.points_per_lamport_bps, wager)
+    };
+    if points == 0 {
+        return 0;
+    }
+    player_state.loyalty_points = player_state.loyalty_points.saturating_add(points);
+    emit!(LoyaltyPointsAccrued {
+        user: *user,
+        wager,
+        points,
+        balance: player_state.loyalty_points,
+    });
+    points
+}
+
+/// Burn the point cost for a free-spin credit, earmarked in the pool.
+pub fn redeem(state: &mut SlotsState, player_state: &mut PlayerState, user: &Pubkey) -> Result<()> {
+    let bet = state.free_spin_bet;
+    let cost = state.free_spin_point_cost;
+    require!(bet > 0, ErrorCode::FreeSpinsDisabled);
+    require!(
+        player_state.free_spin_credit == 0,
+        ErrorCode::FreeSpinAlreadyRedeemed
+    );
+    require!(
+        player_state.loyalty_points >= cost,
+        ErrorCode::InsufficientLoyaltyPoints
+    );
+    let headroom = state.total_pool.saturating_sub(required_floor(state)?);
+    require!(bet <= headroom, ErrorCode::InsufficientPool);
+
+    state.earmarks.earmark(EarmarkKind::Voucher, bet)?;
+    player_state.loyalty_points -= cost;
+    player_state.free_spin_credit = bet;
+
+    emit!(FreeSpinRedeemed {
+        user: *user,
+        points_burned: cost,
+        points_left: player_state.loyalty_points,
+        free_spin_bet: bet,
+    });
+    Ok(())
+}
+
+/// Spend the player's credit on `stake` if there is one: release its
+/// voucher and mark the pending spin. Returns whether the stake is house
+/// funded.
+pub fn fund(state: &mut SlotsState, player_state: &mut PlayerState, stake: u64) -> Result<bool> {
+    let credit = player_state.free_spin_credit;
+    if credit == 0 {
+        return Ok(false);
+    }
+    require!(stake <= credit, ErrorCode::FreeSpinBetTooLarge);
+
+    state.earmarks.release(EarmarkKind::Voucher, credit)?;
+    player_state.free_spin_credit = 0;
+    player_state.pending_free_spin = true;
+    Ok(true)
+}
+
+/// Undo [`fund`] for a voided spin whose commit accounting was already
+/// unwound. Returns the lamports still owed to the player: the stake of a
+/// paid spin, nothing for a free one.
+pub fn unwind(state: &mut SlotsState, player_state: &mut PlayerState, stake: u64) -> Result<u64> {
+    if !std::mem::take(&mut player_state.pending_free_spin) {
+        return Ok(stake);
+    }
+    state.earmarks.earmark(EarmarkKind::Voucher, stake)?;
+    player_state.free_spin_credit = stake;
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Game;
+    use crate::{total_jackpot_amounts, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    #[test]
+    fn points_round_down_and_saturate() {
+        assert_eq!(points_for(0, 1_000_000), 0);
+        assert_eq!(points_for(100, 1_000_000), 10_000);
+        assert_eq!(points_for(100, 99), 0);
+        assert_eq!(points_for(u16::MAX, u64::MAX), u64::MAX);
+    }
+
+    /// A machine paying one loyalty spin of BET for 10 * BET of wagers.
+    fn loyalty_game() -> Game {
+        let mut game = Game::new();
+        game.state.points_per_lamport_bps = 10_000;
+        game.state.free_spin_point_cost = 10 * BET;
+        game.state.free_spin_bet = BET;
+        game.player.loyalty_points = 10 * BET;
+        redeem(&mut game.state, &mut game.player, &game.user).expect("redeems");
+        game
+    }
+
+    #[test]
+    fn a_loyalty_spin_books_no_wager_and_funds_no_jackpot() {
+        let mut game = loyalty_game();
+        let pool = game.state.total_pool;
+        let wagered = game.state.total_wagered;
+        let jackpots = total_jackpot_amounts(&game.state.jackpots).unwrap();
+        let wallet = game.wallet;
+
+        game.request(BET).expect("commits");
+        assert!(game.player.pending_free_spin);
+        assert_eq!(game.player.free_spin_credit, 0);
+        assert_eq!(game.state.earmarks.total().unwrap(), 0);
+        assert_eq!(game.wallet, wallet);
+        assert_eq!(game.state.total_pool, pool);
+        assert_eq!(game.state.total_wagered, wagered);
+        assert_eq!(game.player.pending_jackpot_contribs, [0; 3]);
+        assert_eq!(total_jackpot_amounts(&game.state.jackpots).unwrap(), jackpots);
+        game.verify().expect("books hold");
+
+        game.reveal([7; 32]);
+        let settled = game.settle().expect("settles");
+        assert_eq!(settled.staked, 0);
+        assert_eq!(game.state.total_wagered, wagered);
+        assert_eq!(game.player.loyalty_points, 0);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_voided_loyalty_spin_reissues_the_credit() {
+        let mut game = loyalty_game();
+        let pool = game.state.total_pool;
+        game.request(BET).expect("commits");
+
+        assert_eq!(game.admin_void().expect("voids"), 0);
+        assert_eq!(game.player.free_spin_credit, BET);
+        assert_eq!(game.state.earmarks.total().unwrap(), BET);
+        assert_eq!(game.state.total_pool, pool);
+        assert_eq!(game.player.claimable_balance, 0);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_loyalty_spin_above_the_credit_is_refused() {
+        let mut game = loyalty_game();
+        let err = game.request_lines(BET, 2).unwrap_err();
+        assert_eq!(err, ErrorCode::FreeSpinBetTooLarge.into());
+        assert_eq!(game.player.free_spin_credit, BET);
+    }
+}
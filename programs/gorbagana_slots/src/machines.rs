@@ -0,0 +1,89 @@
+//! Several slot machines under one deployment.
+//!
+//! Each machine is its own SlotsState with its own paytable, bet range,
+//! jackpots and treasury. `SlotsState.machine_id` names it, and the
+//! machine's PDAs are seeded with it:
+//!
+//! - SlotsState: `[SLOTS_SEED, machine_id]`, created by initialize_machine
+//!   (`[SLOTS_SEED]` for the legacy machine, created by initialize)
+//! - treasury: `[TREASURY_SEED, machine_id]`
+//! - fee vault: `[FEE_VAULT_SEED, machine_id]`
+//! - PlayerState: `[PLAYER_SEED, machine_id, user]`
+//!
+//! so a player has an independent PlayerState, and pending spin, on each
+//! machine. Every context derives these from the `slots_state` it is
+//! given; nothing takes the machine as an argument. PDAs already keyed by
+//! the SlotsState address (metrics, RTP history, referrals, ...) are
+//! per-machine as they are.
+//!
+//! The all-zero machine id is reserved for the legacy deployment, and
+//! [`machine_seed`] turns it into an empty seed. Its SlotsState is the
+//! `[SLOTS_SEED]` PDA, so initialize can only run once: a second legacy
+//! state would share the treasury, fee vault and PlayerStates below. A PDA address hashes
+//! the concatenation of its seeds, so `[TREASURY_SEED, &[]]` is the same
+//! address as `[TREASURY_SEED]`: an existing machine keeps its treasury,
+//! fee vault and every PlayerState, with no migration beyond migrate_state
+//! (which leaves the id zero). The AccountIndex records the legacy
+//! machine's PlayerState only.
+
+use crate::SlotsState;
+
+/// Machine id of the deployment initialize creates.
+pub const LEGACY_MACHINE_ID: [u8; 32] = [0; 32];
+
+pub fn is_legacy(machine_id: &[u8; 32]) -> bool {
+    *machine_id == LEGACY_MACHINE_ID
+}
+
+/// The seed `machine_id` adds to a machine's PDAs: nothing for the
+/// legacy machine, so its addresses are unchanged.
+pub fn machine_seed(machine_id: &[u8; 32]) -> &[u8] {
+    if is_legacy(machine_id) {
+        &[]
+    } else {
+        machine_id
+    }
+}
+
+/// Whether the player's AccountIndex tracks their PlayerState on `state`.
+pub fn indexes_players(state: &SlotsState) -> bool {
+    is_legacy(&state.machine_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+
+    use crate::{PLAYER_SEED, TREASURY_SEED};
+
+    fn pda(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &crate::ID).0
+    }
+
+    #[test]
+    fn legacy_machine_keeps_its_addresses() {
+        let user = Pubkey::new_unique();
+        let legacy = machine_seed(&LEGACY_MACHINE_ID);
+        assert_eq!(pda(&[TREASURY_SEED, legacy]), pda(&[TREASURY_SEED]));
+        assert_eq!(
+            pda(&[PLAYER_SEED, legacy, user.as_ref()]),
+            pda(&[PLAYER_SEED, user.as_ref()])
+        );
+    }
+
+    #[test]
+    fn machines_get_their_own_treasury_and_players() {
+        let user = Pubkey::new_unique();
+        let a = [1u8; 32];
+        let b = [2u8; 32];
+        assert_ne!(
+            pda(&[TREASURY_SEED, machine_seed(&a)]),
+            pda(&[TREASURY_SEED, machine_seed(&b)])
+        );
+        assert_ne!(
+            pda(&[PLAYER_SEED, machine_seed(&a), user.as_ref()]),
+            pda(&[PLAYER_SEED, user.as_ref()])
+        );
+    }
+}
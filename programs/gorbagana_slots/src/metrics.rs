@@ -0,0 +1,65 @@
+//! Fixed-layout gauges for scrapers.
+//!
+//! The `Metrics` PDA (seeds `[METRICS_SEED, slots_state]`) is a zero-copy
+//! account of plain little-endian integers, 120 bytes including the
+//! discriminator, so a scraper can `getAccountInfo` with a data slice and
+//! read fields at the offsets below without Borsh.
+//!
+//! | offset | size | field            |
+//! |-------:|-----:|------------------|
+//! |      0 |    8 | discriminator    |
+//! |      8 |   32 | slots_state      |
+//! |     40 |    8 | updated_slot     |
+//! |     48 |    8 | total_pool       |
+//! |     56 |    8 | spendable        |
+//! |     64 |    8 | pending_spins    |
+//! |     72 |    8 | total_spins      |
+//! |     80 |   24 | jackpot_amounts  |
+//! |    104 |    8 | fee_balance      |
+//! |    112 |    4 | current_rtp_bps  |
+//! |    116 |    1 | pause_flags      |
+//! |    117 |    1 | bootstrap_active |
+//! |    118 |    1 | beta_mode        |
+//! |    119 |    1 | bump             |
+//!
+//! The account is refreshed at the end of settle_spin, execute_withdrawal
+//! and emergency_pause when passed, and by the permissionless refresh_metrics.
+
+use crate::{required_floor, Metrics, SlotsState};
+
+pub const OFFSET_SLOTS_STATE: usize = 8;
+pub const OFFSET_UPDATED_SLOT: usize = 40;
+pub const OFFSET_TOTAL_POOL: usize = 48;
+pub const OFFSET_SPENDABLE: usize = 56;
+pub const OFFSET_PENDING_SPINS: usize = 64;
+pub const OFFSET_TOTAL_SPINS: usize = 72;
+pub const OFFSET_JACKPOT_AMOUNTS: usize = 80;
+pub const OFFSET_FEE_BALANCE: usize = 104;
+pub const OFFSET_CURRENT_RTP_BPS: usize = 112;
+pub const OFFSET_PAUSE_FLAGS: usize = 116;
+pub const OFFSET_BOOTSTRAP_ACTIVE: usize = 117;
+pub const OFFSET_BETA_MODE: usize = 118;
+pub const OFFSET_BUMP: usize = 119;
+
+/// Account length including the discriminator.
+pub const METRICS_LEN: usize = 120;
+
+/// Copy the current gauges from `state` into `metrics`.
+pub fn snapshot(metrics: &mut Metrics, state: &SlotsState, slot: u64) {
+    let floor = required_floor(state).unwrap_or(u64::MAX);
+    metrics.updated_slot = slot;
+    metrics.total_pool = state.total_pool;
+    metrics.spendable = state.total_pool.saturating_sub(floor);
+    metrics.pending_spins = state.pending_spins;
+    metrics.total_spins = state.total_spins;
+    metrics.jackpot_amounts = [
+        state.jackpots.mini.amount,
+        state.jackpots.major.amount,
+        state.jackpots.grand.amount,
+    ];
+    metrics.fee_balance = state.fee_balance;
+    metrics.current_rtp_bps = state.current_rtp_bps;
+    metrics.pause_flags = state.pause_flags;
+    metrics.bootstrap_active = state.bootstrap_active as u8;
+    metrics.beta_mode = state.beta_mode as u8;
+}
@@ -0,0 +1,272 @@
+//! SlotsState layout versions and in-place upgrades.
+//!
+//! `SlotsState.version` records the layout an account was last written
+//! with. Accounts created before the field existed read 0 and count as
+//! version 1. Fields are only ever appended, so an older account is a
+//! prefix of the current layout. migrate_state grows it to
+//! `SlotsState::LEN`, zero-filling the tail, then [`upgrade`] gives every
+//! field whose zero is not a usable value what initialize would have set
+//! (a zero `max_outcomes_per_reveal`, say, refuses every commit).
+//!
+//! Only an account carrying the SlotsState discriminator is grown
+//! ([`ensure_discriminator`]), and only at the address of the machine it
+//! claims to be ([`is_state_address`]); its stored `machine_id` must match
+//! once it can be read.
+//!
+//! When appending a field whose zero value is not a safe default, bump
+//! [`SLOTS_STATE_VERSION`] and add a step to [`upgrade`].
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::extraction::DEFAULT_EXTRACTION_BUDGET_BPS;
+use crate::leaderboard::DEFAULT_LEADERBOARD_EPOCH_SLOTS;
+use crate::outcome_budget::DEFAULT_MAX_OUTCOMES_PER_REVEAL;
+use crate::pause::PAUSE_ALL;
+use crate::responsible_gaming::DEFAULT_LOSS_WINDOW_SLOTS;
+use crate::paytable::{self, DEFAULT_MAX_RTP_BPS};
+use crate::tiers::{TierLimits, MAX_PLAYER_TIERS};
+use crate::withdrawal_timelock::DEFAULT_WITHDRAWAL_DELAY_SLOTS;
+use crate::machines;
+use crate::{
+    bootstrap, game_math, jackpot_bounds, risk, ErrorCode, SlotsState, DEFAULT_MIN_BET,
+    DEFAULT_ORACLE_EXPECTED_REVEAL_SLOTS, DEFAULT_RECOVERY_INACTIVITY_SLOTS,
+    DEFAULT_SPIN_EXPIRY_SLOTS, NO_WILD, PAYTABLE, REEL_WEIGHTS,
+    SLOTS_SEED, SYMBOL_WEIGHTS,
+};
+
+/// Layout version written by this build.
+pub const SLOTS_STATE_VERSION: u8 = 11;
+
+/// Refuse to grow an account that is not a `T`. A realloc only has the
+/// owner check to go on; the layout is read once the account is full
+/// length.
+pub fn ensure_discriminator<T: Discriminator>(data: &[u8]) -> Result<()> {
+    require!(
+        data.get(..8) == Some(&T::DISCRIMINATOR[..]),
+        anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+    );
+    Ok(())
+}
+
+/// Whether `key` is `machine_id`'s SlotsState: its
+/// `[SLOTS_SEED, machine_id]` PDA, `[SLOTS_SEED]` for the legacy machine.
+pub fn is_state_address(key: &Pubkey, machine_id: &[u8; 32]) -> bool {
+    let seeds = [SLOTS_SEED, machines::machine_seed(machine_id)];
+    *key == Pubkey::find_program_address(&seeds, &crate::ID).0
+}
+
+/// Bring `state` up to [`SLOTS_STATE_VERSION`]; returns the version it
+/// had. Refuses an account written by a newer build.
+pub fn upgrade(state: &mut SlotsState, now_slot: u64) -> Result<u8> {
+    let from = state.version.max(1);
+    require!(
+        from <= SLOTS_STATE_VERSION,
+        ErrorCode::UnsupportedStateVersion
+    );
+
+    if from < 2 {
+        // Unversioned accounts may predate any of these tunables.
+        default_if_zero(
+            &mut state.traffic_half_life_secs,
+            risk::DEFAULT_TRAFFIC_HALF_LIFE_SECS,
+        );
+        default_if_zero(
+            &mut state.bootstrap_payout_bps,
+            bootstrap::DEFAULT_BOOTSTRAP_PAYOUT_BPS,
+        );
+        default_if_zero(
+            &mut state.bootstrap_hysteresis_bps,
+            bootstrap::DEFAULT_BOOTSTRAP_HYSTERESIS_BPS,
+        );
+        default_if_zero(
+            &mut state.recovery_inactivity_slots,
+            DEFAULT_RECOVERY_INACTIVITY_SLOTS,
+        );
+        default_if_zero(&mut state.last_authority_action_slot, now_slot);
+        default_if_zero(&mut state.last_successful_reveal_slot, now_slot);
+        default_if_zero(
+            &mut state.oracle_expected_reveal_slots,
+            DEFAULT_ORACLE_EXPECTED_REVEAL_SLOTS,
+        );
+        default_if_zero(
+            &mut state.jackpot_max_hit_ppm,
+            jackpot_bounds::ABSOLUTE_MAX_TIER_HIT_PPM,
+        );
+        default_if_zero(
+            &mut state.jackpot_max_cost_bps,
+            jackpot_bounds::DEFAULT_MAX_JACKPOT_COST_BPS,
+        );
+        if state.tier_limits.iter().all(|t| t.max_bet_multiplier_bps == 0) {
+            state.tier_limits = [TierLimits::default(); MAX_PLAYER_TIERS];
+        }
+        default_if_zero(&mut state.spin_expiry_slots, DEFAULT_SPIN_EXPIRY_SLOTS);
+        default_if_zero(&mut state.min_bet, DEFAULT_MIN_BET);
+        default_if_zero(
+            &mut state.max_bet,
+            state.max_payout_per_spin / game_math::max_3oak_multiplier(),
+        );
+        default_if_zero(
+            &mut state.max_outcomes_per_reveal,
+            DEFAULT_MAX_OUTCOMES_PER_REVEAL,
+        );
+    }
+    if from < 3 {
+        state.extraction_budget_bps = DEFAULT_EXTRACTION_BUDGET_BPS;
+    }
+    if from < 4 {
+        state.paytable = PAYTABLE;
+        state.max_rtp_bps = DEFAULT_MAX_RTP_BPS;
+    }
+    if from < 5 {
+        // Recorded only: a live config over the target stays playable
+        // until the next change has to fit.
+        state.theoretical_rtp_bps =
+            paytable::theoretical_rtp_bps(&SYMBOL_WEIGHTS, &state.paytable, &state.jackpots);
+    }
+    if from < 6 {
+        state.withdrawal_delay_slots = DEFAULT_WITHDRAWAL_DELAY_SLOTS;
+    }
+    if from < 7 && state.pause_flags != 0 {
+        // The byte was `paused: bool`, which stopped everything.
+        state.pause_flags = PAUSE_ALL;
+    }
+    if from < 8 {
+        state.loss_window_slots = DEFAULT_LOSS_WINDOW_SLOTS;
+    }
+    if from < 9 {
+        // Zero weights would land symbol 0 on every reel.
+        state.reel_weights = REEL_WEIGHTS;
+    }
+    if from < 10 {
+        // Zero would make symbol 0 wild.
+        state.wild_symbol = NO_WILD;
+    }
+    if from < 11 {
+        state.leaderboard_epoch_slots = DEFAULT_LEADERBOARD_EPOCH_SLOTS;
+    }
+
+    state.version = SLOTS_STATE_VERSION;
+    Ok(from)
+}
+
+fn default_if_zero<T: Copy + Default + PartialEq>(field: &mut T, value: T) {
+    if *field == T::default() {
+        *field = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AccountDeserialize;
+
+    use super::*;
+    use crate::layout;
+    use crate::test_util::zeroed_state;
+    use crate::PlayerState;
+
+    /// A live version-8 account, the layout before `reel_weights`, and
+    /// its authority.
+    fn v8_account() -> (Vec<u8>, Pubkey) {
+        let authority = Pubkey::new_unique();
+        let mut state = zeroed_state();
+        state.authority = authority;
+        state.initialized = true;
+        state.version = 8;
+        state.min_bet = 7;
+        state.paytable = PAYTABLE;
+        let cut = layout::slots_state(&state)
+            .field("reel_weights")
+            .expect("reel_weights in layout")
+            .offset;
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        data.truncate(cut);
+        (data, authority)
+    }
+
+    #[test]
+    fn old_account_grows_and_gets_defaults() {
+        let (mut data, authority) = v8_account();
+        ensure_discriminator::<SlotsState>(&data).unwrap();
+        assert!(data.len() < SlotsState::LEN);
+        // What realloc(LEN, true) leaves: the old bytes, then zeros.
+        data.resize(SlotsState::LEN, 0);
+        let mut state = SlotsState::try_deserialize(&mut &data[..]).unwrap();
+        assert!(state.reel_weights != REEL_WEIGHTS);
+
+        assert_eq!(upgrade(&mut state, 1_000).unwrap(), 8);
+        assert_eq!(state.version, SLOTS_STATE_VERSION);
+        assert_eq!(state.reel_weights, REEL_WEIGHTS);
+        assert_eq!(state.wild_symbol, NO_WILD);
+        assert_eq!(state.leaderboard_epoch_slots, DEFAULT_LEADERBOARD_EPOCH_SLOTS);
+        // Fields the old layout had keep their values.
+        assert_eq!(state.authority, authority);
+        assert_eq!(state.min_bet, 7);
+        assert!(state.initialized);
+        assert!(machines::is_legacy(&state.machine_id));
+
+        let mut written = Vec::new();
+        state.try_serialize(&mut written).unwrap();
+        assert!(written.len() <= SlotsState::LEN);
+    }
+
+    #[test]
+    fn old_player_grows_with_zeroed_stats() {
+        let owner = Pubkey::new_unique();
+        let mut player = crate::test_util::zeroed_player();
+        player.owner = owner;
+        player.bump = 254;
+        player.claimable_balance = 9;
+        // The layout before the lifetime stats were appended.
+        let cut = layout::player_state(&player)
+            .field("total_spins")
+            .expect("total_spins in layout")
+            .offset;
+        let mut data = Vec::new();
+        player.try_serialize(&mut data).unwrap();
+        data.truncate(cut);
+
+        ensure_discriminator::<PlayerState>(&data).unwrap();
+        assert!(PlayerState::try_deserialize(&mut &data[..]).is_err());
+        data.resize(PlayerState::LEN, 0);
+        let grown = PlayerState::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(grown.owner, owner);
+        assert_eq!(grown.bump, 254);
+        assert_eq!(grown.claimable_balance, 9);
+        assert_eq!(
+            (grown.total_spins, grown.total_wagered, grown.total_won, grown.biggest_win),
+            (0, 0, 0, 0)
+        );
+        assert_eq!(grown.spins_remaining, 0);
+    }
+
+    #[test]
+    fn only_slots_state_accounts_are_grown() {
+        let mut player = Vec::new();
+        crate::test_util::zeroed_player()
+            .try_serialize(&mut player)
+            .unwrap();
+        assert!(ensure_discriminator::<SlotsState>(&player).is_err());
+        assert!(ensure_discriminator::<PlayerState>(&player).is_ok());
+        assert!(ensure_discriminator::<SlotsState>(&[]).is_err());
+    }
+
+    #[test]
+    fn machine_states_must_sit_at_their_pda() {
+        let id = [3u8; 32];
+        let pda = Pubkey::find_program_address(&[SLOTS_SEED, &id], &crate::ID).0;
+        assert!(is_state_address(&pda, &id));
+        assert!(!is_state_address(&Pubkey::new_unique(), &id));
+        let legacy = Pubkey::find_program_address(&[SLOTS_SEED], &crate::ID).0;
+        assert!(is_state_address(&legacy, &machines::LEGACY_MACHINE_ID));
+        assert!(!is_state_address(&Pubkey::new_unique(), &machines::LEGACY_MACHINE_ID));
+    }
+
+    #[test]
+    fn newer_accounts_are_refused() {
+        let mut state = zeroed_state();
+        state.version = SLOTS_STATE_VERSION + 1;
+        assert!(upgrade(&mut state, 0).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Several spins committed against one randomness reveal.
+//!
+//! request_spins commits `count` single-line spins (up to
+//! MAX_SPINS_PER_COMMIT) of `bet_amount` each and escrows the whole stake
+//! at once. The settle draws the spins one after the other from the
+//! reveal's seed (game_math::derive_outcome_spins), caps each one on its
+//! own and pays their sum in a single settlement. SpinSettled carries the
+//! totals; SpinsSettled lists the reels and payout of every spin. Each
+//! spin counts in `total_spins`, while the settle is one
+//! `global_spin_counter` tick, as it is one reveal.
+//!
+//! The commit reserves `count` payout caps, stored as one
+//! `commit_payout_cap` so reservations.rs releases it unchanged, and is
+//! only accepted if the pool covers all of them. Each spin may pay up to
+//! its share of that cap. Each spin rolls for the jackpot at the odds of
+//! its own stake (game_math::jackpot_odds), so N spins have the chance of
+//! one spin of their total stake; at most one jackpot is awarded per
+//! commit.
+//! Every spin draws an outcome, so `count` must fit the outcome budget.
+//!
+//! Free spins play one at a time through request_spin: request_spins
+//! refuses while scatter free spins are held and leaves a loyalty credit
+//! for the next request_spin. A void or cancel refunds the full escrow
+//! like any pending bet. The deferred settle only takes single spins.
+
+use anchor_lang::prelude::*;
+
+use crate::{pool_level, tiers, ErrorCode, PlayerState, SlotsState};
+
+/// Spins in the pending commit; 1 for spins committed any other way.
+pub fn count(player_state: &PlayerState) -> u8 {
+    player_state.spins_remaining.max(1)
+}
+
+/// Stake of one line of one spin of the pending commit.
+pub fn unit_bet(player_state: &PlayerState) -> u64 {
+    let units = player_state.pending_lines.max(1) as u64 * count(player_state) as u64;
+    player_state.pending_bet_amount / units
+}
+
+/// The pool must cover the `spins - 1` caps the commit reserves beyond
+/// the one validate_commit already checked.
+pub fn ensure_covered(
+    state: &SlotsState,
+    tier: u8,
+    spins: u8,
+    treasury_spendable: u64,
+) -> Result<()> {
+    let extra = tiers::payout_cap(state, tier).saturating_mul(spins.saturating_sub(1) as u64);
+    let required = pool_level::required_pool(state, tier).saturating_add(extra);
+    require!(
+        state.total_pool >= required && treasury_spendable >= required,
+        ErrorCode::InsufficientPool
+    );
+    Ok(())
+}
+
+/// Cap reserved for `spins` spins of `spin_cap` each.
+pub fn commit_cap(spin_cap: u64, spins: u8) -> Result<u64> {
+    Ok(spin_cap
+        .checked_mul(spins as u64)
+        .ok_or(ErrorCode::MathOverflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_math::{self, PayoutLimits, JACKPOT_ODDS_ONE};
+    use crate::test_util::{set_clock_slot, zeroed_player, zeroed_state};
+    use crate::*;
+
+    const QUALIFYING_BET: u64 = 10_000_000;
+    const TRIALS: u32 = 20_000;
+
+    fn game() -> SlotsState {
+        set_clock_slot(1_000);
+        let mut state = zeroed_state();
+        init_slots_state(
+            &mut state,
+            Pubkey::new_unique(),
+            None,
+            None,
+            None,
+            Pubkey::new_unique(),
+        )
+        .expect("state initializes");
+        state.total_pool = 1_000_000_000_000;
+        state.jackpot_qualifying_bet = QUALIFYING_BET;
+        // The mini hits 5% of full-odds rolls; the other tiers never.
+        state.jackpots.mini.hit_weight = 50;
+        state.jackpots.major.hit_weight = 0;
+        state.jackpots.grand.hit_weight = 0;
+        state.jackpots.hit_weight_total = 1_000;
+        state
+    }
+
+    /// A player who committed `stake` on `spins` spins.
+    fn committed(state: &mut SlotsState, stake: u64, spins: u8) -> PlayerState {
+        let user = Pubkey::new_unique();
+        let mut player = zeroed_player();
+        init_player_state(&mut player, user, 255, None).expect("player initializes");
+        let commit = CommitTarget {
+            seed_slot: 1_000,
+            seed_slothash: [1; 32],
+            current_slot: 1_000,
+            payout_cap: state.max_payout_per_spin * spins as u64,
+            jackpot_mask: 0b111,
+            lines: 1,
+            spins,
+            outcomes: spins,
+        };
+        record_commit(state, &mut player, &user, Pubkey::new_unique(), stake, commit, 0)
+            .expect("commit books");
+        player
+    }
+
+    /// Reveals of `player`'s commit that award a jackpot.
+    fn jackpot_hits(state: &SlotsState, player: &PlayerState) -> u32 {
+        let limits = PayoutLimits::uniform(u64::MAX);
+        (0..TRIALS)
+            .filter(|trial| {
+                let mut bytes = [0u8; 32];
+                bytes[..4].copy_from_slice(&trial.to_le_bytes());
+                let (outcome, _) = derive_pending_outcome(
+                    player,
+                    &game_math::vrf_hash(&bytes),
+                    limits,
+                    &state.jackpots,
+                    0,
+                    &state.reel_weights,
+                    &state.paytable,
+                    state.wild_symbol,
+                );
+                outcome.jackpot_tier.is_some()
+            })
+            .count() as u32
+    }
+
+    #[test]
+    fn sub_qualifying_spins_share_the_odds_of_their_total_stake() {
+        let mut state = game();
+        let single = committed(&mut state, QUALIFYING_BET, 1);
+        let spins = committed(&mut state, QUALIFYING_BET, MAX_SPINS_PER_COMMIT);
+
+        // Each spin rolls at its own stake's share of the full odds.
+        assert_eq!(single.commit_jackpot_odds, JACKPOT_ODDS_ONE);
+        let per_spin = spins.commit_jackpot_odds * MAX_SPINS_PER_COMMIT as u64;
+        assert!(JACKPOT_ODDS_ONE - per_spin < MAX_SPINS_PER_COMMIT as u64);
+
+        // Ten rolls at a tenth of the odds hit about as often as one roll
+        // at full odds: 5% against 1 - 0.995^10, about 4.9%.
+        let one_roll = jackpot_hits(&state, &single);
+        let ten_rolls = jackpot_hits(&state, &spins);
+        assert!((900..=1_100).contains(&one_roll), "{one_roll} hits");
+        assert!(
+            one_roll.abs_diff(ten_rolls) * 100 <= one_roll * 15,
+            "{ten_rolls} hits in ten rolls against {one_roll} in one"
+        );
+    }
+
+    #[test]
+    fn a_multi_line_spin_rolls_once_on_its_whole_stake() {
+        let mut state = game();
+        let user = Pubkey::new_unique();
+        let mut player = zeroed_player();
+        init_player_state(&mut player, user, 255, None).expect("player initializes");
+        let commit = CommitTarget {
+            seed_slot: 1_000,
+            seed_slothash: [1; 32],
+            current_slot: 1_000,
+            payout_cap: state.max_payout_per_spin,
+            jackpot_mask: 0b111,
+            lines: 5,
+            spins: 1,
+            outcomes: 5,
+        };
+        let stake = QUALIFYING_BET;
+        record_commit(&mut state, &mut player, &user, Pubkey::new_unique(), stake, commit, 0)
+            .expect("commit books");
+        assert_eq!(player.commit_jackpot_odds, JACKPOT_ODDS_ONE);
+        assert_eq!(unit_bet(&player), QUALIFYING_BET / 5);
+    }
+}
@@ -0,0 +1,199 @@
+//! "Must hit by" jackpot ceilings.
+//!
+//! `SlotsState.jackpot_must_hit_by` holds a ceiling per tier (mini, major,
+//! grand; 0 = none). They sit at the end of SlotsState, not in
+//! JackpotPool, because JackpotPool is embedded mid-layout. Once a tier's
+//! amount reaches its ceiling, through contributions or a donation, the
+//! tier is armed: its bit in `SlotsState.jackpot_forced_hits` is set and
+//! JackpotMustHitArmed is emitted.
+//!
+//! The next settle that draws against an armed tier awards it without a
+//! roll (game_math::derive_outcome), biggest tier first, provided the spin
+//! can afford it and its stake gets full jackpot odds. A tier the spin may
+//! not win (see tiers::jackpot_mask) reads as empty and so is never
+//! affordable. An unaffordable tier stays armed, and that spin rolls as
+//! usual. Any hit on a tier disarms it, and JackpotHit reports whether
+//! the ceiling forced it.
+//!
+//! In a Split-mode batch, every eligible entry that peeks an armed tier
+//! is counted as its winner, so they share the pre-batch amount like
+//! any other split hit.
+//!
+//! update_config sets the ceilings and refuses one below its tier's
+//! seed, which would arm the tier again right after every reseed.
+
+use anchor_lang::prelude::*;
+
+use crate::batch_settle::SplitAmounts;
+use crate::game_math::{jackpot_pool, JACKPOT_ODDS_ONE};
+use crate::{tiers, ErrorCode, JackpotMustHitArmed, PlayerState, SlotsState};
+
+/// Every ceiling must be 0 or at least its tier's seed.
+pub fn validate(state: &SlotsState) -> Result<()> {
+    for tier in 0..3u8 {
+        let ceiling = state.jackpot_must_hit_by[tier as usize];
+        require!(
+            ceiling == 0 || ceiling >= jackpot_pool(&state.jackpots, tier).seed,
+            ErrorCode::MustHitBelowSeed
+        );
+    }
+    Ok(())
+}
+
+/// Re-derive which tiers are armed after their amounts or ceilings
+/// changed, announcing the ones newly armed.
+pub fn refresh(state: &mut SlotsState) {
+    let mut armed = 0u8;
+    for tier in 0..3u8 {
+        let ceiling = state.jackpot_must_hit_by[tier as usize];
+        let amount = jackpot_pool(&state.jackpots, tier).amount;
+        if ceiling == 0 || amount < ceiling {
+            continue;
+        }
+        armed |= 1 << tier;
+        if state.jackpot_forced_hits & (1 << tier) == 0 {
+            emit!(JackpotMustHitArmed {
+                tier,
+                amount,
+                must_hit_by: ceiling,
+            });
+        }
+    }
+    state.jackpot_forced_hits = armed;
+}
+
+/// Disarm `tier` once it has been hit.
+pub fn clear(state: &mut SlotsState, tier: u8) {
+    state.jackpot_forced_hits &= !(1 << tier);
+}
+
+/// Tiers the pending spin must award if it can afford them, as a bit per
+/// tier. A Split-mode entry is forced on exactly the split tiers it was
+/// counted as winning, so it settles as it was peeked.
+pub fn forced_tiers(
+    state: &SlotsState,
+    player: &PlayerState,
+    split: Option<&SplitAmounts>,
+) -> u8 {
+    if tiers::committed_jackpot_odds(player) < JACKPOT_ODDS_ONE {
+        return 0;
+    }
+    let mut forced = state.jackpot_forced_hits;
+    for (tier, amount) in split.into_iter().flatten().enumerate() {
+        if let Some(amount) = amount {
+            if *amount > 0 {
+                forced |= 1 << tier;
+            } else {
+                forced &= !(1 << tier);
+            }
+        }
+    }
+    forced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_math::JACKPOT_TIER_MAJOR;
+    use crate::test_util::{emitted, Game};
+    use crate::{JackpotHit, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+    const MAJOR: usize = JACKPOT_TIER_MAJOR as usize;
+
+    /// A game whose major stands at `amount`, where nothing hits by the
+    /// roll and the next commit's contribution takes the major past a
+    /// ceiling one lamport away.
+    fn about_to_arm(amount: u64) -> Game {
+        let mut game = Game::new();
+        game.state.jackpots.hit_weight_total = 0;
+        game.state.jackpots.major.amount = amount;
+        game.state.jackpot_must_hit_by[MAJOR] = amount + 1;
+        game
+    }
+
+    #[test]
+    fn a_ceiling_below_its_seed_is_refused() {
+        let mut state = Game::new().state;
+        state.jackpots.major.seed = 1_000;
+        assert!(validate(&state).is_ok());
+
+        state.jackpot_must_hit_by[MAJOR] = 999;
+        assert_eq!(
+            validate(&state).unwrap_err(),
+            ErrorCode::MustHitBelowSeed.into()
+        );
+        state.jackpot_must_hit_by[MAJOR] = 1_000;
+        assert!(validate(&state).is_ok());
+    }
+
+    #[test]
+    fn contributions_past_the_ceiling_arm_the_tier_once() {
+        let mut game = about_to_arm(BET);
+        emitted::<JackpotMustHitArmed>();
+        game.request(BET).unwrap();
+
+        assert_eq!(game.state.jackpot_forced_hits, 1 << JACKPOT_TIER_MAJOR);
+        let mut events = emitted::<JackpotMustHitArmed>();
+        assert_eq!(events.len(), 1);
+        let event = events.pop().unwrap();
+        assert_eq!(event.tier, JACKPOT_TIER_MAJOR);
+        assert_eq!(event.amount, game.state.jackpots.major.amount);
+        assert_eq!(event.must_hit_by, BET + 1);
+
+        // Still armed on the next refresh, and not announced again.
+        refresh(&mut game.state);
+        assert_eq!(game.state.jackpot_forced_hits, 1 << JACKPOT_TIER_MAJOR);
+        assert!(emitted::<JackpotMustHitArmed>().is_empty());
+    }
+
+    #[test]
+    fn the_next_settle_awards_an_armed_tier_without_a_roll() {
+        let mut game = about_to_arm(BET);
+        game.request(BET).unwrap();
+        let major = game.state.jackpots.major.clone();
+        game.reveal([5; 32]);
+        emitted::<JackpotHit>();
+
+        let settlement = game.settle().unwrap();
+        assert_eq!(settlement.outcome.jackpot_tier, Some(JACKPOT_TIER_MAJOR));
+        assert!(settlement.outcome.jackpot_forced);
+        assert_eq!(settlement.outcome.jackpot_payout, major.amount);
+        assert_eq!(game.state.jackpot_forced_hits, 0);
+        assert_eq!(game.state.jackpots.major.amount, major.seed);
+
+        let event = emitted::<JackpotHit>().pop().expect("hit reported");
+        assert!(event.forced);
+        assert_eq!(event.tier, JACKPOT_TIER_MAJOR);
+    }
+
+    #[test]
+    fn an_unaffordable_tier_stays_armed() {
+        let mut game = about_to_arm(BET);
+        game.request(BET).unwrap();
+        // The armed major has outgrown what the spin may pay.
+        let cap = game.player.commit_payout_cap;
+        game.state.jackpots.major.amount = cap + 1;
+        refresh(&mut game.state);
+        game.reveal([5; 32]);
+
+        let settlement = game.settle().unwrap();
+        assert_eq!(settlement.outcome.jackpot_tier, None);
+        assert_eq!(game.state.jackpot_forced_hits, 1 << JACKPOT_TIER_MAJOR);
+        assert_eq!(game.state.jackpots.major.amount, cap + 1);
+    }
+
+    #[test]
+    fn a_stake_below_full_odds_is_not_forced() {
+        let mut game = about_to_arm(BET);
+        game.state.jackpot_qualifying_bet = 2 * BET;
+        game.request(BET).unwrap();
+        assert_eq!(game.state.jackpot_forced_hits, 1 << JACKPOT_TIER_MAJOR);
+        assert_eq!(forced_tiers(&game.state, &game.player, None), 0);
+        game.reveal([5; 32]);
+
+        let settlement = game.settle().unwrap();
+        assert_eq!(settlement.outcome.jackpot_tier, None);
+        assert_eq!(game.state.jackpot_forced_hits, 1 << JACKPOT_TIER_MAJOR);
+    }
+}
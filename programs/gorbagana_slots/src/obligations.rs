@@ -0,0 +1,151 @@
+//! Shared ledger of pool funds earmarked for future payouts.
+//!
+//! Tournaments, vouchers and pending awards promise lamports that still sit
+//! in the pool. Each of them registers its outstanding amount here with
+//! [`EarmarkedObligations::earmark`] and gives it back with
+//! [`EarmarkedObligations::release`] once the obligation resolves, so the
+//! pool floor (`required_floor` in the program) is computed in one place
+//! instead of once per feature.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, SlotsState};
+
+/// Subsystems that can hold an earmark.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EarmarkKind {
+    Tournament,
+    Voucher,
+    PendingAward,
+}
+
+pub const EARMARK_KINDS: usize = 3;
+
+/// Outstanding earmarked lamports per subsystem, indexed by EarmarkKind.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct EarmarkedObligations {
+    pub amounts: [u64; EARMARK_KINDS],
+}
+
+impl EarmarkedObligations {
+    pub fn get(&self, kind: EarmarkKind) -> u64 {
+        self.amounts[kind as usize]
+    }
+
+    pub fn earmark(&mut self, kind: EarmarkKind, amount: u64) -> Result<()> {
+        let slot = &mut self.amounts[kind as usize];
+        *slot = slot.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Release part of an earmark. Releasing more than is held is a bug in
+    /// the subsystem and fails rather than saturating.
+    pub fn release(&mut self, kind: EarmarkKind, amount: u64) -> Result<()> {
+        let slot = &mut self.amounts[kind as usize];
+        *slot = slot.checked_sub(amount).ok_or(ErrorCode::EarmarkUnderflow)?;
+        Ok(())
+    }
+
+    pub fn total(&self) -> Result<u64> {
+        self.amounts
+            .iter()
+            .try_fold(0u64, |acc, a| acc.checked_add(*a))
+            .ok_or_else(|| error!(ErrorCode::MathOverflow))
+    }
+}
+
+/// Ledger invariants. `subsystem_outstanding` is each subsystem's own
+/// count of what it owes, indexed by EarmarkKind; every entry must match
+/// the ledger exactly, and the ledger must fit inside the pool.
+pub fn validate_invariants(
+    slots_state: &SlotsState,
+    subsystem_outstanding: &[u64; EARMARK_KINDS],
+) -> Result<()> {
+    let ledger = &slots_state.earmarks;
+    for (held, owed) in ledger.amounts.iter().zip(subsystem_outstanding) {
+        require!(held == owed, ErrorCode::EarmarkMismatch);
+    }
+    require!(
+        ledger.total()? <= slots_state.total_pool,
+        ErrorCode::ObligationsExceedPool
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed_state;
+
+    const KINDS: [EarmarkKind; EARMARK_KINDS] = [
+        EarmarkKind::Tournament,
+        EarmarkKind::Voucher,
+        EarmarkKind::PendingAward,
+    ];
+
+    /// A pool of 1_000 holding an earmark of 100 per kind, and what each
+    /// subsystem counts as owed.
+    fn earmarked() -> (SlotsState, [u64; EARMARK_KINDS]) {
+        let mut state = zeroed_state();
+        state.total_pool = 1_000;
+        for kind in KINDS {
+            state.earmarks.earmark(kind, 100).unwrap();
+        }
+        (state, [100; EARMARK_KINDS])
+    }
+
+    #[test]
+    fn a_matching_ledger_inside_the_pool_holds() {
+        let (mut state, owed) = earmarked();
+        validate_invariants(&state, &owed).unwrap();
+        // Exactly the pool is still inside it.
+        state.total_pool = 300;
+        validate_invariants(&state, &owed).unwrap();
+    }
+
+    #[test]
+    fn each_kind_out_of_step_with_its_subsystem_is_reported() {
+        for kind in KINDS {
+            let (mut state, owed) = earmarked();
+            // The subsystem forgot to earmark, then to release.
+            state.earmarks.release(kind, 1).unwrap();
+            assert_eq!(
+                validate_invariants(&state, &owed).unwrap_err(),
+                ErrorCode::EarmarkMismatch.into(),
+                "{kind:?} under"
+            );
+            state.earmarks.earmark(kind, 2).unwrap();
+            assert_eq!(
+                validate_invariants(&state, &owed).unwrap_err(),
+                ErrorCode::EarmarkMismatch.into(),
+                "{kind:?} over"
+            );
+        }
+    }
+
+    #[test]
+    fn obligations_past_the_pool_are_reported() {
+        let (mut state, owed) = earmarked();
+        state.total_pool = 299;
+        assert_eq!(
+            validate_invariants(&state, &owed).unwrap_err(),
+            ErrorCode::ObligationsExceedPool.into()
+        );
+    }
+
+    #[test]
+    fn the_ledger_refuses_to_underflow_or_overflow() {
+        let (mut state, _) = earmarked();
+        assert_eq!(
+            state.earmarks.release(EarmarkKind::Voucher, 101).unwrap_err(),
+            ErrorCode::EarmarkUnderflow.into()
+        );
+        assert_eq!(state.earmarks.get(EarmarkKind::Voucher), 100);
+        assert_eq!(
+            state.earmarks.earmark(EarmarkKind::Voucher, u64::MAX).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        state.earmarks.amounts = [u64::MAX, 1, 0];
+        assert_eq!(state.earmarks.total().unwrap_err(), ErrorCode::MathOverflow.into());
+    }
+}
@@ -0,0 +1,171 @@
+//! Sponsored onboarding budget.
+//!
+//! `init_player_sponsored` lets the operator's `onboarding_sponsor` key
+//! pay PlayerState rent for a new user. To keep a bot farm from draining
+//! that wallet:
+//!
+//! - the user must co-sign, so each init costs the attacker a real key;
+//! - a user is sponsored at most once, because the PlayerState PDA can
+//!   only be created once and this program never closes it;
+//! - at most `sponsored_daily_budget` sponsored inits land per UTC day.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, SlotsState};
+
+/// Count one sponsored init against today's budget. Returns how many
+/// sponsored inits have landed today, including this one.
+pub fn consume_budget(state: &mut SlotsState, now_ts: i64) -> Result<u32> {
+    let (today, advanced) =
+        time::advance_bucket(state.sponsored_day, now_ts, time::SECONDS_PER_DAY);
+    if advanced {
+        state.sponsored_day = today;
+        state.sponsored_today = 0;
+    }
+    require!(
+        state.sponsored_today < state.sponsored_daily_budget,
+        ErrorCode::SponsoredBudgetExhausted
+    );
+    state.sponsored_today += 1;
+    state.sponsored_total = state
+        .sponsored_total
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(state.sponsored_today)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::entrypoint::ProgramResult;
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{pda, program_error, simulate, zeroed_state, Fixture, Game};
+    use crate::{instruction, PlayerState, ACCOUNT_INDEX_SEED, PLAYER_SEED};
+
+    const BUDGET: u32 = 2;
+
+    /// init_player_sponsored's accounts for a fresh user of `state`.
+    fn onboarding(state: &Fixture, sponsor: &Fixture) -> [Fixture; 6] {
+        let user = Pubkey::new_unique();
+        let machine_id = state.read::<SlotsState>().machine_id;
+        let machine = crate::machine_seed(&machine_id);
+        [
+            state.clone(),
+            Fixture::uninit(pda(&[PLAYER_SEED, machine, user.as_ref()]).0, PlayerState::LEN),
+            Fixture::wallet(pda(&[ACCOUNT_INDEX_SEED, user.as_ref()]).0, 0),
+            Fixture::wallet(user, 0).signer(),
+            sponsor.clone(),
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    fn onboard(accounts: &mut [Fixture; 6]) -> ProgramResult {
+        simulate(accounts, &instruction::InitPlayerSponsored {}.data())
+    }
+
+    #[test]
+    fn the_budget_resets_each_utc_day() {
+        let mut state = zeroed_state();
+        state.sponsored_daily_budget = BUDGET;
+        let day = time::SECONDS_PER_DAY;
+
+        assert_eq!(consume_budget(&mut state, day + 5).unwrap(), 1);
+        assert_eq!(consume_budget(&mut state, 2 * day - 1).unwrap(), 2);
+        assert_eq!(
+            consume_budget(&mut state, 2 * day - 1).unwrap_err(),
+            ErrorCode::SponsoredBudgetExhausted.into()
+        );
+        assert_eq!(consume_budget(&mut state, 2 * day).unwrap(), 1);
+        assert_eq!(state.sponsored_today, 1);
+        assert_eq!(state.sponsored_total, 3);
+
+        state.sponsored_daily_budget = 0;
+        assert_eq!(
+            consume_budget(&mut state, 3 * day).unwrap_err(),
+            ErrorCode::SponsoredBudgetExhausted.into()
+        );
+    }
+
+    #[test]
+    fn only_the_authority_sets_the_sponsor() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut authority = game.authority();
+        let sponsor = Pubkey::new_unique();
+        let set = instruction::SetOnboardingSponsor { sponsor, daily_budget: BUDGET }.data();
+
+        let mut no_metrics = Fixture::omitted();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set).unwrap();
+        let state = state.read::<SlotsState>();
+        assert_eq!(state.onboarding_sponsor, sponsor);
+        assert_eq!(state.sponsored_daily_budget, BUDGET);
+    }
+
+    #[test]
+    fn the_sponsor_pays_up_to_the_daily_budget() {
+        let mut game = Game::new();
+        let sponsor = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        game.state.onboarding_sponsor = sponsor.key;
+        game.state.sponsored_daily_budget = BUDGET;
+        let [mut state, ..] = game.fixtures();
+
+        for today in 1..=BUDGET {
+            let mut accounts = onboarding(&state, &sponsor);
+            onboard(&mut accounts).unwrap();
+            assert_eq!(accounts[1].read::<PlayerState>().owner, accounts[3].key);
+            assert_eq!(accounts[3].lamports, 0);
+            assert!(accounts[4].lamports < sponsor.lamports);
+            state = accounts[0].clone();
+            assert_eq!(state.read::<SlotsState>().sponsored_today, today);
+        }
+        let mut accounts = onboarding(&state, &sponsor);
+        assert_eq!(
+            onboard(&mut accounts),
+            Err(program_error(ErrorCode::SponsoredBudgetExhausted))
+        );
+
+        // The next UTC day opens a fresh budget.
+        game.advance(time::SECONDS_PER_DAY as u64);
+        let mut accounts = onboarding(&state, &sponsor);
+        onboard(&mut accounts).unwrap();
+        let state = accounts[0].read::<SlotsState>();
+        assert_eq!(state.sponsored_today, 1);
+        assert_eq!(state.sponsored_total, u64::from(BUDGET) + 1);
+    }
+
+    #[test]
+    fn the_user_cosigns_and_is_sponsored_once() {
+        let mut game = Game::new();
+        let sponsor = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        game.state.onboarding_sponsor = sponsor.key;
+        game.state.sponsored_daily_budget = BUDGET;
+        let [state, ..] = game.fixtures();
+
+        // Any other payer is refused.
+        let stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut accounts = onboarding(&state, &stranger);
+        assert_eq!(onboard(&mut accounts), Err(program_error(ErrorCode::Unauthorized)));
+
+        // The user must sign.
+        let mut accounts = onboarding(&state, &sponsor);
+        accounts[3].signer = false;
+        let unsigned =
+            anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::AccountNotSigner);
+        assert_eq!(onboard(&mut accounts), Err(unsigned.into()));
+
+        // A second sponsored init of the same user finds the account taken.
+        let mut accounts = onboarding(&state, &sponsor);
+        onboard(&mut accounts).unwrap();
+        accounts[4] = sponsor.clone();
+        assert_eq!(onboard(&mut accounts), Err(ProgramError::AccountAlreadyInitialized));
+        assert_eq!(accounts[0].read::<SlotsState>().sponsored_total, 1);
+    }
+}
@@ -0,0 +1,148 @@
+//! Operator fee on every bet.
+//!
+//! `operator_fee_bps` of each committed stake is taken before the stake
+//! joins `total_pool` and booked to `operator_fee_balance`. Like the
+//! community share, the balance sits in the treasury next to the pool, so
+//! reservations, min_pool_threshold and the jackpots never count it.
+//! `total_wagered` and the RTP stats still see the full bet.
+//!
+//! `withdraw_fees` moves the booked balance into the fee vault PDA and
+//! pays the authority out of the vault. The sweep is charged against the
+//! extraction budget (extraction.rs); the vault pays no one else, so the
+//! payout is not charged again. A voided spin takes its fee back
+//! before the bet is refunded; if the fee was already withdrawn, the pool
+//! covers the difference.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::machines::machine_seed;
+use crate::{extraction, ErrorCode, PlayerState, SlotsState, FEE_VAULT_SEED};
+
+/// Highest settable operator fee.
+pub const MAX_OPERATOR_FEE_BPS: u16 = 2_000;
+
+pub fn fee_for(bps: u16, stake: u64) -> u64 {
+    ((stake as u128) * (bps as u128) / 10_000) as u64
+}
+
+/// Book the operator fee of a committed stake and return it. The caller
+/// adds the rest of the stake to `total_pool`.
+pub fn take(state: &mut SlotsState, player_state: &mut PlayerState, stake: u64) -> Result<u64> {
+    let fee = fee_for(state.operator_fee_bps, stake);
+    state.operator_fee_balance = state
+        .operator_fee_balance
+        .checked_add(fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    state.operator_fees_accrued = state.operator_fees_accrued.saturating_add(fee);
+    player_state.pending_operator_fee = fee;
+    Ok(fee)
+}
+
+/// Take back the fee of a voided spin, as far as it is still booked.
+/// Returns the amount taken back.
+pub fn unwind(state: &mut SlotsState, player_state: &mut PlayerState) -> u64 {
+    let fee = player_state.pending_operator_fee.min(state.operator_fee_balance);
+    state.operator_fee_balance -= fee;
+    state.operator_fees_accrued = state.operator_fees_accrued.saturating_sub(fee);
+    player_state.pending_operator_fee = 0;
+    fee
+}
+
+/// What a withdraw_fees moves: `swept` from the treasury into the fee
+/// vault, then `amount` from the vault to the authority.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FeeWithdrawal {
+    pub swept: u64,
+    pub amount: u64,
+}
+
+/// Book a withdraw_fees against `vault_available`, the vault's balance
+/// above rent before the sweep. `amount` 0 takes everything. Only the
+/// sweep out of the treasury is charged to the extraction budget.
+pub fn withdraw(
+    state: &mut SlotsState,
+    vault_available: u64,
+    amount: u64,
+    now_slot: u64,
+) -> Result<FeeWithdrawal> {
+    let swept = state.operator_fee_balance;
+    state.operator_fee_balance = 0;
+
+    let available = vault_available
+        .checked_add(swept)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let amount = if amount == 0 { available } else { amount };
+    require!(amount <= available, ErrorCode::InsufficientFunds);
+    extraction::charge(state, swept, now_slot)?;
+    Ok(FeeWithdrawal { swept, amount })
+}
+
+/// Pay `amount` out of `machine_id`'s fee vault PDA.
+pub fn pay_out<'info>(
+    system_program: &Program<'info, System>,
+    fee_vault: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    machine_id: &[u8; 32],
+    fee_vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let bump = [fee_vault_bump];
+    let signer_seeds: &[&[u8]] = &[FEE_VAULT_SEED, machine_seed(machine_id), &bump];
+    let signer = [signer_seeds];
+    let transfer_ctx = CpiContext::new(
+        system_program.to_account_info(),
+        system_program::Transfer {
+            from: fee_vault.to_account_info(),
+            to,
+        },
+    )
+    .with_signer(&signer);
+    system_program::transfer(transfer_ctx, amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed_state;
+
+    #[test]
+    fn fee_rounds_down_and_never_exceeds_the_stake() {
+        assert_eq!(fee_for(0, 1_000_000), 0);
+        assert_eq!(fee_for(250, 1_000_000), 25_000);
+        assert_eq!(fee_for(250, 39), 0);
+        assert_eq!(fee_for(10_000, u64::MAX), u64::MAX);
+        assert_eq!(fee_for(MAX_OPERATOR_FEE_BPS, 10), 2);
+    }
+
+    #[test]
+    fn withdraw_charges_the_sweep_to_the_extraction_budget() {
+        let mut state = zeroed_state();
+        state.total_pool = 100_000;
+        state.extraction_budget_bps = extraction::DEFAULT_EXTRACTION_BUDGET_BPS;
+        state.operator_fee_balance = 15_000;
+
+        let taken = withdraw(&mut state, 2_000, 0, 1).unwrap();
+        assert_eq!(
+            taken,
+            FeeWithdrawal {
+                swept: 15_000,
+                amount: 17_000
+            }
+        );
+        assert_eq!(state.operator_fee_balance, 0);
+        assert_eq!(extraction::remaining(&state), 5_000);
+
+        // A sweep beyond what is left fails and books nothing.
+        state.operator_fee_balance = 6_000;
+        assert!(withdraw(&mut state, 0, 0, 2).is_err());
+        assert_eq!(extraction::remaining(&state), 5_000);
+
+        // More than the vault holds after the sweep.
+        state.operator_fee_balance = 1_000;
+        assert!(withdraw(&mut state, 0, 1_001, 3).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Operator keys may not play.
+//!
+//! The house should not bet against its own pool. Every commit goes
+//! through validate_commit, which refuses a player whose key holds an
+//! operator role: the authority, a proposed authority, the recovery and
+//! community authorities, the onboarding sponsor, or one of the staff
+//! wallets the authority registers with set_staff_wallets. Roles are read
+//! from SlotsState at commit time, so a rotation takes effect at once:
+//! the outgoing key may play again and the incoming one may not.
+//!
+//! A refused commit emits [`OperatorPlayBlocked`] before failing. The
+//! event does not persist, but it stays in the failed transaction's logs
+//! for compliance tooling to pick up. A refused respin only skips the
+//! respin, so there the event lands on chain.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, OperatorPlayBlocked, SlotsState};
+
+/// Staff wallets the authority may register.
+pub const MAX_STAFF_WALLETS: usize = 8;
+
+/// Role of an operator key, as reported in OperatorPlayBlocked.
+pub const ROLE_AUTHORITY: u8 = 1;
+pub const ROLE_PENDING_AUTHORITY: u8 = 2;
+pub const ROLE_RECOVERY_AUTHORITY: u8 = 3;
+pub const ROLE_COMMUNITY_AUTHORITY: u8 = 4;
+pub const ROLE_ONBOARDING_SPONSOR: u8 = 5;
+pub const ROLE_STAFF: u8 = 6;
+
+/// Operator role held by `key`, if any. Unset roles (the default key)
+/// never match.
+pub fn operator_role(state: &SlotsState, key: &Pubkey) -> Option<u8> {
+    if *key == Pubkey::default() {
+        return None;
+    }
+    let roles = [
+        (state.authority, ROLE_AUTHORITY),
+        (state.pending_authority, ROLE_PENDING_AUTHORITY),
+        (state.recovery_authority, ROLE_RECOVERY_AUTHORITY),
+        (state.community_authority, ROLE_COMMUNITY_AUTHORITY),
+        (state.onboarding_sponsor, ROLE_ONBOARDING_SPONSOR),
+    ];
+    roles
+        .iter()
+        .find(|(role_key, _)| role_key == key)
+        .map(|&(_, role)| role)
+        .or_else(|| state.staff_wallets.contains(key).then_some(ROLE_STAFF))
+}
+
+pub fn is_operator_key(state: &SlotsState, key: &Pubkey) -> bool {
+    operator_role(state, key).is_some()
+}
+
+/// Refuse a bet from an operator key.
+pub fn ensure_not_operator(state: &SlotsState, player: &Pubkey) -> Result<()> {
+    if let Some(role) = operator_role(state, player) {
+        emit!(OperatorPlayBlocked {
+            player: *player,
+            role,
+            slot: Clock::get()?.slot,
+        });
+        return err!(ErrorCode::OperatorCannotPlay);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{emitted, program_error, simulate, Fixture, Game};
+    use crate::{instruction, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    /// Where a role's key lives in SlotsState.
+    type RoleKey = fn(&mut SlotsState) -> &mut Pubkey;
+
+    /// Commit as `game`'s player and report the role that blocked it.
+    fn blocked_as(game: &Game) -> Option<u8> {
+        emitted::<OperatorPlayBlocked>();
+        match game.clone().request(BET) {
+            Ok(()) => None,
+            Err(err) => {
+                assert_eq!(err, ErrorCode::OperatorCannotPlay.into());
+                let event = emitted::<OperatorPlayBlocked>().pop().expect("blocked attempt logged");
+                assert_eq!((event.player, event.slot), (game.user, game.slot));
+                Some(event.role)
+            }
+        }
+    }
+
+    #[test]
+    fn every_role_is_refused() {
+        let game = Game::new();
+        assert_eq!(blocked_as(&game), None);
+
+        let roles: [(RoleKey, u8); 5] = [
+            (|s| &mut s.authority, ROLE_AUTHORITY),
+            (|s| &mut s.pending_authority, ROLE_PENDING_AUTHORITY),
+            (|s| &mut s.recovery_authority, ROLE_RECOVERY_AUTHORITY),
+            (|s| &mut s.community_authority, ROLE_COMMUNITY_AUTHORITY),
+            (|s| &mut s.onboarding_sponsor, ROLE_ONBOARDING_SPONSOR),
+        ];
+        for (role_key, role) in roles {
+            let mut operator = game.clone();
+            *role_key(&mut operator.state) = game.user;
+            assert_eq!(blocked_as(&operator), Some(role));
+        }
+        let mut staff = game.clone();
+        staff.state.staff_wallets[MAX_STAFF_WALLETS - 1] = game.user;
+        assert_eq!(blocked_as(&staff), Some(ROLE_STAFF));
+
+        // Unset roles hold the default key, which is nobody.
+        assert!(!is_operator_key(&game.state, &Pubkey::default()));
+    }
+
+    #[test]
+    fn a_rotation_moves_the_block_with_the_role() {
+        let mut game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut outgoing = game.authority();
+        let mut incoming = Fixture::wallet(game.user, 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let propose = instruction::ProposeAuthority { new_authority: game.user }.data();
+
+        simulate([&mut state, &mut outgoing, &mut no_metrics], &propose).unwrap();
+        game.state = state.read::<SlotsState>();
+        assert_eq!(blocked_as(&game), Some(ROLE_PENDING_AUTHORITY));
+
+        simulate([&mut state, &mut incoming], &instruction::AcceptAuthority {}.data()).unwrap();
+        game.state = state.read::<SlotsState>();
+        assert_eq!(blocked_as(&game), Some(ROLE_AUTHORITY));
+
+        // The outgoing authority is a player like any other now.
+        let mut former = game.clone();
+        former.user = outgoing.key;
+        assert_eq!(blocked_as(&former), None);
+    }
+
+    #[test]
+    fn staff_wallets_are_set_by_the_authority_and_apply_at_once() {
+        let mut game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let mut wallets = [Pubkey::default(); MAX_STAFF_WALLETS];
+        wallets[0] = game.user;
+        let set = |wallets| instruction::SetStaffWallets { wallets }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(wallets)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(wallets)).unwrap();
+        game.state = state.read::<SlotsState>();
+        assert_eq!(blocked_as(&game), Some(ROLE_STAFF));
+
+        // Every commit instruction refuses the staff wallet.
+        game.randomness.seed(game.slot, 1);
+        let spins = instruction::RequestSpins {
+            randomness_account: game.randomness.key,
+            bet_amount: BET,
+            count: 2,
+        };
+        for data in [game.request_spin_data(BET), spins.data()] {
+            let mut accounts = game.request_spin_fixtures();
+            assert_eq!(
+                simulate(&mut accounts, &data),
+                Err(program_error(ErrorCode::OperatorCannotPlay))
+            );
+        }
+
+        let cleared = [Pubkey::default(); MAX_STAFF_WALLETS];
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(cleared)).unwrap();
+        game.state = state.read::<SlotsState>();
+        assert_eq!(blocked_as(&game), None);
+    }
+}
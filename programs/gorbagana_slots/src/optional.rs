@@ -0,0 +1,299 @@
+//! Handling of optional accounts.
+//!
+//! Wallets simulate a transaction before asking for a signature, and a
+//! failed simulation shows up as a red warning. Leaving out an optional
+//! account must therefore never fail an instruction unless the current
+//! configuration really needs it. Every optional path goes through these
+//! helpers, so an omission is either a logged skip
+//! (`optional account absent: <name>`) or one documented error,
+//! OptionalAccountMissing, logged as `optional account required: <name>`.
+//!
+//! A settle someone other than the player submits (a crank) must not
+//! drop accounts the player benefits from. The player's own settle
+//! records the VIP token account and leaderboard it used in PlayerState;
+//! a cranked settle must then pass those same accounts
+//! (RecordedAccountMismatch otherwise), and must call a registered
+//! settle hook.
+//!
+//! | Account             | When absent                                   |
+//! |---------------------|-----------------------------------------------|
+//! | `metrics`           | snapshot skipped                              |
+//! | `rtp_history`       | hourly bucket skipped                         |
+//! | `receipt_tree`      | receipt not appended                          |
+//! | `epoch_report_page` | epoch close deferred to a later call          |
+//! | `wager_race`        | settle not counted towards the race           |
+//! | `referral_earnings` | required when the player has a referrer       |
+//! | `vip_token_account` | cranked settle: required when recorded        |
+//! | `leaderboard`       | cranked settle: required when recorded        |
+//! | `symbol_meta`       | config hash uses zero names and hashes        |
+//! | settle hook         | hook not invoked; unusable ones are skipped;  |
+//! |                     | cranked settle: required when one is set      |
+//! | `allowlist`         | required in beta mode, else unused            |
+//! | `oracle_queue`      | required while the queue whitelist is set     |
+//! | `treasury_token`    | required in a token deployment (accounting)   |
+
+use std::ops::Deref;
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// Log the skip for an optional account that was left out.
+pub fn note_absent(name: &str) {
+    msg!("optional account absent: {}", name);
+}
+
+/// Pass `account` through, logging a skip when it is absent.
+pub fn present<T>(account: Option<T>, name: &str) -> Option<T> {
+    if account.is_none() {
+        note_absent(name);
+    }
+    account
+}
+
+/// Unwrap an optional account the current configuration requires.
+pub fn required<T>(account: Option<T>, name: &str) -> Result<T> {
+    match account {
+        Some(account) => Ok(account),
+        None => {
+            msg!("optional account required: {}", name);
+            err!(ErrorCode::OptionalAccountMissing)
+        }
+    }
+}
+
+/// An optional account the player's state records as `recorded`
+/// (default = none), on a settle the player did not submit: it must be
+/// passed, and be that account.
+pub fn recorded<T>(account: Option<T>, recorded: &Pubkey, name: &str) -> Result<Option<T>>
+where
+    T: Deref,
+    T::Target: Key,
+{
+    if *recorded == Pubkey::default() {
+        return Ok(present(account, name));
+    }
+    let account = required(account, name)?;
+    require_keys_eq!(account.key(), *recorded, ErrorCode::RecordedAccountMismatch);
+    Ok(Some(account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{pda, program_error, simulate, zeroed, Fixture, Game};
+    use crate::*;
+    use crate::ErrorCode;
+    use anchor_lang::InstructionData;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn a_recorded_account_must_be_passed_and_match() {
+        let kept = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let none = Pubkey::default();
+
+        assert_eq!(recorded(None::<&Pubkey>, &none, "x").unwrap(), None);
+        assert_eq!(recorded(Some(&other), &none, "x").unwrap(), Some(&other));
+        assert_eq!(recorded(Some(&kept), &kept, "x").unwrap(), Some(&kept));
+        assert_eq!(
+            recorded(None::<&Pubkey>, &kept, "x").unwrap_err(),
+            ErrorCode::OptionalAccountMissing.into()
+        );
+        assert_eq!(
+            recorded(Some(&other), &kept, "x").unwrap_err(),
+            ErrorCode::RecordedAccountMismatch.into()
+        );
+    }
+
+    // request_spin's optional accounts, by their place in its context.
+    const ALLOWLIST: usize = 5;
+    const ORACLE_QUEUE: usize = 6;
+
+    /// request_spin's accounts for a commit on `game`'s seeded
+    /// randomness, every optional one passed.
+    fn request_spin_accounts(game: &Game) -> Vec<Fixture> {
+        let mut accounts = game.request_spin_fixtures();
+        let allowlist = Allowlist {
+            slots_state: accounts[0].key,
+            page: 0,
+            bump: 0,
+            entries: vec![game.user],
+        };
+        let mut queue = vec![0; 8];
+        queue.extend_from_slice(game.state.oracle_queues[0].authority.as_ref());
+        accounts[ALLOWLIST] = Fixture::account(Pubkey::new_unique(), &allowlist);
+        accounts[ORACLE_QUEUE] = Fixture {
+            key: game.randomness.data.queue,
+            owner: SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+            data: queue,
+            writable: false,
+            ..Fixture::wallet(Pubkey::default(), 1)
+        };
+        accounts
+    }
+
+    #[test]
+    fn request_spin_runs_with_each_optional_account_omitted() {
+        let mut game = Game::new();
+        game.randomness.seed(game.slot, 0);
+        game.randomness.data.queue = Pubkey::new_unique();
+        let data = game.request_spin_data(DEFAULT_MIN_BET);
+
+        // Beta mode needs the allowlist, a queue whitelist the queue.
+        let mut gated = game.clone();
+        gated.state.beta_mode = true;
+        gated.state.oracle_queues[0] = OracleQueueEntry {
+            authority: Pubkey::new_unique(),
+            expected_reveal_slots: 1,
+        };
+        for (game, needed) in [(&game, None), (&gated, Some([ALLOWLIST, ORACLE_QUEUE]))] {
+            let mut accounts = request_spin_accounts(game);
+            simulate(&mut accounts, &data).expect("commits with every account");
+            assert!(accounts[1].read::<PlayerState>().has_pending_spin);
+
+            for omitted in [ALLOWLIST, ORACLE_QUEUE] {
+                let mut accounts = request_spin_accounts(game);
+                accounts[omitted] = Fixture::omitted();
+                let result = simulate(&mut accounts, &data);
+                if needed.is_some_and(|needed| needed.contains(&omitted)) {
+                    assert_eq!(
+                        result.unwrap_err(),
+                        program_error(ErrorCode::OptionalAccountMissing)
+                    );
+                } else {
+                    result.expect("commits without it");
+                    assert!(accounts[1].read::<PlayerState>().has_pending_spin);
+                }
+            }
+        }
+    }
+
+    // settle_spin's optional accounts, by their place in its context.
+    const RTP_HISTORY: usize = 6;
+    const METRICS: usize = 7;
+    const RECEIPT_TREE: usize = 8;
+    const EPOCH_REPORT_PAGE: usize = 9;
+    const WAGER_RACE: usize = 10;
+    const REFERRAL_EARNINGS: usize = 11;
+    const LEADERBOARD: usize = 12;
+    const VIP_TOKEN_ACCOUNT: usize = 13;
+    const SETTLE_OPTIONAL: [usize; 8] = [
+        RTP_HISTORY,
+        METRICS,
+        RECEIPT_TREE,
+        EPOCH_REPORT_PAGE,
+        WAGER_RACE,
+        REFERRAL_EARNINGS,
+        LEADERBOARD,
+        VIP_TOKEN_ACCOUNT,
+    ];
+
+    /// settle_spin's accounts for `game`'s revealed spin, every optional
+    /// one passed, settled by the player or by `cranker`.
+    fn settle_spin_accounts(game: &Game, cranker: Option<Pubkey>) -> Vec<Fixture> {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use anchor_spl::token::spl_token::state::{Account as SplAccount, AccountState};
+
+        let mut accounts = game.settle_spin_fixtures();
+        if let Some(key) = cranker {
+            accounts[3] = Fixture::wallet(key, 1_000_000_000).signer();
+        }
+        let state_key = accounts[0].key;
+        let page = game.state.epoch_report_page.to_le_bytes();
+        let (page_key, page_bump) = pda(&[EPOCH_REPORT_SEED, state_key.as_ref(), &page]);
+        let (rtp_history, _) = pda(&[RTP_HISTORY_SEED, state_key.as_ref()]);
+        let (metrics, _) = pda(&[METRICS_SEED, state_key.as_ref()]);
+        let (receipt_tree, _) = pda(&[RECEIPT_TREE_SEED, state_key.as_ref()]);
+        let referrer = game.player.referrer;
+        let (referral_key, referral_bump) =
+            pda(&[REFERRAL_SEED, state_key.as_ref(), referrer.as_ref()]);
+
+        let mut report_page: EpochReportPage = zeroed(EpochReportPage::LEN);
+        report_page.slots_state = state_key;
+        report_page.bump = page_bump;
+        let mut race: WagerRace = zeroed(WagerRace::LEN);
+        race.slots_state = state_key;
+        let mut referral: ReferralEarnings = zeroed(ReferralEarnings::LEN);
+        referral.referrer = referrer;
+        referral.slots_state = state_key;
+        referral.bump = referral_bump;
+        let mut board: Leaderboard = zeroed(Leaderboard::LEN);
+        board.slots_state = state_key;
+        board.epoch = leaderboard::epoch_at(&game.state, game.slot);
+        board.epoch_slots = game.state.leaderboard_epoch_slots;
+        let mut token = vec![0; SplAccount::LEN];
+        SplAccount {
+            mint: game.state.vip_config.mint,
+            owner: game.user,
+            amount: game.state.vip_config.min_balance,
+            state: AccountState::Initialized,
+            ..SplAccount::default()
+        }
+        .pack_into_slice(&mut token);
+
+        accounts[RTP_HISTORY] = Fixture::zero_copy(rtp_history, &RtpHistory::zeroed());
+        accounts[METRICS] = Fixture::zero_copy(metrics, &Metrics::zeroed());
+        accounts[RECEIPT_TREE] = Fixture::zero_copy(receipt_tree, &ReceiptTree::zeroed());
+        accounts[EPOCH_REPORT_PAGE] = Fixture::account(page_key, &report_page);
+        accounts[WAGER_RACE] = Fixture::account(Pubkey::new_unique(), &race);
+        accounts[REFERRAL_EARNINGS] = Fixture::account(referral_key, &referral);
+        accounts[LEADERBOARD] = Fixture::account(game.player.leaderboard, &board);
+        accounts[VIP_TOKEN_ACCOUNT] = Fixture {
+            key: game.player.vip_token_account,
+            owner: anchor_spl::token::ID,
+            data: token,
+            writable: false,
+            ..Fixture::wallet(Pubkey::default(), 1)
+        };
+        accounts
+    }
+
+    #[test]
+    fn settle_spin_runs_with_each_optional_account_omitted() {
+        // A win, so a crank must pass the board the player's last settle
+        // recorded, by a referred VIP player.
+        let mut game = Game::new();
+        game.state.vip_config = VipConfig {
+            mint: Pubkey::new_unique(),
+            min_balance: 1,
+            rebate_bps: 100,
+        };
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        let win = game.reveal_where(|settlement| settlement.total_payout > 0);
+        game.reveal(win);
+        game.player.referrer = Pubkey::new_unique();
+        game.player.vip_token_account = Pubkey::new_unique();
+        game.player.leaderboard = Pubkey::new_unique();
+        game.player.leaderboard_epoch = leaderboard::epoch_at(&game.state, game.slot);
+
+        let data = crate::instruction::SettleSpin {}.data();
+        for cranker in [None, Some(Pubkey::new_unique())] {
+            let mut accounts = settle_spin_accounts(&game, cranker);
+            simulate(&mut accounts, &data).expect("settles with every account");
+            assert!(!accounts[1].read::<PlayerState>().has_pending_spin);
+
+            for omitted in SETTLE_OPTIONAL {
+                let mut accounts = settle_spin_accounts(&game, cranker);
+                accounts[omitted] = Fixture::omitted();
+                let expected = match omitted {
+                    REFERRAL_EARNINGS => Err(ErrorCode::OptionalAccountMissing),
+                    LEADERBOARD | VIP_TOKEN_ACCOUNT if cranker.is_some() => {
+                        Err(ErrorCode::OptionalAccountMissing)
+                    }
+                    _ => Ok(()),
+                };
+                let result = simulate(&mut accounts, &data);
+                assert_eq!(
+                    result,
+                    expected.map_err(program_error),
+                    "omitting account {omitted}, cranked: {}",
+                    cranker.is_some()
+                );
+                if result.is_ok() {
+                    assert!(!accounts[1].read::<PlayerState>().has_pending_spin);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Per-spin randomness (oracle) cost accounting.
+//!
+//! Switchboard charges for each randomness request off-chain. With
+//! `oracle_cost_lamports` set, every committed spin books that cost in
+//! `oracle_costs_paid` so the spend shows up on-chain next to game revenue.
+//! Who bears it depends on [`OracleCostMode`]:
+//!
+//! - `PlayerPays`: the player is charged bet + cost; the cost goes to the
+//!   fee balance and is counted in `oracle_costs_charged`.
+//! - `HouseAbsorbs`: the cost is moved from the fee balance into the pool
+//!   (as far as the fee balance covers it), reimbursing the house for the
+//!   spend. A token deployment's pool is in tokens, so there the cost
+//!   is only booked and the fee balance keeps its lamports.
+//!
+//! The cost never enters `total_wagered` or any RTP figure. It is not
+//! refunded when a spin is voided: the randomness was still requested.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, SlotsState};
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum OracleCostMode {
+    #[default]
+    HouseAbsorbs,
+    PlayerPays,
+}
+
+/// Lamports the player pays on top of the bet for one spin.
+pub fn player_charge(state: &SlotsState) -> u64 {
+    match state.oracle_cost_mode {
+        OracleCostMode::PlayerPays => state.oracle_cost_lamports,
+        OracleCostMode::HouseAbsorbs => 0,
+    }
+}
+
+/// Book the oracle cost of one committed spin. Returns the cost.
+pub fn book_spin(state: &mut SlotsState) -> Result<u64> {
+    let cost = state.oracle_cost_lamports;
+    if cost == 0 {
+        return Ok(0);
+    }
+    state.oracle_costs_paid = state
+        .oracle_costs_paid
+        .checked_add(cost)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    match state.oracle_cost_mode {
+        OracleCostMode::PlayerPays => {
+            state.fee_balance = state
+                .fee_balance
+                .checked_add(cost)
+                .ok_or(ErrorCode::MathOverflow)?;
+            state.oracle_costs_charged = state
+                .oracle_costs_charged
+                .checked_add(cost)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        OracleCostMode::HouseAbsorbs if state.exclusive_mint().is_some() => {}
+        OracleCostMode::HouseAbsorbs => {
+            let covered = cost.min(state.fee_balance);
+            state.fee_balance -= covered;
+            state.total_pool = state
+                .total_pool
+                .checked_add(covered)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+    }
+    Ok(cost)
+}
+
+/// Oracle P&L: costs recovered from players minus costs incurred.
+pub fn net_oracle_pnl(state: &SlotsState) -> i128 {
+    state.oracle_costs_charged as i128 - state.oracle_costs_paid as i128
+}
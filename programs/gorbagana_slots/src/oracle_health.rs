@@ -0,0 +1,243 @@
+//! Oracle-health gate for new commits.
+//!
+//! When the Switchboard network stops revealing, every commit ends in a
+//! refund. With `oracle_max_reveal_gap_slots` set, commits are refused
+//! with `OracleUnhealthy` while the oracle looks stuck:
+//!
+//! - **Quiet**: no reveal for longer than the gap, but nothing has been
+//!   waiting longer than `oracle_expected_reveal_slots`. Low traffic, not
+//!   an outage; commits are accepted.
+//! - **Stuck**: no reveal for longer than the gap *and* the oldest commit
+//!   made since the last reveal is still pending past the expected reveal
+//!   time. Commits are refused.
+//!
+//! `oldest_unrevealed_commit_slot` is the first commit after the last
+//! reveal, so it can be older than the oldest commit actually pending
+//! (voids and rotations do not move it); the gate errs towards closing.
+//! Any successful reveal clears it and reopens the gate.
+//!
+//! A refused commit cannot record anything, so the closed state is
+//! recorded (and `OracleGateChanged` emitted) by the permissionless
+//! `check_oracle_health`; the next successful settle reopens it.
+//! Deferred settles hold SlotsState read-only and do not count as
+//! reveals here.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, OracleGateChanged, SlotsState};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleHealth {
+    /// Gate not configured.
+    Disabled,
+    Healthy,
+    /// No recent reveal, but no overdue commit either.
+    Quiet,
+    /// No recent reveal and a commit is overdue.
+    Stuck,
+}
+
+/// Return data of `get_oracle_health`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OracleHealthStatus {
+    pub health: OracleHealth,
+    pub slot: u64,
+    pub last_successful_reveal_slot: u64,
+    pub oldest_unrevealed_commit_slot: u64,
+    /// Gate state last recorded on-chain.
+    pub gate_closed: bool,
+}
+
+pub fn assess(state: &SlotsState, now: u64) -> OracleHealth {
+    if state.oracle_max_reveal_gap_slots == 0 {
+        return OracleHealth::Disabled;
+    }
+    let gap = now.saturating_sub(state.last_successful_reveal_slot);
+    if gap <= state.oracle_max_reveal_gap_slots {
+        return OracleHealth::Healthy;
+    }
+    let overdue = state.pending_spins > 0
+        && state.oldest_unrevealed_commit_slot != 0
+        && now.saturating_sub(state.oldest_unrevealed_commit_slot)
+            > state.oracle_expected_reveal_slots;
+    if overdue {
+        OracleHealth::Stuck
+    } else {
+        OracleHealth::Quiet
+    }
+}
+
+pub fn status(state: &SlotsState, now: u64) -> OracleHealthStatus {
+    OracleHealthStatus {
+        health: assess(state, now),
+        slot: now,
+        last_successful_reveal_slot: state.last_successful_reveal_slot,
+        oldest_unrevealed_commit_slot: state.oldest_unrevealed_commit_slot,
+        gate_closed: state.oracle_gate_closed,
+    }
+}
+
+/// Refuse a commit while the oracle is stuck.
+pub fn ensure_healthy(state: &SlotsState, now: u64) -> Result<()> {
+    require!(
+        assess(state, now) != OracleHealth::Stuck,
+        ErrorCode::OracleUnhealthy
+    );
+    Ok(())
+}
+
+/// Record an accepted commit.
+pub fn note_commit(state: &mut SlotsState, now: u64) {
+    if state.oldest_unrevealed_commit_slot == 0 {
+        state.oldest_unrevealed_commit_slot = now;
+    }
+}
+
+/// Record a successful reveal and reopen the gate if it was closed.
+pub fn note_reveal(state: &mut SlotsState, now: u64) {
+    state.last_successful_reveal_slot = now;
+    state.oldest_unrevealed_commit_slot = 0;
+    set_gate(state, false, now);
+}
+
+/// Store the gate state implied by the current assessment.
+pub fn refresh_gate(state: &mut SlotsState, now: u64) -> OracleHealth {
+    let health = assess(state, now);
+    set_gate(state, health == OracleHealth::Stuck, now);
+    health
+}
+
+fn set_gate(state: &mut SlotsState, closed: bool, now: u64) {
+    if state.oracle_gate_closed == closed {
+        return;
+    }
+    state.oracle_gate_closed = closed;
+    emit!(OracleGateChanged {
+        open: !closed,
+        slot: now,
+        last_successful_reveal_slot: state.last_successful_reveal_slot,
+        oldest_unrevealed_commit_slot: state.oldest_unrevealed_commit_slot,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, zeroed_player, Fixture, Game};
+    use crate::{init_player_state, instruction, DEFAULT_MIN_BET};
+
+    const GAP: u64 = 100;
+    const EXPECTED: u64 = 10;
+
+    /// A game gated at GAP slots, its last reveal now.
+    fn gated() -> Game {
+        let mut game = Game::new();
+        game.state.oracle_max_reveal_gap_slots = GAP;
+        game.state.oracle_expected_reveal_slots = EXPECTED;
+        game.state.last_successful_reveal_slot = game.slot;
+        game
+    }
+
+    /// Another player committing on `game`'s machine.
+    fn newcomer(game: &Game) -> Game {
+        let mut other = game.clone();
+        other.user = Pubkey::new_unique();
+        other.player = zeroed_player();
+        init_player_state(&mut other.player, other.user, 255, None).unwrap();
+        other
+    }
+
+    #[test]
+    fn a_quiet_oracle_keeps_taking_bets() {
+        let mut game = gated();
+        game.advance(10 * GAP);
+        assert_eq!(assess(&game.state, game.slot), OracleHealth::Quiet);
+        game.request(DEFAULT_MIN_BET).unwrap();
+
+        // Pending, but not yet overdue.
+        game.advance(EXPECTED);
+        let mut other = newcomer(&game);
+        assert_eq!(assess(&other.state, other.slot), OracleHealth::Quiet);
+        other.request(DEFAULT_MIN_BET).unwrap();
+    }
+
+    #[test]
+    fn a_stuck_oracle_refuses_bets_until_a_reveal() {
+        let mut game = gated();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        assert_eq!(game.state.oldest_unrevealed_commit_slot, game.slot);
+        game.advance(GAP);
+        assert_eq!(assess(&game.state, game.slot), OracleHealth::Healthy);
+
+        game.advance(1);
+        assert_eq!(assess(&game.state, game.slot), OracleHealth::Stuck);
+        let mut other = newcomer(&game);
+        assert_eq!(
+            other.request(DEFAULT_MIN_BET).unwrap_err(),
+            ErrorCode::OracleUnhealthy.into()
+        );
+
+        // A disabled gate lets it through.
+        let mut ungated = newcomer(&game);
+        ungated.state.oracle_max_reveal_gap_slots = 0;
+        assert_eq!(assess(&ungated.state, ungated.slot), OracleHealth::Disabled);
+        ungated.request(DEFAULT_MIN_BET).unwrap();
+
+        game.reveal([3; 32]);
+        game.settle().unwrap();
+        assert_eq!(game.state.last_successful_reveal_slot, game.slot);
+        assert_eq!(game.state.oldest_unrevealed_commit_slot, 0);
+        let mut other = newcomer(&game);
+        other.request(DEFAULT_MIN_BET).unwrap();
+    }
+
+    #[test]
+    fn check_oracle_health_records_the_gate() {
+        let mut game = gated();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        game.advance(GAP + 1);
+        let [mut state, ..] = game.fixtures();
+        let check = instruction::CheckOracleHealth {}.data();
+
+        simulate([&mut state], &check).unwrap();
+        game.state = state.read::<SlotsState>();
+        assert!(game.state.oracle_gate_closed);
+
+        game.reveal([3; 32]);
+        game.settle().unwrap();
+        assert!(!game.state.oracle_gate_closed);
+    }
+
+    #[test]
+    fn only_the_authority_configures_the_gate() {
+        let game = gated();
+        let [mut state, ..] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut authority = game.authority();
+        let mut no_metrics = Fixture::omitted();
+        let set = |max_reveal_gap_slots, expected_reveal_slots| {
+            instruction::SetOracleHealthGate {
+                max_reveal_gap_slots,
+                expected_reveal_slots,
+            }
+            .data()
+        };
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(GAP, EXPECTED)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(GAP, 0)),
+            Err(program_error(ErrorCode::InvalidOracleHealthConfig))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(0, 0)).unwrap();
+        assert_eq!(state.read::<SlotsState>().oracle_max_reveal_gap_slots, 0);
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(GAP, EXPECTED)).unwrap();
+        let state = state.read::<SlotsState>();
+        assert_eq!(state.oracle_max_reveal_gap_slots, GAP);
+        assert_eq!(state.oracle_expected_reveal_slots, EXPECTED);
+    }
+}
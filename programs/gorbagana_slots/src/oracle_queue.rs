@@ -0,0 +1,89 @@
+//! Oracle queue whitelist, checked at commit.
+//!
+//! Switchboard queues differ in reveal latency and cost. A spin committed
+//! against a slow queue may not reveal before cancel_spin's expiry
+//! (`spin_expiry_slots`). The admin lists up to [`MAX_ORACLE_QUEUES`]
+//! queue authorities in `SlotsState.oracle_queues`, each with the reveal
+//! latency expected from its queues. A fee the operator would not accept
+//! is a reason to leave a queue off the list.
+//!
+//! Once any entry is set, every commit must pass the queue its randomness
+//! account points at (`RandomnessAccountData.queue`). The queue's
+//! authority must be listed, and its expected latency must fit within
+//! `spin_expiry_slots - oracle_queue_margin_slots`. With an empty list
+//! the check is off and the queue account may be omitted.
+//!
+//! The queue account does not publish a reveal latency, so the latency is
+//! the operator's figure, kept next to the authority it was measured for.
+
+use anchor_lang::prelude::*;
+
+use crate::{optional, ErrorCode, SlotsState, SWITCHBOARD_ON_DEMAND_PROGRAM_ID};
+
+/// Whitelisted queue authorities.
+pub const MAX_ORACLE_QUEUES: usize = 4;
+/// Offset of `authority` in a Switchboard On-Demand queue account: the
+/// first field after the 8-byte discriminator.
+const QUEUE_AUTHORITY_OFFSET: usize = 8;
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct OracleQueueEntry {
+    /// Queue authority (default key = empty entry).
+    pub authority: Pubkey,
+    /// Slots a commit on this authority's queues usually waits to reveal.
+    pub expected_reveal_slots: u32,
+}
+
+pub fn gate_enabled(state: &SlotsState) -> bool {
+    state
+        .oracle_queues
+        .iter()
+        .any(|e| e.authority != Pubkey::default())
+}
+
+/// Authority of a Switchboard-owned queue account.
+pub fn queue_authority(queue_ai: &AccountInfo) -> Result<Pubkey> {
+    require_keys_eq!(
+        *queue_ai.owner,
+        SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+        ErrorCode::OracleQueueMismatch
+    );
+
+    let data = queue_ai.data.borrow();
+    let bytes = data
+        .get(QUEUE_AUTHORITY_OFFSET..QUEUE_AUTHORITY_OFFSET + 32)
+        .ok_or(ErrorCode::OracleQueueMismatch)?;
+    Ok(Pubkey::try_from(bytes).map_err(|_| ErrorCode::OracleQueueMismatch)?)
+}
+
+/// Refuse a commit whose randomness account uses `queue` unless the
+/// queue's authority is whitelisted and fast enough.
+pub fn ensure_allowed(
+    state: &SlotsState,
+    queue: &Pubkey,
+    queue_ai: Option<&AccountInfo>,
+) -> Result<()> {
+    if !gate_enabled(state) {
+        return Ok(());
+    }
+    let queue_ai = optional::required(queue_ai, "oracle_queue")?;
+    require_keys_eq!(queue_ai.key(), *queue, ErrorCode::OracleQueueMismatch);
+
+    let authority = queue_authority(queue_ai)?;
+    let entry = state
+        .oracle_queues
+        .iter()
+        .find(|e| e.authority != Pubkey::default() && e.authority == authority)
+        .ok_or(ErrorCode::OracleQueueNotWhitelisted)?;
+
+    let budget = state
+        .spin_expiry_slots
+        .saturating_sub(state.oracle_queue_margin_slots as u64);
+    require!(
+        entry.expected_reveal_slots as u64 <= budget,
+        ErrorCode::OracleQueueTooSlow
+    );
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Cap on the outcomes derived from one VRF reveal.
+//!
+//! Every draw after the first reuses the same 32 revealed bytes, so the
+//! entropy behind each outcome shrinks as more are derived. The admin sets
+//! `SlotsState.max_outcomes_per_reveal`. A commit states how many outcomes
+//! it will take from its reveal, which must fit under the cap, and stores
+//! that budget in `PlayerState.pending_outcomes`. Settle draws it down
+//! outcome by outcome, so a settle can never take more than was committed,
+//! even after the admin lowers the cap.
+//!
+//! A plain spin takes [`SPIN_OUTCOMES`]. Features that derive more from
+//! the same reveal commit a larger budget and consume from it.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, PlayerState, SlotsState};
+
+pub const DEFAULT_MAX_OUTCOMES_PER_REVEAL: u8 = 16;
+/// Outcomes a plain spin derives from its reveal.
+pub const SPIN_OUTCOMES: u8 = 1;
+
+/// Check a commit's outcome count against the cap.
+pub fn ensure_commit(state: &SlotsState, outcomes: u8) -> Result<()> {
+    require!(
+        outcomes > 0 && outcomes <= state.max_outcomes_per_reveal,
+        ErrorCode::TooManyOutcomes
+    );
+    Ok(())
+}
+
+/// Take `outcomes` from the pending spin's committed budget.
+pub fn consume(player_state: &mut PlayerState, outcomes: u8) -> Result<()> {
+    player_state.pending_outcomes = player_state
+        .pending_outcomes
+        .checked_sub(outcomes)
+        .ok_or(ErrorCode::OutcomeBudgetExceeded)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, zeroed_player, zeroed_state, Fixture, Game};
+    use crate::{instruction, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    #[test]
+    fn a_commit_takes_between_one_outcome_and_the_cap() {
+        let mut state = zeroed_state();
+        state.max_outcomes_per_reveal = 4;
+        ensure_commit(&state, 1).unwrap();
+        ensure_commit(&state, 4).unwrap();
+        for outcomes in [0, 5] {
+            assert_eq!(
+                ensure_commit(&state, outcomes).unwrap_err(),
+                ErrorCode::TooManyOutcomes.into()
+            );
+        }
+    }
+
+    #[test]
+    fn draws_cannot_exceed_the_committed_budget() {
+        let mut player = zeroed_player();
+        player.pending_outcomes = 3;
+        consume(&mut player, 2).unwrap();
+        assert_eq!(
+            consume(&mut player, 2).unwrap_err(),
+            ErrorCode::OutcomeBudgetExceeded.into()
+        );
+        consume(&mut player, 1).unwrap();
+        assert_eq!(player.pending_outcomes, 0);
+    }
+
+    #[test]
+    fn a_settle_draws_down_what_the_commit_stored() {
+        let mut game = Game::new();
+        game.state.max_outcomes_per_reveal = 2;
+        assert_eq!(
+            game.request_lines(BET, 3).unwrap_err(),
+            ErrorCode::TooManyOutcomes.into()
+        );
+
+        game.state.max_outcomes_per_reveal = 3;
+        game.request_lines(BET, 3).unwrap();
+        assert_eq!(game.player.pending_outcomes, 3);
+
+        // Lowering the cap later does not strand the committed spin.
+        game.state.max_outcomes_per_reveal = 1;
+        game.reveal([3; 32]);
+        let mut overdrawn = game.clone();
+        game.settle().unwrap();
+        assert_eq!(game.player.pending_outcomes, 0);
+
+        // A budget short of what the settle derives is refused.
+        overdrawn.player.pending_outcomes = 2;
+        assert_eq!(
+            overdrawn.settle().unwrap_err(),
+            ErrorCode::OutcomeBudgetExceeded.into()
+        );
+        assert!(overdrawn.player.has_pending_spin);
+    }
+
+    #[test]
+    fn request_spins_commits_no_more_spins_than_the_cap() {
+        let mut game = Game::new();
+        game.state.max_outcomes_per_reveal = 2;
+        game.randomness.seed(game.slot, 1);
+        let spins = |count| instruction::RequestSpins {
+            randomness_account: game.randomness.key,
+            bet_amount: BET,
+            count,
+        };
+
+        let mut accounts = game.request_spin_fixtures();
+        assert_eq!(
+            simulate(&mut accounts, &spins(3).data()),
+            Err(program_error(ErrorCode::TooManyOutcomes))
+        );
+        let mut accounts = game.request_spin_fixtures();
+        simulate(&mut accounts, &spins(2).data()).unwrap();
+        assert_eq!(accounts[1].read::<PlayerState>().pending_outcomes, 2);
+    }
+
+    #[test]
+    fn only_the_authority_sets_a_nonzero_cap() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut no_metrics = Fixture::omitted();
+        let set = |max| instruction::SetMaxOutcomesPerReveal { max }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(4)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(0)),
+            Err(program_error(ErrorCode::InvalidConfig))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(4)).unwrap();
+        assert_eq!(state.read::<SlotsState>().max_outcomes_per_reveal, 4);
+    }
+}
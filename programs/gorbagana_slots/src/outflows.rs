@@ -0,0 +1,103 @@
+//! Categorized treasury outflows for the finance ledger.
+//!
+//! Every lamport leaving the treasury other than a spin payout carries an
+//! [`OutflowCategory`] and a 32-byte memo, is emitted in `TreasuryOutflow`
+//! and is added to the per-category lifetime total in
+//! `SlotsState.outflow_totals`. Each instruction only accepts the
+//! categories that make sense for it, so the ledger cannot be mislabelled
+//! (operator profit only comes out of execute_withdrawal, and so on).
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, SlotsState, TreasuryOutflow};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OutflowCategory {
+    OperatorProfit = 0,
+    Refund = 1,
+    Compensation = 2,
+    Fee = 3,
+    LpWithdrawal = 4,
+}
+
+/// Number of categories; length of `SlotsState.outflow_totals`.
+pub const OUTFLOW_CATEGORIES: usize = 5;
+
+impl OutflowCategory {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::OperatorProfit),
+            1 => Some(Self::Refund),
+            2 => Some(Self::Compensation),
+            3 => Some(Self::Fee),
+            4 => Some(Self::LpWithdrawal),
+            _ => None,
+        }
+    }
+}
+
+/// Instruction an outflow comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutflowSource {
+    OperatorWithdrawal,
+    WithdrawClaimable,
+    RelayerFee,
+}
+
+/// Whether `source` may book an outflow under `category`.
+pub fn category_allowed(source: OutflowSource, category: OutflowCategory) -> bool {
+    use OutflowCategory::*;
+    match source {
+        OutflowSource::OperatorWithdrawal => {
+            matches!(category, OperatorProfit | Compensation | LpWithdrawal)
+        }
+        OutflowSource::WithdrawClaimable => category == Refund,
+        OutflowSource::RelayerFee => category == Fee,
+    }
+}
+
+/// A memo is UTF-8 text padded with trailing zeros; all zeros means none.
+pub fn memo_is_valid(memo: &[u8; 32]) -> bool {
+    let len = memo.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    let text = &memo[..len];
+    !text.contains(&0) && std::str::from_utf8(text).is_ok()
+}
+
+/// Validate a category and memo for `source`.
+pub fn validate(
+    source: OutflowSource,
+    category: u8,
+    memo: &[u8; 32],
+) -> Result<OutflowCategory> {
+    let category =
+        OutflowCategory::from_u8(category).ok_or(ErrorCode::InvalidOutflowCategory)?;
+    require!(
+        category_allowed(source, category),
+        ErrorCode::InvalidOutflowCategory
+    );
+    require!(memo_is_valid(memo), ErrorCode::InvalidOutflowMemo);
+    Ok(category)
+}
+
+/// Add `amount` to the category's lifetime total and emit the ledger
+/// event.
+pub fn book(
+    state: &mut SlotsState,
+    category: OutflowCategory,
+    amount: u64,
+    to: Pubkey,
+    memo: [u8; 32],
+) -> Result<()> {
+    let total = &mut state.outflow_totals[category as usize];
+    *total = total.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(TreasuryOutflow {
+        category,
+        amount,
+        to,
+        memo,
+        category_total: *total,
+    });
+    Ok(())
+}
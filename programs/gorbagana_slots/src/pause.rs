@@ -0,0 +1,152 @@
+//! Pause flags.
+//!
+//! `SlotsState.pause_flags` stops one kind of instruction at a time, so an
+//! incident can stop new bets while pending spins still settle, or freeze
+//! withdrawals while deposits continue:
+//!
+//! - [`PAUSE_NEW_SPINS`]: request_spin and its variants.
+//! - [`PAUSE_SETTLEMENT`]: settle_spin and its variants. cancel_spin and
+//!   admin_void_spin stay open, so a player is never stuck.
+//! - [`PAUSE_DEPOSITS`]: add_to_pool, add_to_pool_token and boost_jackpot.
+//! - [`PAUSE_WITHDRAWALS`]: withdraw_from_pool, execute_withdrawal(_token)
+//!   and withdraw_fees. Players' claimable balances stay withdrawable.
+//!
+//! set_pause_flags writes the byte; emergency_pause sets [`PAUSE_ALL`], or
+//! clears every flag when any is set. A scheduled resume clears them all.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, EmergencyAction, ErrorCode, SlotsState};
+
+pub const PAUSE_NEW_SPINS: u8 = 1 << 0;
+pub const PAUSE_SETTLEMENT: u8 = 1 << 1;
+pub const PAUSE_DEPOSITS: u8 = 1 << 2;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 3;
+pub const PAUSE_ALL: u8 = PAUSE_NEW_SPINS | PAUSE_SETTLEMENT | PAUSE_DEPOSITS | PAUSE_WITHDRAWALS;
+
+pub fn is_paused(flags: u8, flag: u8) -> bool {
+    flags & flag != 0
+}
+
+pub fn validate(flags: u8) -> Result<()> {
+    require!(flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+    Ok(())
+}
+
+/// What emergency_pause writes: everything paused, or nothing if any flag
+/// was set.
+pub fn toggled(flags: u8) -> u8 {
+    if flags == 0 {
+        PAUSE_ALL
+    } else {
+        0
+    }
+}
+
+/// Refuse an instruction whose `flag` is set. A pause whose scheduled
+/// resume slot has passed is cleared here, lazily, and the resume is
+/// announced once with the same event a manual resume emits.
+pub fn ensure_not_paused(state: &mut SlotsState, flag: u8) -> Result<()> {
+    if !is_paused(state.pause_flags, flag) {
+        return Ok(());
+    }
+    require!(state.frozen_version == 0, ErrorCode::GamePaused);
+    let at_slot = state.scheduled_resume_slot;
+    require!(
+        at_slot != 0 && time::has_reached(at_slot, Clock::get()?.slot),
+        ErrorCode::GamePaused
+    );
+
+    state.pause_flags = 0;
+    state.scheduled_resume_slot = 0;
+    emit!(EmergencyAction {
+        pause_flags: 0,
+        authority: state.authority,
+    });
+    Ok(())
+}
+
+/// Read-only check for instructions that cannot write SlotsState
+/// (settle_spin_deferred); a due scheduled resume is not applied.
+pub fn ensure_open(state: &SlotsState, flag: u8) -> Result<()> {
+    require!(!is_paused(state.pause_flags, flag), ErrorCode::GamePaused);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{set_clock_slot, zeroed_state};
+
+    #[test]
+    fn emergency_toggle_pauses_everything_or_nothing() {
+        assert_eq!(toggled(0), PAUSE_ALL);
+        assert_eq!(toggled(PAUSE_ALL), 0);
+        assert_eq!(toggled(PAUSE_NEW_SPINS), 0);
+    }
+
+    #[test]
+    fn flags_are_independent() {
+        let flags = PAUSE_NEW_SPINS | PAUSE_WITHDRAWALS;
+        assert!(is_paused(flags, PAUSE_NEW_SPINS));
+        assert!(!is_paused(flags, PAUSE_SETTLEMENT));
+        assert!(!is_paused(flags, PAUSE_DEPOSITS));
+        assert!(is_paused(flags, PAUSE_WITHDRAWALS));
+        assert!(validate(PAUSE_ALL).is_ok());
+        assert!(validate(1 << 4).is_err());
+    }
+
+    #[test]
+    fn a_paused_area_does_not_stop_the_others() {
+        let mut s = zeroed_state();
+        s.pause_flags = PAUSE_NEW_SPINS;
+        set_clock_slot(10);
+        assert_eq!(
+            ensure_not_paused(&mut s, PAUSE_NEW_SPINS),
+            Err(ErrorCode::GamePaused.into())
+        );
+        for flag in [PAUSE_SETTLEMENT, PAUSE_DEPOSITS, PAUSE_WITHDRAWALS] {
+            assert!(ensure_not_paused(&mut s, flag).is_ok());
+            assert!(ensure_open(&s, flag).is_ok());
+        }
+        assert!(ensure_open(&s, PAUSE_NEW_SPINS).is_err());
+        assert_eq!(s.pause_flags, PAUSE_NEW_SPINS);
+    }
+
+    #[test]
+    fn a_scheduled_resume_clears_every_flag_once_due() {
+        let mut s = zeroed_state();
+        s.pause_flags = PAUSE_ALL;
+        s.scheduled_resume_slot = 100;
+
+        set_clock_slot(99);
+        assert!(ensure_not_paused(&mut s, PAUSE_DEPOSITS).is_err());
+        assert_eq!(s.pause_flags, PAUSE_ALL);
+
+        set_clock_slot(100);
+        assert!(ensure_not_paused(&mut s, PAUSE_DEPOSITS).is_ok());
+        assert_eq!(s.pause_flags, 0);
+        assert_eq!(s.scheduled_resume_slot, 0);
+        assert!(ensure_open(&s, PAUSE_NEW_SPINS).is_ok());
+    }
+
+    #[test]
+    fn no_scheduled_resume_or_a_freeze_keeps_the_pause() {
+        let mut s = zeroed_state();
+        s.pause_flags = PAUSE_SETTLEMENT;
+        set_clock_slot(1_000);
+        assert!(ensure_not_paused(&mut s, PAUSE_SETTLEMENT).is_err());
+
+        // An upgrade freeze outranks a due resume.
+        s.scheduled_resume_slot = 10;
+        s.frozen_version = 1;
+        assert!(ensure_not_paused(&mut s, PAUSE_SETTLEMENT).is_err());
+        assert_eq!(s.pause_flags, PAUSE_SETTLEMENT);
+        assert_eq!(s.scheduled_resume_slot, 10);
+
+        // The read-only check never applies a resume.
+        s.frozen_version = 0;
+        assert!(ensure_open(&s, PAUSE_SETTLEMENT).is_err());
+        assert_eq!(s.pause_flags, PAUSE_SETTLEMENT);
+    }
+}
@@ -0,0 +1,240 @@
+//! Tunable paytable and its RTP ceiling.
+//!
+//! `SlotsState.paytable` prices every win. It starts as [`PAYTABLE`]:
+//! 3-of-a-kind only, as the game has always paid. set_paytable replaces
+//! the 2- and 3-of-a-kind columns. The new table must keep its implied
+//! base-game RTP under `max_rtp_bps` and its top win on `max_bet` within
+//! `max_payout_per_spin`. Changes are only accepted while new spins are
+//! paused and none is pending, so a settle always pays the table its
+//! spin was committed under.
+//!
+//! The reel weights live in `SlotsState.reel_weights`, starting as
+//! [`REEL_WEIGHTS`]. set_reel_config replaces them together with the
+//! 3-of-a-kind column, under the same checks, so one deployment can run a
+//! different volatility profile without a rebuild.
+//!
+//! The implied RTP is exact for the reel weights. A reel lands on symbol
+//! `i` with probability `p = w_i / total_weight`, so symbol `i` pays 3 of
+//! a kind with probability `p^3` and exactly 2 of a kind with
+//! `3 p^2 (1 - p)`. Jackpots are not included. Scatter free spins are:
+//! their return has no wager behind it, so [`implied_rtp_with_scatter_bps`]
+//! adds it (see scatter.rs) and the checks below use that.
+//!
+//! A wild (`SlotsState.wild_symbol`, set by set_wild_symbol) completes
+//! matches the closed form cannot see, so with one set the RTP is summed
+//! over all `SYMBOL_COUNT^3` reel combinations instead, each priced by
+//! game_math::calculate_payout exactly as a settle prices it. Without a
+//! wild both give the same result.
+//!
+//! The theoretical RTP adds the jackpots: every contribution is paid back
+//! to some player eventually, so each tier returns its `contrib_bps`. It
+//! must stay within `10_000 - house_edge_bps`; initialize, set_paytable
+//! and update_config refuse a configuration above that and store the
+//! value in `SlotsState.theoretical_rtp_bps` for frontends.
+//!
+//! [`PAYTABLE`]: crate::PAYTABLE
+//! [`REEL_WEIGHTS`]: crate::REEL_WEIGHTS
+
+use anchor_lang::prelude::*;
+
+use crate::scatter::{self, ScatterConfig};
+use crate::{
+    game_math, tiers, ErrorCode, JackpotsConfig, PayTable, ReelWeights, SlotsState, SYMBOL_COUNT,
+};
+
+/// Default ceiling on the implied base-game RTP (95%).
+pub const DEFAULT_MAX_RTP_BPS: u16 = 9_500;
+
+/// Base-game RTP of `table` on `reels` with `wild` (NO_WILD for none) in
+/// basis points, rounded down.
+pub fn implied_rtp_bps(reels: &ReelWeights, table: &PayTable, wild: u8) -> u64 {
+    if (wild as usize) < SYMBOL_COUNT {
+        wild_rtp_bps(&reels.weights, table, wild)
+    } else {
+        base_rtp_bps(&reels.weights, table)
+    }
+}
+
+/// implied_rtp_bps plus what `scatter`'s free spins return on top.
+pub fn implied_rtp_with_scatter_bps(
+    reels: &ReelWeights,
+    table: &PayTable,
+    wild: u8,
+    scatter: &ScatterConfig,
+) -> u64 {
+    let base = implied_rtp_bps(reels, table, wild);
+    base.saturating_add(scatter::bonus_rtp_bps(scatter, reels, base))
+}
+
+/// Base-game RTP with a wild, summed over every reel combination.
+pub fn wild_rtp_bps(weights: &[u64; SYMBOL_COUNT], table: &PayTable, wild: u8) -> u64 {
+    let t: u128 = weights.iter().map(|&w| w as u128).sum();
+    if t == 0 {
+        return 0;
+    }
+    let mut numerator: u128 = 0;
+    for (a, &wa) in weights.iter().enumerate() {
+        for (b, &wb) in weights.iter().enumerate() {
+            for (c, &wc) in weights.iter().enumerate() {
+                let odds = (wa as u128) * (wb as u128) * (wc as u128);
+                if odds == 0 {
+                    continue;
+                }
+                let symbols = [a as u8, b as u8, c as u8];
+                let (multiplier, _) = game_math::calculate_payout(symbols, 1, table, wild);
+                numerator = numerator.saturating_add(odds.saturating_mul(multiplier as u128));
+            }
+        }
+    }
+    let rtp = numerator.saturating_mul(10_000) / t.saturating_mul(t).saturating_mul(t);
+    rtp.min(u64::MAX as u128) as u64
+}
+
+/// Base-game RTP of `table` on reels weighted `weights`, rounded down.
+pub fn base_rtp_bps(weights: &[u64; SYMBOL_COUNT], table: &PayTable) -> u64 {
+    let t: u128 = weights.iter().map(|&w| w as u128).sum();
+    if t == 0 {
+        return 0;
+    }
+    let mut numerator: u128 = 0;
+    for (i, &w) in weights.iter().enumerate() {
+        let w = w as u128;
+        let three = table.multiplier(i as u8, 3) as u128;
+        let two = table.multiplier(i as u8, 2) as u128;
+        numerator = numerator
+            .saturating_add(w * w * w * three)
+            .saturating_add(3 * w * w * (t - w) * two);
+    }
+    let rtp = numerator.saturating_mul(10_000) / t.saturating_mul(t).saturating_mul(t);
+    rtp.min(u64::MAX as u128) as u64
+}
+
+/// Expected return of a bet in basis points: the base game on `weights`
+/// plus every jackpot contribution.
+pub fn theoretical_rtp_bps(
+    weights: &[u64; SYMBOL_COUNT],
+    table: &PayTable,
+    jackpots: &JackpotsConfig,
+) -> u32 {
+    with_jackpots_bps(base_rtp_bps(weights, table), jackpots)
+}
+
+/// `game_rtp_bps` plus every jackpot contribution.
+fn with_jackpots_bps(game_rtp_bps: u64, jackpots: &JackpotsConfig) -> u32 {
+    let contrib = [&jackpots.mini, &jackpots.major, &jackpots.grand]
+        .iter()
+        .map(|pool| pool.contrib_bps as u64)
+        .sum::<u64>();
+    game_rtp_bps.saturating_add(contrib).min(u32::MAX as u64) as u32
+}
+
+/// Recompute `theoretical_rtp_bps` from the live config and refuse it
+/// above `10_000 - house_edge_bps`.
+pub fn refresh_theoretical_rtp(state: &mut SlotsState) -> Result<u32> {
+    let game = implied_rtp_with_scatter_bps(
+        &state.reel_weights,
+        &state.paytable,
+        state.wild_symbol,
+        &state.scatter,
+    );
+    let rtp = with_jackpots_bps(game, &state.jackpots);
+    let target = 10_000u32.saturating_sub(state.house_edge_bps as u32);
+    require!(rtp <= target, ErrorCode::RtpExceedsTarget);
+    state.theoretical_rtp_bps = rtp;
+    Ok(rtp)
+}
+
+/// Check `table` on `reels` against the RTP ceiling and the payout cap.
+pub fn validate(state: &SlotsState, reels: &ReelWeights, table: &PayTable) -> Result<u64> {
+    validate_with(state, reels, table, state.wild_symbol, &state.scatter)
+}
+
+/// validate for a candidate wild and scatter config; the table must
+/// leave the scatter unpaid.
+pub fn validate_with(
+    state: &SlotsState,
+    reels: &ReelWeights,
+    table: &PayTable,
+    wild: u8,
+    scatter: &ScatterConfig,
+) -> Result<u64> {
+    scatter::validate(scatter, table)?;
+    let rtp = implied_rtp_with_scatter_bps(reels, table, wild, scatter);
+    require!(
+        rtp <= state.max_rtp_bps as u64,
+        ErrorCode::PaytableRtpTooHigh
+    );
+    require!(
+        tiers::max_win_covered(state, table, state.max_bet),
+        ErrorCode::BetLimitsExceedPayoutCap
+    );
+    Ok(rtp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JackpotPool, NO_WILD, PAYTABLE, REEL_WEIGHTS, SYMBOL_WEIGHTS, TOTAL_WEIGHT};
+
+    fn jackpots(contrib_bps: [u16; 3]) -> JackpotsConfig {
+        let pool = |bps| JackpotPool {
+            contrib_bps: bps,
+            ..JackpotPool::default()
+        };
+        JackpotsConfig {
+            mini: pool(contrib_bps[0]),
+            major: pool(contrib_bps[1]),
+            grand: pool(contrib_bps[2]),
+            ..JackpotsConfig::default()
+        }
+    }
+
+    #[test]
+    fn default_table_rtp_is_pinned() {
+        assert_eq!(SYMBOL_WEIGHTS.iter().sum::<u64>(), TOTAL_WEIGHT);
+        assert_eq!(REEL_WEIGHTS.total_weight, TOTAL_WEIGHT);
+        assert_eq!(implied_rtp_bps(&REEL_WEIGHTS, &PAYTABLE, NO_WILD), 5_983);
+        assert_eq!(base_rtp_bps(&SYMBOL_WEIGHTS, &PAYTABLE), 5_983);
+        // initialize's jackpots contribute 0.5% + 1% + 0.5%.
+        let rtp = theoretical_rtp_bps(&SYMBOL_WEIGHTS, &PAYTABLE, &jackpots([50, 100, 50]));
+        assert_eq!(rtp, 6_183);
+        assert!(rtp <= 10_000 - 500);
+    }
+
+    #[test]
+    fn two_of_a_kind_counts_toward_rtp() {
+        let mut table = PAYTABLE;
+        table.two_of_a_kind[SYMBOL_COUNT - 1] = 1;
+        // Symbol 11 pairs with probability 3 * 12^2 * 66 / 78^3, adding
+        // 28_512 / 474_552 to the 283_933 / 474_552 of the 3oak column.
+        assert_eq!(implied_rtp_bps(&REEL_WEIGHTS, &table, NO_WILD), 6_583);
+    }
+
+    #[test]
+    fn wild_rtp_enumerates_every_combination() {
+        // Without a wild the enumeration is the closed form.
+        let mut two = PAYTABLE;
+        two.two_of_a_kind[SYMBOL_COUNT - 1] = 1;
+        assert_eq!(wild_rtp_bps(&SYMBOL_WEIGHTS, &PAYTABLE, NO_WILD), 5_983);
+        assert_eq!(wild_rtp_bps(&SYMBOL_WEIGHTS, &two, NO_WILD), 6_583);
+        // The rarest symbol wild adds about 29 points; the commonest one
+        // completes so many lines the game pays out ten times over.
+        assert_eq!(implied_rtp_bps(&REEL_WEIGHTS, &PAYTABLE, 0), 8_890);
+        assert_eq!(implied_rtp_bps(&REEL_WEIGHTS, &PAYTABLE, 11), 98_936);
+    }
+
+    #[test]
+    fn flatter_reels_change_the_rtp() {
+        // Even weights: each symbol lands 3 of a kind with 1 / 1_728, so
+        // the RTP is the 3oak column's sum over 1_728.
+        let flat = ReelWeights::new([1; SYMBOL_COUNT]);
+        let sum: u64 = PAYTABLE.multipliers.iter().map(|row| row[0]).sum();
+        assert_eq!(implied_rtp_bps(&flat, &PAYTABLE, NO_WILD), sum * 10_000 / 1_728);
+    }
+
+    #[test]
+    fn empty_reels_return_nothing() {
+        assert_eq!(base_rtp_bps(&[0; SYMBOL_COUNT], &PAYTABLE), 0);
+        assert_eq!(theoretical_rtp_bps(&[0; SYMBOL_COUNT], &PAYTABLE, &jackpots([1, 2, 3])), 6);
+    }
+}
@@ -0,0 +1,62 @@
+//! Lifetime statistics on PlayerState.
+//!
+//! A frontend can show "your spins / wagered / won / biggest win" by
+//! reading the account, with no history indexing. Wagers count at commit
+//! and come off again if the spin is voided. Spins, wins and the last
+//! spin slot count at settle, both full and deferred. Wins are the
+//! settled total payout (base plus jackpot), not including fee rebates.
+//!
+//! Accounts created before these fields existed are too short for the
+//! current layout. The owner grows them with migrate_player, and their
+//! stats start from zero at that point.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, PlayerState, PlayerStatsUpdated};
+
+/// Count a committed stake.
+pub fn record_wager(player_state: &mut PlayerState, stake: u64) -> Result<()> {
+    player_state.total_wagered = player_state
+        .total_wagered
+        .checked_add(stake)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+/// Take a voided stake back off. Saturates: the stake may have been
+/// committed before migrate_player added the counter.
+pub fn unrecord_wager(player_state: &mut PlayerState, stake: u64) {
+    player_state.total_wagered = player_state.total_wagered.saturating_sub(stake);
+}
+
+/// Count `spins` settled spins that together paid `won`, then emit the
+/// totals.
+pub fn record_settle(
+    player_state: &mut PlayerState,
+    user: Pubkey,
+    spins: u8,
+    won: u64,
+    slot: u64,
+) -> Result<()> {
+    player_state.total_spins = player_state
+        .total_spins
+        .checked_add(spins as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.total_won = player_state
+        .total_won
+        .checked_add(won)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.biggest_win = player_state.biggest_win.max(won);
+    player_state.last_spin_slot = slot;
+
+    emit!(PlayerStatsUpdated {
+        user,
+        total_spins: player_state.total_spins,
+        total_wagered: player_state.total_wagered,
+        total_won: player_state.total_won,
+        biggest_win: player_state.biggest_win,
+        last_spin_slot: slot,
+        spin_nonce: player_state.spin_nonce,
+    });
+    Ok(())
+}
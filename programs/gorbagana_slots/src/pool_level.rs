@@ -0,0 +1,159 @@
+//! Pool level against what a new spin needs.
+//!
+//! request_spin refuses a bet with InsufficientPool while `total_pool` is
+//! below [`required_pool`]: min_pool_threshold, the jackpot reserves
+//! (see accounting.rs), earmarks, the payout caps reserved by pending
+//! spins and a pending operator withdrawal, plus the payout cap of the
+//! player's tier. [`shortfall`] is the add_to_pool deposit that would let
+//! a public-tier spin through again, and get_game_status reports it.
+//!
+//! A rejected request_spin rolls back, so it cannot record anything.
+//! Instead every instruction that moves the pool (deposits, claims,
+//! settles, flush_stats) calls [`refresh`], as can anyone through
+//! check_pool_level. It emits PoolBelowRequired once when the pool drops
+//! below the requirement and PoolRestored once when it is back above it
+//! by POOL_RESTORE_HYSTERESIS_BPS, so a pool hovering at the line does
+//! not flap.
+
+use anchor_lang::prelude::*;
+
+use crate::{tiers, total_jackpot_amounts, PoolBelowRequired, PoolRestored, SlotsState};
+
+/// Margin above the requirement before the pool counts as restored.
+pub const POOL_RESTORE_HYSTERESIS_BPS: u64 = 100;
+
+/// Pool a spin by a `tier` player needs. Shared with request_spin.
+pub fn required_pool(state: &SlotsState, tier: u8) -> u64 {
+    state
+        .min_pool_threshold
+        .saturating_add(total_jackpot_amounts(&state.jackpots).unwrap_or(u64::MAX))
+        .saturating_add(state.earmarks.total().unwrap_or(u64::MAX))
+        .saturating_add(state.reserved_liability)
+        .saturating_add(state.pending_withdrawal_amount)
+        .saturating_add(tiers::payout_cap(state, tier))
+}
+
+/// Deposit that would lift the pool to the public-tier requirement.
+pub fn shortfall(state: &SlotsState) -> u64 {
+    required_pool(state, 0).saturating_sub(state.total_pool)
+}
+
+/// Update `pool_below_required`, emitting on a transition. Returns the
+/// current shortfall.
+pub fn refresh(state: &mut SlotsState) -> u64 {
+    let required = required_pool(state, 0);
+    let short = required.saturating_sub(state.total_pool);
+
+    if !state.pool_below_required && short > 0 {
+        state.pool_below_required = true;
+        emit!(PoolBelowRequired {
+            total_pool: state.total_pool,
+            required_pool: required,
+            shortfall: short,
+        });
+    } else if state.pool_below_required {
+        let margin = ((required as u128) * (POOL_RESTORE_HYSTERESIS_BPS as u128) / 10_000) as u64;
+        if state.total_pool >= required.saturating_add(margin) {
+            state.pool_below_required = false;
+            emit!(PoolRestored {
+                total_pool: state.total_pool,
+                required_pool: required,
+            });
+        }
+    }
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{emitted, program_error, returned, simulate, Fixture, Game};
+    use crate::{instruction, ErrorCode, GameStatus, DEFAULT_MIN_BET};
+
+    /// A game whose pool is `short` lamports under the requirement.
+    fn starved(short: u64) -> Game {
+        let mut game = Game::new();
+        let required = required_pool(&game.state, 0) - game.state.min_pool_threshold;
+        game.state.min_pool_threshold = (game.state.total_pool + short) - required;
+        game
+    }
+
+    #[test]
+    fn the_shortfall_is_the_deposit_that_reopens_the_table() {
+        let mut game = starved(1_000);
+        assert_eq!(shortfall(&game.state), 1_000);
+        assert_eq!(
+            game.request(DEFAULT_MIN_BET).unwrap_err(),
+            ErrorCode::InsufficientPool.into()
+        );
+
+        game.fund(999);
+        assert_eq!(shortfall(&game.state), 1);
+        assert_eq!(
+            game.request(DEFAULT_MIN_BET).unwrap_err(),
+            ErrorCode::InsufficientPool.into()
+        );
+        game.fund(1);
+        assert_eq!(shortfall(&game.state), 0);
+        game.request(DEFAULT_MIN_BET).unwrap();
+    }
+
+    #[test]
+    fn transitions_emit_once_with_hysteresis() {
+        let mut game = starved(1_000);
+        emitted::<PoolBelowRequired>();
+
+        assert_eq!(refresh(&mut game.state), 1_000);
+        assert_eq!(refresh(&mut game.state), 1_000);
+        let below = emitted::<PoolBelowRequired>();
+        assert_eq!(below.len(), 1);
+        assert_eq!(below[0].shortfall, 1_000);
+        assert_eq!(below[0].required_pool, below[0].total_pool + 1_000);
+        assert!(game.state.pool_below_required);
+
+        // Back at the line is not yet restored.
+        let required = required_pool(&game.state, 0);
+        game.fund(1_000);
+        assert_eq!(refresh(&mut game.state), 0);
+        assert!(game.state.pool_below_required);
+        assert!(emitted::<PoolRestored>().is_empty());
+
+        let margin = required * POOL_RESTORE_HYSTERESIS_BPS / 10_000;
+        game.fund(margin);
+        refresh(&mut game.state);
+        refresh(&mut game.state);
+        assert!(!game.state.pool_below_required);
+        let restored = emitted::<PoolRestored>();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].total_pool, required + margin);
+        assert!(emitted::<PoolBelowRequired>().is_empty());
+    }
+
+    #[test]
+    fn status_and_check_pool_level_report_the_shortfall() {
+        let game = starved(5_000);
+        let [mut state, ..] = game.fixtures();
+
+        simulate([&mut state, &mut Fixture::omitted()], &instruction::GetGameStatus {}.data())
+            .unwrap();
+        assert_eq!(returned::<GameStatus>().pool_shortfall, 5_000);
+
+        emitted::<PoolBelowRequired>();
+        for _ in 0..2 {
+            simulate([&mut state], &instruction::CheckPoolLevel {}.data()).unwrap();
+            assert_eq!(returned::<u64>(), 5_000);
+        }
+        assert_eq!(emitted::<PoolBelowRequired>().len(), 1);
+        assert!(state.read::<SlotsState>().pool_below_required);
+
+        let mut uninitialized = state.read::<SlotsState>();
+        uninitialized.initialized = false;
+        let mut uninitialized = Fixture::account(state.key, &uninitialized);
+        assert_eq!(
+            simulate([&mut uninitialized], &instruction::CheckPoolLevel {}.data()),
+            Err(program_error(ErrorCode::Uninitialized))
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! LP shares in the house pool.
+//!
+//! add_to_pool mints shares into the depositor's [`PoolPosition`] at the
+//! current price, `amount * total_shares / total_pool`, and
+//! withdraw_from_pool burns them for the same slice of the pool as it
+//! stands then, so depositors carry the house's wins and losses pro rata.
+//! Every rounding goes in favour of the pool: shares minted and lamports
+//! paid round down, shares burned round up.
+//!
+//! Redemptions are priced against the pool net of `reserved_liability`,
+//! the payout caps of committed spins that have not settled. A spin can
+//! be revealed long before it is settled; an LP who holds that spin, or
+//! simply saw its reveal, must not cash out at the price from before the
+//! win is paid. Whatever a reservation does not pay stays in the pool for
+//! the remaining holders.
+//!
+//! The first deposit bootstraps the share supply. A pool the operator
+//! funded before shares existed is minted to the house as `house_shares`,
+//! one share per lamport, so the newcomer buys in at par and does not get
+//! a claim on money already there. execute_withdrawal burns house shares
+//! for what it takes, so the authority can only withdraw the house's own
+//! slice once LPs are in.
+//!
+//! A pool the house's losses emptied leaves its shares worth nothing,
+//! and no deposit could be priced against it. The next deposit writes
+//! them off instead: the supply restarts at zero, the newcomer mints at
+//! par, and `share_epoch` moves on. A position minted in an earlier epoch
+//! holds nothing from then on; it is zeroed the next time it is used.
+//! Positions opened before the epoch existed are grown with
+//! migrate_pool_position.
+//!
+//! A withdrawal that would take the pool below required_floor (the
+//! threshold, jackpot balances, earmarks and pending spin reservations)
+//! is rejected outright, never partially filled.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, PoolPosition, PoolSharesWrittenOff, SlotsState};
+
+impl PoolPosition {
+    pub const LEN: usize = 8 + 32 + 32 + 16 + 8 + 8 + 1 + 4;
+}
+
+/// Shares `amount` buys; 0 if it is too small for one share.
+pub fn shares_for_deposit(amount: u64, total_shares: u128, total_pool: u64) -> u128 {
+    if total_shares == 0 {
+        return amount as u128;
+    }
+    if total_pool == 0 {
+        return 0;
+    }
+    (amount as u128).saturating_mul(total_shares) / total_pool as u128
+}
+
+/// Lamports `shares` redeem for, rounded down.
+pub fn value_of(shares: u128, total_shares: u128, total_pool: u64) -> u64 {
+    if total_shares == 0 {
+        return 0;
+    }
+    let value = shares.saturating_mul(total_pool as u128) / total_shares;
+    value.min(u64::MAX as u128) as u64
+}
+
+/// Shares that must be burned to take `amount` out, rounded up.
+pub fn shares_for_withdrawal(amount: u64, total_shares: u128, total_pool: u64) -> u128 {
+    if total_pool == 0 {
+        return total_shares;
+    }
+    let pool = total_pool as u128;
+    (amount as u128).saturating_mul(total_shares).div_ceil(pool)
+}
+
+/// Pool value shares redeem against: total_pool less the payout caps
+/// reserved by pending spins.
+pub fn redeemable_pool(state: &SlotsState) -> u64 {
+    state.total_pool.saturating_sub(state.reserved_liability)
+}
+
+/// Mint the pre-existing pool to the house before the first LP shares.
+fn bootstrap(state: &mut SlotsState) {
+    if state.total_shares == 0 && state.total_pool > 0 {
+        state.house_shares = state.total_pool as u128;
+        state.total_shares = state.house_shares;
+    }
+}
+
+/// Write the share supply off once the pool behind it is gone.
+fn write_off_if_empty(state: &mut SlotsState) -> Result<()> {
+    if state.total_shares == 0 || state.total_pool > 0 {
+        return Ok(());
+    }
+    let shares = state.total_shares;
+    state.total_shares = 0;
+    state.house_shares = 0;
+    state.share_epoch = state
+        .share_epoch
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    emit!(PoolSharesWrittenOff {
+        shares,
+        share_epoch: state.share_epoch,
+    });
+    Ok(())
+}
+
+/// Zero a position whose shares were written off since it last minted.
+fn sync(state: &SlotsState, position: &mut PoolPosition) {
+    if position.share_epoch != state.share_epoch {
+        position.shares = 0;
+        position.share_epoch = state.share_epoch;
+    }
+}
+
+/// Mint shares for a deposit of `amount` that has not joined
+/// `total_pool` yet. Returns the shares minted.
+pub fn mint(state: &mut SlotsState, position: &mut PoolPosition, amount: u64) -> Result<u128> {
+    write_off_if_empty(state)?;
+    sync(state, position);
+    bootstrap(state);
+    let shares = shares_for_deposit(amount, state.total_shares, state.total_pool);
+    require!(shares > 0, ErrorCode::DepositTooSmallForShares);
+
+    state.total_shares = state
+        .total_shares
+        .checked_add(shares)
+        .ok_or(ErrorCode::MathOverflow)?;
+    position.shares = position
+        .shares
+        .checked_add(shares)
+        .ok_or(ErrorCode::MathOverflow)?;
+    position.deposited = position.deposited.saturating_add(amount);
+    Ok(shares)
+}
+
+/// Burn `shares` of `position` and return the lamports they redeem for.
+/// The caller checks the reserve and moves the lamports.
+pub fn burn(state: &mut SlotsState, position: &mut PoolPosition, shares: u128) -> Result<u64> {
+    sync(state, position);
+    require!(shares > 0, ErrorCode::InvalidAmount);
+    require!(position.shares >= shares, ErrorCode::InsufficientShares);
+    let amount = value_of(shares, state.total_shares, redeemable_pool(state));
+    require!(amount > 0, ErrorCode::InvalidAmount);
+
+    position.shares -= shares;
+    state.total_shares -= shares;
+    position.withdrawn = position.withdrawn.saturating_add(amount);
+    Ok(amount)
+}
+
+/// Burn the house shares backing an authority withdrawal of `amount`.
+/// Nothing to do while no LP holds shares.
+pub fn burn_house(state: &mut SlotsState, amount: u64) -> Result<()> {
+    if state.total_shares == state.house_shares {
+        state.total_shares = 0;
+        state.house_shares = 0;
+        return Ok(());
+    }
+    let shares = shares_for_withdrawal(amount, state.total_shares, redeemable_pool(state));
+    require!(state.house_shares >= shares, ErrorCode::InsufficientShares);
+    state.house_shares -= shares;
+    state.total_shares -= shares;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed_state;
+
+    fn position() -> PoolPosition {
+        PoolPosition {
+            owner: Pubkey::default(),
+            slots_state: Pubkey::default(),
+            shares: 0,
+            deposited: 0,
+            withdrawn: 0,
+            bump: 0,
+            share_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn first_deposit_mints_at_par() {
+        assert_eq!(shares_for_deposit(1_000, 0, 0), 1_000);
+    }
+
+    #[test]
+    fn deposits_and_redemptions_track_the_pool() {
+        // 1_000 shares over a pool that grew to 1_500.
+        assert_eq!(shares_for_deposit(300, 1_000, 1_500), 200);
+        assert_eq!(value_of(200, 1_200, 1_800), 300);
+        // After a loss the same shares are worth less.
+        assert_eq!(value_of(200, 1_200, 900), 150);
+    }
+
+    #[test]
+    fn rounding_favours_the_pool() {
+        // 10 lamports at 3 lamports per share buys 3 shares, not 4.
+        assert_eq!(shares_for_deposit(10, 1_000, 3_000), 3);
+        assert_eq!(value_of(1, 3, 10), 3);
+        assert_eq!(shares_for_withdrawal(10, 3, 10), 3);
+        assert_eq!(shares_for_withdrawal(4, 3, 10), 2);
+        assert_eq!(shares_for_deposit(1, 1_000, 3_000), 0);
+    }
+
+    #[test]
+    fn a_revealed_win_cannot_be_front_run_by_redeeming() {
+        // An LP holding half the shares commits a spin that reserves 400
+        // and is revealed as a 400 win, then redeems before settling.
+        let mut state = zeroed_state();
+        let mut lp = position();
+        let mut other = position();
+        for position in [&mut lp, &mut other] {
+            mint(&mut state, position, 1_000).unwrap();
+            state.total_pool += 1_000;
+        }
+        state.reserved_liability = 400;
+
+        assert_eq!(burn(&mut state, &mut lp, 1_000).unwrap(), 800);
+        state.total_pool -= 800;
+
+        // The settle pays the win out of what is left.
+        state.total_pool -= 400;
+        state.reserved_liability = 0;
+        assert_eq!(value_of(other.shares, state.total_shares, state.total_pool), 800);
+    }
+
+    #[test]
+    fn an_unpaid_reservation_returns_to_the_holders() {
+        let mut state = zeroed_state();
+        let mut lp = position();
+        mint(&mut state, &mut lp, 1_000).unwrap();
+        state.total_pool += 1_000;
+        state.reserved_liability = 1_000;
+        assert_eq!(redeemable_pool(&state), 0);
+        assert!(burn(&mut state, &mut lp, 1_000).is_err());
+
+        // The spin loses and releases its reservation.
+        state.reserved_liability = 0;
+        assert_eq!(burn(&mut state, &mut lp, 1_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn worthless_pool_mints_nothing() {
+        assert_eq!(shares_for_deposit(1_000, 500, 0), 0);
+        assert_eq!(value_of(500, 500, 0), 0);
+    }
+
+    #[test]
+    fn an_emptied_pool_writes_its_shares_off() {
+        let mut state = zeroed_state();
+        state.total_pool = 2_000;
+        let mut lp = position();
+        mint(&mut state, &mut lp, 1_000).unwrap();
+        state.total_pool += 1_000;
+        assert_eq!(state.house_shares, 2_000);
+
+        // The house's losses take the whole pool.
+        state.total_pool = 0;
+        assert_eq!(shares_for_deposit(500, state.total_shares, 0), 0);
+
+        let mut newcomer = position();
+        assert_eq!(mint(&mut state, &mut newcomer, 500).unwrap(), 500);
+        state.total_pool += 500;
+        assert_eq!((state.total_shares, state.house_shares), (500, 0));
+        assert_eq!(state.share_epoch, 1);
+
+        // The old shares claim nothing of the new deposit.
+        assert_eq!(
+            burn(&mut state, &mut lp, 1_000).unwrap_err(),
+            ErrorCode::InsufficientShares.into()
+        );
+        assert_eq!(lp.shares, 0);
+        assert_eq!(burn(&mut state, &mut newcomer, 500).unwrap(), 500);
+
+        // A written-off position mints afresh in the new epoch.
+        state.total_pool = 0;
+        mint(&mut state, &mut newcomer, 100).unwrap();
+        state.total_pool += 100;
+        assert_eq!(mint(&mut state, &mut lp, 100).unwrap(), 100);
+        assert_eq!((lp.shares, lp.share_epoch), (100, 1));
+    }
+}
@@ -0,0 +1,204 @@
+//! The supported public surface of this crate.
+//!
+//! Downstream Rust consumers (indexers, bots, verifiers) should import from
+//! here rather than reaching into the program module. Anything not
+//! re-exported below is an implementation detail and may change without a
+//! version bump. Adding or removing an item here is a deliberate API change,
+//! recorded in `api/public.txt` (see api_snapshot.rs).
+
+pub use crate::gorbagana_slots_vrf;
+pub use crate::ID;
+
+// Accounts and their embedded config types.
+pub use crate::{
+    AccountIndex, Allowlist, CommunityVault, EpochReportPage, JackpotBatchMode, JackpotPool,
+    JackpotsConfig, Leaderboard, Metrics, PayTable, PlayerState, PoolPosition, ReceiptTree,
+    ReelWeights, RtpHistory, RtpHourEntry, SlotsState, SymbolMeta, WagerRace,
+};
+
+// Events.
+pub use crate::{
+    AllowlistUpdated, AuthorityHeartbeat, AuthorityTransferCancelled, AuthorityTransferProposed,
+    AuthorityTransferred, BatchSettled, BetLimitsChanged, BetaModeChanged, BootstrapModeChanged,
+    ClaimableWithdrawn, CommunityAuthorityChanged, CommunityDestinationsChanged,
+    CommunityShareChanged, CommunitySwept, CommunityWithdrawn, ConfigUpdated, EmergencyAction,
+    EpochClosed, ExtractionBudgetChanged, ExtractionCharged, ExtractionOverrideProposed,
+    FaucetConfigChanged, FaucetDrip, FaucetFunded, FeeBalanceFunded, FeesWithdrawn,
+    FreeSpinRedeemed, FreeSpinsAwarded, JackpotBatchModeChanged, JackpotBatchShare, JackpotBoosted,
+    JackpotBoundsChanged, JackpotContribution, JackpotHit, JackpotMaxCapChanged,
+    JackpotMustHitArmed, JackpotQualifyingBetChanged, JackpotReseedPolicyChanged,
+    JackpotWeightsChanged, JackpotWeightsProposed, LeaderboardCreated, LeaderboardEpochChanged,
+    LeaderboardUpdated, LossLimitSet, LossWindowChanged, LoyaltyConfigChanged,
+    LoyaltyPointsAccrued, MachineInitialized, OnboardingSponsorChanged, OperatorFeeChanged,
+    OperatorPlayBlocked, OracleCostChanged, OracleGateChanged, OracleHealthConfigChanged,
+    OracleQueuesChanged, PaytableChanged, PlayerMigrated, PlayerOnboarded, PlayerStatsUpdated,
+    PlayerTierChanged, PoolBelowRequired, PoolDeposit, PoolPositionMigrated, PoolRestored,
+    PoolSharesWrittenOff, PoolSynced, PoolWithdrawal, RTPUpdate, RandomnessRotated,
+    ReceiptAppended, RecoveryClaimed, RecoveryConfigChanged, ReelConfigChanged, ReferralAccrued,
+    ReferralBpsChanged, ReferralEarningsClaimed, ReferrerSet, ResumeScheduled,
+    ScatterConfigChanged, SelfExclusionSet, SettleHookChanged, SettleHookInvoked,
+    SettleHookProposed, SettleHookSkipped, SpinCancelled, SpinCommitted, SpinGapChanged,
+    SpinLinesSettled, SpinProof, SpinSettled, SpinVoided, SpinsSettled, StaffWalletsChanged,
+    StateMigrated, StatsFlushed, SymbolMetaChanged, SymbolMetaProposed, TierLimitsChanged,
+    TokenTreasuryInitialized, TreasuryOutflow, UpgradeFrozen, UpgradeThawed, VipConfigChanged,
+    VipRebateAccrued, VipRebateClaimed, WagerRaceCreated, WagerRaceFinalized, WagerRacePrize,
+    WildSymbolChanged, WinnerRebateStatus, WinningsRebet, WithdrawalCancelled,
+    WithdrawalDelayChanged, WithdrawalExecuted, WithdrawalRequested,
+};
+
+// Settle hook interface for partner programs.
+pub use crate::{OnSpinSettledArgs, MAX_SETTLE_HOOKS, ON_SPIN_SETTLED_DISCRIMINATOR};
+
+// Errors.
+pub use crate::ErrorCode;
+
+// Game constants.
+pub use crate::{
+    ALLOWLIST_PAGE_CAPACITY, DEFAULT_MIN_BET, DEFAULT_ORACLE_EXPECTED_REVEAL_SLOTS,
+    DEFAULT_RECOVERY_INACTIVITY_SLOTS, DEFAULT_SPIN_EXPIRY_SLOTS, NO_WILD, PAYTABLE,
+    PROGRAM_VERSION, REEL_WEIGHTS, RESPIN_CONTEXT_INDEX, RTP_HISTORY_HOURS, SPIN_SETTLED_VERSION,
+    SWITCHBOARD_ON_DEMAND_PROGRAM_ID, SWITCHBOARD_ON_DEMAND_PROGRAM_ID_STR, SYMBOL_COUNT,
+    SYMBOL_META_TIMELOCK_SLOTS, SYMBOL_PAYOUTS_3OAK, SYMBOL_WEIGHTS, TOTAL_WEIGHT,
+    VOID_REASON_UPGRADE,
+};
+
+// PDA seeds.
+pub use crate::machines::{machine_seed, LEGACY_MACHINE_ID};
+pub use crate::{
+    ACCOUNT_INDEX_SEED, ALLOWLIST_SEED, COMMUNITY_VAULT_SEED, EPOCH_REPORT_SEED, FEE_VAULT_SEED,
+    LEADERBOARD_SEED, METRICS_SEED, PLAYER_SEED, POOL_POSITION_SEED, RECEIPT_TREE_SEED,
+    REFERRAL_SEED, RTP_HISTORY_SEED, SLOTS_SEED, SYMBOL_META_SEED, TREASURY_SEED, WAGER_RACE_SEED,
+};
+
+// Reel replay for off-chain fairness verifiers.
+pub use crate::fairness;
+
+// Return data of read-only instructions.
+pub use crate::accounting::AccountingReport;
+pub use crate::bootstrap::BootstrapStatus;
+pub use crate::reseed::{JackpotRebuildReport, TierRebuildStatus};
+pub use crate::risk::{JackpotEta, JackpotEtaReport};
+pub use crate::simulate::PayoutSimulation;
+pub use crate::GameStatus;
+
+// Jackpot reseed policy.
+pub use crate::reseed::ReseedPolicy;
+
+// Off-chain signed bet message schema.
+pub use crate::signed_bet::{SignedBet, SIGNED_BET_DOMAIN};
+
+// Compressed receipt hashing and proof checks.
+pub use crate::receipts;
+
+// Oracle cost accounting.
+pub use crate::oracle_cost::{self, OracleCostMode};
+
+// Oracle-health gate.
+pub use crate::oracle_health::{OracleHealth, OracleHealthStatus};
+
+// House P&L per feature.
+pub use crate::feature_stats::{Feature, FeaturePnl, FeatureTotals, FEATURE_KINDS};
+
+// Oracle queue whitelist.
+pub use crate::oracle_queue::{OracleQueueEntry, MAX_ORACLE_QUEUES};
+
+// Outcomes per VRF reveal.
+pub use crate::outcome_budget::{DEFAULT_MAX_OUTCOMES_PER_REVEAL, SPIN_OUTCOMES};
+
+// Authority extraction budget.
+pub use crate::extraction::{DEFAULT_EXTRACTION_BUDGET_BPS, EXTRACTION_PERIOD_SLOTS};
+
+// Tunable paytable RTP ceiling.
+pub use crate::paytable::DEFAULT_MAX_RTP_BPS;
+
+// Operator keys barred from playing.
+pub use crate::operators::{is_operator_key, MAX_STAFF_WALLETS};
+
+// Operator fee on bets.
+pub use crate::operator_fee::MAX_OPERATOR_FEE_BPS;
+
+// Pool LP share math.
+pub use crate::pool_shares;
+
+// Pause flags.
+pub use crate::pause::{
+    PAUSE_ALL, PAUSE_DEPOSITS, PAUSE_NEW_SPINS, PAUSE_SETTLEMENT, PAUSE_WITHDRAWALS,
+};
+
+// SlotsState layout versions.
+pub use crate::migration::SLOTS_STATE_VERSION;
+
+// Community revenue share routing.
+pub use crate::community::{self, MAX_COMMUNITY_DESTINATIONS};
+
+// Symbol identities and the config hash.
+pub use crate::symbol_meta::{self, SymbolEntry, SYMBOL_NAME_LEN};
+
+// Per-epoch accounting reports.
+pub use crate::epoch_reports::{EpochReport, EpochTotals, EPOCH_REPORTS_PER_PAGE};
+
+// Authority-updatable game configuration.
+pub use crate::config::{ConfigSnapshot, UpdateConfigArgs};
+
+// Jackpot tail-risk bounds.
+pub use crate::jackpot_bounds::{self, JackpotWeights};
+
+// Must-hit-by jackpot ceilings.
+pub use crate::must_hit;
+
+// Test-only instruction guard.
+pub use crate::qa::{self, ForcedOutcome};
+
+// Pool level against the spin requirement.
+pub use crate::pool_level;
+
+// Commit-order payout reservations.
+pub use crate::reservations;
+
+// Shared randomness account checks and their diagnostics.
+pub use crate::randomness::{self, RandomnessCheck};
+
+// Scatter-triggered free spins.
+pub use crate::scatter::{self, ScatterConfig};
+
+// Wager race boards.
+pub use crate::wager_race::{WagerRaceEntry, WAGER_RACE_BOARD_SIZE};
+
+// Token-gated VIP rebates.
+pub use crate::vip::{self, VipConfig};
+
+// Several spins committed against one reveal.
+pub use crate::game_math::MAX_SPINS_PER_COMMIT;
+pub use crate::multi_spin;
+
+// Per-epoch winner leaderboards.
+pub use crate::leaderboard::{self, LeaderboardEntry, LEADERBOARD_SIZE};
+
+// Per-player limit tiers.
+pub use crate::tiers::{TierLimits, MAX_PLAYER_TIERS};
+
+// Treasury outflow categories and memo rules.
+pub use crate::outflows::{category_allowed, OutflowCategory, OutflowSource, OUTFLOW_CATEGORIES};
+
+// Per-player account registry kinds.
+pub use crate::account_index;
+
+// Earmark ledger shared by prize-holding features.
+pub use crate::obligations::{
+    validate_invariants, EarmarkKind, EarmarkedObligations, EARMARK_KINDS,
+};
+
+// Off-chain account decoders and layouts.
+#[cfg(not(target_os = "solana"))]
+pub use crate::{client, layout};
+
+// Published outcome test vectors.
+#[cfg(all(feature = "vectors", not(target_os = "solana")))]
+pub use crate::vectors;
+
+// Fixed byte offsets of the Metrics account.
+pub use crate::metrics;
+
+// Pure outcome math shared with off-chain verifiers.
+pub use crate::game_math;
+pub use crate::game_math::{PayoutLimits, SpinOutcome};
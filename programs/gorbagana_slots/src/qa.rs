@@ -0,0 +1,108 @@
+//! Guard for test-only instructions.
+//!
+//! QA conveniences such as force_outcome must never run on mainnet, even
+//! if a build mistake ships them. Two independent layers:
+//!
+//! 1. Compile time: the code that honours a forced outcome only exists
+//!    with the `qa` cargo feature, which release builds leave off. The
+//!    instructions themselves are in every build, as Anchor's dispatcher
+//!    ignores cfg on a handler, and refuse to run without the feature.
+//! 2. Run time: `SlotsState.network_guard` is set once at initialize and
+//!    test-only instructions refuse to run unless it is a known dev/test
+//!    value: a cluster's genesis hash, or [`LOCALNET_MARKER`] on a local
+//!    validator, whose genesis changes on every reset. A program cannot
+//!    read the genesis hash, so the guard is the initializer's declaration,
+//!    not a proof of the cluster. Only IMMUTABLE_PROGRAM_DEPLOYER, the key
+//!    baked into the build, may declare a test network
+//!    ([`check_network_guard`]); an upgrade authority initializing with
+//!    any other key gets a production guard.
+//!
+//! The devnet faucet (faucet.rs) only has the run-time layer: it cannot
+//! influence outcomes, and devnet deployments need it without `qa`.
+//!
+//! The fields they write are part of the account layout in every build,
+//! so turning the feature on or off never changes an account's size.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::pubkey;
+
+use crate::{ErrorCode, SlotsState};
+
+/// Solana devnet genesis hash.
+pub const DEVNET_GENESIS: Pubkey = pubkey!("EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG");
+/// Solana testnet genesis hash.
+pub const TESTNET_GENESIS: Pubkey = pubkey!("4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY");
+/// Marker for local validators.
+pub const LOCALNET_MARKER: Pubkey =
+    Pubkey::new_from_array(*b"gorbagana-slots:localnet-qa\0\0\0\0\0");
+
+/// No jackpot forced.
+pub const NO_FORCED_JACKPOT: u8 = u8::MAX;
+
+/// Outcome the next settle of a player must produce (QA builds only).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ForcedOutcome {
+    pub active: bool,
+    pub symbols: [u8; 3],
+    /// Jackpot tier to award, or NO_FORCED_JACKPOT.
+    pub jackpot_tier: u8,
+}
+
+pub fn is_test_network(network_guard: &Pubkey) -> bool {
+    [DEVNET_GENESIS, TESTNET_GENESIS, LOCALNET_MARKER].contains(network_guard)
+}
+
+/// Refuse a test `network_guard` unless `payer` is the build's deployer.
+pub fn check_network_guard(
+    network_guard: &Pubkey,
+    payer: &Pubkey,
+    deployer: Option<Pubkey>,
+) -> Result<()> {
+    require!(
+        !is_test_network(network_guard) || deployer == Some(*payer),
+        ErrorCode::TestNetworkGuardNotAllowed
+    );
+    Ok(())
+}
+
+/// Runtime layer of the guard; call first in every test-only instruction.
+pub fn require_test_network(state: &SlotsState) -> Result<()> {
+    require!(
+        is_test_network(&state.network_guard),
+        ErrorCode::TestOnlyInstruction
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed_state;
+
+    #[test]
+    fn test_only_instructions_refuse_a_production_guard() {
+        let mut state = zeroed_state();
+        for guard in [Pubkey::default(), Pubkey::new_unique()] {
+            state.network_guard = guard;
+            // force_outcome's run-time check.
+            assert!(require_test_network(&state).is_err());
+        }
+        for guard in [DEVNET_GENESIS, TESTNET_GENESIS, LOCALNET_MARKER] {
+            state.network_guard = guard;
+            assert!(require_test_network(&state).is_ok());
+        }
+    }
+
+    #[test]
+    fn only_the_deployer_declares_a_test_network() {
+        let deployer = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mainnet = Pubkey::new_unique();
+
+        assert!(check_network_guard(&DEVNET_GENESIS, &deployer, Some(deployer)).is_ok());
+        assert!(check_network_guard(&DEVNET_GENESIS, &other, Some(deployer)).is_err());
+        assert!(check_network_guard(&LOCALNET_MARKER, &other, None).is_err());
+        assert!(check_network_guard(&mainnet, &other, Some(deployer)).is_ok());
+        assert!(check_network_guard(&mainnet, &other, None).is_ok());
+    }
+}
@@ -0,0 +1,489 @@
+//! Checks of the Switchboard randomness account shared by commits and
+//! settles.
+//!
+//! Every refusal gets its own error: an account of another program
+//! (RandomnessAccountWrongOwner), one too small to hold
+//! RandomnessAccountData (RandomnessAccountTooSmall), one that does not
+//! parse (RandomnessParseFailed), a generation too old to commit to
+//! (RandomnessSeedSlotTooOld), an account re-seeded since the commit
+//! (RandomnessSeedChanged) and a committed generation that has not
+//! revealed yet (RandomnessNotResolved). Anchor errors carry no data, so
+//! each is preceded by a [`RandomnessDiagnostic`] log line with what was
+//! read: the owner, data length, seed and reveal slots, the seed slot
+//! the caller expected and the current slot. Fields not read before the
+//! failure are zero. It is logged rather than emitted: a failed
+//! transaction drops its events but keeps its logs.
+//!
+//! validate_commit and the settles build a [`RandomnessCheck`] and go
+//! through it, so every commit and settle path reports the same way. A
+//! key that is not the committed account is still
+//! RandomnessAccountMismatch, with no diagnostic: the account read is not
+//! the spin's.
+
+use std::cell::Ref;
+use std::fmt;
+
+use anchor_lang::prelude::*;
+use switchboard_on_demand::on_demand::accounts::RandomnessAccountData;
+
+use crate::{time, ErrorCode, PlayerState, SWITCHBOARD_ON_DEMAND_PROGRAM_ID};
+
+/// Smallest account that can hold RandomnessAccountData.
+pub const RANDOMNESS_ACCOUNT_MIN_LEN: usize = 8 + core::mem::size_of::<RandomnessAccountData>();
+
+/// What was read from a refused randomness account; fields not read yet
+/// are zero. Logged as one `randomness refused:` line before the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomnessDiagnostic {
+    pub user: Pubkey,
+    pub randomness_account: Pubkey,
+    /// Anchor error code returned right after.
+    pub error_code: u32,
+    pub owner: Pubkey,
+    pub data_len: u64,
+    /// The committed seed slot at settle; the oldest fresh one at commit.
+    pub expected_seed_slot: u64,
+    pub seed_slot: u64,
+    pub reveal_slot: u64,
+    pub current_slot: u64,
+}
+
+impl fmt::Display for RandomnessDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "randomness refused: error_code={} user={} randomness_account={} owner={} \
+             data_len={} expected_seed_slot={} seed_slot={} reveal_slot={} current_slot={}",
+            self.error_code,
+            self.user,
+            self.randomness_account,
+            self.owner,
+            self.data_len,
+            self.expected_seed_slot,
+            self.seed_slot,
+            self.reveal_slot,
+            self.current_slot,
+        )
+    }
+}
+
+/// Who reads the account, and what they expect of it.
+pub struct RandomnessCheck {
+    pub user: Pubkey,
+    /// The committed seed slot at settle; the oldest fresh one at commit.
+    pub expected_seed_slot: u64,
+    /// The committed generation's slothash at settle; unused at commit.
+    pub expected_seed_slothash: [u8; 32],
+    pub current_slot: u64,
+}
+
+impl RandomnessCheck {
+    /// For a commit in `current_slot`: the generation may be at most one
+    /// slot old.
+    pub fn for_commit(user: Pubkey, current_slot: u64) -> Self {
+        RandomnessCheck {
+            user,
+            expected_seed_slot: current_slot.saturating_sub(1),
+            expected_seed_slothash: [0; 32],
+            current_slot,
+        }
+    }
+
+    /// For a settle of the player's pending spin: the generation seeded at
+    /// its commit slot with its slothash.
+    pub fn for_settle(user: Pubkey, player_state: &PlayerState, current_slot: u64) -> Self {
+        RandomnessCheck {
+            user,
+            expected_seed_slot: player_state.commit_slot,
+            expected_seed_slothash: player_state.commit_seed_slothash,
+            current_slot,
+        }
+    }
+
+    /// Owner, size and layout of the account; its data when all pass.
+    pub fn load<'a>(&self, ai: &'a AccountInfo) -> Result<Ref<'a, RandomnessAccountData>> {
+        if *ai.owner != SWITCHBOARD_ON_DEMAND_PROGRAM_ID {
+            return Err(self.fail(ai, None, ErrorCode::RandomnessAccountWrongOwner));
+        }
+        if ai.data_len() < RANDOMNESS_ACCOUNT_MIN_LEN {
+            return Err(self.fail(ai, None, ErrorCode::RandomnessAccountTooSmall));
+        }
+        RandomnessAccountData::parse(ai.data.borrow())
+            .map_err(|_| self.fail(ai, None, ErrorCode::RandomnessParseFailed))
+    }
+
+    /// A commit may only bind a generation seeded this slot or the last.
+    pub fn ensure_fresh(&self, ai: &AccountInfo, data: &RandomnessAccountData) -> Result<()> {
+        if time::is_fresh(data.seed_slot, self.current_slot, 1) {
+            return Ok(());
+        }
+        let code = if data.seed_slot < self.expected_seed_slot {
+            ErrorCode::RandomnessSeedSlotTooOld
+        } else {
+            ErrorCode::RandomnessNotResolved
+        };
+        Err(self.fail(ai, Some(data), code))
+    }
+
+    /// Revealed bytes of the expected generation. Read from the account
+    /// rather than through `get_value`, which only answers in the reveal
+    /// slot itself and so failed any settle landing later. An account
+    /// re-seeded since the commit is refused: its value belongs to another
+    /// round, and the player, who owns the account, could re-seed after
+    /// seeing a losing reveal.
+    pub fn reveal(&self, ai: &AccountInfo, data: &RandomnessAccountData) -> Result<[u8; 32]> {
+        if !self.same_generation(data) {
+            return Err(self.fail(ai, Some(data), ErrorCode::RandomnessSeedChanged));
+        }
+        if data.reveal_slot <= self.expected_seed_slot {
+            return Err(self.fail(ai, Some(data), ErrorCode::RandomnessNotResolved));
+        }
+        Ok(data.value)
+    }
+
+    /// Whether the account still holds the committed generation.
+    pub fn same_generation(&self, data: &RandomnessAccountData) -> bool {
+        data.seed_slot == self.expected_seed_slot
+            && data.seed_slothash == self.expected_seed_slothash
+    }
+
+    /// What a refusal with `code` reports about `ai`.
+    pub fn diagnostic(
+        &self,
+        ai: &AccountInfo,
+        data: Option<&RandomnessAccountData>,
+        code: ErrorCode,
+    ) -> RandomnessDiagnostic {
+        let (seed_slot, reveal_slot) = data.map_or((0, 0), |d| (d.seed_slot, d.reveal_slot));
+        RandomnessDiagnostic {
+            user: self.user,
+            randomness_account: ai.key(),
+            error_code: code.into(),
+            owner: *ai.owner,
+            data_len: ai.data_len() as u64,
+            expected_seed_slot: self.expected_seed_slot,
+            seed_slot,
+            reveal_slot,
+            current_slot: self.current_slot,
+        }
+    }
+
+    /// Log the RandomnessDiagnostic of `code` and return it as the error.
+    pub fn fail(
+        &self,
+        ai: &AccountInfo,
+        data: Option<&RandomnessAccountData>,
+        code: ErrorCode,
+    ) -> Error {
+        msg!("{}", self.diagnostic(ai, data, code));
+        code.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{zeroed_player, Game, Randomness};
+    use crate::{game_math, oracle_cost, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+
+    /// Run `step` on an account of `owner` holding `data`.
+    fn with_account<R>(
+        owner: Pubkey,
+        mut data: Vec<u8>,
+        step: impl FnOnce(&AccountInfo) -> R,
+    ) -> R {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        step(&info)
+    }
+
+    /// Assert `step` refuses `ai` with `code`; the diagnostic `fail`
+    /// logged for it.
+    fn refused(
+        check: &RandomnessCheck,
+        ai: &AccountInfo,
+        step: impl FnOnce(&AccountInfo) -> Result<()>,
+        code: ErrorCode,
+    ) -> RandomnessDiagnostic {
+        assert_eq!(step(ai).unwrap_err(), code.into());
+        let data = check.load(ai).ok();
+        check.diagnostic(ai, data.as_deref(), code)
+    }
+
+    #[test]
+    fn an_unreadable_account_is_refused_with_what_was_read() {
+        let check = RandomnessCheck::for_commit(Pubkey::new_unique(), 100);
+        let load = |ai: &AccountInfo| check.load(ai).map(|_| ());
+
+        let d = with_account(Pubkey::new_unique(), vec![0; 16], |ai| {
+            refused(&check, ai, load, ErrorCode::RandomnessAccountWrongOwner)
+        });
+        assert_eq!((d.data_len, d.seed_slot, d.current_slot), (16, 0, 100));
+        assert_ne!(d.owner, SWITCHBOARD_ON_DEMAND_PROGRAM_ID);
+
+        let d = with_account(SWITCHBOARD_ON_DEMAND_PROGRAM_ID, vec![0; 16], |ai| {
+            refused(&check, ai, load, ErrorCode::RandomnessAccountTooSmall)
+        });
+        assert_eq!((d.owner, d.data_len), (SWITCHBOARD_ON_DEMAND_PROGRAM_ID, 16));
+
+        let len = RANDOMNESS_ACCOUNT_MIN_LEN;
+        let d = with_account(SWITCHBOARD_ON_DEMAND_PROGRAM_ID, vec![0; len], |ai| {
+            refused(&check, ai, load, ErrorCode::RandomnessParseFailed)
+        });
+        assert_eq!(d.error_code, u32::from(ErrorCode::RandomnessParseFailed));
+        assert_eq!(d.data_len, len as u64);
+        assert!(d.to_string().starts_with("randomness refused: error_code="));
+    }
+
+    #[test]
+    fn a_commit_refuses_a_stale_or_future_generation() {
+        let mut randomness = Randomness::default();
+        let check = RandomnessCheck::for_commit(Pubkey::new_unique(), 100);
+        let fresh = |ai: &AccountInfo| {
+            let data = check.load(ai)?;
+            check.ensure_fresh(ai, &data)
+        };
+
+        randomness.seed(97, 1);
+        let d = randomness.with_info(|ai| {
+            refused(&check, ai, fresh, ErrorCode::RandomnessSeedSlotTooOld)
+        });
+        assert_eq!((d.expected_seed_slot, d.seed_slot, d.current_slot), (99, 97, 100));
+        assert_eq!(d.randomness_account, randomness.key);
+
+        randomness.seed(101, 1);
+        let d = randomness.with_info(|ai| {
+            refused(&check, ai, fresh, ErrorCode::RandomnessNotResolved)
+        });
+        assert_eq!(d.seed_slot, 101);
+
+        randomness.seed(99, 1);
+        randomness.with_info(fresh).expect("a generation one slot old is fresh");
+    }
+
+    #[test]
+    fn a_settle_refuses_a_rotated_or_unrevealed_generation() {
+        let mut randomness = Randomness::default();
+        randomness.seed(100, 1);
+        let mut player = zeroed_player();
+        player.commit_slot = 100;
+        player.commit_seed_slothash = randomness.data.seed_slothash;
+        let check = RandomnessCheck::for_settle(Pubkey::new_unique(), &player, 110);
+        let reveal = |ai: &AccountInfo| {
+            let data = check.load(ai)?;
+            check.reveal(ai, &data).map(|_| ())
+        };
+
+        let d = randomness.with_info(|ai| {
+            refused(&check, ai, reveal, ErrorCode::RandomnessNotResolved)
+        });
+        assert_eq!((d.expected_seed_slot, d.seed_slot, d.reveal_slot), (100, 100, 0));
+
+        randomness.seed(105, 2);
+        randomness.reveal(106, [3; 32]);
+        let d = randomness.with_info(|ai| {
+            refused(&check, ai, reveal, ErrorCode::RandomnessSeedChanged)
+        });
+        assert_eq!((d.seed_slot, d.reveal_slot, d.current_slot), (105, 106, 110));
+    }
+
+    /// A reveal that pays nothing on one line of BET.
+    fn losing_value(game: &Game) -> [u8; 32] {
+        (0..=u8::MAX)
+            .map(|byte| [byte; 32])
+            .find(|&value| {
+                let mut probe = game.clone();
+                probe.reveal(value);
+                probe.settle().expect("probe settles").total_payout == 0
+            })
+            .expect("some reveal loses")
+    }
+
+    #[test]
+    fn a_rotation_before_the_reveal_holds_the_stake_for_review() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        let wallet = game.wallet;
+
+        // The player re-seeds the account before the oracle answers.
+        game.advance(1);
+        game.randomness.seed(game.slot, 0xee);
+        game.reveal([7; 32]);
+
+        let err = game.settle().unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessSeedChanged.into());
+        assert!(game.player.has_pending_spin);
+        assert_eq!(game.wallet, wallet);
+        game.verify().expect("the stake is still held");
+
+        // Only the admin's review returns it, and then to the claimable
+        // balance.
+        let refund = game.admin_void().expect("voids");
+        assert_eq!(refund, BET);
+        assert_eq!(game.player.claimable_balance, BET);
+        assert_eq!(game.wallet, wallet);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_void_takes_back_exactly_the_jackpot_contributions() {
+        let mut game = Game::new();
+        let before = game.state.jackpots.mini.amount;
+        game.request(BET).expect("commits");
+        let contrib = game.player.pending_jackpot_contribs[0];
+        assert!(contrib > 0);
+
+        // Another player hit the mini since, and it reseeded below the
+        // contribution.
+        let mut short = game.clone();
+        short.state.jackpots.mini.amount = contrib - 1;
+        let err = short.admin_void().unwrap_err();
+        assert_eq!(err, ErrorCode::JackpotUnwindShortfall.into());
+        assert!(short.player.has_pending_spin);
+        assert_eq!(short.state.jackpots.mini.amount, contrib - 1);
+
+        assert_eq!(game.admin_void().expect("voids"), BET);
+        assert_eq!(game.state.jackpots.mini.amount, before);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_rotation_after_a_losing_reveal_does_not_refund_it() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        let wallet = game.wallet;
+        let losing = losing_value(&game);
+        game.reveal(losing);
+
+        // The revealed loss cannot be voided while it stands.
+        let err = game.admin_void().unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessAlreadyRevealed.into());
+
+        // Re-seeding over it does not turn it into a refund.
+        game.advance(1);
+        game.randomness.seed(game.slot, 0xee);
+        let err = game.settle().unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessSeedChanged.into());
+        assert!(game.player.has_pending_spin);
+        assert_eq!(game.wallet, wallet);
+        assert_eq!(game.player.claimable_balance, 0);
+        game.verify().expect("the stake is still held");
+    }
+
+    #[test]
+    fn an_expired_unrevealed_spin_is_refunded() {
+        let mut game = Game::new();
+        let before = game.clone();
+        game.request(BET).expect("commits");
+        assert!(game.state.jackpots.major.amount > before.state.jackpots.major.amount);
+
+        game.advance(game.state.spin_expiry_slots - 1);
+        let err = game.cancel().unwrap_err();
+        assert_eq!(err, ErrorCode::SpinNotExpired.into());
+
+        // The reveal never came: the commit is undone and the bet paid back.
+        game.advance(1);
+        let refunded = game.cancel().expect("cancels");
+        assert_eq!(refunded, BET);
+        assert_eq!(game.wallet, before.wallet - oracle_cost::player_charge(&game.state));
+        assert_eq!(game.state.total_wagered, before.state.total_wagered);
+        assert_eq!(game.state.total_pool, before.state.total_pool);
+        assert_eq!(game.state.jackpots.mini.amount, before.state.jackpots.mini.amount);
+        assert_eq!(game.state.jackpots.major.amount, before.state.jackpots.major.amount);
+        assert_eq!(game.state.jackpots.grand.amount, before.state.jackpots.grand.amount);
+        assert!(!game.player.has_pending_spin);
+        assert_eq!(game.state.reserved_liability, 0);
+        assert_eq!(game.state.pending_spins, 0);
+        game.verify().expect("books hold");
+
+        // The player is free to commit again.
+        game.request(BET).expect("commits again");
+    }
+
+    #[test]
+    fn a_rotated_spin_is_forfeit_on_cancel() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        let wallet = game.wallet;
+        game.advance(1);
+        game.randomness.seed(game.slot, 0xee);
+        game.reveal([7; 32]);
+
+        // The player replaced the generation and may have seen it.
+        let pool = game.state.total_pool;
+        game.advance(game.state.spin_expiry_slots);
+        assert_eq!(game.cancel().expect("cancels"), 0);
+        assert_eq!(game.wallet, wallet);
+        assert_eq!(game.state.total_pool, pool);
+        assert_eq!(game.player.claimable_balance, 0);
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_revealed_spin_cannot_be_cancelled() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        game.reveal([7; 32]);
+        game.advance(game.state.spin_expiry_slots);
+
+        let err = game.cancel().unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessAlreadyRevealed.into());
+        game.settle().expect("settles");
+    }
+
+    #[test]
+    fn a_spin_settles_in_its_reveal_slot() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        game.reveal([7; 32]);
+        let settled = game.settle().expect("settles");
+        assert_eq!(settled.vrf_hash, game_math::vrf_hash(&[7; 32]));
+        assert!(!game.player.has_pending_spin);
+    }
+
+    #[test]
+    fn a_spin_settles_many_slots_after_its_reveal() {
+        let mut game = Game::new();
+        game.request(BET).expect("commits");
+        game.reveal([7; 32]);
+        game.advance(500);
+        let settled = game.settle().expect("settles late");
+        assert_eq!(settled.vrf_hash, game_math::vrf_hash(&[7; 32]));
+        game.verify().expect("books hold");
+    }
+
+    #[test]
+    fn one_account_serves_two_commits_one_generation_each() {
+        let mut game = Game::new();
+        let account = game.randomness.key;
+        game.request(BET).expect("first commit");
+        game.reveal([7; 32]);
+        game.settle().expect("first settle");
+
+        // The same account, re-seeded for the next commit.
+        game.advance(1);
+        game.request(BET).expect("second commit");
+        assert_eq!(game.player.randomness_account, account);
+        assert_eq!(game.player.commit_slot, game.slot);
+
+        // Its fresh generation is unrevealed: the first value is gone.
+        let err = game.settle().unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessNotResolved.into());
+
+        game.reveal([9; 32]);
+        let settled = game.settle().expect("second settle");
+        assert_eq!(settled.vrf_hash, game_math::vrf_hash(&[9; 32]));
+    }
+}
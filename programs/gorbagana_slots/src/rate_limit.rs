@@ -0,0 +1,59 @@
+//! Minimum slot gap between a player's spin commits.
+//!
+//! `SlotsState.min_slots_between_spins` (0 = off) is the fewest slots a
+//! commit must come after the same player's previous one, measured from
+//! `PlayerState.last_request_slot`. Only commits stamp it. A cancelled or
+//! voided spin keeps its stamp, so cancelling never shortens the gap, and
+//! a settle_and_respin re-commit is held to it like any other commit.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, PlayerState, SlotsState};
+
+/// Whether a commit at `now` is far enough from the last one. A player
+/// who never committed (`last_request_slot` 0) always passes.
+pub fn gap_elapsed(last_request_slot: u64, now: u64, min_gap: u16) -> bool {
+    last_request_slot == 0
+        || time::slots_elapsed_saturating(last_request_slot, now) >= min_gap as u64
+}
+
+pub fn ensure_spin_gap(state: &SlotsState, player_state: &PlayerState, now: u64) -> Result<()> {
+    require!(
+        gap_elapsed(
+            player_state.last_request_slot,
+            now,
+            state.min_slots_between_spins
+        ),
+        ErrorCode::SpinTooSoon
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_slot_commit_is_rejected_with_a_gap() {
+        assert!(!gap_elapsed(100, 100, 1));
+        assert!(gap_elapsed(100, 101, 1));
+    }
+
+    #[test]
+    fn no_gap_allows_back_to_back_commits() {
+        assert!(gap_elapsed(100, 100, 0));
+    }
+
+    #[test]
+    fn gap_runs_from_the_cancelled_commit() {
+        // cancel_spin leaves last_request_slot at the cancelled commit.
+        let cancelled_commit = 100;
+        assert!(!gap_elapsed(cancelled_commit, 104, 5));
+        assert!(gap_elapsed(cancelled_commit, 105, 5));
+    }
+
+    #[test]
+    fn first_commit_always_passes() {
+        assert!(gap_elapsed(0, 0, u16::MAX));
+    }
+}
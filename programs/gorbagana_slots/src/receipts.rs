@@ -0,0 +1,113 @@
+//! Compressed per-spin receipts.
+//!
+//! Instead of one receipt PDA per spin, settle appends the hash of the
+//! receipt to an append-only Merkle tree of depth [`RECEIPT_TREE_DEPTH`]
+//! and emits the receipt in `ReceiptAppended`, from which indexers rebuild
+//! the leaves and proofs. Settle is the only writer and it takes the tree
+//! write lock, so a sequential incremental tree is enough: each append
+//! updates `filled_subtrees` along one path and produces the new root.
+//!
+//! The last [`RECEIPT_ROOT_HISTORY`] roots are kept so a proof built
+//! against a slightly older root still verifies after further appends.
+//! Hashing is sha256; interior nodes are `hash(left || right)`, empty
+//! subtrees use the zero hashes `z[0] = [0; 32]`, `z[i+1] = hash(z[i] || z[i])`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{ErrorCode, ReceiptTree};
+
+pub const RECEIPT_TREE_DEPTH: usize = 20;
+pub const RECEIPT_ROOT_HISTORY: usize = 16;
+
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[left, right]).to_bytes()
+}
+
+/// Leaf hash of one settled spin.
+pub fn receipt_leaf(
+    player: &Pubkey,
+    leaf_index: u64,
+    bet: u64,
+    symbols: [u8; 3],
+    base_payout: u64,
+    jackpot_payout: u64,
+    vrf_hash: &[u8; 32],
+) -> [u8; 32] {
+    hashv(&[
+        player.as_ref(),
+        &leaf_index.to_le_bytes(),
+        &bet.to_le_bytes(),
+        &symbols,
+        &base_payout.to_le_bytes(),
+        &jackpot_payout.to_le_bytes(),
+        vrf_hash,
+    ])
+    .to_bytes()
+}
+
+/// Root implied by `leaf` at `index` with sibling `proof` (leaf level first).
+pub fn root_from_proof(leaf: &[u8; 32], index: u64, proof: &[[u8; 32]]) -> [u8; 32] {
+    let mut node = *leaf;
+    for (level, sibling) in proof.iter().enumerate() {
+        node = if (index >> level) & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+    }
+    node
+}
+
+impl ReceiptTree {
+    /// Set up an empty tree.
+    pub fn init_empty(&mut self) {
+        let mut zero = [0u8; 32];
+        for level in 0..RECEIPT_TREE_DEPTH {
+            self.zero_hashes[level] = zero;
+            self.filled_subtrees[level] = zero;
+            zero = hash_pair(&zero, &zero);
+        }
+        self.root = zero;
+        self.next_index = 0;
+        self.root_history = [[0u8; 32]; RECEIPT_ROOT_HISTORY];
+        self.root_history[0] = zero;
+        self.root_history_index = 0;
+    }
+
+    /// Append a leaf; returns its index.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<u64> {
+        let index = self.next_index;
+        require!(
+            index < (1u64 << RECEIPT_TREE_DEPTH),
+            ErrorCode::ReceiptTreeFull
+        );
+
+        let mut node = leaf;
+        for level in 0..RECEIPT_TREE_DEPTH {
+            if (index >> level) & 1 == 0 {
+                self.filled_subtrees[level] = node;
+                node = hash_pair(&node, &self.zero_hashes[level]);
+            } else {
+                node = hash_pair(&self.filled_subtrees[level], &node);
+            }
+        }
+
+        self.root = node;
+        self.next_index = index + 1;
+        self.root_history_index = (self.root_history_index + 1) % RECEIPT_ROOT_HISTORY as u64;
+        self.root_history[self.root_history_index as usize] = node;
+        Ok(index)
+    }
+
+    /// Whether `root` is the current root or one of the recent ones.
+    pub fn is_known_root(&self, root: &[u8; 32]) -> bool {
+        self.root_history.iter().any(|r| r == root)
+    }
+
+    pub fn verify(&self, leaf: &[u8; 32], index: u64, proof: &[[u8; 32]]) -> bool {
+        proof.len() == RECEIPT_TREE_DEPTH
+            && index < self.next_index
+            && self.is_known_root(&root_from_proof(leaf, index, proof))
+    }
+}
@@ -0,0 +1,100 @@
+//! Referrer payouts.
+//!
+//! A player names a referrer once, with set_referrer, before their first
+//! spin; it is never changed afterwards and may not be the player. Every
+//! spin they settle then credits the referrer `referral_bps` of the
+//! house's expected edge on the bet,
+//! `bet * (10_000 - theoretical_rtp_bps) / 10_000`, win or lose.
+//!
+//! The credit leaves `total_pool` for `referral_balance`, which sits in
+//! the treasury next to the pool like the community share, and is added
+//! to the referrer's [`ReferralEarnings`] PDA. It is capped at what the
+//! pool holds above required_floor, so accrual never eats into the
+//! reserve; a settle is never failed for it. claim_referral_earnings pays
+//! the accrued lamports out of the treasury.
+//!
+//! settle_spin and settle_and_respin require the referrer's PDA for a
+//! referred player. settle_spin_deferred refuses them with
+//! SettleNeedsWriteLock. Referrals are SOL only.
+
+use anchor_lang::prelude::*;
+
+use crate::{required_floor, ErrorCode, ReferralAccrued, ReferralEarnings, SlotsState};
+
+/// Highest settable referral share: half the house edge.
+pub const MAX_REFERRAL_BPS: u16 = 5_000;
+
+impl ReferralEarnings {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
+}
+
+/// Referral credit for a bet under a config returning `rtp_bps`.
+pub fn credit_for(referral_bps: u16, rtp_bps: u32, bet: u64) -> u64 {
+    let edge_bps = 10_000u128.saturating_sub(rtp_bps as u128);
+    let credit = (bet as u128) * edge_bps * (referral_bps as u128) / 100_000_000;
+    credit.min(u64::MAX as u128) as u64
+}
+
+/// Move the referral credit of a settled bet out of the pool into
+/// `earnings`. Returns the amount credited.
+pub fn accrue(
+    state: &mut SlotsState,
+    earnings: &mut ReferralEarnings,
+    user: &Pubkey,
+    bet: u64,
+) -> Result<u64> {
+    let headroom = state.total_pool.saturating_sub(required_floor(state)?);
+    let credit = credit_for(state.referral_bps, state.theoretical_rtp_bps, bet).min(headroom);
+    earnings.referred_spins = earnings.referred_spins.saturating_add(1);
+    if credit > 0 {
+        state.total_pool -= credit;
+        state.referral_balance = state
+            .referral_balance
+            .checked_add(credit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        earnings.accrued = earnings
+            .accrued
+            .checked_add(credit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        earnings.total_earned = earnings.total_earned.saturating_add(credit);
+    }
+
+    emit!(ReferralAccrued {
+        referrer: earnings.referrer,
+        user: *user,
+        bet_amount: bet,
+        amount: credit,
+        accrued: earnings.accrued,
+    });
+    Ok(credit)
+}
+
+/// Take everything `earnings` has accrued off the books; the caller pays
+/// it out of the treasury.
+pub fn take(state: &mut SlotsState, earnings: &mut ReferralEarnings) -> Result<u64> {
+    let amount = earnings.accrued;
+    require!(amount > 0, ErrorCode::NothingClaimable);
+    state.referral_balance = state
+        .referral_balance
+        .checked_sub(amount)
+        .ok_or(ErrorCode::AccountingInvariantViolated)?;
+    earnings.accrued = 0;
+    earnings.total_claimed = earnings.total_claimed.saturating_add(amount);
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credit_is_a_share_of_the_expected_edge() {
+        // 4% edge, 25% of it: 1% of the bet.
+        assert_eq!(credit_for(2_500, 9_600, 1_000_000), 10_000);
+        assert_eq!(credit_for(0, 9_600, 1_000_000), 0);
+        // No edge, no credit.
+        assert_eq!(credit_for(MAX_REFERRAL_BPS, 10_000, 1_000_000), 0);
+        assert_eq!(credit_for(MAX_REFERRAL_BPS, 10_500, 1_000_000), 0);
+        assert_eq!(credit_for(2_500, 9_600, 99), 0);
+    }
+}
@@ -0,0 +1,187 @@
+//! How a jackpot tier gets back to its seed after a hit.
+//!
+//! `ReserveFunded` is the original behaviour: the tier resets straight to
+//! `seed`, funded out of the house pool at the moment of the hit.
+//!
+//! `FeeRebuilt` resets the tier to zero instead. Until it is back at
+//! `seed`, each bet routes `rebuild_bps` of the wager (the house's share,
+//! so it never comes out of player payouts) into the tier in place of the
+//! normal contribution; once the seed is reached normal contributions take
+//! over. The displayed jackpot is then always money the bets have already
+//! put in, so the liability is funded by construction. While rebuilding
+//! the tier is worth less than its seed, so the game temporarily offers a
+//! lower expected return than the configured tables suggest.
+
+use anchor_lang::prelude::*;
+
+use crate::{JackpotPool, JackpotsConfig};
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum ReseedPolicy {
+    #[default]
+    ReserveFunded,
+    FeeRebuilt,
+}
+
+/// One tier in the return data of `get_jackpot_rebuild_status`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct TierRebuildStatus {
+    pub policy: ReseedPolicy,
+    pub rebuilding: bool,
+    pub amount: u64,
+    pub seed: u64,
+    /// Progress back to the seed; 10_000 when not rebuilding.
+    pub progress_bps: u16,
+}
+
+/// Return data of `get_jackpot_rebuild_status`, mini / major / grand.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct JackpotRebuildReport {
+    pub tiers: [TierRebuildStatus; 3],
+}
+
+/// Whether the tier is still climbing back to its seed.
+pub fn is_rebuilding(pool: &JackpotPool, policy: ReseedPolicy) -> bool {
+    policy == ReseedPolicy::FeeRebuilt && pool.amount < pool.seed
+}
+
+/// Amount the tier restarts at after a hit.
+pub fn amount_after_hit(pool: &JackpotPool, policy: ReseedPolicy) -> u64 {
+    match policy {
+        ReseedPolicy::ReserveFunded => pool.seed,
+        ReseedPolicy::FeeRebuilt => 0,
+    }
+}
+
+/// Rebuild slice of `bet_amount` for a rebuilding tier, never overshooting
+/// the seed.
+pub fn rebuild_contribution(pool: &JackpotPool, bet_amount: u64, rebuild_bps: u16) -> u64 {
+    let slice = ((bet_amount as u128) * (rebuild_bps as u128) / 10_000) as u64;
+    slice.min(pool.seed.saturating_sub(pool.amount))
+}
+
+pub fn tier_status(pool: &JackpotPool, policy: ReseedPolicy) -> TierRebuildStatus {
+    let rebuilding = is_rebuilding(pool, policy);
+    let progress_bps = if rebuilding {
+        ((pool.amount as u128) * 10_000 / pool.seed as u128) as u16
+    } else {
+        10_000
+    };
+    TierRebuildStatus {
+        policy,
+        rebuilding,
+        amount: pool.amount,
+        seed: pool.seed,
+        progress_bps,
+    }
+}
+
+pub fn rebuild_report(
+    jackpots: &JackpotsConfig,
+    policies: [ReseedPolicy; 3],
+) -> JackpotRebuildReport {
+    JackpotRebuildReport {
+        tiers: [
+            tier_status(&jackpots.mini, policies[0]),
+            tier_status(&jackpots.major, policies[1]),
+            tier_status(&jackpots.grand, policies[2]),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Game;
+    use crate::{accounting, bootstrap, game_math, SlotsState, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+    const REBUILD_BPS: u16 = 2_000;
+    /// What one BET rebuilds.
+    const SLICE: u64 = BET * REBUILD_BPS as u64 / 10_000;
+
+    /// A machine whose major, the only tier that can hit, rebuilds from
+    /// bets and has a seed of 5.5 slices.
+    fn rebuilding_major() -> Game {
+        let mut game = Game::new();
+        game.state.jackpot_reseed_policies[1] = ReseedPolicy::FeeRebuilt;
+        game.state.jackpot_rebuild_bps[1] = REBUILD_BPS;
+        let jackpots = &mut game.state.jackpots;
+        jackpots.mini.hit_weight = 0;
+        jackpots.grand.hit_weight = 0;
+        jackpots.major.hit_weight = jackpots.hit_weight_total;
+        jackpots.major.seed = 5 * SLICE + SLICE / 2;
+        jackpots.major.amount = jackpots.major.seed;
+        game
+    }
+
+    #[test]
+    fn a_hit_tier_rebuilds_from_bets_then_contributes_normally() {
+        let mut game = rebuilding_major();
+        let seed = game.state.jackpots.major.seed;
+        game.request(BET).unwrap();
+        let hit = game.reveal_where(|settlement| {
+            settlement.outcome.jackpot_tier == Some(game_math::JACKPOT_TIER_MAJOR)
+        });
+        game.reveal(hit);
+        let before = game.clone();
+        let settlement = game.settle().unwrap();
+
+        // The hit pays the tier out of the pool and restarts it at zero;
+        // nothing is drawn from the fees to reseed it.
+        assert_eq!(settlement.outcome.jackpot_payout, before.state.jackpots.major.amount);
+        assert_eq!(game.state.jackpots.major.amount, 0);
+        assert_eq!(
+            before.state.total_pool - game.state.total_pool,
+            settlement.total_payout
+        );
+        assert_eq!(
+            accounting::base_pool(&before.state).unwrap()
+                - accounting::base_pool(&game.state).unwrap(),
+            settlement.outcome.base_payout
+        );
+        assert_eq!(game.state.fee_balance, before.state.fee_balance);
+        assert!(is_rebuilding(&game.state.jackpots.major, ReseedPolicy::FeeRebuilt));
+        game.verify().expect("books hold");
+
+        game.state.jackpots.major.hit_weight = 0;
+        let tiers = |state: &SlotsState| {
+            let jackpots = &state.jackpots;
+            jackpots.mini.amount + jackpots.major.amount + jackpots.grand.amount
+        };
+        let mut rebuilt = Vec::new();
+        while is_rebuilding(&game.state.jackpots.major, ReseedPolicy::FeeRebuilt) {
+            let before = game.clone();
+            game.request(BET).unwrap();
+            let major = game.state.jackpots.major.amount - before.state.jackpots.major.amount;
+            rebuilt.push(major);
+            // The slice moves inside the pool: the bet joins it whole and
+            // the base pool gives up what the tiers took.
+            let tiers = tiers(&game.state) - tiers(&before.state);
+            assert_eq!(game.state.total_pool - before.state.total_pool, BET);
+            assert_eq!(
+                accounting::base_pool(&game.state).unwrap()
+                    - accounting::base_pool(&before.state).unwrap(),
+                BET - tiers
+            );
+            assert_eq!(game.state.fee_balance, before.state.fee_balance);
+            let loss = game.reveal_where(|settlement| settlement.owed() == 0);
+            game.reveal(loss);
+            game.settle().unwrap();
+            game.verify().expect("books hold");
+        }
+        // Five full slices, then only what the seed still lacked.
+        assert_eq!(rebuilt, [SLICE, SLICE, SLICE, SLICE, SLICE, SLICE / 2]);
+        assert_eq!(game.state.jackpots.major.amount, seed);
+
+        let contrib_bps = game.state.jackpots.major.contrib_bps;
+        let bps = bootstrap::effective_contrib_bps(contrib_bps, game.state.bootstrap_active);
+        game.request(BET).unwrap();
+        assert_eq!(
+            game.state.jackpots.major.amount - seed,
+            BET * bps as u64 / 10_000
+        );
+    }
+}
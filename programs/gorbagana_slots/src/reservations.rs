@@ -0,0 +1,153 @@
+//! Commit-order fairness for pool headroom.
+//!
+//! Every commit reserves its payout cap (the `commit_payout_cap` snapshot,
+//! see tiers.rs) in `SlotsState.reserved_liability`, and a commit is only
+//! accepted if the pool covers min_pool_threshold, earmarks, all existing
+//! reservations and the new one. A settle may spend what is left of the
+//! pool after everyone else's reservations, which always includes its own
+//! reservation in full. A later committer that settles first can
+//! therefore never eat the headroom an earlier committer was promised.
+//! Whatever part of a reservation the outcome does not pay stays in the
+//! pool when the reservation is released at settle or void.
+//!
+//! The reservation is the spin's whole exposure to the base pool: the cap
+//! bounds every paytable win, and a jackpot is paid from its own tier,
+//! which the floor already holds back in full. Many players committing at
+//! once therefore cannot together be promised more than the pool holds.
+//!
+//! A PlayerState holds at most one pending spin, so the spin's
+//! reservation lives there rather than under a commit nonce. A
+//! multi-spin commit is one pending spin reserving a cap per spin.
+//!
+//! Deferred settles cannot write SlotsState. They move the reservation
+//! to `PlayerState.deferred_reserved`, and flush_stats releases it. Until
+//! then the stale reservation only makes other settles more
+//! conservative.
+//!
+//! Withdrawals keep reserved funds in the pool through required_floor.
+
+use anchor_lang::prelude::*;
+
+use crate::{accounting, ErrorCode, PlayerState, SlotsState};
+
+pub fn reserve(state: &mut SlotsState, amount: u64) -> Result<()> {
+    state.reserved_liability = state
+        .reserved_liability
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+/// Release the player's reservation. Saturating, since spins committed
+/// before reservations existed hold none.
+pub fn release(state: &mut SlotsState, player_state: &mut PlayerState) {
+    state.reserved_liability = state
+        .reserved_liability
+        .saturating_sub(player_state.commit_payout_cap);
+    player_state.commit_payout_cap = 0;
+}
+
+/// Base pool the player's pending spin may pay out of: everything above
+/// the threshold, earmarks, a pending withdrawal and the other spins'
+/// reservations. The jackpot
+/// reserves are never part of it; see accounting.rs.
+pub fn headroom_for(state: &SlotsState, player_state: &PlayerState) -> Result<u64> {
+    headroom_excluding(state, player_state.commit_payout_cap)
+}
+
+/// headroom_for a spin that holds no reservation yet: what a fresh
+/// commit could be paid out of right now.
+pub fn unreserved_headroom(state: &SlotsState) -> Result<u64> {
+    headroom_excluding(state, 0)
+}
+
+fn headroom_excluding(state: &SlotsState, own_reservation: u64) -> Result<u64> {
+    let others = state.reserved_liability.saturating_sub(own_reservation);
+    Ok(accounting::base_pool(state)?
+        .saturating_sub(state.min_pool_threshold)
+        .saturating_sub(state.earmarks.total()?)
+        .saturating_sub(state.pending_withdrawal_amount)
+        .saturating_sub(others))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obligations::EarmarkKind;
+    use crate::pool_level;
+    use crate::test_util::{zeroed_player, zeroed_state};
+
+    #[test]
+    fn concurrent_commits_cannot_overbook_the_pool() {
+        let mut s = zeroed_state();
+        s.min_pool_threshold = 1_000;
+        s.max_payout_per_spin = 5_000;
+        s.total_pool = 10_000;
+        let mut a = zeroed_player();
+        let cap = s.max_payout_per_spin;
+
+        // Each spin on its own fits: threshold plus one cap.
+        assert!(s.total_pool >= s.min_pool_threshold + cap);
+        assert_eq!(pool_level::required_pool(&s, 0), 6_000);
+        reserve(&mut s, cap).unwrap();
+        a.commit_payout_cap = cap;
+
+        // Together they need 11_000 of a 10_000 pool: the second waits.
+        assert_eq!(pool_level::required_pool(&s, 0), 11_000);
+        assert!(pool_level::required_pool(&s, 0) > s.total_pool);
+        assert_eq!(unreserved_headroom(&s).unwrap(), 4_000);
+        assert_eq!(headroom_for(&s, &a).unwrap(), 9_000);
+
+        release(&mut s, &mut a);
+        assert_eq!(s.reserved_liability, 0);
+        assert_eq!(a.commit_payout_cap, 0);
+        assert_eq!(pool_level::required_pool(&s, 0), 6_000);
+    }
+
+    #[test]
+    fn headroom_leaves_out_every_claim_on_the_pool() {
+        let mut s = zeroed_state();
+        s.total_pool = 100_000;
+        assert_eq!(unreserved_headroom(&s).unwrap(), 100_000);
+
+        s.jackpots.grand.amount = 10_000;
+        s.min_pool_threshold = 5_000;
+        s.earmarks.earmark(EarmarkKind::PendingAward, 3_000).unwrap();
+        s.pending_withdrawal_amount = 2_000;
+        assert_eq!(unreserved_headroom(&s).unwrap(), 80_000);
+
+        // A spin's own reservation stays available to it; others' do not.
+        let mut a = zeroed_player();
+        a.commit_payout_cap = 30_000;
+        reserve(&mut s, 30_000).unwrap();
+        reserve(&mut s, 60_000).unwrap();
+        assert_eq!(headroom_for(&s, &a).unwrap(), 20_000);
+        assert_eq!(unreserved_headroom(&s).unwrap(), 0);
+    }
+
+    #[test]
+    fn headroom_fails_when_the_jackpots_exceed_the_pool() {
+        let mut s = zeroed_state();
+        s.total_pool = 1_000;
+        s.jackpots.mini.amount = 1_001;
+        assert!(unreserved_headroom(&s).is_err());
+    }
+
+    #[test]
+    fn release_saturates_for_spins_without_a_reservation() {
+        let mut s = zeroed_state();
+        let mut legacy = zeroed_player();
+        legacy.commit_payout_cap = 500;
+        release(&mut s, &mut legacy);
+        assert_eq!(s.reserved_liability, 0);
+        assert_eq!(legacy.commit_payout_cap, 0);
+    }
+
+    #[test]
+    fn reserve_refuses_to_overflow() {
+        let mut s = zeroed_state();
+        reserve(&mut s, u64::MAX).unwrap();
+        assert!(reserve(&mut s, 1).is_err());
+        assert_eq!(s.reserved_liability, u64::MAX);
+    }
+}
@@ -0,0 +1,103 @@
+//! Player-set self-exclusion and session loss limits.
+//!
+//! set_self_exclusion locks the player out of new commits until a slot.
+//! The lock can be extended but never shortened or lifted early, not
+//! even by the player.
+//!
+//! set_loss_limit caps net losses (stakes minus winnings, never below
+//! zero) per window of `SlotsState.loss_window_slots`. A window opens at
+//! the first settle after the previous one ran out. A commit is refused
+//! once the window's losses have reached the limit. Lowering or setting
+//! a limit applies at once; raising or removing it is refused while the
+//! current window has losses, so it cannot be undone mid-session.
+//!
+//! Only commits are gated. Settles and cancels of a spin already pending
+//! go through, so no stake is ever trapped.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, PlayerState, SlotsState};
+
+/// One day of slots.
+pub const DEFAULT_LOSS_WINDOW_SLOTS: u64 = 216_000;
+
+/// Losses counted so far in the window open at `now`.
+pub fn window_losses(state: &SlotsState, player_state: &PlayerState, now: u64) -> u64 {
+    losses_in_window(
+        player_state.session_start_slot,
+        player_state.session_losses,
+        state.loss_window_slots,
+        now,
+    )
+}
+
+/// `losses` of a window opened at `start`, or 0 once it has run out.
+pub fn losses_in_window(start: u64, losses: u64, window: u64, now: u64) -> u64 {
+    if time::is_expired(start, window, now) {
+        0
+    } else {
+        losses
+    }
+}
+
+pub fn ensure_may_commit(state: &SlotsState, player_state: &PlayerState, now: u64) -> Result<()> {
+    require!(
+        time::has_reached(player_state.self_excluded_until, now),
+        ErrorCode::SelfExcluded
+    );
+    let limit = player_state.loss_limit_lamports;
+    require!(
+        limit == 0 || window_losses(state, player_state, now) < limit,
+        ErrorCode::LossLimitReached
+    );
+    Ok(())
+}
+
+/// Count a settled spin. A house-funded free spin stakes nothing of the
+/// player's, so only its winnings count.
+pub fn record_settle(
+    state: &SlotsState,
+    player_state: &mut PlayerState,
+    stake: u64,
+    won: u64,
+    now: u64,
+) {
+    let stake = if player_state.pending_free_spin { 0 } else { stake };
+    if time::is_expired(player_state.session_start_slot, state.loss_window_slots, now) {
+        player_state.session_start_slot = now;
+        player_state.session_losses = 0;
+    }
+    player_state.session_losses = player_state
+        .session_losses
+        .saturating_add(stake)
+        .saturating_sub(won);
+}
+
+/// Whether `new_limit` may replace `current` (0 = no limit) with
+/// `losses` in the open window.
+pub fn limit_change_allowed(current: u64, new_limit: u64, losses: u64) -> bool {
+    let tightens = new_limit != 0 && (current == 0 || new_limit <= current);
+    tightens || losses == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn losses_reset_when_the_window_runs_out() {
+        assert_eq!(losses_in_window(1_000, 500, 100, 1_099), 500);
+        assert_eq!(losses_in_window(1_000, 500, 100, 1_100), 0);
+    }
+
+    #[test]
+    fn limits_only_loosen_between_sessions() {
+        // Setting or lowering always applies.
+        assert!(limit_change_allowed(0, 1_000, 900));
+        assert!(limit_change_allowed(1_000, 800, 900));
+        // Raising or removing waits for a window without losses.
+        assert!(!limit_change_allowed(1_000, 2_000, 900));
+        assert!(!limit_change_allowed(1_000, 0, 1));
+        assert!(limit_change_allowed(1_000, 0, 0));
+    }
+}
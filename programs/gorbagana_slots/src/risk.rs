@@ -0,0 +1,332 @@
+//! Traffic tracking and jackpot time-to-hit estimation for the risk team.
+//!
+//! Traffic is kept as two exponentially-decayed sums updated once per
+//! settled spin: a spin count (fixed point, 1e6 = one spin) and a wager
+//! total in lamports. Both decay with the same half-life, so their ratio is
+//! the recency-weighted average bet, and at a steady arrival rate λ the
+//! spin sum converges to λ·H/ln2, which gives the spin rate back.
+
+use anchor_lang::prelude::*;
+
+use crate::game_math::jackpot_pool;
+use crate::time;
+use crate::JackpotsConfig;
+
+pub const DEFAULT_TRAFFIC_HALF_LIFE_SECS: u32 = 6 * 60 * 60;
+
+/// One spin in the decayed spin-count sum.
+pub const SPIN_WEIGHT_ONE: u64 = 1_000_000;
+
+const PPB: u64 = 1_000_000_000;
+const PPM: u64 = 1_000_000;
+/// ln(2) in parts per million / billion.
+const LN2_PPM: u128 = 693_147;
+const LN2_PPB: u128 = 693_147_181;
+
+/// 2^(-i/16) in parts per billion, i = 0..16.
+const DECAY_TABLE_PPB: [u64; 16] = [
+    1000000000, 957603281, 917004043, 878126080, 840896415, 805245166, 771105413, 738413073,
+    707106781, 677127773, 648419777, 620928906, 594603558, 569394317, 545253866, 522136891,
+];
+
+/// Expected jackpot hit timing for one tier.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct JackpotEta {
+    /// 0=mini, 1=major, 2=grand.
+    pub tier: u8,
+    /// Probability a settled spin hits this tier, parts per million.
+    pub hit_probability_ppm: u32,
+    /// 1 / probability; u64::MAX when the tier cannot hit.
+    pub expected_spins_to_hit: u64,
+    /// Expected seconds until a hit at current traffic; u64::MAX with no traffic.
+    pub eta_seconds: u64,
+}
+
+/// Return data of `estimate_jackpot_eta`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct JackpotEtaReport {
+    /// Recency-weighted spin rate, spins per hour × 1000.
+    pub spins_per_hour_x1000: u64,
+    /// Recency-weighted average bet, lamports.
+    pub avg_bet_lamports: u64,
+    pub tiers: [JackpotEta; 3],
+}
+
+/// Decay multiplier 2^(-elapsed/half_life) in parts per billion.
+/// Whole half-lives are exact shifts, sixteenths come from a table, and the
+/// rest uses a second-order expansion (relative error below 2e-5).
+pub fn decay_ppb(elapsed_secs: u64, half_life_secs: u64) -> u64 {
+    if half_life_secs == 0 {
+        return 0;
+    }
+    let halvings = elapsed_secs / half_life_secs;
+    if halvings >= 64 {
+        return 0;
+    }
+    let h = half_life_secs as u128;
+    let frac = (elapsed_secs % half_life_secs) as u128 * 16;
+    let idx = (frac / h) as usize;
+    let rem = frac % h;
+
+    // Remaining exponent y = (rem / 16h)·ln2, in ppb; 2^-x ≈ 1 - y + y²/2.
+    let y = rem * LN2_PPB / (16 * h);
+    let tail = PPB as u128 - y + y * y / (2 * PPB as u128);
+
+    let value = (DECAY_TABLE_PPB[idx] as u128) * tail / PPB as u128;
+    (value as u64) >> halvings
+}
+
+fn apply_decay(value: u64, decay: u64) -> u64 {
+    ((value as u128) * (decay as u128) / (PPB as u128)) as u64
+}
+
+/// Traffic sums decayed to `now` without recording a spin.
+/// Timestamps that move backwards count as zero elapsed time.
+pub fn decayed_traffic(
+    spin_weight: u64,
+    wager_weight: u64,
+    last_update_ts: i64,
+    now_ts: i64,
+    half_life_secs: u32,
+) -> (u64, u64) {
+    let elapsed = time::secs_elapsed_saturating(last_update_ts, now_ts);
+    let decay = decay_ppb(elapsed, half_life_secs as u64);
+    (apply_decay(spin_weight, decay), apply_decay(wager_weight, decay))
+}
+
+/// Fold one settled spin into the traffic sums. Cheap enough for settle.
+pub fn record_spin(
+    spin_weight: &mut u64,
+    wager_weight: &mut u64,
+    last_update_ts: &mut i64,
+    now_ts: i64,
+    half_life_secs: u32,
+    bet_amount: u64,
+) {
+    record_spins(spin_weight, wager_weight, last_update_ts, now_ts, half_life_secs, 1, bet_amount);
+}
+
+/// Fold `count` spins totalling `wagered` at once, all stamped `now_ts`
+/// (used when flushing deferred stats).
+pub fn record_spins(
+    spin_weight: &mut u64,
+    wager_weight: &mut u64,
+    last_update_ts: &mut i64,
+    now_ts: i64,
+    half_life_secs: u32,
+    count: u64,
+    wagered: u64,
+) {
+    let (spins, wagers) =
+        decayed_traffic(*spin_weight, *wager_weight, *last_update_ts, now_ts, half_life_secs);
+    *spin_weight = spins.saturating_add(SPIN_WEIGHT_ONE.saturating_mul(count));
+    *wager_weight = wagers.saturating_add(wagered);
+    *last_update_ts = core::cmp::max(*last_update_ts, now_ts);
+}
+
+/// Spin rate (spins/hour × 1000) implied by a decayed spin sum.
+pub fn spins_per_hour_x1000(spin_weight: u64, half_life_secs: u32) -> u64 {
+    if half_life_secs == 0 {
+        return 0;
+    }
+    // λ/sec = W·ln2/H, with W in 1e6 units and ln2 in ppm.
+    let num = (spin_weight as u128) * LN2_PPM * 3_600 * 1_000;
+    let den = (PPM as u128) * (PPM as u128) * half_life_secs as u128;
+    (num / den) as u64
+}
+
+/// Per-tier hit probability per settled spin, from the current weights.
+/// Assumes the jackpot is affordable when rolled.
+pub fn hit_probability_ppm(jackpots: &JackpotsConfig, tier: u8) -> u32 {
+    if jackpots.hit_weight_total == 0 {
+        return 0;
+    }
+    let weight = jackpot_pool(jackpots, tier).hit_weight;
+    ((weight as u64) * PPM / jackpots.hit_weight_total as u64) as u32
+}
+
+pub fn estimate_eta(
+    jackpots: &JackpotsConfig,
+    spin_weight: u64,
+    wager_weight: u64,
+    half_life_secs: u32,
+) -> JackpotEtaReport {
+    let rate_x1000 = spins_per_hour_x1000(spin_weight, half_life_secs);
+    let avg_bet = if spin_weight == 0 {
+        0
+    } else {
+        ((wager_weight as u128) * (SPIN_WEIGHT_ONE as u128) / spin_weight as u128) as u64
+    };
+
+    let mut report = JackpotEtaReport {
+        spins_per_hour_x1000: rate_x1000,
+        avg_bet_lamports: avg_bet,
+        ..JackpotEtaReport::default()
+    };
+
+    for (tier, eta) in report.tiers.iter_mut().enumerate() {
+        let p = hit_probability_ppm(jackpots, tier as u8);
+        eta.tier = tier as u8;
+        eta.hit_probability_ppm = p;
+        eta.expected_spins_to_hit = if p == 0 { u64::MAX } else { PPM / p as u64 };
+        eta.eta_seconds = if p == 0 || rate_x1000 == 0 {
+            u64::MAX
+        } else {
+            // spins_to_hit / (spins per second) = 1e6/p · 3600·1000/rate_x1000
+            let num = (PPM as u128) * 3_600 * 1_000;
+            let den = (p as u128) * rate_x1000 as u128;
+            u64::try_from(num / den).unwrap_or(u64::MAX)
+        };
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, Fixture, Game};
+    use crate::{ErrorCode, SlotsState, DEFAULT_MIN_BET};
+
+    const HOUR: i64 = 3_600;
+
+    /// Traffic sums after a spin of `bet` every `gap` seconds for
+    /// `duration` seconds, read at the last spin.
+    fn steady(gap: i64, duration: i64, bets: &[u64], half_life: u32) -> (u64, u64) {
+        let (mut spins, mut wagers, mut last) = (0, 0, 0);
+        for (i, now) in (0..duration).step_by(gap as usize).enumerate() {
+            let bet = bets[i % bets.len()];
+            record_spin(&mut spins, &mut wagers, &mut last, now, half_life, bet);
+        }
+        (spins, wagers)
+    }
+
+    fn within(actual: u64, expected: u64, tolerance_ppm: u64) -> bool {
+        actual.abs_diff(expected) as u128 * PPM as u128 <= expected as u128 * tolerance_ppm as u128
+    }
+
+    #[test]
+    fn decay_follows_the_half_life() {
+        let h = 6 * HOUR as u64;
+        assert_eq!(decay_ppb(0, h), PPB);
+        assert_eq!(decay_ppb(h, h), PPB / 2);
+        assert_eq!(decay_ppb(3 * h, h), PPB / 8);
+        assert_eq!(decay_ppb(64 * h, h), 0);
+        assert_eq!(decay_ppb(1, 0), 0);
+        for elapsed in (0..4 * h).step_by(97) {
+            let exact = 2f64.powf(-(elapsed as f64) / h as f64) * PPB as f64;
+            assert!(within(decay_ppb(elapsed, h), exact as u64, 20), "{elapsed}");
+        }
+    }
+
+    #[test]
+    fn steady_traffic_reads_back_its_rate_and_mix() {
+        let half_life = HOUR as u32;
+        // 100 spins an hour, alternating 1 and 3 SOL, for ten half-lives.
+        let (spins, wagers) = steady(36, 10 * HOUR, &[1_000_000_000, 3_000_000_000], half_life);
+        let report = estimate_eta(&JackpotsConfig::default(), spins, wagers, half_life);
+        assert!(within(report.spins_per_hour_x1000, 100_000, 20_000));
+        assert!(within(report.avg_bet_lamports, 2_000_000_000, 20_000));
+
+        // Twice the rate reads twice as high.
+        let (faster, _) = steady(18, 10 * HOUR, &[1], half_life);
+        assert!(within(spins_per_hour_x1000(faster, half_life), 200_000, 20_000));
+    }
+
+    #[test]
+    fn a_burst_fades_with_the_half_life() {
+        let half_life = HOUR as u32;
+        let (spins, wagers) = steady(1, 600, &[DEFAULT_MIN_BET], half_life);
+        let rate = |at: i64| {
+            let (s, _) = decayed_traffic(spins, wagers, 599, at, half_life);
+            spins_per_hour_x1000(s, half_life)
+        };
+        assert!(within(rate(599 + HOUR), rate(599) / 2, 1_000));
+        assert!(within(rate(599 + 3 * HOUR), rate(599) / 8, 1_000));
+        // The mix is unchanged by decay.
+        let (s, w) = decayed_traffic(spins, wagers, 599, 599 + 2 * HOUR, half_life);
+        let report = estimate_eta(&JackpotsConfig::default(), s, w, half_life);
+        assert!(within(report.avg_bet_lamports, DEFAULT_MIN_BET, 1_000));
+    }
+
+    #[test]
+    fn a_clock_going_backwards_does_not_decay_or_rewind() {
+        let (mut spins, mut wagers, mut last) = (0, 0, 0);
+        record_spin(&mut spins, &mut wagers, &mut last, 1_000, 60, 5);
+        record_spin(&mut spins, &mut wagers, &mut last, 400, 60, 5);
+        assert_eq!((spins, wagers, last), (2 * SPIN_WEIGHT_ONE, 10, 1_000));
+
+        // Sums saturate rather than wrap.
+        let (mut spins, mut wagers) = (u64::MAX, u64::MAX);
+        record_spin(&mut spins, &mut wagers, &mut last, 1_000, 60, 5);
+        assert_eq!((spins, wagers), (u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn eta_divides_spins_to_hit_by_the_rate() {
+        let game = Game::new();
+        let jackpots = game.state.jackpots;
+        let half_life = HOUR as u32;
+        let (spins, wagers) = steady(36, 10 * HOUR, &[DEFAULT_MIN_BET], half_life);
+        let report = estimate_eta(&jackpots, spins, wagers, half_life);
+        for (tier, eta) in report.tiers.iter().enumerate() {
+            let weight = jackpot_pool(&jackpots, tier as u8).hit_weight as u64;
+            let p = weight * PPM / jackpots.hit_weight_total as u64;
+            assert_eq!(eta.hit_probability_ppm as u64, p);
+            assert_eq!(eta.expected_spins_to_hit, PPM / p);
+            // A spin every 36 seconds.
+            assert!(within(eta.eta_seconds, PPM * 36 / p, 30_000), "tier {tier}");
+        }
+
+        // No traffic, or a tier that cannot hit, never arrives.
+        let idle = estimate_eta(&jackpots, 0, 0, half_life);
+        assert!(idle.tiers.iter().all(|eta| eta.eta_seconds == u64::MAX));
+        assert_eq!(idle.avg_bet_lamports, 0);
+        let mut off = jackpots;
+        off.grand.hit_weight = 0;
+        let grand = estimate_eta(&off, spins, wagers, half_life).tiers[2];
+        assert_eq!((grand.expected_spins_to_hit, grand.eta_seconds), (u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn each_settle_counts_towards_traffic() {
+        let mut game = Game::new();
+        for _ in 0..3 {
+            game.request(DEFAULT_MIN_BET).unwrap();
+            game.reveal([9; 32]);
+            game.settle().unwrap();
+        }
+        let (spins, wagers) = decayed_traffic(
+            game.state.traffic_spin_weight,
+            game.state.traffic_wager_weight,
+            game.state.traffic_last_update_ts,
+            game.state.traffic_last_update_ts,
+            game.state.traffic_half_life_secs,
+        );
+        assert!(within(spins, 3 * SPIN_WEIGHT_ONE, 1_000));
+        assert!(within(wagers, 3 * DEFAULT_MIN_BET, 1_000));
+    }
+
+    #[test]
+    fn only_the_authority_sets_a_nonzero_half_life() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let set = |half_life_secs| crate::instruction::SetTrafficHalfLife { half_life_secs }.data();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut authority = game.authority();
+        let mut no_metrics = Fixture::omitted();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &set(60)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &set(0)),
+            Err(program_error(ErrorCode::InvalidAmount))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &set(60)).unwrap();
+        assert_eq!(state.read::<SlotsState>().traffic_half_life_secs, 60);
+    }
+}
@@ -0,0 +1,190 @@
+//! Scatter-triggered free spins.
+//!
+//! A settled spin whose payline (SpinSettled.symbols, row 0 of a
+//! multi-line grid) shows three of `ScatterConfig.symbol` wins
+//! `free_spins` free spins instead of a multiplier: the scatter's own
+//! paytable entries must pay nothing, which set_scatter_config and every
+//! paytable change check. `free_spins == 0` turns the feature off. The
+//! spins are held in `PlayerState.free_spins_remaining` at the triggering
+//! line bet, `PlayerState.free_spin_bet`. Landing the scatter again, free
+//! spin or not, adds to them up to `max_free_spins`; spins won while
+//! others are left join them at the bet already held.
+//!
+//! request_spin plays a held free spin before anything else, as a single
+//! line at `free_spin_bet`; the caller must ask for exactly that. Its
+//! stake is notional: the player transfers nothing, and the commit books
+//! no wager, operator fee or jackpot contribution, so `total_wagered`
+//! only ever counts real stakes. The spin reserves its payout cap and
+//! settles like any other, its winnings counting toward `total_payout`.
+//! `PlayerState.pending_bonus_spin` marks it; SpinSettled reports it as
+//! `free_spin`. A void hands the free spin back.
+//!
+//! Free spins return RTP on top of the base game with no wager behind
+//! them. With `q` the chance of a trigger and `n` the spins it awards,
+//! a paid spin leads to `q n / (1 - q n)` free spins on average,
+//! retriggers included, so the bonus adds that many times the base RTP.
+//! The cap only ever lowers this; paytable.rs adds it to the implied RTP
+//! and refuses a configuration with `q n >= 1`.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    multi_spin, ErrorCode, FreeSpinsAwarded, PayTable, PlayerState, ReelWeights, SYMBOL_COUNT,
+};
+
+/// Scatter symbol and the free spins it awards. All zero = off.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct ScatterConfig {
+    pub symbol: u8,
+    /// Free spins per trigger (0 = feature off).
+    pub free_spins: u16,
+    /// Most free spins a player may hold.
+    pub max_free_spins: u16,
+}
+
+impl ScatterConfig {
+    pub fn enabled(&self) -> bool {
+        self.free_spins > 0
+    }
+}
+
+/// Check a candidate config against `table`.
+pub fn validate(config: &ScatterConfig, table: &PayTable) -> Result<()> {
+    if !config.enabled() {
+        return Ok(());
+    }
+    require!(
+        (config.symbol as usize) < SYMBOL_COUNT && config.max_free_spins >= config.free_spins,
+        ErrorCode::InvalidScatterConfig
+    );
+    require!(pays_nothing(config, table), ErrorCode::ScatterSymbolPays);
+    Ok(())
+}
+
+/// Whether `table` leaves the scatter unpaid, as the feature requires.
+pub fn pays_nothing(config: &ScatterConfig, table: &PayTable) -> bool {
+    !config.enabled() || (2..=3).all(|count| table.multiplier(config.symbol, count) == 0)
+}
+
+/// RTP the free spins add to a base game returning `base_rtp_bps`, in
+/// basis points, rounded down; u64::MAX when triggers would never end.
+pub fn bonus_rtp_bps(config: &ScatterConfig, reels: &ReelWeights, base_rtp_bps: u64) -> u64 {
+    if !config.enabled() || (config.symbol as usize) >= SYMBOL_COUNT {
+        return 0;
+    }
+    let w = reels.weights[config.symbol as usize] as u128;
+    let t = reels.total_weight as u128;
+    // q n = w^3 n / t^3.
+    let hits = w.saturating_mul(w).saturating_mul(w).saturating_mul(config.free_spins as u128);
+    let draws = t.saturating_mul(t).saturating_mul(t);
+    if hits == 0 {
+        return 0;
+    }
+    if hits >= draws {
+        return u64::MAX;
+    }
+    let bonus = (base_rtp_bps as u128).saturating_mul(hits) / (draws - hits);
+    bonus.min(u64::MAX as u128) as u64
+}
+
+/// Take a held free spin for a request_spin of `bet_amount` on `lines`.
+/// Returns whether the spin is free; its stake is then notional.
+pub fn fund(player_state: &mut PlayerState, bet_amount: u64, lines: u8) -> Result<bool> {
+    if player_state.free_spins_remaining == 0 {
+        return Ok(false);
+    }
+    require!(
+        bet_amount == player_state.free_spin_bet && lines == 1,
+        ErrorCode::FreeSpinTermsMismatch
+    );
+    player_state.free_spins_remaining -= 1;
+    player_state.pending_bonus_spin = true;
+    Ok(true)
+}
+
+/// Hand a voided free spin back. Returns whether the spin was free.
+pub fn unwind(player_state: &mut PlayerState) -> bool {
+    if !std::mem::take(&mut player_state.pending_bonus_spin) {
+        return false;
+    }
+    player_state.free_spins_remaining = player_state.free_spins_remaining.saturating_add(1);
+    true
+}
+
+/// Award free spins if the settled payline triggers them. Returns the
+/// number awarded.
+pub fn award(
+    config: &ScatterConfig,
+    player_state: &mut PlayerState,
+    user: &Pubkey,
+    symbols: [u8; 3],
+    context_index: u8,
+) -> u16 {
+    if !config.enabled() || symbols != [config.symbol; 3] {
+        return 0;
+    }
+    let held = player_state.free_spins_remaining;
+    let remaining = held
+        .saturating_add(config.free_spins)
+        .min(config.max_free_spins.max(held));
+    let awarded = remaining - held;
+    if awarded == 0 {
+        return 0;
+    }
+    if held == 0 {
+        player_state.free_spin_bet = multi_spin::unit_bet(player_state);
+    }
+    player_state.free_spins_remaining = remaining;
+    emit!(FreeSpinsAwarded {
+        user: *user,
+        awarded,
+        free_spins_remaining: remaining,
+        free_spin_bet: player_state.free_spin_bet,
+        spin_nonce: player_state.spin_nonce,
+        context_index,
+    });
+    awarded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PAYTABLE, REEL_WEIGHTS};
+
+    fn config(symbol: u8, free_spins: u16) -> ScatterConfig {
+        ScatterConfig {
+            symbol,
+            free_spins,
+            max_free_spins: 50,
+        }
+    }
+
+    #[test]
+    fn scatter_must_not_pay() {
+        let mut table = PAYTABLE;
+        table.multipliers[0][0] = 0;
+        table.two_of_a_kind[0] = 0;
+        assert!(validate(&config(0, 10), &table).is_ok());
+        assert!(validate(&config(0, 10), &PAYTABLE).is_err());
+        assert!(validate(&config(SYMBOL_COUNT as u8, 10), &table).is_err());
+        // Off, anything goes.
+        assert!(validate(&config(0, 0), &PAYTABLE).is_ok());
+    }
+
+    #[test]
+    fn bonus_rtp_counts_retriggers() {
+        // Four even symbols: q = 1 / 64. Ten spins a trigger give
+        // 10 / 54 free spins per paid spin.
+        let reels = ReelWeights::new({
+            let mut w = [0; SYMBOL_COUNT];
+            w[..4].copy_from_slice(&[1; 4]);
+            w
+        });
+        assert_eq!(bonus_rtp_bps(&config(0, 10), &reels, 5_400), 1_000);
+        assert_eq!(bonus_rtp_bps(&config(0, 64), &reels, 5_400), u64::MAX);
+        assert_eq!(bonus_rtp_bps(&config(0, 0), &reels, 5_400), 0);
+        assert_eq!(bonus_rtp_bps(&config(0, 10), &REEL_WEIGHTS, 0), 0);
+    }
+}
@@ -0,0 +1,104 @@
+//! Randomness generations a player may not commit to again.
+//!
+//! A Switchboard randomness account is re-seeded for every generation,
+//! identified by (account, seed_slot). Once a generation is revealed its
+//! value is public, so a player who could commit to it again would only
+//! play spins they already know win. validate_commit therefore refuses a
+//! generation that is already revealed, and one among the player's last
+//! [`RECENT_SEEDS`] commits. The ring is written by record_commit.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, PlayerState};
+
+/// Generations remembered per player.
+pub const RECENT_SEEDS: usize = 4;
+
+/// One randomness generation a spin was committed to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsedSeed {
+    pub randomness_account: Pubkey,
+    pub seed_slot: u64,
+}
+
+/// Whether the generation seeded at `seed_slot` has been revealed.
+pub fn is_revealed(seed_slot: u64, reveal_slot: u64) -> bool {
+    reveal_slot != 0 && reveal_slot >= seed_slot
+}
+
+pub fn contains(recent: &[UsedSeed], seed: &UsedSeed) -> bool {
+    recent.iter().any(|used| used == seed)
+}
+
+/// Overwrite the oldest entry of the ring with `seed`.
+pub fn push(recent: &mut [UsedSeed; RECENT_SEEDS], head: &mut u8, seed: UsedSeed) {
+    let at = *head as usize % RECENT_SEEDS;
+    recent[at] = seed;
+    *head = ((at + 1) % RECENT_SEEDS) as u8;
+}
+
+/// Refuse a commit to a revealed or recently used generation.
+pub fn ensure_unused(player_state: &PlayerState, seed: &UsedSeed, reveal_slot: u64) -> Result<()> {
+    require!(
+        !is_revealed(seed.seed_slot, reveal_slot),
+        ErrorCode::RandomnessAlreadyRevealed
+    );
+    require!(
+        !contains(&player_state.recent_seeds, seed),
+        ErrorCode::RandomnessGenerationReused
+    );
+    Ok(())
+}
+
+pub fn record(player_state: &mut PlayerState, seed: UsedSeed) {
+    push(
+        &mut player_state.recent_seeds,
+        &mut player_state.recent_seed_head,
+        seed,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(account: u8, seed_slot: u64) -> UsedSeed {
+        UsedSeed {
+            randomness_account: Pubkey::new_from_array([account; 32]),
+            seed_slot,
+        }
+    }
+
+    #[test]
+    fn replaying_the_previous_generation_is_blocked() {
+        let mut recent = [UsedSeed::default(); RECENT_SEEDS];
+        let mut head = 0;
+        push(&mut recent, &mut head, seed(1, 100));
+        assert!(contains(&recent, &seed(1, 100)));
+        // The same account re-seeded is a new generation.
+        assert!(!contains(&recent, &seed(1, 101)));
+        assert!(!contains(&recent, &seed(2, 100)));
+    }
+
+    #[test]
+    fn ring_remembers_the_last_recent_seeds() {
+        let mut recent = [UsedSeed::default(); RECENT_SEEDS];
+        let mut head = 0;
+        for slot in 0..RECENT_SEEDS as u64 + 1 {
+            push(&mut recent, &mut head, seed(1, 100 + slot));
+        }
+        assert!(!contains(&recent, &seed(1, 100)));
+        for slot in 1..RECENT_SEEDS as u64 + 1 {
+            assert!(contains(&recent, &seed(1, 100 + slot)));
+        }
+        assert_eq!(head as usize, 1);
+    }
+
+    #[test]
+    fn revealed_generations_are_refused() {
+        assert!(!is_revealed(100, 0));
+        assert!(!is_revealed(100, 99));
+        assert!(is_revealed(100, 100));
+        assert!(is_revealed(100, 102));
+    }
+}
@@ -0,0 +1,442 @@
+//! Random instruction sequences against the program's bookkeeping.
+//!
+//! A [`Machine`] holds one SlotsState, its players and every lamport
+//! balance the instructions move. Each action runs the body of its
+//! handler: the same helpers in the same order, with the CPI transfers
+//! replaced by moves between the modelled balances. An action that fails
+//! is rolled back whole, as its transaction would be. The oracle side
+//! (re-seeding and revealing a player's randomness account) and stray
+//! transfers into the treasury are actions too, so sequences interleave
+//! commits, reveals, rotations, settles, cancels, deposits, fee sweeps
+//! and pool syncs in any order.
+//!
+//! After every action:
+//!
+//! - `accounting::verify` passes against the treasury's spendable
+//!   balance, so the rent-exempt reserve is never counted as funds;
+//! - `obligations::validate_invariants` passes (no subsystem modelled
+//!   here earmarks, so the ledger stays empty);
+//! - `reserved_liability` is the sum of the pending spins' caps and
+//!   `pending_spins` their count;
+//! - no lamport is created or lost;
+//! - `total_wagered` is what the live commits staked.
+//!
+//! `Clock::get` reads the machine's slot (test_util::set_clock_slot).
+//! CI runs [`CI_CASES`] sequences; set `PROPTEST_CASES` for a longer run.
+
+use proptest::prelude::*;
+
+use crate::test_util::{set_clock_slot, zeroed_player, zeroed_state, Randomness};
+use crate::*;
+
+/// Sequences per run when `PROPTEST_CASES` is unset.
+const CI_CASES: u32 = 48;
+const PLAYERS: usize = 3;
+const PLAYER_WALLET: u64 = 50_000_000_000;
+const HOUSE_WALLET: u64 = 1_000_000_000_000;
+const OPENING_DEPOSIT: u64 = 20_000_000_000;
+
+#[derive(Clone)]
+struct Player {
+    key: Pubkey,
+    state: PlayerState,
+    wallet: u64,
+    randomness: Randomness,
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Deposit(u64),
+    Commit { player: usize, bet: u64, lines: u8 },
+    Advance(u64),
+    Reveal { player: usize, value: [u8; 32] },
+    Reseed { player: usize },
+    Settle { player: usize },
+    Cancel { player: usize },
+    WithdrawFees,
+    Airdrop(u64),
+    SyncPool,
+}
+
+#[derive(Clone)]
+struct Machine {
+    state: SlotsState,
+    players: Vec<Player>,
+    position: PoolPosition,
+    slot: u64,
+    /// Spendable treasury lamports, above its rent-exempt reserve.
+    treasury: u64,
+    fee_vault: u64,
+    house_wallet: u64,
+    authority_wallet: u64,
+    /// Lamports sent to the treasury from outside the model.
+    airdropped: u64,
+    /// Stakes of every commit not voided.
+    wagered: u64,
+}
+
+impl Machine {
+    fn new() -> Self {
+        let slot = 1_000;
+        set_clock_slot(slot);
+
+        let authority = Pubkey::new_unique();
+        let mut state = zeroed_state();
+        init_slots_state(
+            &mut state,
+            authority,
+            None,
+            None,
+            None,
+            Pubkey::new_unique(),
+        )
+        .expect("state initializes");
+        state.operator_fee_bps = 100;
+
+        let players = (0..PLAYERS)
+            .map(|_| {
+                let key = Pubkey::new_unique();
+                let mut state = zeroed_player();
+                init_player_state(&mut state, key, 255, None).expect("player initializes");
+                Player {
+                    key,
+                    state,
+                    wallet: PLAYER_WALLET,
+                    randomness: Randomness::default(),
+                }
+            })
+            .collect();
+
+        let mut machine = Machine {
+            state,
+            players,
+            position: PoolPosition {
+                owner: Pubkey::new_unique(),
+                slots_state: Pubkey::new_unique(),
+                shares: 0,
+                deposited: 0,
+                withdrawn: 0,
+                bump: 255,
+                share_epoch: 0,
+            },
+            slot,
+            treasury: 0,
+            fee_vault: 0,
+            house_wallet: HOUSE_WALLET,
+            authority_wallet: 0,
+            airdropped: 0,
+            wagered: 0,
+        };
+        machine.apply(&Action::Deposit(OPENING_DEPOSIT));
+        assert_eq!(machine.treasury, OPENING_DEPOSIT, "opening deposit lands");
+        machine.check();
+        machine
+    }
+
+    fn total_lamports(&self) -> u64 {
+        self.players.iter().map(|p| p.wallet).sum::<u64>()
+            + self.treasury
+            + self.fee_vault
+            + self.house_wallet
+            + self.authority_wallet
+    }
+
+    /// Run `action`; a failed instruction leaves no trace.
+    fn apply(&mut self, action: &Action) {
+        let before = self.clone();
+        set_clock_slot(self.slot);
+        if self.run(action).is_err() {
+            *self = before;
+        }
+    }
+
+    fn run(&mut self, action: &Action) -> Result<()> {
+        match *action {
+            Action::Deposit(amount) => self.deposit(amount),
+            Action::Commit { player, bet, lines } => self.commit(player, bet, lines),
+            Action::Advance(slots) => {
+                self.slot += slots;
+                Ok(())
+            }
+            Action::Reveal { player, value } => {
+                self.players[player].randomness.reveal(self.slot, value);
+                Ok(())
+            }
+            Action::Reseed { player } => {
+                let salt = player as u8 ^ 0xa5;
+                self.players[player].randomness.seed(self.slot, salt);
+                Ok(())
+            }
+            Action::Settle { player } => self.settle(player),
+            Action::Cancel { player } => self.cancel(player),
+            Action::WithdrawFees => self.withdraw_fees(),
+            Action::Airdrop(amount) => {
+                self.treasury += amount;
+                self.airdropped += amount;
+                Ok(())
+            }
+            Action::SyncPool => self.sync_pool(),
+        }
+    }
+
+    /// add_to_pool.
+    fn deposit(&mut self, amount: u64) -> Result<()> {
+        let state = &mut self.state;
+        require_sol_mode(state)?;
+        pause::ensure_not_paused(state, PAUSE_DEPOSITS)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(self.house_wallet >= amount, ErrorCode::InsufficientFunds);
+        self.house_wallet -= amount;
+        self.treasury += amount;
+
+        pool_shares::mint(state, &mut self.position, amount)?;
+        state.total_pool = state
+            .total_pool
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        state.total_deposits = state
+            .total_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        refresh_bootstrap(state);
+        pool_level::refresh(state);
+        Ok(())
+    }
+
+    /// request_spin, after the client seeds a fresh generation.
+    fn commit(&mut self, player: usize, bet: u64, lines: u8) -> Result<()> {
+        let state = &mut self.state;
+        let p = &mut self.players[player];
+        p.randomness.seed(self.slot, player as u8);
+
+        require_sol_mode(state)?;
+        require!((1..=MAX_PAYLINES).contains(&lines), ErrorCode::InvalidLines);
+        let mut commit = p.randomness.with_info(|info| {
+            validate_commit(
+                state,
+                &p.state,
+                &p.key,
+                self.treasury,
+                info,
+                p.randomness.key,
+                bet,
+                None,
+                None,
+            )
+        })?;
+        outcome_budget::ensure_commit(state, lines)?;
+        commit.lines = lines;
+        commit.outcomes = lines;
+        let stake = bet
+            .checked_mul(lines as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let user_stake = if scatter::fund(&mut p.state, bet, lines)?
+            || loyalty::fund(state, &mut p.state, stake)?
+        {
+            0
+        } else {
+            stake
+        };
+        let charge = user_stake
+            .checked_add(oracle_cost::player_charge(state))
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(p.wallet >= charge, ErrorCode::InsufficientFunds);
+        p.wallet -= charge;
+        self.treasury += charge;
+
+        record_commit(
+            state,
+            &mut p.state,
+            &p.key,
+            p.randomness.key,
+            stake,
+            commit,
+            0,
+        )?;
+        // A free spin, scatter or loyalty, books no wager.
+        self.wagered += user_stake;
+        Ok(())
+    }
+
+    /// settle_spin, without the optional accounts.
+    fn settle(&mut self, player: usize) -> Result<()> {
+        let state = &mut self.state;
+        let p = &mut self.players[player];
+
+        require_sol_mode(state)?;
+        let settlement = p
+            .randomness
+            .with_info(|info| settle_pending(state, &mut p.state, &p.key, info, &None, 0, None))?;
+        record_wager_race_if_present(None, &mut p.state, &settlement)?;
+        record_leaderboard(state, None, &mut p.state, &settlement, false)?;
+        accrue_vip_rebate_if_present(state, &mut p.state, None, &p.key, &settlement, false)?;
+        accrue_referral_if_present(state, &p.state, None, &p.key, &settlement)?;
+
+        let owed = settlement.owed();
+        assert!(
+            owed <= self.treasury,
+            "treasury holds {} but owes {owed}",
+            self.treasury
+        );
+        self.treasury -= owed;
+        p.wallet += owed;
+
+        finish_settle(state, &settlement, &p.key, &None, &None, &[])?;
+        // pay_crank_fee: crank_fee stays 0 and the settler is the player.
+        Ok(())
+    }
+
+    /// cancel_spin.
+    fn cancel(&mut self, player: usize) -> Result<()> {
+        let state = &mut self.state;
+        let p = &mut self.players[player];
+
+        require_sol_mode(state)?;
+        require!(p.state.has_pending_spin, ErrorCode::NoPendingSpin);
+        require!(
+            time::is_expired(p.state.commit_slot, state.spin_expiry_slots, self.slot),
+            ErrorCode::SpinNotExpired
+        );
+        p.randomness
+            .with_info(|info| ensure_voidable(state, &p.state, info))?;
+
+        if !p
+            .randomness
+            .with_info(|info| holds_committed_generation(&p.state, info))
+        {
+            forfeit_pending_spin(state, &mut p.state)?;
+            return Ok(());
+        }
+        let wager = booked_wager(&p.state);
+        let refund = void_pending_spin(state, &mut p.state)?;
+        self.wagered -= wager;
+        if refund > 0 {
+            require!(refund <= self.treasury, ErrorCode::InsufficientFunds);
+            self.treasury -= refund;
+            p.wallet += refund;
+            outflows::book(state, OutflowCategory::Refund, refund, p.key, [0; 32])?;
+        }
+        pool_level::refresh(state);
+        Ok(())
+    }
+
+    /// withdraw_fees, sweeping everything to the authority.
+    fn withdraw_fees(&mut self) -> Result<()> {
+        let state = &mut self.state;
+        require_sol_mode(state)?;
+        pause::ensure_not_paused(state, PAUSE_WITHDRAWALS)?;
+
+        let operator_fee::FeeWithdrawal { swept, amount } =
+            operator_fee::withdraw(state, self.fee_vault, 0, self.slot)?;
+        require!(swept <= self.treasury, ErrorCode::InsufficientFunds);
+        self.treasury -= swept;
+        self.fee_vault += swept;
+
+        self.fee_vault -= amount;
+        self.authority_wallet += amount;
+        Ok(())
+    }
+
+    /// sync_pool.
+    fn sync_pool(&mut self) -> Result<()> {
+        let state = &mut self.state;
+        require_sol_mode(state)?;
+        accounting::sync_pool(state, self.treasury)?;
+        accounting::ensure_pool_split(state)?;
+        pool_level::refresh(state);
+        Ok(())
+    }
+
+    fn check(&self) {
+        let state = &self.state;
+        if let Err(err) = accounting::verify(state, self.treasury, None) {
+            panic!(
+                "accounting broken ({err}): pool {}, buckets {:?}, treasury {}",
+                state.total_pool,
+                accounting::other_buckets(state),
+                self.treasury
+            );
+        }
+
+        obligations::validate_invariants(state, &[0; obligations::EARMARK_KINDS])
+            .expect("earmark ledger");
+
+        let pending: Vec<&Player> = self
+            .players
+            .iter()
+            .filter(|p| p.state.has_pending_spin)
+            .collect();
+        assert_eq!(state.pending_spins as usize, pending.len(), "pending_spins");
+        assert_eq!(
+            state.reserved_liability,
+            pending
+                .iter()
+                .map(|p| p.state.commit_payout_cap)
+                .sum::<u64>(),
+            "reserved_liability"
+        );
+        assert_eq!(
+            self.total_lamports(),
+            PLAYER_WALLET * PLAYERS as u64 + HOUSE_WALLET + self.airdropped,
+            "lamports conserved"
+        );
+        assert_eq!(state.total_wagered, self.wagered, "total_wagered");
+    }
+}
+
+fn player() -> impl Strategy<Value = usize> {
+    0..PLAYERS
+}
+
+/// Mostly bets the default limits accept, sometimes one either side.
+fn bet() -> impl Strategy<Value = u64> {
+    let max_bet = 1_000_000_000 / game_math::max_3oak_multiplier();
+    prop_oneof![
+        8 => DEFAULT_MIN_BET..=max_bet,
+        1 => 0..DEFAULT_MIN_BET,
+        1 => max_bet + 1..max_bet * 4,
+    ]
+}
+
+fn lines() -> impl Strategy<Value = u8> {
+    prop_oneof![9 => 1..=MAX_PAYLINES, 1 => Just(0), 1 => Just(MAX_PAYLINES + 1)]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        1 => (1_000_000u64..5_000_000_000).prop_map(Action::Deposit),
+        6 => (player(), bet(), lines())
+            .prop_map(|(player, bet, lines)| Action::Commit { player, bet, lines }),
+        4 => prop_oneof![1u64..4, 4u64..DEFAULT_SPIN_EXPIRY_SLOTS + 100].prop_map(Action::Advance),
+        4 => (player(), any::<[u8; 32]>())
+            .prop_map(|(player, value)| Action::Reveal { player, value }),
+        1 => player().prop_map(|player| Action::Reseed { player }),
+        5 => player().prop_map(|player| Action::Settle { player }),
+        1 => player().prop_map(|player| Action::Cancel { player }),
+        1 => Just(Action::WithdrawFees),
+        1 => (1u64..1_000_000_000).prop_map(Action::Airdrop),
+        1 => Just(Action::SyncPool),
+    ]
+}
+
+fn config() -> ProptestConfig {
+    if std::env::var_os("PROPTEST_CASES").is_some() {
+        ProptestConfig::default()
+    } else {
+        ProptestConfig::with_cases(CI_CASES)
+    }
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn any_sequence_keeps_the_books(actions in prop::collection::vec(action(), 1..80)) {
+        let mut machine = Machine::new();
+        for action in &actions {
+            machine.apply(action);
+            machine.check();
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Which partner settle hook, if any, a settle calls.
+//!
+//! Hooks are best-effort: the accounts a settle is given for a hook never
+//! fail it. The hook program is `remaining_accounts[0]` and its accounts
+//! follow. A program that is not registered or not executable, or more
+//! than MAX_SETTLE_HOOK_ACCOUNTS accounts after it, is skipped with a
+//! SettleHookSkipped event and the settle goes through without the hook.
+//!
+//! A hook that fails once called cannot be caught: the runtime aborts the
+//! whole transaction on a failed CPI. The hook accounts are therefore
+//! always optional. A settle sent without them pays out the same and only
+//! misses the hook, so a failing hook costs the player a retry, never the
+//! spin.
+//!
+//! That holds for the player's own settle. A crank settling for them
+//! must call a registered hook: one sent without usable hook accounts
+//! fails (see require_for_crank), and the player can still settle
+//! without the hook themselves.
+
+use anchor_lang::prelude::*;
+
+use crate::{optional, ErrorCode, SlotsState};
+
+/// Most accounts a hook may be handed, after its program.
+pub const MAX_SETTLE_HOOK_ACCOUNTS: usize = 8;
+
+pub enum HookCall<'a, 'info> {
+    /// No hook accounts were passed.
+    Absent,
+    /// Hook accounts were passed but cannot be used; the settle carries on.
+    Skip { hook_program: Pubkey, code: ErrorCode },
+    Invoke {
+        hook_program: &'a AccountInfo<'info>,
+        hook_accounts: &'a [AccountInfo<'info>],
+    },
+}
+
+/// What the settle does with `remaining_accounts`.
+pub fn plan<'a, 'info>(
+    state: &SlotsState,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> HookCall<'a, 'info> {
+    let Some((hook_program, hook_accounts)) = remaining_accounts.split_first() else {
+        return HookCall::Absent;
+    };
+    let key = hook_program.key();
+    let code = if key == Pubkey::default() || !state.settle_hooks.contains(&key) {
+        ErrorCode::UnknownSettleHook
+    } else if !hook_program.executable {
+        ErrorCode::InvalidSettleHook
+    } else if hook_accounts.len() > MAX_SETTLE_HOOK_ACCOUNTS {
+        ErrorCode::TooManySettleHookAccounts
+    } else {
+        return HookCall::Invoke {
+            hook_program,
+            hook_accounts,
+        };
+    };
+    HookCall::Skip {
+        hook_program: key,
+        code,
+    }
+}
+
+/// A settle the player did not submit must invoke a hook whenever one
+/// is registered, so a crank cannot leave it out.
+pub fn require_for_crank(state: &SlotsState, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if state.settle_hooks.iter().all(|h| *h == Pubkey::default()) {
+        return Ok(());
+    }
+    match plan(state, remaining_accounts) {
+        HookCall::Invoke { .. } => Ok(()),
+        HookCall::Absent => optional::required(None::<()>, "settle hook"),
+        HookCall::Skip { code, .. } => Err(code.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use anchor_lang::solana_program::entrypoint::ProgramResult;
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use anchor_lang::solana_program::program::{invoke, invoke_signed};
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{
+        simulate, stub_program, zeroed_state, Fixture, Game, REENTRANCY_NOT_ALLOWED,
+    };
+    use crate::{OnSpinSettledArgs, PlayerState, DEFAULT_MIN_BET, ON_SPIN_SETTLED_DISCRIMINATOR};
+
+    struct Acc {
+        key: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        owner: Pubkey,
+        executable: bool,
+    }
+
+    impl Acc {
+        fn new(key: Pubkey, executable: bool) -> Self {
+            Acc {
+                key,
+                lamports: 0,
+                data: Vec::new(),
+                owner: Pubkey::default(),
+                executable,
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                false,
+                false,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                self.executable,
+                0,
+            )
+        }
+    }
+
+    fn skipped(call: HookCall) -> Option<ErrorCode> {
+        match call {
+            HookCall::Skip { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn failing_hook_accounts_are_skipped_not_fatal() {
+        let hook = Pubkey::new_unique();
+        let mut state = zeroed_state();
+        state.settle_hooks[0] = hook;
+
+        assert!(matches!(plan(&state, &[]), HookCall::Absent));
+
+        let mut unknown = Acc::new(Pubkey::new_unique(), true);
+        let code = skipped(plan(&state, &[unknown.info()]));
+        assert!(matches!(code, Some(ErrorCode::UnknownSettleHook)));
+
+        let mut not_a_program = Acc::new(hook, false);
+        let code = skipped(plan(&state, &[not_a_program.info()]));
+        assert!(matches!(code, Some(ErrorCode::InvalidSettleHook)));
+
+        let mut program = Acc::new(hook, true);
+        let mut extra: Vec<Acc> = (0..=MAX_SETTLE_HOOK_ACCOUNTS)
+            .map(|_| Acc::new(Pubkey::new_unique(), false))
+            .collect();
+        let mut infos = vec![program.info()];
+        infos.extend(extra.iter_mut().map(Acc::info));
+        let code = skipped(plan(&state, &infos));
+        assert!(matches!(code, Some(ErrorCode::TooManySettleHookAccounts)));
+
+        infos.pop();
+        assert!(matches!(
+            plan(&state, &infos),
+            HookCall::Invoke { hook_accounts, .. } if hook_accounts.len() == MAX_SETTLE_HOOK_ACCOUNTS
+        ));
+    }
+
+    #[test]
+    fn a_crank_must_call_a_registered_hook() {
+        let hook = Pubkey::new_unique();
+        let mut state = zeroed_state();
+        require_for_crank(&state, &[]).unwrap();
+
+        state.settle_hooks[0] = hook;
+        assert_eq!(
+            require_for_crank(&state, &[]).unwrap_err(),
+            ErrorCode::OptionalAccountMissing.into()
+        );
+        let mut unknown = Acc::new(Pubkey::new_unique(), true);
+        assert_eq!(
+            require_for_crank(&state, &[unknown.info()]).unwrap_err(),
+            ErrorCode::UnknownSettleHook.into()
+        );
+        let mut program = Acc::new(hook, true);
+        require_for_crank(&state, &[program.info()]).unwrap();
+    }
+
+    /// Seed of the PDA a hook program signs its own CPIs with.
+    const HOOK_SIGNER_SEED: &[u8] = b"hook";
+
+    thread_local! {
+        /// Instruction data the recording hook received, and whether any
+        /// account reached it as a signer.
+        static CALLS: RefCell<Vec<(Vec<u8>, bool)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record_call(ix: &Instruction, accounts: &[AccountInfo]) -> ProgramResult {
+        let signed = accounts.iter().any(|a| a.is_signer);
+        CALLS.with(|calls| calls.borrow_mut().push((ix.data.clone(), signed)));
+        Ok(())
+    }
+
+    /// Settle the spin again from inside the hook, cranked by its PDA.
+    fn resettle(ix: &Instruction, accounts: &[AccountInfo]) -> ProgramResult {
+        let mut metas: Vec<AccountMeta> = accounts[..6]
+            .iter()
+            .enumerate()
+            .map(|(i, a)| AccountMeta::new(*a.key, i == 3))
+            .collect();
+        metas.extend((0..8).map(|_| AccountMeta::new_readonly(crate::ID, false)));
+        metas.push(AccountMeta::new_readonly(anchor_lang::system_program::ID, false));
+        let resettle = Instruction {
+            program_id: crate::ID,
+            accounts: metas,
+            data: crate::instruction::SettleSpin {}.data(),
+        };
+        let (_, bump) = Pubkey::find_program_address(&[HOOK_SIGNER_SEED], &ix.program_id);
+        invoke_signed(&resettle, accounts, &[&[HOOK_SIGNER_SEED, &[bump]]])
+    }
+
+    /// Commit another spin for the player from inside the hook.
+    fn respin(_ix: &Instruction, accounts: &[AccountInfo]) -> ProgramResult {
+        let respin = Instruction {
+            program_id: crate::ID,
+            accounts: vec![
+                AccountMeta::new(*accounts[0].key, false),
+                AccountMeta::new(*accounts[1].key, false),
+                AccountMeta::new(*accounts[2].key, true),
+                AccountMeta::new(*accounts[4].key, false),
+                AccountMeta::new(*accounts[5].key, false),
+                AccountMeta::new_readonly(crate::ID, false),
+                AccountMeta::new_readonly(crate::ID, false),
+                AccountMeta::new_readonly(anchor_lang::system_program::ID, false),
+            ],
+            data: crate::instruction::RequestSpin {
+                randomness_account: *accounts[5].key,
+                bet_amount: DEFAULT_MIN_BET,
+                lines: 1,
+            }
+            .data(),
+        };
+        invoke(&respin, accounts)
+    }
+
+    /// The player's settle of a revealed spin, calling `hook` with the
+    /// machine, player, user, the hook's signer PDA, treasury, randomness,
+    /// this program and the system program.
+    fn settle_calling(hook: Pubkey) -> Vec<Fixture> {
+        let mut game = Game::new();
+        game.state.settle_hooks[0] = hook;
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        game.reveal([1; 32]);
+
+        let mut accounts = game.settle_spin_fixtures();
+        let (hook_signer, _) = Pubkey::find_program_address(&[HOOK_SIGNER_SEED], &hook);
+        let hook_accounts = vec![
+            Fixture::program(hook),
+            accounts[0].clone(),
+            accounts[1].clone(),
+            accounts[2].clone(),
+            Fixture::wallet(hook_signer, 0),
+            accounts[4].clone(),
+            accounts[5].clone(),
+            Fixture::omitted(),
+            Fixture::program(anchor_lang::system_program::ID),
+        ];
+        accounts.extend(hook_accounts);
+        accounts
+    }
+
+    fn still_pending(accounts: &[Fixture]) -> bool {
+        accounts[1].read::<PlayerState>().has_pending_spin
+    }
+
+    #[test]
+    fn a_hook_is_called_after_the_settle_without_signers() {
+        let hook = Pubkey::new_unique();
+        stub_program(hook, record_call);
+        let mut accounts = settle_calling(hook);
+        let user = accounts[2].key;
+
+        simulate(&mut accounts, &crate::instruction::SettleSpin {}.data()).expect("settles");
+        assert!(!still_pending(&accounts));
+        let calls = CALLS.with(|calls| calls.take());
+        assert_eq!(calls.len(), 1);
+        let (data, signed) = &calls[0];
+        assert_eq!(data[..8], ON_SPIN_SETTLED_DISCRIMINATOR);
+        let args = OnSpinSettledArgs::deserialize(&mut &data[8..]).unwrap();
+        assert_eq!((args.user, args.bet), (user, DEFAULT_MIN_BET));
+        // The user signed the settle, but not for the hook.
+        assert!(!signed);
+    }
+
+    #[test]
+    fn a_hook_cannot_reenter_settle_or_spin() {
+        let data = crate::instruction::SettleSpin {}.data();
+
+        // The hook's PDA may sign as a cranker, but the runtime refuses
+        // A -> B -> A, failing the whole settle.
+        let hook = Pubkey::new_unique();
+        stub_program(hook, resettle);
+        let mut accounts = settle_calling(hook);
+        assert_eq!(simulate(&mut accounts, &data).unwrap_err(), REENTRANCY_NOT_ALLOWED);
+        assert!(still_pending(&accounts));
+
+        // A new spin needs the player's signature, which the hook never
+        // gets.
+        let hook = Pubkey::new_unique();
+        stub_program(hook, respin);
+        let mut accounts = settle_calling(hook);
+        assert_eq!(
+            simulate(&mut accounts, &data).unwrap_err(),
+            ProgramError::MissingRequiredSignature
+        );
+        assert!(still_pending(&accounts));
+    }
+}
@@ -0,0 +1,223 @@
+//! Bets signed off-chain by the player and relayed on-chain.
+//!
+//! The player signs `SIGNED_BET_DOMAIN || borsh(SignedBet)` with their
+//! wallet key. The relayer submits an Ed25519 program instruction carrying
+//! that signature immediately before `request_spin_with_signature`; the
+//! program reads it back through the instructions sysvar and checks that
+//! it verified exactly this player key over exactly this message. Replay is
+//! stopped by a strictly sequential nonce in PlayerState and expiry by
+//! `max_slot`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::Instruction;
+
+use crate::ErrorCode;
+
+pub const SIGNED_BET_DOMAIN: &[u8] = b"trashrush:signed_bet:v1";
+
+/// The structure a player signs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignedBet {
+    pub program_id: Pubkey,
+    /// SlotsState the bet is for.
+    pub game_id: Pubkey,
+    pub player: Pubkey,
+    /// Must equal PlayerState.next_bet_nonce.
+    pub nonce: u64,
+    pub bet_amount: u64,
+    pub randomness_account: Pubkey,
+    /// Last slot at which the bet may land.
+    pub max_slot: u64,
+    /// Paid to the relayer from the player's session balance.
+    pub relayer_fee: u64,
+}
+
+impl SignedBet {
+    /// Exact bytes the player signs.
+    pub fn message(&self) -> Vec<u8> {
+        let mut out = SIGNED_BET_DOMAIN.to_vec();
+        out.extend_from_slice(&self.try_to_vec().expect("bet serializes"));
+        out
+    }
+}
+
+// Ed25519 program instruction data: num_signatures (u8), padding (u8),
+// then one 14-byte offsets record per signature.
+const OFFSETS_START: usize = 2;
+const OFFSETS_LEN: usize = 14;
+/// Instruction index meaning "data lives in this same instruction".
+const THIS_INSTRUCTION: u16 = u16::MAX;
+
+fn read_u16(data: &[u8], at: usize) -> Result<u16> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| error!(ErrorCode::InvalidBetSignature))
+}
+
+fn slice(data: &[u8], offset: u16, len: usize) -> Result<&[u8]> {
+    let start = offset as usize;
+    data.get(start..start + len)
+        .ok_or_else(|| error!(ErrorCode::InvalidBetSignature))
+}
+
+/// Check that `ix` is an Ed25519 program instruction verifying one
+/// signature by `signer` over `message`, with all data inline.
+pub fn verify_ed25519_ix(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::InvalidBetSignature
+    );
+    require!(
+        ix.accounts.is_empty() && ix.data.first() == Some(&1),
+        ErrorCode::InvalidBetSignature
+    );
+
+    let data = &ix.data;
+    let o = OFFSETS_START;
+    let signature_ix = read_u16(data, o + 2)?;
+    let pubkey_offset = read_u16(data, o + 4)?;
+    let pubkey_ix = read_u16(data, o + 6)?;
+    let message_offset = read_u16(data, o + 8)?;
+    let message_size = read_u16(data, o + 10)?;
+    let message_ix = read_u16(data, o + 12)?;
+    require!(
+        data.len() >= o + OFFSETS_LEN
+            && signature_ix == THIS_INSTRUCTION
+            && pubkey_ix == THIS_INSTRUCTION
+            && message_ix == THIS_INSTRUCTION,
+        ErrorCode::InvalidBetSignature
+    );
+
+    require!(
+        slice(data, pubkey_offset, 32)? == signer.as_ref(),
+        ErrorCode::InvalidBetSignature
+    );
+    require!(
+        message_size as usize == message.len()
+            && slice(data, message_offset, message.len())? == message,
+        ErrorCode::InvalidBetSignature
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::AccountMeta;
+
+    fn bet(player: Pubkey) -> SignedBet {
+        SignedBet {
+            program_id: crate::ID,
+            game_id: Pubkey::new_unique(),
+            player,
+            nonce: 7,
+            bet_amount: 1_000_000,
+            randomness_account: Pubkey::new_unique(),
+            max_slot: 500,
+            relayer_fee: 5_000,
+        }
+    }
+
+    /// The Ed25519 program instruction a relayer builds for one
+    /// signature, everything inline: offsets, key, signature, message.
+    fn ed25519_ix(signer: &Pubkey, message: &[u8]) -> Instruction {
+        let pubkey_offset = (OFFSETS_START + OFFSETS_LEN) as u16;
+        let signature_offset = pubkey_offset + 32;
+        let message_offset = signature_offset + 64;
+        let mut data = vec![1, 0];
+        for field in [
+            signature_offset,
+            THIS_INSTRUCTION,
+            pubkey_offset,
+            THIS_INSTRUCTION,
+            message_offset,
+            message.len() as u16,
+            THIS_INSTRUCTION,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0xab; 64]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: vec![],
+            data,
+        }
+    }
+
+    fn rejected(result: Result<()>) -> bool {
+        result == Err(ErrorCode::InvalidBetSignature.into())
+    }
+
+    #[test]
+    fn message_is_the_domain_then_the_borsh_bet() {
+        let bet = bet(Pubkey::new_unique());
+        let message = bet.message();
+        assert!(message.starts_with(SIGNED_BET_DOMAIN));
+        let body = &message[SIGNED_BET_DOMAIN.len()..];
+        assert_eq!(SignedBet::try_from_slice(body).unwrap(), bet);
+    }
+
+    #[test]
+    fn the_players_signature_over_the_bet_verifies() {
+        let player = Pubkey::new_unique();
+        let message = bet(player).message();
+        let ix = ed25519_ix(&player, &message);
+        assert!(verify_ed25519_ix(&ix, &player, &message).is_ok());
+    }
+
+    #[test]
+    fn another_signer_or_message_is_refused() {
+        let player = Pubkey::new_unique();
+        let message = bet(player).message();
+        let ix = ed25519_ix(&player, &message);
+
+        assert!(rejected(verify_ed25519_ix(&ix, &Pubkey::new_unique(), &message)));
+        let mut other = bet(player);
+        other.bet_amount += 1;
+        assert!(rejected(verify_ed25519_ix(&ix, &player, &other.message())));
+        // A prefix of the signed message is not the message.
+        let short = &message[..message.len() - 1];
+        assert!(rejected(verify_ed25519_ix(&ix, &player, short)));
+    }
+
+    #[test]
+    fn only_one_inline_ed25519_signature_is_accepted() {
+        let player = Pubkey::new_unique();
+        let message = bet(player).message();
+
+        let mut ix = ed25519_ix(&player, &message);
+        ix.program_id = Pubkey::new_unique();
+        assert!(rejected(verify_ed25519_ix(&ix, &player, &message)));
+
+        let mut ix = ed25519_ix(&player, &message);
+        ix.data[0] = 2;
+        assert!(rejected(verify_ed25519_ix(&ix, &player, &message)));
+
+        let mut ix = ed25519_ix(&player, &message);
+        ix.accounts.push(AccountMeta::new_readonly(player, false));
+        assert!(rejected(verify_ed25519_ix(&ix, &player, &message)));
+
+        // The key, signature or message read from another instruction.
+        for at in [4, 8, 14] {
+            let mut ix = ed25519_ix(&player, &message);
+            ix.data[at..at + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert!(rejected(verify_ed25519_ix(&ix, &player, &message)), "{at}");
+        }
+    }
+
+    #[test]
+    fn truncated_instruction_data_is_refused() {
+        let player = Pubkey::new_unique();
+        let message = bet(player).message();
+        let ix = ed25519_ix(&player, &message);
+        for len in [0, 1, OFFSETS_START + OFFSETS_LEN - 1, ix.data.len() - 1] {
+            let mut short = ix.clone();
+            short.data.truncate(len);
+            assert!(rejected(verify_ed25519_ix(&short, &player, &message)), "{len}");
+        }
+    }
+}
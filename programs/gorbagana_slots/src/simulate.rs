@@ -0,0 +1,219 @@
+//! Payout preview for frontends.
+//!
+//! simulate_payout answers "if the reels land on these symbols with this
+//! bet, what would settle pay right now", so clients fetch it through a
+//! simulated transaction instead of porting the math. It runs the same
+//! calculate_payout and game_math::compute_capped_payout settle runs,
+//! against the live table, pool and jackpots, as a public-tier player
+//! committing now. Nothing is written.
+
+use anchor_lang::prelude::*;
+
+use crate::game_math::{
+    calculate_payout, compute_capped_payout, jackpot_award, jackpot_pool, PayoutLimits,
+};
+use crate::{reservations, tiers, SlotsState};
+
+/// Return data of `simulate_payout`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PayoutSimulation {
+    /// Paytable win before any cap.
+    pub base_payout_full: u64,
+    /// 3, 2, or 0 for no win.
+    pub match_count: u8,
+    /// Base win after pool headroom and the payout cap.
+    pub base_payout: u64,
+    /// Base pool a fresh commit could be paid out of.
+    pub headroom: u64,
+    pub payout_cap: u64,
+    /// What each jackpot tier (mini, major, grand) would pay on top if
+    /// it hit on this spin; 0 when unaffordable.
+    pub jackpot_if_hit: [u64; 3],
+}
+
+pub fn simulate(state: &SlotsState, symbols: [u8; 3], bet_amount: u64) -> Result<PayoutSimulation> {
+    let headroom = reservations::unreserved_headroom(state)?;
+    let payout_cap = tiers::payout_cap(state, 0);
+    let limits = PayoutLimits::for_settle(headroom, payout_cap);
+    let (base_payout_full, match_count) =
+        calculate_payout(symbols, bet_amount, &state.paytable, state.wild_symbol);
+    let capped = compute_capped_payout(base_payout_full, limits);
+    let jackpots = tiers::jackpots_for(state, 0);
+
+    Ok(PayoutSimulation {
+        base_payout_full,
+        match_count,
+        base_payout: capped.base_payout,
+        headroom,
+        payout_cap,
+        jackpot_if_hit: [0, 1, 2]
+            .map(|tier| jackpot_award(jackpot_pool(&jackpots, tier), capped.remaining_for_jackpot)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scatter::ScatterConfig;
+    use crate::test_util::{Game, GAME_POOL};
+    use crate::{loyalty, paytable, DEFAULT_MIN_BET};
+
+    const BET: u64 = DEFAULT_MIN_BET;
+    const SESSIONS: u64 = 40;
+    /// Paid spins per session; the free spins they lead to come on top.
+    const PAID_SPINS: u64 = 1_000;
+    /// The commonest symbol scatters, so sessions see many triggers.
+    const SCATTER: u8 = 11;
+    const FREE_SPINS: u16 = 20;
+    /// One point per lamport; a loyalty spin costs twenty paid ones.
+    const POINTS_PER_LAMPORT_BPS: u16 = 10_000;
+    const POINT_COST: u64 = 20 * BET;
+
+    /// What a session's spins drew, by how each was funded.
+    #[derive(Default)]
+    struct Tally {
+        paid_spins: u64,
+        scatter_spins: u64,
+        loyalty_spins: u64,
+        /// Paytable wins before caps, so pool size plays no part.
+        paid_won: u64,
+        scatter_won: u64,
+        loyalty_won: u64,
+    }
+
+    impl Tally {
+        fn spins(&self) -> u64 {
+            self.paid_spins + self.scatter_spins + self.loyalty_spins
+        }
+
+        fn won(&self) -> u64 {
+            self.paid_won + self.scatter_won + self.loyalty_won
+        }
+    }
+
+    fn configured() -> Game {
+        let mut game = Game::new();
+        // Deep enough that no session's run of luck nears the cap.
+        game.fund(1_000 * GAME_POOL);
+        let state = &mut game.state;
+        state.paytable.multipliers[SCATTER as usize] = [0; 3];
+        state.paytable.two_of_a_kind[SCATTER as usize] = 0;
+        state.scatter = ScatterConfig {
+            symbol: SCATTER,
+            free_spins: FREE_SPINS,
+            max_free_spins: 10 * FREE_SPINS,
+        };
+        state.points_per_lamport_bps = POINTS_PER_LAMPORT_BPS;
+        state.free_spin_point_cost = POINT_COST;
+        state.free_spin_bet = BET;
+        game
+    }
+
+    /// One player's session: PAID_SPINS paid spins, every free spin they
+    /// lead to, and a loyalty spin whenever the points cover one.
+    fn play_session(seed: u64, tally: &mut Tally) {
+        let mut game = configured();
+        let mut rng = seed;
+        let mut paid = 0;
+        while paid < PAID_SPINS || game.player.free_spins_remaining > 0 {
+            let redeemable = game.player.free_spins_remaining == 0
+                && game.player.free_spin_credit == 0
+                && game.player.loyalty_points >= POINT_COST;
+            if redeemable {
+                loyalty::redeem(&mut game.state, &mut game.player, &game.user).expect("redeems");
+            }
+            game.request(BET).expect("commits");
+            let scatter_spin = game.player.pending_bonus_spin;
+            let loyalty_spin = game.player.pending_free_spin;
+
+            let mut value = [0; 32];
+            for chunk in value.chunks_mut(8) {
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+                chunk.copy_from_slice(&rng.to_le_bytes());
+            }
+            game.reveal(value);
+            let won = game.settle().expect("settles").outcome.base_payout_full;
+            game.advance(1);
+
+            if scatter_spin {
+                tally.scatter_spins += 1;
+                tally.scatter_won += won;
+            } else if loyalty_spin {
+                tally.loyalty_spins += 1;
+                tally.loyalty_won += won;
+            } else {
+                paid += 1;
+                tally.paid_spins += 1;
+                tally.paid_won += won;
+            }
+        }
+        // Free spins of either kind book no wager.
+        assert_eq!(game.state.total_wagered, paid * BET);
+        game.verify().expect("books hold");
+    }
+
+    fn assert_near(measured: f64, expected: f64, tolerance: f64, what: &str) {
+        assert!(
+            (measured - expected).abs() <= expected * tolerance,
+            "{what}: measured {measured:.4}, expected {expected:.4}"
+        );
+    }
+
+    #[test]
+    fn sessions_with_free_spins_return_the_implied_rtp() {
+        let mut tally = Tally::default();
+        for session in 0..SESSIONS {
+            play_session(0x9e37_79b9_7f4a_7c15 ^ (session + 1), &mut tally);
+        }
+        let game = configured();
+        let state = &game.state;
+        let bet = BET as f64;
+
+        // Every spin, free or paid, draws from the same table.
+        let base = paytable::implied_rtp_bps(&state.reel_weights, &state.paytable, state.wild_symbol)
+            as f64
+            / 10_000.0;
+        let per_spin = tally.won() as f64 / (tally.spins() as f64 * bet);
+        assert_near(per_spin, base, 0.10, "base RTP per spin");
+
+        // Scatter spins per spin that could trigger them: q n / (1 - q n).
+        let scatter = scatter_share(state);
+        let triggers = (tally.paid_spins + tally.loyalty_spins) as f64;
+        assert_near(tally.scatter_spins as f64 / triggers, scatter, 0.15, "scatter spins");
+
+        // Loyalty spins follow from the points paid spins earn.
+        let points = tally.paid_spins * BET * POINTS_PER_LAMPORT_BPS as u64 / 10_000;
+        let redeemable = points / POINT_COST;
+        assert!(
+            tally.loyalty_spins <= redeemable && tally.loyalty_spins + SESSIONS >= redeemable,
+            "{} loyalty spins for {redeemable} redeemable",
+            tally.loyalty_spins
+        );
+
+        // The player's return on their own money carries both features.
+        let with_scatter = paytable::implied_rtp_with_scatter_bps(
+            &state.reel_weights,
+            &state.paytable,
+            state.wild_symbol,
+            &state.scatter,
+        ) as f64
+            / 10_000.0;
+        let loyalty_share = POINTS_PER_LAMPORT_BPS as f64 / 10_000.0 * bet / POINT_COST as f64;
+        let overall = tally.won() as f64 / (tally.paid_spins as f64 * bet);
+        assert_near(
+            overall,
+            with_scatter * (1.0 + loyalty_share),
+            0.10,
+            "RTP on paid stakes",
+        );
+    }
+
+    /// Free spins a triggering spin leads to, retriggers included.
+    fn scatter_share(state: &crate::SlotsState) -> f64 {
+        let w = state.reel_weights.weights[SCATTER as usize] as f64;
+        let q = (w / state.reel_weights.total_weight as f64).powi(3);
+        let qn = q * FREE_SPINS as f64;
+        qn / (1.0 - qn)
+    }
+}
@@ -0,0 +1,993 @@
+//! Account state and the types stored in it.
+
+use anchor_lang::prelude::*;
+
+use crate::account_index::ACCOUNT_INDEX_SLOTS;
+use crate::community::MAX_COMMUNITY_DESTINATIONS;
+use crate::epoch_reports::{EpochReport, EpochTotals, EPOCH_REPORTS_PER_PAGE};
+use crate::feature_stats::{FeatureTotals, FEATURE_KINDS};
+use crate::jackpot_bounds::JackpotWeights;
+use crate::leaderboard::{LeaderboardEntry, LEADERBOARD_SIZE};
+use crate::obligations::EarmarkedObligations;
+use crate::operators::MAX_STAFF_WALLETS;
+use crate::oracle_cost::OracleCostMode;
+use crate::oracle_queue::{OracleQueueEntry, MAX_ORACLE_QUEUES};
+use crate::outflows::OUTFLOW_CATEGORIES;
+use crate::qa::ForcedOutcome;
+use crate::receipts::{RECEIPT_ROOT_HISTORY, RECEIPT_TREE_DEPTH};
+use crate::reseed::ReseedPolicy;
+use crate::scatter::ScatterConfig;
+use crate::seed_replay::{UsedSeed, RECENT_SEEDS};
+use crate::symbol_meta::SymbolEntry;
+use crate::tiers::{TierLimits, MAX_PLAYER_TIERS};
+use crate::vip::VipConfig;
+use crate::wager_race::{WagerRaceEntry, WAGER_RACE_BOARD_SIZE};
+use crate::{time, ALLOWLIST_PAGE_CAPACITY, MAX_SETTLE_HOOKS, RTP_HISTORY_HOURS, SYMBOL_COUNT};
+
+/// A single progressive jackpot pool (e.g. Mini / Major / Grand)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct JackpotPool {
+    /// Current jackpot amount (lamports) available to be won.
+    pub amount: u64,
+    /// Seed amount (lamports) after reset when hit.
+    pub seed: u64,
+    /// Contribution per paid bet (basis points of bet).
+    /// e.g. 100 = 1% of bet.
+    pub contrib_bps: u16,
+    /// Selection weight when a jackpot hit occurs.
+    pub hit_weight: u32,
+}
+
+/// Win multipliers (× bet) by symbol and match length: exactly 2 of a
+/// kind, then columns for 3, 4 and 5 of a kind.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct PayTable {
+    pub two_of_a_kind: [u64; SYMBOL_COUNT],
+    pub multipliers: [[u64; 3]; SYMBOL_COUNT],
+}
+
+impl PayTable {
+    /// Table with `three` as the 3-of-a-kind column and no other wins.
+    pub const fn from_3oak(three: [u64; SYMBOL_COUNT]) -> Self {
+        let mut multipliers = [[0u64; 3]; SYMBOL_COUNT];
+        let mut i = 0;
+        while i < SYMBOL_COUNT {
+            multipliers[i][0] = three[i];
+            i += 1;
+        }
+        PayTable {
+            two_of_a_kind: [0; SYMBOL_COUNT],
+            multipliers,
+        }
+    }
+
+    /// Multiplier for `count` matching `symbol`s; 0 outside 2..=5 or for
+    /// an unknown symbol.
+    pub fn multiplier(&self, symbol: u8, count: u8) -> u64 {
+        let s = symbol as usize;
+        match count {
+            _ if s >= SYMBOL_COUNT => 0,
+            2 => self.two_of_a_kind[s],
+            3..=5 => self.multipliers[s][(count - 3) as usize],
+            _ => 0,
+        }
+    }
+
+    /// Largest multiplier in the table.
+    pub fn max_multiplier(&self) -> u64 {
+        let two = self.two_of_a_kind.iter();
+        let rest = self.multipliers.iter().flatten();
+        two.chain(rest).copied().max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_multiplier() == 0
+    }
+}
+
+/// Reel strip weights: a reel lands on symbol `i` with probability
+/// `weights[i] / total_weight`. Every reel uses the same strip.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct ReelWeights {
+    pub weights: [u64; SYMBOL_COUNT],
+    /// Sum of `weights`, kept in step by [`ReelWeights::new`].
+    pub total_weight: u64,
+}
+
+impl ReelWeights {
+    /// Weights `weights` with their total; saturates rather than wraps.
+    pub const fn new(weights: [u64; SYMBOL_COUNT]) -> Self {
+        let mut total_weight = 0u64;
+        let mut i = 0;
+        while i < SYMBOL_COUNT {
+            total_weight = total_weight.saturating_add(weights[i]);
+            i += 1;
+        }
+        ReelWeights {
+            weights,
+            total_weight,
+        }
+    }
+}
+
+/// All jackpots combined
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct JackpotsConfig {
+    pub mini: JackpotPool,
+    pub major: JackpotPool,
+    pub grand: JackpotPool,
+    /// Sum of mini + major + grand hit weights.
+    pub hit_weight_total: u32,
+}
+
+/// How jackpot hits are paid when several spins settle in one batch.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub enum JackpotBatchMode {
+    /// Entries settle in order; a later hit sees the reseeded amount.
+    #[default]
+    Sequential,
+    /// Hits on the same tier split the pre-batch amount equally. The
+    /// indivisible remainder stays in the pool.
+    Split,
+}
+
+// =========================
+// ACCOUNT STATE
+// =========================
+
+#[account]
+#[derive(InitSpace)]
+pub struct SlotsState {
+    /// Admin authority.
+    pub authority: Pubkey,
+    /// Treasury PDA (holds game funds + pool).
+    pub treasury: Pubkey,
+
+    pub initialized: bool,
+    /// Mask of pause::PAUSE_*; see pause.rs.
+    pub pause_flags: u8,
+
+    // RTP stats
+    pub total_spins: u64,
+    pub total_wagered: u64,
+    pub total_payout: u64,
+    pub house_profit: u64,
+    /// Current RTP, basis points (e.g. 9550 = 95.50%).
+    pub current_rtp_bps: u32,
+
+    /// Optional target house edge, basis points.
+    pub house_edge_bps: u16,
+
+    // Pool accounting
+    pub total_pool: u64,
+    pub max_payout_per_spin: u64,
+    pub min_pool_threshold: u64,
+
+    // Progressive jackpots
+    pub jackpots: JackpotsConfig,
+
+    // Beta launch gating
+    /// When set, only allowlisted players may request spins.
+    pub beta_mode: bool,
+    /// Number of allowlist pages created so far (pages are 0..allowlist_pages).
+    pub allowlist_pages: u8,
+
+    // Traffic EWMA (see risk module)
+    /// Half-life of the traffic averages, seconds.
+    pub traffic_half_life_secs: u32,
+    /// Decayed count of settled spins (1e6 = one spin).
+    pub traffic_spin_weight: u64,
+    /// Decayed sum of settled bets, lamports.
+    pub traffic_wager_weight: u64,
+    /// Unix timestamp of the last traffic update.
+    pub traffic_last_update_ts: i64,
+
+    // Fee balance & winner fee rebate
+    /// Lamports in the treasury earmarked for fees, outside total_pool.
+    pub fee_balance: u64,
+    /// Lamports added to every winning payout (0 = disabled).
+    pub winner_fee_rebate_lamports: u64,
+    /// Max rebate lamports per player per UTC day.
+    pub rebate_daily_cap_per_player: u64,
+    /// Rebates suspend when fee_balance drops below this...
+    pub rebate_suspend_below: u64,
+    /// ...and resume once it is back at or above this.
+    pub rebate_resume_at: u64,
+    pub rebate_suspended: bool,
+
+    // Partner settle hooks
+    /// Registered hook programs (default key = empty slot).
+    pub settle_hooks: [Pubkey; MAX_SETTLE_HOOKS],
+    /// Hook waiting out the timelock (default key = none).
+    pub pending_settle_hook: Pubkey,
+    pub pending_settle_hook_index: u8,
+    pub pending_settle_hook_activate_slot: u64,
+
+    /// Jackpot payout policy for batch settlement.
+    pub jackpot_batch_mode: JackpotBatchMode,
+
+    /// Pool size at which bootstrap limits end (0 = bootstrap disabled).
+    pub bootstrap_maturity_threshold: u64,
+    /// Bootstrap payout cap, bps of total_pool.
+    pub bootstrap_payout_bps: u16,
+    /// How far below the threshold the pool must fall to re-enter, bps.
+    pub bootstrap_hysteresis_bps: u16,
+    pub bootstrap_active: bool,
+
+    /// Slot of the most recent request_spin.
+    pub last_commit_slot: u64,
+    /// Unused and zero: outflows no longer wait after a commit, the
+    /// reservations in required_floor cover pending wins instead.
+    pub withdrawal_cooldown_slots: u64,
+
+    /// Per-tier ceiling on amount for donations [mini, major, grand]
+    /// (0 = uncapped). Excess stays in the general pool. Contributions are
+    /// not capped, so a tier can grow past it.
+    pub jackpot_max_caps: [u64; 3],
+    /// Donations credited to each tier, kept apart from organic contributions.
+    pub jackpot_boosted_totals: [u64; 3],
+
+    /// Lamports owed to players via PlayerState.claimable_balance. Held in
+    /// the treasury but outside total_pool.
+    pub total_claimable: u64,
+
+    /// Pool funds promised to tournaments, vouchers and pending awards.
+    pub earmarks: EarmarkedObligations,
+
+    /// Slot at which a paused game resumes on its own (0 = none).
+    pub scheduled_resume_slot: u64,
+
+    /// Spins committed and not yet settled or voided.
+    pub pending_spins: u64,
+
+    /// Token-only deployment: the SPL mint all bets, pools and fees are in.
+    /// Pubkey::default() = native SOL. Fixed at initialize.
+    pub exclusive_mint: Pubkey,
+
+    /// Per-tier (mini, major, grand) reseed after a hit; see `reseed`.
+    pub jackpot_reseed_policies: [ReseedPolicy; 3],
+    /// Per-tier slice of each bet (bps) that rebuilds a FeeRebuilt tier.
+    pub jackpot_rebuild_bps: [u16; 3],
+
+    /// Lifetime treasury outflows by OutflowCategory, excluding spin payouts.
+    pub outflow_totals: [u64; OUTFLOW_CATEGORIES],
+
+    /// Off-chain randomness cost per spin, lamports (0 = not tracked).
+    pub oracle_cost_lamports: u64,
+    /// Whether the player or the house bears the oracle cost.
+    pub oracle_cost_mode: OracleCostMode,
+    /// Lifetime oracle cost incurred, one charge per committed spin.
+    pub oracle_costs_paid: u64,
+    /// Lifetime oracle cost recovered from players.
+    pub oracle_costs_charged: u64,
+
+    /// PROGRAM_VERSION snapshotted by pre_upgrade_freeze (0 = not frozen).
+    pub frozen_version: u16,
+    /// Spins committed under an older version than this cannot settle.
+    pub min_settle_version: u16,
+
+    // Dead-man's switch
+    /// May take over as authority after `recovery_inactivity_slots` with
+    /// no authority action (Pubkey::default() = none).
+    pub recovery_authority: Pubkey,
+    pub recovery_inactivity_slots: u64,
+    /// Slot of the last instruction the authority signed.
+    pub last_authority_action_slot: u64,
+
+    /// Limits for player tiers 1..=MAX_PLAYER_TIERS (tier 0 uses the flat
+    /// limits); see tiers.rs.
+    pub tier_limits: [TierLimits; MAX_PLAYER_TIERS],
+
+    // Oracle-health gate (see oracle_health.rs)
+    /// Slot of the last settle that consumed a reveal.
+    pub last_successful_reveal_slot: u64,
+    /// Slot of the first commit since that reveal (0 = none).
+    pub oldest_unrevealed_commit_slot: u64,
+    /// Reveal gap after which an overdue commit closes the gate; 0 = off.
+    pub oracle_max_reveal_gap_slots: u64,
+    pub oracle_expected_reveal_slots: u64,
+    pub oracle_gate_closed: bool,
+
+    // Community revenue share (see community.rs)
+    /// Only signer for community vault withdrawals.
+    pub community_authority: Pubkey,
+    /// Share of each spin's house take routed to the community.
+    pub community_bps: u16,
+    /// Accrued share still in the treasury, awaiting sweep_community.
+    pub community_balance: u64,
+    /// Lifetime community accruals.
+    pub community_accrued: u64,
+    /// Share of house losses still to be netted against accruals.
+    pub community_deficit: u64,
+
+    // Sponsored onboarding (see onboarding.rs)
+    /// Key that pays rent in init_player_sponsored (default = disabled).
+    pub onboarding_sponsor: Pubkey,
+    pub sponsored_daily_budget: u32,
+    /// UTC day index of sponsored_today.
+    pub sponsored_day: i64,
+    pub sponsored_today: u32,
+    /// Lifetime sponsored inits.
+    pub sponsored_total: u64,
+
+    // Lifetime counters for epoch reports (see epoch_reports.rs)
+    pub total_deposits: u64,
+    pub total_fee_rebates: u64,
+    pub jackpot_hits: u64,
+    pub jackpot_paid: u64,
+    /// EpochReportPage currently appended to.
+    pub epoch_report_page: u32,
+
+    /// Genesis hash of the cluster (or qa::LOCALNET_MARKER), set once at
+    /// initialize; gates test-only instructions. See qa.rs.
+    pub network_guard: Pubkey,
+
+    // Jackpot tail-risk bounds (see jackpot_bounds.rs)
+    /// Per-tier hit probability ceiling, parts per million.
+    pub jackpot_max_hit_ppm: u32,
+    /// Expected jackpot cost ceiling, bps of the reference bet.
+    pub jackpot_max_cost_bps: u16,
+    pub jackpot_cost_reference_bet: u64,
+    /// Out-of-bounds weights waiting out the timelock.
+    pub pending_jackpot_weights: JackpotWeights,
+    /// 0 = nothing pending.
+    pub pending_jackpot_weights_activate_slot: u64,
+
+    /// Pool below the public-tier spin requirement (see pool_level.rs).
+    pub pool_below_required: bool,
+
+    /// Wager races created so far; the next race id is this plus one.
+    pub wager_race_count: u32,
+
+    /// Sum of the payout caps reserved by pending spins (see
+    /// reservations.rs).
+    pub reserved_liability: u64,
+
+    /// Slots after the commit before cancel_spin may refund an
+    /// unrevealed spin.
+    pub spin_expiry_slots: u64,
+
+    /// Bet range accepted by request_spin for the public tier; tiers scale
+    /// max_bet (see tiers.rs).
+    pub min_bet: u64,
+    pub max_bet: u64,
+
+    /// Pay the part of a base win cut by pool headroom out of fee_balance,
+    /// up to fee_overflow_max_per_spin per spin.
+    pub use_fees_for_overflow: bool,
+    pub fee_overflow_max_per_spin: u64,
+    /// Lifetime lamports paid from fee_balance to cover overflow.
+    pub total_fee_overflow: u64,
+
+    /// Proposed next authority, waiting for accept_authority (default key
+    /// = none).
+    pub pending_authority: Pubkey,
+
+    /// Whitelisted oracle queue authorities and the slack kept between
+    /// their expected reveal latency and spin_expiry_slots. See
+    /// oracle_queue.rs.
+    pub oracle_queues: [OracleQueueEntry; MAX_ORACLE_QUEUES],
+    pub oracle_queue_margin_slots: u32,
+
+    /// Lifetime wagered and paid per Feature; see feature_stats.rs.
+    pub feature_stats: [FeatureTotals; FEATURE_KINDS],
+
+    /// Token deployments: the treasury PDA's associated token account
+    /// (default key until init_token_treasury) and the mint's decimals.
+    /// See token_mode.rs.
+    pub token_treasury: Pubkey,
+    pub token_decimals: u8,
+
+    /// Lamports paid from the house pool to whoever settles another
+    /// player's spin through settle_spin. 0 = no fee.
+    pub crank_fee: u64,
+
+    /// Most outcomes any commit may derive from one VRF reveal. See
+    /// outcome_budget.rs.
+    pub max_outcomes_per_reveal: u8,
+
+    /// Layout version this account was last written with (0 = before
+    /// versioning, read as 1). See migration.rs.
+    pub version: u8,
+
+    /// Authority extraction budget: fraction of the pool per period, and
+    /// the open period's end slot, budget and usage. Override: extra
+    /// lamports allowed past the budget from its activation slot (0 =
+    /// none). See extraction.rs.
+    pub extraction_budget_bps: u16,
+    pub extraction_period_end: u64,
+    pub extraction_period_budget: u64,
+    pub extraction_period_used: u64,
+    pub extraction_override: u64,
+    pub extraction_override_activate_slot: u64,
+
+    /// Live paytable and the ceiling on its implied base-game RTP. See
+    /// paytable.rs.
+    pub paytable: PayTable,
+    pub max_rtp_bps: u16,
+
+    /// Wallets barred from playing alongside the role keys; default keys
+    /// are empty slots. See operators.rs.
+    pub staff_wallets: [Pubkey; MAX_STAFF_WALLETS],
+
+    // Devnet faucet (see faucet.rs)
+    /// Lamports set aside for drips; outside every other bucket.
+    pub faucet_reserve: u64,
+    /// Lamports per drip; 0 = disabled.
+    pub faucet_amount: u64,
+    pub faucet_interval_slots: u64,
+    pub faucet_lifetime_cap: u64,
+    /// Lifetime lamports dripped.
+    pub faucet_dripped: u64,
+
+    /// Stake that gets full jackpot odds; smaller stakes get a
+    /// proportional share (game_math::jackpot_odds). 0 = every stake
+    /// gets full odds.
+    pub jackpot_qualifying_bet: u64,
+
+    /// Operator fee taken from every bet (bps), the vault it is paid out
+    /// of, the booked balance still in the treasury and the lifetime
+    /// total. See operator_fee.rs.
+    pub operator_fee_bps: u16,
+    pub fee_vault: Pubkey,
+    pub operator_fee_balance: u64,
+    pub operator_fees_accrued: u64,
+
+    /// Expected return of a bet, jackpot contributions included; at most
+    /// 10_000 - house_edge_bps. See paytable.rs.
+    pub theoretical_rtp_bps: u32,
+
+    /// LP shares outstanding, and the part of them the house holds for
+    /// the pool it funded before LPs. See pool_shares.rs.
+    pub total_shares: u128,
+    pub house_shares: u128,
+
+    /// Timelocked operator withdrawal: the delay, and the one pending
+    /// request (amount 0 when none). See withdrawal_timelock.rs.
+    pub withdrawal_delay_slots: u64,
+    pub pending_withdrawal_amount: u64,
+    pub pending_withdrawal_unlock_slot: u64,
+    pub pending_withdrawal_category: u8,
+    pub pending_withdrawal_memo: [u8; 32],
+
+    /// Loyalty points per lamport wagered, bps, and the free spin they
+    /// buy: its point cost and house-funded bet (0 = off). See loyalty.rs.
+    pub points_per_lamport_bps: u16,
+    pub free_spin_point_cost: u64,
+    pub free_spin_bet: u64,
+
+    /// Referrers' share of the expected house edge, and their accrued,
+    /// unclaimed credit held in the treasury. See referrals.rs.
+    pub referral_bps: u16,
+    pub referral_balance: u64,
+
+    /// Fewest slots between a player's commits (0 = off). See
+    /// rate_limit.rs.
+    pub min_slots_between_spins: u16,
+
+    /// Window the players' loss limits count over. See
+    /// responsible_gaming.rs.
+    pub loss_window_slots: u64,
+
+    /// Settles so far, across players; the last one's ordinal. Carried
+    /// by SpinSettled as global_spin_counter. flush_stats counts deferred
+    /// settles in bulk and reports the first ordinal in StatsFlushed.
+    pub global_spin_counter: u64,
+
+    /// Live reel weights; the paytable prices what they land. See
+    /// paytable.rs.
+    pub reel_weights: ReelWeights,
+
+    /// Which machine of the deployment this is; all zeros for the legacy
+    /// one. Seeds its PDAs. See machines.rs.
+    pub machine_id: [u8; 32],
+
+    /// Scatter symbol and the free spins it awards; zero = off. See
+    /// scatter.rs.
+    pub scatter: ScatterConfig,
+
+    /// Symbol that completes any match, NO_WILD for none. See
+    /// game_math::calculate_payout.
+    pub wild_symbol: u8,
+
+    /// Length of a leaderboard epoch. See leaderboard.rs.
+    pub leaderboard_epoch_slots: u64,
+
+    /// Per-tier must-hit-by ceiling [mini, major, grand] (0 = none), and
+    /// a bit per tier that reached it and awaits its hit. See
+    /// must_hit.rs.
+    pub jackpot_must_hit_by: [u64; 3],
+    pub jackpot_forced_hits: u8,
+
+    /// VIP token gate and rebate, and the rebates players have accrued
+    /// and not yet claimed (held outside total_pool). See vip.rs.
+    pub vip_config: VipConfig,
+    pub vip_rebate_balance: u64,
+
+    /// Bumped each time an emptied pool writes its LP shares off; see
+    /// pool_shares.rs.
+    pub share_epoch: u32,
+}
+
+impl SlotsState {
+    /// Account size of the current layout, discriminator included.
+    pub const LEN: usize = 8 + SlotsState::INIT_SPACE;
+
+    pub fn exclusive_mint(&self) -> Option<Pubkey> {
+        (self.exclusive_mint != Pubkey::default()).then_some(self.exclusive_mint)
+    }
+}
+
+/// Per-player state. A player can have **one pending spin**
+/// tied to a Switchboard randomness account.
+#[account]
+pub struct PlayerState {
+    pub owner: Pubkey,
+    pub bump: u8,
+
+    /// Randomness account currently committed for a spin (if any).
+    pub randomness_account: Pubkey,
+    /// Bet amount for pending spin.
+    pub pending_bet_amount: u64,
+    /// True if there is a pending spin waiting for VRF reveal.
+    pub has_pending_spin: bool,
+
+    /// UTC day index (unix_timestamp / 86400) of rebate_paid_today.
+    pub rebate_day: i64,
+    /// Winner fee rebate received on rebate_day.
+    pub rebate_paid_today: u64,
+
+    /// Randomness generation the pending spin is bound to: the account's
+    /// seed_slot and seed_slothash at commit time.
+    pub commit_slot: u64,
+    pub commit_seed_slothash: [u8; 32],
+    /// Jackpot contributions [mini, major, grand] made by the pending bet,
+    /// so a void can unwind them exactly.
+    pub pending_jackpot_contribs: [u64; 3],
+
+    /// Lamports held for the player: refunds (e.g. admin voids) and
+    /// session deposits that relayed bets draw from. Withdrawn with
+    /// withdraw_claimable.
+    pub claimable_balance: u64,
+
+    /// Losing settles not yet folded into SlotsState (deferred stats mode).
+    /// Until flush_stats runs, SlotsState.pending_spins still counts them
+    /// and total_spins / traffic do not.
+    pub deferred_spins: u64,
+    pub deferred_wagered: u64,
+    pub deferred_last_ts: i64,
+
+    /// Nonce the next relayed signed bet must carry.
+    pub next_bet_nonce: u64,
+
+    /// PROGRAM_VERSION the pending spin was committed under.
+    pub commit_version: u16,
+
+    /// Limit tier assigned by the authority (0 = public).
+    pub tier: u8,
+
+    /// Outcome forced for the next settle; only ever set in QA builds.
+    pub forced_outcome: ForcedOutcome,
+
+    /// Payout cap and jackpot mask of the pending spin, fixed at commit
+    /// (see tiers.rs). A zero cap means no snapshot.
+    pub commit_payout_cap: u64,
+    pub commit_jackpot_mask: u8,
+
+    /// Wager race the player's running total belongs to (0 = none) and
+    /// that total. See wager_race.rs.
+    pub race_id: u32,
+    pub race_wagered: u64,
+
+    /// Reservations of deferred settles, released by flush_stats.
+    pub deferred_reserved: u64,
+
+    /// Spins committed so far; the pending spin's nonce. Carried by spin
+    /// events, see RESPIN_CONTEXT_INDEX.
+    pub spin_nonce: u64,
+
+    /// Outcomes the pending spin may still derive from its reveal.
+    pub pending_outcomes: u8,
+
+    /// Paylines of the pending spin (0 or 1 = single line). The pending
+    /// bet is the whole stake, split evenly between them.
+    pub pending_lines: u8,
+
+    /// Lifetime statistics; see player_stats.rs.
+    pub total_spins: u64,
+    pub total_wagered: u64,
+    pub total_won: u64,
+    pub biggest_win: u64,
+    pub last_spin_slot: u64,
+
+    /// Slot of the last devnet faucet drip (0 = none) and the lifetime
+    /// total received. See faucet.rs.
+    pub faucet_last_slot: u64,
+    pub faucet_received: u64,
+
+    /// Jackpot odds of the pending spin, fixed at commit (see
+    /// game_math::jackpot_odds); per spin of a multi-spin commit. 0 = no
+    /// snapshot, full odds.
+    pub commit_jackpot_odds: u64,
+
+    /// Randomness generations of the last commits, a ring whose next
+    /// slot is recent_seed_head. See seed_replay.rs.
+    pub recent_seeds: [UsedSeed; RECENT_SEEDS],
+    pub recent_seed_head: u8,
+
+    /// Operator fee booked by the pending bet, taken back on a void.
+    pub pending_operator_fee: u64,
+
+    /// Unspent loyalty points, a redeemed free-spin credit not yet played,
+    /// and whether the pending spin is house funded. See loyalty.rs.
+    pub loyalty_points: u64,
+    pub free_spin_credit: u64,
+    pub pending_free_spin: bool,
+
+    /// Set once, before the first spin; default = none. See referrals.rs.
+    pub referrer: Pubkey,
+
+    /// Slot of the player's last commit; see rate_limit.rs.
+    pub last_request_slot: u64,
+
+    /// Self-exclusion end (0 = none), the loss limit (0 = none) and the
+    /// losses of the window opened at session_start_slot. See
+    /// responsible_gaming.rs.
+    pub self_excluded_until: u64,
+    pub loss_limit_lamports: u64,
+    pub session_losses: u64,
+    pub session_start_slot: u64,
+
+    /// Bytes and seed_slot of the last settled reveal, as SpinProof
+    /// published them. See fairness.rs.
+    pub last_reveal: [u8; 32],
+    pub last_reveal_seed_slot: u64,
+
+    /// Scatter free spins held, the line bet they play at, and whether
+    /// the pending spin is one of them. See scatter.rs.
+    pub free_spins_remaining: u16,
+    pub free_spin_bet: u64,
+    pub pending_bonus_spin: bool,
+
+    /// Leaderboard epoch of the player's running payout total and that
+    /// total. See leaderboard.rs.
+    pub leaderboard_epoch: u64,
+    pub leaderboard_won: u64,
+
+    /// VIP rebates credited and not yet claimed. See vip.rs.
+    pub rebate_accrued: u64,
+
+    /// Spins of the pending commit (0 or 1 = one). The pending bet is
+    /// the whole stake, split evenly between them. See multi_spin.rs.
+    pub spins_remaining: u8,
+
+    /// VIP token account and current leaderboard the player's last own
+    /// settle used (default = none); a cranked settle must pass them.
+    pub vip_token_account: Pubkey,
+    pub leaderboard: Pubkey,
+}
+
+impl PlayerState {
+    pub const LEN: usize = 8
+        + 32
+        + 1
+        + 32
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 32
+        + 24
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 2
+        + 1
+        + 5
+        + 8
+        + 1
+        + 4
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 40
+        + 16
+        + 8
+        + 160
+        + 1
+        + 8
+        + 8
+        + 8
+        + 1
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 32
+        + 8
+        + 2
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 1
+        + 32
+        + 32;
+}
+
+/// Scraper gauges (PDA), plain little-endian fields. Offsets are
+/// documented in the metrics module.
+#[account(zero_copy)]
+pub struct Metrics {
+    pub slots_state: Pubkey,
+    pub updated_slot: u64,
+    pub total_pool: u64,
+    /// total_pool above the required floor.
+    pub spendable: u64,
+    pub pending_spins: u64,
+    pub total_spins: u64,
+    /// [mini, major, grand]
+    pub jackpot_amounts: [u64; 3],
+    pub fee_balance: u64,
+    pub current_rtp_bps: u32,
+    pub pause_flags: u8,
+    pub bootstrap_active: u8,
+    pub beta_mode: u8,
+    pub bump: u8,
+}
+
+impl Metrics {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 * 3 + 8 + 4 + 1 + 1 + 1 + 1;
+}
+
+// Scrapers read fixed offsets: no padding, and the length they expect.
+const _: () = assert!(Metrics::LEN == 8 + core::mem::size_of::<Metrics>());
+const _: () = assert!(Metrics::LEN == crate::metrics::METRICS_LEN);
+
+/// Append-only Merkle tree of spin receipts (PDA). See the receipts module.
+#[account(zero_copy)]
+pub struct ReceiptTree {
+    pub slots_state: Pubkey,
+    /// Leaves appended so far; index of the next leaf.
+    pub next_index: u64,
+    /// Position of `root` in root_history.
+    pub root_history_index: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub root: [u8; 32],
+    pub filled_subtrees: [[u8; 32]; RECEIPT_TREE_DEPTH],
+    pub zero_hashes: [[u8; 32]; RECEIPT_TREE_DEPTH],
+    pub root_history: [[u8; 32]; RECEIPT_ROOT_HISTORY],
+}
+
+impl ReceiptTree {
+    pub const LEN: usize = 8
+        + 32
+        + 8
+        + 8
+        + 1
+        + 7
+        + 32
+        + 32 * RECEIPT_TREE_DEPTH
+        + 32 * RECEIPT_TREE_DEPTH
+        + 32 * RECEIPT_ROOT_HISTORY;
+}
+
+// The zero-copy layout must have no padding beyond `_padding`.
+const _: () = assert!(ReceiptTree::LEN == 8 + core::mem::size_of::<ReceiptTree>());
+
+/// One page of beta-mode allowlisted players (PDA per page).
+#[account]
+pub struct Allowlist {
+    pub slots_state: Pubkey,
+    pub page: u8,
+    pub bump: u8,
+    /// Sorted so membership is a binary search.
+    pub entries: Vec<Pubkey>,
+}
+
+impl Allowlist {
+    pub const LEN: usize = 8 + 32 + 1 + 1 + 4 + 32 * ALLOWLIST_PAGE_CAPACITY;
+
+    pub fn contains(&self, player: &Pubkey) -> bool {
+        self.entries.binary_search(player).is_ok()
+    }
+}
+
+/// Community revenue share vault and its withdrawal allowlist (PDA).
+/// Lamports above rent belong to the community.
+#[account]
+pub struct CommunityVault {
+    pub slots_state: Pubkey,
+    /// Where the community authority may withdraw to; unused slots are
+    /// Pubkey::default().
+    pub destinations: [Pubkey; MAX_COMMUNITY_DESTINATIONS],
+    pub total_withdrawn: u64,
+    pub bump: u8,
+}
+
+/// A depositor's LP shares in the house pool (PDA per depositor). See
+/// pool_shares.rs.
+#[account]
+pub struct PoolPosition {
+    pub owner: Pubkey,
+    pub slots_state: Pubkey,
+    pub shares: u128,
+    /// Lifetime lamports deposited and withdrawn.
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub bump: u8,
+    /// SlotsState.share_epoch `shares` were minted in; shares of an
+    /// earlier epoch were written off.
+    pub share_epoch: u32,
+}
+
+/// A referrer's credit (PDA per referrer). See referrals.rs.
+#[account]
+pub struct ReferralEarnings {
+    pub referrer: Pubkey,
+    pub slots_state: Pubkey,
+    /// Credited and not yet claimed.
+    pub accrued: u64,
+    pub total_earned: u64,
+    pub total_claimed: u64,
+    pub referred_spins: u64,
+    pub bump: u8,
+}
+
+/// One page of per-epoch accounting reports (PDA per page). See
+/// epoch_reports.rs.
+#[account]
+pub struct EpochReportPage {
+    pub slots_state: Pubkey,
+    pub page: u32,
+    pub epoch_length_secs: u32,
+    /// Epoch still open, and the lifetime totals it opened at.
+    pub open_epoch: i64,
+    pub open_base: EpochTotals,
+    /// Closed reports in `reports`.
+    pub count: u8,
+    pub reports: [EpochReport; EPOCH_REPORTS_PER_PAGE],
+    pub bump: u8,
+}
+
+/// Volume campaign with a top-N board (PDA per race). See wager_race.rs.
+#[account]
+pub struct WagerRace {
+    pub slots_state: Pubkey,
+    pub race_id: u32,
+    /// Settles in [start_slot, end_slot) count.
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Earmarked from the pool at creation.
+    pub prize_budget: u64,
+    /// Share of the budget per board place, bps.
+    pub prize_split_bps: [u16; WAGER_RACE_BOARD_SIZE],
+    /// Ranked entries, best first.
+    pub board: [WagerRaceEntry; WAGER_RACE_BOARD_SIZE],
+    pub finalized: bool,
+    pub bump: u8,
+}
+
+/// Top winners of one epoch (PDA per epoch). See leaderboard.rs.
+#[account]
+pub struct Leaderboard {
+    pub slots_state: Pubkey,
+    pub epoch: u64,
+    /// SlotsState.leaderboard_epoch_slots at creation.
+    pub epoch_slots: u64,
+    /// Ranked entries, best first.
+    pub entries: [LeaderboardEntry; LEADERBOARD_SIZE],
+    pub bump: u8,
+}
+
+/// Canonical name and content hash per symbol index (PDA). See
+/// symbol_meta.rs.
+#[account]
+pub struct SymbolMeta {
+    pub slots_state: Pubkey,
+    pub entries: [SymbolEntry; SYMBOL_COUNT],
+    /// Table waiting out the timelock; used once activate_slot is set.
+    pub pending_entries: [SymbolEntry; SYMBOL_COUNT],
+    /// Slot the pending table may be activated at (0 = none pending).
+    pub pending_activate_slot: u64,
+    /// Bumped on every activation, starting at 1.
+    pub version: u32,
+    pub bump: u8,
+}
+
+/// Return data of `get_game_status`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct GameStatus {
+    /// Mask of pause::PAUSE_*.
+    pub pause_flags: u8,
+    pub frozen_version: u16,
+    pub program_version: u16,
+    /// Paytable plus symbol meta; see symbol_meta::config_hash.
+    pub config_hash: [u8; 32],
+    /// 0 when no symbol meta table was passed.
+    pub symbol_meta_version: u32,
+    /// add_to_pool amount that would let a public-tier spin through
+    /// (0 when the pool is sufficient).
+    pub pool_shortfall: u64,
+}
+
+/// Which per-player accounts exist, and their bumps (PDA per player).
+/// Maintained by the account_index module.
+#[account]
+pub struct AccountIndex {
+    pub owner: Pubkey,
+    /// Bit `kind` set = that account exists.
+    pub present: u32,
+    /// Bump of each present account, by kind.
+    pub bumps: [u8; ACCOUNT_INDEX_SLOTS],
+    pub bump: u8,
+}
+
+/// One hour of settled activity.
+#[zero_copy]
+#[derive(Default)]
+pub struct RtpHourEntry {
+    pub wagered: u64,
+    pub paid: u64,
+    pub spins: u64,
+}
+
+/// Ring buffer of the last RTP_HISTORY_HOURS hourly buckets (PDA).
+/// Written lazily by settle_spin when passed; bucket for hour `h` lives
+/// at index `h % RTP_HISTORY_HOURS`.
+#[account(zero_copy)]
+pub struct RtpHistory {
+    pub slots_state: Pubkey,
+    /// Hour index (unix_timestamp / 3600) of the newest bucket.
+    pub head_hour: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub entries: [RtpHourEntry; RTP_HISTORY_HOURS],
+}
+
+impl RtpHistory {
+    pub const LEN: usize = 8 + 32 + 8 + 1 + 7 + (8 + 8 + 8) * RTP_HISTORY_HOURS;
+
+    /// Add a settled spin to the bucket for `now_ts`, zeroing any hours
+    /// skipped since the last write. Timestamps behind the head land in
+    /// the head bucket.
+    pub fn record(&mut self, now_ts: i64, wagered: u64, paid: u64) {
+        let previous = self.head_hour;
+        let (hour, advanced) = time::advance_bucket(previous, now_ts, time::SECONDS_PER_HOUR);
+        if advanced {
+            let skipped = (hour - previous).min(RTP_HISTORY_HOURS as i64);
+            for h in (hour - skipped + 1)..=hour {
+                self.entries[(h as usize) % RTP_HISTORY_HOURS] = RtpHourEntry::default();
+            }
+            self.head_hour = hour;
+        }
+
+        let entry = &mut self.entries[(self.head_hour as usize) % RTP_HISTORY_HOURS];
+        entry.wagered = entry.wagered.saturating_add(wagered);
+        entry.paid = entry.paid.saturating_add(paid);
+        entry.spins = entry.spins.saturating_add(1);
+    }
+}
+
+// The zero-copy layout must have no padding beyond `_padding`.
+const _: () = assert!(RtpHistory::LEN == 8 + core::mem::size_of::<RtpHistory>());
+
+/// Arguments of the partner hook's `on_spin_settled` instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OnSpinSettledArgs {
+    pub user: Pubkey,
+    pub bet: u64,
+    pub payout: u64,
+    pub symbols: [u8; 3],
+}
@@ -0,0 +1,186 @@
+//! Canonical identities for symbol indices.
+//!
+//! SpinSettled reports symbols by index. The optional `SymbolMeta` PDA
+//! (seeds `[SYMBOL_META_SEED, slots_state]`) gives each index a 16-byte
+//! name and a 32-byte content hash (artwork hash or URI hash), so a
+//! dispute can be resolved against the chain rather than a reskinned UI.
+//!
+//! [`config_hash`] binds each index's weight and 3-of-a-kind payout to its
+//! meta entry. A build that reorders the paytable without reordering the
+//! meta therefore reports a different hash. Table changes go through
+//! propose_symbol_meta and wait SYMBOL_META_TIMELOCK_SLOTS, like settle
+//! hooks.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{PayTable, ReelWeights, SymbolMeta, SYMBOL_COUNT};
+
+pub const SYMBOL_NAME_LEN: usize = 16;
+
+/// Domain tag of the config hash; bump the suffix if the preimage changes.
+pub const CONFIG_HASH_DOMAIN: &[u8] = b"gorbagana-slots:config:v1";
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SymbolEntry {
+    /// Zero-padded UTF-8 name.
+    pub name: [u8; SYMBOL_NAME_LEN],
+    pub content_hash: [u8; 32],
+}
+
+impl SymbolMeta {
+    pub const LEN: usize = 8 + 32 + 2 * SYMBOL_COUNT * (SYMBOL_NAME_LEN + 32) + 8 + 4 + 1;
+}
+
+/// Whether every entry has a name and a content hash.
+pub fn entries_valid(entries: &[SymbolEntry; SYMBOL_COUNT]) -> bool {
+    entries
+        .iter()
+        .all(|e| e.name.iter().any(|&b| b != 0) && e.content_hash != [0; 32])
+}
+
+/// Hash of the paytable, index by index, with the symbol meta when one is
+/// set: sha256(domain || for each i: weight_i || payout_i || name_i ||
+/// content_hash_i || [for each i: two_of_a_kind_i]). payout_i is the
+/// 3-of-a-kind multiplier; the 2-of-a-kind block is only appended when
+/// the table pays any, so a 3-of-a-kind-only table hashes as before.
+/// Without meta, names and hashes hash as zeros. weight_i is the live
+/// reel weight, so the default reels hash as before set_reel_config.
+pub fn config_hash(
+    reels: &ReelWeights,
+    paytable: &PayTable,
+    entries: Option<&[SymbolEntry; SYMBOL_COUNT]>,
+) -> [u8; 32] {
+    let empty = [SymbolEntry::default(); SYMBOL_COUNT];
+    let entries = entries.unwrap_or(&empty);
+
+    let mut preimage = Vec::with_capacity(SYMBOL_COUNT * (16 + SYMBOL_NAME_LEN + 32));
+    for (i, entry) in entries.iter().enumerate() {
+        preimage.extend_from_slice(&reels.weights[i].to_le_bytes());
+        preimage.extend_from_slice(&paytable.multiplier(i as u8, 3).to_le_bytes());
+        preimage.extend_from_slice(&entry.name);
+        preimage.extend_from_slice(&entry.content_hash);
+    }
+    if paytable.two_of_a_kind.iter().any(|&m| m != 0) {
+        for m in paytable.two_of_a_kind {
+            preimage.extend_from_slice(&m.to_le_bytes());
+        }
+    }
+    hashv(&[CONFIG_HASH_DOMAIN, &preimage]).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{pda, program_error, returned, simulate, Fixture, Game};
+    use crate::{
+        instruction, ErrorCode, GameStatus, SlotsState, SYMBOL_META_SEED,
+        SYMBOL_META_TIMELOCK_SLOTS,
+    };
+
+    fn entries(salt: u8) -> [SymbolEntry; SYMBOL_COUNT] {
+        core::array::from_fn(|i| SymbolEntry {
+            name: [b'a' + i as u8; SYMBOL_NAME_LEN],
+            content_hash: [salt ^ i as u8 | 0x80; 32],
+        })
+    }
+
+    #[test]
+    fn reordering_symbols_without_the_meta_changes_the_hash() {
+        let state = Game::new().state;
+        let (reels, paytable) = (state.reel_weights, state.paytable);
+        let meta = entries(1);
+        let hash = config_hash(&reels, &paytable, Some(&meta));
+        assert_ne!(hash, config_hash(&reels, &paytable, None));
+        assert_ne!(hash, config_hash(&reels, &paytable, Some(&entries(2))));
+
+        let (a, b) = (0, SYMBOL_COUNT - 1);
+        assert_ne!(paytable.multipliers[a], paytable.multipliers[b]);
+        let mut reordered = paytable;
+        reordered.multipliers.swap(a, b);
+        reordered.two_of_a_kind.swap(a, b);
+        assert_ne!(config_hash(&reels, &reordered, Some(&meta)), hash);
+        let mut reweighted = reels;
+        reweighted.weights.swap(a, b);
+        assert_ne!(reels.weights[a], reels.weights[b]);
+        assert_ne!(config_hash(&reweighted, &paytable, Some(&meta)), hash);
+    }
+
+    #[test]
+    fn every_entry_needs_a_name_and_a_hash() {
+        assert!(entries_valid(&entries(1)));
+        let mut unnamed = entries(1);
+        unnamed[3].name = [0; SYMBOL_NAME_LEN];
+        assert!(!entries_valid(&unnamed));
+        let mut unhashed = entries(1);
+        unhashed[0].content_hash = [0; 32];
+        assert!(!entries_valid(&unhashed));
+    }
+
+    /// get_game_status with the symbol meta passed.
+    fn status(state: &Fixture, meta: &Fixture) -> GameStatus {
+        let view = instruction::GetGameStatus {}.data();
+        simulate([&mut state.clone(), &mut meta.clone()], &view).unwrap();
+        returned()
+    }
+
+    #[test]
+    fn the_table_changes_only_after_its_timelock() {
+        let mut game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1_000_000_000).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let (key, _) = pda(&[SYMBOL_META_SEED, state.key.as_ref()]);
+        let mut meta = Fixture::uninit(key, SymbolMeta::LEN);
+        let init = |entries| instruction::InitSymbolMeta { entries }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut meta, &mut stranger, &mut system], &init(entries(1))),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        let mut meta = Fixture::uninit(key, SymbolMeta::LEN);
+        let mut blank = entries(1);
+        blank[0].name = [0; SYMBOL_NAME_LEN];
+        assert_eq!(
+            simulate([&mut state, &mut meta, &mut authority, &mut system], &init(blank)),
+            Err(program_error(ErrorCode::InvalidSymbolMeta))
+        );
+        let mut meta = Fixture::uninit(key, SymbolMeta::LEN);
+        simulate([&mut state, &mut meta, &mut authority, &mut system], &init(entries(1))).unwrap();
+
+        let slots_state = state.read::<SlotsState>();
+        let hash_of = |salt| {
+            config_hash(&slots_state.reel_weights, &slots_state.paytable, Some(&entries(salt)))
+        };
+        let before = status(&state, &meta);
+        assert_eq!(before.symbol_meta_version, 1);
+        assert_eq!(before.config_hash, hash_of(1));
+
+        let activate = instruction::ActivateSymbolMeta {}.data();
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut meta], &activate),
+            Err(program_error(ErrorCode::InvalidSymbolMeta))
+        );
+        let propose = instruction::ProposeSymbolMeta { entries: entries(2) }.data();
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut meta], &propose),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut authority, &mut meta], &propose).unwrap();
+        game.advance(SYMBOL_META_TIMELOCK_SLOTS - 1);
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut meta], &activate),
+            Err(program_error(ErrorCode::SymbolMetaTimelocked))
+        );
+        assert_eq!(status(&state, &meta).config_hash, before.config_hash);
+
+        game.advance(1);
+        simulate([&mut state, &mut authority, &mut meta], &activate).unwrap();
+        let after = status(&state, &meta);
+        assert_eq!(after.symbol_meta_version, 2);
+        assert_eq!(after.config_hash, hash_of(2));
+    }
+}
@@ -0,0 +1,838 @@
+//! Fixtures shared by the unit tests.
+
+use std::cell::{Cell, RefCell};
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::{ProgramResult, SUCCESS};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::UNSUPPORTED_SYSVAR;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::InstructionData;
+use bytemuck::{Pod, Zeroable};
+use switchboard_on_demand::on_demand::accounts::RandomnessAccountData;
+
+use crate::accounting::AccountingReport;
+use crate::*;
+use crate::ErrorCode;
+
+/// An account of `len` bytes, discriminator included, read from zeros:
+/// every field at its zero value, as a freshly grown account has them.
+pub fn zeroed<T: AnchorDeserialize>(len: usize) -> T {
+    T::deserialize(&mut &vec![0u8; len - 8][..]).expect("zeroed account deserializes")
+}
+
+pub fn zeroed_state() -> SlotsState {
+    zeroed(SlotsState::LEN)
+}
+
+pub fn zeroed_player() -> PlayerState {
+    zeroed(PlayerState::LEN)
+}
+
+/// RandomnessAccountData's account discriminator.
+const RANDOMNESS_DISCRIMINATOR: [u8; 8] = [10, 66, 229, 135, 220, 239, 217, 114];
+
+/// Another program, run by [`simulate`] when this one CPIs it. It gets
+/// the instruction and the accounts it names, with the privileges the
+/// instruction grants.
+pub type ProgramStub = fn(&Instruction, &[AccountInfo]) -> ProgramResult;
+
+/// Stands in for the runtime's InstructionError::ReentrancyNotAllowed,
+/// which has no ProgramError.
+pub const REENTRANCY_NOT_ALLOWED: ProgramError = ProgramError::Custom(u32::MAX);
+
+thread_local! {
+    /// Slot the running test's Clock reads; None until it sets one.
+    static NOW: Cell<Option<u64>> = const { Cell::new(None) };
+    /// Programs a CPI runs; any other (the system program's transfers)
+    /// does nothing.
+    static PROGRAMS: RefCell<Vec<(Pubkey, ProgramStub)>> = const { RefCell::new(Vec::new()) };
+    /// Programs executing in [`simulate`], outermost first.
+    static CALL_STACK: RefCell<Vec<Pubkey>> = const { RefCell::new(Vec::new()) };
+    /// The last return data set, and the program that set it.
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    /// Events emitted on this thread and not yet taken by [`emitted`].
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `run` for CPIs into `program` on this thread.
+pub fn stub_program(program: Pubkey, run: ProgramStub) {
+    PROGRAMS.with(|programs| {
+        let mut programs = programs.borrow_mut();
+        programs.retain(|(key, _)| *key != program);
+        programs.push((program, run));
+    });
+}
+
+/// The System program instructions this program CPIs: create_account,
+/// assign, transfer and allocate. Accounts keep the length a test gave
+/// them, so create_account and allocate must ask for exactly that.
+fn system_program(data: &[u8], accounts: &[AccountInfo]) -> ProgramResult {
+    let u64_at = |at: usize| {
+        data.get(at..at + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(ProgramError::InvalidInstructionData)
+    };
+    let key_at = |at: usize| {
+        data.get(at..at + 32)
+            .map(|b| Pubkey::try_from(b).unwrap())
+            .ok_or(ProgramError::InvalidInstructionData)
+    };
+    // Only a signing account the System program still owns can be
+    // allocated or assigned.
+    let claim = |account: &AccountInfo| {
+        if !account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *account.owner != anchor_lang::system_program::ID {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        Ok(())
+    };
+    let allocate = |account: &AccountInfo, space: u64| {
+        claim(account)?;
+        if account.data_len() as u64 != space {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    };
+    let move_lamports = |from: &AccountInfo, to: &AccountInfo, lamports: u64| {
+        if !from.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *from.owner != anchor_lang::system_program::ID || !from.data_is_empty() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let left = from
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **from.try_borrow_mut_lamports()? = left;
+        **to.try_borrow_mut_lamports()? += lamports;
+        Ok(())
+    };
+    let tag = data
+        .get(..4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match (tag, accounts) {
+        // CreateAccount { lamports, space, owner }
+        (0, [from, to, ..]) => {
+            if to.lamports() > 0 || !to.is_signer {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            allocate(to, u64_at(12)?)?;
+            move_lamports(from, to, u64_at(4)?)?;
+            to.assign(&key_at(20)?);
+            Ok(())
+        }
+        // Assign { owner }
+        (1, [account, ..]) => {
+            claim(account)?;
+            account.assign(&key_at(4)?);
+            Ok(())
+        }
+        // Transfer { lamports }
+        (2, [from, to, ..]) => move_lamports(from, to, u64_at(4)?),
+        // Allocate { space }
+        (8, [account, ..]) => allocate(account, u64_at(4)?),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Run `program` as the runtime would: on the call stack, which it may
+/// only join again as its own caller.
+fn execute(program: Pubkey, run: impl FnOnce() -> ProgramResult) -> ProgramResult {
+    let reentry = CALL_STACK.with(|stack| {
+        let stack = stack.borrow();
+        stack.contains(&program) && stack.last() != Some(&program)
+    });
+    if reentry {
+        return Err(REENTRANCY_NOT_ALLOWED);
+    }
+    CALL_STACK.with(|stack| stack.borrow_mut().push(program));
+    let result = run();
+    CALL_STACK.with(|stack| stack.borrow_mut().pop());
+    result
+}
+
+struct SysvarStub;
+
+impl SyscallStubs for SysvarStub {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let Some(slot) = NOW.with(Cell::get) else {
+            return UNSUPPORTED_SYSVAR;
+        };
+        let clock = Clock {
+            slot,
+            unix_timestamp: slot as i64,
+            ..Clock::default()
+        };
+        // SAFETY: Clock::get hands over a pointer to a Clock.
+        unsafe { (var_addr as *mut Clock).write(clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // SAFETY: Rent::get hands over a pointer to a Rent.
+        unsafe { (var_addr as *mut Rent).write(Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let program = CALL_STACK.with(|stack| stack.borrow().last().copied());
+        let returned = program.map(|program| (program, data.to_vec()));
+        RETURN_DATA.with(|slot| *slot.borrow_mut() = returned);
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|slot| slot.borrow().clone())
+    }
+
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(data.concat()));
+    }
+
+    /// Hand the callee the accounts its instruction names. As in the
+    /// runtime, an account may only be a signer or writable there if it
+    /// is in the caller, or if it is a signer the caller's seeds derive.
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = CALL_STACK.with(|stack| stack.borrow().last().copied());
+        let caller = caller.unwrap_or(crate::ID);
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        let mut infos = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if meta.is_signer && !info.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let mut info = info.clone();
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            infos.push(info);
+        }
+        // The entrypoint wants its accounts borrowed for as long as they
+        // live; a test leaks the few a CPI builds.
+        let infos: &[AccountInfo] = infos.leak();
+
+        let program = instruction.program_id;
+        if program == crate::ID {
+            return execute(program, || crate::entry(&crate::ID, infos, &instruction.data));
+        }
+        if program == anchor_lang::system_program::ID {
+            return system_program(&instruction.data, infos);
+        }
+        let stub = PROGRAMS.with(|programs| {
+            programs
+                .borrow()
+                .iter()
+                .find(|(key, _)| *key == program)
+                .map(|(_, run)| *run)
+        });
+        match stub {
+            Some(run) => execute(program, || run(instruction, infos)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Serve `Clock::get` on this thread from `slot`, one second a slot,
+/// and `Rent::get` at the default rent.
+pub fn set_clock_slot(slot: u64) {
+    static STUB: Once = Once::new();
+    STUB.call_once(|| {
+        set_syscall_stubs(Box::new(SysvarStub));
+    });
+    NOW.with(|now| now.set(Some(slot)));
+}
+
+/// A player's Switchboard randomness account.
+#[derive(Clone)]
+pub struct Randomness {
+    pub key: Pubkey,
+    pub data: RandomnessAccountData,
+}
+
+impl Default for Randomness {
+    /// A fresh account that has never been seeded.
+    fn default() -> Self {
+        Randomness {
+            key: Pubkey::new_unique(),
+            data: RandomnessAccountData::zeroed(),
+        }
+    }
+}
+
+impl Randomness {
+    /// Request a new generation in `slot`; it has not revealed yet.
+    pub fn seed(&mut self, slot: u64, salt: u8) {
+        let mut slothash = [salt; 32];
+        slothash[..8].copy_from_slice(&slot.to_le_bytes());
+        self.data.seed_slot = slot;
+        self.data.seed_slothash = slothash;
+        self.data.reveal_slot = 0;
+        self.data.value = [0; 32];
+    }
+
+    /// The oracle reveals the current generation, from the slot after
+    /// its seed on.
+    pub fn reveal(&mut self, slot: u64, value: [u8; 32]) {
+        if self.data.reveal_slot == 0 && slot > self.data.seed_slot {
+            self.data.reveal_slot = slot;
+            self.data.value = value;
+        }
+    }
+
+    pub fn with_info<R>(&self, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let mut fixture = self.fixture();
+        f(&fixture.info())
+    }
+
+    /// The account as a transaction passes it.
+    pub fn fixture(&self) -> Fixture {
+        let mut data = RANDOMNESS_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&self.data));
+        Fixture {
+            key: self.key,
+            owner: SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+            lamports: 0,
+            data,
+            signer: false,
+            writable: false,
+            executable: false,
+        }
+    }
+}
+
+/// An account as a transaction hands it to the program.
+#[derive(Clone)]
+pub struct Fixture {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub signer: bool,
+    pub writable: bool,
+    pub executable: bool,
+}
+
+impl Fixture {
+    /// A writable system account holding `lamports`.
+    pub fn wallet(key: Pubkey, lamports: u64) -> Self {
+        Fixture {
+            key,
+            owner: anchor_lang::system_program::ID,
+            lamports,
+            data: Vec::new(),
+            signer: false,
+            writable: true,
+            executable: false,
+        }
+    }
+
+    /// A writable account of this program holding `account`.
+    pub fn account<T: AccountSerialize>(key: Pubkey, account: &T) -> Self {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).expect("account serializes");
+        Fixture {
+            key,
+            owner: crate::ID,
+            lamports: 1,
+            data,
+            signer: false,
+            writable: true,
+            executable: false,
+        }
+    }
+
+    /// A writable zero-copy account of this program holding `account`.
+    pub fn zero_copy<T: anchor_lang::ZeroCopy + Owner + Pod>(key: Pubkey, account: &T) -> Self {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(account));
+        Fixture {
+            key,
+            owner: T::owner(),
+            lamports: 1,
+            data,
+            signer: false,
+            writable: true,
+            executable: false,
+        }
+    }
+
+    /// A writable system account of `len` zero bytes and no lamports,
+    /// for an instruction to create.
+    pub fn uninit(key: Pubkey, len: usize) -> Self {
+        Fixture {
+            data: vec![0; len],
+            ..Fixture::wallet(key, 0)
+        }
+    }
+
+    /// An executable program account.
+    pub fn program(key: Pubkey) -> Self {
+        Fixture {
+            key,
+            owner: anchor_lang::solana_program::bpf_loader::ID,
+            lamports: 1,
+            data: Vec::new(),
+            signer: false,
+            writable: false,
+            executable: true,
+        }
+    }
+
+    /// An optional account left out: Anchor reads the program id in its
+    /// place as None.
+    pub fn omitted() -> Self {
+        Fixture {
+            writable: false,
+            ..Fixture::program(crate::ID)
+        }
+    }
+
+    pub fn signer(self) -> Self {
+        Fixture {
+            signer: true,
+            ..self
+        }
+    }
+
+    /// Deserialize the account of this program it holds.
+    pub fn read<T: AccountDeserialize>(&self) -> T {
+        T::try_deserialize(&mut &self.data[..]).expect("account deserializes")
+    }
+
+    pub fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.signer,
+            self.writable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            self.executable,
+            0,
+        )
+    }
+}
+
+/// Run an instruction through the program's entrypoint, the way a wallet
+/// simulates it: account validation, the handler and the write-back.
+/// Only the clock, rent and return data syscalls are served. CPIs run
+/// this program, the System program's account and lamport instructions,
+/// and programs given to [`stub_program`]; any other does nothing.
+pub fn simulate<'a>(
+    accounts: impl IntoIterator<Item = &'a mut Fixture>,
+    data: &[u8],
+) -> ProgramResult {
+    let mut accounts: Vec<&mut Fixture> = accounts.into_iter().collect();
+    let infos: Vec<AccountInfo> = accounts.iter_mut().map(|fixture| fixture.info()).collect();
+    RETURN_DATA.with(|slot| *slot.borrow_mut() = None);
+    execute(crate::ID, || crate::entry(&crate::ID, &infos, data))
+}
+
+/// What the last [`simulate`] on this thread returned, as a view
+/// instruction's client decodes it.
+pub fn returned<T: AnchorDeserialize>() -> T {
+    let (program, data) = RETURN_DATA
+        .with(|slot| slot.borrow().clone())
+        .expect("instruction returned data");
+    assert_eq!(program, crate::ID);
+    T::try_from_slice(&data).expect("return data deserializes")
+}
+
+/// The `T` events emitted on this thread since the last call, oldest
+/// first. Other events stay for their own callers.
+pub fn emitted<T: anchor_lang::Event + AnchorDeserialize>() -> Vec<T> {
+    EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let (ours, others) = std::mem::take(&mut *events)
+            .into_iter()
+            .partition::<Vec<_>, _>(|data| data.starts_with(&T::DISCRIMINATOR[..]));
+        *events = others;
+        ours.iter()
+            .map(|data| {
+                T::try_from_slice(&data[T::DISCRIMINATOR.len()..]).expect("event deserializes")
+            })
+            .collect()
+    })
+}
+
+/// This program's PDA for `seeds`, and its bump.
+pub fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &crate::ID)
+}
+
+/// `code` as the entrypoint reports it.
+pub fn program_error(code: ErrorCode) -> ProgramError {
+    Error::from(code).into()
+}
+
+/// Opening pool of a [`Game`].
+pub const GAME_POOL: u64 = 20_000_000_000;
+/// Opening wallet of a [`Game`]'s player.
+pub const GAME_WALLET: u64 = 50_000_000_000;
+
+/// A SOL machine with one player, its lamport balances modelled next to
+/// the state. Each step runs its handler's body with the transfers
+/// replaced by moves between the balances; a step that fails is rolled
+/// back whole, as its transaction would be.
+#[derive(Clone)]
+pub struct Game {
+    pub state: SlotsState,
+    pub player: PlayerState,
+    pub user: Pubkey,
+    pub randomness: Randomness,
+    pub slot: u64,
+    /// Spendable treasury lamports, above its rent-exempt reserve.
+    pub treasury: u64,
+    pub wallet: u64,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    /// An initialized machine holding GAME_POOL, and a fresh player.
+    pub fn new() -> Self {
+        let slot = 1_000;
+        set_clock_slot(slot);
+        let mut state = zeroed_state();
+        init_slots_state(
+            &mut state,
+            Pubkey::new_unique(),
+            None,
+            None,
+            None,
+            Pubkey::new_unique(),
+        )
+        .expect("state initializes");
+
+        let user = Pubkey::new_unique();
+        let mut player = zeroed_player();
+        init_player_state(&mut player, user, 255, None).expect("player initializes");
+
+        let mut game = Game {
+            state,
+            player,
+            user,
+            randomness: Randomness::default(),
+            slot,
+            treasury: 0,
+            wallet: GAME_WALLET,
+        };
+        game.fund(GAME_POOL);
+        game
+    }
+
+    /// Another player at this machine, with a fresh PlayerState, wallet
+    /// and randomness account. The state, treasury and slot carry over,
+    /// so players made in turn share one machine.
+    pub fn next_player(&self) -> Self {
+        let user = Pubkey::new_unique();
+        let mut player = zeroed_player();
+        init_player_state(&mut player, user, 255, None).expect("player initializes");
+        Game {
+            player,
+            user,
+            randomness: Randomness::default(),
+            wallet: GAME_WALLET,
+            ..self.clone()
+        }
+    }
+
+    pub fn advance(&mut self, slots: u64) {
+        self.slot += slots;
+        set_clock_slot(self.slot);
+    }
+
+    /// Run `step`; if it fails, nothing it did remains.
+    pub fn atomic<R>(&mut self, step: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        let before = self.clone();
+        let result = step(self);
+        if result.is_err() {
+            *self = before;
+        }
+        result
+    }
+
+    /// add_to_pool, without LP shares.
+    pub fn fund(&mut self, amount: u64) {
+        self.treasury += amount;
+        self.state.total_pool += amount;
+        self.state.total_deposits += amount;
+        refresh_bootstrap(&mut self.state);
+        pool_level::refresh(&mut self.state);
+    }
+
+    /// request_spin on one line, after the client seeds a fresh generation.
+    pub fn request(&mut self, bet_amount: u64) -> Result<()> {
+        self.request_lines(bet_amount, 1)
+    }
+
+    /// request_spin on `lines` paylines of `bet_amount` each.
+    pub fn request_lines(&mut self, bet_amount: u64, lines: u8) -> Result<()> {
+        let salt = self.player.spin_nonce as u8;
+        self.randomness.seed(self.slot, salt);
+        self.atomic(|game| {
+            let state = &mut game.state;
+            let player = &mut game.player;
+            require_sol_mode(state)?;
+            require!((1..=MAX_PAYLINES).contains(&lines), ErrorCode::InvalidLines);
+            let mut commit = game.randomness.with_info(|info| {
+                validate_commit(
+                    state,
+                    player,
+                    &game.user,
+                    game.treasury,
+                    info,
+                    game.randomness.key,
+                    bet_amount,
+                    None,
+                    None,
+                )
+            })?;
+            outcome_budget::ensure_commit(state, lines)?;
+            commit.lines = lines;
+            commit.outcomes = lines;
+            let stake = bet_amount
+                .checked_mul(lines as u64)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let user_stake = if scatter::fund(player, bet_amount, lines)?
+                || loyalty::fund(state, player, stake)?
+            {
+                0
+            } else {
+                stake
+            };
+            let charge = user_stake
+                .checked_add(oracle_cost::player_charge(state))
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(game.wallet >= charge, ErrorCode::InsufficientFunds);
+            game.wallet -= charge;
+            game.treasury += charge;
+            record_commit(
+                state,
+                player,
+                &game.user,
+                game.randomness.key,
+                stake,
+                commit,
+                0,
+            )
+        })
+    }
+
+    /// The oracle reveals `value` for the pending generation next slot.
+    pub fn reveal(&mut self, value: [u8; 32]) {
+        self.advance(1);
+        self.randomness.reveal(self.slot, value);
+    }
+
+    /// A reveal whose settle by the player satisfies `wanted`.
+    pub(crate) fn reveal_where(&self, wanted: impl Fn(&Settlement) -> bool) -> [u8; 32] {
+        (0..=u8::MAX)
+            .map(|byte| [byte; 32])
+            .find(|&value| {
+                let mut probe = self.clone();
+                probe.reveal(value);
+                wanted(&probe.settle().expect("probe settles"))
+            })
+            .expect("some reveal qualifies")
+    }
+
+    /// Commit `bet_amount`, reveal a value whose settle satisfies
+    /// `wanted`, and settle.
+    pub(crate) fn play_where(
+        &mut self,
+        bet_amount: u64,
+        wanted: impl Fn(&Settlement) -> bool,
+    ) -> Result<Settlement> {
+        self.request(bet_amount)?;
+        let value = self.reveal_where(wanted);
+        self.reveal(value);
+        self.settle()
+    }
+
+    /// settle_spin by the player, without the optional accounts.
+    pub(crate) fn settle(&mut self) -> Result<Settlement> {
+        self.settle_by(false)
+    }
+
+    /// settle_spin by a crank, without the optional accounts.
+    pub(crate) fn crank(&mut self) -> Result<Settlement> {
+        self.settle_by(true)
+    }
+
+    fn settle_by(&mut self, cranked: bool) -> Result<Settlement> {
+        self.atomic(|game| {
+            let state = &mut game.state;
+            let player = &mut game.player;
+            require_sol_mode(state)?;
+            if cranked {
+                settle_hooks::require_for_crank(state, &[])?;
+            }
+            let settlement = game.randomness.with_info(|info| {
+                settle_pending(state, player, &game.user, info, &None, 0, None)
+            })?;
+            record_wager_race_if_present(None, player, &settlement)?;
+            record_leaderboard(state, None, player, &settlement, cranked)?;
+            accrue_vip_rebate_if_present(state, player, None, &game.user, &settlement, cranked)?;
+            accrue_referral_if_present(state, player, None, &game.user, &settlement)?;
+            let owed = settlement.owed();
+            require!(owed <= game.treasury, ErrorCode::InsufficientPool);
+            game.treasury -= owed;
+            game.wallet += owed;
+            finish_settle(state, &settlement, &game.user, &None, &None, &[])?;
+            Ok(settlement)
+        })
+    }
+
+    /// admin_void_spin; returns the refund credited to the claimable
+    /// balance.
+    pub fn admin_void(&mut self) -> Result<u64> {
+        self.atomic(|game| {
+            let state = &mut game.state;
+            let player = &mut game.player;
+            require_sol_mode(state)?;
+            require!(player.has_pending_spin, ErrorCode::NoPendingSpin);
+            game.randomness
+                .with_info(|info| ensure_voidable(state, player, info))?;
+            let refund = void_pending_spin(state, player)?;
+            player.claimable_balance += refund;
+            state.total_claimable += refund;
+            Ok(refund)
+        })
+    }
+
+    /// cancel_spin; returns the refund paid to the wallet, zero for a
+    /// re-seeded spin whose stake was forfeit.
+    pub fn cancel(&mut self) -> Result<u64> {
+        self.atomic(|game| {
+            let state = &mut game.state;
+            let player = &mut game.player;
+            require_sol_mode(state)?;
+            require!(player.has_pending_spin, ErrorCode::NoPendingSpin);
+            require!(
+                time::is_expired(player.commit_slot, state.spin_expiry_slots, game.slot),
+                ErrorCode::SpinNotExpired
+            );
+            game.randomness
+                .with_info(|info| ensure_voidable(state, player, info))?;
+            if !game
+                .randomness
+                .with_info(|info| holds_committed_generation(player, info))
+            {
+                forfeit_pending_spin(state, player)?;
+                return Ok(0);
+            }
+            let refund = void_pending_spin(state, player)?;
+            if refund > 0 {
+                require!(refund <= game.treasury, ErrorCode::InsufficientPool);
+                game.treasury -= refund;
+                game.wallet += refund;
+                outflows::book(state, OutflowCategory::Refund, refund, game.user, [0; 32])?;
+            }
+            pool_level::refresh(state);
+            Ok(refund)
+        })
+    }
+
+    /// accounting::verify against the modelled treasury.
+    pub fn verify(&self) -> Result<AccountingReport> {
+        accounting::verify(&self.state, self.treasury, None)
+    }
+
+    /// The machine state, player state and treasury as a transaction
+    /// passes them.
+    pub fn fixtures(&self) -> [Fixture; 3] {
+        let machine = machine_seed(&self.state.machine_id);
+        let (treasury, _) = pda(&[TREASURY_SEED, machine]);
+        let (player_key, player_bump) = pda(&[PLAYER_SEED, machine, self.user.as_ref()]);
+        let mut state = self.state.clone();
+        state.treasury = treasury;
+        let mut player = self.player.clone();
+        player.bump = player_bump;
+        let rent = Rent::default().minimum_balance(0);
+        [
+            Fixture::account(Pubkey::new_unique(), &state),
+            Fixture::account(player_key, &player),
+            Fixture::wallet(treasury, self.treasury + rent),
+        ]
+    }
+
+    /// The machine authority, signing.
+    pub fn authority(&self) -> Fixture {
+        Fixture::wallet(self.state.authority, GAME_WALLET).signer()
+    }
+
+    /// request_spin's accounts for a commit on the seeded randomness,
+    /// every optional one left out.
+    pub fn request_spin_fixtures(&self) -> Vec<Fixture> {
+        let [state, player, treasury] = self.fixtures();
+        vec![
+            state,
+            player,
+            Fixture::wallet(self.user, self.wallet).signer(),
+            treasury,
+            Fixture {
+                writable: true,
+                ..self.randomness.fixture()
+            },
+            Fixture::omitted(),
+            Fixture::omitted(),
+            Fixture::program(anchor_lang::system_program::ID),
+        ]
+    }
+
+    /// request_spin's instruction data for one line of `bet_amount`.
+    pub fn request_spin_data(&self, bet_amount: u64) -> Vec<u8> {
+        crate::instruction::RequestSpin {
+            randomness_account: self.randomness.key,
+            bet_amount,
+            lines: 1,
+        }
+        .data()
+    }
+
+    /// settle_spin's accounts for the revealed spin, submitted by the
+    /// player, every optional one left out.
+    pub fn settle_spin_fixtures(&self) -> Vec<Fixture> {
+        let [state, player, treasury] = self.fixtures();
+        let user = Fixture::wallet(self.user, self.wallet).signer();
+        let mut accounts = vec![
+            state,
+            player,
+            user.clone(),
+            user,
+            treasury,
+            Fixture {
+                writable: true,
+                ..self.randomness.fixture()
+            },
+        ];
+        accounts.extend(std::iter::repeat_with(Fixture::omitted).take(8));
+        accounts.push(Fixture::program(anchor_lang::system_program::ID));
+        accounts
+    }
+}
@@ -0,0 +1,295 @@
+//! Per-player limit tiers (VIP levels).
+//!
+//! Tier 0 is the public tier and uses the flat limits (`max_bet`,
+//! `max_payout_per_spin`). Tiers 1 to [`MAX_PLAYER_TIERS`] look up a row
+//! of `SlotsState.tier_limits` that scales the bet cap and the payout cap
+//! and can exclude jackpot tiers. `min_bet` applies to every tier.
+//!
+//! Precedence when resolving a payout cap, tightest wins:
+//! 1. pool headroom above the required floor (applied by the settle math);
+//! 2. the bootstrap cap while bootstrapping, which tiers never lift;
+//! 3. the tier-scaled `max_payout_per_spin`.
+//!
+//! Terms at commit: the payout cap (3, or 2 while bootstrapping) and the
+//! jackpot mask are snapshotted into the PlayerState when a spin is
+//! committed, and its settle uses the snapshot. Lowering the limits or
+//! moving the player to another tier mid-flight therefore only affects
+//! spins committed afterwards. Pool headroom (1) is always read live.
+
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, JackpotsConfig, PayTable, PlayerState, SlotsState};
+
+/// Number of non-public tiers.
+pub const MAX_PLAYER_TIERS: usize = 4;
+/// Multiplier ceiling: 100x the flat limits.
+pub const MAX_TIER_MULTIPLIER_BPS: u32 = 1_000_000;
+/// Bit per jackpot tier (mini, major, grand) in `jackpot_mask`.
+pub const ALL_JACKPOTS_MASK: u8 = 0b111;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct TierLimits {
+    /// Bet cap as a multiple of max_bet, bps (10_000 = 1x).
+    pub max_bet_multiplier_bps: u32,
+    /// Payout cap as a multiple of max_payout_per_spin, bps.
+    pub max_payout_multiplier_bps: u32,
+    /// Jackpot tiers this player tier may win; bit 0 = mini.
+    pub jackpot_mask: u8,
+}
+
+impl Default for TierLimits {
+    fn default() -> Self {
+        TierLimits {
+            max_bet_multiplier_bps: 10_000,
+            max_payout_multiplier_bps: 10_000,
+            jackpot_mask: ALL_JACKPOTS_MASK,
+        }
+    }
+}
+
+pub fn validate_limits(limits: &TierLimits) -> Result<()> {
+    let in_range = |bps: u32| (1..=MAX_TIER_MULTIPLIER_BPS).contains(&bps);
+    require!(
+        in_range(limits.max_bet_multiplier_bps)
+            && in_range(limits.max_payout_multiplier_bps)
+            && limits.jackpot_mask <= ALL_JACKPOTS_MASK,
+        ErrorCode::InvalidTierLimits
+    );
+    Ok(())
+}
+
+/// Limits row for `tier`, or None for the public tier.
+pub fn limits_for(state: &SlotsState, tier: u8) -> Option<TierLimits> {
+    match tier {
+        0 => None,
+        t => state.tier_limits.get(t as usize - 1).copied(),
+    }
+}
+
+fn scale(amount: u64, bps: u32) -> u64 {
+    ((amount as u128) * (bps as u128) / 10_000).min(u64::MAX as u128) as u64
+}
+
+/// Largest bet the player may commit.
+pub fn bet_cap(state: &SlotsState, tier: u8) -> u64 {
+    match limits_for(state, tier) {
+        None => state.max_bet,
+        Some(l) => scale(state.max_bet, l.max_bet_multiplier_bps),
+    }
+}
+
+/// Whether the top paytable win on a `max_bet` bet fits within
+/// max_payout_per_spin, so the flat payout cap never has to cut a
+/// public-tier win.
+pub fn max_bet_covered(state: &SlotsState, max_bet: u64) -> bool {
+    max_win_covered(state, &state.paytable, max_bet)
+}
+
+/// max_bet_covered for a candidate paytable.
+pub fn max_win_covered(state: &SlotsState, paytable: &PayTable, max_bet: u64) -> bool {
+    (max_bet as u128) * (paytable.max_multiplier() as u128) <= state.max_payout_per_spin as u128
+}
+
+/// Per-spin payout cap for the player, before pool headroom.
+pub fn payout_cap(state: &SlotsState, tier: u8) -> u64 {
+    let flat = crate::effective_max_payout(state);
+    match limits_for(state, tier) {
+        Some(l) if !state.bootstrap_active => {
+            scale(state.max_payout_per_spin, l.max_payout_multiplier_bps)
+        }
+        _ => flat,
+    }
+}
+
+/// Jackpot tiers the player may win.
+pub fn jackpot_mask(state: &SlotsState, tier: u8) -> u8 {
+    limits_for(state, tier).map_or(ALL_JACKPOTS_MASK, |l| l.jackpot_mask)
+}
+
+/// Jackpots as seen by the player's spin: excluded tiers read as empty,
+/// so the roll is consumed exactly as usual but cannot award them.
+pub fn jackpots_for(state: &SlotsState, tier: u8) -> JackpotsConfig {
+    masked_jackpots(state, jackpot_mask(state, tier))
+}
+
+pub fn masked_jackpots(state: &SlotsState, mask: u8) -> JackpotsConfig {
+    let mut jackpots = state.jackpots.clone();
+    for (bit, pool) in [&mut jackpots.mini, &mut jackpots.major, &mut jackpots.grand]
+        .into_iter()
+        .enumerate()
+    {
+        if mask & (1 << bit) == 0 {
+            pool.amount = 0;
+        }
+    }
+    jackpots
+}
+
+/// Jackpot odds the pending spin was committed under; spins committed
+/// before the snapshot existed get full odds.
+pub fn committed_jackpot_odds(player: &PlayerState) -> u64 {
+    match player.commit_jackpot_odds {
+        0 => crate::game_math::JACKPOT_ODDS_ONE,
+        odds => odds,
+    }
+}
+
+/// Payout cap and jackpots the pending spin was committed under. Spins
+/// committed before the snapshot existed (cap 0) get the live terms.
+pub fn committed_terms(state: &SlotsState, player: &PlayerState) -> (u64, JackpotsConfig) {
+    if player.commit_payout_cap == 0 {
+        return (payout_cap(state, player.tier), jackpots_for(state, player.tier));
+    }
+    (
+        player.commit_payout_cap,
+        masked_jackpots(state, player.commit_jackpot_mask),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::test_util::{program_error, simulate, Fixture, Game};
+    use crate::{instruction, refresh_bootstrap};
+
+    const TRIPLE: TierLimits = TierLimits {
+        max_bet_multiplier_bps: 30_000,
+        max_payout_multiplier_bps: 30_000,
+        jackpot_mask: 0b001,
+    };
+
+    /// A game whose player is on tier 1 with `limits`.
+    fn vip(limits: TierLimits) -> Game {
+        let mut game = Game::new();
+        game.state.tier_limits[0] = limits;
+        game.player.tier = 1;
+        game
+    }
+
+    #[test]
+    fn limits_are_bounded() {
+        validate_limits(&TierLimits::default()).unwrap();
+        let top = TierLimits {
+            max_bet_multiplier_bps: MAX_TIER_MULTIPLIER_BPS,
+            max_payout_multiplier_bps: MAX_TIER_MULTIPLIER_BPS,
+            jackpot_mask: 0,
+        };
+        validate_limits(&top).unwrap();
+        for bad in [
+            TierLimits { max_bet_multiplier_bps: 0, ..top },
+            TierLimits { max_payout_multiplier_bps: 0, ..top },
+            TierLimits { max_bet_multiplier_bps: MAX_TIER_MULTIPLIER_BPS + 1, ..top },
+            TierLimits { max_payout_multiplier_bps: MAX_TIER_MULTIPLIER_BPS + 1, ..top },
+            TierLimits { jackpot_mask: 0b1000, ..top },
+        ] {
+            assert_eq!(
+                validate_limits(&bad).unwrap_err(),
+                ErrorCode::InvalidTierLimits.into()
+            );
+        }
+        // Scaling saturates rather than wrapping.
+        assert_eq!(scale(u64::MAX, MAX_TIER_MULTIPLIER_BPS), u64::MAX);
+    }
+
+    #[test]
+    fn a_tier_bets_above_the_public_cap() {
+        let mut game = vip(TRIPLE);
+        let bet = 3 * game.state.max_bet;
+        assert_eq!(bet_cap(&game.state, 1), bet);
+        assert_eq!(payout_cap(&game.state, 1), 3 * game.state.max_payout_per_spin);
+
+        let mut public = game.clone();
+        public.player.tier = 0;
+        assert_eq!(public.request(bet).unwrap_err(), ErrorCode::BetTooHigh.into());
+        assert_eq!(game.request(bet + 1).unwrap_err(), ErrorCode::BetTooHigh.into());
+
+        game.request(bet).unwrap();
+        assert_eq!(game.player.commit_payout_cap, 3 * game.state.max_payout_per_spin);
+        assert_eq!(game.player.commit_jackpot_mask, 0b001);
+        let (_, jackpots) = committed_terms(&game.state, &game.player);
+        assert!(jackpots.mini.amount > 0);
+        assert_eq!((jackpots.major.amount, jackpots.grand.amount), (0, 0));
+    }
+
+    #[test]
+    fn a_tier_cap_still_needs_the_pool_to_cover_it() {
+        let mut game = vip(TierLimits {
+            max_payout_multiplier_bps: MAX_TIER_MULTIPLIER_BPS,
+            ..TRIPLE
+        });
+        let bet = game.state.max_bet;
+        assert_eq!(game.request(bet).unwrap_err(), ErrorCode::InsufficientPool.into());
+
+        game.player.tier = 0;
+        game.request(bet).unwrap();
+    }
+
+    #[test]
+    fn bootstrap_caps_every_tier() {
+        let mut game = vip(TRIPLE);
+        game.state.bootstrap_maturity_threshold = u64::MAX;
+        refresh_bootstrap(&mut game.state);
+        let flat = crate::effective_max_payout(&game.state);
+        assert!(flat < game.state.max_payout_per_spin);
+        assert_eq!(payout_cap(&game.state, 1), flat);
+        assert_eq!(payout_cap(&game.state, 0), flat);
+    }
+
+    #[test]
+    fn a_committed_spin_keeps_its_tier_terms() {
+        let mut game = vip(TRIPLE);
+        game.request(game.state.max_bet).unwrap();
+        game.player.tier = 0;
+        game.state.tier_limits[0] = TierLimits::default();
+
+        let (cap, jackpots) = committed_terms(&game.state, &game.player);
+        assert_eq!(cap, 3 * game.state.max_payout_per_spin);
+        assert_eq!(jackpots.grand.amount, 0);
+    }
+
+    #[test]
+    fn only_the_authority_assigns_tiers_and_their_limits() {
+        let game = Game::new();
+        let [mut state, mut player, _] = game.fixtures();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), 1).signer();
+        let mut authority = game.authority();
+        let mut no_metrics = Fixture::omitted();
+        let user = game.user;
+        let assign = |tier| instruction::SetPlayerTier { player: user, tier }.data();
+        let limit = |tier, limits| instruction::SetTierLimits { tier, limits }.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut player], &assign(1)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut player], &assign(5)),
+            Err(program_error(ErrorCode::InvalidPlayerTier))
+        );
+        simulate([&mut state, &mut authority, &mut player], &assign(4)).unwrap();
+        assert_eq!(player.read::<PlayerState>().tier, 4);
+
+        assert_eq!(
+            simulate([&mut state, &mut stranger, &mut no_metrics], &limit(1, TRIPLE)),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        for tier in [0, 5] {
+            assert_eq!(
+                simulate([&mut state, &mut authority, &mut no_metrics], &limit(tier, TRIPLE)),
+                Err(program_error(ErrorCode::InvalidPlayerTier))
+            );
+        }
+        let zero = TierLimits {
+            max_bet_multiplier_bps: 0,
+            ..TRIPLE
+        };
+        assert_eq!(
+            simulate([&mut state, &mut authority, &mut no_metrics], &limit(4, zero)),
+            Err(program_error(ErrorCode::InvalidTierLimits))
+        );
+        simulate([&mut state, &mut authority, &mut no_metrics], &limit(4, TRIPLE)).unwrap();
+        assert_eq!(state.read::<SlotsState>().tier_limits[3], TRIPLE);
+    }
+}
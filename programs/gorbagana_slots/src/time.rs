@@ -0,0 +1,116 @@
+//! Clock handling shared by every time-dependent feature.
+//!
+//! Slots are the only clock used for anything security-relevant: TTLs,
+//! cooldowns, timelocks, randomness freshness and scheduled actions. They
+//! come from consensus and never go backwards on a given fork.
+//!
+//! `unix_timestamp` is a stake-weighted estimate that can lag, drift from
+//! the slot rate and briefly step backwards across leaders. It is used
+//! only for reporting and allowance buckets (hourly RTP, daily rebate
+//! caps, traffic decay), and every helper here treats a backwards step as
+//! "no time passed" rather than letting a bucket or a window move back.
+
+pub const SECONDS_PER_HOUR: i64 = 3_600;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Bucket index of `ts` for buckets of `bucket_secs` seconds. Timestamps
+/// before the epoch clamp into bucket 0.
+pub fn bucket_for_timestamp(ts: i64, bucket_secs: i64) -> i64 {
+    if bucket_secs <= 0 {
+        return 0;
+    }
+    ts.max(0) / bucket_secs
+}
+
+/// Bucket to store after observing `ts`: never earlier than `stored`, so a
+/// timestamp stepping back across a boundary cannot reopen a closed bucket.
+/// Returns (bucket, advanced).
+pub fn advance_bucket(stored: i64, ts: i64, bucket_secs: i64) -> (i64, bool) {
+    let observed = bucket_for_timestamp(ts, bucket_secs);
+    if observed > stored {
+        (observed, true)
+    } else {
+        (stored, false)
+    }
+}
+
+/// Seconds from `since` to `now`; zero if the clock moved backwards.
+pub fn secs_elapsed_saturating(since: i64, now: i64) -> u64 {
+    now.saturating_sub(since).max(0) as u64
+}
+
+/// Slots from `since` to `now`; zero if `now` is behind `since`.
+pub fn slots_elapsed_saturating(since: u64, now: u64) -> u64 {
+    now.saturating_sub(since)
+}
+
+/// Whether `now` has reached the slot `deadline`.
+pub fn has_reached(deadline: u64, now: u64) -> bool {
+    now >= deadline
+}
+
+/// Whether a window of `ttl` slots opened at `since` has run out by `now`.
+pub fn is_expired(since: u64, ttl: u64, now: u64) -> bool {
+    has_reached(since.saturating_add(ttl), now)
+}
+
+/// Whether `observed` is no later than `now` and at most `max_age` slots
+/// old. A slot from the future is never fresh.
+pub fn is_fresh(observed: u64, now: u64, max_age: u64) -> bool {
+    observed <= now && slots_elapsed_saturating(observed, now) <= max_age
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_deadline_is_reached_on_its_own_slot() {
+        assert!(!has_reached(100, 99));
+        assert!(has_reached(100, 100));
+        assert!(has_reached(100, 101));
+    }
+
+    #[test]
+    fn a_window_expires_on_its_last_slot_plus_one() {
+        assert!(!is_expired(100, 10, 109));
+        assert!(is_expired(100, 10, 110));
+        assert!(is_expired(100, 10, 111));
+        assert!(is_expired(100, 0, 100));
+    }
+
+    #[test]
+    fn a_window_near_the_end_of_the_slot_range_saturates() {
+        // A wrapped deadline would be 4 and expire at once.
+        assert!(!is_expired(u64::MAX - 5, 10, 100));
+        assert!(!is_expired(u64::MAX - 5, 10, u64::MAX - 1));
+        assert!(is_expired(u64::MAX - 5, 10, u64::MAX));
+        assert!(has_reached(u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn freshness_is_bounded_on_both_sides() {
+        assert!(is_fresh(100, 100, 10));
+        assert!(is_fresh(90, 100, 10));
+        assert!(!is_fresh(89, 100, 10));
+        assert!(!is_fresh(101, 100, 10));
+        assert!(is_fresh(0, u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn elapsed_time_never_goes_negative() {
+        assert_eq!(slots_elapsed_saturating(10, 5), 0);
+        assert_eq!(slots_elapsed_saturating(5, 10), 5);
+        assert_eq!(secs_elapsed_saturating(10, 5), 0);
+        assert_eq!(secs_elapsed_saturating(i64::MIN, i64::MAX), i64::MAX as u64);
+    }
+
+    #[test]
+    fn a_bucket_never_moves_back() {
+        assert_eq!(bucket_for_timestamp(-1, SECONDS_PER_HOUR), 0);
+        assert_eq!(bucket_for_timestamp(SECONDS_PER_HOUR, SECONDS_PER_HOUR), 1);
+        assert_eq!(bucket_for_timestamp(5, 0), 0);
+        assert_eq!(advance_bucket(3, 2 * SECONDS_PER_HOUR, SECONDS_PER_HOUR), (3, false));
+        assert_eq!(advance_bucket(3, 4 * SECONDS_PER_HOUR, SECONDS_PER_HOUR), (4, true));
+    }
+}
@@ -0,0 +1,427 @@
+//! SPL token betting.
+//!
+//! A deployment initialized with `exclusive_mint` bets in that token
+//! instead of lamports. The mint is fixed at initialize. After that,
+//! init_token_treasury creates the treasury PDA's associated token account
+//! and records the mint's decimals, so bet limits, pool thresholds and
+//! payouts are all read in the token's base units.
+//!
+//! The token instructions (request_spin_token, settle_spin_token,
+//! add_to_pool_token, execute_withdrawal_token, withdraw_claimable_token)
+//! reuse the SOL accounting and only swap the transfers. The SOL
+//! instructions refuse a token deployment and the token ones refuse a SOL
+//! deployment (WrongCurrencyMode), so a bet can never be settled against
+//! the other treasury. The oracle cost stays in lamports and still goes
+//! through the SOL treasury; when the house absorbs it, the fee balance is
+//! not moved into the token pool.
+//!
+//! Only the pool and the claimables are in tokens (see accounting.rs).
+//! LP shares (pool_shares.rs) are SOL only: add_to_pool_token is the
+//! authority funding the pool and mints no position. The cuts that would
+//! book part of a bet into a lamport bucket stay off: set_operator_fee,
+//! set_community_bps, set_referral_bps, set_vip_config, set_winner_rebate
+//! and set_fee_overflow_policy refuse a token deployment, and so do the
+//! instructions paying those buckets out. verify_accounting and sync_pool
+//! take the token treasury and check the pool against it.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::machines::machine_seed;
+use crate::{ErrorCode, SlotsState, TREASURY_SEED};
+
+/// Require a token deployment for `mint` with its treasury account set up.
+pub fn require_token_mode(state: &SlotsState, mint: &Pubkey) -> Result<()> {
+    require!(
+        state.exclusive_mint() == Some(*mint),
+        ErrorCode::WrongCurrencyMode
+    );
+    require!(
+        state.token_treasury != Pubkey::default(),
+        ErrorCode::TokenTreasuryMissing
+    );
+    Ok(())
+}
+
+/// Move tokens from a signer's account into the token treasury.
+pub fn transfer_to_treasury<'info>(
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    treasury_token: &Account<'info, TokenAccount>,
+    owner: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let accounts = Transfer {
+        from: from.to_account_info(),
+        to: treasury_token.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    token::transfer(CpiContext::new(token_program.to_account_info(), accounts), amount)
+}
+
+/// Move tokens out of the token treasury, signing as the treasury PDA.
+pub fn transfer_from_treasury<'info>(
+    token_program: &Program<'info, Token>,
+    treasury_token: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    treasury: &SystemAccount<'info>,
+    machine_id: &[u8; 32],
+    treasury_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let accounts = Transfer {
+        from: treasury_token.to_account_info(),
+        to: to.to_account_info(),
+        authority: treasury.to_account_info(),
+    };
+    let signer_seeds: &[&[u8]] = &[TREASURY_SEED, machine_seed(machine_id), &[treasury_bump]];
+    token::transfer(
+        CpiContext::new(token_program.to_account_info(), accounts).with_signer(&[signer_seeds]),
+        amount,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::oracle_cost::OracleCostMode;
+    use crate::outflows::OutflowCategory;
+    use crate::test_util::{
+        program_error, set_clock_slot, simulate, zeroed_player, zeroed_state, Fixture, Game,
+        Randomness,
+    };
+    use crate::*;
+
+    const OPENING_POOL: u64 = 20_000_000_000;
+    const TREASURY_RENT: u64 = 1_000_000;
+    const ORACLE_COST: u64 = 5_000;
+    /// Within the authority's extraction budget.
+    const WITHDRAWAL: u64 = OPENING_POOL / 100;
+
+    /// A token deployment with one player, its token and lamport balances
+    /// modelled next to the state. Each step runs its handler's body with
+    /// the transfers replaced by moves between the balances.
+    struct Deployment {
+        state: SlotsState,
+        player: PlayerState,
+        user: Pubkey,
+        randomness: Randomness,
+        slot: u64,
+        treasury_tokens: u64,
+        treasury_lamports: u64,
+        user_tokens: u64,
+        authority_tokens: u64,
+    }
+
+    impl Deployment {
+        fn new() -> Self {
+            let slot = 1_000;
+            set_clock_slot(slot);
+            let authority = Pubkey::new_unique();
+            let mut state = zeroed_state();
+            init_slots_state(
+                &mut state,
+                authority,
+                Some(Pubkey::new_unique()),
+                None,
+                None,
+                Pubkey::new_unique(),
+            )
+            .expect("state initializes");
+            // init_token_treasury.
+            state.token_treasury = Pubkey::new_unique();
+            state.token_decimals = 6;
+
+            let user = Pubkey::new_unique();
+            let mut player = zeroed_player();
+            init_player_state(&mut player, user, 255, None).expect("player initializes");
+
+            let mut deployment = Deployment {
+                state,
+                player,
+                user,
+                randomness: Randomness::default(),
+                slot,
+                treasury_tokens: 0,
+                treasury_lamports: TREASURY_RENT,
+                user_tokens: 1_000_000_000,
+                authority_tokens: 0,
+            };
+            deployment.fund(OPENING_POOL);
+            deployment
+        }
+
+        fn advance(&mut self, slots: u64) {
+            self.slot += slots;
+            set_clock_slot(self.slot);
+        }
+
+        /// add_to_pool_token.
+        fn fund(&mut self, amount: u64) {
+            let state = &mut self.state;
+            require_token_mode(state, &state.exclusive_mint).expect("token mode");
+            self.treasury_tokens += amount;
+            state.total_pool += amount;
+            state.total_deposits += amount;
+            refresh_bootstrap(state);
+            pool_level::refresh(state);
+        }
+
+        /// request_spin_token, after the client seeds a fresh generation.
+        fn request(&mut self, bet_amount: u64) -> Result<()> {
+            let state = &mut self.state;
+            self.randomness.seed(self.slot, 7);
+            require_token_mode(state, &state.exclusive_mint)?;
+            let commit = self.randomness.with_info(|info| {
+                validate_commit(
+                    state,
+                    &self.player,
+                    &self.user,
+                    self.treasury_tokens,
+                    info,
+                    self.randomness.key,
+                    bet_amount,
+                    None,
+                    None,
+                )
+            })?;
+            require!(self.user_tokens >= bet_amount, ErrorCode::InsufficientFunds);
+            self.treasury_lamports += oracle_cost::player_charge(state);
+            self.user_tokens -= bet_amount;
+            self.treasury_tokens += bet_amount;
+            record_commit(
+                state,
+                &mut self.player,
+                &self.user,
+                self.randomness.key,
+                bet_amount,
+                commit,
+                0,
+            )
+        }
+
+        /// settle_spin_token once the oracle has revealed `value`.
+        fn settle(&mut self, value: [u8; 32]) -> Result<Settlement> {
+            self.advance(1);
+            self.randomness.reveal(self.slot, value);
+            let state = &mut self.state;
+            require_token_mode(state, &state.exclusive_mint)?;
+            let settlement = self.randomness.with_info(|info| {
+                settle_pending(state, &mut self.player, &self.user, info, &None, 0, None)
+            })?;
+            let owed = settlement.owed();
+            assert!(owed <= self.treasury_tokens, "token treasury short");
+            self.treasury_tokens -= owed;
+            self.user_tokens += owed;
+            finish_settle(state, &settlement, &self.user, &None, &None, &[])?;
+            Ok(settlement)
+        }
+
+        /// request_withdrawal, then execute_withdrawal_token once matured.
+        fn withdraw(&mut self, amount: u64) -> Result<()> {
+            let state = &mut self.state;
+            let available = state.total_pool.saturating_sub(required_floor(state)?);
+            require!(amount <= available, ErrorCode::InsufficientPool);
+            withdrawal_timelock::request(
+                state,
+                amount,
+                OutflowCategory::OperatorProfit,
+                [0; 32],
+                self.slot,
+            )?;
+            let delay = state.withdrawal_delay_slots;
+            self.advance(delay);
+
+            let state = &mut self.state;
+            require_token_mode(state, &state.exclusive_mint)?;
+            let spendable = self
+                .treasury_tokens
+                .saturating_sub(accounting::other_buckets(state)?);
+            let matured = withdrawal_timelock::execute(state, self.slot, spendable)?;
+            let amount = matured.amount;
+            self.treasury_tokens -= amount;
+            self.authority_tokens += amount;
+            outflows::book(state, matured.category, amount, state.authority, matured.memo)
+        }
+
+        fn verify(&self) -> Result<accounting::AccountingReport> {
+            accounting::verify(&self.state, self.treasury_lamports, Some(self.treasury_tokens))
+        }
+    }
+
+    #[test]
+    fn a_token_spin_moves_tokens_and_books_the_oracle_cost_in_lamports() {
+        let mut deployment = Deployment::new();
+        deployment.state.oracle_cost_lamports = ORACLE_COST;
+        deployment.state.oracle_cost_mode = OracleCostMode::PlayerPays;
+        let bet = DEFAULT_MIN_BET;
+
+        deployment.request(bet).expect("commit accepted");
+        assert_eq!(deployment.treasury_tokens, OPENING_POOL + bet);
+        assert_eq!(deployment.state.total_pool, OPENING_POOL + bet);
+        assert_eq!(deployment.state.fee_balance, ORACLE_COST);
+        assert_eq!(deployment.treasury_lamports, TREASURY_RENT + ORACLE_COST);
+        deployment.verify().expect("books hold after the commit");
+
+        let settlement = deployment.settle([3; 32]).expect("settles");
+        assert!(!deployment.player.has_pending_spin);
+        assert_eq!(
+            deployment.treasury_tokens,
+            OPENING_POOL + bet - settlement.owed()
+        );
+        assert_eq!(deployment.state.total_pool, deployment.treasury_tokens);
+        assert_eq!(deployment.state.fee_balance, ORACLE_COST);
+        let report = deployment.verify().expect("books hold after the settle");
+        assert_eq!(report.treasury_tokens, deployment.treasury_tokens);
+    }
+
+    #[test]
+    fn a_rotated_token_spin_is_refused_and_keeps_the_stake() {
+        let mut deployment = Deployment::new();
+        let before = deployment.user_tokens;
+        deployment.request(DEFAULT_MIN_BET).expect("commit accepted");
+        deployment.advance(1);
+        deployment.randomness.seed(deployment.slot, 9);
+
+        let err = deployment.settle([5; 32]).unwrap_err();
+        assert_eq!(err, ErrorCode::RandomnessSeedChanged.into());
+        assert!(deployment.player.has_pending_spin);
+        assert_eq!(deployment.user_tokens, before - DEFAULT_MIN_BET);
+        assert_eq!(deployment.treasury_tokens, OPENING_POOL + DEFAULT_MIN_BET);
+        deployment.verify().expect("books hold");
+    }
+
+    #[test]
+    fn house_absorbed_oracle_cost_stays_out_of_a_token_pool() {
+        let mut deployment = Deployment::new();
+        deployment.state.oracle_cost_lamports = ORACLE_COST;
+        deployment.state.oracle_cost_mode = OracleCostMode::HouseAbsorbs;
+        deployment.state.fee_balance = ORACLE_COST;
+        deployment.treasury_lamports += ORACLE_COST;
+
+        deployment.request(DEFAULT_MIN_BET).expect("commit accepted");
+        assert_eq!(deployment.state.total_pool, OPENING_POOL + DEFAULT_MIN_BET);
+        assert_eq!(deployment.state.fee_balance, ORACLE_COST);
+        deployment.verify().expect("books hold");
+    }
+
+    #[test]
+    fn a_token_withdrawal_pays_the_authority_out_of_the_pool() {
+        let mut deployment = Deployment::new();
+        let amount = WITHDRAWAL;
+        deployment.withdraw(amount).expect("withdrawal executes");
+        assert_eq!(deployment.authority_tokens, amount);
+        assert_eq!(deployment.state.total_pool, OPENING_POOL - amount);
+        assert_eq!(deployment.treasury_tokens, OPENING_POOL - amount);
+        deployment.verify().expect("books hold after the withdrawal");
+    }
+
+    #[test]
+    fn a_token_withdrawal_leaves_the_claimables_in_the_treasury() {
+        let mut deployment = Deployment::new();
+        // A wager race prize credited out of the pool.
+        let prize = OPENING_POOL / 2;
+        deployment.state.total_pool -= prize;
+        deployment.state.total_claimable = prize;
+        deployment.verify().expect("the prize is still held");
+
+        // The pool's books allow the withdrawal, but the token treasury
+        // only holds it by counting the tokens owed to the claimables.
+        deployment.treasury_tokens = prize + WITHDRAWAL - 1;
+        let err = deployment.withdraw(WITHDRAWAL).unwrap_err();
+        assert_eq!(err, ErrorCode::InsufficientPool.into());
+        assert_eq!(deployment.authority_tokens, 0);
+    }
+
+    #[test]
+    fn verify_holds_a_token_pool_to_the_token_treasury() {
+        let mut deployment = Deployment::new();
+        deployment.verify().expect("books hold");
+
+        let err = accounting::verify(&deployment.state, u64::MAX, None).unwrap_err();
+        assert_eq!(err, ErrorCode::TokenTreasuryMissing.into());
+
+        // Lamports cannot stand in for missing tokens.
+        deployment.treasury_tokens -= 1;
+        deployment.treasury_lamports = u64::MAX;
+        let err = deployment.verify().unwrap_err();
+        assert_eq!(err, ErrorCode::AccountingInvariantViolated.into());
+        deployment.treasury_tokens += 1;
+
+        // Nor tokens for the lamport buckets.
+        deployment.state.fee_balance = ORACLE_COST;
+        deployment.treasury_lamports = ORACLE_COST - 1;
+        let err = deployment.verify().unwrap_err();
+        assert_eq!(err, ErrorCode::AccountingInvariantViolated.into());
+    }
+
+    #[test]
+    fn sync_pool_reads_the_token_treasury_less_the_claimables() {
+        let mut deployment = Deployment::new();
+        deployment.state.total_claimable = 1_000;
+        deployment.state.fee_balance = ORACLE_COST;
+        // Tokens sent straight to the treasury.
+        let balance = deployment.treasury_tokens + 5_000;
+
+        let pool = accounting::sync_pool(&mut deployment.state, balance).expect("syncs");
+        assert_eq!(pool, balance - 1_000);
+        assert_eq!(accounting::lamport_buckets(&deployment.state), Ok(ORACLE_COST));
+    }
+
+    #[test]
+    fn the_sol_instructions_are_dead_in_a_token_deployment() {
+        let mut game = Game::new();
+        game.request(DEFAULT_MIN_BET).unwrap();
+        game.reveal([3; 32]);
+        game.player.claimable_balance = 1_000;
+        game.state.total_claimable = 1_000;
+        let mint = Pubkey::new_unique();
+        game.state.exclusive_mint = mint;
+        assert_eq!(game.state.exclusive_mint(), Some(mint));
+        let dead = Err(program_error(ErrorCode::WrongCurrencyMode));
+
+        let mut accounts = game.request_spin_fixtures();
+        let data = game.request_spin_data(DEFAULT_MIN_BET);
+        assert_eq!(simulate(&mut accounts, &data), dead);
+
+        let mut accounts = game.settle_spin_fixtures();
+        assert_eq!(simulate(&mut accounts, &instruction::SettleSpin {}.data()), dead);
+
+        let [mut state, mut player, mut treasury] = game.fixtures();
+        let mut user = Fixture::wallet(game.user, game.wallet).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let withdraw = instruction::WithdrawClaimable {}.data();
+        let accounts = [&mut state, &mut player, &mut user, &mut treasury, &mut system];
+        assert_eq!(simulate(accounts, &withdraw), dead);
+
+        let boost = instruction::BoostJackpot { tier: 2, amount: 1_000 }.data();
+        assert_eq!(
+            simulate([&mut state, &mut user, &mut treasury, &mut system], &boost),
+            dead
+        );
+        let err = game.request(DEFAULT_MIN_BET).unwrap_err();
+        assert_eq!(err, ErrorCode::WrongCurrencyMode.into());
+    }
+
+    #[test]
+    fn the_mint_is_chosen_at_genesis() {
+        let deployment = Deployment::new();
+        assert!(deployment.state.exclusive_mint().is_some());
+        assert_eq!(Game::new().state.exclusive_mint(), None);
+
+        // A token deployment's token instructions refuse any other mint.
+        let err = require_token_mode(&deployment.state, &Pubkey::new_unique()).unwrap_err();
+        assert_eq!(err, ErrorCode::WrongCurrencyMode.into());
+        let sol = Game::new().state;
+        let err = require_token_mode(&sol, &sol.exclusive_mint).unwrap_err();
+        assert_eq!(err, ErrorCode::WrongCurrencyMode.into());
+    }
+}
@@ -0,0 +1,359 @@
+//! Canonical outcome test vectors for cross-implementation verification.
+//!
+//! Each vector pins the inputs settle_spin feeds into
+//! `game_math::derive_outcome` (VRF bytes, bet, affordable cap, jackpot
+//! amounts/weights) together with the exact outcome and the `vrf_hash`
+//! SpinSettled publishes for the reveal, on the default `REEL_WEIGHTS`
+//! and `PAYTABLE`. `wild_symbol` is NO_WILD (255) except in the `wild_*`
+//! vectors, which use [`VECTOR_WILD`]. The committed file
+//! `vectors/outcomes.jsonl` is the contract other verifiers (TypeScript,
+//! auditor scripts) test against: one compact JSON object per line, field
+//! order as declared below.
+//!
+//! Regenerating with [`generate`] + [`to_jsonl`] must reproduce the
+//! committed file byte-for-byte. If it doesn't, outcome derivation changed
+//! and the file has to be regenerated deliberately in the same change.
+//!
+//! Combinations the PRNG cannot produce are omitted rather than faked. In
+//! particular symbol 0 never lands on two consecutive reels: the LCG flips
+//! parity every step and symbol 0 needs an even draw, so `triple_0` and
+//! `pair_0` do not exist.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_math::{
+    derive_outcome, seed_from_vrf, spin_symbols, vrf_hash, PayoutLimits, JACKPOT_ODDS_ONE,
+};
+use crate::{JackpotPool, JackpotsConfig, NO_WILD, PAYTABLE, REEL_WEIGHTS};
+
+/// The committed vector set.
+pub const COMMITTED_VECTORS: &str = include_str!("../vectors/outcomes.jsonl");
+
+const DEFAULT_BET: u64 = 1_000_000; // 0.001 SOL
+const DEFAULT_CAP: u64 = 1_000_000_000; // max_payout_per_spin default
+const DEFAULT_JACKPOT_AMOUNTS: [u64; 3] = [10_000_000, 100_000_000, 1_000_000_000];
+const DEFAULT_HIT_WEIGHTS: [u32; 3] = [500, 300, 200];
+/// Wild symbol of the `wild_*` vectors.
+pub const VECTOR_WILD: u8 = 6;
+/// Counters searched when looking for a specific reel combination.
+const SEARCH_LIMIT: u64 = 1 << 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedOutcome {
+    pub symbols: [u8; 3],
+    pub base_payout_full: u64,
+    pub base_payout: u64,
+    pub jackpot_roll: Option<u32>,
+    pub jackpot_tier: Option<u8>,
+    pub jackpot_payout: u64,
+    pub total_payout: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    pub name: String,
+    /// 32 revealed bytes, lowercase hex.
+    pub vrf_bytes: String,
+    /// sha256 of the revealed bytes as published in SpinSettled, lowercase hex.
+    pub vrf_hash: String,
+    pub bet_amount: u64,
+    /// min(pool headroom, max_payout_per_spin) at settle time.
+    pub max_total_affordable: u64,
+    /// Current amounts of [mini, major, grand].
+    pub jackpot_amounts: [u64; 3],
+    /// Hit weights of [mini, major, grand].
+    pub jackpot_hit_weights: [u32; 3],
+    /// Symbol that substitutes for any other, or NO_WILD.
+    pub wild_symbol: u8,
+    pub expected: ExpectedOutcome,
+}
+
+impl TestVector {
+    pub fn jackpots(&self) -> JackpotsConfig {
+        jackpots_for(self.jackpot_amounts, self.jackpot_hit_weights)
+    }
+
+    /// Recompute the outcome from the inputs and compare.
+    pub fn check(&self) -> Result<(), String> {
+        let vrf = decode_hex(&self.vrf_bytes)?;
+        if encode_hex(&vrf_hash(&vrf)) != self.vrf_hash {
+            return Err(format!("{}: vrf_hash does not match vrf_bytes", self.name));
+        }
+        let actual = outcome_for(
+            &vrf,
+            self.bet_amount,
+            self.max_total_affordable,
+            &self.jackpots(),
+            self.wild_symbol,
+        );
+        if actual != self.expected {
+            return Err(format!(
+                "{}: expected {:?}, derived {:?}",
+                self.name, self.expected, actual
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn jackpots_for(amounts: [u64; 3], weights: [u32; 3]) -> JackpotsConfig {
+    let pool = |i: usize| JackpotPool {
+        amount: amounts[i],
+        seed: amounts[i],
+        contrib_bps: 0,
+        hit_weight: weights[i],
+    };
+    JackpotsConfig {
+        mini: pool(0),
+        major: pool(1),
+        grand: pool(2),
+        hit_weight_total: weights[0] + weights[1] + weights[2],
+    }
+}
+
+fn outcome_for(
+    vrf: &[u8; 32],
+    bet_amount: u64,
+    max_total_affordable: u64,
+    jackpots: &JackpotsConfig,
+    wild_symbol: u8,
+) -> ExpectedOutcome {
+    let o = derive_outcome(
+        vrf,
+        bet_amount,
+        PayoutLimits::uniform(max_total_affordable),
+        jackpots,
+        JACKPOT_ODDS_ONE,
+        0,
+        &REEL_WEIGHTS,
+        &PAYTABLE,
+        wild_symbol,
+    );
+    ExpectedOutcome {
+        symbols: o.symbols,
+        base_payout_full: o.base_payout_full,
+        base_payout: o.base_payout,
+        jackpot_roll: o.jackpot_roll,
+        jackpot_tier: o.jackpot_tier,
+        jackpot_payout: o.jackpot_payout,
+        total_payout: o.total_payout,
+    }
+}
+
+/// Deterministic reveal for a counter: the counter little-endian in the
+/// seed bytes, filler in the rest so verifiers exercise the full 32 bytes.
+pub fn vrf_bytes_for(counter: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[0..8].copy_from_slice(&counter.to_le_bytes());
+    for (i, b) in bytes.iter_mut().enumerate().skip(8) {
+        *b = (i as u8).wrapping_mul(31) ^ (counter as u8);
+    }
+    bytes
+}
+
+fn symbols_for(counter: u64) -> [u8; 3] {
+    let mut seed = seed_from_vrf(&vrf_bytes_for(counter));
+    spin_symbols(&mut seed, &REEL_WEIGHTS)
+}
+
+fn first_counter(pred: impl Fn([u8; 3]) -> bool) -> Option<u64> {
+    (0..SEARCH_LIMIT).find(|&c| pred(symbols_for(c)))
+}
+
+fn vector(
+    name: String,
+    counter: u64,
+    bet_amount: u64,
+    max_total_affordable: u64,
+    jackpot_amounts: [u64; 3],
+    jackpot_hit_weights: [u32; 3],
+) -> TestVector {
+    let (amounts, weights) = (jackpot_amounts, jackpot_hit_weights);
+    wild_vector(name, counter, bet_amount, max_total_affordable, amounts, weights, NO_WILD)
+}
+
+fn wild_vector(
+    name: String,
+    counter: u64,
+    bet_amount: u64,
+    max_total_affordable: u64,
+    jackpot_amounts: [u64; 3],
+    jackpot_hit_weights: [u32; 3],
+    wild_symbol: u8,
+) -> TestVector {
+    let vrf = vrf_bytes_for(counter);
+    let jackpots = jackpots_for(jackpot_amounts, jackpot_hit_weights);
+    TestVector {
+        name,
+        vrf_bytes: encode_hex(&vrf),
+        vrf_hash: encode_hex(&vrf_hash(&vrf)),
+        bet_amount,
+        max_total_affordable,
+        jackpot_amounts,
+        jackpot_hit_weights,
+        wild_symbol,
+        expected: outcome_for(&vrf, bet_amount, max_total_affordable, &jackpots, wild_symbol),
+    }
+}
+
+/// Build the canonical vector set. Ordering is part of the contract.
+pub fn generate() -> Vec<TestVector> {
+    let mut out = Vec::new();
+    let amounts = DEFAULT_JACKPOT_AMOUNTS;
+    let weights = DEFAULT_HIT_WEIGHTS;
+
+    // Every reachable 3-of-a-kind, as (symbol, counter).
+    let triples: Vec<(u8, u64)> = (0..12u8)
+        .filter_map(|k| first_counter(|s| s == [k, k, k]).map(|c| (k, c)))
+        .collect();
+    for &(k, c) in &triples {
+        out.push(vector(format!("triple_{}", k), c, DEFAULT_BET, DEFAULT_CAP, amounts, weights));
+    }
+
+    // First two reels match, third doesn't: pays nothing.
+    for k in 0..12u8 {
+        if let Some(c) = first_counter(|s| s[0] == k && s[1] == k && s[2] != k) {
+            out.push(vector(format!("pair_{}", k), c, DEFAULT_BET, DEFAULT_CAP, amounts, weights));
+        }
+    }
+
+    // Plain sequential reveals under the default config.
+    for c in 0..30u64 {
+        out.push(vector(format!("sequential_{}", c), c, DEFAULT_BET, DEFAULT_CAP, amounts, weights));
+    }
+
+    // Rare triples on a big bet: base payout truncated to the cap, no roll.
+    for &(k, c) in triples.iter().take(5) {
+        out.push(vector(format!("cap_truncation_{}", k), c, 10_000_000, DEFAULT_CAP, amounts, weights));
+    }
+
+    // Payout exactly at the cap, and one lamport of jackpot headroom.
+    let c11 = triples.iter().find(|t| t.0 == 11).map(|t| t.1).unwrap();
+    out.push(vector("cap_exact".to_string(), c11, DEFAULT_BET, 14 * DEFAULT_BET, amounts, weights));
+    out.push(vector("cap_plus_one".to_string(), c11, DEFAULT_BET, 14 * DEFAULT_BET + 1, amounts, weights));
+
+    // Only the mini is affordable.
+    for c in 30..50u64 {
+        out.push(vector(format!("jackpot_unaffordable_{}", c), c, DEFAULT_BET, 50_000_000, amounts, weights));
+    }
+
+    // No headroom at all.
+    for c in 0..5u64 {
+        out.push(vector(format!("zero_headroom_{}", c), c, DEFAULT_BET, 0, amounts, weights));
+    }
+
+    // Jackpots disabled: no roll consumed.
+    for c in 0..10u64 {
+        out.push(vector(format!("no_jackpot_weights_{}", c), c, DEFAULT_BET, DEFAULT_CAP, amounts, [0, 0, 0]));
+    }
+
+    // Drained pools: roll happens, nothing paid.
+    for c in 0..5u64 {
+        out.push(vector(format!("empty_jackpot_{}", c), c, DEFAULT_BET, DEFAULT_CAP, [0, 0, 0], weights));
+    }
+
+    // Wild interactions: one wild completing each other symbol's triple,
+    // two wilds, three wilds and a wild between two strangers.
+    let w = VECTOR_WILD;
+    let wilds = |s: [u8; 3]| s.iter().filter(|&&x| x == w).count();
+    let mut wild_cases: Vec<(String, Option<u64>)> = (0..12u8)
+        .filter(|&k| k != w)
+        .map(|k| {
+            let c = first_counter(|s| wilds(s) == 1 && s.iter().all(|&x| x == w || x == k));
+            (format!("wild_one_{}", k), c)
+        })
+        .collect();
+    wild_cases.push(("wild_two".to_string(), first_counter(|s| wilds(s) == 2)));
+    wild_cases.push(("wild_three".to_string(), first_counter(|s| wilds(s) == 3)));
+    wild_cases.push((
+        "wild_strangers".to_string(),
+        first_counter(|s| wilds(s) == 1 && s[0] != s[1] && s[1] != s[2] && s[0] != s[2]),
+    ));
+    for (name, c) in wild_cases {
+        if let Some(c) = c {
+            out.push(wild_vector(name, c, DEFAULT_BET, DEFAULT_CAP, amounts, weights, w));
+        }
+    }
+
+    out
+}
+
+/// One compact JSON object per line, trailing newline.
+pub fn to_jsonl(vectors: &[TestVector]) -> String {
+    let mut out = String::new();
+    for v in vectors {
+        out.push_str(&serde_json::to_string(v).expect("vector serializes"));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn from_jsonl(contents: &str) -> Result<Vec<TestVector>, String> {
+    contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(i, l)| serde_json::from_str(l).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// Check every vector in a file against the current derivation.
+pub fn check_all(contents: &str) -> Result<usize, String> {
+    let vectors = from_jsonl(contents)?;
+    for v in &vectors {
+        v.check()?;
+    }
+    Ok(vectors.len())
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn decode_hex(s: &str) -> Result<[u8; 32], String> {
+    if s.len() != 64 {
+        return Err(format!("vrf_bytes must be 64 hex chars, got {}", s.len()));
+    }
+    let mut out = [0u8; 32];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+            .map_err(|e| format!("vrf_bytes: {}", e))?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set to rewrite `vectors/outcomes.jsonl` from the current derivation.
+    const UPDATE_VAR: &str = "UPDATE_VECTORS";
+
+    #[test]
+    fn committed_vectors_regenerate_byte_for_byte() {
+        let fresh = to_jsonl(&generate());
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/vectors/outcomes.jsonl");
+            std::fs::write(path, &fresh).expect("write vectors");
+            return;
+        }
+        assert!(
+            fresh == COMMITTED_VECTORS,
+            "outcome derivation changed; if deliberate, rerun with {}=1 and commit the file",
+            UPDATE_VAR
+        );
+    }
+
+    #[test]
+    fn committed_vectors_check_against_the_derivation() {
+        let count = check_all(COMMITTED_VECTORS).unwrap();
+        assert_eq!(count, generate().len());
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = vrf_bytes_for(0xdead_beef);
+        assert_eq!(decode_hex(&encode_hex(&bytes)).unwrap(), bytes);
+        assert!(decode_hex("00").is_err());
+    }
+}
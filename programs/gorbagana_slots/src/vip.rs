@@ -0,0 +1,166 @@
+//! Token-gated VIP rebates on losing spins.
+//!
+//! `SlotsState.vip_config` names a mint and the balance of it that makes
+//! a player VIP. A settle given the player's `vip_token_account` for that
+//! mint, holding at least `min_balance`, refunds `rebate_bps` of a losing
+//! spin's stake. A token account of another mint or another owner fails
+//! the settle (VipTokenAccountMismatch); one below the minimum simply
+//! earns nothing. To gate on an NFT, name its mint with a minimum of 1:
+//! collections are not resolved, as that needs the metadata program.
+//!
+//! Only the player's own money is rebated: scatter and loyalty free
+//! spins earn nothing. The rebate leaves `total_pool` for
+//! `vip_rebate_balance`, which sits in the treasury next to the pool like
+//! the referral balance, and is added to `PlayerState.rebate_accrued`.
+//! Like a referral credit it is capped at what the pool holds above
+//! required_floor, so a settle is never failed for it. claim_rebate pays
+//! the accrued lamports out of the treasury.
+//!
+//! A rebate of `rebate_bps` on every losing stake costs the house at most
+//! `rebate_bps` of its volume, so set_vip_config keeps it within the
+//! house edge, `10_000 - theoretical_rtp_bps`. settle_spin and
+//! settle_and_respin take the token account and record it in
+//! `PlayerState.vip_token_account` (or clear it when the player leaves
+//! it out), so a crank settling for the player must pass the same one.
+//! The batch and token settles pay no rebate, and neither a batch crank
+//! nor settle_spin_deferred settles a spin whose player records one.
+//! VIP rebates are SOL only.
+
+use anchor_lang::prelude::*;
+
+use crate::{required_floor, ErrorCode, PlayerState, SlotsState, VipRebateAccrued};
+
+/// Token gate and rebate of the VIP tier. `rebate_bps == 0` = off.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct VipConfig {
+    pub mint: Pubkey,
+    /// Smallest balance of `mint`, in base units, that qualifies.
+    pub min_balance: u64,
+    /// Share of a losing stake refunded, bps.
+    pub rebate_bps: u16,
+}
+
+impl VipConfig {
+    pub fn enabled(&self) -> bool {
+        self.rebate_bps > 0
+    }
+}
+
+/// Check a candidate config against the machine's theoretical RTP.
+pub fn validate(config: &VipConfig, rtp_bps: u32) -> Result<()> {
+    if !config.enabled() {
+        return Ok(());
+    }
+    let edge_bps = 10_000u32.saturating_sub(rtp_bps);
+    require!(
+        config.mint != Pubkey::default() && (config.rebate_bps as u32) <= edge_bps,
+        ErrorCode::InvalidVipConfig
+    );
+    Ok(())
+}
+
+/// Rebate of a losing `stake`.
+pub fn rebate_for(rebate_bps: u16, stake: u64) -> u64 {
+    ((stake as u128) * (rebate_bps as u128) / 10_000) as u64
+}
+
+/// Move the rebate of a lost `stake` out of the pool into the player's
+/// accrued balance. Returns the amount credited.
+pub fn accrue(
+    state: &mut SlotsState,
+    player_state: &mut PlayerState,
+    user: &Pubkey,
+    stake: u64,
+) -> Result<u64> {
+    let headroom = state.total_pool.saturating_sub(required_floor(state)?);
+    let rebate = rebate_for(state.vip_config.rebate_bps, stake).min(headroom);
+    if rebate == 0 {
+        return Ok(0);
+    }
+    state.total_pool -= rebate;
+    state.vip_rebate_balance = state
+        .vip_rebate_balance
+        .checked_add(rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+    player_state.rebate_accrued = player_state
+        .rebate_accrued
+        .checked_add(rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(VipRebateAccrued {
+        user: *user,
+        stake,
+        amount: rebate,
+        accrued: player_state.rebate_accrued,
+        spin_nonce: player_state.spin_nonce,
+    });
+    Ok(rebate)
+}
+
+/// Take everything the player has accrued off the books; the caller pays
+/// it out of the treasury.
+pub fn take(state: &mut SlotsState, player_state: &mut PlayerState) -> Result<u64> {
+    let amount = player_state.rebate_accrued;
+    require!(amount > 0, ErrorCode::NothingClaimable);
+    state.vip_rebate_balance = state
+        .vip_rebate_balance
+        .checked_sub(amount)
+        .ok_or(ErrorCode::AccountingInvariantViolated)?;
+    player_state.rebate_accrued = 0;
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rebate_bps: u16) -> VipConfig {
+        VipConfig {
+            mint: Pubkey::new_unique(),
+            min_balance: 1,
+            rebate_bps,
+        }
+    }
+
+    #[test]
+    fn rebate_stays_within_the_edge() {
+        assert!(validate(&config(400), 9_600).is_ok());
+        assert!(validate(&config(401), 9_600).is_err());
+        assert!(validate(&config(1), 10_000).is_err());
+        let unminted = VipConfig {
+            mint: Pubkey::default(),
+            ..config(100)
+        };
+        assert!(validate(&unminted, 9_600).is_err());
+        // Off, anything goes.
+        assert!(validate(&config(0), 10_500).is_ok());
+
+        assert_eq!(rebate_for(250, 1_000_000), 25_000);
+        assert_eq!(rebate_for(250, 39), 0);
+        assert_eq!(rebate_for(10_000, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn a_crank_must_pass_the_recorded_token_account() {
+        use crate::test_util::Game;
+        use crate::DEFAULT_MIN_BET;
+
+        let mut game = Game::new();
+        game.state.vip_config = config(100);
+        game.player.vip_token_account = Pubkey::new_unique();
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        game.reveal([1; 32]);
+
+        assert_eq!(game.crank().unwrap_err(), ErrorCode::OptionalAccountMissing.into());
+        assert!(game.player.has_pending_spin);
+
+        // The player may leave it out, which clears the record.
+        game.settle().expect("the player settles");
+        assert_eq!(game.player.vip_token_account, Pubkey::default());
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        game.reveal([1; 32]);
+        game.crank().expect("nothing recorded, the crank settles");
+    }
+}
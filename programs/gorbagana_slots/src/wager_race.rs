@@ -0,0 +1,372 @@
+//! Wager races: volume campaigns with a top-N board.
+//!
+//! A `WagerRace` PDA (seeds `[WAGER_RACE_SEED, slots_state, race_id as u32
+//! LE]`) ranks players by the lamports they wagered on spins settled
+//! between `start_slot` and `end_slot`. Unlike tournaments, which score
+//! win multipliers, a race scores raw volume.
+//!
+//! A player's running total lives in their PlayerState (`race_id`,
+//! `race_wagered`), so falling off the board does not lose it. A player
+//! is in one race at a time; settling into another race restarts the
+//! total. Settles update the board when the race account is passed, so a
+//! spin settled without it (or through the deferred path) does not count.
+//!
+//! Ranking is by total, then by the slot the total was reached: on a tie
+//! the player who got there first ranks higher, and a late entrant only
+//! displaces the last entry by wagering strictly more.
+//!
+//! The prize budget is earmarked from the pool (EarmarkKind::PendingAward)
+//! at creation. finalize_wager_race credits each ranked player's share to
+//! their claimable balance and returns unpaid shares to the pool.
+//!
+//! Abuse: self-play wash wagering does raise volume, and that is fine.
+//! Every lamport wagered pays the house edge, so climbing the board costs
+//! about (1 - RTP) of the volume added. At a 96% RTP, outbidding a rival
+//! by V lamports costs about 0.04 * V in expectation. Operators should
+//! size prizes so the top prize stays below the edge cost of the volume
+//! needed to win it.
+
+use anchor_lang::prelude::*;
+
+use crate::{time, ErrorCode, PlayerState, WagerRace};
+
+/// Ranked places on a race board.
+pub const WAGER_RACE_BOARD_SIZE: usize = 10;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WagerRaceEntry {
+    /// Default key = empty place.
+    pub player: Pubkey,
+    pub wagered: u64,
+    /// Slot `wagered` was reached; breaks ties, earlier first.
+    pub reached_slot: u64,
+}
+
+impl WagerRaceEntry {
+    pub fn is_empty(&self) -> bool {
+        self.player == Pubkey::default()
+    }
+
+    /// Whether `self` ranks above `other`.
+    fn outranks(&self, other: &WagerRaceEntry) -> bool {
+        if other.is_empty() {
+            return !self.is_empty();
+        }
+        self.wagered > other.wagered
+            || (self.wagered == other.wagered && self.reached_slot < other.reached_slot)
+    }
+}
+
+impl WagerRace {
+    pub const LEN: usize = 8
+        + 32
+        + 4
+        + 8
+        + 8
+        + 8
+        + 2 * WAGER_RACE_BOARD_SIZE
+        + WAGER_RACE_BOARD_SIZE * (32 + 8 + 8)
+        + 1
+        + 1;
+
+    pub fn is_open(&self, slot: u64) -> bool {
+        !self.finalized
+            && time::has_reached(self.start_slot, slot)
+            && !time::has_reached(self.end_slot, slot)
+    }
+
+    /// Prize for board place `rank` (0 = first).
+    pub fn prize_for(&self, rank: usize) -> u64 {
+        ((self.prize_budget as u128) * (self.prize_split_bps[rank] as u128) / 10_000) as u64
+    }
+}
+
+/// Split must be non-increasing and sum to at most 100%.
+pub fn split_valid(split: &[u16; WAGER_RACE_BOARD_SIZE]) -> bool {
+    let total: u32 = split.iter().map(|&b| b as u32).sum();
+    total > 0 && total <= 10_000 && split.windows(2).all(|w| w[0] >= w[1])
+}
+
+/// Add a settled bet to the player's race total and the board. Returns
+/// the player's total, or None when the race is not open at `slot`.
+pub fn record(
+    race: &mut WagerRace,
+    player_state: &mut PlayerState,
+    bet_amount: u64,
+    slot: u64,
+) -> Result<Option<u64>> {
+    if !race.is_open(slot) || bet_amount == 0 {
+        return Ok(None);
+    }
+    if player_state.race_id != race.race_id {
+        player_state.race_id = race.race_id;
+        player_state.race_wagered = 0;
+    }
+    player_state.race_wagered = player_state
+        .race_wagered
+        .checked_add(bet_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let entry = WagerRaceEntry {
+        player: player_state.owner,
+        wagered: player_state.race_wagered,
+        reached_slot: slot,
+    };
+    place(&mut race.board, entry);
+    Ok(Some(entry.wagered))
+}
+
+/// Put `entry` on the board in rank order, replacing the player's old
+/// entry or, if the player is new and outranks it, the last one.
+pub fn place(board: &mut [WagerRaceEntry; WAGER_RACE_BOARD_SIZE], entry: WagerRaceEntry) {
+    let last = WAGER_RACE_BOARD_SIZE - 1;
+    let mut i = match board.iter().position(|e| e.player == entry.player) {
+        Some(i) => i,
+        None if entry.outranks(&board[last]) => last,
+        None => return,
+    };
+    board[i] = entry;
+    while i > 0 && board[i].outranks(&board[i - 1]) {
+        board.swap(i, i - 1);
+        i -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+    use anchor_lang::InstructionData;
+
+    use super::*;
+    use crate::obligations::EarmarkKind;
+    use crate::test_util::{pda, program_error, simulate, zeroed_player, Fixture, Game};
+    use crate::{instruction, SlotsState, DEFAULT_MIN_BET, WAGER_RACE_SEED};
+
+    const SPLIT: [u16; WAGER_RACE_BOARD_SIZE] = [6_000, 4_000, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    fn entry(player: Pubkey, wagered: u64, reached_slot: u64) -> WagerRaceEntry {
+        WagerRaceEntry {
+            player,
+            wagered,
+            reached_slot,
+        }
+    }
+
+    fn players(board: &[WagerRaceEntry]) -> Vec<Pubkey> {
+        board.iter().take_while(|e| !e.is_empty()).map(|e| e.player).collect()
+    }
+
+    fn create_data(race_id: u32, start_slot: u64, end_slot: u64, budget: u64) -> Vec<u8> {
+        instruction::CreateWagerRace {
+            race_id,
+            start_slot,
+            end_slot,
+            prize_budget: budget,
+            prize_split_bps: SPLIT,
+        }
+        .data()
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_total() {
+        let mut board = [WagerRaceEntry::default(); WAGER_RACE_BOARD_SIZE];
+        let [a, b, c] = [(); 3].map(|_| Pubkey::new_unique());
+
+        place(&mut board, entry(a, 100, 10));
+        place(&mut board, entry(b, 100, 9));
+        place(&mut board, entry(c, 100, 11));
+        assert_eq!(players(&board), [b, a, c]);
+
+        // A player's new total replaces their entry and climbs.
+        place(&mut board, entry(c, 101, 12));
+        assert_eq!(players(&board), [c, b, a]);
+    }
+
+    #[test]
+    fn a_late_entrant_must_wager_strictly_more_than_the_last_place() {
+        let mut board = [WagerRaceEntry::default(); WAGER_RACE_BOARD_SIZE];
+        let ranked: Vec<Pubkey> =
+            (0..WAGER_RACE_BOARD_SIZE).map(|_| Pubkey::new_unique()).collect();
+        for (i, player) in ranked.iter().enumerate() {
+            place(&mut board, entry(*player, 1_000 - i as u64, 1));
+        }
+        let last = board[WAGER_RACE_BOARD_SIZE - 1];
+
+        let late = Pubkey::new_unique();
+        place(&mut board, entry(late, last.wagered, 2));
+        assert_eq!(players(&board), ranked);
+
+        place(&mut board, entry(late, last.wagered + 1, 3));
+        assert_eq!(board[WAGER_RACE_BOARD_SIZE - 1].player, late);
+        assert!(!players(&board).contains(&last.player));
+    }
+
+    #[test]
+    fn only_open_races_record_and_a_new_race_restarts_the_total() {
+        let mut race = WagerRace {
+            slots_state: Pubkey::new_unique(),
+            race_id: 1,
+            start_slot: 100,
+            end_slot: 200,
+            prize_budget: 1,
+            prize_split_bps: SPLIT,
+            board: [WagerRaceEntry::default(); WAGER_RACE_BOARD_SIZE],
+            finalized: false,
+            bump: 0,
+        };
+        let mut player = zeroed_player();
+        player.owner = Pubkey::new_unique();
+
+        assert_eq!(record(&mut race, &mut player, 50, 99).unwrap(), None);
+        assert_eq!(record(&mut race, &mut player, 50, 200).unwrap(), None);
+        assert_eq!(record(&mut race, &mut player, 0, 150).unwrap(), None);
+        assert_eq!(record(&mut race, &mut player, 50, 100).unwrap(), Some(50));
+        assert_eq!(record(&mut race, &mut player, 50, 199).unwrap(), Some(100));
+        assert_eq!(race.board[0], entry(player.owner, 100, 199));
+
+        race.race_id = 2;
+        assert_eq!(record(&mut race, &mut player, 30, 150).unwrap(), Some(30));
+        assert_eq!(player.race_id, 2);
+
+        player.race_wagered = u64::MAX;
+        assert_eq!(
+            record(&mut race, &mut player, 1, 150).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+
+        race.finalized = true;
+        assert_eq!(record(&mut race, &mut player, 50, 150).unwrap(), None);
+    }
+
+    #[test]
+    fn splits_are_non_increasing_and_within_the_budget() {
+        assert!(split_valid(&SPLIT));
+        assert!(!split_valid(&[0; WAGER_RACE_BOARD_SIZE]));
+        assert!(!split_valid(&[4_000, 6_000, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(split_valid(&[1_000; WAGER_RACE_BOARD_SIZE]));
+        let mut over = [1_000; WAGER_RACE_BOARD_SIZE];
+        over[0] = 1_001;
+        assert!(!split_valid(&over));
+    }
+
+    #[test]
+    fn a_race_is_created_settled_into_and_finalized() {
+        let mut game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let stranger = Fixture::wallet(Pubkey::new_unique(), LAMPORTS_PER_SOL).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let (start, end) = (game.slot, game.slot + 100);
+        let machine = state.key;
+        let race_key = |id: u32| pda(&[WAGER_RACE_SEED, machine.as_ref(), &id.to_le_bytes()]).0;
+        let uninit = |id| Fixture::uninit(race_key(id), WagerRace::LEN);
+
+        let sol = LAMPORTS_PER_SOL;
+        for (signer, id, (start, end), budget, error) in [
+            (&stranger, 1, (start, end), sol, ErrorCode::Unauthorized),
+            (&authority, 2, (start, end), sol, ErrorCode::InvalidWagerRace),
+            (&authority, 1, (end, start), sol, ErrorCode::InvalidWagerRace),
+            (&authority, 1, (start, end), 100 * sol, ErrorCode::InsufficientPool),
+        ] {
+            let (mut race, mut signer) = (uninit(id), signer.clone());
+            let data = create_data(id, start, end, budget);
+            assert_eq!(
+                simulate([&mut state, &mut race, &mut signer, &mut system], &data),
+                Err(program_error(error))
+            );
+        }
+        let mut race = uninit(1);
+        let create = create_data(1, start, end, LAMPORTS_PER_SOL);
+        simulate([&mut state, &mut race, &mut authority, &mut system], &create).unwrap();
+        let created = state.read::<SlotsState>();
+        assert_eq!(created.earmarks.get(EarmarkKind::PendingAward), LAMPORTS_PER_SOL);
+        assert_eq!(created.wager_race_count, 1);
+
+        // A settle with the race passed puts the player on the board.
+        game.state = created;
+        game.request(DEFAULT_MIN_BET).unwrap();
+        game.reveal([1; 32]);
+        let mut accounts = game.settle_spin_fixtures();
+        accounts[0].key = state.key;
+        accounts[10] = race;
+        simulate(&mut accounts, &instruction::SettleSpin {}.data()).unwrap();
+        let mut state = accounts[0].clone();
+        let mut player = accounts[1].clone();
+        let mut board = accounts[10].read::<WagerRace>();
+        assert_eq!(board.board[0], entry(game.user, DEFAULT_MIN_BET, game.slot));
+
+        // A rival who matched the total later ranks second.
+        let mut rival = zeroed_player();
+        rival.owner = Pubkey::new_unique();
+        place(&mut board.board, entry(rival.owner, DEFAULT_MIN_BET, game.slot + 1));
+        let mut race = Fixture::account(race_key(1), &board);
+        let mut rival = Fixture::account(Pubkey::new_unique(), &rival);
+        let finalize = instruction::FinalizeWagerRace {}.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut player, &mut rival], &finalize),
+            Err(program_error(ErrorCode::WagerRaceNotEnded))
+        );
+        game.advance(end - game.slot);
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut player], &finalize),
+            Err(program_error(ErrorCode::WagerRaceWinnerMismatch))
+        );
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut rival, &mut player], &finalize),
+            Err(program_error(ErrorCode::WagerRaceWinnerMismatch))
+        );
+
+        let before = state.read::<SlotsState>();
+        let claimable = player.read::<PlayerState>().claimable_balance;
+        simulate([&mut state, &mut race, &mut player, &mut rival], &finalize).unwrap();
+        let after = state.read::<SlotsState>();
+        let first = LAMPORTS_PER_SOL * 6 / 10;
+        let second = LAMPORTS_PER_SOL * 4 / 10;
+        assert_eq!(player.read::<PlayerState>().claimable_balance, claimable + first);
+        assert_eq!(rival.read::<PlayerState>().claimable_balance, second);
+        assert_eq!(after.earmarks.get(EarmarkKind::PendingAward), 0);
+        assert_eq!(after.total_pool, before.total_pool - first - second);
+        assert_eq!(after.total_claimable, before.total_claimable + first + second);
+        assert!(race.read::<WagerRace>().finalized);
+
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut player, &mut rival], &finalize),
+            Err(program_error(ErrorCode::WagerRaceClosed))
+        );
+    }
+
+    #[test]
+    fn only_the_authority_cancels_and_the_budget_returns() {
+        let game = Game::new();
+        let [mut state, ..] = game.fixtures();
+        let mut authority = game.authority();
+        let mut stranger = Fixture::wallet(Pubkey::new_unique(), LAMPORTS_PER_SOL).signer();
+        let mut system = Fixture::program(anchor_lang::system_program::ID);
+        let race_key = pda(&[WAGER_RACE_SEED, state.key.as_ref(), &1u32.to_le_bytes()]).0;
+        let mut race = Fixture::uninit(race_key, WagerRace::LEN);
+        let create = create_data(1, game.slot, game.slot + 100, LAMPORTS_PER_SOL);
+        simulate([&mut state, &mut race, &mut authority, &mut system], &create).unwrap();
+        let cancel = instruction::CancelWagerRace {}.data();
+
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut stranger], &cancel),
+            Err(program_error(ErrorCode::Unauthorized))
+        );
+        simulate([&mut state, &mut race, &mut authority], &cancel).unwrap();
+        assert_eq!(state.read::<SlotsState>().earmarks.get(EarmarkKind::PendingAward), 0);
+        assert_eq!(
+            simulate([&mut state, &mut race, &mut authority], &cancel),
+            Err(program_error(ErrorCode::WagerRaceClosed))
+        );
+
+        // Another machine's state cannot close the race.
+        let mut other = Game::new().fixtures()[0].clone();
+        let has_one =
+            anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintHasOne);
+        assert_eq!(
+            simulate([&mut other, &mut race, &mut authority], &cancel),
+            Err(has_one.into())
+        );
+    }
+}
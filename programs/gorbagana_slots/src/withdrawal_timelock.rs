@@ -0,0 +1,231 @@
+//! Two-phase operator withdrawals.
+//!
+//! The authority can no longer take pool funds in one step, so a stolen
+//! key cannot drain the pool before anyone notices. request_withdrawal
+//! records an amount, its outflow category and memo, and an unlock slot
+//! `withdrawal_delay_slots` ahead. execute_withdrawal (or its token
+//! counterpart) pays it once the slot is reached, re-checking the pool
+//! floor and the extraction budget at that point.
+//! cancel_withdrawal drops the request. One request is pending at a time.
+//!
+//! While pending, the amount counts toward required_floor, the commit
+//! requirement (pool_level::required_pool) and settle headroom
+//! (reservations::headroom_for), so spins cannot be booked against money
+//! that is about to leave, and the withdrawal cannot be starved by them.
+//!
+//! Every pool outflow (execute_withdrawal, its token counterpart and LP
+//! withdraw_from_pool) may only take the pool above required_floor, which
+//! counts the payout cap each pending spin reserved at commit. Nobody can
+//! leave ahead of a win they see coming and short it: the win is already
+//! covered. Outflows used to also wait out a quiet period after the last
+//! commit, but any player spinning often enough could hold that open and
+//! lock every withdrawal.
+
+use anchor_lang::prelude::*;
+
+use crate::outflows::OutflowCategory;
+use crate::{extraction, pool_shares, required_floor, time, ErrorCode, SlotsState};
+
+/// Default delay, about a day of 400 ms slots.
+pub const DEFAULT_WITHDRAWAL_DELAY_SLOTS: u64 = 216_000;
+/// Shortest delay the authority may set, about an hour.
+pub const MIN_WITHDRAWAL_DELAY_SLOTS: u64 = 9_000;
+
+/// A matured request, cleared from SlotsState.
+pub struct MaturedWithdrawal {
+    pub amount: u64,
+    pub category: OutflowCategory,
+    pub memo: [u8; 32],
+}
+
+/// Record a request; returns its unlock slot.
+pub fn request(
+    state: &mut SlotsState,
+    amount: u64,
+    category: OutflowCategory,
+    memo: [u8; 32],
+    now: u64,
+) -> Result<u64> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(
+        state.pending_withdrawal_amount == 0,
+        ErrorCode::WithdrawalAlreadyPending
+    );
+    let unlock_slot = now.saturating_add(state.withdrawal_delay_slots);
+    state.pending_withdrawal_amount = amount;
+    state.pending_withdrawal_unlock_slot = unlock_slot;
+    state.pending_withdrawal_category = category as u8;
+    state.pending_withdrawal_memo = memo;
+    Ok(unlock_slot)
+}
+
+/// Take the pending request if its unlock slot has been reached.
+pub fn take_matured(state: &mut SlotsState, now: u64) -> Result<MaturedWithdrawal> {
+    require!(
+        state.pending_withdrawal_amount > 0,
+        ErrorCode::NoPendingWithdrawal
+    );
+    require!(
+        time::has_reached(state.pending_withdrawal_unlock_slot, now),
+        ErrorCode::WithdrawalLocked
+    );
+    let category = OutflowCategory::from_u8(state.pending_withdrawal_category)
+        .ok_or(ErrorCode::InvalidOutflowCategory)?;
+    let matured = MaturedWithdrawal {
+        amount: state.pending_withdrawal_amount,
+        category,
+        memo: state.pending_withdrawal_memo,
+    };
+    clear(state);
+    Ok(matured)
+}
+
+/// Book the execution of the matured request: the pool floor (pending
+/// spins' reservations included), what the treasury can spend
+/// (`spendable`, net of other buckets) and the extraction budget are all
+/// re-checked here. The caller moves the funds and books the outflow.
+pub fn execute(state: &mut SlotsState, now: u64, spendable: u64) -> Result<MaturedWithdrawal> {
+    let matured = take_matured(state, now)?;
+    let amount = matured.amount;
+
+    let available_for_claim = state.total_pool.saturating_sub(required_floor(state)?);
+    require!(amount <= available_for_claim, ErrorCode::InsufficientPool);
+    require!(spendable >= amount, ErrorCode::InsufficientPool);
+    extraction::charge(state, amount, now)?;
+    pool_shares::burn_house(state, amount)?;
+
+    state.total_pool = state
+        .total_pool
+        .checked_sub(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(matured)
+}
+
+/// Drop the pending request; returns its amount.
+pub fn cancel(state: &mut SlotsState) -> Result<u64> {
+    let amount = state.pending_withdrawal_amount;
+    require!(amount > 0, ErrorCode::NoPendingWithdrawal);
+    clear(state);
+    Ok(amount)
+}
+
+fn clear(state: &mut SlotsState) {
+    state.pending_withdrawal_amount = 0;
+    state.pending_withdrawal_unlock_slot = 0;
+    state.pending_withdrawal_category = 0;
+    state.pending_withdrawal_memo = [0; 32];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{zeroed_state, Game};
+    use crate::DEFAULT_MIN_BET;
+
+    fn state() -> SlotsState {
+        let mut state = zeroed_state();
+        state.withdrawal_delay_slots = 100;
+        state
+    }
+
+    #[test]
+    fn withdrawal_unlocks_after_the_delay() {
+        let mut s = state();
+        let unlock = request(&mut s, 5_000, OutflowCategory::OperatorProfit, [0; 32], 10).unwrap();
+        assert_eq!(unlock, 110);
+        assert!(take_matured(&mut s, 109).is_err());
+        assert_eq!(s.pending_withdrawal_amount, 5_000);
+
+        let matured = take_matured(&mut s, 110).unwrap();
+        assert_eq!(matured.amount, 5_000);
+        assert_eq!(matured.category, OutflowCategory::OperatorProfit);
+        assert_eq!(s.pending_withdrawal_amount, 0);
+        assert!(take_matured(&mut s, 200).is_err());
+    }
+
+    #[test]
+    fn one_request_at_a_time_and_cancel_clears_it() {
+        let mut s = state();
+        request(&mut s, 1, OutflowCategory::Compensation, [0; 32], 0).unwrap();
+        assert!(request(&mut s, 2, OutflowCategory::Compensation, [0; 32], 0).is_err());
+        assert_eq!(cancel(&mut s).unwrap(), 1);
+        assert!(cancel(&mut s).is_err());
+        assert!(request(&mut s, 0, OutflowCategory::Compensation, [0; 32], 0).is_err());
+    }
+
+    #[test]
+    fn the_request_records_its_category_and_memo() {
+        let mut s = state();
+        let memo = [7; 32];
+        request(&mut s, 9, OutflowCategory::Fee, memo, 0).unwrap();
+        let matured = take_matured(&mut s, 100).unwrap();
+        assert_eq!(matured.category, OutflowCategory::Fee);
+        assert_eq!(matured.memo, memo);
+        assert_eq!(s.pending_withdrawal_memo, [0; 32]);
+        assert_eq!(s.pending_withdrawal_unlock_slot, 0);
+    }
+
+    #[test]
+    fn an_unlock_past_the_last_slot_saturates() {
+        let mut s = state();
+        s.withdrawal_delay_slots = u64::MAX;
+        let unlock = request(&mut s, 1, OutflowCategory::Fee, [0; 32], 5).unwrap();
+        assert_eq!(unlock, u64::MAX);
+        assert!(take_matured(&mut s, u64::MAX - 1).is_err());
+    }
+
+    #[test]
+    fn a_corrupt_category_is_refused_at_execution() {
+        let mut s = state();
+        request(&mut s, 1, OutflowCategory::Fee, [0; 32], 0).unwrap();
+        s.pending_withdrawal_category = u8::MAX;
+        assert_eq!(
+            take_matured(&mut s, 100).err(),
+            Some(ErrorCode::InvalidOutflowCategory.into())
+        );
+        assert_eq!(s.pending_withdrawal_amount, 1);
+    }
+
+    #[test]
+    fn a_commit_in_the_same_slot_does_not_hold_up_a_withdrawal() {
+        let mut game = Game::new();
+        game.state.extraction_budget_bps = 9_999;
+        let unlock = request(&mut game.state, 1_000_000, OutflowCategory::Fee, [0; 32], game.slot)
+            .unwrap();
+        game.advance(unlock - game.slot);
+
+        // A player commits in the very slot the withdrawal executes.
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        let pool = game.state.total_pool;
+        let matured = execute(&mut game.state, game.slot, game.treasury).unwrap();
+        assert_eq!(matured.amount, 1_000_000);
+        assert_eq!(game.state.total_pool, pool - 1_000_000);
+    }
+
+    #[test]
+    fn a_withdrawal_cannot_reach_into_pending_reservations() {
+        let mut game = Game::new();
+        game.state.extraction_budget_bps = 9_999;
+        game.request(DEFAULT_MIN_BET).expect("commits");
+        let reserved = game.state.reserved_liability;
+        assert!(reserved > 0);
+
+        // Everything above the floor, reservations included, may leave.
+        let headroom = game.state.total_pool - required_floor(&game.state).unwrap();
+        let unlock =
+            request(&mut game.state, headroom + 1, OutflowCategory::Fee, [0; 32], game.slot)
+                .unwrap();
+        game.advance(unlock - game.slot);
+        let before = game.state.clone();
+        assert_eq!(
+            execute(&mut game.state, game.slot, game.treasury).err(),
+            Some(ErrorCode::InsufficientPool.into())
+        );
+
+        game.state = before;
+        game.state.pending_withdrawal_amount = headroom;
+        execute(&mut game.state, game.slot, game.treasury).unwrap();
+        assert_eq!(game.state.total_pool, required_floor(&game.state).unwrap());
+        assert_eq!(game.state.reserved_liability, reserved);
+    }
+}